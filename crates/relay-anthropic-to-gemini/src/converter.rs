@@ -0,0 +1,286 @@
+use relay_claude::{MessagesRequest, MessagesResponse, Usage};
+use relay_core::RelayError;
+use relay_gemini::{
+    Blob, Content, FunctionCall, FunctionResponse, GenerateContentRequest, GenerateContentResponse,
+    GenerationConfig, Part,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// JSON Schema keywords the Gemini API rejects in function parameters.
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &["$schema", "additionalProperties"];
+
+pub struct ClaudeToGeminiConverter;
+
+impl ClaudeToGeminiConverter {
+    /// The Gemini model is not part of the body; callers pass it in the URL.
+    pub fn convert_request(req: MessagesRequest) -> Result<GenerateContentRequest, RelayError> {
+        let system_instruction = req.system.as_ref().and_then(|system| {
+            let parts: Vec<Part> = text_of(system)
+                .into_iter()
+                .map(|text| Part::Text { text })
+                .collect();
+            (!parts.is_empty()).then(|| Content {
+                role: "user".to_string(),
+                parts,
+            })
+        });
+
+        // Anthropic matches tool results to calls by id, Gemini by name
+        let mut tool_names: HashMap<String, String> = HashMap::new();
+        let mut contents = Vec::new();
+
+        for message in &req.messages {
+            let role = if message.role == "assistant" {
+                "model"
+            } else {
+                "user"
+            };
+            let mut parts = Vec::new();
+
+            let blocks = match &message.content {
+                Value::String(text) => vec![json!({"type": "text", "text": text})],
+                Value::Array(blocks) => blocks.clone(),
+                _ => Vec::new(),
+            };
+
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        let text = block["text"].as_str().unwrap_or_default();
+                        if !text.is_empty() {
+                            parts.push(Part::Text {
+                                text: text.to_string(),
+                            });
+                        }
+                    }
+                    Some("image") | Some("document") => {
+                        let source = &block["source"];
+                        match source["type"].as_str() {
+                            Some("base64") => parts.push(Part::InlineData {
+                                inline_data: Blob {
+                                    mime_type: source["media_type"]
+                                        .as_str()
+                                        .unwrap_or_default()
+                                        .to_string(),
+                                    data: source["data"].as_str().unwrap_or_default().to_string(),
+                                },
+                            }),
+                            Some("text") => parts.push(Part::Text {
+                                text: source["data"].as_str().unwrap_or_default().to_string(),
+                            }),
+                            other => {
+                                return Err(RelayError::InvalidRequest(format!(
+                                    "Unsupported {} source type for Gemini: {}",
+                                    block["type"].as_str().unwrap_or_default(),
+                                    other.unwrap_or("missing")
+                                )));
+                            }
+                        }
+                    }
+                    Some("tool_use") => {
+                        let name = block["name"].as_str().unwrap_or_default().to_string();
+                        if let Some(id) = block["id"].as_str() {
+                            tool_names.insert(id.to_string(), name.clone());
+                        }
+                        parts.push(Part::FunctionCall {
+                            function_call: FunctionCall {
+                                name,
+                                args: block.get("input").cloned().unwrap_or(json!({})),
+                            },
+                        });
+                    }
+                    Some("tool_result") => {
+                        let id = block["tool_use_id"].as_str().unwrap_or_default();
+                        let name = tool_names
+                            .get(id)
+                            .cloned()
+                            .unwrap_or_else(|| id.to_string());
+                        let output = block
+                            .get("content")
+                            .map(|content| text_of(content).join("\n"))
+                            .unwrap_or_default();
+                        let key = if block["is_error"].as_bool().unwrap_or(false) {
+                            "error"
+                        } else {
+                            "content"
+                        };
+                        parts.push(Part::FunctionResponse {
+                            function_response: FunctionResponse {
+                                name,
+                                response: json!({ key: output }),
+                            },
+                        });
+                    }
+                    _ => {} // Thinking blocks have no Gemini counterpart
+                }
+            }
+
+            if !parts.is_empty() {
+                contents.push(Content {
+                    role: role.to_string(),
+                    parts,
+                });
+            }
+        }
+
+        let declarations: Vec<Value> = req
+            .tools
+            .iter()
+            .flatten()
+            // Server tools such as web search have no schema and cannot be declared
+            .filter(|tool| tool.get("input_schema").is_some())
+            .map(|tool| {
+                let mut parameters = tool["input_schema"].clone();
+                strip_unsupported_schema_keys(&mut parameters);
+                let mut declaration = json!({
+                    "name": tool["name"],
+                    "parameters": parameters
+                });
+                if let Some(description) = tool.get("description") {
+                    declaration["description"] = description.clone();
+                }
+                declaration
+            })
+            .collect();
+
+        let mut extra = serde_json::Map::new();
+        if !declarations.is_empty() {
+            if let Some(tool_config) = req.tool_choice.as_ref().and_then(convert_tool_choice) {
+                extra.insert("toolConfig".to_string(), tool_config);
+            }
+        }
+
+        let stop_sequences = req
+            .extra
+            .get("stop_sequences")
+            .and_then(|v| serde_json::from_value(v.clone()).ok());
+
+        Ok(GenerateContentRequest {
+            contents,
+            system_instruction,
+            generation_config: Some(GenerationConfig {
+                temperature: req.temperature,
+                top_p: req.top_p,
+                top_k: req.top_k,
+                max_output_tokens: (req.max_tokens > 0).then_some(req.max_tokens),
+                candidate_count: None,
+                stop_sequences,
+            }),
+            safety_settings: None,
+            tools: if declarations.is_empty() {
+                None
+            } else {
+                Some(vec![json!({"functionDeclarations": declarations})])
+            },
+            extra,
+        })
+    }
+
+    /// `model` is reported back to the client as the model that answered.
+    pub fn convert_response(resp: GenerateContentResponse, model: &str) -> MessagesResponse {
+        let id = message_id();
+        let mut content = Vec::new();
+        let mut has_tool_use = false;
+
+        let candidate = resp.candidates.first();
+        let parts = candidate
+            .map(|c| c.content.parts.as_slice())
+            .unwrap_or_default();
+        for part in parts {
+            match part {
+                Part::Text { text } => content.push(json!({"type": "text", "text": text})),
+                Part::FunctionCall { function_call } => {
+                    has_tool_use = true;
+                    content.push(json!({
+                        "type": "tool_use",
+                        "id": tool_use_id(&id, content.len()),
+                        "name": function_call.name,
+                        "input": function_call.args
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        let usage = resp.usage_metadata.unwrap_or_default();
+        let finish_reason = candidate.and_then(|c| c.finish_reason.as_deref());
+
+        MessagesResponse {
+            id,
+            response_type: "message".to_string(),
+            role: "assistant".to_string(),
+            content: Value::Array(content),
+            model: model.to_string(),
+            stop_reason: Some(stop_reason(finish_reason, has_tool_use).to_string()),
+            stop_sequence: None,
+            usage: Usage {
+                input_tokens: usage.prompt_token_count,
+                output_tokens: usage.candidates_token_count,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+            },
+        }
+    }
+}
+
+pub(crate) fn message_id() -> String {
+    format!("msg_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Gemini function calls carry no id, so one is derived from the message id
+/// and the block's position.
+pub(crate) fn tool_use_id(message_id: &str, index: usize) -> String {
+    format!(
+        "toolu_{}_{}",
+        message_id.strip_prefix("msg_").unwrap_or(message_id),
+        index
+    )
+}
+
+/// Anthropic `stop_reason` for a Gemini `finishReason`.
+pub(crate) fn stop_reason(finish_reason: Option<&str>, has_tool_use: bool) -> &'static str {
+    if has_tool_use {
+        return "tool_use";
+    }
+    match finish_reason {
+        Some("MAX_TOKENS") => "max_tokens",
+        Some("SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII") => "refusal",
+        _ => "end_turn",
+    }
+}
+
+/// Text of a string or an array of content blocks, one entry per text block.
+fn text_of(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(text) => vec![text.clone()],
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn convert_tool_choice(choice: &Value) -> Option<Value> {
+    let config = match choice.get("type")?.as_str()? {
+        "auto" => json!({"mode": "AUTO"}),
+        "any" => json!({"mode": "ANY"}),
+        "none" => json!({"mode": "NONE"}),
+        "tool" => json!({"mode": "ANY", "allowedFunctionNames": [choice.get("name")?]}),
+        _ => return None,
+    };
+    Some(json!({ "functionCallingConfig": config }))
+}
+
+fn strip_unsupported_schema_keys(schema: &mut Value) {
+    match schema {
+        Value::Object(map) => {
+            map.retain(|key, _| !UNSUPPORTED_SCHEMA_KEYS.contains(&key.as_str()));
+            map.values_mut().for_each(strip_unsupported_schema_keys);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_unsupported_schema_keys),
+        _ => {}
+    }
+}
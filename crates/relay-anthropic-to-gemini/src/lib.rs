@@ -0,0 +1,5 @@
+mod converter;
+mod stream;
+
+pub use converter::ClaudeToGeminiConverter;
+pub use stream::ClaudeStreamConverter;
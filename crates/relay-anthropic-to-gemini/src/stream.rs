@@ -0,0 +1,186 @@
+use relay_core::SseLines;
+use relay_gemini::{GenerateContentResponse, Part, UsageMetadata};
+use serde_json::{json, Value};
+
+use crate::converter::{message_id, stop_reason, tool_use_id};
+
+/// Translates a Gemini `streamGenerateContent?alt=sse` byte stream into
+/// Anthropic Messages SSE events.
+///
+/// Text parts become `text_delta`s on an open text block. Gemini sends
+/// function calls whole, so each one is emitted as a complete `tool_use`
+/// block. The chunk with a `finishReason` closes the message.
+pub struct ClaudeStreamConverter {
+    lines: SseLines,
+    message_id: String,
+    model: String,
+    started: bool,
+    finished: bool,
+    next_index: usize,
+    text_block: Option<usize>,
+    has_tool_use: bool,
+    usage: UsageMetadata,
+}
+
+impl ClaudeStreamConverter {
+    pub fn new(model: &str) -> Self {
+        Self::with_message_id(message_id(), model)
+    }
+
+    pub fn with_message_id(message_id: String, model: &str) -> Self {
+        Self {
+            lines: SseLines::new(),
+            message_id,
+            model: model.to_string(),
+            started: false,
+            finished: false,
+            next_index: 0,
+            text_block: None,
+            has_tool_use: false,
+            usage: UsageMetadata::default(),
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Value> {
+        let mut events = Vec::new();
+        for data in self.lines.push(bytes) {
+            if let Ok(chunk) = serde_json::from_str::<GenerateContentResponse>(&data) {
+                self.convert_chunk(chunk, &mut events);
+            }
+        }
+        events
+    }
+
+    /// Closes the message if the upstream stream ended without a
+    /// `finishReason`.
+    pub fn finish(&mut self) -> Vec<Value> {
+        let mut events = Vec::new();
+        if self.started && !self.finished {
+            self.close_message(None, &mut events);
+        }
+        events
+    }
+
+    pub fn encode(event: &Value) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            event["type"].as_str().unwrap_or_default(),
+            serde_json::to_string(event).unwrap_or_default()
+        )
+    }
+
+    fn convert_chunk(&mut self, chunk: GenerateContentResponse, events: &mut Vec<Value>) {
+        if self.finished {
+            return;
+        }
+        if let Some(usage) = chunk.usage_metadata {
+            self.usage = usage;
+        }
+        if !self.started {
+            self.started = true;
+            events.push(json!({
+                "type": "message_start",
+                "message": {
+                    "id": self.message_id,
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [],
+                    "model": self.model,
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {
+                        "input_tokens": self.usage.prompt_token_count,
+                        "output_tokens": 0
+                    }
+                }
+            }));
+        }
+
+        let Some(candidate) = chunk.candidates.into_iter().next() else {
+            return;
+        };
+
+        for part in candidate.content.parts {
+            match part {
+                Part::Text { text } if !text.is_empty() => {
+                    let index = match self.text_block {
+                        Some(index) => index,
+                        None => {
+                            let index =
+                                self.start_block(json!({"type": "text", "text": ""}), events);
+                            self.text_block = Some(index);
+                            index
+                        }
+                    };
+                    events.push(json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {"type": "text_delta", "text": text}
+                    }));
+                }
+                Part::FunctionCall { function_call } => {
+                    self.close_text_block(events);
+                    self.has_tool_use = true;
+                    let id = tool_use_id(&self.message_id, self.next_index);
+                    let index = self.start_block(
+                        json!({
+                            "type": "tool_use",
+                            "id": id,
+                            "name": function_call.name,
+                            "input": {}
+                        }),
+                        events,
+                    );
+                    events.push(json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {
+                            "type": "input_json_delta",
+                            "partial_json": function_call.args.to_string()
+                        }
+                    }));
+                    events.push(json!({"type": "content_block_stop", "index": index}));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(reason) = candidate.finish_reason {
+            self.close_message(Some(&reason), events);
+        }
+    }
+
+    fn start_block(&mut self, content_block: Value, events: &mut Vec<Value>) -> usize {
+        let index = self.next_index;
+        self.next_index += 1;
+        events.push(json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": content_block
+        }));
+        index
+    }
+
+    fn close_text_block(&mut self, events: &mut Vec<Value>) {
+        if let Some(index) = self.text_block.take() {
+            events.push(json!({"type": "content_block_stop", "index": index}));
+        }
+    }
+
+    fn close_message(&mut self, finish_reason: Option<&str>, events: &mut Vec<Value>) {
+        self.finished = true;
+        self.close_text_block(events);
+        events.push(json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": stop_reason(finish_reason, self.has_tool_use),
+                "stop_sequence": null
+            },
+            "usage": {
+                "input_tokens": self.usage.prompt_token_count,
+                "output_tokens": self.usage.candidates_token_count
+            }
+        }));
+        events.push(json!({"type": "message_stop"}));
+    }
+}
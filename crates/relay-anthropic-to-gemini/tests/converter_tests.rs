@@ -0,0 +1,162 @@
+use relay_anthropic_to_gemini::ClaudeToGeminiConverter;
+use relay_claude::MessagesRequest;
+use relay_gemini::{GenerateContentResponse, Part};
+use serde_json::json;
+
+fn request(value: serde_json::Value) -> MessagesRequest {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_messages_and_system() {
+    let req = request(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 256,
+        "temperature": 0.5,
+        "stop_sequences": ["END"],
+        "system": [{"type": "text", "text": "Be brief."}],
+        "messages": [
+            {"role": "user", "content": "Hi"},
+            {"role": "assistant", "content": [
+                {"type": "thinking", "thinking": "...", "signature": "sig"},
+                {"type": "text", "text": "Hello!"}
+            ]},
+            {"role": "user", "content": [
+                {"type": "text", "text": "Describe"},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+            ]}
+        ]
+    }));
+
+    let gemini_request = ClaudeToGeminiConverter::convert_request(req).unwrap();
+
+    let system = gemini_request.system_instruction.unwrap();
+    assert!(matches!(&system.parts[..], [Part::Text { text }] if text == "Be brief."));
+
+    let roles: Vec<&str> = gemini_request
+        .contents
+        .iter()
+        .map(|c| c.role.as_str())
+        .collect();
+    assert_eq!(roles, ["user", "model", "user"]);
+    assert_eq!(gemini_request.contents[1].parts.len(), 1);
+    assert!(matches!(
+        &gemini_request.contents[2].parts[1],
+        Part::InlineData { inline_data } if inline_data.mime_type == "image/png"
+    ));
+
+    let config = gemini_request.generation_config.unwrap();
+    assert_eq!(config.max_output_tokens, Some(256));
+    assert_eq!(config.temperature, Some(0.5));
+    assert_eq!(config.stop_sequences, Some(vec!["END".to_string()]));
+}
+
+#[test]
+fn test_tools_and_results() {
+    let req = request(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "tools": [
+            {
+                "name": "get_weather",
+                "description": "Current weather",
+                "input_schema": {
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "required": ["city"],
+                    "additionalProperties": false
+                }
+            },
+            {"type": "web_search_20250305", "name": "web_search"}
+        ],
+        "tool_choice": {"type": "tool", "name": "get_weather"},
+        "messages": [
+            {"role": "user", "content": "Weather in Paris?"},
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"city": "Paris"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_01", "content": [{"type": "text", "text": "18°C"}]}
+            ]}
+        ]
+    }));
+
+    let gemini_request = ClaudeToGeminiConverter::convert_request(req).unwrap();
+
+    let tools = gemini_request.tools.unwrap();
+    assert_eq!(
+        tools,
+        [json!({"functionDeclarations": [{
+            "name": "get_weather",
+            "description": "Current weather",
+            "parameters": {
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "required": ["city"]
+            }
+        }]})]
+    );
+    assert_eq!(
+        gemini_request.extra["toolConfig"],
+        json!({"functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["get_weather"]}})
+    );
+
+    assert!(matches!(
+        &gemini_request.contents[1].parts[0],
+        Part::FunctionCall { function_call } if function_call.args == json!({"city": "Paris"})
+    ));
+    match &gemini_request.contents[2].parts[0] {
+        Part::FunctionResponse { function_response } => {
+            assert_eq!(function_response.name, "get_weather");
+            assert_eq!(function_response.response, json!({"content": "18°C"}));
+        }
+        other => panic!("expected function response, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_url_image_is_rejected() {
+    let req = request(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "messages": [{"role": "user", "content": [
+            {"type": "image", "source": {"type": "url", "url": "https://example.com/cat.png"}}
+        ]}]
+    }));
+
+    assert!(ClaudeToGeminiConverter::convert_request(req).is_err());
+}
+
+#[test]
+fn test_convert_response() {
+    let resp: GenerateContentResponse = serde_json::from_value(json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": [
+                {"text": "Checking."},
+                {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}
+            ]},
+            "finishReason": "STOP"
+        }],
+        "usageMetadata": {"promptTokenCount": 40, "candidatesTokenCount": 18, "totalTokenCount": 58}
+    }))
+    .unwrap();
+
+    let message = ClaudeToGeminiConverter::convert_response(resp, "gemini-2.5-pro");
+
+    assert!(message.id.starts_with("msg_"));
+    assert_eq!(message.model, "gemini-2.5-pro");
+    assert_eq!(message.stop_reason.as_deref(), Some("tool_use"));
+    assert_eq!(
+        message.content[0],
+        json!({"type": "text", "text": "Checking."})
+    );
+    assert_eq!(message.content[1]["type"], "tool_use");
+    assert_eq!(message.content[1]["name"], "get_weather");
+    assert!(message.content[1]["id"]
+        .as_str()
+        .unwrap()
+        .starts_with("toolu_"));
+    assert_eq!(message.usage.input_tokens, 40);
+    assert_eq!(message.usage.output_tokens, 18);
+}
@@ -0,0 +1,45 @@
+use relay_anthropic_to_gemini::ClaudeStreamConverter;
+use relay_test_support::{assert_fixture, fixture, replay};
+
+/// Feeds a recorded Gemini SSE fixture through the converter in small
+/// pieces, returning the exact bytes sent to an Anthropic client.
+fn convert_fixture(name: &str) -> String {
+    let mut converter =
+        ClaudeStreamConverter::with_message_id("msg_test".to_string(), "gemini-2.5-pro");
+    replay(
+        &fixture!(&format!("{}.sse", name)),
+        &mut converter,
+        ClaudeStreamConverter::push,
+        ClaudeStreamConverter::finish,
+        ClaudeStreamConverter::encode,
+    )
+}
+
+#[test]
+fn test_stream_text() {
+    assert_fixture!(convert_fixture("text"), "text");
+}
+
+#[test]
+fn test_stream_tool_use() {
+    assert_fixture!(convert_fixture("tool_use"), "tool_use");
+}
+
+#[test]
+fn test_stream_without_finish_reason_is_closed() {
+    let mut converter =
+        ClaudeStreamConverter::with_message_id("msg_test".to_string(), "gemini-2.5-pro");
+    let events = converter.push(
+        b"data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi\"}],\"role\":\"model\"}}]}\r\n\r\n",
+    );
+    assert_eq!(events.len(), 3);
+
+    let events = converter.finish();
+    let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(
+        types,
+        ["content_block_stop", "message_delta", "message_stop"]
+    );
+    assert_eq!(events[1]["delta"]["stop_reason"], "end_turn");
+    assert!(converter.finish().is_empty());
+}
@@ -0,0 +1,271 @@
+use relay_claude::{MessagesRequest, MessagesResponse, Usage};
+use relay_core::RelayError;
+use relay_openai_to_anthropic::{
+    ChatCompletionRequest, ChatCompletionResponse, ChatMessage, ContentPart, FunctionCall,
+    FunctionDefinition, ImageUrl, MessageContent, StopSequence, StreamOptions, Tool, ToolCall,
+};
+use serde_json::{json, Value};
+
+pub struct ClaudeToOpenAIConverter;
+
+impl ClaudeToOpenAIConverter {
+    pub fn convert_request(req: MessagesRequest) -> Result<ChatCompletionRequest, RelayError> {
+        let mut messages = Vec::new();
+
+        if let Some(system) = &req.system {
+            let text = text_of(system).join("\n");
+            if !text.is_empty() {
+                messages.push(message("system", MessageContent::Text(text)));
+            }
+        }
+
+        for msg in &req.messages {
+            let blocks = match &msg.content {
+                Value::String(text) => vec![json!({"type": "text", "text": text})],
+                Value::Array(blocks) => blocks.clone(),
+                _ => Vec::new(),
+            };
+
+            let mut parts = Vec::new();
+            let mut tool_calls = Vec::new();
+
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        let text = block["text"].as_str().unwrap_or_default();
+                        if !text.is_empty() {
+                            parts.push(ContentPart::Text {
+                                text: text.to_string(),
+                            });
+                        }
+                    }
+                    Some("image") => parts.push(ContentPart::ImageUrl {
+                        image_url: image_url(&block["source"])?,
+                    }),
+                    Some("document") if block["source"]["type"] == "text" => {
+                        parts.push(ContentPart::Text {
+                            text: block["source"]["data"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                        });
+                    }
+                    Some("document") => {
+                        return Err(RelayError::InvalidRequest(
+                            "Only text documents can be sent to OpenAI-compatible backends"
+                                .to_string(),
+                        ));
+                    }
+                    Some("tool_use") => tool_calls.push(ToolCall {
+                        id: block["id"].as_str().unwrap_or_default().to_string(),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: block["name"].as_str().unwrap_or_default().to_string(),
+                            arguments: block.get("input").unwrap_or(&json!({})).to_string(),
+                        },
+                    }),
+                    // OpenAI expects each tool result as its own message
+                    // directly after the assistant's tool calls
+                    Some("tool_result") => {
+                        let output = block
+                            .get("content")
+                            .map(|content| text_of(content).join("\n"))
+                            .unwrap_or_default();
+                        let mut result = message("tool", MessageContent::Text(output));
+                        result.tool_call_id = block["tool_use_id"].as_str().map(str::to_string);
+                        messages.push(result);
+                    }
+                    _ => {} // Thinking blocks have no OpenAI counterpart
+                }
+            }
+
+            if msg.role == "assistant" {
+                if parts.is_empty() && tool_calls.is_empty() {
+                    continue;
+                }
+                let mut assistant = message("assistant", MessageContent::Text(joined(&parts)));
+                assistant.tool_calls = (!tool_calls.is_empty()).then_some(tool_calls);
+                messages.push(assistant);
+            } else if !parts.is_empty() {
+                let content = match &parts[..] {
+                    [ContentPart::Text { text }] => MessageContent::Text(text.clone()),
+                    _ => MessageContent::Parts(parts),
+                };
+                messages.push(message("user", content));
+            }
+        }
+
+        let tools: Vec<Tool> = req
+            .tools
+            .iter()
+            .flatten()
+            // Server tools such as web search have no schema and cannot be declared
+            .filter(|tool| tool.get("input_schema").is_some())
+            .map(|tool| Tool {
+                tool_type: "function".to_string(),
+                function: FunctionDefinition {
+                    name: tool["name"].as_str().unwrap_or_default().to_string(),
+                    description: tool["description"].as_str().map(str::to_string),
+                    parameters: Some(tool["input_schema"].clone()),
+                },
+            })
+            .collect();
+        let tool_choice = if tools.is_empty() {
+            None
+        } else {
+            req.tool_choice.as_ref().and_then(convert_tool_choice)
+        };
+
+        let stop = req
+            .extra
+            .get("stop_sequences")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .map(StopSequence::Multiple);
+
+        Ok(ChatCompletionRequest {
+            model: req.model,
+            messages,
+            stream: req.stream,
+            max_tokens: (req.max_tokens > 0).then_some(req.max_tokens),
+            temperature: req.temperature,
+            top_p: req.top_p,
+            stop,
+            tools: (!tools.is_empty()).then_some(tools),
+            tool_choice,
+            // Usage is only reported in a final chunk when asked for
+            stream_options: req.stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    /// `model` is reported back to the client as the model that answered.
+    pub fn convert_response(resp: ChatCompletionResponse, model: &str) -> MessagesResponse {
+        let mut content = Vec::new();
+        let choice = resp.choices.into_iter().next();
+        let finish_reason = choice.as_ref().and_then(|c| c.finish_reason.clone());
+
+        if let Some(message) = choice.map(|c| c.message) {
+            // DeepSeek-style reasoning is Claude's thinking; there is no
+            // signature to return with it
+            if let Some(thinking) = message.reasoning_content.filter(|text| !text.is_empty()) {
+                content.push(json!({"type": "thinking", "thinking": thinking, "signature": ""}));
+            }
+            if let Some(text) = message.content.filter(|text| !text.is_empty()) {
+                content.push(json!({"type": "text", "text": text}));
+            }
+            for call in message.tool_calls.into_iter().flatten() {
+                content.push(json!({
+                    "type": "tool_use",
+                    "id": call.id,
+                    "name": call.function.name,
+                    "input": tool_input(&call.function.arguments)
+                }));
+            }
+        }
+
+        let has_tool_use = content.iter().any(|block| block["type"] == "tool_use");
+        let usage = resp.usage.unwrap_or_default();
+
+        MessagesResponse {
+            id: message_id(),
+            response_type: "message".to_string(),
+            role: "assistant".to_string(),
+            content: Value::Array(content),
+            model: model.to_string(),
+            stop_reason: Some(stop_reason(finish_reason.as_deref(), has_tool_use).to_string()),
+            stop_sequence: None,
+            usage: Usage {
+                input_tokens: usage.prompt_tokens,
+                output_tokens: usage.completion_tokens,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+            },
+        }
+    }
+}
+
+pub(crate) fn message_id() -> String {
+    format!("msg_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Anthropic `stop_reason` for an OpenAI `finish_reason`.
+pub(crate) fn stop_reason(finish_reason: Option<&str>, has_tool_use: bool) -> &'static str {
+    if has_tool_use {
+        return "tool_use";
+    }
+    match finish_reason {
+        Some("length") => "max_tokens",
+        Some("content_filter") => "refusal",
+        _ => "end_turn",
+    }
+}
+
+/// Tool call arguments as a JSON object; local models sometimes emit
+/// invalid JSON, which becomes an empty input.
+fn tool_input(arguments: &str) -> Value {
+    serde_json::from_str(arguments).unwrap_or_else(|_| json!({}))
+}
+
+fn message(role: &str, content: MessageContent) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content,
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+fn joined(parts: &[ContentPart]) -> String {
+    parts
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { text } => Some(text.as_str()),
+            ContentPart::ImageUrl { .. } => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn image_url(source: &Value) -> Result<ImageUrl, RelayError> {
+    let url = match source["type"].as_str() {
+        Some("base64") => format!(
+            "data:{};base64,{}",
+            source["media_type"].as_str().unwrap_or_default(),
+            source["data"].as_str().unwrap_or_default()
+        ),
+        Some("url") => source["url"].as_str().unwrap_or_default().to_string(),
+        other => {
+            return Err(RelayError::InvalidRequest(format!(
+                "Unsupported image source type for OpenAI-compatible backends: {}",
+                other.unwrap_or("missing")
+            )));
+        }
+    };
+    Ok(ImageUrl { url, detail: None })
+}
+
+/// Text of a string or an array of content blocks, one entry per text block.
+fn text_of(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(text) => vec![text.clone()],
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn convert_tool_choice(choice: &Value) -> Option<Value> {
+    Some(match choice.get("type")?.as_str()? {
+        "auto" => json!("auto"),
+        "any" => json!("required"),
+        "none" => json!("none"),
+        "tool" => json!({"type": "function", "function": {"name": choice.get("name")?}}),
+        _ => return None,
+    })
+}
@@ -0,0 +1,5 @@
+mod converter;
+mod stream;
+
+pub use converter::ClaudeToOpenAIConverter;
+pub use stream::ClaudeStreamConverter;
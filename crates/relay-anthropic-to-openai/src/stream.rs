@@ -0,0 +1,243 @@
+use relay_core::SseLines;
+use relay_openai_to_anthropic::{ChatCompletionChunk, Usage};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::converter::{message_id, stop_reason};
+
+/// Translates an OpenAI `chat.completion.chunk` SSE byte stream into
+/// Anthropic Messages SSE events.
+///
+/// Content deltas go to an open text block and `reasoning_content` deltas,
+/// as sent by DeepSeek, to an open `thinking` block. Each tool call index opens a
+/// `tool_use` block whose argument fragments become `input_json_delta`s.
+/// The `finish_reason` is held until `[DONE]`, since the usage chunk
+/// requested with `stream_options.include_usage` comes after it.
+pub struct ClaudeStreamConverter {
+    lines: SseLines,
+    message_id: String,
+    model: String,
+    started: bool,
+    finished: bool,
+    next_index: usize,
+    /// Index and kind of the block receiving deltas.
+    open_block: Option<(usize, OpenBlock)>,
+    /// Anthropic block index for each OpenAI tool call index.
+    tool_blocks: HashMap<u32, usize>,
+    finish_reason: Option<String>,
+    usage: Usage,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OpenBlock {
+    Thinking,
+    Text,
+    ToolUse(u32),
+}
+
+impl ClaudeStreamConverter {
+    pub fn new(model: &str) -> Self {
+        Self::with_message_id(message_id(), model)
+    }
+
+    pub fn with_message_id(message_id: String, model: &str) -> Self {
+        Self {
+            lines: SseLines::new(),
+            message_id,
+            model: model.to_string(),
+            started: false,
+            finished: false,
+            next_index: 0,
+            open_block: None,
+            tool_blocks: HashMap::new(),
+            finish_reason: None,
+            usage: Usage::default(),
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Value> {
+        let mut events = Vec::new();
+        for data in self.lines.push(bytes) {
+            if data == "[DONE]" {
+                self.finish_into(&mut events);
+            } else if let Ok(chunk) = serde_json::from_str::<ChatCompletionChunk>(&data) {
+                self.convert_chunk(chunk, &mut events);
+            }
+        }
+        events
+    }
+
+    /// Closes the message if the upstream stream ended without `[DONE]`.
+    pub fn finish(&mut self) -> Vec<Value> {
+        let mut events = Vec::new();
+        self.finish_into(&mut events);
+        events
+    }
+
+    pub fn encode(event: &Value) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            event["type"].as_str().unwrap_or_default(),
+            serde_json::to_string(event).unwrap_or_default()
+        )
+    }
+
+    fn convert_chunk(&mut self, chunk: ChatCompletionChunk, events: &mut Vec<Value>) {
+        if self.finished {
+            return;
+        }
+        if let Some(usage) = chunk.usage {
+            self.usage = usage;
+        }
+        if !self.started {
+            self.started = true;
+            events.push(json!({
+                "type": "message_start",
+                "message": {
+                    "id": self.message_id,
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [],
+                    "model": self.model,
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {
+                        "input_tokens": self.usage.prompt_tokens,
+                        "output_tokens": 0
+                    }
+                }
+            }));
+        }
+
+        let Some(choice) = chunk.choices.into_iter().next() else {
+            return;
+        };
+
+        if let Some(thinking) = choice
+            .delta
+            .reasoning_content
+            .filter(|text| !text.is_empty())
+        {
+            let index = self.block(
+                OpenBlock::Thinking,
+                json!({"type": "thinking", "thinking": "", "signature": ""}),
+                events,
+            );
+            events.push(json!({
+                "type": "content_block_delta",
+                "index": index,
+                "delta": {"type": "thinking_delta", "thinking": thinking}
+            }));
+        }
+
+        if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+            let index = self.block(OpenBlock::Text, json!({"type": "text", "text": ""}), events);
+            events.push(json!({
+                "type": "content_block_delta",
+                "index": index,
+                "delta": {"type": "text_delta", "text": text}
+            }));
+        }
+
+        for call in choice.delta.tool_calls.into_iter().flatten() {
+            let (name, arguments) = call
+                .function
+                .map(|f| (f.name, f.arguments))
+                .unwrap_or_default();
+            let index = match self.tool_blocks.get(&call.index) {
+                Some(&index)
+                    if self.open_block == Some((index, OpenBlock::ToolUse(call.index))) =>
+                {
+                    index
+                }
+                // Arguments for a call whose block was already closed
+                Some(_) => continue,
+                None => {
+                    let id = call
+                        .id
+                        .unwrap_or_else(|| tool_use_id(&self.message_id, self.next_index));
+                    let index = self.block(
+                        OpenBlock::ToolUse(call.index),
+                        json!({
+                            "type": "tool_use",
+                            "id": id,
+                            "name": name.unwrap_or_default(),
+                            "input": {}
+                        }),
+                        events,
+                    );
+                    self.tool_blocks.insert(call.index, index);
+                    index
+                }
+            };
+            if let Some(arguments) = arguments.filter(|a| !a.is_empty()) {
+                events.push(json!({
+                    "type": "content_block_delta",
+                    "index": index,
+                    "delta": {"type": "input_json_delta", "partial_json": arguments}
+                }));
+            }
+        }
+
+        if let Some(reason) = choice.finish_reason {
+            self.finish_reason = Some(reason);
+        }
+    }
+
+    /// Index of the open block of `kind`, closing any other open block and
+    /// starting `content_block` if needed.
+    fn block(&mut self, kind: OpenBlock, content_block: Value, events: &mut Vec<Value>) -> usize {
+        if let Some((index, open)) = self.open_block {
+            if open == kind {
+                return index;
+            }
+        }
+        self.close_block(events);
+        let index = self.next_index;
+        self.next_index += 1;
+        self.open_block = Some((index, kind));
+        events.push(json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": content_block
+        }));
+        index
+    }
+
+    fn close_block(&mut self, events: &mut Vec<Value>) {
+        if let Some((index, _)) = self.open_block.take() {
+            events.push(json!({"type": "content_block_stop", "index": index}));
+        }
+    }
+
+    fn finish_into(&mut self, events: &mut Vec<Value>) {
+        if !self.started || self.finished {
+            return;
+        }
+        self.finished = true;
+        self.close_block(events);
+        let has_tool_use = !self.tool_blocks.is_empty();
+        events.push(json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": stop_reason(self.finish_reason.as_deref(), has_tool_use),
+                "stop_sequence": null
+            },
+            "usage": {
+                "input_tokens": self.usage.prompt_tokens,
+                "output_tokens": self.usage.completion_tokens
+            }
+        }));
+        events.push(json!({"type": "message_stop"}));
+    }
+}
+
+/// Some servers omit tool call ids, so one is derived from the message id
+/// and the block's position.
+fn tool_use_id(message_id: &str, index: usize) -> String {
+    format!(
+        "toolu_{}_{}",
+        message_id.strip_prefix("msg_").unwrap_or(message_id),
+        index
+    )
+}
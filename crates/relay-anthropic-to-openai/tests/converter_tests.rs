@@ -0,0 +1,199 @@
+use relay_anthropic_to_openai::ClaudeToOpenAIConverter;
+use relay_claude::MessagesRequest;
+use relay_openai_to_anthropic::{ChatCompletionResponse, ContentPart, MessageContent};
+use serde_json::json;
+
+fn request(value: serde_json::Value) -> MessagesRequest {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_messages_and_system() {
+    let req = request(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 256,
+        "temperature": 0.5,
+        "stream": true,
+        "stop_sequences": ["END"],
+        "system": [{"type": "text", "text": "Be brief."}],
+        "messages": [
+            {"role": "user", "content": "Hi"},
+            {"role": "assistant", "content": [
+                {"type": "thinking", "thinking": "...", "signature": "sig"},
+                {"type": "text", "text": "Hello!"}
+            ]},
+            {"role": "user", "content": [
+                {"type": "text", "text": "Describe"},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+            ]}
+        ]
+    }));
+
+    let openai_request = ClaudeToOpenAIConverter::convert_request(req).unwrap();
+
+    let roles: Vec<&str> = openai_request
+        .messages
+        .iter()
+        .map(|m| m.role.as_str())
+        .collect();
+    assert_eq!(roles, ["system", "user", "assistant", "user"]);
+    assert!(matches!(
+        &openai_request.messages[0].content,
+        MessageContent::Text(text) if text == "Be brief."
+    ));
+    assert!(matches!(
+        &openai_request.messages[2].content,
+        MessageContent::Text(text) if text == "Hello!"
+    ));
+    match &openai_request.messages[3].content {
+        MessageContent::Parts(parts) => assert!(matches!(
+            &parts[1],
+            ContentPart::ImageUrl { image_url } if image_url.url == "data:image/png;base64,iVBORw0KGgo="
+        )),
+        other => panic!("expected content parts, got {:?}", other),
+    }
+
+    assert_eq!(openai_request.max_tokens, Some(256));
+    assert_eq!(openai_request.temperature, Some(0.5));
+    assert!(openai_request.include_usage());
+    assert_eq!(
+        serde_json::to_value(&openai_request.stop).unwrap(),
+        json!(["END"])
+    );
+}
+
+#[test]
+fn test_tools_and_results() {
+    let req = request(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "tools": [
+            {
+                "name": "get_weather",
+                "description": "Current weather",
+                "input_schema": {
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "required": ["city"]
+                }
+            },
+            {"type": "web_search_20250305", "name": "web_search"}
+        ],
+        "tool_choice": {"type": "any"},
+        "messages": [
+            {"role": "user", "content": "Weather in Paris?"},
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"city": "Paris"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_01", "content": [{"type": "text", "text": "18°C"}]},
+                {"type": "text", "text": "And tomorrow?"}
+            ]}
+        ]
+    }));
+
+    let openai_request = ClaudeToOpenAIConverter::convert_request(req).unwrap();
+
+    let tools = openai_request.tools.unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].function.name, "get_weather");
+    assert_eq!(openai_request.tool_choice, Some(json!("required")));
+
+    let roles: Vec<&str> = openai_request
+        .messages
+        .iter()
+        .map(|m| m.role.as_str())
+        .collect();
+    assert_eq!(roles, ["user", "assistant", "tool", "user"]);
+    let call = &openai_request.messages[1].tool_calls.as_ref().unwrap()[0];
+    assert_eq!(call.id, "toolu_01");
+    assert_eq!(call.function.arguments, r#"{"city":"Paris"}"#);
+    let result = &openai_request.messages[2];
+    assert_eq!(result.tool_call_id.as_deref(), Some("toolu_01"));
+    assert!(matches!(&result.content, MessageContent::Text(text) if text == "18°C"));
+}
+
+#[test]
+fn test_pdf_document_is_rejected() {
+    let req = request(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "messages": [{"role": "user", "content": [
+            {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0="}}
+        ]}]
+    }));
+
+    assert!(ClaudeToOpenAIConverter::convert_request(req).is_err());
+}
+
+#[test]
+fn test_convert_response() {
+    let resp: ChatCompletionResponse = serde_json::from_value(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "llama3.1",
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "Checking.",
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                }]
+            },
+            "finish_reason": "tool_calls"
+        }],
+        "usage": {"prompt_tokens": 40, "completion_tokens": 18, "total_tokens": 58}
+    }))
+    .unwrap();
+
+    let message = ClaudeToOpenAIConverter::convert_response(resp, "claude-sonnet-4-20250514");
+
+    assert!(message.id.starts_with("msg_"));
+    assert_eq!(message.model, "claude-sonnet-4-20250514");
+    assert_eq!(message.stop_reason.as_deref(), Some("tool_use"));
+    assert_eq!(
+        message.content,
+        json!([
+            {"type": "text", "text": "Checking."},
+            {"type": "tool_use", "id": "call_1", "name": "get_weather", "input": {"city": "Paris"}}
+        ])
+    );
+    assert_eq!(message.usage.input_tokens, 40);
+    assert_eq!(message.usage.output_tokens, 18);
+}
+
+#[test]
+fn test_reasoning_content_becomes_thinking() {
+    let resp: ChatCompletionResponse = serde_json::from_value(json!({
+        "id": "a1b2c3",
+        "object": "chat.completion",
+        "created": 1736000000,
+        "model": "deepseek-reasoner",
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "9.9 is larger.",
+                "reasoning_content": "9.90 > 9.11"
+            },
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 18, "completion_tokens": 42, "total_tokens": 60}
+    }))
+    .unwrap();
+
+    let message = ClaudeToOpenAIConverter::convert_response(resp, "claude-sonnet-4-20250514");
+
+    assert_eq!(message.stop_reason.as_deref(), Some("end_turn"));
+    assert_eq!(
+        message.content,
+        json!([
+            {"type": "thinking", "thinking": "9.90 > 9.11", "signature": ""},
+            {"type": "text", "text": "9.9 is larger."}
+        ])
+    );
+}
@@ -0,0 +1,50 @@
+use relay_anthropic_to_openai::ClaudeStreamConverter;
+use relay_test_support::{assert_fixture, fixture, replay};
+
+/// Feeds a recorded OpenAI SSE fixture through the converter in small
+/// pieces, returning the exact bytes sent to an Anthropic client.
+fn convert_fixture(name: &str) -> String {
+    let mut converter =
+        ClaudeStreamConverter::with_message_id("msg_test".to_string(), "claude-sonnet-4-20250514");
+    replay(
+        &fixture!(&format!("{}.sse", name)),
+        &mut converter,
+        ClaudeStreamConverter::push,
+        ClaudeStreamConverter::finish,
+        ClaudeStreamConverter::encode,
+    )
+}
+
+#[test]
+fn test_stream_text() {
+    assert_fixture!(convert_fixture("text"), "text");
+}
+
+#[test]
+fn test_stream_tool_calls() {
+    assert_fixture!(convert_fixture("tool_calls"), "tool_calls");
+}
+
+#[test]
+fn test_stream_reasoning_as_thinking() {
+    assert_fixture!(convert_fixture("reasoning"), "reasoning");
+}
+
+#[test]
+fn test_stream_without_done_is_closed() {
+    let mut converter =
+        ClaudeStreamConverter::with_message_id("msg_test".to_string(), "claude-sonnet-4-20250514");
+    let events = converter.push(
+        b"data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"llama3.1\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n",
+    );
+    assert_eq!(events.len(), 3);
+
+    let events = converter.finish();
+    let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(
+        types,
+        ["content_block_stop", "message_delta", "message_stop"]
+    );
+    assert_eq!(events[1]["delta"]["stop_reason"], "end_turn");
+    assert!(converter.finish().is_empty());
+}
@@ -0,0 +1,100 @@
+use relay_core::{RelayError, Result};
+use std::collections::HashMap;
+
+/// Prelude (total and header lengths, prelude CRC) plus the message CRC.
+const FRAME_OVERHEAD: usize = 16;
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// One message of an `application/vnd.amazon.eventstream` response.
+#[derive(Debug, Default)]
+pub struct EventStreamMessage {
+    /// String-valued headers, such as `:message-type` and `:event-type`.
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+impl EventStreamMessage {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|v| v.as_str())
+    }
+}
+
+/// Splits the AWS event stream framing of a response body into messages.
+/// CRCs are not checked; TLS already protects the stream.
+#[derive(Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<EventStreamMessage>> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut messages = Vec::new();
+        while self.buffer.len() >= 12 {
+            let total = read_u32(&self.buffer[0..4]) as usize;
+            let headers_len = read_u32(&self.buffer[4..8]) as usize;
+            if !(FRAME_OVERHEAD..=MAX_MESSAGE_SIZE).contains(&total)
+                || headers_len > total - FRAME_OVERHEAD
+            {
+                return Err(RelayError::Internal(format!(
+                    "Invalid event stream frame: length {}, headers {}",
+                    total, headers_len
+                )));
+            }
+            if self.buffer.len() < total {
+                break;
+            }
+
+            let frame: Vec<u8> = self.buffer.drain(..total).collect();
+            let headers = parse_headers(&frame[12..12 + headers_len])?;
+            messages.push(EventStreamMessage {
+                headers,
+                payload: frame[12 + headers_len..total - 4].to_vec(),
+            });
+        }
+        Ok(messages)
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn parse_headers(mut bytes: &[u8]) -> Result<HashMap<String, String>> {
+    let invalid = || RelayError::Internal("Invalid event stream header".to_string());
+    let mut headers = HashMap::new();
+
+    while !bytes.is_empty() {
+        let name_len = bytes[0] as usize;
+        let name = bytes.get(1..1 + name_len).ok_or_else(invalid)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        let value_type = *bytes.get(1 + name_len).ok_or_else(invalid)?;
+        bytes = &bytes[2 + name_len..];
+
+        let value_len = match value_type {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => {
+                let len = bytes.get(0..2).ok_or_else(invalid)?;
+                bytes = &bytes[2..];
+                u16::from_be_bytes([len[0], len[1]]) as usize
+            }
+            _ => return Err(invalid()),
+        };
+        let value = bytes.get(..value_len).ok_or_else(invalid)?;
+        if value_type == 7 {
+            headers.insert(name, String::from_utf8_lossy(value).into_owned());
+        }
+        bytes = &bytes[value_len..];
+    }
+    Ok(headers)
+}
@@ -0,0 +1,9 @@
+mod account;
+mod event_stream;
+mod relay;
+mod sigv4;
+
+pub use account::BedrockAccount;
+pub use event_stream::{EventStreamDecoder, EventStreamMessage};
+pub use relay::BedrockRelay;
+pub use sigv4::sign;
@@ -0,0 +1,273 @@
+use async_stream::try_stream;
+use async_trait::async_trait;
+use base64::Engine;
+use bytes::Bytes;
+use futures::StreamExt;
+use relay_claude::{MessagesRequest, MessagesResponse};
+use relay_core::{
+    read_error_response, send_captured, AccountProvider, AwsCredentials, BoxStream, Capture,
+    ClientCache, Credentials, HttpClientOptions, Relay, RelayError, Result, UpstreamTimeouts,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::event_stream::{EventStreamDecoder, EventStreamMessage};
+use crate::sigv4;
+
+/// Relays Anthropic Messages requests to the Bedrock runtime API.
+pub struct BedrockRelay {
+    clients: ClientCache,
+    model_ids: HashMap<String, String>,
+    timeouts: Arc<UpstreamTimeouts>,
+    capture: Option<Arc<Capture>>,
+}
+
+impl BedrockRelay {
+    const ANTHROPIC_VERSION: &'static str = "bedrock-2023-05-31";
+    const SERVICE: &'static str = "bedrock";
+
+    pub fn new() -> Self {
+        Self {
+            clients: ClientCache::default(),
+            model_ids: HashMap::new(),
+            timeouts: Arc::default(),
+            capture: None,
+        }
+    }
+
+    /// Bounds each upstream request, body included.
+    pub fn with_timeouts(mut self, timeouts: Arc<UpstreamTimeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Writes each upstream exchange to `capture`, for debugging.
+    pub fn with_capture(mut self, capture: Option<Arc<Capture>>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// HTTP version, pooling and keepalive of upstream connections.
+    pub fn with_client_options(mut self, options: HttpClientOptions) -> Self {
+        self.clients = ClientCache::new(options);
+        self
+    }
+
+    /// Bedrock model ids (or inference profile ARNs) for Anthropic model
+    /// names, such as `claude-sonnet-4-20250514` to
+    /// `us.anthropic.claude-sonnet-4-20250514-v1:0`.
+    pub fn with_model_ids(mut self, model_ids: HashMap<String, String>) -> Self {
+        self.model_ids = model_ids;
+        self
+    }
+
+    /// The configured id for `model`; names that already look like Bedrock
+    /// ids are used as they are, others get the `anthropic.` prefix and the
+    /// `-v1:0` suffix.
+    pub fn model_id(&self, model: &str) -> String {
+        if let Some(id) = self.model_ids.get(model) {
+            return id.clone();
+        }
+        if model.contains('.') || model.starts_with("arn:") {
+            model.to_string()
+        } else {
+            format!("anthropic.{}-v1:0", model)
+        }
+    }
+
+    fn endpoint(account: &dyn AccountProvider, credentials: &AwsCredentials) -> String {
+        account
+            .api_url()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| {
+                format!(
+                    "https://bedrock-runtime.{}.amazonaws.com",
+                    credentials.region
+                )
+            })
+    }
+
+    /// The request body without the fields Bedrock takes from the URL or
+    /// rejects.
+    fn body(request: &MessagesRequest) -> Result<Vec<u8>> {
+        let mut body = serde_json::to_value(request)
+            .map_err(|e| RelayError::InvalidRequest(format!("Invalid request: {}", e)))?;
+        if let Some(object) = body.as_object_mut() {
+            object.remove("model");
+            object.remove("stream");
+            object.remove("metadata");
+            object.insert(
+                "anthropic_version".to_string(),
+                serde_json::Value::String(Self::ANTHROPIC_VERSION.to_string()),
+            );
+        }
+        serde_json::to_vec(&body)
+            .map_err(|e| RelayError::Internal(format!("Failed to encode request: {}", e)))
+    }
+
+    async fn send(
+        &self,
+        account: &dyn AccountProvider,
+        request: &MessagesRequest,
+        action: &str,
+    ) -> Result<reqwest::Response> {
+        let credentials = match account.get_credentials().await? {
+            Credentials::AwsSigV4(credentials) => credentials,
+            _ => {
+                return Err(RelayError::Config(format!(
+                    "Account {} has no AWS credentials",
+                    account.id()
+                )))
+            }
+        };
+        let client = self.clients.client(account.proxy_config())?;
+
+        let model_id = self.model_id(&request.model);
+        let url = format!(
+            "{}/model/{}/{}",
+            Self::endpoint(account, &credentials),
+            sigv4::uri_encode(&model_id),
+            action
+        );
+        let url = reqwest::Url::parse(&url)
+            .map_err(|e| RelayError::Config(format!("Invalid Bedrock URL: {}", e)))?;
+        let body = Self::body(request)?;
+
+        debug!(
+            account_id = %account.id(),
+            model = %request.model,
+            model_id = %model_id,
+            url = %url,
+            "Sending Bedrock request"
+        );
+
+        let content_type = ("content-type", "application/json");
+        let mut builder = client
+            .post(url.clone())
+            .header(content_type.0, content_type.1)
+            .timeout(self.timeouts.for_request(account.id(), &request.model));
+        for (name, value) in sigv4::sign(
+            &credentials,
+            Self::SERVICE,
+            "POST",
+            &url,
+            &[content_type],
+            &body,
+            chrono::Utc::now(),
+        ) {
+            builder = builder.header(name, value);
+        }
+        let response = send_captured(builder.body(body), self.capture.as_deref()).await?;
+
+        let status = response.status();
+        debug!(
+            account_id = %account.id(),
+            status = %status,
+            "Received Bedrock response"
+        );
+        if !status.is_success() {
+            let error = read_error_response(response).await;
+            warn!(
+                account_id = %account.id(),
+                model_id = %model_id,
+                error = %error,
+                "Bedrock request failed"
+            );
+            return Err(error);
+        }
+        Ok(response)
+    }
+}
+
+impl Default for BedrockRelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The Anthropic SSE event carried by a `chunk` event, or an `error` event
+/// for exceptions raised mid-stream.
+fn sse_event(message: &EventStreamMessage) -> Option<String> {
+    match message.header(":message-type") {
+        Some("event") if message.header(":event-type") == Some("chunk") => {
+            let payload: serde_json::Value = serde_json::from_slice(&message.payload).ok()?;
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(payload["bytes"].as_str()?)
+                .ok()?;
+            let event: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+            let event_type = event["type"].as_str()?.to_string();
+            Some(format!("event: {}\ndata: {}\n\n", event_type, event))
+        }
+        Some("exception") | Some("error") => {
+            let detail: serde_json::Value =
+                serde_json::from_slice(&message.payload).unwrap_or_default();
+            let error = serde_json::json!({
+                "type": "error",
+                "error": {
+                    "type": "overloaded_error",
+                    "message": detail["message"].as_str().unwrap_or(
+                        message.header(":exception-type").unwrap_or("Bedrock stream error")
+                    ),
+                }
+            });
+            Some(format!("event: error\ndata: {}\n\n", error))
+        }
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl Relay for BedrockRelay {
+    type Request = MessagesRequest;
+    type Response = MessagesResponse;
+
+    async fn relay(
+        &self,
+        account: &dyn AccountProvider,
+        request: MessagesRequest,
+    ) -> Result<MessagesResponse> {
+        let response = self.send(account, &request, "invoke").await?;
+
+        let mut body: serde_json::Value = response.json().await?;
+        if body.get("model").is_none() {
+            body["model"] = serde_json::Value::String(request.model.clone());
+        }
+        let resp: MessagesResponse = serde_json::from_value(body)
+            .map_err(|e| RelayError::Internal(format!("Invalid Bedrock response: {}", e)))?;
+
+        info!(
+            account_id = %account.id(),
+            input_tokens = resp.usage.input_tokens,
+            output_tokens = resp.usage.output_tokens,
+            stop_reason = ?resp.stop_reason,
+            "Bedrock request completed"
+        );
+        Ok(resp)
+    }
+
+    async fn relay_stream(
+        &self,
+        account: &dyn AccountProvider,
+        request: MessagesRequest,
+    ) -> Result<BoxStream<Result<Bytes>>> {
+        let response = self
+            .send(account, &request, "invoke-with-response-stream")
+            .await?;
+
+        let stream = try_stream! {
+            let mut byte_stream = response.bytes_stream();
+            let mut decoder = EventStreamDecoder::new();
+
+            while let Some(chunk) = byte_stream.next().await {
+                for message in decoder.push(&chunk?)? {
+                    if let Some(event) = sse_event(&message) {
+                        yield Bytes::from(event);
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use relay_core::AwsCredentials;
+use ring::hmac;
+use sha2::{Digest, Sha256};
+
+/// Headers that authenticate a request with AWS Signature Version 4:
+/// `x-amz-date`, `x-amz-security-token` for temporary credentials, and
+/// `authorization`. `headers` are signed along with `host` and must be sent
+/// unchanged.
+pub fn sign(
+    credentials: &AwsCredentials,
+    service: &str,
+    method: &str,
+    url: &reqwest::Url,
+    headers: &[(&str, &str)],
+    payload: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = &amz_date[..8];
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut signed: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+        .collect();
+    signed.push(("host".to_string(), host));
+    signed.push(("x-amz-date".to_string(), amz_date.clone()));
+    if let Some(token) = &credentials.session_token {
+        signed.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    signed.sort();
+
+    let canonical_headers: String = signed
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = signed
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri(url.path()),
+        canonical_query(url),
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(payload))
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, credentials.region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = format!("AWS4{}", credentials.secret_access_key);
+    let hmac_sha256 =
+        |key: &[u8], message: &[u8]| hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), message);
+    let key = hmac_sha256(key.as_bytes(), date.as_bytes());
+    let key = hmac_sha256(key.as_ref(), credentials.region.as_bytes());
+    let key = hmac_sha256(key.as_ref(), service.as_bytes());
+    let key = hmac_sha256(key.as_ref(), b"aws4_request");
+    let signature = hex::encode(hmac_sha256(key.as_ref(), string_to_sign.as_bytes()));
+
+    let mut result = vec![("x-amz-date", amz_date)];
+    if let Some(token) = &credentials.session_token {
+        result.push(("x-amz-security-token", token.clone()));
+    }
+    result.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    result
+}
+
+/// Encodes each path segment again, as every service but S3 expects.
+fn canonical_uri(path: &str) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn canonical_query(url: &reqwest::Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (uri_encode(&key), uri_encode(&value)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub(crate) fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
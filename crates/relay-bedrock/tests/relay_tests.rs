@@ -0,0 +1,238 @@
+use base64::Engine;
+use bytes::Bytes;
+use futures::StreamExt;
+use relay_bedrock::{sign, BedrockAccount, BedrockRelay, EventStreamDecoder};
+use relay_claude::{Message, MessagesRequest};
+use relay_core::{AwsCredentials, Relay};
+use std::collections::HashMap;
+
+fn credentials(region: &str) -> AwsCredentials {
+    AwsCredentials {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        session_token: None,
+        region: region.to_string(),
+    }
+}
+
+/// Encodes one event stream message with string headers; CRCs are left
+/// zeroed since the decoder does not check them.
+fn frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+    let mut encoded_headers = Vec::new();
+    for (name, value) in headers {
+        encoded_headers.push(name.len() as u8);
+        encoded_headers.extend_from_slice(name.as_bytes());
+        encoded_headers.push(7);
+        encoded_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        encoded_headers.extend_from_slice(value.as_bytes());
+    }
+
+    let total = 16 + encoded_headers.len() + payload.len();
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&(total as u32).to_be_bytes());
+    frame.extend_from_slice(&(encoded_headers.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&[0; 4]);
+    frame.extend_from_slice(&encoded_headers);
+    frame.extend_from_slice(payload);
+    frame.extend_from_slice(&[0; 4]);
+    frame
+}
+
+fn chunk_frame(event: serde_json::Value) -> Vec<u8> {
+    let bytes = base64::engine::general_purpose::STANDARD.encode(event.to_string());
+    frame(
+        &[
+            (":message-type", "event"),
+            (":event-type", "chunk"),
+            (":content-type", "application/json"),
+        ],
+        serde_json::json!({ "bytes": bytes }).to_string().as_bytes(),
+    )
+}
+
+fn request(stream: bool) -> MessagesRequest {
+    MessagesRequest {
+        model: "claude-sonnet-4-20250514".to_string(),
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: serde_json::json!("Hello"),
+        }],
+        max_tokens: 64,
+        stream,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_sign_matches_aws_test_suite() {
+    let url = reqwest::Url::parse("https://example.amazonaws.com/").unwrap();
+    let now = chrono::DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z")
+        .unwrap()
+        .with_timezone(&chrono::Utc);
+
+    let headers = sign(
+        &credentials("us-east-1"),
+        "service",
+        "GET",
+        &url,
+        &[],
+        b"",
+        now,
+    );
+
+    assert_eq!(headers[0], ("x-amz-date", "20150830T123600Z".to_string()));
+    assert_eq!(
+        headers[1].1,
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+         SignedHeaders=host;x-amz-date, \
+         Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+}
+
+#[test]
+fn test_model_id_mapping() {
+    let relay = BedrockRelay::new().with_model_ids(HashMap::from([(
+        "claude-sonnet-4-20250514".to_string(),
+        "us.anthropic.claude-sonnet-4-20250514-v1:0".to_string(),
+    )]));
+
+    assert_eq!(
+        relay.model_id("claude-sonnet-4-20250514"),
+        "us.anthropic.claude-sonnet-4-20250514-v1:0"
+    );
+    assert_eq!(
+        relay.model_id("claude-3-5-haiku-20241022"),
+        "anthropic.claude-3-5-haiku-20241022-v1:0"
+    );
+    assert_eq!(
+        relay.model_id("eu.anthropic.claude-3-7-sonnet-20250219-v1:0"),
+        "eu.anthropic.claude-3-7-sonnet-20250219-v1:0"
+    );
+}
+
+#[test]
+fn test_decoder_handles_split_frames() {
+    let bytes = [
+        chunk_frame(serde_json::json!({"type": "ping"})),
+        chunk_frame(serde_json::json!({"type": "message_stop"})),
+    ]
+    .concat();
+    let mut decoder = EventStreamDecoder::new();
+
+    let first = decoder.push(&bytes[..10]).unwrap();
+    let rest = decoder.push(&bytes[10..]).unwrap();
+
+    assert!(first.is_empty());
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest[1].header(":event-type"), Some("chunk"));
+}
+
+async fn start_bedrock_upstream() -> String {
+    use axum::{body::Body, http::HeaderMap, http::Uri, routing::post, Json, Router};
+
+    let app = Router::new()
+        .route(
+            "/model/:model_id/invoke",
+            post(
+                |uri: Uri, headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(
+                        uri.path(),
+                        "/model/anthropic.claude-sonnet-4-20250514-v1%3A0/invoke"
+                    );
+                    let auth = headers["authorization"].to_str().unwrap();
+                    assert!(auth.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+                    assert!(auth.contains("/us-west-2/bedrock/aws4_request"));
+                    assert_eq!(body["anthropic_version"], "bedrock-2023-05-31");
+                    assert!(body.get("model").is_none());
+                    assert!(body.get("stream").is_none());
+                    Json(serde_json::json!({
+                        "id": "msg_1",
+                        "type": "message",
+                        "role": "assistant",
+                        "content": [{"type": "text", "text": "Hi"}],
+                        "stop_reason": "end_turn",
+                        "usage": {"input_tokens": 10, "output_tokens": 2}
+                    }))
+                },
+            ),
+        )
+        .route(
+            "/model/:model_id/invoke-with-response-stream",
+            post(|| async {
+                let mut body = [
+                    chunk_frame(serde_json::json!({
+                        "type": "message_start",
+                        "message": {"usage": {"input_tokens": 10, "output_tokens": 1}}
+                    })),
+                    chunk_frame(serde_json::json!({"type": "message_stop"})),
+                    frame(
+                        &[
+                            (":message-type", "exception"),
+                            (":exception-type", "throttlingException"),
+                        ],
+                        br#"{"message":"Too many requests"}"#,
+                    ),
+                ]
+                .concat();
+                let tail = body.split_off(7);
+                Body::from_stream(futures::stream::iter([
+                    Ok::<_, std::io::Error>(Bytes::from(body)),
+                    Ok(Bytes::from(tail)),
+                ]))
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+fn account(base: String) -> BedrockAccount {
+    BedrockAccount::new(
+        "bedrock1".to_string(),
+        "Bedrock".to_string(),
+        100,
+        true,
+        credentials("us-west-2"),
+        Some(base),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_invoke_is_signed_and_translated() {
+    let account = account(start_bedrock_upstream().await);
+
+    let response = BedrockRelay::new()
+        .relay(&account, request(false))
+        .await
+        .unwrap();
+
+    assert_eq!(response.model, "claude-sonnet-4-20250514");
+    assert_eq!(response.usage.input_tokens, 10);
+    assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+}
+
+#[tokio::test]
+async fn test_stream_is_converted_to_sse() {
+    let account = account(start_bedrock_upstream().await);
+
+    let mut stream = BedrockRelay::new()
+        .relay_stream(&account, request(true))
+        .await
+        .unwrap();
+    let mut output = String::new();
+    while let Some(chunk) = stream.next().await {
+        output.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+    }
+
+    let events: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.strip_prefix("event: "))
+        .collect();
+    assert_eq!(events, ["message_start", "message_stop", "error"]);
+    assert!(output.contains(r#""input_tokens":10"#));
+    assert!(output.contains("Too many requests"));
+}
@@ -1,5 +1,9 @@
 mod api;
 mod oauth;
+mod openrouter;
+mod session;
 
 pub use api::ClaudeApiAccount;
 pub use oauth::ClaudeOAuthAccount;
+pub use openrouter::OpenRouterAccount;
+pub use session::ClaudeSessionAccount;
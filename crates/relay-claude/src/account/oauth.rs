@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 use parking_lot::RwLock;
-use relay_core::{AccountProvider, Credentials, Platform, ProxyConfig, Result, TokenInfo};
+use relay_core::{
+    AccountProvider, Credentials, Platform, ProxyConfig, Result, TokenCacheStatus, TokenInfo,
+};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
@@ -107,6 +109,10 @@ impl AccountProvider for ClaudeOAuthAccount {
         self.api_url.as_deref()
     }
 
+    fn token_cache_status(&self) -> Option<TokenCacheStatus> {
+        Some(TokenCacheStatus::of(self.token_cache.read().as_ref()))
+    }
+
     fn mark_unavailable(&self, duration: Duration, _reason: &str) {
         let mut until = self.unavailable_until.write();
         *until = Some(Instant::now() + duration);
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use relay_core::{AccountProvider, Credentials, Platform, ProxyConfig, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// An OpenRouter API key, serving Claude models through OpenRouter's
+/// Anthropic-compatible Messages endpoint.
+pub struct OpenRouterAccount {
+    id: String,
+    name: String,
+    priority: u32,
+    enabled: AtomicBool,
+    api_key: String,
+    api_url: String,
+    proxy: Option<ProxyConfig>,
+    headers: Vec<(String, String)>,
+    model_ids: HashMap<String, String>,
+    unavailable_until: RwLock<Option<Instant>>,
+}
+
+impl OpenRouterAccount {
+    pub const DEFAULT_API_URL: &'static str = "https://openrouter.ai/api";
+
+    pub fn new(
+        id: String,
+        name: String,
+        priority: u32,
+        enabled: bool,
+        api_key: String,
+        api_url: Option<String>,
+        proxy: Option<ProxyConfig>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            priority,
+            enabled: AtomicBool::new(enabled),
+            api_key,
+            api_url: api_url.unwrap_or_else(|| Self::DEFAULT_API_URL.to_string()),
+            proxy,
+            headers: Vec::new(),
+            model_ids: HashMap::new(),
+            unavailable_until: RwLock::new(None),
+        }
+    }
+
+    /// The `HTTP-Referer` and `X-Title` headers OpenRouter uses to
+    /// attribute requests to an app.
+    pub fn with_attribution(
+        mut self,
+        http_referer: Option<String>,
+        x_title: Option<String>,
+    ) -> Self {
+        self.headers.clear();
+        if let Some(referer) = http_referer {
+            self.headers.push(("HTTP-Referer".to_string(), referer));
+        }
+        if let Some(title) = x_title {
+            self.headers.push(("X-Title".to_string(), title));
+        }
+        self
+    }
+
+    /// OpenRouter model ids for Anthropic model names, overriding
+    /// [`Self::model_id`]'s derivation.
+    pub fn with_model_ids(mut self, model_ids: HashMap<String, String>) -> Self {
+        self.model_ids = model_ids;
+        self
+    }
+
+    /// The OpenRouter id for `model`: the configured id, otherwise
+    /// `anthropic/` followed by the name without its date and with dotted
+    /// versions, such as `claude-opus-4-1-20250805` to
+    /// `anthropic/claude-opus-4.1`. Ids that already name a provider are
+    /// kept.
+    pub fn model_id(&self, model: &str) -> String {
+        if let Some(id) = self.model_ids.get(model) {
+            return id.clone();
+        }
+        if model.contains('/') {
+            return model.to_string();
+        }
+
+        let name = match model.rsplit_once('-') {
+            Some((name, date)) if date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()) => {
+                name
+            }
+            _ => model,
+        };
+        let parts: Vec<&str> = name.split('-').collect();
+        let mut id = String::from("anthropic/");
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                let is_version = |s: &str| s.len() == 1 && s.chars().all(|c| c.is_ascii_digit());
+                id.push(if is_version(parts[i - 1]) && is_version(part) {
+                    '.'
+                } else {
+                    '-'
+                });
+            }
+            id.push_str(part);
+        }
+        id
+    }
+}
+
+#[async_trait]
+impl AccountProvider for OpenRouterAccount {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn platform(&self) -> Platform {
+        Platform::Claude
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    fn is_available(&self) -> bool {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        if let Some(until) = *self.unavailable_until.read() {
+            if Instant::now() < until {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    async fn get_credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::Bearer(self.api_key.clone()))
+    }
+
+    fn proxy_config(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    fn api_url(&self) -> Option<&str> {
+        Some(&self.api_url)
+    }
+
+    fn extra_headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    fn upstream_model(&self, model: &str) -> Option<String> {
+        Some(self.model_id(model))
+    }
+
+    fn mark_unavailable(&self, duration: Duration, _reason: &str) {
+        let mut until = self.unavailable_until.write();
+        *until = Some(Instant::now() + duration);
+    }
+
+    fn mark_available(&self) {
+        let mut until = self.unavailable_until.write();
+        *until = None;
+    }
+}
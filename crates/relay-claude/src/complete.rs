@@ -0,0 +1,247 @@
+//! The legacy Text Completions API (`/v1/complete`), served by translating
+//! to and from the Messages API.
+
+use relay_core::{RelayError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::types::{Message, MessagesRequest, MessagesResponse};
+
+const HUMAN_PROMPT: &str = "\n\nHuman:";
+const AI_PROMPT: &str = "\n\nAssistant:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteRequest {
+    pub model: String,
+    /// Alternating `\n\nHuman:` and `\n\nAssistant:` turns, ending with an
+    /// `\n\nAssistant:` turn for the model to continue.
+    pub prompt: String,
+    pub max_tokens_to_sample: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteResponse {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    pub id: String,
+    pub completion: String,
+    /// `stop_sequence` or `max_tokens`; `None` on all but the last
+    /// streamed event.
+    pub stop_reason: Option<String>,
+    /// The stop sequence that ended the completion, if any.
+    #[serde(default)]
+    pub stop: Option<String>,
+    pub model: String,
+}
+
+impl CompleteRequest {
+    /// Splits the prompt into messages: text before the first Human turn
+    /// becomes the system prompt and a non-empty final Assistant turn is
+    /// sent as a prefill for the model to continue.
+    pub fn into_messages_request(self) -> Result<MessagesRequest> {
+        let (system, turns) = split_prompt(&self.prompt)?;
+
+        let messages = turns
+            .into_iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(role, text)| Message {
+                role: role.to_string(),
+                content: Value::String(text),
+            })
+            .collect();
+
+        let mut extra = serde_json::Map::new();
+        if let Some(stop_sequences) = self.stop_sequences {
+            extra.insert("stop_sequences".to_string(), json!(stop_sequences));
+        }
+
+        Ok(MessagesRequest {
+            model: self.model,
+            messages,
+            max_tokens: self.max_tokens_to_sample,
+            stream: self.stream,
+            system: (!system.is_empty()).then_some(Value::String(system)),
+            temperature: self.temperature,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            metadata: self.metadata,
+            tools: None,
+            tool_choice: None,
+            extra,
+        })
+    }
+}
+
+impl CompleteResponse {
+    /// The text of a Messages response as a completion; thinking and tool
+    /// blocks have no place in it.
+    pub fn from_messages(response: MessagesResponse) -> Self {
+        let completion = response
+            .content
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+
+        Self {
+            response_type: "completion".to_string(),
+            id: completion_id(&response.id),
+            completion,
+            stop_reason: response.stop_reason.as_deref().map(legacy_stop_reason),
+            stop: response.stop_sequence,
+            model: response.model,
+        }
+    }
+}
+
+/// Translates a Messages SSE byte stream into `completion` events.
+///
+/// Network chunks are buffered until a complete SSE event is available.
+/// Text deltas become completion events with no stop reason, and
+/// `message_delta`'s stop reason is sent in a final empty completion.
+/// `ping` and `error` events pass through unchanged.
+#[derive(Default)]
+pub struct CompleteStreamConverter {
+    buffer: Vec<u8>,
+    id: String,
+    model: String,
+}
+
+impl CompleteStreamConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let event: Vec<u8> = self.buffer.drain(..pos + 2).collect();
+            let event = String::from_utf8_lossy(&event[..pos]);
+            let data = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect::<String>();
+            if let Ok(value) = serde_json::from_str::<Value>(&data) {
+                events.extend(self.convert_event(&value));
+            }
+        }
+        events
+    }
+
+    fn convert_event(&mut self, event: &Value) -> Option<String> {
+        match event["type"].as_str()? {
+            "message_start" => {
+                let message = &event["message"];
+                self.id = completion_id(message["id"].as_str().unwrap_or_default());
+                self.model = message["model"].as_str().unwrap_or_default().to_string();
+                None
+            }
+            "content_block_delta" if event["delta"]["type"] == "text_delta" => {
+                let text = event["delta"]["text"].as_str()?;
+                Some(self.completion(text, None, None))
+            }
+            "message_delta" => {
+                let delta = &event["delta"];
+                let stop_reason = delta["stop_reason"].as_str()?;
+                Some(self.completion(
+                    "",
+                    Some(legacy_stop_reason(stop_reason)),
+                    delta["stop_sequence"].as_str().map(str::to_string),
+                ))
+            }
+            event_type @ ("ping" | "error") => Some(encode(event_type, event)),
+            _ => None,
+        }
+    }
+
+    fn completion(&self, text: &str, stop_reason: Option<String>, stop: Option<String>) -> String {
+        let completion = CompleteResponse {
+            response_type: "completion".to_string(),
+            id: self.id.clone(),
+            completion: text.to_string(),
+            stop_reason,
+            stop,
+            model: self.model.clone(),
+        };
+        encode("completion", &json!(completion))
+    }
+}
+
+fn encode(event_type: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", event_type, data)
+}
+
+/// The legacy API only distinguishes running out of tokens from stopping.
+fn legacy_stop_reason(stop_reason: &str) -> String {
+    match stop_reason {
+        "max_tokens" => "max_tokens",
+        _ => "stop_sequence",
+    }
+    .to_string()
+}
+
+fn completion_id(message_id: &str) -> String {
+    match message_id.strip_prefix("msg_") {
+        Some(id) => format!("compl_{}", id),
+        None => message_id.to_string(),
+    }
+}
+
+/// The system prompt and the (role, text) turns of a legacy prompt.
+fn split_prompt(prompt: &str) -> Result<(String, Vec<(&'static str, String)>)> {
+    let Some(first) = prompt.find(HUMAN_PROMPT) else {
+        return Err(RelayError::InvalidRequest(
+            "prompt must contain a \"\\n\\nHuman:\" turn".to_string(),
+        ));
+    };
+    if !ends_with_ai_turn(prompt) {
+        return Err(RelayError::InvalidRequest(
+            "prompt must end with an \"\\n\\nAssistant:\" turn".to_string(),
+        ));
+    }
+
+    let system = prompt[..first].trim().to_string();
+    let mut turns = Vec::new();
+    let mut rest = &prompt[first..];
+    while !rest.is_empty() {
+        let (role, marker) = if rest.starts_with(HUMAN_PROMPT) {
+            ("user", HUMAN_PROMPT)
+        } else {
+            ("assistant", AI_PROMPT)
+        };
+        rest = &rest[marker.len()..];
+        let end = [rest.find(HUMAN_PROMPT), rest.find(AI_PROMPT)]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(rest.len());
+        turns.push((role, rest[..end].trim().to_string()));
+        rest = &rest[end..];
+    }
+    Ok((system, turns))
+}
+
+/// Whether the last turn is the model's, which may hold prefilled text.
+fn ends_with_ai_turn(prompt: &str) -> bool {
+    match (prompt.rfind(AI_PROMPT), prompt.rfind(HUMAN_PROMPT)) {
+        (Some(ai), Some(human)) => ai > human,
+        _ => false,
+    }
+}
@@ -1,9 +1,15 @@
 mod account;
+mod complete;
 mod oauth;
 mod relay;
 mod types;
+mod usage;
 
-pub use account::{ClaudeApiAccount, ClaudeOAuthAccount};
+pub use account::{
+    ClaudeApiAccount, ClaudeOAuthAccount, ClaudeSessionAccount, OpenRouterAccount,
+};
+pub use complete::{CompleteRequest, CompleteResponse, CompleteStreamConverter};
 pub use oauth::ClaudeOAuth;
-pub use relay::{extract_usage_from_chunk, ClaudeRelay};
+pub use relay::{extract_usage_from_chunk, parse_rate_limit_headers, ClaudeRelay, MessagesRelay};
 pub use types::*;
+pub use usage::UsageTracker;
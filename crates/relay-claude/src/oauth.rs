@@ -1,16 +1,37 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use relay_core::{sanitize_response_body, ProxyConfig, RelayError, Result, TokenInfo};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tracing::{debug, error, info};
+use uuid::Uuid;
 
-pub struct ClaudeOAuth;
+pub struct ClaudeOAuth {
+    token_url: String,
+    claude_ai_url: String,
+}
 
 impl ClaudeOAuth {
     const TOKEN_URL: &'static str = "https://console.anthropic.com/v1/oauth/token";
+    const CLAUDE_AI_URL: &'static str = "https://claude.ai";
     const CLIENT_ID: &'static str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+    const REDIRECT_URI: &'static str = "https://console.anthropic.com/oauth/code/callback";
+    const SESSION_SCOPE: &'static str = "user:profile user:inference";
 
     pub fn new() -> Self {
-        Self
+        Self {
+            token_url: Self::TOKEN_URL.to_string(),
+            claude_ai_url: Self::CLAUDE_AI_URL.to_string(),
+        }
+    }
+
+    /// Uses other endpoints than `console.anthropic.com` and `claude.ai`.
+    pub fn with_urls(token_url: String, claude_ai_url: String) -> Self {
+        Self {
+            token_url,
+            claude_ai_url,
+        }
     }
 
     fn build_client(proxy_config: Option<&ProxyConfig>) -> Result<Client> {
@@ -47,7 +68,7 @@ impl ClaudeOAuth {
         };
 
         let response = client
-            .post(Self::TOKEN_URL)
+            .post(&self.token_url)
             .header("Content-Type", "application/json")
             .header("Accept", "application/json")
             .json(&request)
@@ -78,6 +99,122 @@ impl ClaudeOAuth {
             token_response.expires_in,
         ))
     }
+
+    /// Exchanges a claude.ai `sessionKey` cookie for an access token by
+    /// authorizing the CLI client for the session's organization, the way
+    /// `claude setup-token` does in a browser.
+    pub async fn exchange_session_key(
+        &self,
+        session_key: &str,
+        proxy_config: Option<&ProxyConfig>,
+    ) -> Result<TokenInfo> {
+        let client = Self::build_client(proxy_config)?;
+        let cookie = format!("sessionKey={}", session_key);
+
+        debug!("Exchanging Claude session key for an OAuth token");
+
+        let response = client
+            .get(format!("{}/api/organizations", self.claude_ai_url))
+            .header("Cookie", &cookie)
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+        let organizations: Vec<Organization> = Self::parse(response, "organizations").await?;
+        let organization = organizations
+            .iter()
+            .find(|o| o.capabilities.iter().any(|c| c == "chat"))
+            .or(organizations.first())
+            .ok_or_else(|| {
+                RelayError::OAuth("Session key has no Claude organization".to_string())
+            })?;
+
+        // PKCE verifier from two v4 UUIDs, 244 random bits
+        let random = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+        let verifier = URL_SAFE_NO_PAD.encode(random);
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let state = Uuid::new_v4().simple().to_string();
+
+        let response = client
+            .post(format!(
+                "{}/v1/oauth/{}/authorize",
+                self.claude_ai_url, organization.uuid
+            ))
+            .header("Cookie", &cookie)
+            .header("Accept", "application/json")
+            .header("Origin", &self.claude_ai_url)
+            .json(&serde_json::json!({
+                "response_type": "code",
+                "client_id": Self::CLIENT_ID,
+                "organization_uuid": organization.uuid,
+                "redirect_uri": Self::REDIRECT_URI,
+                "scope": Self::SESSION_SCOPE,
+                "state": state,
+                "code_challenge": challenge,
+                "code_challenge_method": "S256",
+            }))
+            .send()
+            .await?;
+        let authorization: Authorization = Self::parse(response, "authorization").await?;
+        let code = query_param(&authorization.redirect_uri, "code")
+            .ok_or_else(|| RelayError::OAuth("Authorization response has no code".to_string()))?;
+
+        let response = client
+            .post(&self.token_url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .json(&serde_json::json!({
+                "grant_type": "authorization_code",
+                "client_id": Self::CLIENT_ID,
+                "code": code,
+                "redirect_uri": Self::REDIRECT_URI,
+                "code_verifier": verifier,
+                "state": state,
+            }))
+            .send()
+            .await?;
+        let token_response: TokenResponse = Self::parse(response, "token").await?;
+
+        info!(
+            organization_uuid = %organization.uuid,
+            expires_in = token_response.expires_in,
+            "Claude session key exchanged for an OAuth token"
+        );
+
+        Ok(TokenInfo::new(
+            token_response.access_token,
+            token_response.expires_in,
+        ))
+    }
+
+    async fn parse<T: serde::de::DeserializeOwned>(
+        response: reqwest::Response,
+        what: &str,
+    ) -> Result<T> {
+        let status = response.status();
+        if !status.is_success() {
+            let body = match response.text().await {
+                Ok(text) => sanitize_response_body(text),
+                Err(e) => format!("[Failed to read response body: {}]", e),
+            };
+            error!("Claude {} request failed: HTTP {} - {}", what, status, body);
+            return Err(RelayError::OAuth(format!("HTTP {}: {}", status, body)));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| RelayError::OAuth(format!("Failed to parse {} response: {}", what, e)))
+    }
+}
+
+/// Value of a query parameter of `url`, without any `#fragment`.
+fn query_param(url: &str, name: &str) -> Option<String> {
+    let query = url.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or(query);
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
 }
 
 impl Default for ClaudeOAuth {
@@ -102,3 +239,15 @@ struct TokenResponse {
     #[serde(default, rename = "scope")]
     _scope: Option<String>,
 }
+
+#[derive(Debug, Deserialize)]
+struct Organization {
+    uuid: String,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Authorization {
+    redirect_uri: String,
+}
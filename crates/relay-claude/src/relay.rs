@@ -3,16 +3,30 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use futures::StreamExt;
 use relay_core::{
-    read_error_response_body, AccountProvider, BoxStream, Credentials, ProxyConfig, Relay,
-    RelayError, Result,
+    read_error_response, redact_content, redacted_text, send_captured, AccountProvider, BoxStream,
+    Capture, ClientCache, Credentials, HttpClientOptions, RateLimitObserver, RateLimitSnapshot,
+    RateLimitWindow, Relay, RelayError, Result, UpstreamTimeouts,
 };
+use reqwest::header::HeaderMap;
 use reqwest::Client;
+use std::sync::Arc;
 use tracing::{debug, info, trace, warn};
 
-use crate::types::{ClientHeaders, MessagesRequest, MessagesResponse, StreamUsage};
+use crate::types::{ClientHeaders, ClientProfile, MessagesRequest, MessagesResponse, StreamUsage};
+use crate::usage::UsageTracker;
+
+/// Serves Messages requests for accounts the Anthropic API cannot.
+pub type MessagesRelay = dyn Relay<Request = MessagesRequest, Response = MessagesResponse>;
 
 pub struct ClaudeRelay {
-    default_client: Client,
+    clients: ClientCache,
+    rate_limits: Option<Arc<dyn RateLimitObserver>>,
+    sigv4: Option<Arc<MessagesRelay>>,
+    vertex: Option<Arc<MessagesRelay>>,
+    timeouts: Arc<UpstreamTimeouts>,
+    capture: Option<Arc<Capture>>,
+    /// Keeps message content out of debug and trace logs.
+    redact_logs: bool,
 }
 
 impl ClaudeRelay {
@@ -23,13 +37,93 @@ impl ClaudeRelay {
 
     pub fn new() -> Self {
         Self {
-            default_client: Client::builder()
-                .timeout(std::time::Duration::from_secs(600))
-                .build()
-                .expect("Failed to create HTTP client"),
+            clients: ClientCache::default(),
+            rate_limits: None,
+            sigv4: None,
+            vertex: None,
+            timeouts: Arc::default(),
+            capture: None,
+            redact_logs: false,
         }
     }
 
+    /// Bounds each upstream request, body included.
+    pub fn with_timeouts(mut self, timeouts: Arc<UpstreamTimeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Writes each upstream exchange to `capture`, for debugging.
+    pub fn with_capture(mut self, capture: Option<Arc<Capture>>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// HTTP version, pooling and keepalive of upstream connections.
+    pub fn with_client_options(mut self, options: HttpClientOptions) -> Self {
+        self.clients = ClientCache::new(options);
+        self
+    }
+
+    /// Replaces message content, system prompts and tool arguments in logs
+    /// with their lengths and hashes.
+    pub fn with_log_redaction(mut self, redact: bool) -> Self {
+        self.redact_logs = redact;
+        self
+    }
+
+    /// Reports the `anthropic-ratelimit-*` headers of every response.
+    pub fn with_rate_limit_observer(mut self, observer: Arc<dyn RateLimitObserver>) -> Self {
+        self.rate_limits = Some(observer);
+        self
+    }
+
+    /// Relays Messages requests of accounts with AWS SigV4 credentials, such
+    /// as Bedrock accounts, so they can share the Claude account pool.
+    pub fn with_sigv4_relay(mut self, relay: Arc<MessagesRelay>) -> Self {
+        self.sigv4 = Some(relay);
+        self
+    }
+
+    /// Relays Messages requests of Vertex AI accounts.
+    pub fn with_vertex_relay(mut self, relay: Arc<MessagesRelay>) -> Self {
+        self.vertex = Some(relay);
+        self
+    }
+
+    /// The relay serving accounts whose credentials the Anthropic API does
+    /// not accept, if the account has such credentials.
+    fn delegated_relay(&self, credentials: &Credentials) -> Result<Option<&MessagesRelay>> {
+        let (relay, kind) = match credentials {
+            Credentials::AwsSigV4(_) => (&self.sigv4, "AWS SigV4"),
+            Credentials::Vertex(_) => (&self.vertex, "Vertex AI"),
+            Credentials::Bearer(_) | Credentials::ApiKey(_) | Credentials::AzureOpenAI(_) => {
+                return Ok(None)
+            }
+        };
+        relay
+            .as_deref()
+            .map(Some)
+            .ok_or_else(|| RelayError::Config(format!("No relay for {} accounts", kind)))
+    }
+
+    fn observe_rate_limits(&self, account_id: &str, headers: &HeaderMap) {
+        let Some(observer) = &self.rate_limits else {
+            return;
+        };
+        let snapshot = parse_rate_limit_headers(headers);
+        if snapshot.is_empty() {
+            return;
+        }
+        trace!(
+            account_id = account_id,
+            requests_remaining = snapshot.requests.map(|w| w.remaining),
+            tokens_remaining = snapshot.tokens.map(|w| w.remaining),
+            "Observed upstream rate limits"
+        );
+        observer.observe_rate_limits(account_id, snapshot);
+    }
+
     pub fn default_api_url() -> &'static str {
         Self::DEFAULT_API_URL
     }
@@ -46,8 +140,31 @@ impl ClaudeRelay {
         }
     }
 
+    /// Beta flags for a request, taking the client profile into account.
+    pub fn beta_header_for_request(model: &str, client_headers: &ClientHeaders) -> String {
+        match client_headers.profile {
+            ClientProfile::ClaudeCode => Self::beta_header_for_model(model).to_string(),
+            ClientProfile::AgentSdk => {
+                let mut betas: Vec<&str> = Self::BETA_HEADER_FULL.split(',').collect();
+                let requested = client_headers.beta.as_deref().unwrap_or_default();
+                for beta in requested.split(',').map(str::trim) {
+                    if !beta.is_empty() && !betas.contains(&beta) {
+                        betas.push(beta);
+                    }
+                }
+                betas.join(",")
+            }
+        }
+    }
+
     /// Log detailed request information for debugging
-    fn log_request_details(request: &MessagesRequest, account_id: &str, api_url: &str, stream: bool) {
+    fn log_request_details(
+        &self,
+        request: &MessagesRequest,
+        account_id: &str,
+        api_url: &str,
+        stream: bool,
+    ) {
         let message_count = request.messages.len();
         let has_system = request.system.is_some();
         let has_tools = request.tools.as_ref().map(|t| t.len()).unwrap_or(0);
@@ -87,6 +204,8 @@ impl ClaudeRelay {
                 format!("array[{}]: {:?}", arr.len(), types)
             } else if let Some(s) = msg.content.as_str() {
                 format!("string(len={})", s.len())
+            } else if self.redact_logs {
+                redacted_text(&msg.content.to_string())
             } else {
                 format!("{:?}", msg.content)
             };
@@ -125,6 +244,7 @@ impl ClaudeRelay {
 
     /// Log error with full request details
     fn log_request_error(
+        &self,
         request: &MessagesRequest,
         error: &RelayError,
         account_id: &str,
@@ -140,7 +260,7 @@ impl ClaudeRelay {
         );
 
         // Debug level: dump full request body
-        if let Ok(request_json) = serde_json::to_string_pretty(&request) {
+        if let Some(request_json) = self.loggable_request(request) {
             debug!(
                 account_id = %account_id,
                 "Failed request body:\n{}", request_json
@@ -148,6 +268,17 @@ impl ClaudeRelay {
         }
     }
 
+    /// The request as dumped to debug logs.
+    fn loggable_request(&self, request: &MessagesRequest) -> Option<String> {
+        let body = serde_json::to_value(request).ok()?;
+        let body = if self.redact_logs {
+            redact_content(&body)
+        } else {
+            body
+        };
+        serde_json::to_string_pretty(&body).ok()
+    }
+
     fn get_api_url(account: &dyn AccountProvider) -> String {
         account
             .api_url()
@@ -164,35 +295,32 @@ impl ClaudeRelay {
             .unwrap_or_else(|| Self::DEFAULT_API_URL.to_string())
     }
 
-    fn build_client(&self, proxy_config: Option<&ProxyConfig>) -> Result<Client> {
-        if proxy_config.is_none() || proxy_config.map(|p| p.is_none()).unwrap_or(true) {
-            return Ok(self.default_client.clone());
-        }
-
-        let proxy = proxy_config.unwrap();
-        let mut builder = Client::builder().timeout(std::time::Duration::from_secs(600));
-
-        if let Some(proxy_url) = proxy.to_url() {
-            let proxy = reqwest::Proxy::all(&proxy_url)
-                .map_err(|e| RelayError::Config(format!("Invalid proxy URL: {}", e)))?;
-            builder = builder.proxy(proxy);
-        }
-
-        builder
-            .build()
-            .map_err(|e| RelayError::Config(format!("Failed to build HTTP client: {}", e)))
+    fn build_client(&self, account: &dyn AccountProvider) -> Result<Client> {
+        self.clients
+            .client_with_headers(account.proxy_config(), account.extra_headers())
     }
 
-    fn build_auth_header(credentials: &Credentials) -> (&'static str, String) {
+    fn build_auth_header(credentials: &Credentials) -> Result<(&'static str, String)> {
         match credentials {
-            Credentials::Bearer(token) => ("Authorization", format!("Bearer {}", token)),
-            Credentials::ApiKey(key) => ("x-api-key", key.clone()),
+            Credentials::Bearer(token) => Ok(("Authorization", format!("Bearer {}", token))),
+            Credentials::ApiKey(key) => Ok(("x-api-key", key.clone())),
+            // Only Messages requests are delegated to the SigV4 and Vertex relays
+            Credentials::AwsSigV4(_) => Err(RelayError::Upstream {
+                status: 501,
+                message: "Not supported by AWS SigV4 accounts".to_string(),
+            }),
+            Credentials::Vertex(_) => Err(RelayError::Upstream {
+                status: 501,
+                message: "Not supported by Vertex AI accounts".to_string(),
+            }),
+            Credentials::AzureOpenAI(_) => Err(RelayError::Config(
+                "Claude accounts cannot use Azure OpenAI credentials".to_string(),
+            )),
         }
     }
 
     async fn handle_error_response(&self, response: reqwest::Response) -> RelayError {
-        let (status, body) = read_error_response_body(response).await;
-        RelayError::from_response_body(status, &body)
+        read_error_response(response).await
     }
 
     fn apply_client_headers(
@@ -209,27 +337,38 @@ impl ClaudeRelay {
     pub async fn relay_with_headers(
         &self,
         account: &dyn AccountProvider,
-        request: MessagesRequest,
+        mut request: MessagesRequest,
         client_headers: &ClientHeaders,
     ) -> Result<MessagesResponse> {
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
-        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials);
+        if let Some(relay) = self.delegated_relay(&credentials)? {
+            return relay.relay(account, request).await;
+        }
+        if let Some(model) = account.upstream_model(&request.model) {
+            request.model = model;
+        }
+        let client = self.build_client(account)?;
+        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials)?;
         let api_url = Self::get_api_url(account);
         let auth_type = match &credentials {
             Credentials::Bearer(_) => "Bearer",
             Credentials::ApiKey(_) => "ApiKey",
+            Credentials::AwsSigV4(_) => "AwsSigV4",
+            Credentials::Vertex(_) => "Vertex",
+            Credentials::AzureOpenAI(_) => "AzureOpenAI",
         };
 
         // Log detailed request information
-        Self::log_request_details(&request, account.id(), &api_url, false);
+        self.log_request_details(&request, account.id(), &api_url, false);
         Self::log_client_headers(client_headers, account.id());
 
+        let beta = Self::beta_header_for_request(&request.model, client_headers);
         debug!(
             account_id = %account.id(),
             auth_type = auth_type,
+            profile = ?client_headers.profile,
             anthropic_version = Self::API_VERSION,
-            anthropic_beta = Self::beta_header_for_model(&request.model),
+            anthropic_beta = %beta,
             "Sending non-streaming request"
         );
 
@@ -237,11 +376,12 @@ impl ClaudeRelay {
             .post(&api_url)
             .header(auth_header_name, auth_header_value)
             .header("anthropic-version", Self::API_VERSION)
-            .header("anthropic-beta", Self::beta_header_for_model(&request.model))
-            .header("Content-Type", "application/json");
+            .header("anthropic-beta", beta)
+            .header("Content-Type", "application/json")
+            .timeout(self.timeouts.for_request(account.id(), &request.model));
 
         builder = Self::apply_client_headers(builder, client_headers);
-        let response = builder.json(&request).send().await?;
+        let response = send_captured(builder.json(&request), self.capture.as_deref()).await?;
 
         let status = response.status();
         debug!(
@@ -249,10 +389,11 @@ impl ClaudeRelay {
             status = %status,
             "Received response"
         );
+        self.observe_rate_limits(account.id(), response.headers());
 
         if !status.is_success() {
             let error = self.handle_error_response(response).await;
-            Self::log_request_error(&request, &error, account.id(), &api_url);
+            self.log_request_error(&request, &error, account.id(), &api_url);
             return Err(error);
         }
 
@@ -271,6 +412,194 @@ impl ClaudeRelay {
         Ok(resp)
     }
 
+    /// Forwards a `/v1/messages/count_tokens` request body unchanged and
+    /// returns the upstream's `{"input_tokens": N}` answer.
+    pub async fn count_tokens(
+        &self,
+        account: &dyn AccountProvider,
+        request: &serde_json::Value,
+        client_headers: &ClientHeaders,
+    ) -> Result<serde_json::Value> {
+        let credentials = account.get_credentials().await?;
+        let client = self.build_client(account)?;
+        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials)?;
+        let api_url = format!("{}/count_tokens", Self::get_api_url(account));
+        let model = request["model"].as_str().unwrap_or_default();
+        let beta = Self::beta_header_for_request(model, client_headers);
+        debug!(
+            account_id = %account.id(),
+            model = model,
+            anthropic_beta = %beta,
+            "Sending count_tokens request"
+        );
+
+        let builder = client
+            .post(&api_url)
+            .header(auth_header_name, auth_header_value)
+            .header("anthropic-version", Self::API_VERSION)
+            .header("anthropic-beta", beta)
+            .header("Content-Type", "application/json")
+            .timeout(self.timeouts.for_request(account.id(), model));
+        let builder = Self::apply_client_headers(builder, client_headers).json(request);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
+
+        let status = response.status();
+        debug!(
+            account_id = %account.id(),
+            status = %status,
+            "Received count_tokens response"
+        );
+        self.observe_rate_limits(account.id(), response.headers());
+
+        if !status.is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Ids of the models the account can use, from `GET /v1/models`.
+    pub async fn list_models(&self, account: &dyn AccountProvider) -> Result<Vec<String>> {
+        let credentials = account.get_credentials().await?;
+        let client = self.build_client(account)?;
+        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials)?;
+        let messages_url = Self::get_api_url(account);
+        let api_url = format!(
+            "{}/models?limit=1000",
+            messages_url.trim_end_matches("/messages")
+        );
+        debug!(account_id = %account.id(), url = %api_url, "Listing upstream models");
+
+        let builder = client
+            .get(&api_url)
+            .header(auth_header_name, auth_header_value)
+            .header("anthropic-version", Self::API_VERSION)
+            .timeout(self.timeouts.for_request(account.id(), ""));
+        let response = send_captured(builder, self.capture.as_deref()).await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(body["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|model| model["id"].as_str().map(|id| id.to_string()))
+            .collect())
+    }
+
+    /// Sends a Message Batches API request; `path` is relative to
+    /// `/v1/messages/batches`.
+    async fn send_batch_request(
+        &self,
+        account: &dyn AccountProvider,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&serde_json::Value>,
+        client_headers: &ClientHeaders,
+    ) -> Result<reqwest::Response> {
+        let credentials = account.get_credentials().await?;
+        let client = self.build_client(account)?;
+        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials)?;
+        let api_url = format!("{}/batches{}", Self::get_api_url(account), path);
+        debug!(
+            account_id = %account.id(),
+            method = %method,
+            url = %api_url,
+            "Sending message batches request"
+        );
+
+        let mut builder = client
+            .request(method, &api_url)
+            .header(auth_header_name, auth_header_value)
+            .header("anthropic-version", Self::API_VERSION)
+            .header("anthropic-beta", Self::beta_header_for_request("", client_headers))
+            .timeout(self.timeouts.for_request(account.id(), ""));
+        builder = Self::apply_client_headers(builder, client_headers);
+        if let Some(body) = body {
+            builder = builder.json(body);
+        }
+        let response = send_captured(builder, self.capture.as_deref()).await?;
+
+        debug!(
+            account_id = %account.id(),
+            status = %response.status(),
+            "Received message batches response"
+        );
+        self.observe_rate_limits(account.id(), response.headers());
+
+        if !response.status().is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+        Ok(response)
+    }
+
+    /// `POST /v1/messages/batches`
+    pub async fn create_batch(
+        &self,
+        account: &dyn AccountProvider,
+        request: &serde_json::Value,
+        client_headers: &ClientHeaders,
+    ) -> Result<serde_json::Value> {
+        let response = self
+            .send_batch_request(
+                account,
+                reqwest::Method::POST,
+                "",
+                Some(request),
+                client_headers,
+            )
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// `GET /v1/messages/batches/{batch_id}`
+    pub async fn get_batch(
+        &self,
+        account: &dyn AccountProvider,
+        batch_id: &str,
+        client_headers: &ClientHeaders,
+    ) -> Result<serde_json::Value> {
+        let path = format!("/{}", batch_id);
+        let response = self
+            .send_batch_request(account, reqwest::Method::GET, &path, None, client_headers)
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// `POST /v1/messages/batches/{batch_id}/cancel`
+    pub async fn cancel_batch(
+        &self,
+        account: &dyn AccountProvider,
+        batch_id: &str,
+        client_headers: &ClientHeaders,
+    ) -> Result<serde_json::Value> {
+        let path = format!("/{}/cancel", batch_id);
+        let response = self
+            .send_batch_request(account, reqwest::Method::POST, &path, None, client_headers)
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// `GET /v1/messages/batches/{batch_id}/results`: the JSONL results,
+    /// streamed as received.
+    pub async fn batch_results(
+        &self,
+        account: &dyn AccountProvider,
+        batch_id: &str,
+        client_headers: &ClientHeaders,
+    ) -> Result<BoxStream<Result<Bytes>>> {
+        let path = format!("/{}/results", batch_id);
+        let response = self
+            .send_batch_request(account, reqwest::Method::GET, &path, None, client_headers)
+            .await?;
+        Ok(Box::pin(
+            response
+                .bytes_stream()
+                .map(|chunk| chunk.map_err(RelayError::from)),
+        ))
+    }
+
     pub async fn relay_stream_with_headers(
         &self,
         account: &dyn AccountProvider,
@@ -280,23 +609,34 @@ impl ClaudeRelay {
         request.stream = true;
 
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
-        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials);
+        if let Some(relay) = self.delegated_relay(&credentials)? {
+            return relay.relay_stream(account, request).await;
+        }
+        if let Some(model) = account.upstream_model(&request.model) {
+            request.model = model;
+        }
+        let client = self.build_client(account)?;
+        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials)?;
         let api_url = Self::get_api_url(account);
         let auth_type = match &credentials {
             Credentials::Bearer(_) => "Bearer",
             Credentials::ApiKey(_) => "ApiKey",
+            Credentials::AwsSigV4(_) => "AwsSigV4",
+            Credentials::Vertex(_) => "Vertex",
+            Credentials::AzureOpenAI(_) => "AzureOpenAI",
         };
 
         // Log detailed request information
-        Self::log_request_details(&request, account.id(), &api_url, true);
+        self.log_request_details(&request, account.id(), &api_url, true);
         Self::log_client_headers(client_headers, account.id());
 
+        let beta = Self::beta_header_for_request(&request.model, client_headers);
         debug!(
             account_id = %account.id(),
             auth_type = auth_type,
+            profile = ?client_headers.profile,
             anthropic_version = Self::API_VERSION,
-            anthropic_beta = Self::beta_header_for_model(&request.model),
+            anthropic_beta = %beta,
             "Sending streaming request"
         );
 
@@ -304,11 +644,12 @@ impl ClaudeRelay {
             .post(&api_url)
             .header(auth_header_name, auth_header_value)
             .header("anthropic-version", Self::API_VERSION)
-            .header("anthropic-beta", Self::beta_header_for_model(&request.model))
-            .header("Content-Type", "application/json");
+            .header("anthropic-beta", beta)
+            .header("Content-Type", "application/json")
+            .timeout(self.timeouts.for_request(account.id(), &request.model));
 
         builder = Self::apply_client_headers(builder, client_headers);
-        let response = builder.json(&request).send().await?;
+        let response = send_captured(builder.json(&request), self.capture.as_deref()).await?;
 
         let status = response.status();
         debug!(
@@ -316,10 +657,11 @@ impl ClaudeRelay {
             status = %status,
             "Received streaming response"
         );
+        self.observe_rate_limits(account.id(), response.headers());
 
         if !status.is_success() {
             let error = self.handle_error_response(response).await;
-            Self::log_request_error(&request, &error, account.id(), &api_url);
+            self.log_request_error(&request, &error, account.id(), &api_url);
             return Err(error);
         }
 
@@ -327,25 +669,16 @@ impl ClaudeRelay {
 
         let stream = try_stream! {
             let mut byte_stream = response.bytes_stream();
-            let mut total_usage = StreamUsage::default();
+            let mut tracker = UsageTracker::new();
 
             while let Some(chunk_result) = byte_stream.next().await {
                 let chunk = chunk_result?;
-
-                if let Some(usage) = extract_usage_from_chunk(&chunk) {
-                    total_usage.input_tokens = total_usage.input_tokens.max(usage.input_tokens);
-                    total_usage.output_tokens = total_usage.output_tokens.max(usage.output_tokens);
-                    if usage.cache_creation_input_tokens.is_some() {
-                        total_usage.cache_creation_input_tokens = usage.cache_creation_input_tokens;
-                    }
-                    if usage.cache_read_input_tokens.is_some() {
-                        total_usage.cache_read_input_tokens = usage.cache_read_input_tokens;
-                    }
-                }
-
+                tracker.push(&chunk);
                 yield chunk;
             }
 
+            let total_usage = tracker.usage();
+
             if total_usage.input_tokens > 0 || total_usage.output_tokens > 0 {
                 info!(
                     account_id = account_id,
@@ -376,19 +709,28 @@ impl Relay for ClaudeRelay {
     async fn relay(
         &self,
         account: &dyn AccountProvider,
-        request: Self::Request,
+        mut request: Self::Request,
     ) -> Result<Self::Response> {
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
-        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials);
+        if let Some(relay) = self.delegated_relay(&credentials)? {
+            return relay.relay(account, request).await;
+        }
+        if let Some(model) = account.upstream_model(&request.model) {
+            request.model = model;
+        }
+        let client = self.build_client(account)?;
+        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials)?;
         let api_url = Self::get_api_url(account);
         let auth_type = match &credentials {
             Credentials::Bearer(_) => "Bearer",
             Credentials::ApiKey(_) => "ApiKey",
+            Credentials::AwsSigV4(_) => "AwsSigV4",
+            Credentials::Vertex(_) => "Vertex",
+            Credentials::AzureOpenAI(_) => "AzureOpenAI",
         };
 
         // Log detailed request information
-        Self::log_request_details(&request, account.id(), &api_url, false);
+        self.log_request_details(&request, account.id(), &api_url, false);
 
         debug!(
             account_id = %account.id(),
@@ -398,15 +740,15 @@ impl Relay for ClaudeRelay {
             "Sending non-streaming request (no client headers)"
         );
 
-        let response = client
+        let builder = client
             .post(&api_url)
             .header(auth_header_name, auth_header_value)
             .header("anthropic-version", Self::API_VERSION)
             .header("anthropic-beta", Self::beta_header_for_model(&request.model))
             .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+            .timeout(self.timeouts.for_request(account.id(), &request.model))
+            .json(&request);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
 
         let status = response.status();
         debug!(
@@ -414,10 +756,11 @@ impl Relay for ClaudeRelay {
             status = %status,
             "Received response"
         );
+        self.observe_rate_limits(account.id(), response.headers());
 
         if !status.is_success() {
             let error = self.handle_error_response(response).await;
-            Self::log_request_error(&request, &error, account.id(), &api_url);
+            self.log_request_error(&request, &error, account.id(), &api_url);
             return Err(error);
         }
 
@@ -444,16 +787,25 @@ impl Relay for ClaudeRelay {
         request.stream = true;
 
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
-        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials);
+        if let Some(relay) = self.delegated_relay(&credentials)? {
+            return relay.relay_stream(account, request).await;
+        }
+        if let Some(model) = account.upstream_model(&request.model) {
+            request.model = model;
+        }
+        let client = self.build_client(account)?;
+        let (auth_header_name, auth_header_value) = Self::build_auth_header(&credentials)?;
         let api_url = Self::get_api_url(account);
         let auth_type = match &credentials {
             Credentials::Bearer(_) => "Bearer",
             Credentials::ApiKey(_) => "ApiKey",
+            Credentials::AwsSigV4(_) => "AwsSigV4",
+            Credentials::Vertex(_) => "Vertex",
+            Credentials::AzureOpenAI(_) => "AzureOpenAI",
         };
 
         // Log detailed request information
-        Self::log_request_details(&request, account.id(), &api_url, true);
+        self.log_request_details(&request, account.id(), &api_url, true);
 
         debug!(
             account_id = %account.id(),
@@ -463,15 +815,15 @@ impl Relay for ClaudeRelay {
             "Sending streaming request (no client headers)"
         );
 
-        let response = client
+        let builder = client
             .post(&api_url)
             .header(auth_header_name, auth_header_value)
             .header("anthropic-version", Self::API_VERSION)
             .header("anthropic-beta", Self::beta_header_for_model(&request.model))
             .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+            .timeout(self.timeouts.for_request(account.id(), &request.model))
+            .json(&request);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
 
         let status = response.status();
         debug!(
@@ -479,10 +831,11 @@ impl Relay for ClaudeRelay {
             status = %status,
             "Received streaming response"
         );
+        self.observe_rate_limits(account.id(), response.headers());
 
         if !status.is_success() {
             let error = self.handle_error_response(response).await;
-            Self::log_request_error(&request, &error, account.id(), &api_url);
+            self.log_request_error(&request, &error, account.id(), &api_url);
             return Err(error);
         }
 
@@ -490,25 +843,16 @@ impl Relay for ClaudeRelay {
 
         let stream = try_stream! {
             let mut byte_stream = response.bytes_stream();
-            let mut total_usage = StreamUsage::default();
+            let mut tracker = UsageTracker::new();
 
             while let Some(chunk_result) = byte_stream.next().await {
                 let chunk = chunk_result?;
-
-                if let Some(usage) = extract_usage_from_chunk(&chunk) {
-                    total_usage.input_tokens = total_usage.input_tokens.max(usage.input_tokens);
-                    total_usage.output_tokens = total_usage.output_tokens.max(usage.output_tokens);
-                    if usage.cache_creation_input_tokens.is_some() {
-                        total_usage.cache_creation_input_tokens = usage.cache_creation_input_tokens;
-                    }
-                    if usage.cache_read_input_tokens.is_some() {
-                        total_usage.cache_read_input_tokens = usage.cache_read_input_tokens;
-                    }
-                }
-
+                tracker.push(&chunk);
                 yield chunk;
             }
 
+            let total_usage = tracker.usage();
+
             if total_usage.input_tokens > 0 || total_usage.output_tokens > 0 {
                 info!(
                     account_id = account_id,
@@ -525,79 +869,67 @@ impl Relay for ClaudeRelay {
     }
 }
 
+/// Reads the `anthropic-ratelimit-{requests,tokens,input-tokens,output-tokens}-*`
+/// headers. Windows missing their limit or remaining count are skipped.
+pub fn parse_rate_limit_headers(headers: &HeaderMap) -> RateLimitSnapshot {
+    let window = |name: &str| {
+        let header = |suffix: &str| {
+            headers
+                .get(format!("anthropic-ratelimit-{}-{}", name, suffix))
+                .and_then(|v| v.to_str().ok())
+        };
+        let limit = header("limit")?.parse().ok()?;
+        let remaining = header("remaining")?.parse().ok()?;
+        let reset_in = header("reset")
+            .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+            .map(|reset| {
+                (reset.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                    .to_std()
+                    .unwrap_or_default()
+            });
+        Some(RateLimitWindow {
+            limit,
+            remaining,
+            reset_in,
+        })
+    };
+
+    RateLimitSnapshot {
+        requests: window("requests"),
+        tokens: window("tokens"),
+        input_tokens: window("input-tokens"),
+        output_tokens: window("output-tokens"),
+    }
+}
+
 pub fn extract_usage_from_chunk(chunk: &Bytes) -> Option<StreamUsage> {
     let text = std::str::from_utf8(chunk).ok()?;
+    text.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .find_map(extract_usage_from_data)
+}
 
-    for line in text.lines() {
-        if !line.starts_with("data: ") {
-            continue;
-        }
-
-        let json_str = line.strip_prefix("data: ")?;
-        if json_str == "[DONE]" {
-            continue;
-        }
-
-        let value: serde_json::Value = serde_json::from_str(json_str).ok()?;
-
-        if let Some(usage) = value.get("usage") {
-            let input = usage
-                .get("input_tokens")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0) as u32;
-            let output = usage
-                .get("output_tokens")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0) as u32;
-            let cache_creation = usage
-                .get("cache_creation_input_tokens")
-                .and_then(|v| v.as_u64())
-                .map(|v| v as u32);
-            let cache_read = usage
-                .get("cache_read_input_tokens")
-                .and_then(|v| v.as_u64())
-                .map(|v| v as u32);
-
-            if input > 0 || output > 0 {
-                return Some(StreamUsage {
-                    input_tokens: input,
-                    output_tokens: output,
-                    cache_creation_input_tokens: cache_creation,
-                    cache_read_input_tokens: cache_read,
-                });
-            }
-        }
+/// Usage reported by the data of one stream event: `message_start` nests
+/// it in the message, `message_delta` has it at the top level.
+pub(crate) fn extract_usage_from_data(data: &str) -> Option<StreamUsage> {
+    let value: serde_json::Value = serde_json::from_str(data).ok()?;
+    value
+        .get("usage")
+        .and_then(stream_usage)
+        .or_else(|| value.get("message")?.get("usage").and_then(stream_usage))
+}
 
-        if let Some(message) = value.get("message") {
-            if let Some(usage) = message.get("usage") {
-                let input = usage
-                    .get("input_tokens")
-                    .and_then(|v| v.as_u64())
-                    .unwrap_or(0) as u32;
-                let output = usage
-                    .get("output_tokens")
-                    .and_then(|v| v.as_u64())
-                    .unwrap_or(0) as u32;
-                let cache_creation = usage
-                    .get("cache_creation_input_tokens")
-                    .and_then(|v| v.as_u64())
-                    .map(|v| v as u32);
-                let cache_read = usage
-                    .get("cache_read_input_tokens")
-                    .and_then(|v| v.as_u64())
-                    .map(|v| v as u32);
-
-                if input > 0 || output > 0 {
-                    return Some(StreamUsage {
-                        input_tokens: input,
-                        output_tokens: output,
-                        cache_creation_input_tokens: cache_creation,
-                        cache_read_input_tokens: cache_read,
-                    });
-                }
-            }
-        }
+fn stream_usage(usage: &serde_json::Value) -> Option<StreamUsage> {
+    let tokens = |field: &str| usage.get(field).and_then(|v| v.as_u64()).map(|v| v as u32);
+    let input = tokens("input_tokens").unwrap_or(0);
+    let output = tokens("output_tokens").unwrap_or(0);
+    if input == 0 && output == 0 {
+        return None;
     }
-
-    None
+    Some(StreamUsage {
+        input_tokens: input,
+        output_tokens: output,
+        cache_creation_input_tokens: tokens("cache_creation_input_tokens"),
+        cache_read_input_tokens: tokens("cache_read_input_tokens"),
+    })
 }
@@ -1,3 +1,4 @@
+use relay_core::RelayError;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +46,40 @@ impl Default for MessagesRequest {
     }
 }
 
+impl MessagesRequest {
+    /// Rejects what the Messages API would, before an account is chosen:
+    /// a missing model or `max_tokens`, no messages, or messages with an
+    /// unknown role or content that is neither text nor blocks. Messages
+    /// name the offending field as the API does, e.g. `messages.0.role`.
+    pub fn validate(&self) -> Result<(), RelayError> {
+        let invalid = |message: String| Err(RelayError::InvalidRequest(message));
+        if self.model.trim().is_empty() {
+            return invalid("model: Field required".to_string());
+        }
+        if self.max_tokens == 0 {
+            return invalid("max_tokens: Must be set to at least 1".to_string());
+        }
+        if self.messages.is_empty() {
+            return invalid("messages: At least one message is required".to_string());
+        }
+        for (i, message) in self.messages.iter().enumerate() {
+            if message.role != "user" && message.role != "assistant" {
+                return invalid(format!(
+                    "messages.{}.role: Input should be 'user' or 'assistant'",
+                    i
+                ));
+            }
+            if !message.content.is_string() && !message.content.is_array() {
+                return invalid(format!(
+                    "messages.{}.content: Input should be a valid string or list of content blocks",
+                    i
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
@@ -107,19 +142,46 @@ pub struct StreamUsage {
     pub cache_read_input_tokens: Option<u32>,
 }
 
+/// Which client a caller is expected to look like upstream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClientProfile {
+    /// Claude Code CLI: fixed beta set, minimal betas for Haiku.
+    #[default]
+    ClaudeCode,
+    /// Claude Agent SDK: fine-grained tool streaming on every model, client
+    /// betas merged in, and all `x-stainless-*` headers passed through.
+    AgentSdk,
+}
+
+impl ClientProfile {
+    fn user_agent(&self) -> &'static str {
+        match self {
+            ClientProfile::ClaudeCode => "claude-cli/1.0.57 (external, cli)",
+            ClientProfile::AgentSdk => "claude-cli/1.0.57 (external, sdk-ts)",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ClientHeaders {
     pub headers: std::collections::HashMap<String, String>,
+    pub profile: ClientProfile,
+    /// `anthropic-beta` values requested by the client, honored by profiles
+    /// that merge client betas.
+    pub beta: Option<String>,
 }
 
 impl ClientHeaders {
     pub fn new() -> Self {
-        Self {
-            headers: std::collections::HashMap::new(),
-        }
+        Self::default()
     }
 
     pub fn with_defaults() -> Self {
+        Self::with_defaults_for(ClientProfile::ClaudeCode)
+    }
+
+    pub fn with_defaults_for(profile: ClientProfile) -> Self {
         let mut headers = std::collections::HashMap::new();
         headers.insert("x-stainless-retry-count".to_string(), "0".to_string());
         headers.insert("x-stainless-timeout".to_string(), "60".to_string());
@@ -131,10 +193,14 @@ impl ClientHeaders {
         headers.insert("x-stainless-runtime-version".to_string(), "v20.19.2".to_string());
         headers.insert("anthropic-dangerous-direct-browser-access".to_string(), "true".to_string());
         headers.insert("x-app".to_string(), "cli".to_string());
-        headers.insert("user-agent".to_string(), "claude-cli/1.0.57 (external, cli)".to_string());
+        headers.insert("user-agent".to_string(), profile.user_agent().to_string());
         headers.insert("accept-language".to_string(), "*".to_string());
         headers.insert("sec-fetch-mode".to_string(), "cors".to_string());
-        Self { headers }
+        Self {
+            headers,
+            profile,
+            beta: None,
+        }
     }
 
     pub fn insert(&mut self, key: String, value: String) {
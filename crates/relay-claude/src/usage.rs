@@ -0,0 +1,41 @@
+use relay_core::SseLines;
+
+use crate::relay::extract_usage_from_data;
+use crate::types::StreamUsage;
+
+/// Adds up the usage reported by a Messages SSE stream: input and cache
+/// tokens in `message_start`, output tokens so far in each
+/// `message_delta`. Events are reassembled across network reads, so usage
+/// split between chunks is still counted.
+#[derive(Default)]
+pub struct UsageTracker {
+    lines: SseLines,
+    usage: StreamUsage,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        for data in self.lines.push(chunk) {
+            let Some(usage) = extract_usage_from_data(&data) else {
+                continue;
+            };
+            let total = &mut self.usage;
+            total.input_tokens = total.input_tokens.max(usage.input_tokens);
+            total.output_tokens = total.output_tokens.max(usage.output_tokens);
+            total.cache_creation_input_tokens = total
+                .cache_creation_input_tokens
+                .max(usage.cache_creation_input_tokens);
+            total.cache_read_input_tokens = total
+                .cache_read_input_tokens
+                .max(usage.cache_read_input_tokens);
+        }
+    }
+
+    pub fn usage(&self) -> StreamUsage {
+        self.usage.clone()
+    }
+}
@@ -0,0 +1,129 @@
+use relay_claude::{CompleteRequest, CompleteResponse, CompleteStreamConverter, MessagesResponse};
+use serde_json::{json, Value};
+
+fn request(prompt: &str) -> CompleteRequest {
+    serde_json::from_value(json!({
+        "model": "claude-2.1",
+        "prompt": prompt,
+        "max_tokens_to_sample": 256,
+        "stop_sequences": ["\n\nHuman:"]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_prompt_becomes_messages() {
+    let request = request(
+        "You are terse.\n\nHuman: Hi\n\nAssistant: Hello!\n\nHuman: Name a color\n\nAssistant:",
+    )
+    .into_messages_request()
+    .unwrap();
+
+    assert_eq!(request.system, Some(json!("You are terse.")));
+    assert_eq!(request.max_tokens, 256);
+    assert_eq!(request.extra["stop_sequences"], json!(["\n\nHuman:"]));
+    let turns: Vec<(&str, &Value)> = request
+        .messages
+        .iter()
+        .map(|m| (m.role.as_str(), &m.content))
+        .collect();
+    assert_eq!(
+        turns,
+        [
+            ("user", &json!("Hi")),
+            ("assistant", &json!("Hello!")),
+            ("user", &json!("Name a color")),
+        ]
+    );
+}
+
+#[test]
+fn test_final_assistant_text_is_a_prefill() {
+    let request = request("\n\nHuman: Count to three\n\nAssistant: One,")
+        .into_messages_request()
+        .unwrap();
+
+    assert!(request.system.is_none());
+    assert_eq!(request.messages.len(), 2);
+    assert_eq!(request.messages[1].role, "assistant");
+    assert_eq!(request.messages[1].content, json!("One,"));
+}
+
+#[test]
+fn test_prompt_without_turns_is_rejected() {
+    assert!(request("Hello there").into_messages_request().is_err());
+    assert!(request("\n\nHuman: Hi").into_messages_request().is_err());
+}
+
+#[test]
+fn test_response_becomes_completion() {
+    let response: MessagesResponse = serde_json::from_value(json!({
+        "id": "msg_01abc",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            {"type": "thinking", "thinking": "hmm", "signature": "sig"},
+            {"type": "text", "text": "Blue"}
+        ],
+        "model": "claude-2.1",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 10, "output_tokens": 1}
+    }))
+    .unwrap();
+
+    let completion = serde_json::to_value(CompleteResponse::from_messages(response)).unwrap();
+
+    assert_eq!(
+        completion,
+        json!({
+            "type": "completion",
+            "id": "compl_01abc",
+            "completion": "Blue",
+            "stop_reason": "stop_sequence",
+            "stop": null,
+            "model": "claude-2.1"
+        })
+    );
+}
+
+#[test]
+fn test_stream_becomes_completion_events() {
+    let stream = concat!(
+        "event: message_start\n",
+        "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_01abc\",\"model\":\"claude-2.1\",\"usage\":{\"input_tokens\":10}}}\n\n",
+        "event: ping\n",
+        "data: {\"type\":\"ping\"}\n\n",
+        "event: content_block_start\n",
+        "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Blue\"}}\n\n",
+        "event: content_block_stop\n",
+        "data: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+        "event: message_delta\n",
+        "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\",\"stop_sequence\":null},\"usage\":{\"output_tokens\":1}}\n\n",
+        "event: message_stop\n",
+        "data: {\"type\":\"message_stop\"}\n\n",
+    );
+
+    // Split mid-event to check buffering
+    let (first, second) = stream.split_at(150);
+    let mut converter = CompleteStreamConverter::new();
+    let mut events = converter.push(first.as_bytes());
+    events.extend(converter.push(second.as_bytes()));
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0], "event: ping\ndata: {\"type\":\"ping\"}\n\n");
+
+    let data = |event: &str| -> Value {
+        let line = event.lines().nth(1).unwrap();
+        serde_json::from_str(line.strip_prefix("data: ").unwrap()).unwrap()
+    };
+    assert!(events[1].starts_with("event: completion\n"));
+    assert_eq!(data(&events[1])["completion"], "Blue");
+    assert_eq!(data(&events[1])["id"], "compl_01abc");
+    assert_eq!(data(&events[1])["stop_reason"], Value::Null);
+    assert_eq!(data(&events[2])["completion"], "");
+    assert_eq!(data(&events[2])["stop_reason"], "max_tokens");
+    assert_eq!(data(&events[2])["model"], "claude-2.1");
+}
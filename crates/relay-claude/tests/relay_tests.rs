@@ -1,5 +1,12 @@
 use bytes::Bytes;
-use relay_claude::{extract_usage_from_chunk, ClaudeRelay};
+use relay_claude::{
+    extract_usage_from_chunk, parse_rate_limit_headers, ClaudeApiAccount, ClaudeOAuth, ClaudeRelay,
+    ClaudeSessionAccount, ClientHeaders, ClientProfile, Message, MessagesRequest,
+    OpenRouterAccount, UsageTracker,
+};
+use relay_core::{AccountProvider, Credentials, Relay};
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::time::Duration;
 
 #[test]
 fn test_beta_header_contains_all_features() {
@@ -49,6 +56,46 @@ fn test_non_haiku_uses_full_beta() {
     assert!(beta.contains("fine-grained-tool-streaming-2025-05-14"));
 }
 
+#[test]
+fn test_claude_code_profile_ignores_client_betas() {
+    let mut headers = ClientHeaders::with_defaults();
+    headers.beta = Some("context-1m-2025-08-07".to_string());
+
+    let beta = ClaudeRelay::beta_header_for_request("claude-3-5-haiku-20241022", &headers);
+    assert_eq!(
+        beta,
+        ClaudeRelay::beta_header_for_model("claude-3-5-haiku-20241022")
+    );
+}
+
+#[test]
+fn test_agent_sdk_profile_merges_client_betas() {
+    let mut headers = ClientHeaders::with_defaults_for(ClientProfile::AgentSdk);
+    headers.beta =
+        Some("fine-grained-tool-streaming-2025-05-14, context-1m-2025-08-07".to_string());
+
+    let beta = ClaudeRelay::beta_header_for_request("claude-3-5-haiku-20241022", &headers);
+    let flags: Vec<&str> = beta.split(',').collect();
+
+    assert!(flags.contains(&"claude-code-20250219"));
+    assert!(flags.contains(&"context-1m-2025-08-07"));
+    assert_eq!(
+        flags
+            .iter()
+            .filter(|f| **f == "fine-grained-tool-streaming-2025-05-14")
+            .count(),
+        1,
+        "Duplicate betas should be merged"
+    );
+}
+
+#[test]
+fn test_agent_sdk_default_user_agent() {
+    let headers = ClientHeaders::with_defaults_for(ClientProfile::AgentSdk);
+    assert!(headers.get("user-agent").unwrap().contains("sdk-ts"));
+    assert_eq!(headers.profile, ClientProfile::AgentSdk);
+}
+
 #[test]
 fn test_extract_usage_with_cache_tokens() {
     let chunk = Bytes::from(
@@ -65,6 +112,29 @@ fn test_extract_usage_with_cache_tokens() {
     assert_eq!(usage.cache_read_input_tokens, Some(30));
 }
 
+#[test]
+fn test_usage_tracker_handles_split_events() {
+    let stream = concat!(
+        "event: message_start\n",
+        r#"data: {"type":"message_start","message":{"usage":{"input_tokens":100,"output_tokens":1,"cache_read_input_tokens":30}}}"#,
+        "\n\nevent: content_block_delta\n",
+        r#"data: {"type":"content_block_delta","delta":{"type":"text_delta","text":"Hi"}}"#,
+        "\n\nevent: message_delta\n",
+        r#"data: {"type":"message_delta","usage":{"output_tokens":50}}"#,
+        "\n\n",
+    );
+    let mut tracker = UsageTracker::new();
+    for piece in stream.as_bytes().chunks(16) {
+        tracker.push(piece);
+    }
+
+    let usage = tracker.usage();
+    assert_eq!(usage.input_tokens, 100);
+    assert_eq!(usage.output_tokens, 50);
+    assert_eq!(usage.cache_read_input_tokens, Some(30));
+    assert_eq!(usage.cache_creation_input_tokens, None);
+}
+
 #[test]
 fn test_extract_usage_without_cache_tokens() {
     let chunk = Bytes::from(
@@ -80,3 +150,382 @@ fn test_extract_usage_without_cache_tokens() {
     assert_eq!(usage.cache_creation_input_tokens, None);
     assert_eq!(usage.cache_read_input_tokens, None);
 }
+
+#[test]
+fn test_parse_rate_limit_headers() {
+    let reset = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc3339();
+    let mut headers = HeaderMap::new();
+    for (name, value) in [
+        ("anthropic-ratelimit-requests-limit", "50"),
+        ("anthropic-ratelimit-requests-remaining", "49"),
+        ("anthropic-ratelimit-tokens-limit", "80000"),
+        ("anthropic-ratelimit-tokens-remaining", "1200"),
+        ("anthropic-ratelimit-tokens-reset", reset.as_str()),
+        ("anthropic-ratelimit-output-tokens-limit", "16000"),
+    ] {
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+    }
+
+    let snapshot = parse_rate_limit_headers(&headers);
+
+    let requests = snapshot.requests.unwrap();
+    assert_eq!((requests.limit, requests.remaining), (50, 49));
+    assert_eq!(requests.reset_in, None);
+    let tokens = snapshot.tokens.unwrap();
+    assert_eq!((tokens.limit, tokens.remaining), (80000, 1200));
+    let reset_in = tokens.reset_in.unwrap();
+    assert!(reset_in > Duration::from_secs(25) && reset_in <= Duration::from_secs(30));
+    assert_eq!(snapshot.input_tokens, None);
+    assert_eq!(snapshot.output_tokens, None);
+}
+
+#[test]
+fn test_parse_rate_limit_headers_without_headers() {
+    assert!(parse_rate_limit_headers(&HeaderMap::new()).is_empty());
+}
+
+async fn start_count_tokens_upstream() -> String {
+    use axum::{http::HeaderMap as AxumHeaderMap, routing::post, Json, Router};
+
+    let app = Router::new().route(
+        "/v1/messages/count_tokens",
+        post(
+            |headers: AxumHeaderMap, Json(body): Json<serde_json::Value>| async move {
+                assert_eq!(headers["x-api-key"], "sk-test");
+                assert_eq!(headers["anthropic-version"], "2023-06-01");
+                assert!(body.get("max_tokens").is_none());
+                let input_tokens = if body["model"] == "claude-sonnet-4-20250514" {
+                    42
+                } else {
+                    0
+                };
+                Json(serde_json::json!({ "input_tokens": input_tokens }))
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+#[tokio::test]
+async fn test_count_tokens_is_forwarded() {
+    let base = start_count_tokens_upstream().await;
+    let account = ClaudeApiAccount::new(
+        "acc1".to_string(),
+        "Test".to_string(),
+        100,
+        true,
+        "sk-test".to_string(),
+        Some(format!("{}/v1", base)),
+        None,
+    );
+    let request = serde_json::json!({
+        "model": "claude-sonnet-4-20250514",
+        "messages": [{"role": "user", "content": "Hello"}]
+    });
+
+    let response = ClaudeRelay::new()
+        .count_tokens(&account, &request, &ClientHeaders::with_defaults())
+        .await
+        .unwrap();
+
+    assert_eq!(response["input_tokens"], 42);
+}
+
+async fn start_batches_upstream() -> String {
+    use axum::{
+        extract::Path,
+        http::{HeaderMap as AxumHeaderMap, StatusCode},
+        routing::{get, post},
+        Json, Router,
+    };
+
+    let app = Router::new()
+        .route(
+            "/v1/messages/batches",
+            post(
+                |headers: AxumHeaderMap, Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(headers["x-api-key"], "sk-test");
+                    assert_eq!(body["requests"][0]["custom_id"], "req-1");
+                    Json(serde_json::json!({
+                        "id": "msgbatch_1",
+                        "type": "message_batch",
+                        "processing_status": "in_progress"
+                    }))
+                },
+            ),
+        )
+        .route(
+            "/v1/messages/batches/:batch_id",
+            get(|Path(batch_id): Path<String>| async move {
+                if batch_id != "msgbatch_1" {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(serde_json::json!({
+                            "type": "error",
+                            "error": {"type": "not_found_error", "message": "Not found"}
+                        })),
+                    );
+                }
+                (
+                    StatusCode::OK,
+                    Json(serde_json::json!({
+                        "id": batch_id,
+                        "processing_status": "ended"
+                    })),
+                )
+            }),
+        )
+        .route(
+            "/v1/messages/batches/:batch_id/results",
+            get(|| async { "{\"custom_id\":\"req-1\",\"result\":{\"type\":\"succeeded\"}}\n" }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+#[tokio::test]
+async fn test_message_batches_are_forwarded() {
+    use futures::StreamExt;
+
+    let base = start_batches_upstream().await;
+    let account = ClaudeApiAccount::new(
+        "acc1".to_string(),
+        "Test".to_string(),
+        100,
+        true,
+        "sk-test".to_string(),
+        Some(format!("{}/v1", base)),
+        None,
+    );
+    let relay = ClaudeRelay::new();
+    let headers = ClientHeaders::with_defaults();
+    let request = serde_json::json!({
+        "requests": [{
+            "custom_id": "req-1",
+            "params": {"model": "claude-sonnet-4-20250514", "max_tokens": 16, "messages": []}
+        }]
+    });
+
+    let batch = relay
+        .create_batch(&account, &request, &headers)
+        .await
+        .unwrap();
+    assert_eq!(batch["id"], "msgbatch_1");
+
+    let batch = relay
+        .get_batch(&account, "msgbatch_1", &headers)
+        .await
+        .unwrap();
+    assert_eq!(batch["processing_status"], "ended");
+    assert!(relay
+        .get_batch(&account, "missing", &headers)
+        .await
+        .is_err());
+
+    let mut stream = relay
+        .batch_results(&account, "msgbatch_1", &headers)
+        .await
+        .unwrap();
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.unwrap());
+    }
+    let line: serde_json::Value =
+        serde_json::from_slice(body.split(|b| *b == b'\n').next().unwrap()).unwrap();
+    assert_eq!(line["result"]["type"], "succeeded");
+}
+
+async fn start_session_oauth_upstream() -> String {
+    use axum::{
+        extract::{Path, State},
+        http::HeaderMap as AxumHeaderMap,
+        routing::{get, post},
+        Json, Router,
+    };
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use sha2::{Digest, Sha256};
+    use std::sync::{Arc, Mutex};
+
+    let challenge = Arc::new(Mutex::new(String::new()));
+    let app = Router::new()
+        .route(
+            "/api/organizations",
+            get(|headers: AxumHeaderMap| async move {
+                assert_eq!(headers["cookie"], "sessionKey=sk-ant-sid01-test");
+                Json(serde_json::json!([
+                    {"uuid": "org-api", "capabilities": ["api"]},
+                    {"uuid": "org-chat", "capabilities": ["chat", "claude_max"]}
+                ]))
+            }),
+        )
+        .route(
+            "/v1/oauth/:org/authorize",
+            post(
+                |State(challenge): State<Arc<Mutex<String>>>,
+                 Path(org): Path<String>,
+                 headers: AxumHeaderMap,
+                 Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(org, "org-chat");
+                    assert_eq!(headers["cookie"], "sessionKey=sk-ant-sid01-test");
+                    assert_eq!(body["code_challenge_method"], "S256");
+                    *challenge.lock().unwrap() = body["code_challenge"].as_str().unwrap().into();
+                    Json(serde_json::json!({
+                        "redirect_uri": format!(
+                            "https://console.anthropic.com/oauth/code/callback?code=auth-code&state={}",
+                            body["state"].as_str().unwrap()
+                        )
+                    }))
+                },
+            ),
+        )
+        .route(
+            "/v1/oauth/token",
+            post(
+                |State(challenge): State<Arc<Mutex<String>>>,
+                 Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(body["grant_type"], "authorization_code");
+                    assert_eq!(body["code"], "auth-code");
+                    let verifier = body["code_verifier"].as_str().unwrap();
+                    assert_eq!(
+                        URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())),
+                        *challenge.lock().unwrap()
+                    );
+                    Json(serde_json::json!({
+                        "access_token": "sk-ant-oat01-test",
+                        "refresh_token": "sk-ant-ort01-test",
+                        "expires_in": 28800,
+                        "token_type": "Bearer"
+                    }))
+                },
+            ),
+        )
+        .with_state(challenge);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+#[tokio::test]
+async fn test_session_key_is_exchanged_for_oauth_token() {
+    let base = start_session_oauth_upstream().await;
+    let account = ClaudeSessionAccount::new(
+        "session1".to_string(),
+        "Test".to_string(),
+        100,
+        true,
+        "sk-ant-sid01-test".to_string(),
+        None,
+        None,
+    )
+    .with_oauth(ClaudeOAuth::with_urls(
+        format!("{}/v1/oauth/token", base),
+        base.clone(),
+    ));
+
+    let credentials = account.get_credentials().await.unwrap();
+
+    assert!(matches!(credentials, Credentials::Bearer(token) if token == "sk-ant-oat01-test"));
+}
+
+fn openrouter_account(api_url: Option<String>) -> OpenRouterAccount {
+    OpenRouterAccount::new(
+        "or1".to_string(),
+        "Test".to_string(),
+        10,
+        true,
+        "sk-or-test".to_string(),
+        api_url,
+        None,
+    )
+}
+
+#[test]
+fn test_openrouter_model_ids() {
+    let account = openrouter_account(None).with_model_ids(
+        [(
+            "claude-sonnet-4-20250514".to_string(),
+            "anthropic/claude-sonnet-4-custom".to_string(),
+        )]
+        .into(),
+    );
+
+    assert_eq!(
+        account.model_id("claude-opus-4-1-20250805"),
+        "anthropic/claude-opus-4.1"
+    );
+    assert_eq!(
+        account.model_id("claude-3-5-haiku-20241022"),
+        "anthropic/claude-3.5-haiku"
+    );
+    assert_eq!(
+        account.model_id("claude-sonnet-4-20250514"),
+        "anthropic/claude-sonnet-4-custom"
+    );
+    assert_eq!(account.model_id("openai/gpt-4o"), "openai/gpt-4o");
+    assert_eq!(account.api_url(), Some(OpenRouterAccount::DEFAULT_API_URL));
+}
+
+async fn start_openrouter_upstream() -> String {
+    use axum::{http::HeaderMap as AxumHeaderMap, routing::post, Json, Router};
+
+    let app = Router::new().route(
+        "/api/v1/messages",
+        post(
+            |headers: AxumHeaderMap, Json(body): Json<serde_json::Value>| async move {
+                assert_eq!(headers["authorization"], "Bearer sk-or-test");
+                assert_eq!(headers["http-referer"], "https://relay.example.com");
+                assert_eq!(headers["x-title"], "Relay");
+                Json(serde_json::json!({
+                    "id": "gen-1",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": "Hi"}],
+                    "model": body["model"],
+                    "stop_reason": "end_turn",
+                    "usage": {"input_tokens": 3, "output_tokens": 1}
+                }))
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+#[tokio::test]
+async fn test_openrouter_request_is_attributed_and_mapped() {
+    let base = start_openrouter_upstream().await;
+    let account = openrouter_account(Some(format!("{}/api", base))).with_attribution(
+        Some("https://relay.example.com".to_string()),
+        Some("Relay".to_string()),
+    );
+    let request = MessagesRequest {
+        model: "claude-sonnet-4-20250514".to_string(),
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: serde_json::json!("Hello"),
+        }],
+        max_tokens: 64,
+        ..Default::default()
+    };
+
+    let response = ClaudeRelay::new().relay(&account, request).await.unwrap();
+
+    assert_eq!(response.model, "anthropic/claude-sonnet-4");
+    assert_eq!(response.usage.output_tokens, 1);
+}
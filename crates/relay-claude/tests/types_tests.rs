@@ -0,0 +1,70 @@
+use relay_claude::MessagesRequest;
+use relay_core::RelayError;
+use serde_json::{json, Value};
+
+fn request(body: Value) -> MessagesRequest {
+    serde_json::from_value(body).unwrap()
+}
+
+fn rejection(body: Value) -> String {
+    match request(body).validate() {
+        Err(RelayError::InvalidRequest(message)) => message,
+        other => panic!("expected an invalid request, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_valid_request_passes() {
+    let body = json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "messages": [
+            {"role": "user", "content": "Hi"},
+            {"role": "assistant", "content": [{"type": "text", "text": "Hello"}]}
+        ]
+    });
+    assert!(request(body).validate().is_ok());
+}
+
+#[test]
+fn test_invalid_requests_name_the_field() {
+    let message = rejection(json!({
+        "model": "claude-sonnet-4-20250514",
+        "messages": [{"role": "user", "content": "Hi"}]
+    }));
+    assert!(message.starts_with("max_tokens:"));
+
+    let message = rejection(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "messages": []
+    }));
+    assert!(message.starts_with("messages:"));
+
+    let message = rejection(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "messages": [
+            {"role": "user", "content": "Hi"},
+            {"role": "system", "content": "Be terse"}
+        ]
+    }));
+    assert_eq!(
+        message,
+        "messages.1.role: Input should be 'user' or 'assistant'"
+    );
+
+    let message = rejection(json!({
+        "model": "claude-sonnet-4-20250514",
+        "max_tokens": 1024,
+        "messages": [{"role": "user", "content": null}]
+    }));
+    assert!(message.starts_with("messages.0.content:"));
+
+    let message = rejection(json!({
+        "model": " ",
+        "max_tokens": 1024,
+        "messages": [{"role": "user", "content": "Hi"}]
+    }));
+    assert_eq!(message, "model: Field required");
+}
@@ -0,0 +1,102 @@
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use relay_core::{AccountProvider, AzureCredentials, Credentials, Platform, ProxyConfig, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// One deployment of an Azure OpenAI resource, serving Codex requests.
+pub struct AzureOpenAIAccount {
+    id: String,
+    name: String,
+    priority: u32,
+    enabled: AtomicBool,
+    credentials: AzureCredentials,
+    /// Resource endpoint, such as `https://my-resource.openai.azure.com`.
+    api_url: String,
+    proxy: Option<ProxyConfig>,
+    unavailable_until: RwLock<Option<Instant>>,
+}
+
+impl AzureOpenAIAccount {
+    pub const DEFAULT_API_VERSION: &'static str = "2025-04-01-preview";
+
+    pub fn new(
+        id: String,
+        name: String,
+        priority: u32,
+        enabled: bool,
+        credentials: AzureCredentials,
+        api_url: String,
+        proxy: Option<ProxyConfig>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            priority,
+            enabled: AtomicBool::new(enabled),
+            credentials,
+            api_url,
+            proxy,
+            unavailable_until: RwLock::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl AccountProvider for AzureOpenAIAccount {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn platform(&self) -> Platform {
+        Platform::Codex
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    fn is_available(&self) -> bool {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        if let Some(until) = *self.unavailable_until.read() {
+            if Instant::now() < until {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    async fn get_credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::AzureOpenAI(self.credentials.clone()))
+    }
+
+    fn proxy_config(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    fn api_url(&self) -> Option<&str> {
+        Some(&self.api_url)
+    }
+
+    fn api_version(&self) -> Option<&str> {
+        Some(&self.credentials.api_version)
+    }
+
+    fn mark_unavailable(&self, duration: Duration, _reason: &str) {
+        let mut until = self.unavailable_until.write();
+        *until = Some(Instant::now() + duration);
+    }
+
+    fn mark_available(&self) {
+        let mut until = self.unavailable_until.write();
+        *until = None;
+    }
+}
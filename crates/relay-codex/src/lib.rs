@@ -1,7 +1,11 @@
 mod account;
+mod azure;
 mod relay;
 mod types;
+mod usage;
 
 pub use account::CodexAccount;
-pub use relay::CodexRelay;
+pub use azure::AzureOpenAIAccount;
+pub use relay::{CodexRelay, RawResponse};
 pub use types::*;
+pub use usage::UsageTracker;
@@ -2,29 +2,55 @@ use async_stream::try_stream;
 use bytes::Bytes;
 use futures::StreamExt;
 use relay_core::{
-    read_error_response_body, AccountProvider, BoxStream, ProxyConfig, RelayError, Result,
+    read_error_response, send_captured, AccountProvider, AzureCredentials, BoxStream, Capture,
+    ClientCache, Credentials, HttpClientOptions, RelayError, Result, UpstreamTimeouts,
 };
-use reqwest::Client;
+use std::sync::Arc;
 use tracing::{debug, info};
 
 use crate::types::{ResponsesRequest, ResponsesResponse};
 
 const DEFAULT_API_URL: &str = "https://api.openai.com/v1";
 
+/// An upstream response relayed without being parsed.
+pub struct RawResponse {
+    pub content_type: Option<String>,
+    pub body: Bytes,
+}
+
 pub struct CodexRelay {
-    default_client: Client,
+    clients: ClientCache,
+    timeouts: Arc<UpstreamTimeouts>,
+    capture: Option<Arc<Capture>>,
 }
 
 impl CodexRelay {
     pub fn new() -> Self {
         Self {
-            default_client: Client::builder()
-                .timeout(std::time::Duration::from_secs(600))
-                .build()
-                .expect("Failed to create HTTP client"),
+            clients: ClientCache::default(),
+            timeouts: Arc::default(),
+            capture: None,
         }
     }
 
+    /// Bounds each upstream request, body included.
+    pub fn with_timeouts(mut self, timeouts: Arc<UpstreamTimeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Writes each upstream exchange to `capture`, for debugging.
+    pub fn with_capture(mut self, capture: Option<Arc<Capture>>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// HTTP version, pooling and keepalive of upstream connections.
+    pub fn with_client_options(mut self, options: HttpClientOptions) -> Self {
+        self.clients = ClientCache::new(options);
+        self
+    }
+
     pub fn default_api_url(&self) -> &'static str {
         DEFAULT_API_URL
     }
@@ -35,23 +61,46 @@ impl CodexRelay {
         format!("{}{}", base, path)
     }
 
-    fn build_client(&self, proxy_config: Option<&ProxyConfig>) -> Result<Client> {
-        if proxy_config.is_none() || proxy_config.map(|p| p.is_none()).unwrap_or(true) {
-            return Ok(self.default_client.clone());
-        }
-
-        let proxy = proxy_config.unwrap();
-        let mut builder = Client::builder().timeout(std::time::Duration::from_secs(600));
+    /// URL of `path` on an Azure OpenAI deployment, such as
+    /// `{api_url}/openai/deployments/{deployment}/responses?api-version=...`.
+    pub fn build_azure_url(&self, api_url: &str, azure: &AzureCredentials, path: &str) -> String {
+        format!(
+            "{}/openai/deployments/{}{}?api-version={}",
+            api_url.trim_end_matches('/'),
+            azure.deployment,
+            path,
+            azure.api_version
+        )
+    }
 
-        if let Some(proxy_url) = proxy.to_url() {
-            let proxy = reqwest::Proxy::all(&proxy_url)
-                .map_err(|e| RelayError::Config(format!("Invalid proxy URL: {}", e)))?;
-            builder = builder.proxy(proxy);
+    /// URL and auth header for `path` on the account's upstream.
+    fn target(
+        &self,
+        account: &dyn AccountProvider,
+        credentials: &Credentials,
+        path: &str,
+    ) -> Result<(String, (&'static str, String))> {
+        match credentials {
+            Credentials::ApiKey(api_key) => Ok((
+                self.build_url(account.api_url(), path),
+                ("Authorization", format!("Bearer {}", api_key)),
+            )),
+            Credentials::AzureOpenAI(azure) => {
+                let api_url = account.api_url().ok_or_else(|| {
+                    RelayError::Config(format!(
+                        "Azure OpenAI account {} has no endpoint",
+                        account.id()
+                    ))
+                })?;
+                Ok((
+                    self.build_azure_url(api_url, azure, path),
+                    ("api-key", azure.api_key.clone()),
+                ))
+            }
+            _ => Err(RelayError::Unauthorized(
+                "Expected API key credentials".to_string(),
+            )),
         }
-
-        builder
-            .build()
-            .map_err(|e| RelayError::Config(format!("Failed to build HTTP client: {}", e)))
     }
 
     pub async fn relay(
@@ -61,8 +110,9 @@ impl CodexRelay {
         path: &str,
     ) -> Result<ResponsesResponse> {
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
-        let api_url = self.build_url(account.api_url(), path);
+        let client = self.clients.client(account.proxy_config())?;
+        let (api_url, (auth_header_name, auth_header_value)) =
+            self.target(account, &credentials, path)?;
 
         debug!(
             account_id = account.id(),
@@ -71,21 +121,16 @@ impl CodexRelay {
             "Relaying non-streaming Codex request"
         );
 
-        let api_key = credentials.as_api_key().ok_or_else(|| {
-            RelayError::Unauthorized("Expected API key credentials".to_string())
-        })?;
-
-        let response = client
+        let builder = client
             .post(&api_url)
-            .header("Authorization", format!("Bearer {}", api_key))
+            .header(auth_header_name, auth_header_value)
             .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+            .timeout(self.timeouts.for_request(account.id(), &request.model))
+            .json(&request);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
 
         if !response.status().is_success() {
-            let (status, body) = read_error_response_body(response).await;
-            return Err(RelayError::from_response_body(status, &body));
+            return Err(read_error_response(response).await);
         }
 
         let resp: ResponsesResponse = response.json().await?;
@@ -108,8 +153,9 @@ impl CodexRelay {
         request.stream = true;
 
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
-        let api_url = self.build_url(account.api_url(), path);
+        let client = self.clients.client(account.proxy_config())?;
+        let (api_url, (auth_header_name, auth_header_value)) =
+            self.target(account, &credentials, path)?;
 
         debug!(
             account_id = account.id(),
@@ -118,21 +164,16 @@ impl CodexRelay {
             "Relaying streaming Codex request"
         );
 
-        let api_key = credentials.as_api_key().ok_or_else(|| {
-            RelayError::Unauthorized("Expected API key credentials".to_string())
-        })?;
-
-        let response = client
+        let builder = client
             .post(&api_url)
-            .header("Authorization", format!("Bearer {}", api_key))
+            .header(auth_header_name, auth_header_value)
             .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+            .timeout(self.timeouts.for_request(account.id(), &request.model))
+            .json(&request);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
 
         if !response.status().is_success() {
-            let (status, body) = read_error_response_body(response).await;
-            return Err(RelayError::from_response_body(status, &body));
+            return Err(read_error_response(response).await);
         }
 
         let account_id = account.id().to_string();
@@ -153,6 +194,122 @@ impl CodexRelay {
 
         Ok(Box::pin(stream))
     }
+
+    /// Forwards a JSON `body` to `path` unchanged, e.g. a `chat/completions`
+    /// request served without conversion, and returns the upstream reply
+    /// as-is.
+    pub async fn relay_json(
+        &self,
+        account: &dyn AccountProvider,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<RawResponse> {
+        let response = self.send_json(account, path, body).await?;
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let body = response.bytes().await?;
+
+        info!(account_id = account.id(), path, "Codex request completed");
+
+        Ok(RawResponse { content_type, body })
+    }
+
+    /// Streaming counterpart of [`Self::relay_json`], yielding the upstream
+    /// bytes exactly as they arrive.
+    pub async fn relay_json_stream(
+        &self,
+        account: &dyn AccountProvider,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<BoxStream<Result<Bytes>>> {
+        let response = self.send_json(account, path, body).await?;
+
+        let stream = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(RelayError::from));
+        Ok(Box::pin(stream))
+    }
+
+    async fn send_json(
+        &self,
+        account: &dyn AccountProvider,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response> {
+        let credentials = account.get_credentials().await?;
+        let client = self.clients.client(account.proxy_config())?;
+        let (api_url, (auth_header_name, auth_header_value)) =
+            self.target(account, &credentials, path)?;
+
+        debug!(
+            account_id = account.id(),
+            api_url = %api_url,
+            "Relaying JSON Codex request"
+        );
+
+        let model = body["model"].as_str().unwrap_or_default();
+        let builder = client
+            .post(&api_url)
+            .header(auth_header_name, auth_header_value)
+            .timeout(self.timeouts.for_request(account.id(), model))
+            .json(body);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
+
+        if !response.status().is_success() {
+            return Err(read_error_response(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// Forwards `body` unchanged, e.g. a multipart audio upload, and
+    /// returns the upstream reply as-is. The body is streamed, so the
+    /// request cannot be retried on another account.
+    pub async fn relay_raw(
+        &self,
+        account: &dyn AccountProvider,
+        path: &str,
+        content_type: &str,
+        body: reqwest::Body,
+    ) -> Result<RawResponse> {
+        let credentials = account.get_credentials().await?;
+        let client = self.clients.client(account.proxy_config())?;
+        let (api_url, (auth_header_name, auth_header_value)) =
+            self.target(account, &credentials, path)?;
+
+        debug!(
+            account_id = account.id(),
+            api_url = %api_url,
+            "Relaying raw Codex request"
+        );
+
+        let builder = client
+            .post(&api_url)
+            .header(auth_header_name, auth_header_value)
+            .header("Content-Type", content_type)
+            .timeout(self.timeouts.for_request(account.id(), ""))
+            .body(body);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
+
+        if !response.status().is_success() {
+            return Err(read_error_response(response).await);
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let body = response.bytes().await?;
+
+        info!(account_id = account.id(), path, "Codex request completed");
+
+        Ok(RawResponse { content_type, body })
+    }
 }
 
 impl Default for CodexRelay {
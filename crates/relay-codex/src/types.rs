@@ -15,3 +15,42 @@ pub struct ResponsesResponse {
     #[serde(flatten)]
     pub extra: serde_json::Map<String, serde_json::Value>,
 }
+
+impl ResponsesResponse {
+    pub fn usage(&self) -> Option<ResponsesUsage> {
+        self.extra.get("usage").and_then(ResponsesUsage::from_value)
+    }
+}
+
+/// Token counts from a Responses API `usage` object.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponsesUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Included in `output_tokens`.
+    pub reasoning_tokens: u32,
+}
+
+impl ResponsesUsage {
+    pub fn from_value(usage: &serde_json::Value) -> Option<Self> {
+        let count = |v: Option<&serde_json::Value>| v.and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+
+        let input_tokens = count(usage.get("input_tokens"));
+        let output_tokens = count(usage.get("output_tokens"));
+        let reasoning_tokens = count(
+            usage
+                .get("output_tokens_details")
+                .and_then(|d| d.get("reasoning_tokens")),
+        );
+
+        if input_tokens == 0 && output_tokens == 0 {
+            return None;
+        }
+
+        Some(Self {
+            input_tokens,
+            output_tokens,
+            reasoning_tokens,
+        })
+    }
+}
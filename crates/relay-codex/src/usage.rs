@@ -0,0 +1,40 @@
+use crate::types::ResponsesUsage;
+use relay_core::SseLines;
+
+/// Picks the final usage out of a Responses API SSE stream.
+///
+/// Usage only arrives in the `response.completed` event, which carries the
+/// whole response object and is routinely split across network reads, so
+/// lines are buffered until complete.
+#[derive(Default)]
+pub struct UsageTracker {
+    lines: SseLines,
+    usage: Option<ResponsesUsage>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        for data in self.lines.push(chunk) {
+            if let Some(usage) = parse_data(&data) {
+                self.usage = Some(usage);
+            }
+        }
+    }
+
+    pub fn usage(&self) -> Option<ResponsesUsage> {
+        self.usage
+    }
+}
+
+fn parse_data(data: &str) -> Option<ResponsesUsage> {
+    let value: serde_json::Value = serde_json::from_str(data).ok()?;
+    if value.get("type").and_then(|t| t.as_str()) != Some("response.completed") {
+        return None;
+    }
+
+    ResponsesUsage::from_value(value.get("response")?.get("usage")?)
+}
@@ -1,5 +1,5 @@
-use relay_codex::CodexAccount;
-use relay_core::{AccountProvider, Platform};
+use relay_codex::{AzureOpenAIAccount, CodexAccount};
+use relay_core::{AccountProvider, AzureCredentials, Platform};
 
 #[test]
 fn test_codex_account_creation() {
@@ -53,3 +53,33 @@ async fn test_codex_account_credentials() {
         _ => panic!("Expected ApiKey credentials"),
     }
 }
+
+#[tokio::test]
+async fn test_azure_openai_account_credentials() {
+    let account = AzureOpenAIAccount::new(
+        "azure-1".to_string(),
+        "Azure".to_string(),
+        100,
+        true,
+        AzureCredentials {
+            api_key: "azure-key".to_string(),
+            deployment: "gpt-5-prod".to_string(),
+            api_version: AzureOpenAIAccount::DEFAULT_API_VERSION.to_string(),
+        },
+        "https://my-resource.openai.azure.com".to_string(),
+        None,
+    );
+
+    assert_eq!(account.platform(), Platform::Codex);
+    assert_eq!(
+        account.api_url(),
+        Some("https://my-resource.openai.azure.com")
+    );
+    match account.get_credentials().await.unwrap() {
+        relay_core::Credentials::AzureOpenAI(azure) => {
+            assert_eq!(azure.api_key, "azure-key");
+            assert_eq!(azure.deployment, "gpt-5-prod");
+        }
+        _ => panic!("Expected AzureOpenAI credentials"),
+    }
+}
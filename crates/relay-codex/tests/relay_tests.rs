@@ -1,4 +1,5 @@
-use relay_codex::{CodexRelay, ResponsesRequest};
+use relay_codex::{CodexRelay, ResponsesRequest, ResponsesResponse, UsageTracker};
+use relay_core::AzureCredentials;
 
 #[test]
 fn test_codex_relay_creation() {
@@ -39,3 +40,123 @@ fn test_get_api_url_with_trailing_slash() {
     let url = relay.build_url(Some("https://custom.api.com/v1/"), "/responses");
     assert_eq!(url, "https://custom.api.com/v1/responses");
 }
+
+#[test]
+fn test_azure_url_puts_deployment_in_path() {
+    let relay = CodexRelay::new();
+    let azure = AzureCredentials {
+        api_key: "azure-key".to_string(),
+        deployment: "gpt-5-prod".to_string(),
+        api_version: "2025-04-01-preview".to_string(),
+    };
+
+    let url = relay.build_azure_url(
+        "https://my-resource.openai.azure.com/",
+        &azure,
+        "/responses",
+    );
+
+    assert_eq!(
+        url,
+        "https://my-resource.openai.azure.com/openai/deployments/gpt-5-prod/responses?api-version=2025-04-01-preview"
+    );
+}
+
+const COMPLETED_EVENT: &str = "event: response.completed\ndata: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp_1\",\"status\":\"completed\",\"output\":[{\"type\":\"message\",\"content\":[{\"type\":\"output_text\",\"text\":\"Hi\"}]}],\"usage\":{\"input_tokens\":120,\"input_tokens_details\":{\"cached_tokens\":0},\"output_tokens\":300,\"output_tokens_details\":{\"reasoning_tokens\":256},\"total_tokens\":420}}}\n\n";
+
+#[test]
+fn test_usage_tracker_reads_completed_event() {
+    let mut tracker = UsageTracker::new();
+    tracker.push(b"event: response.output_text.delta\ndata: {\"type\":\"response.output_text.delta\",\"delta\":\"Hi\"}\n\n");
+    tracker.push(COMPLETED_EVENT.as_bytes());
+
+    let usage = tracker.usage().expect("Should extract usage");
+    assert_eq!(usage.input_tokens, 120);
+    assert_eq!(usage.output_tokens, 300);
+    assert_eq!(usage.reasoning_tokens, 256);
+}
+
+#[test]
+fn test_usage_tracker_handles_split_event() {
+    let mut tracker = UsageTracker::new();
+    for piece in COMPLETED_EVENT.as_bytes().chunks(16) {
+        tracker.push(piece);
+    }
+
+    assert_eq!(tracker.usage().map(|u| u.output_tokens), Some(300));
+}
+
+#[test]
+fn test_usage_tracker_without_completed_event() {
+    let mut tracker = UsageTracker::new();
+    tracker.push(b"data: {\"type\":\"response.created\",\"response\":{\"id\":\"resp_1\",\"usage\":null}}\n\n");
+
+    assert!(tracker.usage().is_none());
+}
+
+#[test]
+fn test_responses_response_usage() {
+    let response: ResponsesResponse = serde_json::from_str(
+        r#"{"id":"resp_1","usage":{"input_tokens":10,"output_tokens":5,"total_tokens":15}}"#,
+    )
+    .unwrap();
+
+    let usage = response.usage().unwrap();
+    assert_eq!(usage.input_tokens, 10);
+    assert_eq!(usage.output_tokens, 5);
+    assert_eq!(usage.reasoning_tokens, 0);
+}
+
+#[tokio::test]
+async fn test_chat_completions_stream_is_forwarded_verbatim() {
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use futures::StreamExt;
+
+    const SSE: &str = concat!(
+        "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+        "data: [DONE]\n\n"
+    );
+
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(
+            |headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                assert_eq!(headers["authorization"], "Bearer sk-test");
+                assert_eq!(body["logit_bias"]["50256"], -100);
+                SSE
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let account = relay_codex::CodexAccount::new(
+        "codex".to_string(),
+        "Codex".to_string(),
+        1,
+        true,
+        "sk-test".to_string(),
+        Some(base),
+        None,
+    );
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "stream": true,
+        "messages": [{"role": "user", "content": "Hello"}],
+        "logit_bias": {"50256": -100}
+    });
+
+    let mut stream = CodexRelay::new()
+        .relay_json_stream(&account, "/chat/completions", &body)
+        .await
+        .unwrap();
+    let mut received = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        received.extend_from_slice(&chunk.unwrap());
+    }
+
+    assert_eq!(String::from_utf8(received).unwrap(), SSE);
+}
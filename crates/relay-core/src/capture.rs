@@ -0,0 +1,205 @@
+use futures::StreamExt;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, ResponseBuilderExt};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+/// Headers whose values are credentials, written as `[redacted]`.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "api-key",
+    "x-goog-api-key",
+    "x-amz-security-token",
+    "cookie",
+    "set-cookie",
+];
+
+/// Query parameters that carry credentials, such as Gemini's `key`.
+const SECRET_PARAMS: &[&str] = &["key", "api_key", "access_token"];
+
+/// Writes every upstream exchange to its own timestamped file in a
+/// directory: the request with its body, then the response as it arrives,
+/// streams included, so conversion bugs can be reproduced from what the
+/// upstream actually sent. Credentials in headers and query strings are
+/// redacted; bodies are written as they are.
+pub struct Capture {
+    dir: PathBuf,
+    next: AtomicU64,
+}
+
+impl Capture {
+    /// Creates `dir` if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            next: AtomicU64::new(1),
+        })
+    }
+
+    /// Sends `builder` like [`RequestBuilder::send`], writing the exchange
+    /// down. The response body is copied to the file as it is read, so the
+    /// file is complete once the caller has read it all.
+    pub async fn send(&self, builder: RequestBuilder) -> reqwest::Result<Response> {
+        let (client, request) = builder.build_split();
+        let request = request?;
+
+        let mut file = self.create();
+        if let Some(file) = file.as_mut() {
+            let mut head = format!("> {} {}\n", request.method(), redact_url(request.url()));
+            write_headers(&mut head, '>', request.headers());
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default();
+            write(file, head.as_bytes());
+            write(file, body);
+        }
+
+        let response = client.execute(request).await?;
+        let Some(mut file) = file else {
+            return Ok(response);
+        };
+
+        let mut head = format!("\n\n< {}\n", response.status());
+        write_headers(&mut head, '<', response.headers());
+        write(&mut file, head.as_bytes());
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version())
+            .url(response.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let body = response.bytes_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                write(&mut file, bytes);
+            }
+            chunk
+        });
+        let response = builder
+            .body(reqwest::Body::wrap_stream(body))
+            .expect("parts of a valid response");
+        Ok(Response::from(response))
+    }
+
+    fn create(&self) -> Option<File> {
+        let name = format!(
+            "{}-{:06}.http",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            self.next.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = self.dir.join(name);
+        File::create(&path)
+            .inspect_err(
+                |e| warn!(path = %path.display(), error = %e, "Failed to create capture file"),
+            )
+            .ok()
+    }
+}
+
+/// Sends `builder`, through `capture` when there is one.
+pub async fn send_captured(
+    builder: RequestBuilder,
+    capture: Option<&Capture>,
+) -> reqwest::Result<Response> {
+    match capture {
+        Some(capture) => capture.send(builder).await,
+        None => builder.send().await,
+    }
+}
+
+fn write(file: &mut File, bytes: &[u8]) {
+    if let Err(e) = file.write_all(bytes) {
+        warn!(error = %e, "Failed to write capture file");
+    }
+}
+
+fn write_headers(out: &mut String, prefix: char, headers: &HeaderMap) {
+    for (name, value) in headers {
+        let value = if SECRET_HEADERS.contains(&name.as_str()) {
+            "[redacted]"
+        } else {
+            value.to_str().unwrap_or("[binary]")
+        };
+        out.push_str(&format!("{} {}: {}\n", prefix, name, value));
+    }
+    out.push('\n');
+}
+
+fn redact_url(url: &reqwest::Url) -> reqwest::Url {
+    let mut url = url.clone();
+    if url.query().is_none() {
+        return url;
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if SECRET_PARAMS.contains(&key.as_ref()) {
+                "REDACTED".to_string()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_capture_writes_redacted_exchange() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let _ = socket.read(&mut request).await.unwrap();
+            let body = "event: ping\ndata: {}\n\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let capture = Capture::new(dir.path().join("captures")).unwrap();
+        let builder = reqwest::Client::new()
+            .post(format!("http://{}/v1/stream?alt=sse&key=AIza-secret", addr))
+            .header("x-api-key", "sk-secret")
+            .body(r#"{"model":"m"}"#);
+        let response = send_captured(builder, Some(&capture)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "event: ping\ndata: {}\n\n");
+
+        let files: Vec<_> = std::fs::read_dir(dir.path().join("captures"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let captured = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(!captured.contains("secret"));
+        assert!(captured.starts_with(&format!(
+            "> POST http://{}/v1/stream?alt=sse&key=REDACTED\n",
+            addr
+        )));
+        assert!(captured.contains("> x-api-key: [redacted]\n"));
+        assert!(captured.contains("\n\n{\"model\":\"m\"}\n\n< 200 OK\n"));
+        assert!(captured.contains("< content-type: text/event-stream\n"));
+        assert!(captured.ends_with("\n\nevent: ping\ndata: {}\n\n"));
+    }
+}
@@ -6,13 +6,26 @@ pub enum RelayError {
     OAuth(String),
 
     #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(reqwest::Error),
+
+    /// The upstream did not answer, or finish its body, in time.
+    #[error("Upstream timed out: {0}")]
+    Timeout(String),
+
+    /// A stream sent nothing within its first-byte timeout; the account
+    /// cools down for `retry_after_secs` while another is tried.
+    #[error("Upstream sent nothing within {timeout_secs}s")]
+    FirstByteTimeout {
+        timeout_secs: u64,
+        retry_after_secs: u64,
+    },
 
     #[error("No available account for platform {0:?}")]
     NoAccount(Platform),
 
-    #[error("Rate limited, retry after {0}s")]
-    RateLimited(u64),
+    /// Carries the upstream's wait in seconds when its headers give one.
+    #[error("Rate limited{}", retry_hint(.0))]
+    RateLimited(Option<u64>),
 
     #[error("Upstream API error: {status} - {message}")]
     Upstream { status: u16, message: String },
@@ -20,6 +33,10 @@ pub enum RelayError {
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
+    /// A request's tool declarations were rejected, locally or upstream.
+    #[error("Invalid tool: {0}")]
+    InvalidTool(String),
+
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
@@ -29,8 +46,8 @@ pub enum RelayError {
     #[error("Content filtered: {0}")]
     ContentFiltered(String),
 
-    #[error("API overloaded, retry after {retry_after_minutes} minutes")]
-    Overloaded { retry_after_minutes: u32 },
+    #[error("API overloaded{}", retry_hint(.retry_after_secs))]
+    Overloaded { retry_after_secs: Option<u64> },
 
     #[error("Opus weekly limit reached")]
     OpusWeeklyLimit,
@@ -69,6 +86,66 @@ pub async fn read_error_response_body(response: reqwest::Response) -> (u16, Stri
     (status, body)
 }
 
+/// The error an unsuccessful `response` stands for, with the wait of a 429
+/// or 529 taken from its headers when they give one.
+pub async fn read_error_response(response: reqwest::Response) -> RelayError {
+    let retry_after = retry_after_from_headers(response.headers());
+    let (status, body) = read_error_response_body(response).await;
+    RelayError::from_response_body(status, &body).with_retry_after(retry_after)
+}
+
+/// Seconds the upstream asks to wait before retrying. `Retry-After`, in
+/// seconds or as an HTTP date, comes first; otherwise the latest reset of
+/// the `anthropic-ratelimit-*` windows that are used up or rejected.
+pub fn retry_after_from_headers(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(value) = header(reqwest::header::RETRY_AFTER.as_str()) {
+        let value = value.trim();
+        let secs = value.parse().ok().or_else(|| {
+            chrono::DateTime::parse_from_rfc2822(value)
+                .ok()
+                .and_then(secs_until)
+        });
+        if secs.is_some() {
+            return secs;
+        }
+    }
+
+    headers
+        .keys()
+        .filter_map(|name| {
+            name.as_str()
+                .strip_prefix("anthropic-ratelimit-")?
+                .strip_suffix("-reset")
+        })
+        .filter(|window| {
+            header(&format!("anthropic-ratelimit-{}-remaining", window)) == Some("0")
+                || header(&format!("anthropic-ratelimit-{}-status", window)) == Some("rejected")
+        })
+        .filter_map(|window| {
+            let reset = header(&format!("anthropic-ratelimit-{}-reset", window))?.trim();
+            match reset.parse::<i64>() {
+                Ok(epoch) => chrono::DateTime::from_timestamp(epoch, 0).and_then(secs_until),
+                Err(_) => chrono::DateTime::parse_from_rfc3339(reset)
+                    .ok()
+                    .and_then(secs_until),
+            }
+        })
+        .max()
+}
+
+/// Whole seconds from now until `time`, rounded up; `None` once it passed.
+fn secs_until<Tz: chrono::TimeZone>(time: chrono::DateTime<Tz>) -> Option<u64> {
+    let millis = (time.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_milliseconds();
+    (millis > 0).then(|| (millis as u64).div_ceil(1000))
+}
+
+fn retry_hint(retry_after_secs: &Option<u64>) -> String {
+    retry_after_secs
+        .map(|secs| format!(", retry after {}s", secs))
+        .unwrap_or_default()
+}
+
 impl RelayError {
     pub fn from_response_body(status: u16, body: &str) -> Self {
         match status {
@@ -84,9 +161,9 @@ impl RelayError {
             429 if body.contains("weekly usage limit") && body.to_lowercase().contains("opus") => {
                 RelayError::OpusWeeklyLimit
             }
-            429 => RelayError::RateLimited(60),
+            429 => RelayError::RateLimited(None),
             529 => RelayError::Overloaded {
-                retry_after_minutes: 5,
+                retry_after_secs: None,
             },
             _ => RelayError::Upstream {
                 status,
@@ -95,6 +172,20 @@ impl RelayError {
         }
     }
 
+    /// Fills in the wait of a rate limit or overload from the response
+    /// headers; other errors pass through.
+    pub fn with_retry_after(self, retry_after_secs: Option<u64>) -> Self {
+        match self {
+            RelayError::RateLimited(secs) => RelayError::RateLimited(retry_after_secs.or(secs)),
+            RelayError::Overloaded {
+                retry_after_secs: secs,
+            } => RelayError::Overloaded {
+                retry_after_secs: retry_after_secs.or(secs),
+            },
+            error => error,
+        }
+    }
+
     pub fn to_json_error(&self) -> serde_json::Value {
         match self {
             RelayError::InsufficientQuota => serde_json::json!({
@@ -110,7 +201,10 @@ impl RelayError {
                 "error": {
                     "code": "429",
                     "type": "rate_limited",
-                    "message": format!("Rate limited. Retry after {} seconds.", retry_after)
+                    "message": match retry_after {
+                        Some(secs) => format!("Rate limited. Retry after {} seconds.", secs),
+                        None => "Rate limited. Please retry later.".to_string(),
+                    }
                 }
             }),
             RelayError::Unauthorized(msg) => serde_json::json!({
@@ -145,12 +239,39 @@ impl RelayError {
                     "message": "Opus weekly usage limit reached."
                 }
             }),
-            RelayError::Overloaded { retry_after_minutes } => serde_json::json!({
+            RelayError::Overloaded { retry_after_secs } => serde_json::json!({
                 "type": "error",
                 "error": {
                     "code": "529",
                     "type": "overloaded",
-                    "message": format!("API overloaded. Retry after {} minutes.", retry_after_minutes)
+                    "message": match retry_after_secs {
+                        Some(secs) => format!("API overloaded. Retry after {} seconds.", secs),
+                        None => "API overloaded. Please retry later.".to_string(),
+                    }
+                }
+            }),
+            RelayError::InvalidTool(msg) => serde_json::json!({
+                "type": "error",
+                "error": {
+                    "code": "400",
+                    "type": "invalid_tool",
+                    "message": msg
+                }
+            }),
+            RelayError::Timeout(msg) => serde_json::json!({
+                "type": "error",
+                "error": {
+                    "code": "504",
+                    "type": "timeout",
+                    "message": msg
+                }
+            }),
+            RelayError::FirstByteTimeout { .. } => serde_json::json!({
+                "type": "error",
+                "error": {
+                    "code": "504",
+                    "type": "timeout",
+                    "message": self.to_string()
                 }
             }),
             RelayError::NoAccount(platform) => serde_json::json!({
@@ -175,6 +296,16 @@ impl RelayError {
 
 pub type Result<T> = std::result::Result<T, RelayError>;
 
+impl From<reqwest::Error> for RelayError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            RelayError::Timeout(e.to_string())
+        } else {
+            RelayError::Network(e)
+        }
+    }
+}
+
 impl From<serde_json::Error> for RelayError {
     fn from(e: serde_json::Error) -> Self {
         RelayError::Internal(e.to_string())
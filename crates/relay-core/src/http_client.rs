@@ -0,0 +1,186 @@
+use crate::{ProxyConfig, RelayError, Result};
+use parking_lot::RwLock;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The HTTP version upstream clients speak.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    #[default]
+    Http1,
+    /// HTTP/2 when the server offers it in the TLS handshake, HTTP/1.1
+    /// otherwise.
+    Negotiate,
+    /// HTTP/2 without negotiating, for upstreams known to speak it; also
+    /// reaches plain-text h2c servers.
+    Http2,
+}
+
+/// Connection settings of the relays' upstream HTTP clients, proxied ones
+/// included.
+#[derive(Debug, Clone)]
+pub struct HttpClientOptions {
+    pub version: HttpVersion,
+    /// How long an idle pooled connection is kept; `None` keeps it.
+    pub pool_idle_timeout: Option<Duration>,
+    pub pool_max_idle_per_host: usize,
+    /// `None` leaves TCP keepalive off.
+    pub tcp_keepalive: Option<Duration>,
+}
+
+impl Default for HttpClientOptions {
+    /// reqwest's own defaults, over HTTP/1.1.
+    fn default() -> Self {
+        Self {
+            version: HttpVersion::Http1,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: usize::MAX,
+            tcp_keepalive: Some(Duration::from_secs(15)),
+        }
+    }
+}
+
+impl HttpClientOptions {
+    /// A client builder with these settings applied.
+    pub fn builder(&self) -> reqwest::ClientBuilder {
+        let builder = reqwest::Client::builder()
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .tcp_keepalive(self.tcp_keepalive);
+        match self.version {
+            HttpVersion::Http1 => builder.http1_only(),
+            HttpVersion::Negotiate => builder,
+            HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        }
+    }
+
+    /// A client for requests without a proxy.
+    pub fn client(&self) -> reqwest::Client {
+        self.builder()
+            .build()
+            .expect("Failed to create HTTP client")
+    }
+}
+
+/// The clients of a relay: a shared one for accounts without a proxy, and
+/// one per proxy, kept so that proxied accounts reuse their connections
+/// instead of opening new ones for every request.
+pub struct ClientCache {
+    options: HttpClientOptions,
+    default: Client,
+    clients: RwLock<HashMap<String, Client>>,
+}
+
+impl ClientCache {
+    pub fn new(options: HttpClientOptions) -> Self {
+        Self {
+            default: options.client(),
+            options,
+            clients: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The client for accounts behind `proxy_config`.
+    pub fn client(&self, proxy_config: Option<&ProxyConfig>) -> Result<Client> {
+        self.client_with_headers(proxy_config, &[])
+    }
+
+    /// The client for accounts behind `proxy_config` that send
+    /// `extra_headers` on every request.
+    pub fn client_with_headers(
+        &self,
+        proxy_config: Option<&ProxyConfig>,
+        extra_headers: &[(String, String)],
+    ) -> Result<Client> {
+        let proxy_url = proxy_config.and_then(|p| p.to_url());
+        if proxy_url.is_none() && extra_headers.is_empty() {
+            return Ok(self.default.clone());
+        }
+
+        let mut key = proxy_url.clone().unwrap_or_default();
+        for (name, value) in extra_headers {
+            key.push_str(&format!("\n{}: {}", name, value));
+        }
+        if let Some(client) = self.clients.read().get(&key) {
+            return Ok(client.clone());
+        }
+
+        let client = self.build(proxy_url.as_deref(), extra_headers)?;
+        Ok(self.clients.write().entry(key).or_insert(client).clone())
+    }
+
+    fn build(&self, proxy_url: Option<&str>, extra_headers: &[(String, String)]) -> Result<Client> {
+        let mut builder = self.options.builder();
+
+        if let Some(proxy_url) = proxy_url {
+            let proxy = reqwest::Proxy::all(proxy_url)
+                .map_err(|e| RelayError::Config(format!("Invalid proxy URL: {}", e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        if !extra_headers.is_empty() {
+            let mut headers = HeaderMap::new();
+            for (name, value) in extra_headers {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|e| RelayError::Config(format!("Invalid header name: {}", e)))?;
+                let value = HeaderValue::from_str(value)
+                    .map_err(|e| RelayError::Config(format!("Invalid header value: {}", e)))?;
+                headers.insert(name, value);
+            }
+            builder = builder.default_headers(headers);
+        }
+
+        builder
+            .build()
+            .map_err(|e| RelayError::Config(format!("Failed to build HTTP client: {}", e)))
+    }
+}
+
+impl Default for ClientCache {
+    fn default() -> Self {
+        Self::new(HttpClientOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(port: u16) -> ProxyConfig {
+        ProxyConfig::Http {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: None,
+        }
+    }
+
+    fn cached(cache: &ClientCache) -> usize {
+        cache.clients.read().len()
+    }
+
+    #[test]
+    fn test_client_cache_reuses_proxied_clients() {
+        let cache = ClientCache::default();
+        cache.client(None).unwrap();
+        cache.client(Some(&ProxyConfig::None)).unwrap();
+        assert_eq!(cached(&cache), 0);
+
+        cache.client(Some(&proxy(8080))).unwrap();
+        cache.client(Some(&proxy(8080))).unwrap();
+        assert_eq!(cached(&cache), 1);
+        cache.client(Some(&proxy(8081))).unwrap();
+        assert_eq!(cached(&cache), 2);
+
+        let headers = [("x-team".to_string(), "a".to_string())];
+        cache.client_with_headers(None, &headers).unwrap();
+        cache.client_with_headers(None, &headers).unwrap();
+        assert_eq!(cached(&cache), 3);
+
+        let invalid = [("bad header".to_string(), "a".to_string())];
+        assert!(cache.client_with_headers(None, &invalid).is_err());
+        assert_eq!(cached(&cache), 3);
+    }
+}
@@ -1,13 +1,39 @@
+mod capture;
 mod error;
+mod http_client;
+mod policy;
 mod provider;
+mod rate_limit;
+mod redact;
 mod relay;
 mod scheduler;
 mod session;
+mod sse;
+mod timeout;
 mod types;
 
-pub use error::{read_error_response_body, sanitize_response_body, RelayError, Result};
-pub use provider::{AccountProvider, Credentials};
+pub use capture::{send_captured, Capture};
+pub use error::{
+    read_error_response, read_error_response_body, retry_after_from_headers,
+    sanitize_response_body, RelayError, Result,
+};
+pub use http_client::{ClientCache, HttpClientOptions, HttpVersion};
+pub use policy::{
+    ArmStats, BanditPolicy, BanditReward, CooldownPolicy, CooldownReason, FixedCooldownPolicy,
+    MemorySessionStore, PriorityLruPolicy, RequestFeedback, SelectionPolicy, SessionStore,
+    StickyPolicy, StickySession, TtlStickyPolicy,
+};
+pub use provider::{
+    AccountProvider, AwsCredentials, AzureCredentials, Credentials, VertexCredentials,
+};
+pub use rate_limit::{RateLimitObserver, RateLimitSnapshot, RateLimitWindow};
+pub use redact::{redact_content, redacted_text};
 pub use relay::{BoxStream, Relay};
-pub use scheduler::Scheduler;
+pub use scheduler::{
+    CooldownInfo, CooldownListener, Scheduler, UnifiedScheduler, DEFAULT_QUOTA_RESERVE_RATIO,
+    DEFAULT_SERVER_ERROR_THRESHOLD,
+};
 pub use session::generate_session_hash;
+pub use sse::SseLines;
+pub use timeout::UpstreamTimeouts;
 pub use types::*;
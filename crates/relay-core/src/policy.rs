@@ -0,0 +1,791 @@
+use crate::{AccountProvider, Result};
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+// ============================================================================
+// Sticky sessions
+// ============================================================================
+
+/// A session → account binding with its remaining lifetime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StickySession {
+    pub account_id: String,
+    pub remaining: Duration,
+}
+
+/// Decides which account a session is bound to and how bindings are kept alive.
+#[async_trait]
+pub trait StickyPolicy: Send + Sync {
+    async fn lookup(&self, session_hash: &str) -> Option<StickySession>;
+
+    /// Called after a looked-up binding has been accepted by the scheduler.
+    async fn renew(&self, session_hash: &str, session: &StickySession);
+
+    async fn bind(&self, session_hash: &str, account_id: &str);
+}
+
+/// Storage backend for sticky session bindings.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn get(&self, session_hash: &str) -> Result<Option<StickySession>>;
+
+    async fn put(&self, session_hash: &str, account_id: &str, ttl: Duration) -> Result<()>;
+}
+
+/// Binds sessions for a fixed TTL and only renews them when the remaining
+/// time drops below the renewal threshold.
+pub struct TtlStickyPolicy<S> {
+    store: S,
+    ttl: Duration,
+    renewal_threshold: Duration,
+}
+
+impl<S: SessionStore> TtlStickyPolicy<S> {
+    pub fn new(store: S, ttl: Duration, renewal_threshold: Duration) -> Self {
+        Self {
+            store,
+            ttl,
+            renewal_threshold,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn renewal_threshold(&self) -> Duration {
+        self.renewal_threshold
+    }
+}
+
+#[async_trait]
+impl<S: SessionStore> StickyPolicy for TtlStickyPolicy<S> {
+    async fn lookup(&self, session_hash: &str) -> Option<StickySession> {
+        match self.store.get(session_hash).await {
+            Ok(session) => session,
+            Err(e) => {
+                warn!(error = %e, session_hash = %session_hash, "Failed to get sticky session");
+                None
+            }
+        }
+    }
+
+    async fn renew(&self, session_hash: &str, session: &StickySession) {
+        if session.remaining >= self.renewal_threshold {
+            return;
+        }
+
+        if let Err(e) = self
+            .store
+            .put(session_hash, &session.account_id, self.ttl)
+            .await
+        {
+            warn!(error = %e, session_hash = %session_hash, "Failed to renew sticky session");
+        } else {
+            debug!(session_hash = %session_hash, "Renewed sticky session");
+        }
+    }
+
+    async fn bind(&self, session_hash: &str, account_id: &str) {
+        if let Err(e) = self.store.put(session_hash, account_id, self.ttl).await {
+            warn!(error = %e, session_hash = %session_hash, "Failed to set sticky session");
+        }
+    }
+}
+
+/// In-process session store, useful for embedders without a database.
+#[derive(Default)]
+pub struct MemorySessionStore {
+    sessions: RwLock<HashMap<String, (String, Instant)>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn get(&self, session_hash: &str) -> Result<Option<StickySession>> {
+        let sessions = self.sessions.read();
+        Ok(sessions
+            .get(session_hash)
+            .and_then(|(account_id, expires_at)| {
+                let remaining = expires_at.checked_duration_since(Instant::now())?;
+                Some(StickySession {
+                    account_id: account_id.clone(),
+                    remaining,
+                })
+            }))
+    }
+
+    async fn put(&self, session_hash: &str, account_id: &str, ttl: Duration) -> Result<()> {
+        self.sessions.write().insert(
+            session_hash.to_string(),
+            (account_id.to_string(), Instant::now() + ttl),
+        );
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Account selection
+// ============================================================================
+
+/// Picks one account among the candidates that passed availability checks.
+pub trait SelectionPolicy: Send + Sync {
+    fn choose(&self, candidates: &[Arc<dyn AccountProvider>]) -> Option<Arc<dyn AccountProvider>>;
+
+    /// Called whenever an account is handed out, including sticky hits.
+    fn record_selected(&self, _account_id: &str) {}
+
+    /// Called once a request served by the account has completed.
+    fn record_feedback(&self, _account_id: &str, _feedback: &RequestFeedback) {}
+
+    /// Called with the priced cost of a completed request.
+    fn record_cost(&self, _account_id: &str, _cost_usd: f64) {}
+}
+
+/// How a request served by an account went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestFeedback {
+    pub success: bool,
+    pub latency: Duration,
+}
+
+struct AccountUsage {
+    last_used: Instant,
+    request_count: u64,
+}
+
+/// Highest priority first; ties go to the least recently used account.
+#[derive(Default)]
+pub struct PriorityLruPolicy {
+    usage: RwLock<HashMap<String, AccountUsage>>,
+}
+
+impl PriorityLruPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn last_used(&self, account_id: &str) -> Option<Instant> {
+        self.usage.read().get(account_id).map(|u| u.last_used)
+    }
+
+    pub fn request_count(&self, account_id: &str) -> u64 {
+        self.usage
+            .read()
+            .get(account_id)
+            .map(|u| u.request_count)
+            .unwrap_or(0)
+    }
+}
+
+impl SelectionPolicy for PriorityLruPolicy {
+    fn choose(&self, candidates: &[Arc<dyn AccountProvider>]) -> Option<Arc<dyn AccountProvider>> {
+        let usage = self.usage.read();
+        let last_used = |id: &str| usage.get(id).map(|u| u.last_used);
+
+        candidates
+            .iter()
+            .min_by(|a, b| {
+                let priority_cmp = b.priority().cmp(&a.priority());
+                if priority_cmp != std::cmp::Ordering::Equal {
+                    return priority_cmp;
+                }
+
+                match (last_used(a.id()), last_used(b.id())) {
+                    (Some(a_time), Some(b_time)) => a_time.cmp(&b_time),
+                    (None, Some(_)) => std::cmp::Ordering::Less,
+                    (Some(_), None) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                }
+            })
+            .cloned()
+    }
+
+    fn record_selected(&self, account_id: &str) {
+        let mut usage = self.usage.write();
+        let entry = usage.entry(account_id.to_string()).or_insert(AccountUsage {
+            last_used: Instant::now(),
+            request_count: 0,
+        });
+        entry.last_used = Instant::now();
+        entry.request_count += 1;
+    }
+}
+
+/// What a [`BanditPolicy`] optimizes for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BanditReward {
+    /// Share of requests that succeeded.
+    #[default]
+    SuccessRate,
+    /// Mean latency of successful requests, lower is better.
+    Latency,
+    /// Spend per successful request, lower is better.
+    Cost,
+}
+
+#[derive(Default)]
+struct Arm {
+    pulls: u64,
+    requests: u64,
+    successes: u64,
+    latency_ms: f64,
+    cost_usd: f64,
+}
+
+impl Arm {
+    /// `None` until the arm has reported any feedback.
+    fn score(&self, reward: BanditReward) -> Option<f64> {
+        if self.requests == 0 {
+            return None;
+        }
+        if self.successes == 0 {
+            return Some(f64::NEG_INFINITY);
+        }
+        let successes = self.successes as f64;
+        Some(match reward {
+            BanditReward::SuccessRate => successes / self.requests as f64,
+            BanditReward::Latency => -self.latency_ms / successes,
+            BanditReward::Cost => -self.cost_usd / successes,
+        })
+    }
+}
+
+/// Per-account statistics of a [`BanditPolicy`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArmStats {
+    pub account_id: String,
+    /// Times the account was handed out, sticky hits included.
+    pub pulls: u64,
+    pub requests: u64,
+    pub successes: u64,
+    pub success_rate: Option<f64>,
+    pub mean_latency_ms: Option<f64>,
+    pub cost_per_success_usd: Option<f64>,
+    /// Current estimate of the optimized reward; higher is better.
+    pub score: Option<f64>,
+}
+
+/// Epsilon-greedy multi-armed bandit over the candidate accounts: tries
+/// every account once, then exploits the best-scoring one and explores a
+/// random one with probability `exploration_rate`. Priorities are ignored.
+pub struct BanditPolicy {
+    reward: BanditReward,
+    exploration_rate: f64,
+    arms: RwLock<HashMap<String, Arm>>,
+}
+
+impl BanditPolicy {
+    pub fn new(reward: BanditReward, exploration_rate: f64) -> Self {
+        Self {
+            reward,
+            exploration_rate: exploration_rate.clamp(0.0, 1.0),
+            arms: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn reward(&self) -> BanditReward {
+        self.reward
+    }
+
+    pub fn exploration_rate(&self) -> f64 {
+        self.exploration_rate
+    }
+
+    /// Statistics for every account seen so far, sorted by id.
+    pub fn stats(&self) -> Vec<ArmStats> {
+        let arms = self.arms.read();
+        let mut stats: Vec<ArmStats> = arms
+            .iter()
+            .map(|(account_id, arm)| {
+                let per_success =
+                    |total: f64| (arm.successes > 0).then(|| total / arm.successes as f64);
+                ArmStats {
+                    account_id: account_id.clone(),
+                    pulls: arm.pulls,
+                    requests: arm.requests,
+                    successes: arm.successes,
+                    success_rate: (arm.requests > 0)
+                        .then(|| arm.successes as f64 / arm.requests as f64),
+                    mean_latency_ms: per_success(arm.latency_ms),
+                    cost_per_success_usd: per_success(arm.cost_usd),
+                    score: arm.score(self.reward),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        stats
+    }
+}
+
+/// Uniform sample in `[0, 1)` from std's randomly keyed hasher.
+fn random_unit() -> f64 {
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl SelectionPolicy for BanditPolicy {
+    fn choose(&self, candidates: &[Arc<dyn AccountProvider>]) -> Option<Arc<dyn AccountProvider>> {
+        if candidates.is_empty() {
+            return None;
+        }
+        if self.exploration_rate > 0.0 && random_unit() < self.exploration_rate {
+            let index = (random_unit() * candidates.len() as f64) as usize;
+            let account = &candidates[index.min(candidates.len() - 1)];
+            debug!(account_id = account.id(), "Bandit exploring account");
+            return Some(account.clone());
+        }
+
+        let arms = self.arms.read();
+        let key = |account: &Arc<dyn AccountProvider>| match arms.get(account.id()) {
+            // Untried accounts first, spread by pulls while feedback is pending
+            None => (true, f64::INFINITY, 0),
+            Some(arm) => match arm.score(self.reward) {
+                None => (true, f64::INFINITY, arm.pulls),
+                Some(score) => (false, score, arm.pulls),
+            },
+        };
+
+        candidates
+            .iter()
+            .min_by(|a, b| {
+                let (a_new, a_score, a_pulls) = key(a);
+                let (b_new, b_score, b_pulls) = key(b);
+                b_new
+                    .cmp(&a_new)
+                    .then_with(|| b_score.total_cmp(&a_score))
+                    .then_with(|| a_pulls.cmp(&b_pulls))
+            })
+            .cloned()
+    }
+
+    fn record_selected(&self, account_id: &str) {
+        self.arms
+            .write()
+            .entry(account_id.to_string())
+            .or_default()
+            .pulls += 1;
+    }
+
+    fn record_feedback(&self, account_id: &str, feedback: &RequestFeedback) {
+        let mut arms = self.arms.write();
+        let arm = arms.entry(account_id.to_string()).or_default();
+        arm.requests += 1;
+        if feedback.success {
+            arm.successes += 1;
+            arm.latency_ms += feedback.latency.as_secs_f64() * 1000.0;
+        }
+    }
+
+    fn record_cost(&self, account_id: &str, cost_usd: f64) {
+        self.arms
+            .write()
+            .entry(account_id.to_string())
+            .or_default()
+            .cost_usd += cost_usd;
+    }
+}
+
+// ============================================================================
+// Cooldowns
+// ============================================================================
+
+/// Why an account is being taken out of rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CooldownReason<'a> {
+    /// The waits are the upstream's hints, when its response gave one.
+    RateLimited { retry_after_secs: Option<u64> },
+    Overloaded { retry_after_secs: Option<u64> },
+    Unavailable(&'a str),
+    /// Tripped by an error-budget circuit breaker for a fixed duration.
+    CircuitOpen { seconds: u64 },
+    /// A stream sent nothing within its first-byte timeout.
+    FirstByteTimeout { seconds: u64 },
+    /// Upstream 5xx errors in a row; `strikes` counts those past the
+    /// threshold that tripped the cooldown.
+    ServerErrors { strikes: u32 },
+}
+
+impl CooldownReason<'_> {
+    pub fn label(&self) -> &str {
+        match self {
+            CooldownReason::RateLimited { .. } => "rate_limited",
+            CooldownReason::Overloaded { .. } => "overloaded",
+            CooldownReason::Unavailable(reason) => reason,
+            CooldownReason::CircuitOpen { .. } => "circuit_open",
+            CooldownReason::FirstByteTimeout { .. } => "first_byte_timeout",
+            CooldownReason::ServerErrors { .. } => "server_errors",
+        }
+    }
+}
+
+/// Decides how long an account stays in cooldown.
+pub trait CooldownPolicy: Send + Sync {
+    fn cooldown_for(&self, account_id: &str, reason: &CooldownReason<'_>) -> Duration;
+}
+
+/// Honors upstream retry hints, up to a maximum, and uses fixed durations
+/// for unavailable accounts and for rate limits or overloads that came
+/// without a hint. Runs of server errors cool down for a duration doubled
+/// with each strike.
+pub struct FixedCooldownPolicy {
+    unavailable: Duration,
+    rate_limited: Duration,
+    overloaded: Duration,
+    max_hinted: Duration,
+    server_errors: Duration,
+    max_server_errors: Duration,
+}
+
+impl FixedCooldownPolicy {
+    pub fn new(unavailable: Duration) -> Self {
+        Self {
+            unavailable,
+            rate_limited: Duration::from_secs(60),
+            overloaded: Duration::from_secs(5 * 60),
+            max_hinted: Duration::from_secs(6 * 60 * 60),
+            server_errors: Duration::from_secs(30),
+            max_server_errors: Duration::from_secs(10 * 60),
+        }
+    }
+
+    /// The first cooldown after a run of server errors, and the longest
+    /// one it escalates to.
+    pub fn with_server_error_backoff(mut self, first: Duration, max: Duration) -> Self {
+        self.server_errors = first;
+        self.max_server_errors = max;
+        self
+    }
+
+    /// Cooldowns for a 429 and a 529 whose responses named no wait.
+    pub fn with_fallbacks(mut self, rate_limited: Duration, overloaded: Duration) -> Self {
+        self.rate_limited = rate_limited;
+        self.overloaded = overloaded;
+        self
+    }
+
+    /// The longest cooldown an upstream retry hint may ask for.
+    pub fn with_max_hinted(mut self, max: Duration) -> Self {
+        self.max_hinted = max;
+        self
+    }
+
+    pub fn unavailable(&self) -> Duration {
+        self.unavailable
+    }
+}
+
+impl CooldownPolicy for FixedCooldownPolicy {
+    fn cooldown_for(&self, _account_id: &str, reason: &CooldownReason<'_>) -> Duration {
+        match reason {
+            CooldownReason::RateLimited { retry_after_secs } => retry_after_secs
+                .map(|secs| Duration::from_secs(secs).min(self.max_hinted))
+                .unwrap_or(self.rate_limited),
+            CooldownReason::Overloaded { retry_after_secs } => retry_after_secs
+                .map(|secs| Duration::from_secs(secs).min(self.max_hinted))
+                .unwrap_or(self.overloaded),
+            CooldownReason::Unavailable(_) => self.unavailable,
+            CooldownReason::CircuitOpen { seconds }
+            | CooldownReason::FirstByteTimeout { seconds } => Duration::from_secs(*seconds),
+            CooldownReason::ServerErrors { strikes } => self
+                .server_errors
+                .saturating_mul(1 << (*strikes).min(16))
+                .min(self.max_server_errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> TtlStickyPolicy<MemorySessionStore> {
+        TtlStickyPolicy::new(
+            MemorySessionStore::new(),
+            Duration::from_secs(3600),
+            Duration::from_secs(300),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_renew_when_below_threshold() {
+        let policy = policy();
+        policy
+            .store
+            .put("hash", "acc1", Duration::from_secs(100))
+            .await
+            .unwrap();
+
+        let session = policy.lookup("hash").await.unwrap();
+        policy.renew("hash", &session).await;
+
+        let renewed = policy.lookup("hash").await.unwrap();
+        assert!(renewed.remaining > Duration::from_secs(3500));
+    }
+
+    #[tokio::test]
+    async fn test_no_renewal_above_threshold() {
+        let policy = policy();
+        policy
+            .store
+            .put("hash", "acc1", Duration::from_secs(3000))
+            .await
+            .unwrap();
+
+        let session = policy.lookup("hash").await.unwrap();
+        policy.renew("hash", &session).await;
+
+        let current = policy.lookup("hash").await.unwrap();
+        assert!(current.remaining <= Duration::from_secs(3000));
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_expired_session() {
+        let store = MemorySessionStore::new();
+        store.put("hash", "acc1", Duration::ZERO).await.unwrap();
+
+        assert!(store.get("hash").await.unwrap().is_none());
+    }
+
+    struct MockAccount(String);
+
+    #[async_trait]
+    impl AccountProvider for MockAccount {
+        fn id(&self) -> &str {
+            &self.0
+        }
+
+        fn name(&self) -> &str {
+            &self.0
+        }
+
+        fn platform(&self) -> crate::Platform {
+            crate::Platform::Claude
+        }
+
+        fn priority(&self) -> u32 {
+            100
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn get_credentials(&self) -> Result<crate::Credentials> {
+            Ok(crate::Credentials::ApiKey("test-key".to_string()))
+        }
+
+        fn proxy_config(&self) -> Option<&crate::ProxyConfig> {
+            None
+        }
+
+        fn mark_unavailable(&self, _duration: Duration, _reason: &str) {}
+
+        fn mark_available(&self) {}
+    }
+
+    fn accounts(ids: &[&str]) -> Vec<Arc<dyn AccountProvider>> {
+        ids.iter()
+            .map(|id| Arc::new(MockAccount(id.to_string())) as Arc<dyn AccountProvider>)
+            .collect()
+    }
+
+    fn feedback(success: bool, latency_ms: u64) -> RequestFeedback {
+        RequestFeedback {
+            success,
+            latency: Duration::from_millis(latency_ms),
+        }
+    }
+
+    #[test]
+    fn test_bandit_tries_every_arm_first() {
+        let policy = BanditPolicy::new(BanditReward::SuccessRate, 0.0);
+        let candidates = accounts(&["a", "b"]);
+
+        let first = policy.choose(&candidates).unwrap();
+        policy.record_selected(first.id());
+        let second = policy.choose(&candidates).unwrap();
+
+        assert_ne!(first.id(), second.id());
+    }
+
+    #[test]
+    fn test_bandit_exploits_best_arm() {
+        let candidates = accounts(&["fast", "slow", "flaky"]);
+
+        let policy = BanditPolicy::new(BanditReward::Latency, 0.0);
+        policy.record_feedback("fast", &feedback(true, 200));
+        policy.record_feedback("slow", &feedback(true, 900));
+        policy.record_feedback("flaky", &feedback(false, 10));
+        assert_eq!(policy.choose(&candidates).unwrap().id(), "fast");
+
+        let policy = BanditPolicy::new(BanditReward::SuccessRate, 0.0);
+        policy.record_feedback("fast", &feedback(true, 200));
+        policy.record_feedback("fast", &feedback(false, 200));
+        policy.record_feedback("slow", &feedback(true, 900));
+        policy.record_feedback("flaky", &feedback(false, 10));
+        assert_eq!(policy.choose(&candidates).unwrap().id(), "slow");
+
+        let policy = BanditPolicy::new(BanditReward::Cost, 0.0);
+        for id in ["fast", "slow", "flaky"] {
+            policy.record_feedback(id, &feedback(true, 100));
+        }
+        policy.record_cost("fast", 0.03);
+        policy.record_cost("slow", 0.01);
+        policy.record_cost("flaky", 0.02);
+        assert_eq!(policy.choose(&candidates).unwrap().id(), "slow");
+    }
+
+    #[test]
+    fn test_bandit_full_exploration_picks_any_candidate() {
+        let policy = BanditPolicy::new(BanditReward::SuccessRate, 1.0);
+        let candidates = accounts(&["a", "b", "c"]);
+        policy.record_feedback("a", &feedback(true, 100));
+
+        for _ in 0..50 {
+            let chosen = policy.choose(&candidates).unwrap();
+            assert!(["a", "b", "c"].contains(&chosen.id()));
+        }
+        assert!(policy.choose(&[]).is_none());
+    }
+
+    #[test]
+    fn test_bandit_stats() {
+        let policy = BanditPolicy::new(BanditReward::Latency, 0.1);
+        policy.record_selected("b");
+        policy.record_selected("a");
+        policy.record_feedback("a", &feedback(true, 100));
+        policy.record_feedback("a", &feedback(true, 300));
+        policy.record_feedback("a", &feedback(false, 5000));
+        policy.record_cost("a", 0.5);
+
+        let stats = policy.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].account_id, "a");
+        assert_eq!(stats[0].pulls, 1);
+        assert_eq!(stats[0].requests, 3);
+        assert_eq!(stats[0].successes, 2);
+        assert_eq!(stats[0].mean_latency_ms, Some(200.0));
+        assert_eq!(stats[0].cost_per_success_usd, Some(0.25));
+        assert_eq!(stats[0].score, Some(-200.0));
+        assert_eq!(stats[1].account_id, "b");
+        assert_eq!(stats[1].success_rate, None);
+        assert_eq!(stats[1].score, None);
+    }
+
+    #[test]
+    fn test_fixed_cooldown_policy_durations() {
+        let policy = FixedCooldownPolicy::new(Duration::from_secs(1800));
+
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::RateLimited {
+                    retry_after_secs: Some(7)
+                }
+            ),
+            Duration::from_secs(7)
+        );
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::RateLimited {
+                    retry_after_secs: None
+                }
+            ),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::Overloaded {
+                    retry_after_secs: None
+                }
+            ),
+            Duration::from_secs(300)
+        );
+
+        // Hints are capped, however far off the upstream says its reset is
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::RateLimited {
+                    retry_after_secs: Some(u64::MAX)
+                }
+            ),
+            Duration::from_secs(6 * 60 * 60)
+        );
+        let policy = policy.with_max_hinted(Duration::from_secs(600));
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::Overloaded {
+                    retry_after_secs: Some(3600)
+                }
+            ),
+            Duration::from_secs(600)
+        );
+
+        let policy = policy.with_fallbacks(Duration::from_secs(30), Duration::from_secs(120));
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::Overloaded {
+                    retry_after_secs: None
+                }
+            ),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::Overloaded {
+                    retry_after_secs: Some(45)
+                }
+            ),
+            Duration::from_secs(45)
+        );
+        assert_eq!(
+            policy.cooldown_for("a", &CooldownReason::Unavailable("unauthorized")),
+            Duration::from_secs(1800)
+        );
+        assert_eq!(
+            policy.cooldown_for("a", &CooldownReason::CircuitOpen { seconds: 120 }),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            policy.cooldown_for("a", &CooldownReason::FirstByteTimeout { seconds: 30 }),
+            Duration::from_secs(30)
+        );
+
+        let policy =
+            policy.with_server_error_backoff(Duration::from_secs(10), Duration::from_secs(60));
+        let server_errors = |strikes| CooldownReason::ServerErrors { strikes };
+        assert_eq!(
+            policy.cooldown_for("a", &server_errors(0)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            policy.cooldown_for("a", &server_errors(2)),
+            Duration::from_secs(40)
+        );
+        assert_eq!(
+            policy.cooldown_for("a", &server_errors(40)),
+            Duration::from_secs(60)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Platform, ProxyConfig, Result};
+use crate::{Platform, ProxyConfig, Result, TokenCacheStatus};
 use async_trait::async_trait;
 use std::time::Duration;
 
@@ -6,6 +6,36 @@ use std::time::Duration;
 pub enum Credentials {
     Bearer(String),
     ApiKey(String),
+    /// Signed per request with AWS Signature Version 4.
+    AwsSigV4(AwsCredentials),
+    /// An access token for a Google Cloud project's Vertex AI endpoints.
+    Vertex(VertexCredentials),
+    /// An Azure OpenAI key, sent in the `api-key` header to one deployment.
+    AzureOpenAI(AzureCredentials),
+}
+
+#[derive(Debug, Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct VertexCredentials {
+    pub access_token: String,
+    pub project_id: String,
+    pub region: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AzureCredentials {
+    pub api_key: String,
+    /// Deployment name placed in the request path.
+    pub deployment: String,
+    /// `api-version` query parameter.
+    pub api_version: String,
 }
 
 impl Credentials {
@@ -44,6 +74,30 @@ pub trait AccountProvider: Send + Sync + 'static {
         None
     }
 
+    /// Upstream API version path segment, such as `v1beta`, for platforms
+    /// that serve several.
+    fn api_version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Headers sent with every upstream request, such as a provider's
+    /// attribution headers.
+    fn extra_headers(&self) -> &[(String, String)] {
+        &[]
+    }
+
+    /// Upstream name of `model`, for providers that name models
+    /// differently.
+    fn upstream_model(&self, _model: &str) -> Option<String> {
+        None
+    }
+
+    /// State of the cached access token, for accounts that exchange a
+    /// refresh token or key for short-lived ones.
+    fn token_cache_status(&self) -> Option<TokenCacheStatus> {
+        None
+    }
+
     fn mark_unavailable(&self, duration: Duration, reason: &str);
 
     fn mark_available(&self);
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+/// One upstream rate-limit window, e.g. requests per minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitWindow {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Time until the window is replenished, when reported.
+    pub reset_in: Option<Duration>,
+}
+
+impl RateLimitWindow {
+    /// Whether less than `ratio` of the limit is left.
+    pub fn is_below(&self, ratio: f64) -> bool {
+        self.limit > 0 && (self.remaining as f64) < self.limit as f64 * ratio
+    }
+}
+
+/// Remaining quota of an account as reported by the upstream's response
+/// headers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitSnapshot {
+    pub requests: Option<RateLimitWindow>,
+    pub tokens: Option<RateLimitWindow>,
+    pub input_tokens: Option<RateLimitWindow>,
+    pub output_tokens: Option<RateLimitWindow>,
+}
+
+impl RateLimitSnapshot {
+    pub fn windows(&self) -> impl Iterator<Item = &RateLimitWindow> {
+        [
+            &self.requests,
+            &self.tokens,
+            &self.input_tokens,
+            &self.output_tokens,
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows().next().is_none()
+    }
+}
+
+/// Receives the rate limits a relay observed on upstream responses.
+pub trait RateLimitObserver: Send + Sync {
+    fn observe_rate_limits(&self, account_id: &str, snapshot: RateLimitSnapshot);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(limit: u64, remaining: u64) -> RateLimitWindow {
+        RateLimitWindow {
+            limit,
+            remaining,
+            reset_in: None,
+        }
+    }
+
+    #[test]
+    fn test_window_is_below_ratio() {
+        assert!(window(1000, 49).is_below(0.05));
+        assert!(!window(1000, 50).is_below(0.05));
+        assert!(!window(0, 0).is_below(0.05));
+    }
+
+    #[test]
+    fn test_snapshot_windows() {
+        let snapshot = RateLimitSnapshot {
+            tokens: Some(window(100, 10)),
+            output_tokens: Some(window(50, 5)),
+            ..Default::default()
+        };
+
+        assert_eq!(snapshot.windows().count(), 2);
+        assert!(!snapshot.is_empty());
+        assert!(RateLimitSnapshot::default().is_empty());
+    }
+}
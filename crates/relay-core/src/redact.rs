@@ -0,0 +1,86 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Keys whose string values describe a request's shape rather than its
+/// content, and stay readable in redacted logs.
+const STRUCTURAL_KEYS: &[&str] = &[
+    "type",
+    "role",
+    "model",
+    "id",
+    "tool_use_id",
+    "name",
+    "media_type",
+    "stop_reason",
+];
+
+/// Stands in for `text` in logs: its length and a short hash, so equal
+/// texts can still be matched up without being readable.
+pub fn redacted_text(text: &str) -> String {
+    let digest = hex::encode(Sha256::digest(text.as_bytes()));
+    format!(
+        "[redacted {} chars sha256:{}]",
+        text.chars().count(),
+        &digest[..12]
+    )
+}
+
+/// `value` with every string replaced by [`redacted_text`], except the
+/// values of structural keys such as `type`, `role` and `model`. Covers
+/// message content, system prompts and tool arguments alike.
+pub fn redact_content(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(redacted_text(text)),
+        Value::Array(items) => Value::Array(items.iter().map(redact_content).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, child)| {
+                    let child = match child {
+                        Value::String(_) if STRUCTURAL_KEYS.contains(&key.as_str()) => {
+                            child.clone()
+                        }
+                        _ => redact_content(child),
+                    };
+                    (key.clone(), child)
+                })
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_content_keeps_structure() {
+        let request = json!({
+            "model": "claude-sonnet-4-20250514",
+            "max_tokens": 1024,
+            "system": "You are the payroll assistant",
+            "messages": [
+                {"role": "user", "content": "What is Alice's salary?"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "lookup",
+                     "input": {"employee": "Alice"}}
+                ]}
+            ]
+        });
+
+        let redacted = redact_content(&request);
+        let text = redacted.to_string();
+        assert!(!text.contains("payroll"));
+        assert!(!text.contains("Alice"));
+        assert_eq!(redacted["model"], "claude-sonnet-4-20250514");
+        assert_eq!(redacted["max_tokens"], 1024);
+        assert_eq!(redacted["messages"][1]["content"][0]["name"], "lookup");
+        assert_eq!(redacted["messages"][0]["role"], "user");
+        assert_eq!(
+            redacted["messages"][0]["content"],
+            redacted_text("What is Alice's salary?")
+        );
+        assert!(redacted_text("Alice").starts_with("[redacted 5 chars sha256:"));
+    }
+}
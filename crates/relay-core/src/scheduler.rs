@@ -1,6 +1,14 @@
-use crate::{AccountProvider, Platform, Result};
+use crate::policy::{
+    CooldownPolicy, CooldownReason, RequestFeedback, SelectionPolicy, StickyPolicy,
+};
+use crate::rate_limit::{RateLimitObserver, RateLimitSnapshot};
+use crate::{generate_session_hash, AccountProvider, Platform, RelayError, Result};
 use async_trait::async_trait;
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
 #[async_trait]
 pub trait Scheduler: Send + Sync {
@@ -8,9 +16,870 @@ pub trait Scheduler: Send + Sync {
         &self,
         platform: Platform,
         session_hash: Option<&str>,
+    ) -> Result<Arc<dyn AccountProvider>> {
+        self.select_excluding(platform, session_hash, &HashSet::new())
+            .await
+    }
+
+    async fn select_excluding(
+        &self,
+        platform: Platform,
+        session_hash: Option<&str>,
+        excluded: &HashSet<String>,
     ) -> Result<Arc<dyn AccountProvider>>;
 
     fn accounts(&self, platform: Platform) -> Vec<Arc<dyn AccountProvider>>;
 
     fn all_accounts(&self) -> Vec<Arc<dyn AccountProvider>>;
 }
+
+/// Share of an upstream rate limit below which an account is only picked
+/// when no other account is available.
+pub const DEFAULT_QUOTA_RESERVE_RATIO: f64 = 0.05;
+
+/// Upstream 5xx errors in a row after which an account cools down.
+pub const DEFAULT_SERVER_ERROR_THRESHOLD: u32 = 3;
+
+/// Stands in for cooldowns too long to add to an [`Instant`].
+const MAX_COOLDOWN: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+struct AccountCooldown {
+    until: Instant,
+    reason: String,
+}
+
+/// Remaining cooldown of an account, as reported by [`UnifiedScheduler::cooldown`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CooldownInfo {
+    pub remaining: Duration,
+    pub reason: String,
+}
+
+/// Observes accounts entering cooldown, e.g. to raise alerts. Only called
+/// when an account was not already cooling down.
+pub trait CooldownListener: Send + Sync {
+    fn on_cooldown(&self, account_id: &str, reason: &str, duration: Duration);
+}
+
+/// Default [`Scheduler`] implementation: sticky sessions first, then the
+/// selection policy over accounts that are available, not cooling down and
+/// not draining, preferring those not close to their upstream rate limits.
+pub struct UnifiedScheduler {
+    accounts: Vec<Arc<dyn AccountProvider>>,
+    cooldowns: RwLock<HashMap<String, AccountCooldown>>,
+    /// Accounts that keep serving their sticky sessions but take no new ones.
+    draining: RwLock<HashSet<String>>,
+    sticky: Arc<dyn StickyPolicy>,
+    selection: Arc<dyn SelectionPolicy>,
+    cooldown: Arc<dyn CooldownPolicy>,
+    listener: Option<Arc<dyn CooldownListener>>,
+    rate_limits: RwLock<HashMap<String, (Instant, RateLimitSnapshot)>>,
+    quota_reserve_ratio: f64,
+    /// Account id → the tenant it belongs to.
+    tenants: HashMap<String, String>,
+    /// Account id → upstream 5xx errors since its last success.
+    server_errors: RwLock<HashMap<String, u32>>,
+    server_error_threshold: u32,
+}
+
+impl UnifiedScheduler {
+    pub fn new(
+        accounts: Vec<Arc<dyn AccountProvider>>,
+        sticky: Arc<dyn StickyPolicy>,
+        selection: Arc<dyn SelectionPolicy>,
+        cooldown: Arc<dyn CooldownPolicy>,
+    ) -> Self {
+        Self {
+            accounts,
+            cooldowns: RwLock::new(HashMap::new()),
+            draining: RwLock::new(HashSet::new()),
+            sticky,
+            selection,
+            cooldown,
+            listener: None,
+            rate_limits: RwLock::new(HashMap::new()),
+            quota_reserve_ratio: DEFAULT_QUOTA_RESERVE_RATIO,
+            tenants: HashMap::new(),
+            server_errors: RwLock::new(HashMap::new()),
+            server_error_threshold: DEFAULT_SERVER_ERROR_THRESHOLD,
+        }
+    }
+
+    /// Assigns accounts (id → tenant) to tenants. In `select_account*`, a
+    /// tenant's accounts only serve that tenant, and requests without a
+    /// tenant only get accounts that belong to none.
+    pub fn with_tenants(mut self, tenants: HashMap<String, String>) -> Self {
+        self.tenants = tenants;
+        self
+    }
+
+    /// The tenant `account_id` belongs to, if any.
+    pub fn tenant_of(&self, account_id: &str) -> Option<&str> {
+        self.tenants.get(account_id).map(String::as_str)
+    }
+
+    /// Whether `tenant` has any accounts.
+    pub fn has_tenant(&self, tenant: &str) -> bool {
+        self.tenants.values().any(|t| t == tenant)
+    }
+
+    pub fn with_cooldown_listener(mut self, listener: Arc<dyn CooldownListener>) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    pub fn mark_account_rate_limited(&self, account_id: &str, retry_after_secs: Option<u64>) {
+        let duration =
+            self.apply_cooldown(account_id, CooldownReason::RateLimited { retry_after_secs });
+        info!(
+            account_id = account_id,
+            retry_after_secs = duration.as_secs(),
+            "Account marked as rate limited"
+        );
+    }
+
+    pub fn mark_account_overloaded(&self, account_id: &str, retry_after_secs: Option<u64>) {
+        let duration =
+            self.apply_cooldown(account_id, CooldownReason::Overloaded { retry_after_secs });
+        info!(
+            account_id = account_id,
+            retry_after_secs = duration.as_secs(),
+            "Account marked as overloaded"
+        );
+    }
+
+    pub fn mark_account_unavailable(&self, account_id: &str, reason: &str) {
+        let duration = self.apply_cooldown(account_id, CooldownReason::Unavailable(reason));
+        warn!(
+            account_id = account_id,
+            reason = reason,
+            cooldown_seconds = duration.as_secs(),
+            "Account marked as unavailable"
+        );
+    }
+
+    pub fn mark_account_circuit_open(&self, account_id: &str, seconds: u64) {
+        let duration = self.apply_cooldown(account_id, CooldownReason::CircuitOpen { seconds });
+        warn!(
+            account_id = account_id,
+            cooldown_seconds = duration.as_secs(),
+            "Account circuit opened"
+        );
+    }
+
+    /// Counts an upstream 5xx. From the `server_error_threshold`th in a row
+    /// on, each puts the account in a cooldown that escalates until a
+    /// request on it succeeds.
+    pub fn mark_account_server_error(&self, account_id: &str, status: u16) {
+        let consecutive = {
+            let mut server_errors = self.server_errors.write();
+            let count = server_errors.entry(account_id.to_string()).or_default();
+            *count += 1;
+            *count
+        };
+        if self.server_error_threshold == 0 || consecutive < self.server_error_threshold {
+            debug!(
+                account_id = account_id,
+                status = status,
+                consecutive = consecutive,
+                "Upstream server error"
+            );
+            return;
+        }
+
+        let strikes = consecutive - self.server_error_threshold;
+        let duration = self.apply_cooldown(account_id, CooldownReason::ServerErrors { strikes });
+        warn!(
+            account_id = account_id,
+            status = status,
+            consecutive = consecutive,
+            cooldown_seconds = duration.as_secs(),
+            "Account cooling down after consecutive server errors"
+        );
+    }
+
+    /// How many upstream 5xx in a row put an account in cooldown; 0 never does.
+    pub fn with_server_error_threshold(mut self, threshold: u32) -> Self {
+        self.server_error_threshold = threshold;
+        self
+    }
+
+    pub fn mark_account_first_byte_timeout(&self, account_id: &str, seconds: u64) {
+        let duration =
+            self.apply_cooldown(account_id, CooldownReason::FirstByteTimeout { seconds });
+        warn!(
+            account_id = account_id,
+            cooldown_seconds = duration.as_secs(),
+            "Account stream timed out before its first byte"
+        );
+    }
+
+    fn apply_cooldown(&self, account_id: &str, reason: CooldownReason<'_>) -> Duration {
+        let duration = self.cooldown.cooldown_for(account_id, &reason);
+        let entered = !self.is_in_cooldown(account_id);
+        let now = Instant::now();
+        let until = now
+            .checked_add(duration)
+            .unwrap_or_else(|| now + MAX_COOLDOWN);
+        self.cooldowns.write().insert(
+            account_id.to_string(),
+            AccountCooldown {
+                until,
+                reason: reason.label().to_string(),
+            },
+        );
+        if entered {
+            if let Some(listener) = &self.listener {
+                listener.on_cooldown(account_id, reason.label(), duration);
+            }
+        }
+        duration
+    }
+
+    pub fn with_quota_reserve_ratio(mut self, ratio: f64) -> Self {
+        self.quota_reserve_ratio = ratio;
+        self
+    }
+
+    /// The latest upstream rate limits reported for an account.
+    pub fn rate_limits(&self, account_id: &str) -> Option<RateLimitSnapshot> {
+        self.rate_limits
+            .read()
+            .get(account_id)
+            .map(|(_, snapshot)| *snapshot)
+    }
+
+    /// Whether a window that has not been replenished yet has less than
+    /// the quota reserve left.
+    pub fn is_near_rate_limit(&self, account_id: &str) -> bool {
+        let rate_limits = self.rate_limits.read();
+        let Some((observed_at, snapshot)) = rate_limits.get(account_id) else {
+            return false;
+        };
+        let elapsed = observed_at.elapsed();
+        let near_limit = snapshot.windows().any(|window| {
+            window.is_below(self.quota_reserve_ratio)
+                && window.reset_in.is_none_or(|reset_in| elapsed < reset_in)
+        });
+        near_limit
+    }
+
+    pub fn is_in_cooldown(&self, account_id: &str) -> bool {
+        self.cooldown(account_id).is_some()
+    }
+
+    pub fn cooldown(&self, account_id: &str) -> Option<CooldownInfo> {
+        let cooldowns = self.cooldowns.read();
+        let cooldown = cooldowns.get(account_id)?;
+        let remaining = cooldown.until.checked_duration_since(Instant::now())?;
+        if remaining.is_zero() {
+            return None;
+        }
+        Some(CooldownInfo {
+            remaining,
+            reason: cooldown.reason.clone(),
+        })
+    }
+
+    /// Stops (or resumes) assigning new sessions to an account. Returns
+    /// `false` for unknown account ids.
+    pub fn set_draining(&self, account_id: &str, draining: bool) -> bool {
+        if !self.accounts.iter().any(|a| a.id() == account_id) {
+            return false;
+        }
+        let changed = if draining {
+            self.draining.write().insert(account_id.to_string())
+        } else {
+            self.draining.write().remove(account_id)
+        };
+        if changed {
+            info!(
+                account_id = account_id,
+                draining = draining,
+                "Account draining state changed"
+            );
+        }
+        true
+    }
+
+    pub fn is_draining(&self, account_id: &str) -> bool {
+        self.draining.read().contains(account_id)
+    }
+
+    /// Reports a completed request to the selection policy. A success
+    /// also ends a run of server errors.
+    pub fn record_feedback(&self, account_id: &str, feedback: &RequestFeedback) {
+        if feedback.success {
+            self.server_errors.write().remove(account_id);
+        }
+        self.selection.record_feedback(account_id, feedback);
+    }
+
+    pub fn record_cost(&self, account_id: &str, cost_usd: f64) {
+        self.selection.record_cost(account_id, cost_usd);
+    }
+
+    pub async fn select_account(
+        &self,
+        platform: Platform,
+        request_body: &serde_json::Value,
+    ) -> Result<Arc<dyn AccountProvider>> {
+        self.select_account_excluding(platform, request_body, &HashSet::new())
+            .await
+    }
+
+    pub async fn select_account_excluding(
+        &self,
+        platform: Platform,
+        request_body: &serde_json::Value,
+        excluded: &HashSet<String>,
+    ) -> Result<Arc<dyn AccountProvider>> {
+        self.select_tenant_account(platform, None, request_body, excluded)
+            .await
+    }
+
+    /// Like [`Self::select_account_excluding`], among the accounts that
+    /// serve `tenant`.
+    pub async fn select_tenant_account(
+        &self,
+        platform: Platform,
+        tenant: Option<&str>,
+        request_body: &serde_json::Value,
+        excluded: &HashSet<String>,
+    ) -> Result<Arc<dyn AccountProvider>> {
+        let session_hash = generate_session_hash(request_body);
+        if self.tenants.is_empty() {
+            return self
+                .select_excluding(platform, session_hash.as_deref(), excluded)
+                .await;
+        }
+
+        let mut excluded = excluded.clone();
+        excluded.extend(
+            self.accounts
+                .iter()
+                .filter(|a| self.tenant_of(a.id()) != tenant)
+                .map(|a| a.id().to_string()),
+        );
+        self.select_excluding(platform, session_hash.as_deref(), &excluded)
+            .await
+    }
+
+    /// Account `account_id` of `platform`, bypassing sticky sessions,
+    /// cooldowns and draining, for requests pinned to one account.
+    pub fn pinned_account(
+        &self,
+        platform: Platform,
+        account_id: &str,
+    ) -> Result<Arc<dyn AccountProvider>> {
+        self.accounts
+            .iter()
+            .find(|a| a.id() == account_id && a.platform() == platform)
+            .cloned()
+            .ok_or_else(|| {
+                RelayError::InvalidRequest(format!("No {} account {}", platform, account_id))
+            })
+    }
+
+    async fn get_sticky_account(
+        &self,
+        session_hash: &str,
+        platform: Platform,
+        excluded: &HashSet<String>,
+    ) -> Option<Arc<dyn AccountProvider>> {
+        let session = self.sticky.lookup(session_hash).await?;
+
+        if excluded.contains(&session.account_id) || self.is_in_cooldown(&session.account_id) {
+            return None;
+        }
+
+        let account = self.accounts.iter().find(|a| {
+            a.id() == session.account_id && a.platform() == platform && a.is_available()
+        })?;
+
+        self.sticky.renew(session_hash, &session).await;
+
+        Some(account.clone())
+    }
+
+    fn select_available_account(
+        &self,
+        platform: Platform,
+        excluded: &HashSet<String>,
+    ) -> Result<Arc<dyn AccountProvider>> {
+        let available: Vec<_> = self
+            .accounts
+            .iter()
+            .filter(|a| {
+                a.platform() == platform
+                    && a.is_available()
+                    && !excluded.contains(a.id())
+                    && !self.is_in_cooldown(a.id())
+                    && !self.is_draining(a.id())
+            })
+            .cloned()
+            .collect();
+        let (reserve, preferred): (Vec<_>, Vec<_>) = available
+            .into_iter()
+            .partition(|a| self.is_near_rate_limit(a.id()));
+        let candidates = if preferred.is_empty() {
+            reserve
+        } else {
+            preferred
+        };
+
+        self.selection.choose(&candidates).ok_or_else(|| {
+            warn!(platform = ?platform, "No available accounts for platform");
+            RelayError::NoAccount(platform)
+        })
+    }
+
+    pub fn cleanup_expired_cooldowns(&self) {
+        let now = Instant::now();
+        let mut cooldowns = self.cooldowns.write();
+        let before = cooldowns.len();
+        cooldowns.retain(|_, cooldown| now < cooldown.until);
+        let removed = before - cooldowns.len();
+        if removed > 0 {
+            debug!(removed = removed, "Cleaned up expired account cooldowns");
+        }
+    }
+}
+
+impl RateLimitObserver for UnifiedScheduler {
+    fn observe_rate_limits(&self, account_id: &str, snapshot: RateLimitSnapshot) {
+        self.rate_limits
+            .write()
+            .insert(account_id.to_string(), (Instant::now(), snapshot));
+    }
+}
+
+#[async_trait]
+impl Scheduler for UnifiedScheduler {
+    async fn select_excluding(
+        &self,
+        platform: Platform,
+        session_hash: Option<&str>,
+        excluded: &HashSet<String>,
+    ) -> Result<Arc<dyn AccountProvider>> {
+        if let Some(hash) = session_hash {
+            if let Some(account) = self.get_sticky_account(hash, platform, excluded).await {
+                debug!(session_hash = %hash, account_id = account.id(), "Using sticky session account");
+                self.selection.record_selected(account.id());
+                return Ok(account);
+            }
+        }
+
+        let account = self.select_available_account(platform, excluded)?;
+
+        if let Some(hash) = session_hash {
+            self.sticky.bind(hash, account.id()).await;
+            debug!(session_hash = %hash, account_id = account.id(), "Created new sticky session");
+        }
+
+        info!(
+            account_id = account.id(),
+            account_name = account.name(),
+            priority = account.priority(),
+            platform = ?platform,
+            "Selected account for request"
+        );
+
+        self.selection.record_selected(account.id());
+        Ok(account)
+    }
+
+    fn accounts(&self, platform: Platform) -> Vec<Arc<dyn AccountProvider>> {
+        self.accounts
+            .iter()
+            .filter(|a| a.platform() == platform)
+            .cloned()
+            .collect()
+    }
+
+    fn all_accounts(&self) -> Vec<Arc<dyn AccountProvider>> {
+        self.accounts.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{
+        FixedCooldownPolicy, MemorySessionStore, PriorityLruPolicy, TtlStickyPolicy,
+    };
+    use crate::{Credentials, ProxyConfig};
+
+    struct MockAccount {
+        id: String,
+        platform: Platform,
+        priority: u32,
+    }
+
+    #[async_trait]
+    impl AccountProvider for MockAccount {
+        fn id(&self) -> &str {
+            &self.id
+        }
+
+        fn name(&self) -> &str {
+            &self.id
+        }
+
+        fn platform(&self) -> Platform {
+            self.platform
+        }
+
+        fn priority(&self) -> u32 {
+            self.priority
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn get_credentials(&self) -> Result<Credentials> {
+            Ok(Credentials::ApiKey("test-key".to_string()))
+        }
+
+        fn proxy_config(&self) -> Option<&ProxyConfig> {
+            None
+        }
+
+        fn mark_unavailable(&self, _duration: Duration, _reason: &str) {}
+
+        fn mark_available(&self) {}
+    }
+
+    fn account(id: &str, priority: u32) -> Arc<dyn AccountProvider> {
+        Arc::new(MockAccount {
+            id: id.to_string(),
+            platform: Platform::Claude,
+            priority,
+        })
+    }
+
+    fn scheduler(
+        accounts: Vec<Arc<dyn AccountProvider>>,
+        unavailable_secs: u64,
+    ) -> UnifiedScheduler {
+        UnifiedScheduler::new(
+            accounts,
+            Arc::new(TtlStickyPolicy::new(
+                MemorySessionStore::new(),
+                Duration::from_secs(3600),
+                Duration::from_secs(300),
+            )),
+            Arc::new(PriorityLruPolicy::new()),
+            Arc::new(FixedCooldownPolicy::new(Duration::from_secs(
+                unavailable_secs,
+            ))),
+        )
+    }
+
+    #[test]
+    fn test_cooldown_cleanup() {
+        let scheduler = scheduler(vec![account("test-1", 100)], 0);
+
+        scheduler.mark_account_unavailable("test-1", "test_reason");
+        std::thread::sleep(Duration::from_millis(10));
+        scheduler.cleanup_expired_cooldowns();
+
+        assert!(scheduler.cooldowns.read().is_empty());
+    }
+
+    #[test]
+    fn test_consecutive_server_errors_escalate_cooldown() {
+        let scheduler = scheduler(vec![account("test-1", 100)], 3600);
+        let cooldown_secs = |scheduler: &UnifiedScheduler| {
+            let remaining = scheduler.cooldown("test-1").unwrap().remaining;
+            (remaining + Duration::from_millis(500)).as_secs()
+        };
+
+        scheduler.mark_account_server_error("test-1", 502);
+        scheduler.mark_account_server_error("test-1", 500);
+        assert!(!scheduler.is_in_cooldown("test-1"));
+        scheduler.mark_account_server_error("test-1", 503);
+        assert_eq!(
+            scheduler.cooldown("test-1").unwrap().reason,
+            "server_errors"
+        );
+        assert_eq!(cooldown_secs(&scheduler), 30);
+        scheduler.mark_account_server_error("test-1", 503);
+        assert_eq!(cooldown_secs(&scheduler), 60);
+
+        // A success starts the count over
+        let success = RequestFeedback {
+            success: true,
+            latency: Duration::from_millis(10),
+        };
+        scheduler.record_feedback("test-1", &success);
+        scheduler.cooldowns.write().clear();
+        scheduler.mark_account_server_error("test-1", 500);
+        assert!(!scheduler.is_in_cooldown("test-1"));
+
+        let scheduler = scheduler.with_server_error_threshold(0);
+        for _ in 0..5 {
+            scheduler.mark_account_server_error("test-1", 500);
+        }
+        assert!(!scheduler.is_in_cooldown("test-1"));
+    }
+
+    #[test]
+    fn test_pinned_account_ignores_cooldown() {
+        let scheduler = scheduler(vec![account("test-1", 100), account("test-2", 50)], 1800);
+        scheduler.mark_account_unavailable("test-1", "unauthorized");
+
+        let pinned = scheduler
+            .pinned_account(Platform::Claude, "test-1")
+            .unwrap();
+        assert_eq!(pinned.id(), "test-1");
+        assert!(scheduler
+            .pinned_account(Platform::Gemini, "test-1")
+            .is_err());
+        assert!(scheduler
+            .pinned_account(Platform::Claude, "unknown")
+            .is_err());
+    }
+
+    #[test]
+    fn test_huge_retry_after_is_capped() {
+        let scheduler = scheduler(vec![account("test-1", 100)], 0);
+
+        scheduler.mark_account_rate_limited("test-1", Some(u64::MAX));
+
+        let info = scheduler.cooldown("test-1").unwrap();
+        assert_eq!(info.reason, "rate_limited");
+        assert!(info.remaining <= Duration::from_secs(6 * 60 * 60));
+    }
+
+    #[test]
+    fn test_cooldown_reports_reason_and_remaining() {
+        let scheduler = scheduler(vec![account("test-1", 100)], 1800);
+
+        scheduler.mark_account_unavailable("test-1", "unauthorized");
+
+        let info = scheduler.cooldown("test-1").unwrap();
+        assert_eq!(info.reason, "unauthorized");
+        assert!(info.remaining > Duration::from_secs(1790));
+        assert!(scheduler.cooldown("unknown").is_none());
+    }
+
+    #[derive(Default)]
+    struct RecordingListener {
+        events: parking_lot::Mutex<Vec<(String, String, Duration)>>,
+    }
+
+    impl CooldownListener for RecordingListener {
+        fn on_cooldown(&self, account_id: &str, reason: &str, duration: Duration) {
+            self.events
+                .lock()
+                .push((account_id.to_string(), reason.to_string(), duration));
+        }
+    }
+
+    #[test]
+    fn test_cooldown_listener_only_sees_new_cooldowns() {
+        let listener = Arc::new(RecordingListener::default());
+        let scheduler =
+            scheduler(vec![account("test-1", 100)], 1800).with_cooldown_listener(listener.clone());
+
+        scheduler.mark_account_unavailable("test-1", "unauthorized");
+        scheduler.mark_account_rate_limited("test-1", Some(60));
+
+        let events = listener.events.lock();
+        assert_eq!(
+            *events,
+            vec![(
+                "test-1".to_string(),
+                "unauthorized".to_string(),
+                Duration::from_secs(1800)
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_priority_then_least_recently_used() {
+        let scheduler = scheduler(
+            vec![
+                account("low", 50),
+                account("high-1", 100),
+                account("high-2", 100),
+            ],
+            3600,
+        );
+
+        let first = scheduler.select(Platform::Claude, None).await.unwrap();
+        let second = scheduler.select(Platform::Claude, None).await.unwrap();
+        let third = scheduler.select(Platform::Claude, None).await.unwrap();
+
+        assert_eq!(first.id(), "high-1");
+        assert_eq!(second.id(), "high-2");
+        assert_eq!(third.id(), "high-1");
+    }
+
+    #[tokio::test]
+    async fn test_sticky_session_reuses_account() {
+        let scheduler = scheduler(vec![account("acc1", 100), account("acc2", 100)], 3600);
+
+        let first = scheduler
+            .select(Platform::Claude, Some("session"))
+            .await
+            .unwrap();
+        let second = scheduler
+            .select(Platform::Claude, Some("session"))
+            .await
+            .unwrap();
+
+        assert_eq!(first.id(), second.id());
+    }
+
+    #[tokio::test]
+    async fn test_sticky_account_in_cooldown_is_replaced() {
+        let scheduler = scheduler(vec![account("acc1", 100), account("acc2", 50)], 3600);
+
+        let first = scheduler
+            .select(Platform::Claude, Some("session"))
+            .await
+            .unwrap();
+        assert_eq!(first.id(), "acc1");
+
+        scheduler.mark_account_rate_limited("acc1", Some(60));
+        let second = scheduler
+            .select(Platform::Claude, Some("session"))
+            .await
+            .unwrap();
+        assert_eq!(second.id(), "acc2");
+    }
+
+    #[tokio::test]
+    async fn test_draining_account_keeps_sticky_sessions_only() {
+        let scheduler = scheduler(vec![account("acc1", 100), account("acc2", 50)], 3600);
+
+        let bound = scheduler
+            .select(Platform::Claude, Some("existing"))
+            .await
+            .unwrap();
+        assert_eq!(bound.id(), "acc1");
+
+        assert!(scheduler.set_draining("acc1", true));
+        assert!(scheduler.is_draining("acc1"));
+        assert!(!scheduler.set_draining("unknown", true));
+
+        let existing = scheduler
+            .select(Platform::Claude, Some("existing"))
+            .await
+            .unwrap();
+        assert_eq!(existing.id(), "acc1");
+        let new = scheduler
+            .select(Platform::Claude, Some("new"))
+            .await
+            .unwrap();
+        assert_eq!(new.id(), "acc2");
+        let anonymous = scheduler.select(Platform::Claude, None).await.unwrap();
+        assert_eq!(anonymous.id(), "acc2");
+
+        scheduler.set_draining("acc1", false);
+        let resumed = scheduler.select(Platform::Claude, None).await.unwrap();
+        assert_eq!(resumed.id(), "acc1");
+    }
+
+    fn tokens_left(limit: u64, remaining: u64, reset_in: Duration) -> RateLimitSnapshot {
+        RateLimitSnapshot {
+            tokens: Some(crate::RateLimitWindow {
+                limit,
+                remaining,
+                reset_in: Some(reset_in),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accounts_near_rate_limit_are_used_last() {
+        let scheduler = scheduler(vec![account("acc1", 100), account("acc2", 50)], 3600);
+
+        scheduler.observe_rate_limits("acc1", tokens_left(1000, 10, Duration::from_secs(60)));
+        assert!(scheduler.is_near_rate_limit("acc1"));
+        assert_eq!(
+            scheduler
+                .rate_limits("acc1")
+                .unwrap()
+                .tokens
+                .unwrap()
+                .remaining,
+            10
+        );
+        let selected = scheduler.select(Platform::Claude, None).await.unwrap();
+        assert_eq!(selected.id(), "acc2");
+
+        scheduler.observe_rate_limits("acc2", tokens_left(1000, 0, Duration::from_secs(60)));
+        let selected = scheduler.select(Platform::Claude, None).await.unwrap();
+        assert_eq!(selected.id(), "acc1");
+
+        scheduler.observe_rate_limits("acc1", tokens_left(1000, 900, Duration::from_secs(60)));
+        assert!(!scheduler.is_near_rate_limit("acc1"));
+    }
+
+    #[test]
+    fn test_replenished_window_is_not_near_rate_limit() {
+        let scheduler = scheduler(vec![account("acc1", 100)], 3600);
+
+        scheduler.observe_rate_limits("acc1", tokens_left(1000, 0, Duration::ZERO));
+
+        assert!(!scheduler.is_near_rate_limit("acc1"));
+    }
+
+    #[tokio::test]
+    async fn test_tenants_only_get_their_own_accounts() {
+        let scheduler = scheduler(
+            vec![
+                account("shared", 100),
+                account("team-a", 50),
+                account("team-b", 10),
+            ],
+            3600,
+        )
+        .with_tenants(HashMap::from([
+            ("team-a".to_string(), "a".to_string()),
+            ("team-b".to_string(), "b".to_string()),
+        ]));
+        let body = serde_json::Value::Null;
+        let none = HashSet::new();
+        let select =
+            |tenant| scheduler.select_tenant_account(Platform::Claude, tenant, &body, &none);
+
+        assert_eq!(select(Some("a")).await.unwrap().id(), "team-a");
+        assert_eq!(select(Some("b")).await.unwrap().id(), "team-b");
+        assert_eq!(select(None).await.unwrap().id(), "shared");
+        assert!(matches!(
+            select(Some("c")).await,
+            Err(RelayError::NoAccount(Platform::Claude))
+        ));
+        assert_eq!(scheduler.tenant_of("team-a"), Some("a"));
+        assert_eq!(scheduler.tenant_of("shared"), None);
+        assert!(scheduler.has_tenant("b"));
+        assert!(!scheduler.has_tenant("c"));
+    }
+
+    #[tokio::test]
+    async fn test_no_account_for_platform() {
+        let scheduler = scheduler(vec![account("acc1", 100)], 3600);
+
+        let result = scheduler.select(Platform::Gemini, None).await;
+
+        assert!(matches!(
+            result,
+            Err(RelayError::NoAccount(Platform::Gemini))
+        ));
+    }
+
+    #[test]
+    fn test_accounts_filtered_by_platform() {
+        let scheduler = scheduler(vec![account("acc1", 100)], 3600);
+
+        assert_eq!(scheduler.accounts(Platform::Claude).len(), 1);
+        assert!(scheduler.accounts(Platform::Codex).is_empty());
+        assert_eq!(scheduler.all_accounts().len(), 1);
+    }
+}
@@ -0,0 +1,57 @@
+/// Reassembles the `data:` lines of an SSE stream read in arbitrary
+/// network chunks. A line, or a multi-byte character in it, may be split
+/// across any number of chunks; bytes are held until the line ends.
+#[derive(Debug, Default)]
+pub struct SseLines {
+    buffer: Vec<u8>,
+}
+
+impl SseLines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The payloads of the `data:` lines `chunk` completes, in order.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut data = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            if let Some(payload) = parse_data(&line) {
+                data.push(payload);
+            }
+        }
+        data
+    }
+}
+
+fn parse_data(line: &[u8]) -> Option<String> {
+    let line = std::str::from_utf8(line)
+        .ok()?
+        .trim_end_matches(['\n', '\r']);
+    let payload = line.strip_prefix("data:")?;
+    let payload = payload.strip_prefix(' ').unwrap_or(payload);
+    Some(payload.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut lines = SseLines::new();
+        assert!(lines.push(b"event: message_delta\nda").is_empty());
+        assert!(lines.push(b"ta: {\"a\":").is_empty());
+        assert_eq!(
+            lines.push(b"1}\r\n\r\ndata:[DONE]\n"),
+            ["{\"a\":1}", "[DONE]"]
+        );
+
+        // A multi-byte character split between chunks
+        let event = "data: \"é\"\n".as_bytes();
+        assert!(lines.push(&event[..8]).is_empty());
+        assert_eq!(lines.push(&event[8..]), ["\"é\""]);
+    }
+}
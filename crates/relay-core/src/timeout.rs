@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How long an upstream request may take, body included. A model's timeout
+/// wins over its account's, which wins over the default.
+#[derive(Debug, Clone)]
+pub struct UpstreamTimeouts {
+    default: Duration,
+    accounts: HashMap<String, Duration>,
+    /// Model-name prefixes, longest first.
+    models: Vec<(String, Duration)>,
+}
+
+impl Default for UpstreamTimeouts {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT)
+    }
+}
+
+impl UpstreamTimeouts {
+    pub const DEFAULT: Duration = Duration::from_secs(600);
+
+    pub fn new(default: Duration) -> Self {
+        Self {
+            default,
+            accounts: HashMap::new(),
+            models: Vec::new(),
+        }
+    }
+
+    /// Account id → timeout for that account's requests.
+    pub fn with_accounts(mut self, accounts: HashMap<String, Duration>) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    /// Model-name prefix → timeout; the longest matching prefix wins.
+    pub fn with_models(mut self, models: HashMap<String, Duration>) -> Self {
+        self.models = models.into_iter().collect();
+        self.models
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        self
+    }
+
+    pub fn default_timeout(&self) -> Duration {
+        self.default
+    }
+
+    /// The timeout for a request of `account_id` for `model`.
+    pub fn for_request(&self, account_id: &str, model: &str) -> Duration {
+        self.models
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .map(|(_, timeout)| *timeout)
+            .or_else(|| self.accounts.get(account_id).copied())
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_then_account_then_default() {
+        let secs = Duration::from_secs;
+        let timeouts = UpstreamTimeouts::new(secs(600))
+            .with_accounts(HashMap::from([("slow-proxy".to_string(), secs(900))]))
+            .with_models(HashMap::from([
+                ("claude-opus-4".to_string(), secs(1200)),
+                ("claude-opus-4-1".to_string(), secs(1800)),
+            ]));
+
+        let timeout = |account, model| timeouts.for_request(account, model);
+
+        assert_eq!(timeout("main", "claude-sonnet-4"), secs(600));
+        assert_eq!(timeout("slow-proxy", "claude-sonnet-4"), secs(900));
+        assert_eq!(timeout("slow-proxy", "claude-opus-4-20250514"), secs(1200));
+        assert_eq!(timeout("main", "claude-opus-4-1-20250805"), secs(1800));
+        let default = UpstreamTimeouts::default();
+        assert_eq!(default.for_request("main", ""), secs(600));
+    }
+}
@@ -21,7 +21,7 @@ impl std::fmt::Display for Platform {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ProxyConfig {
     Socks5 {
@@ -40,15 +40,10 @@ pub enum ProxyConfig {
         #[serde(default)]
         password: Option<String>,
     },
+    #[default]
     None,
 }
 
-impl Default for ProxyConfig {
-    fn default() -> Self {
-        ProxyConfig::None
-    }
-}
-
 impl ProxyConfig {
     pub fn is_none(&self) -> bool {
         matches!(self, ProxyConfig::None)
@@ -117,6 +112,28 @@ impl TokenInfo {
     }
 }
 
+/// State of an account's cached access token, as reported by
+/// [`AccountProvider::token_cache_status`](crate::AccountProvider::token_cache_status).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenCacheStatus {
+    /// No token fetched yet; the next request fetches one.
+    Empty,
+    Valid,
+    /// The next request refreshes it.
+    Expired,
+}
+
+impl TokenCacheStatus {
+    pub fn of(token: Option<&TokenInfo>) -> Self {
+        match token {
+            None => TokenCacheStatus::Empty,
+            Some(token) if token.is_valid() => TokenCacheStatus::Valid,
+            Some(_) => TokenCacheStatus::Expired,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UsageData {
     pub input_tokens: u32,
@@ -1,4 +1,4 @@
-use relay_core::RelayError;
+use relay_core::{read_error_response, retry_after_from_headers, RelayError};
 
 #[test]
 fn test_organization_disabled_error() {
@@ -16,8 +16,8 @@ fn test_overloaded_error_with_retry_duration() {
     let error = RelayError::from_response_body(529, "API overloaded");
 
     match error {
-        RelayError::Overloaded { retry_after_minutes } => {
-            assert_eq!(retry_after_minutes, 5);
+        RelayError::Overloaded { retry_after_secs } => {
+            assert_eq!(retry_after_secs, None);
         }
         _ => panic!("Expected Overloaded error, got: {:?}", error),
     }
@@ -79,3 +79,108 @@ fn test_insufficient_quota_json_response() {
         .unwrap()
         .contains("Insufficient balance"));
 }
+
+#[test]
+fn test_timeout_json_response() {
+    let error = RelayError::Timeout("operation timed out".to_string());
+    let json = error.to_json_error();
+
+    assert_eq!(json["type"], "error");
+    assert_eq!(json["error"]["code"], "504");
+    assert_eq!(json["error"]["type"], "timeout");
+    assert_eq!(json["error"]["message"], "operation timed out");
+}
+
+#[tokio::test]
+async fn test_reqwest_timeout_becomes_timeout_error() {
+    // Accepts the connection but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let _server = tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        std::future::pending::<()>().await;
+    });
+
+    let error = reqwest::Client::new()
+        .get(&url)
+        .timeout(std::time::Duration::from_millis(50))
+        .send()
+        .await
+        .unwrap_err();
+
+    match RelayError::from(error) {
+        RelayError::Timeout(_) => {}
+        error => panic!("Expected Timeout error, got: {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit_takes_retry_after_header() {
+    let response = |retry_after: &str| {
+        let response = http::Response::builder()
+            .status(429)
+            .header("retry-after", retry_after)
+            .body("rate limited")
+            .unwrap();
+        reqwest::Response::from(response)
+    };
+
+    match read_error_response(response("7")).await {
+        RelayError::RateLimited(Some(7)) => {}
+        error => panic!("Expected RateLimited(Some(7)), got: {:?}", error),
+    }
+    let in_two_minutes = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+    match read_error_response(response(&in_two_minutes)).await {
+        RelayError::RateLimited(Some(secs)) if (119..=120).contains(&secs) => {}
+        error => panic!("Expected RateLimited(Some(120)), got: {:?}", error),
+    }
+    // A date already past names no wait; the cooldown policy's fallback applies
+    match read_error_response(response("Wed, 21 Oct 2015 07:28:00 GMT")).await {
+        RelayError::RateLimited(None) => {}
+        error => panic!("Expected RateLimited(None), got: {:?}", error),
+    }
+}
+
+#[test]
+fn test_retry_after_from_rate_limit_resets() {
+    let headers = |pairs: &[(&str, String)]| {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+        }
+        headers
+    };
+    let in_secs = |secs: i64| (chrono::Utc::now() + chrono::Duration::seconds(secs)).to_rfc3339();
+
+    // Only exhausted windows count, and the latest of them wins
+    let secs = retry_after_from_headers(&headers(&[
+        ("anthropic-ratelimit-requests-remaining", "0".to_string()),
+        ("anthropic-ratelimit-requests-reset", in_secs(30)),
+        ("anthropic-ratelimit-tokens-remaining", "0".to_string()),
+        ("anthropic-ratelimit-tokens-reset", in_secs(90)),
+        ("anthropic-ratelimit-input-tokens-remaining", "5000".to_string()),
+        ("anthropic-ratelimit-input-tokens-reset", in_secs(600)),
+    ]));
+    assert!(matches!(secs, Some(89..=90)), "{:?}", secs);
+
+    // Subscription limits report a status and a Unix timestamp
+    let reset = chrono::Utc::now().timestamp() + 3600;
+    let secs = retry_after_from_headers(&headers(&[
+        ("anthropic-ratelimit-unified-status", "rejected".to_string()),
+        ("anthropic-ratelimit-unified-reset", reset.to_string()),
+    ]));
+    assert!(matches!(secs, Some(3599..=3600)), "{:?}", secs);
+
+    // Retry-After takes precedence
+    let secs = retry_after_from_headers(&headers(&[
+        ("retry-after", "12".to_string()),
+        ("anthropic-ratelimit-tokens-remaining", "0".to_string()),
+        ("anthropic-ratelimit-tokens-reset", in_secs(90)),
+    ]));
+    assert_eq!(secs, Some(12));
+
+    assert_eq!(retry_after_from_headers(&headers(&[])), None);
+}
@@ -0,0 +1,267 @@
+use relay_claude::{Message, MessagesRequest, MessagesResponse};
+use relay_core::RelayError;
+use relay_gemini::{
+    Candidate, Content, FunctionCall, GenerateContentRequest, GenerateContentResponse, Part,
+    UsageMetadata,
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+
+const CLAUDE_CODE_SYSTEM_PROMPT: &str = "You are Claude Code, Anthropic's official CLI for Claude.";
+
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+pub struct GeminiToClaudeConverter;
+
+impl GeminiToClaudeConverter {
+    /// `model` is the Claude model to use; Gemini puts the requested model in
+    /// the URL rather than the body.
+    pub fn convert_request(
+        req: GenerateContentRequest,
+        model: &str,
+        stream: bool,
+    ) -> Result<MessagesRequest, RelayError> {
+        let mut system = vec![json!({"type": "text", "text": CLAUDE_CODE_SYSTEM_PROMPT})];
+        let system_instruction = req.system_instruction.clone().or_else(|| {
+            req.extra
+                .get("systemInstruction")
+                .and_then(|v| serde_json::from_value::<Content>(v.clone()).ok())
+        });
+        if let Some(instruction) = system_instruction {
+            for part in instruction.parts {
+                if let Part::Text { text } = part {
+                    system.push(json!({"type": "text", "text": text}));
+                }
+            }
+        }
+
+        // Gemini matches function responses to calls by name, Anthropic by id
+        let mut pending_calls: HashMap<String, VecDeque<String>> = HashMap::new();
+        let mut call_count = 0;
+        let mut messages: Vec<Message> = Vec::new();
+
+        for content in req.contents {
+            let role = if content.role == "model" {
+                "assistant"
+            } else {
+                "user"
+            };
+            let mut blocks = Vec::new();
+
+            for part in content.parts {
+                match part {
+                    Part::Text { text } => {
+                        if !text.is_empty() {
+                            blocks.push(json!({"type": "text", "text": text}));
+                        }
+                    }
+                    Part::InlineData { inline_data } => {
+                        let block_type = if inline_data.mime_type.starts_with("image/") {
+                            "image"
+                        } else {
+                            "document"
+                        };
+                        blocks.push(json!({
+                            "type": block_type,
+                            "source": {
+                                "type": "base64",
+                                "media_type": inline_data.mime_type,
+                                "data": inline_data.data
+                            }
+                        }));
+                    }
+                    Part::FunctionCall { function_call } => {
+                        call_count += 1;
+                        let id = format!("toolu_gemini_{}", call_count);
+                        pending_calls
+                            .entry(function_call.name.clone())
+                            .or_default()
+                            .push_back(id.clone());
+                        blocks.push(json!({
+                            "type": "tool_use",
+                            "id": id,
+                            "name": function_call.name,
+                            "input": function_call.args
+                        }));
+                    }
+                    Part::FunctionResponse { function_response } => {
+                        let id = pending_calls
+                            .get_mut(&function_response.name)
+                            .and_then(|ids| ids.pop_front())
+                            .unwrap_or_else(|| format!("toolu_gemini_{}", function_response.name));
+                        blocks.push(json!({
+                            "type": "tool_result",
+                            "tool_use_id": id,
+                            "content": function_response.response.to_string()
+                        }));
+                    }
+                }
+            }
+
+            if !blocks.is_empty() {
+                messages.push(Message {
+                    role: role.to_string(),
+                    content: Value::Array(blocks),
+                });
+            }
+        }
+
+        let tools: Vec<Value> = req
+            .tools
+            .iter()
+            .flatten()
+            .flat_map(|tool| {
+                tool.get("functionDeclarations")
+                    .or_else(|| tool.get("function_declarations"))
+                    .and_then(|d| d.as_array())
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .map(|declaration| {
+                let mut schema = declaration
+                    .get("parameters")
+                    .cloned()
+                    .unwrap_or(json!({"type": "object", "properties": {}}));
+                lowercase_schema_types(&mut schema);
+                json!({
+                    "name": declaration["name"],
+                    "description": declaration.get("description").cloned().unwrap_or(Value::Null),
+                    "input_schema": schema
+                })
+            })
+            .collect();
+
+        let tool_choice = if tools.is_empty() {
+            None
+        } else {
+            req.extra
+                .get("toolConfig")
+                .or_else(|| req.extra.get("tool_config"))
+                .and_then(convert_tool_config)
+        };
+
+        let config = req.generation_config.unwrap_or_else(|| {
+            req.extra
+                .get("generationConfig")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default()
+        });
+
+        let mut extra = serde_json::Map::new();
+        if let Some(stop_sequences) = config.stop_sequences {
+            extra.insert("stop_sequences".to_string(), json!(stop_sequences));
+        }
+
+        Ok(MessagesRequest {
+            model: model.to_string(),
+            messages,
+            max_tokens: config.max_output_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            stream,
+            system: Some(Value::Array(system)),
+            temperature: config.temperature,
+            top_p: config.top_p,
+            top_k: config.top_k,
+            metadata: None,
+            tools: if tools.is_empty() { None } else { Some(tools) },
+            tool_choice,
+            extra,
+        })
+    }
+
+    pub fn convert_response(resp: MessagesResponse) -> GenerateContentResponse {
+        let mut parts = Vec::new();
+
+        if let Some(blocks) = resp.content.as_array() {
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        let text = block.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                        parts.push(Part::Text {
+                            text: text.to_string(),
+                        });
+                    }
+                    Some("tool_use") => {
+                        let name = block.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                        parts.push(Part::FunctionCall {
+                            function_call: FunctionCall {
+                                name: name.to_string(),
+                                args: block.get("input").cloned().unwrap_or(json!({})),
+                            },
+                        });
+                    }
+                    _ => {} // Thinking blocks are not returned
+                }
+            }
+        }
+
+        let usage = &resp.usage;
+        let prompt_token_count = usage.input_tokens
+            + usage.cache_creation_input_tokens.unwrap_or(0)
+            + usage.cache_read_input_tokens.unwrap_or(0);
+
+        GenerateContentResponse {
+            candidates: vec![Candidate {
+                content: Content {
+                    role: "model".to_string(),
+                    parts,
+                },
+                finish_reason: resp.stop_reason.as_deref().map(finish_reason),
+                safety_ratings: None,
+            }],
+            usage_metadata: Some(UsageMetadata {
+                prompt_token_count,
+                candidates_token_count: usage.output_tokens,
+                total_token_count: prompt_token_count + usage.output_tokens,
+            }),
+            model_version: Some(resp.model),
+        }
+    }
+}
+
+/// Gemini `finishReason` for an Anthropic `stop_reason`.
+pub(crate) fn finish_reason(stop_reason: &str) -> String {
+    match stop_reason {
+        "max_tokens" => "MAX_TOKENS",
+        "refusal" => "SAFETY",
+        _ => "STOP",
+    }
+    .to_string()
+}
+
+fn convert_tool_config(config: &Value) -> Option<Value> {
+    let calling = config
+        .get("functionCallingConfig")
+        .or_else(|| config.get("function_calling_config"))?;
+    match calling.get("mode")?.as_str()? {
+        "AUTO" => Some(json!({"type": "auto"})),
+        "NONE" => Some(json!({"type": "none"})),
+        "ANY" => {
+            let allowed = calling
+                .get("allowedFunctionNames")
+                .or_else(|| calling.get("allowed_function_names"))
+                .and_then(|names| names.as_array());
+            match allowed.map(|names| names.as_slice()) {
+                Some([name]) => Some(json!({"type": "tool", "name": name})),
+                _ => Some(json!({"type": "any"})),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Gemini schemas use OpenAPI's upper-case type names (`OBJECT`, `STRING`),
+/// which JSON Schema does not accept.
+fn lowercase_schema_types(schema: &mut Value) {
+    match schema {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(s) if key == "type" => *s = s.to_lowercase(),
+                    _ => lowercase_schema_types(value),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(lowercase_schema_types),
+        _ => {}
+    }
+}
@@ -0,0 +1,5 @@
+mod converter;
+mod stream;
+
+pub use converter::GeminiToClaudeConverter;
+pub use stream::GeminiStreamConverter;
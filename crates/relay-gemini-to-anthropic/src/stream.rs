@@ -0,0 +1,161 @@
+use relay_core::SseLines;
+use relay_gemini::{
+    Candidate, Content, FunctionCall, GenerateContentResponse, Part, UsageMetadata,
+};
+use std::collections::HashMap;
+
+use crate::converter::finish_reason;
+
+struct ToolUse {
+    name: String,
+    input_json: String,
+}
+
+/// Translates an Anthropic Messages SSE byte stream into Gemini
+/// `streamGenerateContent?alt=sse` chunks.
+///
+/// Text deltas are forwarded as they arrive; tool calls are sent whole once
+/// their input JSON is complete, since Gemini has no partial function calls.
+/// The last chunk carries the finish reason and usage.
+pub struct GeminiStreamConverter {
+    lines: SseLines,
+    model: String,
+    tool_uses: HashMap<u64, ToolUse>,
+    prompt_tokens: u32,
+    output_tokens: u32,
+    stop_reason: Option<String>,
+}
+
+impl GeminiStreamConverter {
+    pub fn new() -> Self {
+        Self {
+            lines: SseLines::new(),
+            model: String::new(),
+            tool_uses: HashMap::new(),
+            prompt_tokens: 0,
+            output_tokens: 0,
+            stop_reason: None,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<GenerateContentResponse> {
+        let mut chunks = Vec::new();
+        for data in self.lines.push(bytes) {
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&data) {
+                chunks.extend(self.convert_event(&value));
+            }
+        }
+        chunks
+    }
+
+    pub fn encode(chunk: &GenerateContentResponse) -> String {
+        format!(
+            "data: {}\r\n\r\n",
+            serde_json::to_string(chunk).unwrap_or_default()
+        )
+    }
+
+    fn convert_event(&mut self, event: &serde_json::Value) -> Option<GenerateContentResponse> {
+        match event.get("type")?.as_str()? {
+            "message_start" => {
+                let message = &event["message"];
+                self.model = message["model"].as_str().unwrap_or_default().to_string();
+                let usage = &message["usage"];
+                self.prompt_tokens = [
+                    "input_tokens",
+                    "cache_creation_input_tokens",
+                    "cache_read_input_tokens",
+                ]
+                .iter()
+                .map(|key| usage[key].as_u64().unwrap_or_default() as u32)
+                .sum();
+                None
+            }
+            "content_block_start" => {
+                let block = &event["content_block"];
+                if block["type"] == "tool_use" {
+                    self.tool_uses.insert(
+                        event["index"].as_u64()?,
+                        ToolUse {
+                            name: block["name"].as_str().unwrap_or_default().to_string(),
+                            input_json: String::new(),
+                        },
+                    );
+                }
+                None
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                match delta["type"].as_str()? {
+                    "text_delta" => {
+                        let text = delta["text"].as_str()?.to_string();
+                        Some(self.chunk(vec![Part::Text { text }], None))
+                    }
+                    "input_json_delta" => {
+                        let tool_use = self.tool_uses.get_mut(&event["index"].as_u64()?)?;
+                        tool_use
+                            .input_json
+                            .push_str(delta["partial_json"].as_str().unwrap_or_default());
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            "content_block_stop" => {
+                let tool_use = self.tool_uses.remove(&event["index"].as_u64()?)?;
+                let args = serde_json::from_str(&tool_use.input_json)
+                    .unwrap_or_else(|_| serde_json::json!({}));
+                Some(self.chunk(
+                    vec![Part::FunctionCall {
+                        function_call: FunctionCall {
+                            name: tool_use.name,
+                            args,
+                        },
+                    }],
+                    None,
+                ))
+            }
+            "message_delta" => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(output_tokens) = event["usage"]["output_tokens"].as_u64() {
+                    self.output_tokens = output_tokens as u32;
+                }
+                None
+            }
+            "message_stop" => {
+                let reason = finish_reason(self.stop_reason.as_deref().unwrap_or("end_turn"));
+                let mut chunk = self.chunk(Vec::new(), Some(reason));
+                chunk.usage_metadata = Some(UsageMetadata {
+                    prompt_token_count: self.prompt_tokens,
+                    candidates_token_count: self.output_tokens,
+                    total_token_count: self.prompt_tokens + self.output_tokens,
+                });
+                Some(chunk)
+            }
+            _ => None,
+        }
+    }
+
+    fn chunk(&self, parts: Vec<Part>, finish_reason: Option<String>) -> GenerateContentResponse {
+        GenerateContentResponse {
+            candidates: vec![Candidate {
+                content: Content {
+                    role: "model".to_string(),
+                    parts,
+                },
+                finish_reason,
+                safety_ratings: None,
+            }],
+            usage_metadata: None,
+            model_version: Some(self.model.clone()),
+        }
+    }
+}
+
+impl Default for GeminiStreamConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,124 @@
+use relay_claude::MessagesResponse;
+use relay_gemini::{GenerateContentRequest, Part};
+use relay_gemini_to_anthropic::GeminiToClaudeConverter;
+use serde_json::json;
+
+const MODEL: &str = "claude-sonnet-4-20250514";
+
+fn request(value: serde_json::Value) -> GenerateContentRequest {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_contents_and_system_instruction() {
+    let req = request(json!({
+        "contents": [
+            {"role": "user", "parts": [{"text": "Hi"}]},
+            {"role": "model", "parts": [{"text": "Hello!"}]},
+            {"role": "user", "parts": [{"text": "Describe"}, {"inline_data": {"mime_type": "image/png", "data": "iVBORw0KGgo="}}]}
+        ],
+        "systemInstruction": {"role": "user", "parts": [{"text": "Be brief."}]},
+        "generationConfig": {"maxOutputTokens": 256, "temperature": 0.5, "stopSequences": ["END"]}
+    }));
+
+    let claude_request = GeminiToClaudeConverter::convert_request(req, MODEL, true).unwrap();
+
+    assert_eq!(claude_request.model, MODEL);
+    assert!(claude_request.stream);
+    assert_eq!(claude_request.max_tokens, 256);
+    assert_eq!(claude_request.temperature, Some(0.5));
+    assert_eq!(claude_request.extra["stop_sequences"], json!(["END"]));
+
+    let system = claude_request.system.unwrap();
+    assert!(system[0]["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("You are Claude Code"));
+    assert_eq!(system[1]["text"], "Be brief.");
+
+    let roles: Vec<&str> = claude_request
+        .messages
+        .iter()
+        .map(|m| m.role.as_str())
+        .collect();
+    assert_eq!(roles, ["user", "assistant", "user"]);
+    let image = &claude_request.messages[2].content[1];
+    assert_eq!(image["type"], "image");
+    assert_eq!(image["source"]["media_type"], "image/png");
+}
+
+#[test]
+fn test_function_calls_are_paired_by_name() {
+    let req = request(json!({
+        "contents": [
+            {"role": "user", "parts": [{"text": "Weather in Paris and Rome?"}]},
+            {"role": "model", "parts": [
+                {"function_call": {"name": "get_weather", "args": {"city": "Paris"}}},
+                {"function_call": {"name": "get_weather", "args": {"city": "Rome"}}}
+            ]},
+            {"role": "user", "parts": [
+                {"function_response": {"name": "get_weather", "response": {"temp": 18}}},
+                {"function_response": {"name": "get_weather", "response": {"temp": 24}}}
+            ]}
+        ],
+        "tools": [{"functionDeclarations": [{
+            "name": "get_weather",
+            "description": "Current weather",
+            "parameters": {"type": "OBJECT", "properties": {"city": {"type": "STRING"}}}
+        }]}],
+        "toolConfig": {"functionCallingConfig": {"mode": "ANY"}}
+    }));
+
+    let claude_request = GeminiToClaudeConverter::convert_request(req, MODEL, false).unwrap();
+
+    let calls = &claude_request.messages[1].content;
+    let results = &claude_request.messages[2].content;
+    assert_eq!(calls[0]["type"], "tool_use");
+    assert_eq!(calls[0]["input"]["city"], "Paris");
+    assert_eq!(results[0]["tool_use_id"], calls[0]["id"]);
+    assert_eq!(results[1]["tool_use_id"], calls[1]["id"]);
+    assert_ne!(calls[0]["id"], calls[1]["id"]);
+    assert_eq!(results[0]["content"], "{\"temp\":18}");
+
+    let tools = claude_request.tools.unwrap();
+    assert_eq!(tools[0]["name"], "get_weather");
+    assert_eq!(tools[0]["input_schema"]["type"], "object");
+    assert_eq!(
+        tools[0]["input_schema"]["properties"]["city"]["type"],
+        "string"
+    );
+    assert_eq!(claude_request.tool_choice.unwrap()["type"], "any");
+}
+
+#[test]
+fn test_convert_response() {
+    let response: MessagesResponse = serde_json::from_value(json!({
+        "id": "msg_01Abc",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            {"type": "text", "text": "Checking."},
+            {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"city": "Paris"}}
+        ],
+        "model": MODEL,
+        "stop_reason": "tool_use",
+        "usage": {"input_tokens": 30, "output_tokens": 12, "cache_read_input_tokens": 10}
+    }))
+    .unwrap();
+
+    let converted = GeminiToClaudeConverter::convert_response(response);
+
+    let candidate = &converted.candidates[0];
+    assert_eq!(candidate.content.role, "model");
+    assert_eq!(candidate.finish_reason.as_deref(), Some("STOP"));
+    assert!(matches!(&candidate.content.parts[0], Part::Text { text } if text == "Checking."));
+    assert!(matches!(
+        &candidate.content.parts[1],
+        Part::FunctionCall { function_call } if function_call.args["city"] == "Paris"
+    ));
+    let usage = converted.usage_metadata.unwrap();
+    assert_eq!(usage.prompt_token_count, 40);
+    assert_eq!(usage.candidates_token_count, 12);
+    assert_eq!(usage.total_token_count, 52);
+    assert_eq!(converted.model_version.as_deref(), Some(MODEL));
+}
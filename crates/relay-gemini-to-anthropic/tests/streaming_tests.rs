@@ -0,0 +1,36 @@
+use futures::StreamExt;
+use relay_gemini::GenerateContentRequest;
+use relay_gemini_to_anthropic::{GeminiStreamConverter, GeminiToClaudeConverter};
+use relay_test_support::{assert_fixture, fixture, relay_fixture};
+
+/// Runs a recorded Anthropic SSE fixture through the relay and the stream
+/// converter, returning the exact bytes sent to a Gemini client.
+async fn convert_fixture(name: &str) -> String {
+    let request: GenerateContentRequest = serde_json::from_value(serde_json::json!({
+        "contents": [{"role": "user", "parts": [{"text": "Hello"}]}]
+    }))
+    .unwrap();
+    let claude_request =
+        GeminiToClaudeConverter::convert_request(request, "claude-sonnet-4-20250514", true)
+            .unwrap();
+    let mut stream = relay_fixture(fixture!(&format!("{}.sse", name)), claude_request).await;
+
+    let mut converter = GeminiStreamConverter::new();
+    let mut output = String::new();
+    while let Some(chunk) = stream.next().await {
+        for gemini_chunk in converter.push(&chunk.unwrap()) {
+            output.push_str(&GeminiStreamConverter::encode(&gemini_chunk));
+        }
+    }
+    output
+}
+
+#[tokio::test]
+async fn test_stream_text() {
+    assert_fixture!(convert_fixture("text").await, "text");
+}
+
+#[tokio::test]
+async fn test_stream_tool_use() {
+    assert_fixture!(convert_fixture("tool_use").await, "tool_use");
+}
@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 use parking_lot::RwLock;
-use relay_core::{AccountProvider, Credentials, Platform, ProxyConfig, Result, TokenInfo};
+use relay_core::{
+    AccountProvider, Credentials, Platform, ProxyConfig, Result, TokenCacheStatus, TokenInfo,
+};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
@@ -13,6 +15,7 @@ pub struct GeminiAccount {
     enabled: AtomicBool,
     refresh_token: String,
     api_url: Option<String>,
+    api_version: Option<String>,
     proxy: Option<ProxyConfig>,
     token_cache: RwLock<Option<TokenInfo>>,
     oauth: GeminiOAuth,
@@ -36,12 +39,19 @@ impl GeminiAccount {
             enabled: AtomicBool::new(enabled),
             refresh_token,
             api_url,
+            api_version: None,
             proxy,
             token_cache: RwLock::new(None),
             oauth: GeminiOAuth::new(),
             unavailable_until: RwLock::new(None),
         }
     }
+
+    /// Targets an API version other than `v1`, such as `v1beta`.
+    pub fn with_api_version(mut self, api_version: Option<String>) -> Self {
+        self.api_version = api_version;
+        self
+    }
 }
 
 #[async_trait]
@@ -107,6 +117,14 @@ impl AccountProvider for GeminiAccount {
         self.api_url.as_deref()
     }
 
+    fn api_version(&self) -> Option<&str> {
+        self.api_version.as_deref()
+    }
+
+    fn token_cache_status(&self) -> Option<TokenCacheStatus> {
+        Some(TokenCacheStatus::of(self.token_cache.read().as_ref()))
+    }
+
     fn mark_unavailable(&self, duration: Duration, _reason: &str) {
         let mut until = self.unavailable_until.write();
         *until = Some(Instant::now() + duration);
@@ -1,9 +1,13 @@
 mod account;
 mod oauth;
 mod relay;
+mod stream;
+mod tools;
 mod types;
 
 pub use account::GeminiAccount;
 pub use oauth::GeminiOAuth;
-pub use relay::{GeminiRelay, GeminiRequest};
+pub use relay::{extract_usage_from_chunk, GeminiRelay, GeminiRequest, GenerateContentRelay};
+pub use stream::{StreamFormat, StreamUsage};
+pub use tools::{normalize_tools, upstream_tool_error};
 pub use types::*;
@@ -3,65 +3,98 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use futures::StreamExt;
 use relay_core::{
-    read_error_response_body, AccountProvider, BoxStream, Credentials, ProxyConfig, Relay,
-    RelayError, Result,
+    read_error_response_body, retry_after_from_headers, send_captured, AccountProvider, BoxStream,
+    Capture, ClientCache, Credentials, HttpClientOptions, Relay, RelayError, Result,
+    UpstreamTimeouts,
 };
-use reqwest::Client;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
 use tracing::{debug, info};
 
-use crate::types::{GenerateContentRequest, GenerateContentResponse, UsageMetadata};
+use crate::stream::{StreamFormat, StreamUsage};
+use crate::tools::upstream_tool_error;
+use crate::types::{
+    BatchEmbedContentsRequest, BatchEmbedContentsResponse, EmbedContentRequest,
+    EmbedContentResponse, GenerateContentRequest, GenerateContentResponse, UsageMetadata,
+};
+
+/// Serves generateContent requests for accounts the Gemini API cannot.
+pub type GenerateContentRelay =
+    dyn Relay<Request = GeminiRequest, Response = GenerateContentResponse>;
 
 pub struct GeminiRelay {
-    default_client: Client,
+    clients: ClientCache,
+    vertex: Option<Arc<GenerateContentRelay>>,
+    timeouts: Arc<UpstreamTimeouts>,
+    capture: Option<Arc<Capture>>,
 }
 
 impl GeminiRelay {
     const DEFAULT_API_BASE: &'static str = "https://cloudcode.googleapis.com/v1";
+    const DEFAULT_API_VERSION: &'static str = "v1";
 
     pub fn default_api_base() -> &'static str {
         Self::DEFAULT_API_BASE
     }
 
+    /// Base URL for an account's `api_url` and `api_version`. A version
+    /// already at the end of `api_url` is kept unless another one is set.
+    pub fn api_base(api_url: Option<&str>, api_version: Option<&str>) -> String {
+        let url = api_url
+            .unwrap_or(Self::DEFAULT_API_BASE)
+            .trim_end_matches('/');
+        let (root, url_version) = match url.rsplit_once('/') {
+            Some((root, segment)) if is_api_version(segment) => (root, Some(segment)),
+            _ => (url, None),
+        };
+        let version = api_version
+            .or(url_version)
+            .unwrap_or(Self::DEFAULT_API_VERSION);
+        format!("{}/{}", root, version)
+    }
+
     pub fn new() -> Self {
         Self {
-            default_client: Client::builder()
-                .timeout(std::time::Duration::from_secs(600))
-                .build()
-                .expect("Failed to create HTTP client"),
+            clients: ClientCache::default(),
+            vertex: None,
+            timeouts: Arc::default(),
+            capture: None,
         }
     }
 
-    fn build_client(&self, proxy_config: Option<&ProxyConfig>) -> Result<Client> {
-        if proxy_config.is_none() || proxy_config.map(|p| p.is_none()).unwrap_or(true) {
-            return Ok(self.default_client.clone());
-        }
+    /// Bounds each upstream request, body included.
+    pub fn with_timeouts(mut self, timeouts: Arc<UpstreamTimeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Writes each upstream exchange to `capture`, for debugging.
+    pub fn with_capture(mut self, capture: Option<Arc<Capture>>) -> Self {
+        self.capture = capture;
+        self
+    }
 
-        let proxy = proxy_config.unwrap();
-        let mut builder = Client::builder().timeout(std::time::Duration::from_secs(600));
+    /// HTTP version, pooling and keepalive of upstream connections.
+    pub fn with_client_options(mut self, options: HttpClientOptions) -> Self {
+        self.clients = ClientCache::new(options);
+        self
+    }
 
-        if let Some(proxy_url) = proxy.to_url() {
-            let proxy = reqwest::Proxy::all(&proxy_url)
-                .map_err(|e| RelayError::Config(format!("Invalid proxy URL: {}", e)))?;
-            builder = builder.proxy(proxy);
-        }
+    /// Relays generateContent requests of Vertex AI accounts, so they can
+    /// share the Gemini account pool.
+    pub fn with_vertex_relay(mut self, relay: Arc<GenerateContentRelay>) -> Self {
+        self.vertex = Some(relay);
+        self
+    }
 
-        builder
-            .build()
-            .map_err(|e| RelayError::Config(format!("Failed to build HTTP client: {}", e)))
+    fn vertex_relay(&self) -> Result<&GenerateContentRelay> {
+        self.vertex
+            .as_deref()
+            .ok_or_else(|| RelayError::Config("No relay for Vertex AI accounts".to_string()))
     }
 
     fn get_api_base(account: &dyn AccountProvider) -> String {
-        account
-            .api_url()
-            .map(|url| {
-                let base = url.trim_end_matches('/');
-                if base.ends_with("/v1") {
-                    base.to_string()
-                } else {
-                    format!("{}/v1", base)
-                }
-            })
-            .unwrap_or_else(|| Self::DEFAULT_API_BASE.to_string())
+        Self::api_base(account.api_url(), account.api_version())
     }
 
     fn build_url(api_base: &str, model: &str, stream: bool) -> String {
@@ -73,9 +106,86 @@ impl GeminiRelay {
         format!("{}/models/{}:{}", api_base, model, method)
     }
 
+    /// Forwards a `models/*:countTokens` request body unchanged and returns
+    /// the upstream's `{"totalTokens": N}` answer.
+    pub async fn count_tokens(
+        &self,
+        account: &dyn AccountProvider,
+        model: &str,
+        request: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        self.post_method(account, model, "countTokens", request)
+            .await
+    }
+
+    pub async fn embed_content(
+        &self,
+        account: &dyn AccountProvider,
+        model: &str,
+        request: &EmbedContentRequest,
+    ) -> Result<EmbedContentResponse> {
+        self.post_method(account, model, "embedContent", request)
+            .await
+    }
+
+    pub async fn batch_embed_contents(
+        &self,
+        account: &dyn AccountProvider,
+        model: &str,
+        request: &BatchEmbedContentsRequest,
+    ) -> Result<BatchEmbedContentsResponse> {
+        self.post_method(account, model, "batchEmbedContents", request)
+            .await
+    }
+
+    /// Sends a non-streaming `models/{model}:{method}` request.
+    async fn post_method<Req, Resp>(
+        &self,
+        account: &dyn AccountProvider,
+        model: &str,
+        method: &str,
+        request: &Req,
+    ) -> Result<Resp>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        let credentials = account.get_credentials().await?;
+        let client = self.clients.client(account.proxy_config())?;
+
+        let token = bearer_token(credentials)?;
+
+        let api_base = Self::get_api_base(account);
+        let url = format!("{}/models/{}:{}", api_base, model, method);
+
+        debug!(
+            account_id = account.id(),
+            model = model,
+            api_url = %url,
+            "Sending {} request to Gemini API",
+            method
+        );
+
+        let builder = client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/json")
+            .timeout(self.timeouts.for_request(account.id(), model))
+            .json(request);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error_response(response).await);
+        }
+        Ok(response.json().await?)
+    }
+
     async fn handle_error_response(&self, response: reqwest::Response) -> RelayError {
+        let retry_after = retry_after_from_headers(response.headers());
         let (status, body) = read_error_response_body(response).await;
-        RelayError::from_response_body(status, &body)
+        upstream_tool_error(status, &body).unwrap_or_else(|| {
+            RelayError::from_response_body(status, &body).with_retry_after(retry_after)
+        })
     }
 }
 
@@ -89,6 +199,8 @@ pub struct GeminiRequest {
     pub model: String,
     pub body: GenerateContentRequest,
     pub stream: bool,
+    /// Wire format requested from `streamGenerateContent`.
+    pub stream_format: StreamFormat,
 }
 
 #[async_trait]
@@ -102,12 +214,12 @@ impl Relay for GeminiRelay {
         request: Self::Request,
     ) -> Result<Self::Response> {
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
+        if let Credentials::Vertex(_) = credentials {
+            return self.vertex_relay()?.relay(account, request).await;
+        }
+        let client = self.clients.client(account.proxy_config())?;
 
-        let token = match credentials {
-            Credentials::Bearer(t) => t,
-            Credentials::ApiKey(k) => k,
-        };
+        let token = bearer_token(credentials)?;
 
         let api_base = Self::get_api_base(account);
         let url = Self::build_url(&api_base, &request.model, false);
@@ -119,13 +231,13 @@ impl Relay for GeminiRelay {
             "Relaying non-streaming request to Gemini API"
         );
 
-        let response = client
+        let builder = client
             .post(&url)
             .header("Authorization", format!("Bearer {}", token))
             .header("Content-Type", "application/json")
-            .json(&request.body)
-            .send()
-            .await?;
+            .timeout(self.timeouts.for_request(account.id(), &request.model))
+            .json(&request.body);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
 
         if !response.status().is_success() {
             return Err(self.handle_error_response(response).await);
@@ -151,15 +263,19 @@ impl Relay for GeminiRelay {
         request: Self::Request,
     ) -> Result<BoxStream<Result<Bytes>>> {
         let credentials = account.get_credentials().await?;
-        let client = self.build_client(account.proxy_config())?;
+        if let Credentials::Vertex(_) = credentials {
+            return self.vertex_relay()?.relay_stream(account, request).await;
+        }
+        let client = self.clients.client(account.proxy_config())?;
 
-        let token = match credentials {
-            Credentials::Bearer(t) => t,
-            Credentials::ApiKey(k) => k,
-        };
+        let token = bearer_token(credentials)?;
 
         let api_base = Self::get_api_base(account);
-        let url = format!("{}?alt=sse", Self::build_url(&api_base, &request.model, true));
+        let url = format!(
+            "{}{}",
+            Self::build_url(&api_base, &request.model, true),
+            request.stream_format.query()
+        );
 
         debug!(
             account_id = account.id(),
@@ -168,35 +284,32 @@ impl Relay for GeminiRelay {
             "Relaying streaming request to Gemini API"
         );
 
-        let response = client
+        let builder = client
             .post(&url)
             .header("Authorization", format!("Bearer {}", token))
             .header("Content-Type", "application/json")
-            .json(&request.body)
-            .send()
-            .await?;
+            .timeout(self.timeouts.for_request(account.id(), &request.model))
+            .json(&request.body);
+        let response = send_captured(builder, self.capture.as_deref()).await?;
 
         if !response.status().is_success() {
             return Err(self.handle_error_response(response).await);
         }
 
         let account_id = account.id().to_string();
+        let stream_format = request.stream_format;
 
         let stream = try_stream! {
             let mut byte_stream = response.bytes_stream();
-            let mut total_usage = UsageMetadata::default();
+            let mut stream_usage = StreamUsage::new(stream_format);
 
             while let Some(chunk_result) = byte_stream.next().await {
                 let chunk = chunk_result?;
-
-                if let Some(usage) = extract_usage_from_chunk(&chunk) {
-                    total_usage.prompt_token_count = total_usage.prompt_token_count.max(usage.prompt_token_count);
-                    total_usage.candidates_token_count = total_usage.candidates_token_count.max(usage.candidates_token_count);
-                }
-
+                stream_usage.push(&chunk);
                 yield chunk;
             }
 
+            let total_usage = stream_usage.usage();
             if total_usage.prompt_token_count > 0 || total_usage.candidates_token_count > 0 {
                 info!(
                     account_id = account_id,
@@ -211,7 +324,7 @@ impl Relay for GeminiRelay {
     }
 }
 
-fn extract_usage_from_chunk(chunk: &Bytes) -> Option<UsageMetadata> {
+pub fn extract_usage_from_chunk(chunk: &Bytes) -> Option<UsageMetadata> {
     let text = std::str::from_utf8(chunk).ok()?;
 
     for line in text.lines() {
@@ -226,25 +339,60 @@ fn extract_usage_from_chunk(chunk: &Bytes) -> Option<UsageMetadata> {
 
         let value: serde_json::Value = serde_json::from_str(json_str).ok()?;
 
-        if let Some(usage) = value.get("usageMetadata") {
-            let prompt = usage
-                .get("promptTokenCount")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0) as u32;
-            let candidates = usage
-                .get("candidatesTokenCount")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0) as u32;
-
-            if prompt > 0 || candidates > 0 {
-                return Some(UsageMetadata {
-                    prompt_token_count: prompt,
-                    candidates_token_count: candidates,
-                    total_token_count: prompt + candidates,
-                });
-            }
+        if let Some(usage) = extract_usage_from_value(&value) {
+            return Some(usage);
         }
     }
 
     None
 }
+
+/// Usage of one `GenerateContentResponse`, if it reports any tokens.
+pub(crate) fn extract_usage_from_value(value: &serde_json::Value) -> Option<UsageMetadata> {
+    let usage = value.get("usageMetadata")?;
+    let prompt = usage
+        .get("promptTokenCount")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32;
+    let candidates = usage
+        .get("candidatesTokenCount")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32;
+
+    if prompt > 0 || candidates > 0 {
+        Some(UsageMetadata {
+            prompt_token_count: prompt,
+            candidates_token_count: candidates,
+            total_token_count: prompt + candidates,
+        })
+    } else {
+        None
+    }
+}
+
+fn bearer_token(credentials: Credentials) -> Result<String> {
+    match credentials {
+        Credentials::Bearer(t) => Ok(t),
+        Credentials::ApiKey(k) => Ok(k),
+        Credentials::AwsSigV4(_) => Err(RelayError::Config(
+            "Gemini accounts cannot use AWS credentials".to_string(),
+        )),
+        Credentials::AzureOpenAI(_) => Err(RelayError::Config(
+            "Gemini accounts cannot use Azure OpenAI credentials".to_string(),
+        )),
+        // Only generateContent requests are delegated to the Vertex relay
+        Credentials::Vertex(_) => Err(RelayError::Upstream {
+            status: 501,
+            message: "Not supported by Vertex AI accounts".to_string(),
+        }),
+    }
+}
+
+/// Whether a URL path segment names an API version, such as `v1` or
+/// `v1beta`.
+fn is_api_version(segment: &str) -> bool {
+    segment
+        .strip_prefix('v')
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_ascii_digit())
+}
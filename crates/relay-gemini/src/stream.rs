@@ -0,0 +1,165 @@
+use bytes::Bytes;
+use relay_core::SseLines;
+
+use crate::relay::extract_usage_from_value;
+use crate::types::{GenerateContentResponse, UsageMetadata};
+
+/// Wire format of a `streamGenerateContent` response, chosen by the `alt`
+/// query parameter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// `alt=sse`: one `data:` event per response.
+    #[default]
+    Sse,
+    /// No `alt` or `alt=json`: a single JSON array of responses, sent in
+    /// pieces that need not end on element boundaries.
+    Json,
+}
+
+impl StreamFormat {
+    /// `None` for `alt` values other than `sse` and `json`.
+    pub fn from_alt(alt: Option<&str>) -> Option<Self> {
+        match alt {
+            Some("sse") => Some(Self::Sse),
+            None | Some("json") => Some(Self::Json),
+            Some(_) => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Sse => "text/event-stream",
+            Self::Json => "application/json",
+        }
+    }
+
+    /// Query string selecting this format from `streamGenerateContent`.
+    pub fn query(self) -> &'static str {
+        match self {
+            Self::Sse => "?alt=sse",
+            Self::Json => "",
+        }
+    }
+
+    /// Encodes the `index`th response of a stream.
+    pub fn encode(self, chunk: &GenerateContentResponse, index: usize) -> String {
+        let json = serde_json::to_string(chunk).unwrap_or_default();
+        match self {
+            Self::Sse => format!("data: {}\n\n", json),
+            Self::Json if index == 0 => format!("[{}", json),
+            Self::Json => format!(",\r\n{}", json),
+        }
+    }
+
+    /// Terminates a stream of `count` responses.
+    pub fn end(self, count: usize) -> &'static str {
+        match self {
+            Self::Sse => "",
+            Self::Json if count == 0 => "[]",
+            Self::Json => "]",
+        }
+    }
+}
+
+/// Tracks the highest usage reported by a `streamGenerateContent` stream in
+/// either format. Events and array elements are reassembled across network
+/// reads, so usage split between chunks is still counted.
+pub struct StreamUsage {
+    format: StreamFormat,
+    usage: UsageMetadata,
+    /// `data:` lines being received (SSE format only).
+    lines: SseLines,
+    /// Bytes of the array element being received (JSON format only).
+    element: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl StreamUsage {
+    pub fn new(format: StreamFormat) -> Self {
+        Self {
+            format,
+            usage: UsageMetadata::default(),
+            lines: SseLines::new(),
+            element: Vec::new(),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &Bytes) {
+        match self.format {
+            StreamFormat::Sse => {
+                for data in self.lines.push(chunk) {
+                    if let Some(usage) = serde_json::from_str(&data)
+                        .ok()
+                        .and_then(|value| extract_usage_from_value(&value))
+                    {
+                        self.update(usage);
+                    }
+                }
+            }
+            StreamFormat::Json => {
+                for &byte in chunk.iter() {
+                    self.push_json_byte(byte);
+                }
+            }
+        }
+    }
+
+    pub fn usage(&self) -> &UsageMetadata {
+        &self.usage
+    }
+
+    /// Collects the bytes of each top-level element of the array and reads
+    /// its usage once the element is complete.
+    fn push_json_byte(&mut self, byte: u8) {
+        if self.depth >= 2 {
+            self.element.push(byte);
+        }
+        if self.in_string {
+            match byte {
+                _ if self.escaped => self.escaped = false,
+                b'\\' => self.escaped = true,
+                b'"' => self.in_string = false,
+                _ => {}
+            }
+            return;
+        }
+
+        match byte {
+            b'"' => self.in_string = true,
+            b'{' | b'[' => {
+                self.depth += 1;
+                if self.depth == 2 {
+                    self.element.push(byte);
+                }
+            }
+            b'}' | b']' => {
+                self.depth = self.depth.saturating_sub(1);
+                if self.depth == 1 {
+                    let element = std::mem::take(&mut self.element);
+                    if let Some(usage) = serde_json::from_slice(&element)
+                        .ok()
+                        .and_then(|value| extract_usage_from_value(&value))
+                    {
+                        self.update(usage);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, usage: UsageMetadata) {
+        self.usage.prompt_token_count = self.usage.prompt_token_count.max(usage.prompt_token_count);
+        self.usage.candidates_token_count = self
+            .usage
+            .candidates_token_count
+            .max(usage.candidates_token_count);
+        self.usage.total_token_count =
+            self.usage.prompt_token_count + self.usage.candidates_token_count;
+    }
+}
@@ -0,0 +1,120 @@
+use relay_core::{RelayError, Result};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Tool keys clients may send in snake_case, with the camelCase name the
+/// API documents.
+const TOOL_KEYS: &[(&str, &str)] = &[
+    ("function_declarations", "functionDeclarations"),
+    ("google_search", "googleSearch"),
+    ("google_search_retrieval", "googleSearchRetrieval"),
+    ("code_execution", "codeExecution"),
+    ("url_context", "urlContext"),
+];
+
+/// Phrases in an upstream 400's message that show it is about the tools.
+const TOOL_ERROR_MARKERS: &[&str] = &[
+    "tools[",
+    "function_declarations",
+    "function declaration",
+    "tool use",
+    "google_search",
+    "grounding",
+];
+
+/// Longest function name the API accepts.
+const MAX_FUNCTION_NAME_LEN: usize = 64;
+
+/// Renames snake_case tool keys to camelCase and checks each tool before it
+/// is relayed, so a malformed declaration fails here with the offending
+/// tool named instead of as an opaque upstream 400. Tools this relay does
+/// not know are passed through for the upstream to judge.
+pub fn normalize_tools(tools: &mut [Value]) -> Result<()> {
+    let mut names = HashSet::new();
+
+    for (index, tool) in tools.iter_mut().enumerate() {
+        let Some(entry) = tool.as_object_mut() else {
+            return Err(invalid(index, "must be an object"));
+        };
+        if entry.is_empty() {
+            return Err(invalid(index, "declares no tool"));
+        }
+        for (snake, camel) in TOOL_KEYS {
+            if let Some(value) = entry.remove(*snake) {
+                if entry.contains_key(*camel) {
+                    return Err(invalid(
+                        index,
+                        &format!("sets both {} and {}", snake, camel),
+                    ));
+                }
+                entry.insert(camel.to_string(), value);
+            }
+        }
+
+        for grounding in ["googleSearch", "googleSearchRetrieval"] {
+            if entry
+                .get(grounding)
+                .is_some_and(|config| !config.is_object())
+            {
+                return Err(invalid(index, &format!("{} must be an object", grounding)));
+            }
+        }
+
+        let Some(declarations) = entry.get("functionDeclarations") else {
+            continue;
+        };
+        let Some(declarations) = declarations.as_array() else {
+            return Err(invalid(index, "functionDeclarations must be an array"));
+        };
+        for declaration in declarations {
+            let name = declaration
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid(index, "function declaration has no name"))?;
+            if !is_valid_function_name(name) {
+                return Err(invalid(index, &format!("invalid function name {:?}", name)));
+            }
+            if !names.insert(name.to_string()) {
+                return Err(invalid(
+                    index,
+                    &format!("function {:?} is declared more than once", name),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An upstream error about the request's tools, such as an invalid
+/// function schema or grounding the model does not support, as
+/// [`RelayError::InvalidTool`] carrying the upstream message.
+pub fn upstream_tool_error(status: u16, body: &str) -> Option<RelayError> {
+    if status != 400 {
+        return None;
+    }
+    let message = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|value| value["error"]["message"].as_str().map(str::to_string))?;
+
+    let lower = message.to_lowercase();
+    TOOL_ERROR_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+        .then(|| RelayError::InvalidTool(message))
+}
+
+/// Names start with a letter or underscore and contain only letters,
+/// digits, underscores, dots and dashes.
+fn is_valid_function_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    name.len() <= MAX_FUNCTION_NAME_LEN
+        && (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+fn invalid(index: usize, reason: &str) -> RelayError {
+    RelayError::InvalidTool(format!("tools[{}] {}", index, reason))
+}
@@ -15,23 +15,37 @@ pub struct GenerateContentRequest {
     pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Content {
+    #[serde(default)]
     pub role: String,
+    #[serde(default)]
     pub parts: Vec<Part>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Part {
-    Text { text: String },
-    InlineData { inline_data: Blob },
-    FunctionCall { function_call: FunctionCall },
-    FunctionResponse { function_response: FunctionResponse },
+    Text {
+        text: String,
+    },
+    InlineData {
+        #[serde(alias = "inlineData")]
+        inline_data: Blob,
+    },
+    FunctionCall {
+        #[serde(alias = "functionCall")]
+        function_call: FunctionCall,
+    },
+    FunctionResponse {
+        #[serde(alias = "functionResponse")]
+        function_response: FunctionResponse,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Blob {
+    #[serde(alias = "mimeType")]
     pub mime_type: String,
     pub data: String,
 }
@@ -48,7 +62,7 @@ pub struct FunctionResponse {
     pub response: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -74,6 +88,7 @@ pub struct SafetySetting {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerateContentResponse {
+    #[serde(default)]
     pub candidates: Vec<Candidate>,
     #[serde(default)]
     pub usage_metadata: Option<UsageMetadata>,
@@ -84,6 +99,7 @@ pub struct GenerateContentResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Candidate {
+    #[serde(default)]
     pub content: Content,
     #[serde(default)]
     pub finish_reason: Option<String>,
@@ -107,3 +123,41 @@ pub struct UsageMetadata {
     #[serde(default)]
     pub total_token_count: u32,
 }
+
+/// `models/*:embedContent` request; also one entry of a batch request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedContentRequest {
+    /// `models/{model}`, required inside `batchEmbedContents`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub content: Content,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dimensionality: Option<u32>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedContentResponse {
+    pub embedding: ContentEmbedding,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchEmbedContentsRequest {
+    pub requests: Vec<EmbedContentRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchEmbedContentsResponse {
+    pub embeddings: Vec<ContentEmbedding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentEmbedding {
+    pub values: Vec<f32>,
+}
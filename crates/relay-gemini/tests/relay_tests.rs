@@ -1,4 +1,10 @@
-use relay_gemini::GeminiRelay;
+use bytes::Bytes;
+use relay_core::RelayError;
+use relay_gemini::{
+    extract_usage_from_chunk, normalize_tools, upstream_tool_error, BatchEmbedContentsResponse,
+    EmbedContentRequest, GeminiRelay, GenerateContentResponse, StreamFormat, StreamUsage,
+};
+use serde_json::json;
 
 #[test]
 fn test_api_base_uses_cloudcode() {
@@ -14,3 +20,190 @@ fn test_api_base_uses_cloudcode() {
         "Should NOT use generativelanguage.googleapis.com"
     );
 }
+
+#[test]
+fn test_extract_usage_from_sse_chunk() {
+    let chunk = Bytes::from(
+        r#"data: {"candidates":[],"usageMetadata":{"promptTokenCount":12,"candidatesTokenCount":34,"totalTokenCount":46}}
+
+"#,
+    );
+
+    let usage = extract_usage_from_chunk(&chunk).expect("Should extract usage");
+
+    assert_eq!(usage.prompt_token_count, 12);
+    assert_eq!(usage.candidates_token_count, 34);
+}
+
+#[test]
+fn test_extract_usage_ignores_chunk_without_metadata() {
+    let chunk = Bytes::from("data: {\"candidates\":[]}\n\n");
+
+    assert!(extract_usage_from_chunk(&chunk).is_none());
+}
+
+#[test]
+fn test_stream_format_from_alt() {
+    assert_eq!(StreamFormat::from_alt(Some("sse")), Some(StreamFormat::Sse));
+    assert_eq!(
+        StreamFormat::from_alt(Some("json")),
+        Some(StreamFormat::Json)
+    );
+    assert_eq!(StreamFormat::from_alt(None), Some(StreamFormat::Json));
+    assert_eq!(StreamFormat::from_alt(Some("proto")), None);
+}
+
+#[test]
+fn test_stream_usage_from_sse_split_mid_event() {
+    let body = "data: {\"candidates\":[]}\r\n\r\ndata: {\"candidates\":[],\"usageMetadata\":{\"promptTokenCount\":12,\"candidatesTokenCount\":34}}\r\n\r\n";
+    let mut usage = StreamUsage::new(StreamFormat::Sse);
+
+    for piece in body.as_bytes().chunks(7) {
+        usage.push(&Bytes::copy_from_slice(piece));
+    }
+
+    assert_eq!(usage.usage().prompt_token_count, 12);
+    assert_eq!(usage.usage().candidates_token_count, 34);
+}
+
+#[test]
+fn test_stream_usage_from_json_array_split_mid_element() {
+    let body = r#"[{"candidates":[{"content":{"parts":[{"text":"a } \" ]"}]}}]}
+,
+{"candidates":[],"usageMetadata":{"promptTokenCount":12,"candidatesTokenCount":34,"totalTokenCount":46}}]"#;
+    let mut usage = StreamUsage::new(StreamFormat::Json);
+
+    for piece in body.as_bytes().chunks(7) {
+        usage.push(&Bytes::copy_from_slice(piece));
+    }
+
+    assert_eq!(usage.usage().prompt_token_count, 12);
+    assert_eq!(usage.usage().candidates_token_count, 34);
+}
+
+#[test]
+fn test_stream_format_json_encodes_an_array() {
+    let chunk: GenerateContentResponse = serde_json::from_str(r#"{"candidates":[]}"#).unwrap();
+    let format = StreamFormat::Json;
+
+    let body = format!(
+        "{}{}{}",
+        format.encode(&chunk, 0),
+        format.encode(&chunk, 1),
+        format.end(2)
+    );
+
+    let parsed: Vec<GenerateContentResponse> = serde_json::from_str(&body).unwrap();
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(format.end(0), "[]");
+}
+
+#[test]
+fn test_embed_content_request_uses_camel_case() {
+    let request: EmbedContentRequest = serde_json::from_str(
+        r#"{"content":{"parts":[{"text":"hello"}]},"taskType":"RETRIEVAL_QUERY","outputDimensionality":256}"#,
+    )
+    .unwrap();
+
+    assert_eq!(request.task_type.as_deref(), Some("RETRIEVAL_QUERY"));
+    assert_eq!(request.output_dimensionality, Some(256));
+
+    let json = serde_json::to_value(&request).unwrap();
+    assert_eq!(json["taskType"], "RETRIEVAL_QUERY");
+    assert_eq!(json["outputDimensionality"], 256);
+    assert!(json.get("model").is_none());
+}
+
+#[test]
+fn test_batch_embed_contents_response_parses() {
+    let response: BatchEmbedContentsResponse =
+        serde_json::from_str(r#"{"embeddings":[{"values":[0.5,-1.0]},{"values":[]}]}"#).unwrap();
+
+    assert_eq!(response.embeddings.len(), 2);
+    assert_eq!(response.embeddings[0].values, vec![0.5, -1.0]);
+}
+
+#[test]
+fn test_api_base_versions() {
+    assert_eq!(
+        GeminiRelay::api_base(None, None),
+        "https://cloudcode.googleapis.com/v1"
+    );
+    assert_eq!(
+        GeminiRelay::api_base(None, Some("v1beta")),
+        "https://cloudcode.googleapis.com/v1beta"
+    );
+    assert_eq!(
+        GeminiRelay::api_base(Some("https://example.com/"), None),
+        "https://example.com/v1"
+    );
+    assert_eq!(
+        GeminiRelay::api_base(Some("https://example.com/v1beta"), None),
+        "https://example.com/v1beta"
+    );
+    assert_eq!(
+        GeminiRelay::api_base(Some("https://example.com/v1"), Some("v1beta")),
+        "https://example.com/v1beta"
+    );
+}
+
+#[test]
+fn test_normalize_tools_renames_snake_case_keys() {
+    let mut tools = vec![
+        json!({"function_declarations": [{"name": "get_weather", "parameters": {"type": "object"}}]}),
+        json!({"google_search": {}}),
+        json!({"codeExecution": {}}),
+    ];
+
+    normalize_tools(&mut tools).unwrap();
+
+    assert_eq!(tools[0]["functionDeclarations"][0]["name"], "get_weather");
+    assert!(tools[0].get("function_declarations").is_none());
+    assert_eq!(tools[1], json!({"googleSearch": {}}));
+    assert_eq!(tools[2], json!({"codeExecution": {}}));
+}
+
+#[test]
+fn test_normalize_tools_rejects_invalid_declarations() {
+    let rejected = |tools: serde_json::Value| {
+        let mut tools = tools.as_array().unwrap().clone();
+        match normalize_tools(&mut tools) {
+            Err(RelayError::InvalidTool(message)) => message,
+            other => panic!("expected InvalidTool, got {:?}", other),
+        }
+    };
+
+    assert_eq!(rejected(json!([{}])), "tools[0] declares no tool");
+    assert_eq!(
+        rejected(json!([{"googleSearch": true}])),
+        "tools[0] googleSearch must be an object"
+    );
+    assert_eq!(
+        rejected(json!([{"functionDeclarations": [{"description": "no name"}]}])),
+        "tools[0] function declaration has no name"
+    );
+    assert_eq!(
+        rejected(json!([{"functionDeclarations": [{"name": "get weather"}]}])),
+        "tools[0] invalid function name \"get weather\""
+    );
+    assert_eq!(
+        rejected(json!([
+            {"functionDeclarations": [{"name": "lookup"}]},
+            {"function_declarations": [{"name": "lookup"}]}
+        ])),
+        "tools[1] function \"lookup\" is declared more than once"
+    );
+}
+
+#[test]
+fn test_upstream_tool_error() {
+    let body = r#"{"error":{"code":400,"message":"* GenerateContentRequest.tools[0].function_declarations[0].parameters.properties: should be non-empty for OBJECT type","status":"INVALID_ARGUMENT"}}"#;
+    assert!(matches!(
+        upstream_tool_error(400, body),
+        Some(RelayError::InvalidTool(message)) if message.starts_with("* GenerateContentRequest.tools[0]")
+    ));
+
+    let unrelated = r#"{"error":{"code":400,"message":"Request contains an invalid argument.","status":"INVALID_ARGUMENT"}}"#;
+    assert!(upstream_tool_error(400, unrelated).is_none());
+    assert!(upstream_tool_error(500, body).is_none());
+}
@@ -1,11 +1,15 @@
 use relay_claude::{Message, MessagesRequest, MessagesResponse};
 use relay_core::RelayError;
+use tracing::warn;
 
 use crate::types::*;
 
+/// OpenAI sampling parameters the Messages API has no equivalent for.
+const UNSUPPORTED_PARAMETERS: &[&str] = &["frequency_penalty", "presence_penalty", "logit_bias"];
+
 pub struct OpenAIToClaudeConverter;
 
-const CLAUDE_CODE_SYSTEM_PROMPT: &str =
+pub(crate) const CLAUDE_CODE_SYSTEM_PROMPT: &str =
     "You are Claude Code, Anthropic's official CLI for Claude.";
 
 impl OpenAIToClaudeConverter {
@@ -74,10 +78,38 @@ impl OpenAIToClaudeConverter {
                 .collect()
         });
 
+        for key in UNSUPPORTED_PARAMETERS {
+            if req.extra.contains_key(*key) {
+                warn!(
+                    parameter = key,
+                    "Claude does not support this OpenAI parameter, ignoring it"
+                );
+            }
+        }
+
+        let max_tokens = req.max_tokens.or_else(|| {
+            req.extra
+                .get("max_completion_tokens")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+        });
+
+        let mut extra = serde_json::Map::new();
+        if let Some(stop) = req.stop {
+            let stop_sequences = match stop {
+                StopSequence::Single(s) => vec![s],
+                StopSequence::Multiple(v) => v,
+            };
+            extra.insert(
+                "stop_sequences".to_string(),
+                serde_json::json!(stop_sequences),
+            );
+        }
+
         Ok(MessagesRequest {
             model: req.model.clone(),
             messages,
-            max_tokens: req.max_tokens.unwrap_or(4096),
+            max_tokens: max_tokens.unwrap_or(4096),
             stream: req.stream,
             system,
             temperature: req.temperature,
@@ -86,7 +118,7 @@ impl OpenAIToClaudeConverter {
             metadata: None,
             tools,
             tool_choice: req.tool_choice,
-            extra: serde_json::Map::new(),
+            extra,
         })
     }
 
@@ -172,8 +204,14 @@ impl OpenAIToClaudeConverter {
         Some((media_type.to_string(), data.to_string()))
     }
 
-    pub fn convert_response(resp: MessagesResponse) -> ChatCompletionResponse {
+    /// With `include_reasoning`, `thinking` blocks are returned as
+    /// `reasoning_content`; otherwise they are dropped.
+    pub fn convert_response(
+        resp: MessagesResponse,
+        include_reasoning: bool,
+    ) -> ChatCompletionResponse {
         let mut content: Option<String> = None;
+        let mut reasoning_content: Option<String> = None;
         let mut tool_calls: Vec<ToolCall> = Vec::new();
 
         // Handle content as serde_json::Value for full passthrough compatibility
@@ -186,6 +224,13 @@ impl OpenAIToClaudeConverter {
                                 content = Some(text.to_string());
                             }
                         }
+                        "thinking" if include_reasoning => {
+                            if let Some(thinking) = block.get("thinking").and_then(|t| t.as_str()) {
+                                reasoning_content
+                                    .get_or_insert_with(String::new)
+                                    .push_str(thinking);
+                            }
+                        }
                         "tool_use" => {
                             let id = block
                                 .get("id")
@@ -207,19 +252,13 @@ impl OpenAIToClaudeConverter {
                                 },
                             });
                         }
-                        _ => {} // Ignore other content types (thinking, etc.)
+                        _ => {} // Ignore other content types (redacted thinking, etc.)
                     }
                 }
             }
         }
 
-        let finish_reason = resp.stop_reason.as_deref().map(|r| match r {
-            "end_turn" => "stop",
-            "max_tokens" => "length",
-            "tool_use" => "tool_calls",
-            "stop_sequence" => "stop",
-            _ => "stop",
-        });
+        let finish_reason = resp.stop_reason.as_deref().map(finish_reason);
 
         ChatCompletionResponse {
             id: resp.id,
@@ -234,6 +273,7 @@ impl OpenAIToClaudeConverter {
                 message: ResponseMessage {
                     role: "assistant".to_string(),
                     content,
+                    reasoning_content,
                     tool_calls: if tool_calls.is_empty() {
                         None
                     } else {
@@ -250,3 +290,14 @@ impl OpenAIToClaudeConverter {
         }
     }
 }
+
+/// OpenAI `finish_reason` for an Anthropic `stop_reason`.
+pub(crate) fn finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "end_turn" => "stop",
+        "max_tokens" => "length",
+        "tool_use" => "tool_calls",
+        "stop_sequence" => "stop",
+        _ => "stop",
+    }
+}
@@ -1,5 +1,11 @@
 mod converter;
+mod responses;
+mod responses_stream;
+mod stream;
 pub mod types;
 
 pub use converter::OpenAIToClaudeConverter;
+pub use responses::ResponsesToClaudeConverter;
+pub use responses_stream::ResponsesStreamConverter;
+pub use stream::{StreamConverter, DONE_EVENT};
 pub use types::*;
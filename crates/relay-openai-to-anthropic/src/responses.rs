@@ -0,0 +1,347 @@
+use relay_claude::{Message, MessagesRequest, MessagesResponse};
+use relay_core::RelayError;
+use serde_json::{json, Value};
+
+use crate::converter::CLAUDE_CODE_SYSTEM_PROMPT;
+
+/// Codex CLI sends no `max_output_tokens` and writes whole files in tool
+/// calls, so the default is well above the chat completions one.
+const DEFAULT_MAX_TOKENS: u32 = 32000;
+
+/// Converts OpenAI Responses API requests into Anthropic Messages requests
+/// and Messages responses back into Responses objects.
+pub struct ResponsesToClaudeConverter;
+
+impl ResponsesToClaudeConverter {
+    /// `model` replaces the requested one, which is usually an OpenAI model.
+    pub fn convert_request(request: &Value, model: &str) -> Result<MessagesRequest, RelayError> {
+        let mut system = vec![json!({"type": "text", "text": CLAUDE_CODE_SYSTEM_PROMPT})];
+        if let Some(instructions) = request.get("instructions").and_then(|v| v.as_str()) {
+            if !instructions.is_empty() {
+                system.push(json!({"type": "text", "text": instructions}));
+            }
+        }
+
+        let mut messages: Vec<Message> = Vec::new();
+        match request.get("input") {
+            Some(Value::String(text)) => {
+                push_block(&mut messages, "user", json!({"type": "text", "text": text}));
+            }
+            Some(Value::Array(items)) => {
+                for item in items {
+                    Self::convert_item(item, &mut system, &mut messages);
+                }
+            }
+            _ => {
+                return Err(RelayError::InvalidRequest(
+                    "input must be a string or an array of items".to_string(),
+                ))
+            }
+        }
+
+        let tools: Vec<Value> = request
+            .get("tools")
+            .and_then(|t| t.as_array())
+            .map(|tools| {
+                tools
+                    .iter()
+                    .filter(|t| t.get("type").and_then(|v| v.as_str()) == Some("function"))
+                    .map(|t| {
+                        json!({
+                            "name": t["name"],
+                            "description": t.get("description").cloned().unwrap_or(Value::Null),
+                            "input_schema": t.get("parameters").cloned().unwrap_or(json!({"type": "object", "properties": {}}))
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let tool_choice = if tools.is_empty() {
+            None
+        } else {
+            request
+                .get("tool_choice")
+                .and_then(Self::convert_tool_choice)
+        };
+
+        Ok(MessagesRequest {
+            model: model.to_string(),
+            messages,
+            max_tokens: request
+                .get("max_output_tokens")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+                .unwrap_or(DEFAULT_MAX_TOKENS),
+            stream: request
+                .get("stream")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            system: Some(Value::Array(system)),
+            temperature: request
+                .get("temperature")
+                .and_then(|v| v.as_f64())
+                .map(|v| v as f32),
+            top_p: request
+                .get("top_p")
+                .and_then(|v| v.as_f64())
+                .map(|v| v as f32),
+            top_k: None,
+            metadata: None,
+            tools: if tools.is_empty() { None } else { Some(tools) },
+            tool_choice,
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    fn convert_item(item: &Value, system: &mut Vec<Value>, messages: &mut Vec<Message>) {
+        let item_type = item
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("message");
+
+        match item_type {
+            "message" => {
+                let role = item.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+                let blocks = Self::convert_message_content(item.get("content"));
+                match role {
+                    "system" | "developer" => {
+                        system.extend(blocks.into_iter().filter(|b| b["type"] == "text"));
+                    }
+                    "user" | "assistant" => {
+                        for block in blocks {
+                            push_block(messages, role, block);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "function_call" => {
+                let arguments = item
+                    .get("arguments")
+                    .and_then(|a| a.as_str())
+                    .unwrap_or("{}");
+                let input: Value = serde_json::from_str(arguments).unwrap_or(json!({}));
+                push_block(
+                    messages,
+                    "assistant",
+                    json!({
+                        "type": "tool_use",
+                        "id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                        "name": item.get("name").cloned().unwrap_or(Value::Null),
+                        "input": input
+                    }),
+                );
+            }
+            "function_call_output" => {
+                let output = match item.get("output") {
+                    Some(Value::String(text)) => text.clone(),
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                };
+                push_block(
+                    messages,
+                    "user",
+                    json!({
+                        "type": "tool_result",
+                        "tool_use_id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                        "content": output
+                    }),
+                );
+            }
+            // Reasoning and built-in tool items have no Anthropic equivalent
+            _ => {}
+        }
+    }
+
+    fn convert_message_content(content: Option<&Value>) -> Vec<Value> {
+        match content {
+            Some(Value::String(text)) => vec![json!({"type": "text", "text": text})],
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|part| match part.get("type").and_then(|t| t.as_str())? {
+                    "input_text" | "output_text" | "text" => {
+                        let text = part.get("text").and_then(|t| t.as_str())?;
+                        Some(json!({"type": "text", "text": text}))
+                    }
+                    "input_image" => {
+                        let url = part.get("image_url").and_then(|u| u.as_str())?;
+                        Some(image_block(url))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn convert_tool_choice(choice: &Value) -> Option<Value> {
+        match choice {
+            Value::String(mode) => match mode.as_str() {
+                "auto" => Some(json!({"type": "auto"})),
+                "required" => Some(json!({"type": "any"})),
+                "none" => Some(json!({"type": "none"})),
+                _ => None,
+            },
+            Value::Object(_) if choice["type"] == "function" => {
+                Some(json!({"type": "tool", "name": choice["name"]}))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn convert_response(resp: MessagesResponse) -> Value {
+        let response_id = response_id(&resp.id);
+        let mut output = Vec::new();
+        let mut text_parts = Vec::new();
+
+        if let Some(blocks) = resp.content.as_array() {
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        let text = block.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                        text_parts.push(output_text(text));
+                    }
+                    Some("tool_use") => {
+                        let call_id = block.get("id").and_then(|v| v.as_str()).unwrap_or("");
+                        let name = block.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                        let input = block.get("input").cloned().unwrap_or(json!({}));
+                        output.push(function_call_item(
+                            call_id,
+                            name,
+                            &input.to_string(),
+                            "completed",
+                        ));
+                    }
+                    _ => {} // Thinking blocks are not returned
+                }
+            }
+        }
+
+        if !text_parts.is_empty() {
+            output.insert(
+                0,
+                message_item(&format!("msg_{}", response_id), text_parts, "completed"),
+            );
+        }
+
+        response_object(
+            &response_id,
+            now(),
+            &resp.model,
+            resp.stop_reason.as_deref(),
+            output,
+            Some(usage_object(
+                resp.usage.input_tokens,
+                resp.usage.output_tokens,
+                resp.usage.cache_read_input_tokens.unwrap_or(0),
+            )),
+        )
+    }
+}
+
+/// Appends a content block, merging it into the previous message when the
+/// role matches so tool calls and their results stay in single turns.
+fn push_block(messages: &mut Vec<Message>, role: &str, block: Value) {
+    if let Some(last) = messages.last_mut() {
+        if last.role == role {
+            if let Some(blocks) = last.content.as_array_mut() {
+                blocks.push(block);
+                return;
+            }
+        }
+    }
+    messages.push(Message {
+        role: role.to_string(),
+        content: Value::Array(vec![block]),
+    });
+}
+
+fn image_block(url: &str) -> Value {
+    if let Some((metadata, data)) = url.strip_prefix("data:").and_then(|u| u.split_once(',')) {
+        let media_type = metadata.split(';').next().unwrap_or_default();
+        json!({
+            "type": "image",
+            "source": {"type": "base64", "media_type": media_type, "data": data}
+        })
+    } else {
+        json!({"type": "image", "source": {"type": "url", "url": url}})
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub(crate) fn response_id(message_id: &str) -> String {
+    format!("resp_{}", message_id.trim_start_matches("msg_"))
+}
+
+pub(crate) fn output_text(text: &str) -> Value {
+    json!({"type": "output_text", "text": text, "annotations": []})
+}
+
+pub(crate) fn message_item(id: &str, content: Vec<Value>, status: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "message",
+        "status": status,
+        "role": "assistant",
+        "content": content
+    })
+}
+
+pub(crate) fn function_call_item(
+    call_id: &str,
+    name: &str,
+    arguments: &str,
+    status: &str,
+) -> Value {
+    json!({
+        "id": format!("fc_{}", call_id),
+        "type": "function_call",
+        "status": status,
+        "call_id": call_id,
+        "name": name,
+        "arguments": arguments
+    })
+}
+
+pub(crate) fn usage_object(input_tokens: u32, output_tokens: u32, cached_tokens: u32) -> Value {
+    json!({
+        "input_tokens": input_tokens,
+        "input_tokens_details": {"cached_tokens": cached_tokens},
+        "output_tokens": output_tokens,
+        "output_tokens_details": {"reasoning_tokens": 0},
+        "total_tokens": input_tokens + output_tokens
+    })
+}
+
+/// A Responses `response` object. `stop_reason` is `None` while in progress.
+pub(crate) fn response_object(
+    id: &str,
+    created_at: u64,
+    model: &str,
+    stop_reason: Option<&str>,
+    output: Vec<Value>,
+    usage: Option<Value>,
+) -> Value {
+    let (status, incomplete_details) = match stop_reason {
+        None => ("in_progress", Value::Null),
+        Some("max_tokens") => ("incomplete", json!({"reason": "max_output_tokens"})),
+        Some(_) => ("completed", Value::Null),
+    };
+    json!({
+        "id": id,
+        "object": "response",
+        "created_at": created_at,
+        "status": status,
+        "incomplete_details": incomplete_details,
+        "model": model,
+        "output": output,
+        "usage": usage
+    })
+}
@@ -0,0 +1,328 @@
+use relay_core::SseLines;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::responses::{
+    function_call_item, message_item, now, output_text, response_id, response_object, usage_object,
+};
+
+enum OpenItem {
+    Message {
+        text: String,
+    },
+    FunctionCall {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+}
+
+struct OpenBlock {
+    output_index: usize,
+    item_id: String,
+    item: OpenItem,
+}
+
+/// Translates an Anthropic Messages SSE byte stream into OpenAI Responses
+/// stream events (`response.created`, `response.output_text.delta`, ...,
+/// `response.completed`).
+///
+/// Text blocks become `message` output items and tool use blocks become
+/// `function_call` items; thinking blocks are dropped.
+pub struct ResponsesStreamConverter {
+    lines: SseLines,
+    created: u64,
+    sequence: u64,
+    response_id: String,
+    model: String,
+    blocks: HashMap<u64, OpenBlock>,
+    output: Vec<Value>,
+    input_tokens: u32,
+    output_tokens: u32,
+    cached_tokens: u32,
+    stop_reason: Option<String>,
+}
+
+impl ResponsesStreamConverter {
+    pub fn new() -> Self {
+        Self::with_created(now())
+    }
+
+    /// Uses a fixed `created_at` timestamp for the response.
+    pub fn with_created(created: u64) -> Self {
+        Self {
+            lines: SseLines::new(),
+            created,
+            sequence: 0,
+            response_id: String::new(),
+            model: String::new(),
+            blocks: HashMap::new(),
+            output: Vec::new(),
+            input_tokens: 0,
+            output_tokens: 0,
+            cached_tokens: 0,
+            stop_reason: None,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Value> {
+        let mut events = Vec::new();
+        for data in self.lines.push(bytes) {
+            if let Ok(value) = serde_json::from_str::<Value>(&data) {
+                self.convert_event(&value, &mut events);
+            }
+        }
+        events
+    }
+
+    pub fn encode(event: &Value) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            event["type"].as_str().unwrap_or_default(),
+            serde_json::to_string(event).unwrap_or_default()
+        )
+    }
+
+    fn convert_event(&mut self, event: &Value, events: &mut Vec<Value>) {
+        let Some(event_type) = event.get("type").and_then(|t| t.as_str()) else {
+            return;
+        };
+
+        match event_type {
+            "message_start" => {
+                let message = &event["message"];
+                self.response_id = response_id(message["id"].as_str().unwrap_or_default());
+                self.model = message["model"].as_str().unwrap_or_default().to_string();
+                let usage = &message["usage"];
+                self.input_tokens = token_count(&usage["input_tokens"]);
+                self.cached_tokens = token_count(&usage["cache_read_input_tokens"]);
+                self.output_tokens = token_count(&usage["output_tokens"]);
+
+                let response = self.response(None);
+                self.emit(events, "response.created", json!({"response": response}));
+                let response = self.response(None);
+                self.emit(
+                    events,
+                    "response.in_progress",
+                    json!({"response": response}),
+                );
+            }
+            "content_block_start" => {
+                let index = event["index"].as_u64().unwrap_or_default();
+                let block = &event["content_block"];
+                let output_index = self.output.len() + self.blocks.len();
+                let open = match block["type"].as_str() {
+                    Some("text") => OpenBlock {
+                        output_index,
+                        item_id: format!("msg_{}_{}", self.response_id, output_index),
+                        item: OpenItem::Message {
+                            text: String::new(),
+                        },
+                    },
+                    Some("tool_use") => {
+                        let call_id = block["id"].as_str().unwrap_or_default().to_string();
+                        OpenBlock {
+                            output_index,
+                            item_id: format!("fc_{}", call_id),
+                            item: OpenItem::FunctionCall {
+                                call_id,
+                                name: block["name"].as_str().unwrap_or_default().to_string(),
+                                arguments: String::new(),
+                            },
+                        }
+                    }
+                    _ => return,
+                };
+
+                let item = open.item_value("in_progress");
+                self.emit(
+                    events,
+                    "response.output_item.added",
+                    json!({"output_index": output_index, "item": item}),
+                );
+                if let OpenItem::Message { .. } = open.item {
+                    self.emit(
+                        events,
+                        "response.content_part.added",
+                        json!({
+                            "item_id": open.item_id,
+                            "output_index": output_index,
+                            "content_index": 0,
+                            "part": output_text("")
+                        }),
+                    );
+                }
+                self.blocks.insert(index, open);
+            }
+            "content_block_delta" => {
+                let index = event["index"].as_u64().unwrap_or_default();
+                let delta = &event["delta"];
+                let Some(open) = self.blocks.get_mut(&index) else {
+                    return;
+                };
+                let (output_index, item_id) = (open.output_index, open.item_id.clone());
+
+                let (delta_type, payload) = match (&mut open.item, delta["type"].as_str()) {
+                    (OpenItem::Message { text }, Some("text_delta")) => {
+                        let chunk = delta["text"].as_str().unwrap_or_default();
+                        text.push_str(chunk);
+                        (
+                            "response.output_text.delta",
+                            json!({
+                                "item_id": item_id,
+                                "output_index": output_index,
+                                "content_index": 0,
+                                "delta": chunk
+                            }),
+                        )
+                    }
+                    (OpenItem::FunctionCall { arguments, .. }, Some("input_json_delta")) => {
+                        let chunk = delta["partial_json"].as_str().unwrap_or_default();
+                        if chunk.is_empty() {
+                            return;
+                        }
+                        arguments.push_str(chunk);
+                        (
+                            "response.function_call_arguments.delta",
+                            json!({
+                                "item_id": item_id,
+                                "output_index": output_index,
+                                "delta": chunk
+                            }),
+                        )
+                    }
+                    _ => return,
+                };
+                self.emit(events, delta_type, payload);
+            }
+            "content_block_stop" => {
+                let index = event["index"].as_u64().unwrap_or_default();
+                let Some(open) = self.blocks.remove(&index) else {
+                    return;
+                };
+
+                match &open.item {
+                    OpenItem::Message { text } => {
+                        self.emit(
+                            events,
+                            "response.output_text.done",
+                            json!({
+                                "item_id": open.item_id,
+                                "output_index": open.output_index,
+                                "content_index": 0,
+                                "text": text
+                            }),
+                        );
+                        self.emit(
+                            events,
+                            "response.content_part.done",
+                            json!({
+                                "item_id": open.item_id,
+                                "output_index": open.output_index,
+                                "content_index": 0,
+                                "part": output_text(text)
+                            }),
+                        );
+                    }
+                    OpenItem::FunctionCall { arguments, .. } => {
+                        self.emit(
+                            events,
+                            "response.function_call_arguments.done",
+                            json!({
+                                "item_id": open.item_id,
+                                "output_index": open.output_index,
+                                "arguments": arguments
+                            }),
+                        );
+                    }
+                }
+
+                let item = open.item_value("completed");
+                self.emit(
+                    events,
+                    "response.output_item.done",
+                    json!({"output_index": open.output_index, "item": item}),
+                );
+                self.output.push(item);
+            }
+            "message_delta" => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(output_tokens) = event["usage"]["output_tokens"].as_u64() {
+                    self.output_tokens = output_tokens as u32;
+                }
+            }
+            "message_stop" => {
+                let stop_reason = self.stop_reason.clone().unwrap_or_default();
+                let response = self.response(Some(&stop_reason));
+                let event_type = if response["status"] == "incomplete" {
+                    "response.incomplete"
+                } else {
+                    "response.completed"
+                };
+                self.emit(events, event_type, json!({"response": response}));
+            }
+            "error" => {
+                let mut response = self.response(None);
+                response["status"] = json!("failed");
+                response["error"] = json!({
+                    "code": event["error"]["type"],
+                    "message": event["error"]["message"]
+                });
+                self.emit(events, "response.failed", json!({"response": response}));
+            }
+            _ => {}
+        }
+    }
+
+    fn response(&self, stop_reason: Option<&str>) -> Value {
+        let usage = stop_reason
+            .map(|_| usage_object(self.input_tokens, self.output_tokens, self.cached_tokens));
+        response_object(
+            &self.response_id,
+            self.created,
+            &self.model,
+            stop_reason,
+            self.output.clone(),
+            usage,
+        )
+    }
+
+    fn emit(&mut self, events: &mut Vec<Value>, event_type: &str, mut event: Value) {
+        event["type"] = json!(event_type);
+        event["sequence_number"] = json!(self.sequence);
+        self.sequence += 1;
+        events.push(event);
+    }
+}
+
+impl Default for ResponsesStreamConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenBlock {
+    fn item_value(&self, status: &str) -> Value {
+        match &self.item {
+            OpenItem::Message { text } => {
+                let content = if status == "completed" {
+                    vec![output_text(text)]
+                } else {
+                    Vec::new()
+                };
+                message_item(&self.item_id, content, status)
+            }
+            OpenItem::FunctionCall {
+                call_id,
+                name,
+                arguments,
+            } => function_call_item(call_id, name, arguments, status),
+        }
+    }
+}
+
+fn token_count(value: &Value) -> u32 {
+    value.as_u64().unwrap_or_default() as u32
+}
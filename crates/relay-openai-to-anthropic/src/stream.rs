@@ -0,0 +1,243 @@
+use relay_core::SseLines;
+use std::collections::HashMap;
+
+use crate::converter::finish_reason;
+use crate::types::{
+    ChatCompletionChunk, ChunkChoice, Delta, FunctionCallDelta, ToolCallDelta, Usage,
+};
+
+pub const DONE_EVENT: &str = "data: [DONE]\n\n";
+
+const CHUNK_ID: &str = "chatcmpl-relay";
+const CHUNK_MODEL: &str = "claude";
+
+/// Translates an Anthropic Messages SSE byte stream into OpenAI
+/// `chat.completion.chunk` objects.
+///
+/// Network chunks are buffered until a complete SSE line is available, so
+/// events split across reads are not lost.
+///
+/// `tool_use` blocks become OpenAI tool calls: the block start carries the
+/// id and name, and each `input_json_delta` appends to the arguments.
+///
+/// With [`StreamConverter::include_reasoning`], `thinking_delta`s are sent
+/// as `reasoning_content` deltas instead of being dropped.
+///
+/// With [`StreamConverter::include_usage`], `message_stop` is followed by
+/// a chunk with no choices and the token usage, like OpenAI's
+/// `stream_options.include_usage`.
+pub struct StreamConverter {
+    lines: SseLines,
+    created: u64,
+    include_reasoning: bool,
+    include_usage: bool,
+    usage: Usage,
+    /// OpenAI tool call index for each Anthropic `tool_use` block index.
+    tool_calls: HashMap<u64, u32>,
+    stop_reason: Option<String>,
+}
+
+impl StreamConverter {
+    pub fn new() -> Self {
+        Self::with_created(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        )
+    }
+
+    /// Uses a fixed `created` timestamp for every emitted chunk.
+    pub fn with_created(created: u64) -> Self {
+        Self {
+            lines: SseLines::new(),
+            created,
+            include_reasoning: false,
+            include_usage: false,
+            usage: Usage::default(),
+            tool_calls: HashMap::new(),
+            stop_reason: None,
+        }
+    }
+
+    pub fn include_reasoning(mut self, include_reasoning: bool) -> Self {
+        self.include_reasoning = include_reasoning;
+        self
+    }
+
+    pub fn include_usage(mut self, include_usage: bool) -> Self {
+        self.include_usage = include_usage;
+        self
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<ChatCompletionChunk> {
+        let mut chunks = Vec::new();
+        for data in self.lines.push(bytes) {
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&data) {
+                chunks.extend(self.convert_event(&value));
+            }
+        }
+        chunks
+    }
+
+    pub fn encode(chunk: &ChatCompletionChunk) -> String {
+        format!(
+            "data: {}\n\n",
+            serde_json::to_string(chunk).unwrap_or_default()
+        )
+    }
+
+    fn convert_event(&mut self, event: &serde_json::Value) -> Vec<ChatCompletionChunk> {
+        let Some(event_type) = event.get("type").and_then(|t| t.as_str()) else {
+            return Vec::new();
+        };
+
+        match event_type {
+            "message_start" => {
+                self.update_usage(&event["message"]["usage"]);
+                vec![self.chunk(
+                    Delta {
+                        role: Some("assistant".to_string()),
+                        ..Default::default()
+                    },
+                    None,
+                )]
+            }
+            "content_block_start" => {
+                let block = &event["content_block"];
+                if block["type"] != "tool_use" {
+                    return Vec::new();
+                }
+                let index = self.tool_calls.len() as u32;
+                self.tool_calls
+                    .insert(event["index"].as_u64().unwrap_or_default(), index);
+                vec![self.tool_call_chunk(ToolCallDelta {
+                    index,
+                    id: block["id"].as_str().map(|s| s.to_string()),
+                    call_type: Some("function".to_string()),
+                    function: Some(FunctionCallDelta {
+                        name: block["name"].as_str().map(|s| s.to_string()),
+                        arguments: Some(String::new()),
+                    }),
+                })]
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => match delta["text"].as_str() {
+                        Some(text) => vec![self.chunk(
+                            Delta {
+                                content: Some(text.to_string()),
+                                ..Default::default()
+                            },
+                            None,
+                        )],
+                        None => Vec::new(),
+                    },
+                    Some("thinking_delta") if self.include_reasoning => {
+                        match delta["thinking"].as_str() {
+                            Some(thinking) => vec![self.chunk(
+                                Delta {
+                                    reasoning_content: Some(thinking.to_string()),
+                                    ..Default::default()
+                                },
+                                None,
+                            )],
+                            None => Vec::new(),
+                        }
+                    }
+                    Some("input_json_delta") => {
+                        let block_index = event["index"].as_u64().unwrap_or_default();
+                        let (Some(&index), Some(partial_json)) = (
+                            self.tool_calls.get(&block_index),
+                            delta["partial_json"].as_str(),
+                        ) else {
+                            return Vec::new();
+                        };
+                        if partial_json.is_empty() {
+                            return Vec::new();
+                        }
+                        vec![self.tool_call_chunk(ToolCallDelta {
+                            index,
+                            id: None,
+                            call_type: None,
+                            function: Some(FunctionCallDelta {
+                                name: None,
+                                arguments: Some(partial_json.to_string()),
+                            }),
+                        })]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "message_delta" => {
+                self.update_usage(&event["usage"]);
+                if let Some(stop_reason) = event["delta"]["stop_reason"].as_str() {
+                    self.stop_reason = Some(stop_reason.to_string());
+                }
+                Vec::new()
+            }
+            "message_stop" => {
+                let reason = match self.stop_reason.as_deref() {
+                    Some(stop_reason) => finish_reason(stop_reason),
+                    None if !self.tool_calls.is_empty() => "tool_calls",
+                    None => "stop",
+                };
+                let mut chunks = vec![self.chunk(Delta::default(), Some(reason))];
+                if self.include_usage {
+                    chunks.push(ChatCompletionChunk {
+                        choices: Vec::new(),
+                        usage: Some(self.usage.clone()),
+                        ..self.chunk(Delta::default(), None)
+                    });
+                }
+                chunks
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// `message_start` reports the input tokens and `message_delta` the
+    /// running output count; either may also carry the other.
+    fn update_usage(&mut self, usage: &serde_json::Value) {
+        let count = |key: &str| usage[key].as_u64().map(|n| n as u32);
+        if let Some(input_tokens) = count("input_tokens") {
+            self.usage.prompt_tokens = input_tokens;
+        }
+        if let Some(output_tokens) = count("output_tokens") {
+            self.usage.completion_tokens = output_tokens;
+        }
+        self.usage.total_tokens = self.usage.prompt_tokens + self.usage.completion_tokens;
+    }
+
+    fn tool_call_chunk(&self, tool_call: ToolCallDelta) -> ChatCompletionChunk {
+        self.chunk(
+            Delta {
+                tool_calls: Some(vec![tool_call]),
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    fn chunk(&self, delta: Delta, finish_reason: Option<&str>) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: CHUNK_ID.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created: self.created,
+            model: CHUNK_MODEL.to_string(),
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta,
+                finish_reason: finish_reason.map(|r| r.to_string()),
+            }],
+            usage: None,
+        }
+    }
+}
+
+impl Default for StreamConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
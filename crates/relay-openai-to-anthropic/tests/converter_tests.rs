@@ -19,6 +19,7 @@ fn test_model_passthrough_no_mapping() {
         stop: None,
         tools: None,
         tool_choice: None,
+        stream_options: None,
         extra: serde_json::Map::new(),
     };
 
@@ -48,6 +49,7 @@ fn test_claude_model_passthrough() {
         stop: None,
         tools: None,
         tool_choice: None,
+        stream_options: None,
         extra: serde_json::Map::new(),
     };
 
@@ -74,6 +76,7 @@ fn test_arbitrary_model_passthrough() {
         stop: None,
         tools: None,
         tool_choice: None,
+        stream_options: None,
         extra: serde_json::Map::new(),
     };
 
@@ -114,6 +117,7 @@ fn test_xcode_system_message_preserved() {
         stop: None,
         tools: None,
         tool_choice: None,
+        stream_options: None,
         extra: serde_json::Map::new(),
     };
 
@@ -153,6 +157,7 @@ fn test_non_xcode_gets_claude_code_prompt() {
         stop: None,
         tools: None,
         tool_choice: None,
+        stream_options: None,
         extra: serde_json::Map::new(),
     };
 
@@ -164,3 +169,70 @@ fn test_non_xcode_gets_claude_code_prompt() {
         "Non-Xcode should get Claude Code system prompt"
     );
 }
+
+#[test]
+fn test_stop_and_max_completion_tokens_are_mapped() {
+    let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "stop": "END",
+        "max_completion_tokens": 256
+    }))
+    .unwrap();
+
+    let claude_request = OpenAIToClaudeConverter::convert_request(request).unwrap();
+
+    assert_eq!(claude_request.max_tokens, 256);
+    assert_eq!(
+        claude_request.extra.get("stop_sequences"),
+        Some(&serde_json::json!(["END"]))
+    );
+}
+
+#[test]
+fn test_unsupported_sampling_parameters_are_dropped() {
+    let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": 100,
+        "max_completion_tokens": 256,
+        "frequency_penalty": 0.5,
+        "presence_penalty": 0.5,
+        "logit_bias": {"50256": -100}
+    }))
+    .unwrap();
+
+    let claude_request = OpenAIToClaudeConverter::convert_request(request).unwrap();
+    let body = serde_json::to_value(&claude_request).unwrap();
+
+    assert_eq!(claude_request.max_tokens, 100);
+    assert!(body.get("frequency_penalty").is_none());
+    assert!(body.get("presence_penalty").is_none());
+    assert!(body.get("logit_bias").is_none());
+    assert!(body.get("max_completion_tokens").is_none());
+}
+
+#[test]
+fn test_thinking_is_returned_as_reasoning_content_when_enabled() {
+    let response: relay_claude::MessagesResponse = serde_json::from_value(serde_json::json!({
+        "id": "msg_01",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-sonnet-4-20250514",
+        "content": [
+            {"type": "thinking", "thinking": "2 + 2 = 4", "signature": "sig"},
+            {"type": "text", "text": "4"}
+        ],
+        "stop_reason": "end_turn",
+        "usage": {"input_tokens": 10, "output_tokens": 5}
+    }))
+    .unwrap();
+
+    let message =
+        &OpenAIToClaudeConverter::convert_response(response.clone(), true).choices[0].message;
+    assert_eq!(message.reasoning_content.as_deref(), Some("2 + 2 = 4"));
+    assert_eq!(message.content.as_deref(), Some("4"));
+
+    let message = &OpenAIToClaudeConverter::convert_response(response, false).choices[0].message;
+    assert!(message.reasoning_content.is_none());
+}
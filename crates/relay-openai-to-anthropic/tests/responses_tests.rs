@@ -0,0 +1,183 @@
+use futures::StreamExt;
+use relay_claude::MessagesResponse;
+use relay_openai_to_anthropic::{ResponsesStreamConverter, ResponsesToClaudeConverter};
+use relay_test_support::{assert_fixture, fixture, relay_fixture};
+use serde_json::json;
+
+const CREATED: u64 = 1_700_000_000;
+const MODEL: &str = "claude-sonnet-4-20250514";
+
+#[test]
+fn test_string_input_and_instructions() {
+    let request = json!({
+        "model": "gpt-5-codex",
+        "instructions": "Be terse.",
+        "input": "Hello",
+        "stream": true
+    });
+
+    let claude_request = ResponsesToClaudeConverter::convert_request(&request, MODEL).unwrap();
+
+    assert_eq!(claude_request.model, MODEL);
+    assert!(claude_request.stream);
+    let system = claude_request.system.unwrap();
+    assert!(system[0]["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("You are Claude Code"));
+    assert_eq!(system[1]["text"], "Be terse.");
+    assert_eq!(claude_request.messages.len(), 1);
+    assert_eq!(claude_request.messages[0].role, "user");
+    assert_eq!(claude_request.messages[0].content[0]["text"], "Hello");
+}
+
+#[test]
+fn test_function_calls_become_tool_turns() {
+    let request = json!({
+        "model": "gpt-5-codex",
+        "input": [
+            {"type": "message", "role": "developer", "content": [{"type": "input_text", "text": "Use tools."}]},
+            {"type": "message", "role": "user", "content": [{"type": "input_text", "text": "List files"}]},
+            {"type": "reasoning", "summary": []},
+            {"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "Checking."}]},
+            {"type": "function_call", "call_id": "call_1", "name": "shell", "arguments": "{\"command\":[\"ls\"]}"},
+            {"type": "function_call_output", "call_id": "call_1", "output": "README.md"}
+        ],
+        "tools": [
+            {"type": "function", "name": "shell", "description": "Run a command", "parameters": {"type": "object"}},
+            {"type": "web_search"}
+        ],
+        "tool_choice": "required"
+    });
+
+    let claude_request = ResponsesToClaudeConverter::convert_request(&request, MODEL).unwrap();
+
+    assert_eq!(claude_request.system.unwrap()[1]["text"], "Use tools.");
+    let roles: Vec<&str> = claude_request
+        .messages
+        .iter()
+        .map(|m| m.role.as_str())
+        .collect();
+    assert_eq!(roles, ["user", "assistant", "user"]);
+
+    let assistant = &claude_request.messages[1].content;
+    assert_eq!(assistant[0]["text"], "Checking.");
+    assert_eq!(assistant[1]["type"], "tool_use");
+    assert_eq!(assistant[1]["id"], "call_1");
+    assert_eq!(assistant[1]["input"]["command"][0], "ls");
+
+    let result = &claude_request.messages[2].content[0];
+    assert_eq!(result["type"], "tool_result");
+    assert_eq!(result["tool_use_id"], "call_1");
+    assert_eq!(result["content"], "README.md");
+
+    let tools = claude_request.tools.unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "shell");
+    assert_eq!(tools[0]["input_schema"]["type"], "object");
+    assert_eq!(claude_request.tool_choice.unwrap()["type"], "any");
+}
+
+#[test]
+fn test_missing_input_is_rejected() {
+    let request = json!({"model": "gpt-5-codex"});
+    assert!(ResponsesToClaudeConverter::convert_request(&request, MODEL).is_err());
+}
+
+#[test]
+fn test_convert_response() {
+    let response: MessagesResponse = serde_json::from_value(json!({
+        "id": "msg_01Abc",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            {"type": "thinking", "thinking": "hmm", "signature": "sig"},
+            {"type": "text", "text": "Let me look."},
+            {"type": "tool_use", "id": "toolu_01", "name": "shell", "input": {"command": ["ls"]}}
+        ],
+        "model": MODEL,
+        "stop_reason": "tool_use",
+        "usage": {"input_tokens": 100, "output_tokens": 20, "cache_read_input_tokens": 80}
+    }))
+    .unwrap();
+
+    let converted = ResponsesToClaudeConverter::convert_response(response);
+
+    assert_eq!(converted["id"], "resp_01Abc");
+    assert_eq!(converted["object"], "response");
+    assert_eq!(converted["status"], "completed");
+    let output = converted["output"].as_array().unwrap();
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0]["type"], "message");
+    assert_eq!(output[0]["content"][0]["text"], "Let me look.");
+    assert_eq!(output[1]["type"], "function_call");
+    assert_eq!(output[1]["call_id"], "toolu_01");
+    assert_eq!(output[1]["arguments"], "{\"command\":[\"ls\"]}");
+    assert_eq!(converted["usage"]["total_tokens"], 120);
+    assert_eq!(
+        converted["usage"]["input_tokens_details"]["cached_tokens"],
+        80
+    );
+}
+
+#[test]
+fn test_max_tokens_is_incomplete() {
+    let response: MessagesResponse = serde_json::from_value(json!({
+        "id": "msg_01Abc",
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "text", "text": "Cut"}],
+        "model": MODEL,
+        "stop_reason": "max_tokens",
+        "usage": {"input_tokens": 1, "output_tokens": 1}
+    }))
+    .unwrap();
+
+    let converted = ResponsesToClaudeConverter::convert_response(response);
+
+    assert_eq!(converted["status"], "incomplete");
+    assert_eq!(
+        converted["incomplete_details"]["reason"],
+        "max_output_tokens"
+    );
+}
+
+/// Runs a recorded Anthropic SSE fixture through the relay and the Responses
+/// stream converter, returning the exact bytes sent to a Responses client.
+async fn convert_fixture(name: &str) -> String {
+    let request = json!({"model": "gpt-5-codex", "input": "Hello", "stream": true});
+    let claude_request = ResponsesToClaudeConverter::convert_request(&request, MODEL).unwrap();
+    let mut stream = relay_fixture(fixture!(&format!("{}.sse", name)), claude_request).await;
+
+    let mut converter = ResponsesStreamConverter::with_created(CREATED);
+    let mut output = String::new();
+    while let Some(chunk) = stream.next().await {
+        for event in converter.push(&chunk.unwrap()) {
+            output.push_str(&ResponsesStreamConverter::encode(&event));
+        }
+    }
+    output
+}
+
+#[tokio::test]
+async fn test_stream_text() {
+    assert_fixture!(convert_fixture("text").await, "text", "responses.expected");
+}
+
+#[tokio::test]
+async fn test_stream_tool_use() {
+    assert_fixture!(
+        convert_fixture("tool_use").await,
+        "tool_use",
+        "responses.expected"
+    );
+}
+
+#[tokio::test]
+async fn test_stream_thinking() {
+    assert_fixture!(
+        convert_fixture("thinking").await,
+        "thinking",
+        "responses.expected"
+    );
+}
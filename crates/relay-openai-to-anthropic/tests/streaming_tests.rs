@@ -0,0 +1,150 @@
+use futures::StreamExt;
+use relay_openai_to_anthropic::types::{ChatCompletionRequest, ChatMessage, MessageContent};
+use relay_openai_to_anthropic::{OpenAIToClaudeConverter, StreamConverter, DONE_EVENT};
+use relay_test_support::{assert_fixture, fixture, relay_fixture};
+
+const CREATED: u64 = 1_700_000_000;
+
+fn stream_request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: "claude-sonnet-4-20250514".to_string(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Text("Hello".to_string()),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }],
+        stream: true,
+        max_tokens: Some(1024),
+        temperature: None,
+        top_p: None,
+        stop: None,
+        tools: None,
+        tool_choice: None,
+        stream_options: None,
+        extra: serde_json::Map::new(),
+    }
+}
+
+/// Runs a recorded Anthropic SSE fixture through the relay and the stream
+/// converter, returning the exact bytes sent to an OpenAI client.
+async fn convert_fixture(name: &str, mut converter: StreamConverter) -> String {
+    let claude_request = OpenAIToClaudeConverter::convert_request(stream_request()).unwrap();
+    let mut stream = relay_fixture(fixture!(&format!("{}.sse", name)), claude_request).await;
+
+    let mut output = String::new();
+    while let Some(chunk) = stream.next().await {
+        for openai_chunk in converter.push(&chunk.unwrap()) {
+            output.push_str(&StreamConverter::encode(&openai_chunk));
+        }
+    }
+    output.push_str(DONE_EVENT);
+    output
+}
+
+async fn convert(name: &str) -> String {
+    convert_fixture(name, StreamConverter::with_created(CREATED)).await
+}
+
+#[tokio::test]
+async fn test_stream_text() {
+    assert_fixture!(convert("text").await, "text");
+}
+
+#[tokio::test]
+async fn test_stream_tool_use() {
+    assert_fixture!(convert("tool_use").await, "tool_use");
+}
+
+#[tokio::test]
+async fn test_stream_thinking() {
+    assert_fixture!(convert("thinking").await, "thinking");
+}
+
+#[tokio::test]
+async fn test_stream_thinking_as_reasoning() {
+    let converter = StreamConverter::with_created(CREATED).include_reasoning(true);
+    let actual = convert_fixture("thinking", converter).await;
+    assert_fixture!(actual, "thinking", "reasoning.expected");
+}
+
+#[tokio::test]
+async fn test_stream_multi_block() {
+    assert_fixture!(convert("multi_block").await, "multi_block");
+}
+
+#[test]
+fn test_event_split_across_pushes() {
+    let event = "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"hi\"}}\n\n";
+    let (head, tail) = event.split_at(20);
+
+    let mut converter = StreamConverter::with_created(CREATED);
+    assert!(converter.push(head.as_bytes()).is_empty());
+
+    let chunks = converter.push(tail.as_bytes());
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].choices[0].delta.content.as_deref(), Some("hi"));
+}
+
+#[test]
+fn test_parallel_tool_calls_get_sequential_indexes() {
+    let events = [
+        r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_a","name":"a","input":{}}}"#,
+        r#"{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"toolu_b","name":"b","input":{}}}"#,
+        r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"{}"}}"#,
+    ];
+    let body: String = events
+        .iter()
+        .map(|e| format!("event: x\ndata: {}\n\n", e))
+        .collect();
+
+    let mut converter = StreamConverter::with_created(CREATED);
+    let chunks = converter.push(body.as_bytes());
+    let calls: Vec<_> = chunks
+        .iter()
+        .map(|c| &c.choices[0].delta.tool_calls.as_ref().unwrap()[0])
+        .collect();
+
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].index, 0);
+    assert_eq!(calls[0].id.as_deref(), Some("toolu_a"));
+    assert_eq!(calls[1].index, 1);
+    assert_eq!(calls[1].id.as_deref(), Some("toolu_b"));
+    assert_eq!(calls[2].index, 1);
+    assert_eq!(
+        calls[2].function.as_ref().unwrap().arguments.as_deref(),
+        Some("{}")
+    );
+}
+
+#[test]
+fn test_include_usage_appends_usage_chunk() {
+    let body = concat!(
+        "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":310,\"output_tokens\":1}}}\n\n",
+        "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":58}}\n\n",
+        "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+    );
+
+    let mut converter = StreamConverter::with_created(CREATED).include_usage(true);
+    let chunks = converter.push(body.as_bytes());
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[1].choices[0].finish_reason.as_deref(), Some("stop"));
+    assert!(chunks[2].choices.is_empty());
+    let usage = chunks[2].usage.as_ref().unwrap();
+    assert_eq!(usage.prompt_tokens, 310);
+    assert_eq!(usage.completion_tokens, 58);
+    assert_eq!(usage.total_tokens, 368);
+}
+
+#[test]
+fn test_usage_chunk_is_opt_in() {
+    let body = "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+
+    let mut converter = StreamConverter::with_created(CREATED);
+    let chunks = converter.push(body.as_bytes());
+
+    assert_eq!(chunks.len(), 1);
+    assert!(chunks[0].usage.is_none());
+}
@@ -0,0 +1,315 @@
+use relay_core::RelayError;
+use relay_gemini::{
+    Blob, Content, FunctionCall, FunctionResponse, GenerateContentRequest, GenerateContentResponse,
+    GenerationConfig, Part,
+};
+use relay_openai_to_anthropic::{
+    ChatCompletionRequest, ChatCompletionResponse, Choice, ContentPart, MessageContent,
+    ResponseMessage, StopSequence, ToolCall, Usage,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// JSON Schema keywords the Gemini API rejects in function parameters.
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &["$schema", "additionalProperties"];
+
+pub struct OpenAIToGeminiConverter;
+
+impl OpenAIToGeminiConverter {
+    /// The Gemini model is not part of the body; callers pass it in the URL.
+    pub fn convert_request(
+        req: ChatCompletionRequest,
+    ) -> Result<GenerateContentRequest, RelayError> {
+        let mut system_parts = Vec::new();
+        // OpenAI matches tool results to calls by id, Gemini by name
+        let mut tool_names: HashMap<String, String> = HashMap::new();
+        let mut contents: Vec<Content> = Vec::new();
+
+        for msg in req.messages {
+            let (role, parts) = match msg.role.as_str() {
+                "system" | "developer" => {
+                    system_parts.extend(
+                        text_of(msg.content)
+                            .into_iter()
+                            .map(|text| Part::Text { text }),
+                    );
+                    continue;
+                }
+                "user" => ("user", convert_content(msg.content)?),
+                "assistant" => {
+                    let mut parts = convert_content(msg.content)?;
+                    for call in msg.tool_calls.into_iter().flatten() {
+                        tool_names.insert(call.id, call.function.name.clone());
+                        parts.push(Part::FunctionCall {
+                            function_call: FunctionCall {
+                                name: call.function.name,
+                                args: serde_json::from_str(&call.function.arguments)
+                                    .unwrap_or_else(|_| json!({})),
+                            },
+                        });
+                    }
+                    ("model", parts)
+                }
+                "tool" => {
+                    let id = msg.tool_call_id.unwrap_or_default();
+                    let name = tool_names.get(&id).cloned().or(msg.name).unwrap_or(id);
+                    let output = text_of(msg.content).join("\n");
+                    let part = Part::FunctionResponse {
+                        function_response: FunctionResponse {
+                            name,
+                            response: json!({ "content": output }),
+                        },
+                    };
+                    ("user", vec![part])
+                }
+                _ => continue,
+            };
+
+            if parts.is_empty() {
+                continue;
+            }
+            // Gemini expects turns to alternate, and the results of parallel
+            // calls to arrive together
+            match contents.last_mut() {
+                Some(last) if last.role == role => last.parts.extend(parts),
+                _ => contents.push(Content {
+                    role: role.to_string(),
+                    parts,
+                }),
+            }
+        }
+
+        let declarations: Vec<Value> = req
+            .tools
+            .into_iter()
+            .flatten()
+            .map(|tool| {
+                let mut parameters = tool
+                    .function
+                    .parameters
+                    .unwrap_or(json!({"type": "object", "properties": {}}));
+                strip_unsupported_schema_keys(&mut parameters);
+                let mut declaration = json!({
+                    "name": tool.function.name,
+                    "parameters": parameters
+                });
+                if let Some(description) = tool.function.description {
+                    declaration["description"] = json!(description);
+                }
+                declaration
+            })
+            .collect();
+
+        let mut extra = serde_json::Map::new();
+        if !declarations.is_empty() {
+            if let Some(tool_config) = req.tool_choice.as_ref().and_then(convert_tool_choice) {
+                extra.insert("toolConfig".to_string(), tool_config);
+            }
+        }
+
+        let max_tokens = req.max_tokens.or_else(|| {
+            req.extra
+                .get("max_completion_tokens")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+        });
+
+        Ok(GenerateContentRequest {
+            contents,
+            system_instruction: (!system_parts.is_empty()).then(|| Content {
+                role: "user".to_string(),
+                parts: system_parts,
+            }),
+            generation_config: Some(GenerationConfig {
+                temperature: req.temperature,
+                top_p: req.top_p,
+                top_k: None,
+                max_output_tokens: max_tokens,
+                candidate_count: None,
+                stop_sequences: req.stop.map(|stop| match stop {
+                    StopSequence::Single(s) => vec![s],
+                    StopSequence::Multiple(v) => v,
+                }),
+            }),
+            safety_settings: None,
+            tools: if declarations.is_empty() {
+                None
+            } else {
+                Some(vec![json!({"functionDeclarations": declarations})])
+            },
+            extra,
+        })
+    }
+
+    /// `model` is reported back to the client as the model that answered.
+    pub fn convert_response(resp: GenerateContentResponse, model: &str) -> ChatCompletionResponse {
+        let id = completion_id();
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+
+        let candidate = resp.candidates.first();
+        let parts = candidate
+            .map(|c| c.content.parts.as_slice())
+            .unwrap_or_default();
+        for part in parts {
+            match part {
+                Part::Text { text: t } => text.push_str(t),
+                Part::FunctionCall { function_call } => tool_calls.push(ToolCall {
+                    id: tool_call_id(&id, tool_calls.len()),
+                    call_type: "function".to_string(),
+                    function: relay_openai_to_anthropic::FunctionCall {
+                        name: function_call.name.clone(),
+                        arguments: function_call.args.to_string(),
+                    },
+                }),
+                _ => {}
+            }
+        }
+
+        let usage = resp.usage_metadata.unwrap_or_default();
+        let finish_reason = finish_reason(
+            candidate.and_then(|c| c.finish_reason.as_deref()),
+            !tool_calls.is_empty(),
+        );
+
+        ChatCompletionResponse {
+            id,
+            object: "chat.completion".to_string(),
+            created: now(),
+            model: model.to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message: ResponseMessage {
+                    role: "assistant".to_string(),
+                    content: (!text.is_empty()).then_some(text),
+                    reasoning_content: None,
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                },
+                finish_reason: Some(finish_reason.to_string()),
+            }],
+            usage: Some(Usage {
+                prompt_tokens: usage.prompt_token_count,
+                completion_tokens: usage.candidates_token_count,
+                total_tokens: usage.prompt_token_count + usage.candidates_token_count,
+            }),
+        }
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub(crate) fn completion_id() -> String {
+    format!("chatcmpl-{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Gemini function calls carry no id, so one is derived from the completion
+/// id and the call's position.
+pub(crate) fn tool_call_id(completion_id: &str, index: usize) -> String {
+    format!(
+        "call_{}_{}",
+        completion_id
+            .strip_prefix("chatcmpl-")
+            .unwrap_or(completion_id),
+        index
+    )
+}
+
+/// OpenAI `finish_reason` for a Gemini `finishReason`.
+pub(crate) fn finish_reason(finish_reason: Option<&str>, has_tool_calls: bool) -> &'static str {
+    if has_tool_calls {
+        return "tool_calls";
+    }
+    match finish_reason {
+        Some("MAX_TOKENS") => "length",
+        Some("SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII") => {
+            "content_filter"
+        }
+        _ => "stop",
+    }
+}
+
+fn text_of(content: MessageContent) -> Vec<String> {
+    match content {
+        MessageContent::Text(text) => vec![text],
+        MessageContent::Parts(parts) => parts
+            .into_iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+fn convert_content(content: MessageContent) -> Result<Vec<Part>, RelayError> {
+    let parts = match content {
+        MessageContent::Text(text) => vec![ContentPart::Text { text }],
+        MessageContent::Parts(parts) => parts,
+    };
+
+    let mut converted = Vec::new();
+    for part in parts {
+        match part {
+            ContentPart::Text { text } => {
+                if !text.is_empty() {
+                    converted.push(Part::Text { text });
+                }
+            }
+            ContentPart::ImageUrl { image_url } => {
+                let Some((mime_type, data)) = parse_data_url(&image_url.url) else {
+                    return Err(RelayError::InvalidRequest(
+                        "Gemini only accepts images as base64 data URLs".to_string(),
+                    ));
+                };
+                converted.push(Part::InlineData {
+                    inline_data: Blob { mime_type, data },
+                });
+            }
+        }
+    }
+    Ok(converted)
+}
+
+fn parse_data_url(url: &str) -> Option<(String, String)> {
+    let url = url.strip_prefix("data:")?;
+    let (metadata, data) = url.split_once(',')?;
+    let media_type = metadata.split(';').next()?;
+    Some((media_type.to_string(), data.to_string()))
+}
+
+fn convert_tool_choice(choice: &Value) -> Option<Value> {
+    let config = match choice {
+        Value::String(mode) => match mode.as_str() {
+            "auto" => json!({"mode": "AUTO"}),
+            "required" => json!({"mode": "ANY"}),
+            "none" => json!({"mode": "NONE"}),
+            _ => return None,
+        },
+        Value::Object(_) => {
+            let name = choice.get("function")?.get("name")?;
+            json!({"mode": "ANY", "allowedFunctionNames": [name]})
+        }
+        _ => return None,
+    };
+    Some(json!({ "functionCallingConfig": config }))
+}
+
+fn strip_unsupported_schema_keys(schema: &mut Value) {
+    match schema {
+        Value::Object(map) => {
+            map.retain(|key, _| !UNSUPPORTED_SCHEMA_KEYS.contains(&key.as_str()));
+            map.values_mut().for_each(strip_unsupported_schema_keys);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_unsupported_schema_keys),
+        _ => {}
+    }
+}
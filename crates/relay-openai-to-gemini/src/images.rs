@@ -0,0 +1,183 @@
+use relay_core::RelayError;
+use relay_gemini::{Content, GenerateContentRequest, GenerateContentResponse, Part};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::converter::now;
+
+/// Most images OpenAI allows in one request.
+const MAX_IMAGES: u32 = 10;
+
+/// `POST /v1/images/generations` request body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageGenerationRequest {
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ImageGenerationRequest {
+    pub fn image_count(&self) -> u32 {
+        self.n.unwrap_or(1)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImagesResponse {
+    pub created: u64,
+    pub data: Vec<ImageData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub b64_json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revised_prompt: Option<String>,
+}
+
+pub struct ImagesToGeminiConverter;
+
+impl ImagesToGeminiConverter {
+    /// Builds one `generateContent` request; Gemini image models return a
+    /// single image per call, so callers send it `image_count()` times.
+    pub fn convert_request(
+        req: &ImageGenerationRequest,
+    ) -> Result<GenerateContentRequest, RelayError> {
+        if req.prompt.trim().is_empty() {
+            return Err(RelayError::InvalidRequest("prompt is required".to_string()));
+        }
+        if !(1..=MAX_IMAGES).contains(&req.image_count()) {
+            return Err(RelayError::InvalidRequest(format!(
+                "n must be between 1 and {}",
+                MAX_IMAGES
+            )));
+        }
+        if let Some(format) = req.response_format.as_deref() {
+            if format != "b64_json" && format != "url" {
+                return Err(RelayError::InvalidRequest(format!(
+                    "Unsupported response_format: {}",
+                    format
+                )));
+            }
+        }
+
+        let mut generation_config = json!({"responseModalities": ["TEXT", "IMAGE"]});
+        if let Some(aspect_ratio) = req.size.as_deref().and_then(aspect_ratio) {
+            generation_config["imageConfig"] = json!({ "aspectRatio": aspect_ratio });
+        }
+
+        let mut extra = serde_json::Map::new();
+        extra.insert("generationConfig".to_string(), generation_config);
+
+        Ok(GenerateContentRequest {
+            contents: vec![Content {
+                role: "user".to_string(),
+                parts: vec![Part::Text {
+                    text: req.prompt.clone(),
+                }],
+            }],
+            system_instruction: None,
+            generation_config: None,
+            safety_settings: None,
+            tools: None,
+            extra,
+        })
+    }
+
+    /// Collects the images of every response. There is no hosting for
+    /// `response_format: "url"`, so those images are returned as data URLs.
+    pub fn convert_response(
+        req: &ImageGenerationRequest,
+        responses: Vec<GenerateContentResponse>,
+    ) -> Result<ImagesResponse, RelayError> {
+        let as_url = req.response_format.as_deref() == Some("url");
+        let mut data = Vec::new();
+        let mut text = String::new();
+
+        for response in responses {
+            let parts = response
+                .candidates
+                .into_iter()
+                .next()
+                .map(|c| c.content.parts)
+                .unwrap_or_default();
+            for part in parts {
+                match part {
+                    Part::InlineData { inline_data }
+                        if inline_data.mime_type.starts_with("image/") =>
+                    {
+                        data.push(if as_url {
+                            ImageData {
+                                b64_json: None,
+                                url: Some(format!(
+                                    "data:{};base64,{}",
+                                    inline_data.mime_type, inline_data.data
+                                )),
+                                revised_prompt: None,
+                            }
+                        } else {
+                            ImageData {
+                                b64_json: Some(inline_data.data),
+                                url: None,
+                                revised_prompt: None,
+                            }
+                        });
+                    }
+                    Part::Text { text: t } => text.push_str(&t),
+                    _ => {}
+                }
+            }
+        }
+
+        if data.is_empty() {
+            // Refusals come back as text only
+            let message = if text.is_empty() {
+                "Gemini returned no image".to_string()
+            } else {
+                format!("Gemini returned no image: {}", text)
+            };
+            return Err(RelayError::Upstream {
+                status: 502,
+                message,
+            });
+        }
+
+        Ok(ImagesResponse {
+            created: now(),
+            data,
+        })
+    }
+}
+
+/// Gemini aspect ratio for an OpenAI `size` such as `1792x1024`.
+fn aspect_ratio(size: &str) -> Option<&'static str> {
+    let (width, height) = size.split_once('x')?;
+    let (width, height): (u32, u32) = (width.parse().ok()?, height.parse().ok()?);
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let ratio = width as f64 / height as f64;
+    [
+        ("1:1", 1.0),
+        ("3:2", 1.5),
+        ("2:3", 2.0 / 3.0),
+        ("4:3", 4.0 / 3.0),
+        ("3:4", 0.75),
+        ("16:9", 16.0 / 9.0),
+        ("9:16", 9.0 / 16.0),
+    ]
+    .into_iter()
+    .min_by(|a, b| (a.1 - ratio).abs().total_cmp(&(b.1 - ratio).abs()))
+    .map(|(name, _)| name)
+}
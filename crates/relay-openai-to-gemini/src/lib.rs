@@ -0,0 +1,7 @@
+mod converter;
+mod images;
+mod stream;
+
+pub use converter::OpenAIToGeminiConverter;
+pub use images::{ImageData, ImageGenerationRequest, ImagesResponse, ImagesToGeminiConverter};
+pub use stream::OpenAIStreamConverter;
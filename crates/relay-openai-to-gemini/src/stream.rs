@@ -0,0 +1,177 @@
+use relay_core::SseLines;
+use relay_gemini::{GenerateContentResponse, Part, UsageMetadata};
+use relay_openai_to_anthropic::{
+    ChatCompletionChunk, ChunkChoice, Delta, FunctionCallDelta, ToolCallDelta, Usage,
+};
+
+use crate::converter::{completion_id, finish_reason, now, tool_call_id};
+
+/// Translates a Gemini `streamGenerateContent?alt=sse` byte stream into
+/// OpenAI `chat.completion.chunk` objects.
+///
+/// Gemini sends function calls whole, so each becomes a single tool call
+/// delta carrying its complete arguments. The chunk with a `finishReason`
+/// produces the final chunk, followed by a usage chunk when
+/// [`OpenAIStreamConverter::include_usage`] is set; the caller appends
+/// `[DONE]`.
+pub struct OpenAIStreamConverter {
+    lines: SseLines,
+    id: String,
+    created: u64,
+    model: String,
+    started: bool,
+    finished: bool,
+    tool_calls: usize,
+    include_usage: bool,
+    usage: UsageMetadata,
+}
+
+impl OpenAIStreamConverter {
+    pub fn new(model: &str) -> Self {
+        Self::with_id(completion_id(), now(), model)
+    }
+
+    /// Uses a fixed completion id and `created` timestamp for every chunk.
+    pub fn with_id(id: String, created: u64, model: &str) -> Self {
+        Self {
+            lines: SseLines::new(),
+            id,
+            created,
+            model: model.to_string(),
+            started: false,
+            finished: false,
+            tool_calls: 0,
+            include_usage: false,
+            usage: UsageMetadata::default(),
+        }
+    }
+
+    pub fn include_usage(mut self, include_usage: bool) -> Self {
+        self.include_usage = include_usage;
+        self
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<ChatCompletionChunk> {
+        let mut chunks = Vec::new();
+        for data in self.lines.push(bytes) {
+            if let Ok(response) = serde_json::from_str::<GenerateContentResponse>(&data) {
+                self.convert_response(response, &mut chunks);
+            }
+        }
+        chunks
+    }
+
+    /// Emits the final chunk if the upstream stream ended without a
+    /// `finishReason`.
+    pub fn finish(&mut self) -> Vec<ChatCompletionChunk> {
+        if !self.started || self.finished {
+            return Vec::new();
+        }
+        let mut chunks = Vec::new();
+        self.close(finish_reason(None, self.tool_calls > 0), &mut chunks);
+        chunks
+    }
+
+    pub fn encode(chunk: &ChatCompletionChunk) -> String {
+        format!(
+            "data: {}\n\n",
+            serde_json::to_string(chunk).unwrap_or_default()
+        )
+    }
+
+    fn convert_response(
+        &mut self,
+        response: GenerateContentResponse,
+        chunks: &mut Vec<ChatCompletionChunk>,
+    ) {
+        if self.finished {
+            return;
+        }
+        if let Some(usage) = response.usage_metadata {
+            self.usage = usage;
+        }
+        if !self.started {
+            self.started = true;
+            chunks.push(self.chunk(
+                Delta {
+                    role: Some("assistant".to_string()),
+                    ..Default::default()
+                },
+                None,
+            ));
+        }
+
+        let Some(candidate) = response.candidates.into_iter().next() else {
+            return;
+        };
+
+        for part in candidate.content.parts {
+            match part {
+                Part::Text { text } if !text.is_empty() => chunks.push(self.chunk(
+                    Delta {
+                        content: Some(text),
+                        ..Default::default()
+                    },
+                    None,
+                )),
+                Part::FunctionCall { function_call } => {
+                    let index = self.tool_calls;
+                    self.tool_calls += 1;
+                    chunks.push(self.chunk(
+                        Delta {
+                            tool_calls: Some(vec![ToolCallDelta {
+                                index: index as u32,
+                                id: Some(tool_call_id(&self.id, index)),
+                                call_type: Some("function".to_string()),
+                                function: Some(FunctionCallDelta {
+                                    name: Some(function_call.name),
+                                    arguments: Some(function_call.args.to_string()),
+                                }),
+                            }]),
+                            ..Default::default()
+                        },
+                        None,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(reason) = candidate.finish_reason {
+            let reason = finish_reason(Some(&reason), self.tool_calls > 0);
+            self.close(reason, chunks);
+        }
+    }
+
+    fn close(&mut self, finish_reason: &str, chunks: &mut Vec<ChatCompletionChunk>) {
+        self.finished = true;
+        chunks.push(self.chunk(Delta::default(), Some(finish_reason)));
+        if self.include_usage {
+            let usage = &self.usage;
+            chunks.push(ChatCompletionChunk {
+                choices: Vec::new(),
+                usage: Some(Usage {
+                    prompt_tokens: usage.prompt_token_count,
+                    completion_tokens: usage.candidates_token_count,
+                    total_tokens: usage.prompt_token_count + usage.candidates_token_count,
+                }),
+                ..self.chunk(Delta::default(), None)
+            });
+        }
+    }
+
+    fn chunk(&self, delta: Delta, finish_reason: Option<&str>) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: self.id.clone(),
+            object: "chat.completion.chunk".to_string(),
+            created: self.created,
+            model: self.model.clone(),
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta,
+                finish_reason: finish_reason.map(|r| r.to_string()),
+            }],
+            usage: None,
+        }
+    }
+}
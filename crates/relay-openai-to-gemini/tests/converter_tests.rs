@@ -0,0 +1,149 @@
+use relay_gemini::{GenerateContentResponse, Part};
+use relay_openai_to_anthropic::ChatCompletionRequest;
+use relay_openai_to_gemini::OpenAIToGeminiConverter;
+use serde_json::json;
+
+fn request(value: serde_json::Value) -> ChatCompletionRequest {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_messages_and_system() {
+    let req = request(json!({
+        "model": "gemini-2.5-pro",
+        "max_tokens": 256,
+        "temperature": 0.5,
+        "stop": "END",
+        "messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "Hi"},
+            {"role": "assistant", "content": "Hello!"},
+            {"role": "user", "content": [
+                {"type": "text", "text": "Describe"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+            ]}
+        ]
+    }));
+
+    let gemini_request = OpenAIToGeminiConverter::convert_request(req).unwrap();
+
+    let system = gemini_request.system_instruction.unwrap();
+    assert!(matches!(&system.parts[..], [Part::Text { text }] if text == "Be brief."));
+
+    let roles: Vec<&str> = gemini_request
+        .contents
+        .iter()
+        .map(|c| c.role.as_str())
+        .collect();
+    assert_eq!(roles, ["user", "model", "user"]);
+    match &gemini_request.contents[2].parts[1] {
+        Part::InlineData { inline_data } => {
+            assert_eq!(inline_data.mime_type, "image/png");
+            assert_eq!(inline_data.data, "iVBORw0KGgo=");
+        }
+        other => panic!("expected inline data, got {:?}", other),
+    }
+
+    let config = gemini_request.generation_config.unwrap();
+    assert_eq!(config.max_output_tokens, Some(256));
+    assert_eq!(config.temperature, Some(0.5));
+    assert_eq!(config.stop_sequences, Some(vec!["END".to_string()]));
+}
+
+#[test]
+fn test_tool_calls_and_results() {
+    let req = request(json!({
+        "model": "gemini-2.5-pro",
+        "tools": [{
+            "type": "function",
+            "function": {
+                "name": "get_weather",
+                "description": "Current weather",
+                "parameters": {
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "additionalProperties": false
+                }
+            }
+        }],
+        "tool_choice": "required",
+        "messages": [
+            {"role": "user", "content": "Weather in Paris and Rome?"},
+            {"role": "assistant", "content": "", "tool_calls": [
+                {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}},
+                {"id": "call_2", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Rome\"}"}}
+            ]},
+            {"role": "tool", "tool_call_id": "call_1", "content": "18°C"},
+            {"role": "tool", "tool_call_id": "call_2", "content": "22°C"}
+        ]
+    }));
+
+    let gemini_request = OpenAIToGeminiConverter::convert_request(req).unwrap();
+
+    assert_eq!(
+        gemini_request.tools.unwrap(),
+        [json!({"functionDeclarations": [{
+            "name": "get_weather",
+            "description": "Current weather",
+            "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+        }]})]
+    );
+    assert_eq!(
+        gemini_request.extra["toolConfig"],
+        json!({"functionCallingConfig": {"mode": "ANY"}})
+    );
+
+    assert_eq!(gemini_request.contents.len(), 3);
+    assert_eq!(gemini_request.contents[1].parts.len(), 2);
+    let results = &gemini_request.contents[2];
+    assert_eq!(results.role, "user");
+    assert_eq!(results.parts.len(), 2);
+    match &results.parts[1] {
+        Part::FunctionResponse { function_response } => {
+            assert_eq!(function_response.name, "get_weather");
+            assert_eq!(function_response.response, json!({"content": "22°C"}));
+        }
+        other => panic!("expected function response, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_remote_image_is_rejected() {
+    let req = request(json!({
+        "model": "gemini-2.5-pro",
+        "messages": [{"role": "user", "content": [
+            {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}}
+        ]}]
+    }));
+
+    assert!(OpenAIToGeminiConverter::convert_request(req).is_err());
+}
+
+#[test]
+fn test_convert_response() {
+    let resp: GenerateContentResponse = serde_json::from_value(json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": [
+                {"text": "Checking."},
+                {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}
+            ]},
+            "finishReason": "STOP"
+        }],
+        "usageMetadata": {"promptTokenCount": 40, "candidatesTokenCount": 18, "totalTokenCount": 58}
+    }))
+    .unwrap();
+
+    let completion = OpenAIToGeminiConverter::convert_response(resp, "gemini-2.5-pro");
+
+    assert!(completion.id.starts_with("chatcmpl-"));
+    assert_eq!(completion.model, "gemini-2.5-pro");
+    let choice = &completion.choices[0];
+    assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+    assert_eq!(choice.message.content.as_deref(), Some("Checking."));
+    let tool_calls = choice.message.tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls[0].function.name, "get_weather");
+    assert_eq!(tool_calls[0].function.arguments, r#"{"city":"Paris"}"#);
+    let usage = completion.usage.unwrap();
+    assert_eq!(usage.prompt_tokens, 40);
+    assert_eq!(usage.completion_tokens, 18);
+}
@@ -0,0 +1,86 @@
+use relay_gemini::GenerateContentResponse;
+use relay_openai_to_gemini::{ImageGenerationRequest, ImagesToGeminiConverter};
+use serde_json::json;
+
+fn request(value: serde_json::Value) -> ImageGenerationRequest {
+    serde_json::from_value(value).unwrap()
+}
+
+fn image_response(data: &str) -> GenerateContentResponse {
+    serde_json::from_value(json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": [
+                {"text": "Here is your image."},
+                {"inlineData": {"mimeType": "image/png", "data": data}}
+            ]},
+            "finishReason": "STOP"
+        }]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_convert_request() {
+    let req = request(json!({
+        "prompt": "A lighthouse at dusk",
+        "model": "dall-e-3",
+        "size": "1792x1024"
+    }));
+
+    let gemini_request = ImagesToGeminiConverter::convert_request(&req).unwrap();
+    let body = serde_json::to_value(&gemini_request).unwrap();
+
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        "A lighthouse at dusk"
+    );
+    assert_eq!(
+        body["generationConfig"],
+        json!({"responseModalities": ["TEXT", "IMAGE"], "imageConfig": {"aspectRatio": "16:9"}})
+    );
+}
+
+#[test]
+fn test_invalid_requests_are_rejected() {
+    for body in [
+        json!({"prompt": " "}),
+        json!({"prompt": "cat", "n": 0}),
+        json!({"prompt": "cat", "n": 11}),
+        json!({"prompt": "cat", "response_format": "png"}),
+    ] {
+        assert!(ImagesToGeminiConverter::convert_request(&request(body)).is_err());
+    }
+}
+
+#[test]
+fn test_convert_response_formats() {
+    let req = request(json!({"prompt": "cat", "n": 2}));
+    let images = ImagesToGeminiConverter::convert_response(
+        &req,
+        vec![image_response("AAAA"), image_response("BBBB")],
+    )
+    .unwrap();
+    let b64: Vec<_> = images.data.iter().map(|d| d.b64_json.as_deref()).collect();
+    assert_eq!(b64, [Some("AAAA"), Some("BBBB")]);
+
+    let req = request(json!({"prompt": "cat", "response_format": "url"}));
+    let images =
+        ImagesToGeminiConverter::convert_response(&req, vec![image_response("AAAA")]).unwrap();
+    assert_eq!(
+        images.data[0].url.as_deref(),
+        Some("data:image/png;base64,AAAA")
+    );
+    assert!(images.data[0].b64_json.is_none());
+}
+
+#[test]
+fn test_text_only_response_is_an_error() {
+    let req = request(json!({"prompt": "cat"}));
+    let refusal: GenerateContentResponse = serde_json::from_value(json!({
+        "candidates": [{"content": {"role": "model", "parts": [{"text": "I can't draw that."}]}}]
+    }))
+    .unwrap();
+
+    let err = ImagesToGeminiConverter::convert_response(&req, vec![refusal]).unwrap_err();
+    assert!(err.to_string().contains("I can't draw that."));
+}
@@ -0,0 +1,58 @@
+use relay_openai_to_gemini::OpenAIStreamConverter;
+use relay_test_support::{assert_fixture, fixture, replay};
+
+/// Feeds a recorded Gemini SSE fixture through the converter in small
+/// pieces, returning the exact bytes sent to an OpenAI client.
+fn convert_fixture(name: &str) -> String {
+    let mut converter =
+        OpenAIStreamConverter::with_id("chatcmpl-test".to_string(), 1700000000, "gemini-2.5-pro");
+    replay(
+        &fixture!(&format!("{}.sse", name)),
+        &mut converter,
+        OpenAIStreamConverter::push,
+        OpenAIStreamConverter::finish,
+        OpenAIStreamConverter::encode,
+    )
+}
+
+#[test]
+fn test_stream_text() {
+    assert_fixture!(convert_fixture("text"), "text");
+}
+
+#[test]
+fn test_stream_tool_use() {
+    assert_fixture!(convert_fixture("tool_use"), "tool_use");
+}
+
+#[test]
+fn test_stream_without_finish_reason_is_closed() {
+    let mut converter =
+        OpenAIStreamConverter::with_id("chatcmpl-test".to_string(), 1700000000, "gemini-2.5-pro");
+    let chunks = converter.push(
+        b"data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi\"}],\"role\":\"model\"}}]}\r\n\r\n",
+    );
+    assert_eq!(chunks.len(), 2);
+
+    let chunks = converter.finish();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].choices[0].finish_reason.as_deref(), Some("stop"));
+    assert!(converter.finish().is_empty());
+}
+
+#[test]
+fn test_stream_include_usage_appends_usage_chunk() {
+    let mut converter =
+        OpenAIStreamConverter::with_id("chatcmpl-test".to_string(), 1700000000, "gemini-2.5-pro")
+            .include_usage(true);
+    let chunks = converter.push(
+        b"data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi\"}],\"role\":\"model\"},\"finishReason\":\"STOP\"}],\"usageMetadata\":{\"promptTokenCount\":5,\"candidatesTokenCount\":2}}\r\n\r\n",
+    );
+
+    let last = chunks.last().unwrap();
+    assert!(last.choices.is_empty());
+    let usage = last.usage.as_ref().unwrap();
+    assert_eq!(usage.prompt_tokens, 5);
+    assert_eq!(usage.completion_tokens, 2);
+    assert_eq!(usage.total_tokens, 7);
+}
@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use relay_core::{AccountProvider, Credentials, Platform, ProxyConfig, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// A server with an OpenAI-compatible `chat/completions` endpoint, such as
+/// Ollama, vLLM or LM Studio.
+pub struct OpenAICompatibleAccount {
+    id: String,
+    name: String,
+    priority: u32,
+    enabled: AtomicBool,
+    /// Base URL including the version, such as `http://localhost:11434/v1`.
+    api_url: String,
+    /// Local servers usually accept any key or none.
+    api_key: Option<String>,
+    model: Option<String>,
+    proxy: Option<ProxyConfig>,
+    unavailable_until: RwLock<Option<Instant>>,
+}
+
+impl OpenAICompatibleAccount {
+    pub fn new(
+        id: String,
+        name: String,
+        priority: u32,
+        enabled: bool,
+        api_url: String,
+        api_key: Option<String>,
+        proxy: Option<ProxyConfig>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            priority,
+            enabled: AtomicBool::new(enabled),
+            api_url,
+            api_key,
+            model: None,
+            proxy,
+            unavailable_until: RwLock::new(None),
+        }
+    }
+
+    /// Serves every request with `model`, since a local server knows none
+    /// of the cloud model names clients ask for.
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        self.model = model;
+        self
+    }
+}
+
+#[async_trait]
+impl AccountProvider for OpenAICompatibleAccount {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn platform(&self) -> Platform {
+        Platform::OpenAI
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    fn is_available(&self) -> bool {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        if let Some(until) = *self.unavailable_until.read() {
+            if Instant::now() < until {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    async fn get_credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::ApiKey(
+            self.api_key.clone().unwrap_or_default(),
+        ))
+    }
+
+    fn proxy_config(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    fn api_url(&self) -> Option<&str> {
+        Some(&self.api_url)
+    }
+
+    fn upstream_model(&self, _model: &str) -> Option<String> {
+        self.model.clone()
+    }
+
+    fn mark_unavailable(&self, duration: Duration, _reason: &str) {
+        let mut until = self.unavailable_until.write();
+        *until = Some(Instant::now() + duration);
+    }
+
+    fn mark_available(&self) {
+        let mut until = self.unavailable_until.write();
+        *until = None;
+    }
+}
@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use relay_core::{AccountProvider, Credentials, Platform, ProxyConfig, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// A DeepSeek API key, served through DeepSeek's OpenAI-compatible
+/// `chat/completions` endpoint.
+pub struct DeepSeekAccount {
+    id: String,
+    name: String,
+    priority: u32,
+    enabled: AtomicBool,
+    api_key: String,
+    api_url: String,
+    model: String,
+    proxy: Option<ProxyConfig>,
+    unavailable_until: RwLock<Option<Instant>>,
+}
+
+impl DeepSeekAccount {
+    pub const DEFAULT_API_URL: &'static str = "https://api.deepseek.com/v1";
+    pub const DEFAULT_MODEL: &'static str = "deepseek-chat";
+
+    pub fn new(
+        id: String,
+        name: String,
+        priority: u32,
+        enabled: bool,
+        api_key: String,
+        api_url: Option<String>,
+        proxy: Option<ProxyConfig>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            priority,
+            enabled: AtomicBool::new(enabled),
+            api_key,
+            api_url: api_url.unwrap_or_else(|| Self::DEFAULT_API_URL.to_string()),
+            model: Self::DEFAULT_MODEL.to_string(),
+            proxy,
+            unavailable_until: RwLock::new(None),
+        }
+    }
+
+    /// The DeepSeek model serving requests for other providers' models,
+    /// such as `deepseek-reasoner` to stream its reasoning as thinking.
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        if let Some(model) = model {
+            self.model = model;
+        }
+        self
+    }
+}
+
+#[async_trait]
+impl AccountProvider for DeepSeekAccount {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn platform(&self) -> Platform {
+        Platform::OpenAI
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    fn is_available(&self) -> bool {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        if let Some(until) = *self.unavailable_until.read() {
+            if Instant::now() < until {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    async fn get_credentials(&self) -> Result<Credentials> {
+        Ok(Credentials::Bearer(self.api_key.clone()))
+    }
+
+    fn proxy_config(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    fn api_url(&self) -> Option<&str> {
+        Some(&self.api_url)
+    }
+
+    /// Requests naming a DeepSeek model keep it.
+    fn upstream_model(&self, model: &str) -> Option<String> {
+        if model.starts_with("deepseek-") {
+            return None;
+        }
+        Some(self.model.clone())
+    }
+
+    fn mark_unavailable(&self, duration: Duration, _reason: &str) {
+        let mut until = self.unavailable_until.write();
+        *until = Some(Instant::now() + duration);
+    }
+
+    fn mark_available(&self) {
+        let mut until = self.unavailable_until.write();
+        *until = None;
+    }
+}
@@ -0,0 +1,7 @@
+mod account;
+mod deepseek;
+mod relay;
+
+pub use account::OpenAICompatibleAccount;
+pub use deepseek::DeepSeekAccount;
+pub use relay::{extract_usage_from_chunk, ChatCompletionsRelay, UsageTracker};
@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use relay_core::{
+    read_error_response, send_captured, AccountProvider, BoxStream, Capture, ClientCache,
+    Credentials, HttpClientOptions, Relay, RelayError, Result, SseLines, UpstreamTimeouts,
+};
+use relay_openai_to_anthropic::{ChatCompletionRequest, ChatCompletionResponse, Usage};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Relays OpenAI `chat/completions` requests unchanged to an
+/// OpenAI-compatible server.
+pub struct ChatCompletionsRelay {
+    clients: ClientCache,
+    timeouts: Arc<UpstreamTimeouts>,
+    capture: Option<Arc<Capture>>,
+}
+
+impl ChatCompletionsRelay {
+    pub fn new() -> Self {
+        Self {
+            clients: ClientCache::default(),
+            timeouts: Arc::default(),
+            capture: None,
+        }
+    }
+
+    /// Bounds each upstream request, body included.
+    pub fn with_timeouts(mut self, timeouts: Arc<UpstreamTimeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Writes each upstream exchange to `capture`, for debugging.
+    pub fn with_capture(mut self, capture: Option<Arc<Capture>>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// HTTP version, pooling and keepalive of upstream connections.
+    pub fn with_client_options(mut self, options: HttpClientOptions) -> Self {
+        self.clients = ClientCache::new(options);
+        self
+    }
+
+    async fn send(
+        &self,
+        account: &dyn AccountProvider,
+        mut request: ChatCompletionRequest,
+    ) -> Result<reqwest::Response> {
+        let api_url = account.api_url().ok_or_else(|| {
+            RelayError::Config(format!(
+                "OpenAI-compatible account {} has no api_url",
+                account.id()
+            ))
+        })?;
+        let url = format!("{}/chat/completions", api_url.trim_end_matches('/'));
+        if let Some(model) = account.upstream_model(&request.model) {
+            request.model = model;
+        }
+        let client = self.clients.client(account.proxy_config())?;
+
+        debug!(
+            account_id = %account.id(),
+            model = %request.model,
+            url = %url,
+            stream = request.stream,
+            "Sending OpenAI-compatible request"
+        );
+
+        let mut builder = client
+            .post(&url)
+            .timeout(self.timeouts.for_request(account.id(), &request.model))
+            .json(&request);
+        match account.get_credentials().await? {
+            Credentials::ApiKey(key) if key.is_empty() => {}
+            Credentials::ApiKey(key) | Credentials::Bearer(key) => {
+                builder = builder.header("Authorization", format!("Bearer {}", key));
+            }
+            _ => {
+                return Err(RelayError::Config(format!(
+                    "Account {} has no OpenAI-compatible credentials",
+                    account.id()
+                )))
+            }
+        }
+        let response = send_captured(builder, self.capture.as_deref()).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error = read_error_response(response).await;
+            warn!(
+                account_id = %account.id(),
+                model = %request.model,
+                error = %error,
+                "OpenAI-compatible request failed"
+            );
+            return Err(error);
+        }
+        Ok(response)
+    }
+}
+
+impl Default for ChatCompletionsRelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Relay for ChatCompletionsRelay {
+    type Request = ChatCompletionRequest;
+    type Response = ChatCompletionResponse;
+
+    async fn relay(
+        &self,
+        account: &dyn AccountProvider,
+        mut request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        request.stream = false;
+        let response = self.send(account, request).await?;
+
+        let resp: ChatCompletionResponse = response.json().await?;
+        if let Some(ref usage) = resp.usage {
+            info!(
+                account_id = %account.id(),
+                prompt_tokens = usage.prompt_tokens,
+                completion_tokens = usage.completion_tokens,
+                "OpenAI-compatible request completed"
+            );
+        }
+        Ok(resp)
+    }
+
+    async fn relay_stream(
+        &self,
+        account: &dyn AccountProvider,
+        mut request: ChatCompletionRequest,
+    ) -> Result<BoxStream<Result<Bytes>>> {
+        request.stream = true;
+        let response = self.send(account, request).await?;
+
+        let stream = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(RelayError::from));
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Token usage reported by a streamed `chat.completion.chunk`, which
+/// servers send in the final chunk when `stream_options.include_usage` is
+/// set.
+pub fn extract_usage_from_chunk(chunk: &Bytes) -> Option<Usage> {
+    let text = std::str::from_utf8(chunk).ok()?;
+    text.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .find_map(extract_usage_from_data)
+}
+
+fn extract_usage_from_data(data: &str) -> Option<Usage> {
+    let value: serde_json::Value = serde_json::from_str(data).ok()?;
+    serde_json::from_value(value.get("usage")?.clone()).ok()
+}
+
+/// Picks the usage out of a Chat Completions stream, reassembling its
+/// events across network reads so a final chunk split between them is
+/// still counted.
+#[derive(Default)]
+pub struct UsageTracker {
+    lines: SseLines,
+    usage: Option<Usage>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        for data in self.lines.push(chunk) {
+            if let Some(usage) = extract_usage_from_data(&data) {
+                self.usage = Some(usage);
+            }
+        }
+    }
+
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+}
@@ -0,0 +1,188 @@
+use bytes::Bytes;
+use futures::StreamExt;
+use relay_core::{AccountProvider, Platform, Relay};
+use relay_openai::{
+    extract_usage_from_chunk, ChatCompletionsRelay, DeepSeekAccount, OpenAICompatibleAccount,
+    UsageTracker,
+};
+use relay_openai_to_anthropic::{ChatCompletionRequest, ChatMessage, MessageContent};
+
+fn account(api_url: String, api_key: Option<String>) -> OpenAICompatibleAccount {
+    OpenAICompatibleAccount::new(
+        "local".to_string(),
+        "Ollama".to_string(),
+        1,
+        true,
+        api_url,
+        api_key,
+        None,
+    )
+    .with_model(Some("llama3.1".to_string()))
+}
+
+fn request(stream: bool) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: "gpt-4o".to_string(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Text("Hello".to_string()),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }],
+        stream,
+        max_tokens: None,
+        temperature: None,
+        top_p: None,
+        stop: None,
+        tools: None,
+        tool_choice: None,
+        stream_options: None,
+        extra: serde_json::Map::new(),
+    }
+}
+
+async fn start_upstream() -> String {
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(
+            |headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                assert_eq!(body["model"], "llama3.1");
+                let authorization = headers
+                    .get("authorization")
+                    .map(|v| v.to_str().unwrap().to_string());
+                if body["stream"] == true {
+                    return concat!(
+                        "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"llama3.1\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\n",
+                        "data: [DONE]\n\n"
+                    )
+                    .to_string();
+                }
+                serde_json::json!({
+                    "id": "c1",
+                    "object": "chat.completion",
+                    "created": 1,
+                    "model": "llama3.1",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": authorization},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+                })
+                .to_string()
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+#[test]
+fn test_account_joins_openai_pool() {
+    let account = account("http://localhost:11434/v1".to_string(), None);
+
+    assert_eq!(account.platform(), Platform::OpenAI);
+    assert_eq!(
+        account.upstream_model("gpt-4o").as_deref(),
+        Some("llama3.1")
+    );
+}
+
+#[test]
+fn test_deepseek_model_mapping() {
+    let account = DeepSeekAccount::new(
+        "deepseek".to_string(),
+        "DeepSeek".to_string(),
+        1,
+        true,
+        "sk-test".to_string(),
+        None,
+        None,
+    );
+    assert_eq!(account.platform(), Platform::OpenAI);
+    assert_eq!(account.api_url(), Some(DeepSeekAccount::DEFAULT_API_URL));
+    assert_eq!(
+        account
+            .upstream_model("claude-sonnet-4-20250514")
+            .as_deref(),
+        Some("deepseek-chat")
+    );
+    assert_eq!(account.upstream_model("deepseek-reasoner"), None);
+
+    let account = account.with_model(Some("deepseek-reasoner".to_string()));
+    assert_eq!(
+        account.upstream_model("gpt-4o").as_deref(),
+        Some("deepseek-reasoner")
+    );
+}
+
+#[tokio::test]
+async fn test_request_is_forwarded_with_model() {
+    let base = start_upstream().await;
+    let relay = ChatCompletionsRelay::new();
+
+    let keyless = account(format!("{}/v1", base), None);
+    let response = relay.relay(&keyless, request(false)).await.unwrap();
+    assert_eq!(response.model, "llama3.1");
+    assert_eq!(response.choices[0].message.content, None);
+    assert_eq!(response.usage.unwrap().total_tokens, 6);
+
+    let keyed = account(format!("{}/v1/", base), Some("sk-local".to_string()));
+    let response = relay.relay(&keyed, request(false)).await.unwrap();
+    assert_eq!(
+        response.choices[0].message.content.as_deref(),
+        Some("Bearer sk-local")
+    );
+}
+
+#[tokio::test]
+async fn test_stream_is_passed_through() {
+    let base = start_upstream().await;
+    let account = account(format!("{}/v1", base), None);
+
+    let mut stream = ChatCompletionsRelay::new()
+        .relay_stream(&account, request(true))
+        .await
+        .unwrap();
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.unwrap());
+    }
+
+    let body = String::from_utf8(body).unwrap();
+    assert!(body.contains("\"content\":\"Hi\""));
+    assert!(body.ends_with("data: [DONE]\n\n"));
+}
+
+#[test]
+fn test_extract_usage_from_chunk() {
+    let chunk = Bytes::from(
+        "data: {\"id\":\"c1\",\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":4,\"total_tokens\":16}}\n\ndata: [DONE]\n\n",
+    );
+    let usage = extract_usage_from_chunk(&chunk).unwrap();
+    assert_eq!(usage.prompt_tokens, 12);
+    assert_eq!(usage.completion_tokens, 4);
+
+    let chunk = Bytes::from("data: {\"id\":\"c1\",\"choices\":[],\"usage\":null}\n\n");
+    assert!(extract_usage_from_chunk(&chunk).is_none());
+}
+
+#[test]
+fn test_usage_tracker_handles_split_chunk() {
+    let stream = "data: {\"id\":\"c1\",\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":4,\"total_tokens\":16}}\n\ndata: [DONE]\n\n";
+    let mut tracker = UsageTracker::new();
+    for piece in stream.as_bytes().chunks(10) {
+        tracker.push(piece);
+    }
+
+    let usage = tracker.usage().unwrap();
+    assert_eq!(usage.prompt_tokens, 12);
+    assert_eq!(usage.completion_tokens, 4);
+}
@@ -0,0 +1,363 @@
+use chrono::{NaiveDate, Utc};
+use parking_lot::Mutex;
+use relay_core::CooldownListener;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+use crate::config::{AlertChannelConfig, AlertsConfig};
+use crate::routes::OAUTH_REFRESH_FAILED;
+use crate::webhook::{WebhookDispatcher, WebhookEvent};
+
+const CHANNEL_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Alert {
+    AccountCooldown {
+        account_id: String,
+        reason: String,
+        cooldown_seconds: u64,
+    },
+    OauthRefreshFailed {
+        account_id: String,
+        cooldown_seconds: u64,
+    },
+    DailyUsageThreshold {
+        date: String,
+        tokens: u64,
+        threshold: u64,
+    },
+}
+
+impl Alert {
+    pub fn message(&self) -> String {
+        match self {
+            Alert::AccountCooldown {
+                account_id,
+                reason,
+                cooldown_seconds,
+            } => format!(
+                "Account {} entered cooldown for {}s ({})",
+                account_id, cooldown_seconds, reason
+            ),
+            Alert::OauthRefreshFailed {
+                account_id,
+                cooldown_seconds,
+            } => format!(
+                "OAuth token refresh failed for account {}; cooling down for {}s",
+                account_id, cooldown_seconds
+            ),
+            Alert::DailyUsageThreshold {
+                date,
+                tokens,
+                threshold,
+            } => format!(
+                "Usage on {} reached {} tokens (threshold {})",
+                date, tokens, threshold
+            ),
+        }
+    }
+}
+
+struct DailyUsage {
+    date: NaiveDate,
+    tokens: u64,
+    alerted: bool,
+}
+
+/// Sends alerts to the configured chat channels and as `alert` webhook
+/// events. Delivery is best-effort: failures are logged, not retried.
+pub struct AlertManager {
+    config: AlertsConfig,
+    webhooks: Arc<WebhookDispatcher>,
+    client: reqwest::Client,
+    daily: Mutex<DailyUsage>,
+}
+
+impl AlertManager {
+    /// `tokens_today` seeds the daily counter so a restart does not re-arm
+    /// an alert that already fired.
+    pub fn new(config: AlertsConfig, webhooks: Arc<WebhookDispatcher>, tokens_today: u64) -> Self {
+        let alerted = config
+            .daily_token_threshold
+            .is_some_and(|threshold| tokens_today >= threshold);
+        Self {
+            config,
+            webhooks,
+            client: reqwest::Client::new(),
+            daily: Mutex::new(DailyUsage {
+                date: Utc::now().date_naive(),
+                tokens: tokens_today,
+                alerted,
+            }),
+        }
+    }
+
+    pub fn fire(&self, alert: Alert) {
+        let text = alert.message();
+        warn!(alert = %text, "Alert fired");
+
+        for channel in &self.config.channels {
+            let client = self.client.clone();
+            let channel = channel.clone();
+            let text = text.clone();
+            tokio::spawn(async move {
+                if let Err(e) = send(&client, &channel, &text).await {
+                    error!(channel = channel.kind(), error = %e, "Failed to send alert");
+                }
+            });
+        }
+        self.webhooks.emit(WebhookEvent::Alert(alert));
+    }
+
+    /// Adds tokens to today's total, alerting once per UTC day when it
+    /// crosses `daily_token_threshold`.
+    pub fn record_usage(&self, tokens: u64) {
+        let Some(threshold) = self.config.daily_token_threshold else {
+            return;
+        };
+        let today = Utc::now().date_naive();
+        let alert = {
+            let mut daily = self.daily.lock();
+            if daily.date != today {
+                *daily = DailyUsage {
+                    date: today,
+                    tokens: 0,
+                    alerted: false,
+                };
+            }
+            daily.tokens += tokens;
+            if daily.alerted || daily.tokens < threshold {
+                return;
+            }
+            daily.alerted = true;
+            Alert::DailyUsageThreshold {
+                date: today.to_string(),
+                tokens: daily.tokens,
+                threshold,
+            }
+        };
+        self.fire(alert);
+    }
+}
+
+impl CooldownListener for AlertManager {
+    fn on_cooldown(&self, account_id: &str, reason: &str, duration: Duration) {
+        let cooldown_seconds = duration.as_secs();
+        let alert = if reason == OAUTH_REFRESH_FAILED {
+            Alert::OauthRefreshFailed {
+                account_id: account_id.to_string(),
+                cooldown_seconds,
+            }
+        } else if cooldown_seconds >= self.config.min_cooldown_seconds {
+            Alert::AccountCooldown {
+                account_id: account_id.to_string(),
+                reason: reason.to_string(),
+                cooldown_seconds,
+            }
+        } else {
+            return;
+        };
+        self.fire(alert);
+    }
+}
+
+async fn send(
+    client: &reqwest::Client,
+    channel: &AlertChannelConfig,
+    text: &str,
+) -> Result<(), reqwest::Error> {
+    let request = match channel {
+        AlertChannelConfig::Slack { webhook_url } => client
+            .post(webhook_url)
+            .json(&serde_json::json!({ "text": text })),
+        AlertChannelConfig::Telegram {
+            bot_token,
+            chat_id,
+            api_url,
+        } => client
+            .post(format!(
+                "{}/bot{}/sendMessage",
+                api_url.trim_end_matches('/'),
+                bot_token
+            ))
+            .json(&serde_json::json!({ "chat_id": chat_id, "text": text })),
+    };
+    request
+        .timeout(Duration::from_secs(CHANNEL_TIMEOUT_SECS))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, DbPool};
+    use axum::{extract::State, routing::post, Json, Router};
+
+    async fn setup_test_db() -> DbPool {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    type Received = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    async fn start_receiver() -> (Received, String) {
+        let received = Received::default();
+        let app = Router::new()
+            .route(
+                "/slack",
+                post(
+                    |State(received): State<Received>, Json(body): Json<serde_json::Value>| async move {
+                        received.lock().push(("slack".to_string(), body));
+                    },
+                ),
+            )
+            .route(
+                "/botTOKEN/sendMessage",
+                post(
+                    |State(received): State<Received>, Json(body): Json<serde_json::Value>| async move {
+                        received.lock().push(("telegram".to_string(), body));
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (received, base)
+    }
+
+    async fn manager(config: AlertsConfig, tokens_today: u64) -> AlertManager {
+        let webhooks = Arc::new(WebhookDispatcher::new(Vec::new(), setup_test_db().await));
+        AlertManager::new(config, webhooks, tokens_today)
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    #[tokio::test]
+    async fn test_alert_is_sent_to_every_channel() {
+        let (received, base) = start_receiver().await;
+        let config = AlertsConfig {
+            channels: vec![
+                AlertChannelConfig::Slack {
+                    webhook_url: format!("{}/slack", base),
+                },
+                AlertChannelConfig::Telegram {
+                    bot_token: "TOKEN".to_string(),
+                    chat_id: "-1001".to_string(),
+                    api_url: format!("{}/", base),
+                },
+            ],
+            ..AlertsConfig::default()
+        };
+        let alerts = manager(config, 0).await;
+
+        alerts.on_cooldown("acc1", OAUTH_REFRESH_FAILED, Duration::from_secs(5));
+
+        wait_for(|| received.lock().len() == 2).await;
+        let received = received.lock();
+        let text = "OAuth token refresh failed for account acc1; cooling down for 5s";
+        let slack = received.iter().find(|(kind, _)| kind == "slack").unwrap();
+        assert_eq!(slack.1["text"], text);
+        let telegram = received
+            .iter()
+            .find(|(kind, _)| kind == "telegram")
+            .unwrap();
+        assert_eq!(telegram.1["chat_id"], "-1001");
+        assert_eq!(telegram.1["text"], text);
+    }
+
+    #[tokio::test]
+    async fn test_short_cooldowns_are_not_alerted() {
+        let (received, base) = start_receiver().await;
+        let config = AlertsConfig {
+            channels: vec![AlertChannelConfig::Slack {
+                webhook_url: format!("{}/slack", base),
+            }],
+            min_cooldown_seconds: 60,
+            daily_token_threshold: None,
+        };
+        let alerts = manager(config, 0).await;
+
+        alerts.on_cooldown("acc1", "rate_limited", Duration::from_secs(30));
+        alerts.on_cooldown("acc1", "unauthorized", Duration::from_secs(1800));
+
+        wait_for(|| !received.lock().is_empty()).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let received = received.lock();
+        assert_eq!(received.len(), 1);
+        assert_eq!(
+            received[0].1["text"],
+            "Account acc1 entered cooldown for 1800s (unauthorized)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_daily_threshold_alerts_once() {
+        let (received, base) = start_receiver().await;
+        let config = AlertsConfig {
+            channels: vec![AlertChannelConfig::Slack {
+                webhook_url: format!("{}/slack", base),
+            }],
+            daily_token_threshold: Some(1000),
+            ..AlertsConfig::default()
+        };
+        let alerts = manager(config, 400).await;
+
+        alerts.record_usage(500);
+        assert!(!alerts.daily.lock().alerted);
+        alerts.record_usage(200);
+        alerts.record_usage(200);
+
+        wait_for(|| !received.lock().is_empty()).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let received = received.lock();
+        assert_eq!(received.len(), 1);
+        let text = received[0].1["text"].as_str().unwrap();
+        assert!(text.ends_with("reached 1100 tokens (threshold 1000)"));
+    }
+
+    #[tokio::test]
+    async fn test_seeded_usage_over_threshold_does_not_realert() {
+        let config = AlertsConfig {
+            daily_token_threshold: Some(1000),
+            ..AlertsConfig::default()
+        };
+        let alerts = manager(config, 1500).await;
+
+        assert!(alerts.daily.lock().alerted);
+    }
+
+    #[test]
+    fn test_alert_serializes_with_kind() {
+        let alert = Alert::AccountCooldown {
+            account_id: "acc1".to_string(),
+            reason: "overloaded".to_string(),
+            cooldown_seconds: 600,
+        };
+        let value = serde_json::to_value(WebhookEvent::Alert(alert)).unwrap();
+        assert_eq!(value["type"], "alert");
+        assert_eq!(value["data"]["kind"], "account_cooldown");
+        assert_eq!(value["data"]["cooldown_seconds"], 600);
+    }
+}
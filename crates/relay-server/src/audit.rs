@@ -0,0 +1,384 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+
+use crate::config::{AuditConfig, AuditSinkKind};
+use crate::db::{self, DbPool};
+
+const CHANNEL_CAPACITY: usize = 1024;
+const JSONL_FILE_NAME: &str = "audit.jsonl";
+
+/// Object keys whose values are never written to the audit log. Keys are
+/// compared lowercased with `-` read as `_`.
+const REDACTED_KEYS: &[&str] = &["apikey", "authorization", "cookie", "password", "token"];
+
+/// Key suffixes that mark a value as a credential (`session_token`,
+/// `x_api_key`, `client_secret`, ...).
+const REDACTED_SUFFIXES: &[&str] = &["_token", "_key", "secret", "password"];
+
+fn is_redacted_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase().replace('-', "_");
+    REDACTED_KEYS.contains(&key.as_str())
+        || REDACTED_SUFFIXES.iter().any(|suffix| key.ends_with(suffix))
+}
+
+/// Who asked what, keyed by the request id also stored in `request_log`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    pub request_id: String,
+    pub timestamp: String,
+    pub client_api_key_hash: String,
+    /// Resolved through trusted proxies.
+    pub client_ip: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub model: Option<String>,
+    pub account_id: Option<String>,
+    pub status_code: u16,
+    pub user_agent: Option<String>,
+    pub request_body: Option<String>,
+    pub response_body: Option<String>,
+}
+
+#[async_trait]
+pub trait AuditSink: Send {
+    async fn write(&mut self, record: &AuditRecord) -> anyhow::Result<()>;
+}
+
+pub struct DatabaseAuditSink {
+    pool: DbPool,
+}
+
+impl DatabaseAuditSink {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AuditSink for DatabaseAuditSink {
+    async fn write(&mut self, record: &AuditRecord) -> anyhow::Result<()> {
+        db::record_audit(&self.pool, record).await?;
+        Ok(())
+    }
+}
+
+/// Appends one JSON object per line and rotates `audit.jsonl` to
+/// `audit.jsonl.1`, `audit.jsonl.2`, ... once it exceeds `max_file_bytes`.
+pub struct JsonlAuditSink {
+    directory: PathBuf,
+    max_file_bytes: u64,
+    max_files: usize,
+    file: Option<tokio::fs::File>,
+    size: u64,
+}
+
+impl JsonlAuditSink {
+    pub fn new(directory: impl Into<PathBuf>, max_file_bytes: u64, max_files: usize) -> Self {
+        Self {
+            directory: directory.into(),
+            max_file_bytes,
+            max_files,
+            file: None,
+            size: 0,
+        }
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.directory.join(JSONL_FILE_NAME)
+        } else {
+            self.directory
+                .join(format!("{}.{}", JSONL_FILE_NAME, index))
+        }
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+
+        if self.max_files == 0 {
+            return tokio::fs::remove_file(self.path(0)).await;
+        }
+
+        let _ = tokio::fs::remove_file(self.path(self.max_files)).await;
+        for index in (1..self.max_files).rev() {
+            let _ = tokio::fs::rename(self.path(index), self.path(index + 1)).await;
+        }
+        tokio::fs::rename(self.path(0), self.path(1)).await
+    }
+
+    async fn open(&mut self) -> std::io::Result<&mut tokio::fs::File> {
+        if self.file.is_none() {
+            tokio::fs::create_dir_all(&self.directory).await?;
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(0))
+                .await?;
+            self.size = file.metadata().await?.len();
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("file was just opened"))
+    }
+}
+
+#[async_trait]
+impl AuditSink for JsonlAuditSink {
+    async fn write(&mut self, record: &AuditRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        self.open().await?;
+        if self.size > 0 && self.size + line.len() as u64 > self.max_file_bytes {
+            self.rotate().await?;
+        }
+
+        let file = self.open().await?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Hands records to a background writer so requests never wait on the sink.
+pub struct AuditLogger {
+    tx: mpsc::Sender<AuditRecord>,
+    include_bodies: bool,
+    max_body_bytes: usize,
+}
+
+impl AuditLogger {
+    pub fn spawn(
+        mut sink: Box<dyn AuditSink>,
+        include_bodies: bool,
+        max_body_bytes: usize,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::channel::<AuditRecord>(CHANNEL_CAPACITY);
+
+        tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                if let Err(e) = sink.write(&record).await {
+                    error!(error = %e, request_id = %record.request_id, "Failed to write audit record");
+                }
+            }
+        });
+
+        Self {
+            tx,
+            include_bodies,
+            max_body_bytes,
+        }
+    }
+
+    pub fn from_config(config: &AuditConfig, pool: DbPool) -> Self {
+        let sink: Box<dyn AuditSink> = match config.sink {
+            AuditSinkKind::Database => Box::new(DatabaseAuditSink::new(pool)),
+            AuditSinkKind::Jsonl => Box::new(JsonlAuditSink::new(
+                &config.directory,
+                config.max_file_bytes,
+                config.max_files,
+            )),
+        };
+        Self::spawn(sink, config.include_bodies, config.max_body_bytes)
+    }
+
+    pub fn include_bodies(&self) -> bool {
+        self.include_bodies
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes
+    }
+
+    pub fn log(&self, record: AuditRecord) {
+        if let Err(e) = self.tx.try_send(record) {
+            warn!(error = %e, "Dropping audit record");
+        }
+    }
+
+    pub fn redact_body(&self, body: &[u8]) -> String {
+        redact_body(body, self.max_body_bytes)
+    }
+}
+
+/// Masks credentials and inline binary payloads in JSON bodies and caps the
+/// result at `max_bytes`. Non-JSON bodies (e.g. SSE) are only truncated.
+pub fn redact_body(body: &[u8], max_bytes: usize) -> String {
+    let text = match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact_value(&mut value, None);
+            value.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+    truncate_utf8(text, max_bytes)
+}
+
+fn redact_value(value: &mut Value, parent_key: Option<&str>) {
+    match value {
+        Value::Object(map) => {
+            let is_base64_source = map.get("type").and_then(|t| t.as_str()) == Some("base64");
+            let is_inline_data = matches!(parent_key, Some("inlineData" | "inline_data"));
+
+            for (key, child) in map.iter_mut() {
+                if is_redacted_key(key) {
+                    *child = Value::String("[REDACTED]".to_string());
+                } else if key == "data" && (is_base64_source || is_inline_data) {
+                    *child = omitted(child);
+                } else {
+                    redact_value(child, Some(key));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact_value(item, parent_key);
+            }
+        }
+        Value::String(s) if s.starts_with("data:") && s.contains(";base64,") => {
+            *value = omitted(value);
+        }
+        _ => {}
+    }
+}
+
+fn omitted(value: &Value) -> Value {
+    let len = value.as_str().map(|s| s.len()).unwrap_or(0);
+    Value::String(format!("[{} bytes omitted]", len))
+}
+
+fn truncate_utf8(mut text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str("...[truncated]");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(request_id: &str) -> AuditRecord {
+        AuditRecord {
+            request_id: request_id.to_string(),
+            timestamp: "2025-01-01T00:00:00Z".to_string(),
+            client_api_key_hash: "hash".to_string(),
+            client_ip: Some("203.0.113.9".to_string()),
+            method: "POST".to_string(),
+            path: "/v1/messages".to_string(),
+            model: Some("claude-sonnet-4-20250514".to_string()),
+            account_id: Some("acc1".to_string()),
+            status_code: 200,
+            user_agent: None,
+            request_body: None,
+            response_body: None,
+        }
+    }
+
+    #[test]
+    fn test_redact_credentials_and_base64() {
+        let body = json!({
+            "model": "claude-sonnet-4-20250514",
+            "max_tokens": 1024,
+            "metadata": {
+                "api_key": "sk-secret",
+                "Authorization": "Bearer x",
+                "session_token": "s",
+                "bot_token": "b",
+                "token": "t",
+                "x-api-key": "k",
+                "user_id": "u-1"
+            },
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}},
+                    {"inlineData": {"mimeType": "image/png", "data": "iVBORw0KGgo="}},
+                    {"type": "text", "text": "Hello"}
+                ]
+            }]
+        });
+
+        let redacted: Value =
+            serde_json::from_str(&redact_body(body.to_string().as_bytes(), 1 << 20)).unwrap();
+
+        assert_eq!(redacted["max_tokens"], 1024);
+        assert_eq!(redacted["metadata"]["api_key"], "[REDACTED]");
+        assert_eq!(redacted["metadata"]["Authorization"], "[REDACTED]");
+        for key in ["session_token", "bot_token", "token", "x-api-key"] {
+            assert_eq!(redacted["metadata"][key], "[REDACTED]", "{key}");
+        }
+        assert_eq!(redacted["metadata"]["user_id"], "u-1");
+        let content = &redacted["messages"][0]["content"];
+        assert_eq!(content[0]["source"]["data"], "[12 bytes omitted]");
+        assert_eq!(content[1]["image_url"]["url"], "[34 bytes omitted]");
+        assert_eq!(content[2]["inlineData"]["data"], "[12 bytes omitted]");
+        assert_eq!(content[3]["text"], "Hello");
+    }
+
+    #[test]
+    fn test_redact_truncates_on_char_boundary() {
+        let body = "data: 你好你好\n\n";
+        let truncated = redact_body(body.as_bytes(), 8);
+        assert_eq!(truncated, "data: ...[truncated]");
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_sink_rotates_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let line_len = serde_json::to_vec(&record("req-0")).unwrap().len() as u64 + 1;
+        let mut sink = JsonlAuditSink::new(dir.path(), line_len * 2, 2);
+
+        for i in 0..7 {
+            sink.write(&record(&format!("req-{}", i))).await.unwrap();
+        }
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("audit.jsonl").lines().count(), 1);
+        assert!(read("audit.jsonl").contains("req-6"));
+        assert!(read("audit.jsonl.1").contains("req-4"));
+        assert!(read("audit.jsonl.2").contains("req-2"));
+        assert!(!dir.path().join("audit.jsonl.3").exists());
+    }
+
+    #[tokio::test]
+    async fn test_database_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let pool = db::init_database(path.to_str().unwrap(), &Default::default())
+            .await
+            .unwrap();
+
+        let mut sink = DatabaseAuditSink::new(pool.clone());
+        let mut entry = record("req-db");
+        entry.request_body = Some("{\"model\":\"x\"}".to_string());
+        sink.write(&entry).await.unwrap();
+
+        let row: (String, Option<String>, Option<String>) =
+            sqlx::query_as("SELECT request_id, account_id, request_body FROM audit_log")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            row,
+            (
+                "req-db".to_string(),
+                Some("acc1".to_string()),
+                Some("{\"model\":\"x\"}".to_string())
+            )
+        );
+    }
+}
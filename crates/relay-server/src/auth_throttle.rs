@@ -0,0 +1,137 @@
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use crate::config::AuthThrottleConfig;
+
+/// Addresses tracked before stale entries are swept.
+const MAX_TRACKED: usize = 10_000;
+
+#[derive(Default)]
+struct Failures {
+    count: u32,
+    window_start: Option<Instant>,
+    banned_until: Option<Instant>,
+}
+
+/// Bans client addresses that fail authentication too often, so keys cannot
+/// be guessed at full speed.
+pub struct AuthThrottle {
+    max_failures: u32,
+    window: Duration,
+    ban: Duration,
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl AuthThrottle {
+    /// `None` unless `max_failures` is set.
+    pub fn new(config: &AuthThrottleConfig) -> Option<Self> {
+        Some(Self {
+            max_failures: config.max_failures?,
+            window: Duration::from_secs(config.window_seconds),
+            ban: Duration::from_secs(config.ban_seconds),
+            failures: Mutex::default(),
+        })
+    }
+
+    /// How much longer `ip` is banned, if it is.
+    pub fn banned_for(&self, ip: IpAddr) -> Option<Duration> {
+        self.banned_for_at(ip, Instant::now())
+    }
+
+    /// Counts a failed attempt from `ip`, returning whether that got it
+    /// banned.
+    pub fn record_failure(&self, ip: IpAddr) -> bool {
+        self.record_failure_at(ip, Instant::now())
+    }
+
+    fn banned_for_at(&self, ip: IpAddr, now: Instant) -> Option<Duration> {
+        let failures = self.failures.lock();
+        let banned_until = failures.get(&ip)?.banned_until?;
+        (banned_until > now).then(|| banned_until - now)
+    }
+
+    fn record_failure_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut failures = self.failures.lock();
+        if failures.len() >= MAX_TRACKED {
+            failures.retain(|_, entry| self.is_active(entry, now));
+        }
+
+        let entry = failures.entry(ip).or_default();
+        if !self.is_active(entry, now) {
+            *entry = Failures {
+                window_start: Some(now),
+                ..Default::default()
+            };
+        }
+        entry.count += 1;
+        if entry.count < self.max_failures || entry.banned_until.is_some() {
+            return false;
+        }
+        entry.banned_until = Some(now + self.ban);
+        true
+    }
+
+    /// Whether an entry still bans or counts towards a ban.
+    fn is_active(&self, entry: &Failures, now: Instant) -> bool {
+        let banned = entry.banned_until.is_some_and(|until| until > now);
+        let counting = entry
+            .window_start
+            .is_some_and(|start| now.duration_since(start) < self.window);
+        banned || (entry.banned_until.is_none() && counting)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle() -> AuthThrottle {
+        AuthThrottle::new(&AuthThrottleConfig {
+            max_failures: Some(3),
+            window_seconds: 60,
+            ban_seconds: 300,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ban_after_max_failures() {
+        let throttle = throttle();
+        let attacker: IpAddr = "203.0.113.9".parse().unwrap();
+        let other: IpAddr = "198.51.100.1".parse().unwrap();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!throttle.record_failure_at(attacker, at(0)));
+        assert!(!throttle.record_failure_at(attacker, at(1)));
+        assert_eq!(throttle.banned_for_at(attacker, at(2)), None);
+        assert!(throttle.record_failure_at(attacker, at(2)));
+        assert_eq!(
+            throttle.banned_for_at(attacker, at(2)),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(throttle.banned_for_at(other, at(2)), None);
+
+        // The ban lapses, and counting starts over
+        assert_eq!(throttle.banned_for_at(attacker, at(302)), None);
+        assert!(!throttle.record_failure_at(attacker, at(303)));
+        assert_eq!(throttle.banned_for_at(attacker, at(303)), None);
+    }
+
+    #[test]
+    fn test_failures_outside_window_do_not_add_up() {
+        let throttle = throttle();
+        let ip: IpAddr = "203.0.113.9".parse().unwrap();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        throttle.record_failure_at(ip, at(0));
+        throttle.record_failure_at(ip, at(30));
+        assert!(!throttle.record_failure_at(ip, at(61)));
+        assert!(!throttle.record_failure_at(ip, at(62)));
+        assert!(throttle.record_failure_at(ip, at(63)));
+        assert!(AuthThrottle::new(&AuthThrottleConfig::default()).is_none());
+    }
+}
@@ -1,16 +1,266 @@
-use relay_core::ProxyConfig;
+use chrono::{DateTime, Utc};
+use relay_claude::ClientProfile;
+use relay_core::{
+    BanditReward, FixedCooldownPolicy, HttpClientOptions, HttpVersion, Platform, ProxyConfig,
+    UpstreamTimeouts, DEFAULT_QUOTA_RESERVE_RATIO, DEFAULT_SERVER_ERROR_THRESHOLD,
+};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
+
+use crate::ip_filter::IpRange;
+use crate::middleware::{KeyScope, HASHED_KEY_PREFIX};
+use crate::rate_limit::RateLimit;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
     #[serde(default)]
-    pub api_keys: Vec<String>,
+    pub api_keys: Vec<ApiKeyConfig>,
+    #[serde(default)]
+    pub api: ApiConfig,
     #[serde(default)]
     pub accounts: Vec<AccountConfig>,
     #[serde(default)]
     pub session: SessionConfig,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub error_budget: ErrorBudgetConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig,
+    #[serde(default)]
+    pub auth_throttle: AuthThrottleConfig,
+    #[serde(default)]
+    pub jwt: JwtConfig,
+    #[serde(default)]
+    pub admin_oidc: Option<AdminOidcConfig>,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub upstream_errors: UpstreamErrorsConfig,
+    #[serde(default)]
+    pub slow_requests: SlowRequestsConfig,
+    #[serde(default)]
+    pub http_client: HttpClientConfig,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub context_limits: ContextLimitsConfig,
+    #[serde(default)]
+    pub responses: ResponsesConfig,
+    #[serde(default)]
+    pub gemini: GeminiConfig,
+    #[serde(default)]
+    pub messages: MessagesConfig,
+    #[serde(default)]
+    pub openai: OpenAIConfig,
+    /// Model-name prefix → USD prices per million tokens, merged over the
+    /// built-in Claude prices.
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPriceConfig>,
+    /// Requested model name → model actually used, for clients with fixed
+    /// model lists.
+    #[serde(default)]
+    pub model_map: HashMap<String, String>,
+    #[serde(default)]
+    pub models: ModelsConfig,
+    #[serde(default)]
+    pub bedrock: BedrockConfig,
+    #[serde(default)]
+    pub vertex: VertexConfig,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+}
+
+/// A group of accounts reserved for the API keys naming it with `tenant`.
+/// Its accounts serve no other keys, and its keys get no other accounts.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantConfig {
+    pub id: String,
+    /// Account ids; each account belongs to at most one tenant.
+    pub accounts: Vec<String>,
+}
+
+/// Which routes need an API key.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiConfig {
+    /// Path patterns served without a key, such as `/gemini/*`, where `*`
+    /// matches any characters. Once set, every other route needs a key,
+    /// even while none are configured or issued.
+    #[serde(default)]
+    pub anonymous_routes: Option<Vec<String>>,
+}
+
+/// A client API key, either a bare string or a table with per-key options.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ApiKeyConfig {
+    Plain(String),
+    Detailed {
+        /// The key, or `sha256:` and its hex SHA-256 digest.
+        key: String,
+        /// Shown instead of the key hash in usage reports and events.
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        profile: ClientProfile,
+        /// Input, output and cache tokens allowed per UTC day.
+        #[serde(default)]
+        max_tokens_per_day: Option<u64>,
+        /// Lets requests choose their account with `x-relay-account`.
+        #[serde(default)]
+        account_pinning: bool,
+        /// Models the key may use, such as `claude-3-5-haiku-*`; empty
+        /// allows every model.
+        #[serde(default)]
+        allowed_models: Vec<String>,
+        /// Route families the key may call, such as `claude`; empty allows
+        /// every platform.
+        #[serde(default)]
+        allowed_platforms: Vec<Platform>,
+        /// `relay`, `usage` and `admin`: what the key may do; empty allows
+        /// `relay` and `usage`.
+        #[serde(default)]
+        scopes: Vec<KeyScope>,
+        /// Client addresses or CIDR blocks the key may be used from; empty
+        /// allows any address.
+        #[serde(default)]
+        allowed_ips: Vec<IpRange>,
+        /// Overrides `[rate_limit]` for this key.
+        #[serde(default)]
+        requests_per_minute: Option<u32>,
+        #[serde(default)]
+        burst: Option<u32>,
+        /// Overrides `[rate_limit] max_concurrent_requests` for this key.
+        #[serde(default)]
+        max_concurrent_requests: Option<u32>,
+        /// The `[[tenants]]` whose accounts serve this key; without one the
+        /// key only gets accounts outside every tenant.
+        #[serde(default)]
+        tenant: Option<String>,
+        /// RFC 3339 time after which the key is rejected.
+        #[serde(default)]
+        expires_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl ApiKeyConfig {
+    pub fn key(&self) -> &str {
+        match self {
+            ApiKeyConfig::Plain(key) => key,
+            ApiKeyConfig::Detailed { key, .. } => key,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ApiKeyConfig::Plain(_) => None,
+            ApiKeyConfig::Detailed { name, .. } => name.as_deref(),
+        }
+    }
+
+    pub fn profile(&self) -> ClientProfile {
+        match self {
+            ApiKeyConfig::Plain(_) => ClientProfile::default(),
+            ApiKeyConfig::Detailed { profile, .. } => *profile,
+        }
+    }
+
+    pub fn max_tokens_per_day(&self) -> Option<u64> {
+        match self {
+            ApiKeyConfig::Plain(_) => None,
+            ApiKeyConfig::Detailed {
+                max_tokens_per_day, ..
+            } => *max_tokens_per_day,
+        }
+    }
+
+    pub fn account_pinning(&self) -> bool {
+        match self {
+            ApiKeyConfig::Plain(_) => false,
+            ApiKeyConfig::Detailed {
+                account_pinning, ..
+            } => *account_pinning,
+        }
+    }
+
+    pub fn allowed_models(&self) -> &[String] {
+        match self {
+            ApiKeyConfig::Plain(_) => &[],
+            ApiKeyConfig::Detailed { allowed_models, .. } => allowed_models,
+        }
+    }
+
+    pub fn allowed_platforms(&self) -> &[Platform] {
+        match self {
+            ApiKeyConfig::Plain(_) => &[],
+            ApiKeyConfig::Detailed {
+                allowed_platforms, ..
+            } => allowed_platforms,
+        }
+    }
+
+    pub fn scopes(&self) -> &[KeyScope] {
+        match self {
+            ApiKeyConfig::Plain(_) => &[],
+            ApiKeyConfig::Detailed { scopes, .. } => scopes,
+        }
+    }
+
+    pub fn allowed_ips(&self) -> &[IpRange] {
+        match self {
+            ApiKeyConfig::Plain(_) => &[],
+            ApiKeyConfig::Detailed { allowed_ips, .. } => allowed_ips,
+        }
+    }
+
+    /// The key's own request rate, if it has one.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        match self {
+            ApiKeyConfig::Plain(_) => None,
+            ApiKeyConfig::Detailed {
+                requests_per_minute,
+                burst,
+                ..
+            } => RateLimit::new(*requests_per_minute, *burst),
+        }
+    }
+
+    pub fn tenant(&self) -> Option<&str> {
+        match self {
+            ApiKeyConfig::Plain(_) => None,
+            ApiKeyConfig::Detailed { tenant, .. } => tenant.as_deref(),
+        }
+    }
+
+    pub fn max_concurrent_requests(&self) -> Option<u32> {
+        match self {
+            ApiKeyConfig::Plain(_) => None,
+            ApiKeyConfig::Detailed {
+                max_concurrent_requests,
+                ..
+            } => *max_concurrent_requests,
+        }
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            ApiKeyConfig::Plain(_) => None,
+            ApiKeyConfig::Detailed { expires_at, .. } => *expires_at,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -23,6 +273,128 @@ pub struct ServerConfig {
     pub database_path: String,
     #[serde(default = "default_log_level")]
     pub log_level: String,
+    /// Replaces message content, system prompts and tool arguments in
+    /// debug and trace logs with their lengths and hashes.
+    #[serde(default)]
+    pub redact_log_content: bool,
+    /// Paths served without an API key, such as `/v1/models` for clients
+    /// that list models before a key is entered.
+    #[serde(default)]
+    pub public_paths: Vec<String>,
+    /// Serves `/ws/v1/messages`, streaming over a WebSocket for clients
+    /// behind proxies that buffer SSE.
+    #[serde(default)]
+    pub websocket: bool,
+    /// Sends an SSE comment frame on streams idle for this long, so proxies
+    /// do not drop them during long thinking pauses. `0` disables it.
+    #[serde(default = "default_sse_keep_alive_seconds")]
+    pub sse_keep_alive_seconds: u64,
+    #[serde(default)]
+    pub stream_mode: StreamMode,
+    /// Chunks held between the upstream and a slow client in `buffered`
+    /// mode.
+    #[serde(default = "default_stream_buffer")]
+    pub stream_buffer: usize,
+    /// On SIGTERM or Ctrl+C, how long requests in flight may take to finish
+    /// before the streams still running are ended.
+    #[serde(default = "default_shutdown_grace")]
+    pub shutdown_grace_seconds: u64,
+    /// PEM certificate chain; with `tls_key`, the relay serves HTTPS itself
+    /// and reloads the pair when the files are replaced.
+    #[serde(default)]
+    pub tls_cert: Option<String>,
+    /// PEM private key for `tls_cert`.
+    #[serde(default)]
+    pub tls_key: Option<String>,
+    /// PEM CA bundle for client certificates. Requests on a connection with
+    /// a verified certificate need no API key.
+    #[serde(default)]
+    pub tls_client_ca: Option<String>,
+    #[serde(default)]
+    pub tls_client_auth: TlsClientAuth,
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// Authenticates `/admin` routes instead of client API keys, which then
+    /// cannot use them. Plaintext or `sha256:<hex>`.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Serves `/admin` routes on this port only, away from client traffic.
+    /// Requires `admin_token`.
+    #[serde(default)]
+    pub admin_port: Option<u16>,
+}
+
+/// Cross-origin access for browser clients, off without allowed origins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CorsConfig {
+    /// Origins such as `https://chat.example.com`, or `*` for any.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    /// Request headers browsers may send, or `*` for any.
+    #[serde(default = "default_cors_headers")]
+    pub allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight answer.
+    #[serde(default = "default_cors_max_age")]
+    pub max_age_seconds: u64,
+}
+
+fn default_cors_methods() -> Vec<String> {
+    ["GET", "POST"].map(String::from).to_vec()
+}
+
+fn default_cors_headers() -> Vec<String> {
+    [
+        "authorization",
+        "content-type",
+        "x-api-key",
+        "x-goog-api-key",
+        "anthropic-version",
+        "anthropic-beta",
+        "anthropic-dangerous-direct-browser-access",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_cors_max_age() -> u64 {
+    3600
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: default_cors_methods(),
+            allowed_headers: default_cors_headers(),
+            max_age_seconds: default_cors_max_age(),
+        }
+    }
+}
+
+/// How relayed streams reach the client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamMode {
+    /// A task reads the upstream into a `stream_buffer`-chunk channel, so a
+    /// slow client does not hold up the upstream until the buffer fills.
+    #[default]
+    Buffered,
+    /// Streams relayed unchanged are read from the upstream only as the
+    /// client takes them, with no task or buffer in between. Converted
+    /// streams are still buffered.
+    Direct,
+}
+
+/// Whether TLS clients must present a certificate from `tls_client_ca`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsClientAuth {
+    #[default]
+    Required,
+    /// Clients without a certificate may connect and use an API key.
+    Optional,
 }
 
 fn default_host() -> String {
@@ -33,6 +405,18 @@ fn default_port() -> u16 {
     3000
 }
 
+fn default_sse_keep_alive_seconds() -> u64 {
+    15
+}
+
+fn default_stream_buffer() -> usize {
+    32
+}
+
+fn default_shutdown_grace() -> u64 {
+    30
+}
+
 fn default_db_path() -> String {
     "data/relay.db".to_string()
 }
@@ -48,10 +432,30 @@ impl Default for ServerConfig {
             port: default_port(),
             database_path: default_db_path(),
             log_level: default_log_level(),
+            redact_log_content: false,
+            public_paths: Vec::new(),
+            websocket: false,
+            sse_keep_alive_seconds: default_sse_keep_alive_seconds(),
+            stream_mode: StreamMode::default(),
+            stream_buffer: default_stream_buffer(),
+            shutdown_grace_seconds: default_shutdown_grace(),
+            tls_cert: None,
+            tls_key: None,
+            tls_client_ca: None,
+            tls_client_auth: TlsClientAuth::default(),
+            cors: CorsConfig::default(),
+            admin_token: None,
+            admin_port: None,
         }
     }
 }
 
+impl ServerConfig {
+    pub fn sse_keep_alive(&self) -> Option<Duration> {
+        (self.sse_keep_alive_seconds > 0).then(|| Duration::from_secs(self.sse_keep_alive_seconds))
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum AccountConfig {
@@ -62,11 +466,52 @@ pub enum AccountConfig {
         priority: u32,
         #[serde(default = "default_enabled")]
         enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
         refresh_token: String,
         #[serde(default)]
         api_url: Option<String>,
         #[serde(default)]
         proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+        /// Server tools, such as `web_search`, sent to this account; others
+        /// are stripped from requests. Unset sends every server tool.
+        #[serde(default)]
+        server_tools: Option<Vec<String>>,
+    },
+    /// A claude.ai account without API or OAuth access, authenticated with
+    /// its `sessionKey` cookie.
+    ClaudeSession {
+        id: String,
+        name: String,
+        #[serde(default = "default_priority")]
+        priority: u32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
+        session_key: String,
+        #[serde(default)]
+        api_url: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+        /// Server tools, such as `web_search`, sent to this account; others
+        /// are stripped from requests. Unset sends every server tool.
+        #[serde(default)]
+        server_tools: Option<Vec<String>>,
     },
     ClaudeApi {
         id: String,
@@ -75,11 +520,95 @@ pub enum AccountConfig {
         priority: u32,
         #[serde(default = "default_enabled")]
         enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
         api_key: String,
         #[serde(default)]
         api_url: Option<String>,
         #[serde(default)]
         proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+        /// Server tools, such as `web_search`, sent to this account; others
+        /// are stripped from requests. Unset sends every server tool.
+        #[serde(default)]
+        server_tools: Option<Vec<String>>,
+    },
+    /// Anthropic models on AWS Bedrock, signed with the account's IAM keys.
+    Bedrock {
+        id: String,
+        name: String,
+        #[serde(default = "default_priority")]
+        priority: u32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+        /// Set for temporary (STS) credentials.
+        #[serde(default)]
+        session_token: Option<String>,
+        /// Replaces `https://bedrock-runtime.{region}.amazonaws.com`.
+        #[serde(default)]
+        api_url: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+        /// Server tools, such as `web_search`, sent to this account; others
+        /// are stripped from requests. Unset sends every server tool.
+        #[serde(default)]
+        server_tools: Option<Vec<String>>,
+    },
+    /// Claude or Gemini models on Google Cloud Vertex AI, authenticated with
+    /// a service account key.
+    Vertex {
+        id: String,
+        name: String,
+        #[serde(default = "default_priority")]
+        priority: u32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
+        /// Account pool the account joins.
+        #[serde(default)]
+        platform: VertexPlatform,
+        /// Path to the service account's JSON key.
+        service_account_file: String,
+        /// Defaults to the key's project.
+        #[serde(default)]
+        project_id: Option<String>,
+        /// Vertex AI location, such as `us-east5` or `global`.
+        region: String,
+        /// Replaces `https://{region}-aiplatform.googleapis.com`.
+        #[serde(default)]
+        api_url: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+        /// Server tools, such as `web_search`, sent to this account; others
+        /// are stripped from requests. Unset sends every server tool.
+        #[serde(default)]
+        server_tools: Option<Vec<String>>,
     },
     Gemini {
         id: String,
@@ -88,11 +617,23 @@ pub enum AccountConfig {
         priority: u32,
         #[serde(default = "default_enabled")]
         enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
         refresh_token: String,
         #[serde(default)]
         api_url: Option<String>,
+        /// Upstream API version, such as `v1beta`; defaults to `v1`.
+        #[serde(default)]
+        api_version: Option<String>,
         #[serde(default)]
         proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
     },
     OpenaiResponses {
         id: String,
@@ -101,232 +642,3146 @@ pub enum AccountConfig {
         priority: u32,
         #[serde(default = "default_enabled")]
         enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
         api_key: String,
         #[serde(default)]
         api_url: Option<String>,
         #[serde(default)]
         proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
     },
-}
-
-fn default_priority() -> u32 {
-    100
-}
-
-fn default_enabled() -> bool {
-    true
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct SessionConfig {
-    #[serde(default = "default_sticky_ttl")]
-    pub sticky_ttl_seconds: u64,
-    #[serde(default = "default_renewal_threshold")]
-    pub renewal_threshold_seconds: u64,
-    #[serde(default = "default_unavailable_cooldown")]
-    pub unavailable_cooldown_seconds: u64,
-}
-
-fn default_sticky_ttl() -> u64 {
-    3600
-}
-
-fn default_renewal_threshold() -> u64 {
-    300
-}
-
-fn default_unavailable_cooldown() -> u64 {
-    3600
-}
-
-impl Default for SessionConfig {
-    fn default() -> Self {
-        Self {
-            sticky_ttl_seconds: default_sticky_ttl(),
-            renewal_threshold_seconds: default_renewal_threshold(),
-            unavailable_cooldown_seconds: default_unavailable_cooldown(),
-        }
-    }
-}
-
-impl Config {
-    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
-        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| ConfigError::Io {
-            path: path.as_ref().display().to_string(),
-            source: e,
-        })?;
-
-        let config: Config =
-            toml::from_str(&content).map_err(|e| ConfigError::Parse { source: e })?;
-
-        config.validate()?;
-        Ok(config)
-    }
-
-    fn validate(&self) -> Result<(), ConfigError> {
-        if self.accounts.is_empty() {
-            return Err(ConfigError::Validation(
-                "At least one account must be configured".to_string(),
-            ));
+    /// One Azure OpenAI deployment, serving Codex requests.
+    AzureOpenai {
+        id: String,
+        name: String,
+        #[serde(default = "default_priority")]
+        priority: u32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
+        api_key: String,
+        /// Resource endpoint, such as `https://my-resource.openai.azure.com`.
+        api_url: String,
+        deployment: String,
+        #[serde(default)]
+        api_version: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+    },
+    /// An OpenRouter API key in the Claude pool, usually given a low
+    /// priority as overflow capacity.
+    Openrouter {
+        id: String,
+        name: String,
+        #[serde(default = "default_priority")]
+        priority: u32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
+        api_key: String,
+        /// Replaces `https://openrouter.ai/api`.
+        #[serde(default)]
+        api_url: Option<String>,
+        /// Attribution headers shown on openrouter.ai.
+        #[serde(default)]
+        http_referer: Option<String>,
+        #[serde(default)]
+        x_title: Option<String>,
+        /// OpenRouter model ids for Anthropic model names, such as
+        /// `claude-opus-4-1-20250805` to `anthropic/claude-opus-4.1`.
+        #[serde(default)]
+        model_ids: HashMap<String, String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+        /// Server tools, such as `web_search`, sent to this account; others
+        /// are stripped from requests. Unset sends every server tool.
+        #[serde(default)]
+        server_tools: Option<Vec<String>>,
+    },
+    /// A local server with an OpenAI-compatible API, such as Ollama, vLLM or
+    /// LM Studio, used once no Claude account is available.
+    OpenaiCompatible {
+        id: String,
+        name: String,
+        #[serde(default = "default_priority")]
+        priority: u32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
+        /// Base URL including the version, such as `http://localhost:11434/v1`.
+        api_url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        /// Served in place of every requested model.
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+    },
+    /// A DeepSeek API key, pooled with the OpenAI-compatible accounts.
+    Deepseek {
+        id: String,
+        name: String,
+        #[serde(default = "default_priority")]
+        priority: u32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        /// Serve existing sticky sessions but assign no new ones.
+        #[serde(default)]
+        draining: bool,
+        api_key: String,
+        /// Replaces `https://api.deepseek.com/v1`.
+        #[serde(default)]
+        api_url: Option<String>,
+        /// Served in place of non-DeepSeek models; defaults to
+        /// `deepseek-chat`.
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        proxy: Option<ProxyConfig>,
+        /// Replaces `[timeouts] request_seconds` for this account.
+        #[serde(default)]
+        request_timeout_seconds: Option<u64>,
+        /// Models listed by the `models` endpoints.
+        #[serde(default)]
+        models: Vec<String>,
+    },
+}
+
+impl AccountConfig {
+    pub fn id(&self) -> &str {
+        match self {
+            AccountConfig::ClaudeOauth { id, .. }
+            | AccountConfig::ClaudeSession { id, .. }
+            | AccountConfig::ClaudeApi { id, .. }
+            | AccountConfig::Bedrock { id, .. }
+            | AccountConfig::Vertex { id, .. }
+            | AccountConfig::Gemini { id, .. }
+            | AccountConfig::OpenaiResponses { id, .. }
+            | AccountConfig::AzureOpenai { id, .. }
+            | AccountConfig::Openrouter { id, .. }
+            | AccountConfig::OpenaiCompatible { id, .. }
+            | AccountConfig::Deepseek { id, .. } => id,
         }
+    }
 
-        let mut ids = std::collections::HashSet::new();
-        for account in &self.accounts {
-            let id = match account {
-                AccountConfig::ClaudeOauth { id, .. } => id,
-                AccountConfig::ClaudeApi { id, .. } => id,
-                AccountConfig::Gemini { id, .. } => id,
-                AccountConfig::OpenaiResponses { id, .. } => id,
-            };
-            if !ids.insert(id.clone()) {
-                return Err(ConfigError::Validation(format!(
-                    "Duplicate account ID: {}",
-                    id
-                )));
+    pub fn draining(&self) -> bool {
+        match self {
+            AccountConfig::ClaudeOauth { draining, .. }
+            | AccountConfig::ClaudeSession { draining, .. }
+            | AccountConfig::ClaudeApi { draining, .. }
+            | AccountConfig::Bedrock { draining, .. }
+            | AccountConfig::Vertex { draining, .. }
+            | AccountConfig::Gemini { draining, .. }
+            | AccountConfig::OpenaiResponses { draining, .. }
+            | AccountConfig::AzureOpenai { draining, .. }
+            | AccountConfig::Openrouter { draining, .. }
+            | AccountConfig::OpenaiCompatible { draining, .. }
+            | AccountConfig::Deepseek { draining, .. } => *draining,
+        }
+    }
+
+    pub fn models(&self) -> &[String] {
+        match self {
+            AccountConfig::ClaudeOauth { models, .. }
+            | AccountConfig::ClaudeSession { models, .. }
+            | AccountConfig::ClaudeApi { models, .. }
+            | AccountConfig::Bedrock { models, .. }
+            | AccountConfig::Vertex { models, .. }
+            | AccountConfig::Gemini { models, .. }
+            | AccountConfig::OpenaiResponses { models, .. }
+            | AccountConfig::AzureOpenai { models, .. }
+            | AccountConfig::Openrouter { models, .. }
+            | AccountConfig::OpenaiCompatible { models, .. }
+            | AccountConfig::Deepseek { models, .. } => models,
+        }
+    }
+
+    pub fn request_timeout_seconds(&self) -> Option<u64> {
+        match self {
+            AccountConfig::ClaudeOauth {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::ClaudeSession {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::ClaudeApi {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::Bedrock {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::Vertex {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::Gemini {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::OpenaiResponses {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::AzureOpenai {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::Openrouter {
+                request_timeout_seconds,
+                ..
+            }
+            | AccountConfig::OpenaiCompatible {
+                request_timeout_seconds,
+                ..
             }
+            | AccountConfig::Deepseek {
+                request_timeout_seconds,
+                ..
+            } => *request_timeout_seconds,
+        }
+    }
+
+    /// Server tools a Claude account accepts, or `None` for all of them.
+    pub fn server_tools(&self) -> Option<&[String]> {
+        match self {
+            AccountConfig::ClaudeOauth { server_tools, .. }
+            | AccountConfig::ClaudeSession { server_tools, .. }
+            | AccountConfig::ClaudeApi { server_tools, .. }
+            | AccountConfig::Bedrock { server_tools, .. }
+            | AccountConfig::Vertex { server_tools, .. }
+            | AccountConfig::Openrouter { server_tools, .. } => server_tools.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+fn default_priority() -> u32 {
+    100
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatabaseConfig {
+    /// Age after which `usage_stats`, `request_log` and `audit_log` rows are
+    /// purged; unset keeps them forever.
+    #[serde(default)]
+    pub usage_retention_days: Option<u64>,
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    /// How long a connection waits for a lock held by another before
+    /// failing with `database is locked`.
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+    /// Write-ahead logging lets readers run alongside the writer.
+    #[serde(default = "default_enabled")]
+    pub wal: bool,
+    #[serde(default)]
+    pub synchronous: SynchronousMode,
+    /// Usage rows are buffered and written in one transaction at this
+    /// interval; 0 writes every request's usage immediately.
+    #[serde(default = "default_usage_flush_interval_ms")]
+    pub usage_flush_interval_ms: u64,
+    /// Buffered rows that trigger a flush before the interval is up.
+    #[serde(default = "default_usage_batch_size")]
+    pub usage_batch_size: usize,
+}
+
+fn default_max_connections() -> u32 {
+    10
+}
+
+fn default_busy_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_usage_flush_interval_ms() -> u64 {
+    1000
+}
+
+fn default_usage_batch_size() -> usize {
+    200
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            usage_retention_days: None,
+            max_connections: default_max_connections(),
+            busy_timeout_ms: default_busy_timeout_ms(),
+            wal: default_enabled(),
+            synchronous: SynchronousMode::default(),
+            usage_flush_interval_ms: default_usage_flush_interval_ms(),
+            usage_batch_size: default_usage_batch_size(),
+        }
+    }
+}
+
+/// SQLite `synchronous` pragma.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SynchronousMode {
+    Off,
+    /// Safe with WAL; only the last commits may be lost on power failure.
+    #[default]
+    Normal,
+    Full,
+    Extra,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulingStrategy {
+    /// Highest priority first, least recently used among equals.
+    #[default]
+    Priority,
+    /// Experimental: learn the best account from observed rewards.
+    Bandit,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default)]
+    pub strategy: SchedulingStrategy,
+    #[serde(default)]
+    pub bandit: BanditConfig,
+    /// Accounts whose `anthropic-ratelimit-*` headers report less than this
+    /// share of a limit left are only picked when no other account is.
+    #[serde(default = "default_quota_reserve_ratio")]
+    pub quota_reserve_ratio: f64,
+}
+
+fn default_quota_reserve_ratio() -> f64 {
+    DEFAULT_QUOTA_RESERVE_RATIO
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            strategy: SchedulingStrategy::default(),
+            bandit: BanditConfig::default(),
+            quota_reserve_ratio: default_quota_reserve_ratio(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BanditConfig {
+    #[serde(default)]
+    pub reward: BanditReward,
+    /// Share of new sessions sent to a random account instead of the best one.
+    #[serde(default = "default_exploration_rate")]
+    pub exploration_rate: f64,
+}
+
+fn default_exploration_rate() -> f64 {
+    0.1
+}
+
+impl Default for BanditConfig {
+    fn default() -> Self {
+        Self {
+            reward: BanditReward::default(),
+            exploration_rate: default_exploration_rate(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionConfig {
+    #[serde(default = "default_sticky_ttl")]
+    pub sticky_ttl_seconds: u64,
+    #[serde(default = "default_renewal_threshold")]
+    pub renewal_threshold_seconds: u64,
+    #[serde(default = "default_unavailable_cooldown")]
+    pub unavailable_cooldown_seconds: u64,
+}
+
+fn default_sticky_ttl() -> u64 {
+    3600
+}
+
+fn default_renewal_threshold() -> u64 {
+    300
+}
+
+fn default_unavailable_cooldown() -> u64 {
+    3600
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            sticky_ttl_seconds: default_sticky_ttl(),
+            renewal_threshold_seconds: default_renewal_threshold(),
+            unavailable_cooldown_seconds: default_unavailable_cooldown(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSinkKind {
+    #[default]
+    Database,
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub sink: AuditSinkKind,
+    /// Store redacted request/response bodies, not just metadata.
+    #[serde(default)]
+    pub include_bodies: bool,
+    #[serde(default = "default_audit_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Directory for the `jsonl` sink.
+    #[serde(default = "default_audit_directory")]
+    pub directory: String,
+    #[serde(default = "default_audit_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Rotated files kept besides the active one.
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+}
+
+fn default_audit_max_body_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_audit_directory() -> String {
+    "data/audit".to_string()
+}
+
+fn default_audit_max_file_bytes() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_audit_max_files() -> usize {
+    10
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sink: AuditSinkKind::default(),
+            include_bodies: false,
+            max_body_bytes: default_audit_max_body_bytes(),
+            directory: default_audit_directory(),
+            max_file_bytes: default_audit_max_file_bytes(),
+            max_files: default_audit_max_files(),
+        }
+    }
+}
+
+/// Per-account error budgets over rolling windows. Budgets are always
+/// tracked; the circuit breaker only acts when enabled.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorBudgetConfig {
+    #[serde(default = "default_error_budget_windows")]
+    pub windows_seconds: Vec<u64>,
+    /// Requests needed in a window before its rates are judged.
+    #[serde(default = "default_error_budget_min_requests")]
+    pub min_requests: u64,
+    #[serde(default = "default_max_server_error_rate")]
+    pub max_server_error_rate: f64,
+    #[serde(default = "default_max_timeout_rate")]
+    pub max_timeout_rate: f64,
+    /// Put an account in cooldown when the shortest window exhausts its budget.
+    #[serde(default)]
+    pub circuit_breaker: bool,
+    #[serde(default = "default_breaker_cooldown")]
+    pub breaker_cooldown_seconds: u64,
+}
+
+fn default_error_budget_windows() -> Vec<u64> {
+    vec![300, 3600]
+}
+
+fn default_error_budget_min_requests() -> u64 {
+    20
+}
+
+fn default_max_server_error_rate() -> f64 {
+    0.25
+}
+
+fn default_max_timeout_rate() -> f64 {
+    0.25
+}
+
+fn default_breaker_cooldown() -> u64 {
+    300
+}
+
+impl Default for ErrorBudgetConfig {
+    fn default() -> Self {
+        Self {
+            windows_seconds: default_error_budget_windows(),
+            min_requests: default_error_budget_min_requests(),
+            max_server_error_rate: default_max_server_error_rate(),
+            max_timeout_rate: default_max_timeout_rate(),
+            circuit_breaker: false,
+            breaker_cooldown_seconds: default_breaker_cooldown(),
+        }
+    }
+}
+
+/// Request rate for client keys without a limit of their own.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RateLimitConfig {
+    /// Requests per minute per key; unset means no limit.
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Requests a key may send at once after idling; defaults to
+    /// `requests_per_minute`.
+    #[serde(default)]
+    pub burst: Option<u32>,
+    /// Requests a key may have in flight at once, streams included; unset
+    /// means no limit.
+    #[serde(default)]
+    pub max_concurrent_requests: Option<u32>,
+}
+
+/// Bans client addresses after repeated failed authentication.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthThrottleConfig {
+    /// Failed attempts with a key that get an address banned; unset means
+    /// no throttling.
+    #[serde(default)]
+    pub max_failures: Option<u32>,
+    /// Failures further apart than this do not add up.
+    #[serde(default = "default_auth_throttle_window")]
+    pub window_seconds: u64,
+    /// How long a banned address gets 429 without its key being checked.
+    #[serde(default = "default_auth_throttle_ban")]
+    pub ban_seconds: u64,
+}
+
+fn default_auth_throttle_window() -> u64 {
+    300
+}
+
+fn default_auth_throttle_ban() -> u64 {
+    900
+}
+
+impl Default for AuthThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: None,
+            window_seconds: default_auth_throttle_window(),
+            ban_seconds: default_auth_throttle_ban(),
+        }
+    }
+}
+
+/// Client addresses the relay serves, checked before the API key.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IpFilterConfig {
+    /// Addresses or CIDR blocks allowed in; empty allows any address not
+    /// denied.
+    #[serde(default)]
+    pub allow: Vec<IpRange>,
+    /// Rejected even when allowed.
+    #[serde(default)]
+    pub deny: Vec<IpRange>,
+    /// Reverse proxies whose `X-Forwarded-For`, `Forwarded` and `X-Real-IP`
+    /// headers name the client, for IP rules, rate limits and audit logs;
+    /// other peers are taken as the client themselves.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpRange>,
+}
+
+/// Bearer JWTs, such as service tokens from an SSO provider, accepted in
+/// place of API keys.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtConfig {
+    /// Required `iss` claim.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Required in the `aud` claim.
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// Claim recorded as the usage identity.
+    #[serde(default = "default_subject_claim")]
+    pub subject_claim: String,
+    /// Clock skew tolerated for `exp` and `nbf`.
+    #[serde(default = "default_jwt_leeway")]
+    pub leeway_seconds: u64,
+    /// Verification keys; JWTs are not accepted without any.
+    #[serde(default)]
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+fn default_subject_claim() -> String {
+    "sub".to_string()
+}
+
+fn default_jwt_leeway() -> u64 {
+    60
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            issuer: None,
+            audience: None,
+            subject_claim: default_subject_claim(),
+            leeway_seconds: default_jwt_leeway(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+/// A JWT verification key. A key with a `kid` only verifies tokens naming
+/// it; one without verifies any token of its algorithm.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "algorithm")]
+pub enum JwtKeyConfig {
+    HS256 {
+        #[serde(default)]
+        kid: Option<String>,
+        secret: String,
+    },
+    RS256 {
+        #[serde(default)]
+        kid: Option<String>,
+        /// PEM file with the `BEGIN PUBLIC KEY` block.
+        public_key_file: String,
+    },
+}
+
+/// Browser login to the `/admin` routes through an OpenID Connect provider
+/// such as Google or Keycloak, kept as a session cookie.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdminOidcConfig {
+    /// Its `/.well-known/openid-configuration` names the endpoints.
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// This relay's `/admin/oidc/callback`, as registered with the provider.
+    pub redirect_url: String,
+    /// Verified email addresses let in.
+    #[serde(default)]
+    pub allowed_emails: Vec<String>,
+    /// Email domains whose verified addresses are let in.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    #[serde(default = "default_admin_session_ttl")]
+    pub session_ttl_seconds: u64,
+}
+
+fn default_admin_session_ttl() -> u64 {
+    8 * 3600
+}
+
+/// How long an upstream request may take, body included, before the client
+/// gets a 504.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimeoutsConfig {
+    #[serde(default = "default_request_timeout")]
+    pub request_seconds: u64,
+    /// Model-name prefix → timeout in seconds, for models that think for
+    /// long; the longest matching prefix wins over account timeouts.
+    #[serde(default)]
+    pub models: HashMap<String, u64>,
+    /// How long a stream may send nothing before the account is cooled
+    /// down and the request retried on another; 0 waits for the request
+    /// timeout.
+    #[serde(default)]
+    pub first_byte_seconds: u64,
+    #[serde(default = "default_first_byte_cooldown")]
+    pub first_byte_cooldown_seconds: u64,
+}
+
+fn default_request_timeout() -> u64 {
+    600
+}
+
+fn default_first_byte_cooldown() -> u64 {
+    60
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            request_seconds: default_request_timeout(),
+            models: HashMap::new(),
+            first_byte_seconds: 0,
+            first_byte_cooldown_seconds: default_first_byte_cooldown(),
+        }
+    }
+}
+
+impl TimeoutsConfig {
+    pub fn first_byte(&self) -> Option<Duration> {
+        (self.first_byte_seconds > 0).then(|| Duration::from_secs(self.first_byte_seconds))
+    }
+}
+
+/// How long a request waits before it is retried on another account.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryConfig {
+    /// Wait before the first retry, doubled for each one after up to
+    /// `max_backoff_ms`, with jitter; 0 retries at once.
+    #[serde(default = "default_retry_backoff")]
+    pub backoff_ms: u64,
+    #[serde(default = "default_retry_max_backoff")]
+    pub max_backoff_ms: u64,
+    /// Once every account tried has been rate limited, the limit looks
+    /// shared, and the upstream's `Retry-After` is waited out instead, up to
+    /// this long.
+    #[serde(default = "default_max_retry_after")]
+    pub max_retry_after_seconds: u64,
+}
+
+fn default_retry_backoff() -> u64 {
+    200
+}
+
+fn default_retry_max_backoff() -> u64 {
+    2000
+}
+
+fn default_max_retry_after() -> u64 {
+    10
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            backoff_ms: default_retry_backoff(),
+            max_backoff_ms: default_retry_max_backoff(),
+            max_retry_after_seconds: default_max_retry_after(),
+        }
+    }
+}
+
+/// How upstream error statuses are treated. Third-party `api_url`
+/// endpoints do not always mean the same by a 429 or 529 as Anthropic.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpstreamErrorsConfig {
+    /// Cooldown of an account after a 429 whose headers named no wait.
+    #[serde(default = "default_rate_limit_cooldown")]
+    pub rate_limit_cooldown_seconds: u64,
+    /// Cooldown of an account after a 529 whose headers named no wait.
+    #[serde(default = "default_overload_cooldown")]
+    pub overload_cooldown_seconds: u64,
+    /// Longest cooldown a `Retry-After` or rate limit reset header may ask
+    /// for.
+    #[serde(default = "default_max_cooldown")]
+    pub max_cooldown_seconds: u64,
+    /// Upstream statuses a request is retried on another account for.
+    /// Credential and quota failures are retried regardless.
+    #[serde(default = "default_retryable_statuses")]
+    pub retryable_statuses: Vec<u16>,
+    /// Upstream 5xx errors in a row after which an account cools down; 0
+    /// never cools one down for them.
+    #[serde(default = "default_server_error_threshold")]
+    pub server_error_threshold: u32,
+    /// The first such cooldown, doubled for each further 5xx until a
+    /// request on the account succeeds, up to the max.
+    #[serde(default = "default_server_error_cooldown")]
+    pub server_error_cooldown_seconds: u64,
+    #[serde(default = "default_max_server_error_cooldown")]
+    pub max_server_error_cooldown_seconds: u64,
+}
+
+fn default_rate_limit_cooldown() -> u64 {
+    60
+}
+
+fn default_overload_cooldown() -> u64 {
+    300
+}
+
+fn default_max_cooldown() -> u64 {
+    6 * 60 * 60
+}
+
+fn default_retryable_statuses() -> Vec<u16> {
+    vec![429, 529]
+}
+
+fn default_server_error_threshold() -> u32 {
+    DEFAULT_SERVER_ERROR_THRESHOLD
+}
+
+fn default_server_error_cooldown() -> u64 {
+    30
+}
+
+fn default_max_server_error_cooldown() -> u64 {
+    600
+}
+
+impl Default for UpstreamErrorsConfig {
+    fn default() -> Self {
+        Self {
+            rate_limit_cooldown_seconds: default_rate_limit_cooldown(),
+            overload_cooldown_seconds: default_overload_cooldown(),
+            max_cooldown_seconds: default_max_cooldown(),
+            retryable_statuses: default_retryable_statuses(),
+            server_error_threshold: default_server_error_threshold(),
+            server_error_cooldown_seconds: default_server_error_cooldown(),
+            max_server_error_cooldown_seconds: default_max_server_error_cooldown(),
+        }
+    }
+}
+
+/// Latency above which a relayed request is logged and counted as slow;
+/// 0 turns a threshold off. Streams are timed until they end.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SlowRequestsConfig {
+    #[serde(default)]
+    pub threshold_seconds: u64,
+    #[serde(default)]
+    pub stream_threshold_seconds: u64,
+}
+
+/// Connections of the upstream HTTP clients.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpClientConfig {
+    #[serde(default)]
+    pub http_version: HttpVersionMode,
+    /// How long an idle connection stays pooled; 0 keeps it until the
+    /// upstream closes it.
+    #[serde(default = "default_pool_idle_timeout")]
+    pub pool_idle_timeout_seconds: u64,
+    /// Idle connections kept per upstream host; unset keeps all of them.
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// 0 turns TCP keepalive off.
+    #[serde(default = "default_tcp_keepalive")]
+    pub tcp_keepalive_seconds: u64,
+}
+
+fn default_pool_idle_timeout() -> u64 {
+    90
+}
+
+fn default_tcp_keepalive() -> u64 {
+    15
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            http_version: HttpVersionMode::default(),
+            pool_idle_timeout_seconds: default_pool_idle_timeout(),
+            pool_max_idle_per_host: None,
+            tcp_keepalive_seconds: default_tcp_keepalive(),
+        }
+    }
+}
+
+impl HttpClientConfig {
+    pub fn options(&self) -> HttpClientOptions {
+        let secs = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        HttpClientOptions {
+            version: match self.http_version {
+                HttpVersionMode::Http1 => HttpVersion::Http1,
+                HttpVersionMode::Auto => HttpVersion::Negotiate,
+                HttpVersionMode::Http2 => HttpVersion::Http2,
+            },
+            pool_idle_timeout: secs(self.pool_idle_timeout_seconds),
+            pool_max_idle_per_host: self.pool_max_idle_per_host.unwrap_or(usize::MAX),
+            tcp_keepalive: secs(self.tcp_keepalive_seconds),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersionMode {
+    #[default]
+    Http1,
+    /// HTTP/2 where the upstream offers it over TLS.
+    Auto,
+    /// HTTP/2 with prior knowledge; upstreams that only speak HTTP/1.1
+    /// fail.
+    Http2,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextLimitMode {
+    Off,
+    /// Log oversized requests but forward them anyway.
+    Warn,
+    #[default]
+    Reject,
+}
+
+/// Pre-flight check of estimated prompt size against the model's context window.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContextLimitsConfig {
+    #[serde(default)]
+    pub mode: ContextLimitMode,
+    /// Model-name prefix → context window in tokens, merged over the built-in
+    /// table; the longest matching prefix wins.
+    #[serde(default)]
+    pub models: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponsesBackend {
+    /// Codex accounts when any are configured, Claude accounts otherwise.
+    #[default]
+    Auto,
+    Codex,
+    /// Translate to Anthropic Messages and serve from Claude accounts.
+    Claude,
+}
+
+/// Where `/v1/responses` requests are served from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResponsesConfig {
+    #[serde(default)]
+    pub backend: ResponsesBackend,
+    /// Used when a request served by Claude names a non-Claude model.
+    #[serde(default = "default_claude_model")]
+    pub claude_model: String,
+}
+
+fn default_claude_model() -> String {
+    "claude-sonnet-4-20250514".to_string()
+}
+
+impl Default for ResponsesConfig {
+    fn default() -> Self {
+        Self {
+            backend: ResponsesBackend::default(),
+            claude_model: default_claude_model(),
+        }
+    }
+}
+
+/// Gemini API requests when no Gemini account can serve them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeminiConfig {
+    /// Translate to Anthropic Messages and serve from Claude accounts.
+    #[serde(default = "default_enabled")]
+    pub claude_fallback: bool,
+    /// Used in place of the requested Gemini model.
+    #[serde(default = "default_claude_model")]
+    pub claude_model: String,
+}
+
+impl Default for GeminiConfig {
+    fn default() -> Self {
+        Self {
+            claude_fallback: true,
+            claude_model: default_claude_model(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessagesBackend {
+    /// Claude accounts when any are configured, Gemini accounts otherwise.
+    #[default]
+    Auto,
+    Claude,
+    /// Translate to Gemini `generateContent` and serve from Gemini accounts.
+    Gemini,
+}
+
+/// Where Anthropic `/v1/messages` requests are served from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessagesConfig {
+    #[serde(default)]
+    pub backend: MessagesBackend,
+    /// Used when a request served by Gemini names a non-Gemini model.
+    #[serde(default = "default_gemini_model")]
+    pub gemini_model: String,
+    /// Translate to OpenAI `chat/completions` and serve from
+    /// OpenAI-compatible accounts when no Claude account is available.
+    #[serde(default)]
+    pub openai_fallback: bool,
+}
+
+fn default_gemini_model() -> String {
+    "gemini-2.5-pro".to_string()
+}
+
+impl Default for MessagesConfig {
+    fn default() -> Self {
+        Self {
+            backend: MessagesBackend::default(),
+            gemini_model: default_gemini_model(),
+            openai_fallback: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenAIBackend {
+    /// `gemini-*` models on Gemini accounts, everything else on Claude accounts.
+    #[default]
+    Auto,
+    Claude,
+    /// Every model on Gemini accounts.
+    Gemini,
+    /// Every model forwarded unchanged to Codex accounts.
+    Codex,
+}
+
+/// Where OpenAI `/v1/chat/completions` requests are served from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenAIConfig {
+    #[serde(default)]
+    pub backend: OpenAIBackend,
+    /// Used when a request served by Gemini names a non-Gemini model.
+    #[serde(default = "default_gemini_model")]
+    pub gemini_model: String,
+    /// Gemini model behind `/v1/images/generations` when the request does
+    /// not name a Gemini model.
+    #[serde(default = "default_image_model")]
+    pub image_model: String,
+    /// Returns Claude `thinking` blocks as `reasoning_content` instead of
+    /// dropping them.
+    #[serde(default)]
+    pub reasoning_content: bool,
+    /// Models starting with one of these prefixes, such as `gpt-`, are
+    /// forwarded unchanged to Codex accounts' `/chat/completions`.
+    #[serde(default)]
+    pub codex_prefixes: Vec<String>,
+}
+
+fn default_image_model() -> String {
+    "gemini-2.5-flash-image".to_string()
+}
+
+impl Default for OpenAIConfig {
+    fn default() -> Self {
+        Self {
+            backend: OpenAIBackend::default(),
+            gemini_model: default_gemini_model(),
+            image_model: default_image_model(),
+            reasoning_content: false,
+            codex_prefixes: Vec::new(),
+        }
+    }
+}
+
+/// What the `models` endpoints list.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModelsConfig {
+    /// Lists Claude models from the upstream `/v1/models` of the first
+    /// Claude API-key account instead of the declared ones.
+    #[serde(default)]
+    pub upstream: bool,
+}
+
+/// Models a Vertex AI account serves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VertexPlatform {
+    /// Anthropic models, for Claude routes.
+    #[default]
+    Claude,
+    /// Google models, for Gemini routes.
+    Gemini,
+}
+
+/// Settings shared by Vertex AI accounts.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VertexConfig {
+    /// Anthropic model name → Vertex model id, such as
+    /// `claude-sonnet-4@20250514`. Unlisted models turn a trailing
+    /// `-YYYYMMDD` into `@YYYYMMDD`.
+    #[serde(default)]
+    pub model_ids: HashMap<String, String>,
+}
+
+/// Settings shared by Bedrock accounts.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BedrockConfig {
+    /// Anthropic model name → Bedrock model id or inference profile, such as
+    /// `us.anthropic.claude-sonnet-4-20250514-v1:0`. Unlisted models map to
+    /// `anthropic.{model}-v1:0`.
+    #[serde(default)]
+    pub model_ids: HashMap<String, String>,
+}
+
+/// USD per million tokens. Cache prices default to Anthropic's multipliers
+/// of the input price (1.25× for writes, 0.1× for reads).
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ModelPriceConfig {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+}
+
+/// An endpoint receiving signed event notifications.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// HMAC-SHA256 key for the `x-relay-signature` header.
+    pub secret: String,
+    /// Event types to deliver; empty means all.
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each failure.
+    #[serde(default = "default_webhook_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_webhook_timeout")]
+    pub timeout_seconds: u64,
+}
+
+fn default_webhook_max_attempts() -> u32 {
+    5
+}
+
+fn default_webhook_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_webhook_timeout() -> u64 {
+    10
+}
+
+impl WebhookConfig {
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event_type)
+    }
+}
+
+/// Notifications for account cooldowns, OAuth refresh failures and daily
+/// usage. Alerts also go to `[[webhooks]]` as `alert` events.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertsConfig {
+    #[serde(default)]
+    pub channels: Vec<AlertChannelConfig>,
+    /// Shorter cooldowns, such as brief rate limits, raise no alert.
+    #[serde(default = "default_alert_min_cooldown")]
+    pub min_cooldown_seconds: u64,
+    /// Tokens of every kind across all keys per UTC day before alerting.
+    #[serde(default)]
+    pub daily_token_threshold: Option<u64>,
+}
+
+fn default_alert_min_cooldown() -> u64 {
+    60
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            min_cooldown_seconds: default_alert_min_cooldown(),
+            daily_token_threshold: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AlertChannelConfig {
+    Slack {
+        /// Incoming webhook URL.
+        webhook_url: String,
+    },
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+        #[serde(default = "default_telegram_api_url")]
+        api_url: String,
+    },
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+impl AlertChannelConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AlertChannelConfig::Slack { .. } => "slack",
+            AlertChannelConfig::Telegram { .. } => "telegram",
+        }
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| ConfigError::Io {
+            path: path.as_ref().display().to_string(),
+            source: e,
+        })?;
+
+        let config: Config =
+            toml::from_str(&content).map_err(|e| ConfigError::Parse { source: e })?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.accounts.is_empty() {
+            return Err(ConfigError::Validation(
+                "At least one account must be configured".to_string(),
+            ));
+        }
+
+        if self.server.tls_cert.is_some() != self.server.tls_key.is_some() {
+            return Err(ConfigError::Validation(
+                "server.tls_cert and server.tls_key must be set together".to_string(),
+            ));
+        }
+        if self.server.tls_client_ca.is_some() && self.server.tls_cert.is_none() {
+            return Err(ConfigError::Validation(
+                "server.tls_client_ca requires server.tls_cert and server.tls_key".to_string(),
+            ));
+        }
+
+        crate::cors::cors_layer(&self.server.cors).map_err(ConfigError::Validation)?;
+        if self.server.stream_buffer == 0 {
+            return Err(ConfigError::Validation(
+                "server.stream_buffer must be at least 1".to_string(),
+            ));
+        }
+
+        let mut ids = std::collections::HashSet::new();
+        for account in &self.accounts {
+            let id = account.id();
+            if !ids.insert(id) {
+                return Err(ConfigError::Validation(format!(
+                    "Duplicate account ID: {}",
+                    id
+                )));
+            }
+            if account.request_timeout_seconds() == Some(0) {
+                return Err(ConfigError::Validation(format!(
+                    "Account {}: request_timeout_seconds must be at least 1",
+                    id
+                )));
+            }
+        }
+
+        let mut tenant_ids = std::collections::HashSet::new();
+        let mut tenant_accounts = std::collections::HashSet::new();
+        for tenant in &self.tenants {
+            if tenant.id.is_empty() || !tenant_ids.insert(tenant.id.as_str()) {
+                return Err(ConfigError::Validation(format!(
+                    "Tenant IDs must be unique and not empty: '{}'",
+                    tenant.id
+                )));
+            }
+            for account in &tenant.accounts {
+                if !ids.contains(account.as_str()) {
+                    return Err(ConfigError::Validation(format!(
+                        "Tenant {} names unknown account {}",
+                        tenant.id, account
+                    )));
+                }
+                if !tenant_accounts.insert(account.as_str()) {
+                    return Err(ConfigError::Validation(format!(
+                        "Account {} belongs to more than one tenant",
+                        account
+                    )));
+                }
+            }
+        }
+        for tenant in self.api_keys.iter().filter_map(ApiKeyConfig::tenant) {
+            if !tenant_ids.contains(tenant) {
+                return Err(ConfigError::Validation(format!(
+                    "API key names unknown tenant {}",
+                    tenant
+                )));
+            }
+        }
+
+        if self.jwt.subject_claim.is_empty() {
+            return Err(ConfigError::Validation(
+                "jwt.subject_claim must not be empty".to_string(),
+            ));
+        }
+        for key in &self.jwt.keys {
+            if let JwtKeyConfig::HS256 { secret, .. } = key {
+                if secret.len() < 32 {
+                    return Err(ConfigError::Validation(
+                        "jwt HS256 secrets must be at least 32 bytes".to_string(),
+                    ));
+                }
+            }
+        }
+
+        let throttle = &self.auth_throttle;
+        if throttle.max_failures == Some(0)
+            || throttle.window_seconds == 0
+            || throttle.ban_seconds == 0
+        {
+            return Err(ConfigError::Validation(
+                "auth_throttle.max_failures, window_seconds and ban_seconds must be at least 1"
+                    .to_string(),
+            ));
+        }
+
+        let timeouts = &self.timeouts;
+        if timeouts.request_seconds == 0 || timeouts.models.values().any(|&secs| secs == 0) {
+            return Err(ConfigError::Validation(
+                "timeouts must be at least 1 second".to_string(),
+            ));
+        }
+
+        if self.database.usage_retention_days == Some(0) {
+            return Err(ConfigError::Validation(
+                "database.usage_retention_days must be at least 1".to_string(),
+            ));
+        }
+
+        if self.database.max_connections == 0 {
+            return Err(ConfigError::Validation(
+                "database.max_connections must be at least 1".to_string(),
+            ));
+        }
+
+        if self.database.usage_batch_size == 0 {
+            return Err(ConfigError::Validation(
+                "database.usage_batch_size must be at least 1".to_string(),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.scheduler.bandit.exploration_rate) {
+            return Err(ConfigError::Validation(
+                "scheduler.bandit.exploration_rate must be in [0, 1]".to_string(),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.scheduler.quota_reserve_ratio) {
+            return Err(ConfigError::Validation(
+                "scheduler.quota_reserve_ratio must be in [0, 1]".to_string(),
+            ));
+        }
+
+        let budget = &self.error_budget;
+        if budget.windows_seconds.is_empty() || budget.windows_seconds.contains(&0) {
+            return Err(ConfigError::Validation(
+                "error_budget.windows_seconds must list at least one non-zero window".to_string(),
+            ));
+        }
+        for (name, rate) in [
+            ("max_server_error_rate", budget.max_server_error_rate),
+            ("max_timeout_rate", budget.max_timeout_rate),
+        ] {
+            if !(rate > 0.0 && rate <= 1.0) {
+                return Err(ConfigError::Validation(format!(
+                    "error_budget.{} must be in (0, 1]",
+                    name
+                )));
+            }
+        }
+
+        let anonymous_routes = self.api.anonymous_routes.iter().flatten();
+        if let Some(route) = anonymous_routes.into_iter().find(|r| !r.starts_with('/')) {
+            return Err(ConfigError::Validation(format!(
+                "api.anonymous_routes entry '{}' must start with '/'",
+                route
+            )));
+        }
+
+        let admin_token = self.server.admin_token.as_deref();
+        if admin_token == Some("") {
+            return Err(ConfigError::Validation(
+                "server.admin_token must not be empty".to_string(),
+            ));
+        }
+        if let Some(oidc) = &self.admin_oidc {
+            if oidc.allowed_emails.is_empty() && oidc.allowed_domains.is_empty() {
+                return Err(ConfigError::Validation(
+                    "admin_oidc needs allowed_emails or allowed_domains".to_string(),
+                ));
+            }
+            if oidc.session_ttl_seconds == 0 {
+                return Err(ConfigError::Validation(
+                    "admin_oidc.session_ttl_seconds must be at least 1".to_string(),
+                ));
+            }
+        }
+        if let Some(admin_port) = self.server.admin_port {
+            if admin_token.is_none() && self.admin_oidc.is_none() {
+                return Err(ConfigError::Validation(
+                    "server.admin_port requires server.admin_token or [admin_oidc]".to_string(),
+                ));
+            }
+            if admin_port == self.server.port {
+                return Err(ConfigError::Validation(
+                    "server.admin_port must differ from server.port".to_string(),
+                ));
+            }
+        }
+
+        let configured_keys = self.api_keys.iter().map(|key| key.key());
+        for key in configured_keys.chain(admin_token) {
+            let Some(digest) = key.strip_prefix(HASHED_KEY_PREFIX) else {
+                continue;
+            };
+            if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(ConfigError::Validation(format!(
+                    "Hashed keys must be '{}' followed by a 64-character hex SHA-256 digest",
+                    HASHED_KEY_PREFIX
+                )));
+            }
+        }
+
+        let key_rates = self.api_keys.iter().filter_map(|key| match key {
+            ApiKeyConfig::Plain(_) => None,
+            ApiKeyConfig::Detailed {
+                requests_per_minute,
+                burst,
+                ..
+            } => Some((*requests_per_minute, *burst)),
+        });
+        let default_rate = (self.rate_limit.requests_per_minute, self.rate_limit.burst);
+        for (requests_per_minute, burst) in std::iter::once(default_rate).chain(key_rates) {
+            if requests_per_minute == Some(0) || burst == Some(0) {
+                return Err(ConfigError::Validation(
+                    "requests_per_minute and burst must be at least 1".to_string(),
+                ));
+            }
+        }
+        let key_concurrency = self
+            .api_keys
+            .iter()
+            .map(ApiKeyConfig::max_concurrent_requests);
+        if std::iter::once(self.rate_limit.max_concurrent_requests)
+            .chain(key_concurrency)
+            .any(|max| max == Some(0))
+        {
+            return Err(ConfigError::Validation(
+                "max_concurrent_requests must be at least 1".to_string(),
+            ));
+        }
+
+        for webhook in &self.webhooks {
+            if webhook.secret.is_empty() {
+                return Err(ConfigError::Validation(format!(
+                    "Webhook {} must have a secret",
+                    webhook.url
+                )));
+            }
+            if webhook.max_attempts == 0 {
+                return Err(ConfigError::Validation(format!(
+                    "Webhook {} max_attempts must be at least 1",
+                    webhook.url
+                )));
+            }
+        }
+
+        if self.alerts.daily_token_threshold == Some(0) {
+            return Err(ConfigError::Validation(
+                "alerts.daily_token_threshold must be at least 1".to_string(),
+            ));
+        }
+
+        if let Some(status) = self
+            .upstream_errors
+            .retryable_statuses
+            .iter()
+            .find(|status| !(400..=599).contains(*status))
+        {
+            return Err(ConfigError::Validation(format!(
+                "upstream_errors.retryable_statuses: {} is not an error status",
+                status
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// `[session]` and `[upstream_errors]` cooldowns.
+    pub fn cooldown_policy(&self) -> FixedCooldownPolicy {
+        let secs = Duration::from_secs;
+        let errors = &self.upstream_errors;
+        FixedCooldownPolicy::new(secs(self.session.unavailable_cooldown_seconds))
+            .with_fallbacks(
+                secs(errors.rate_limit_cooldown_seconds),
+                secs(errors.overload_cooldown_seconds),
+            )
+            .with_max_hinted(secs(errors.max_cooldown_seconds))
+            .with_server_error_backoff(
+                secs(errors.server_error_cooldown_seconds),
+                secs(errors.max_server_error_cooldown_seconds),
+            )
+    }
+
+    /// Account id → the tenant it belongs to.
+    pub fn account_tenants(&self) -> HashMap<String, String> {
+        self.tenants
+            .iter()
+            .flat_map(|tenant| {
+                tenant
+                    .accounts
+                    .iter()
+                    .map(|account| (account.clone(), tenant.id.clone()))
+            })
+            .collect()
+    }
+
+    /// `[timeouts]` together with the accounts' own timeouts.
+    pub fn upstream_timeouts(&self) -> UpstreamTimeouts {
+        let secs = Duration::from_secs;
+        let accounts = self
+            .accounts
+            .iter()
+            .filter_map(|account| {
+                let timeout = account.request_timeout_seconds()?;
+                Some((account.id().to_string(), secs(timeout)))
+            })
+            .collect();
+        let models = self
+            .timeouts
+            .models
+            .iter()
+            .map(|(model, timeout)| (model.clone(), secs(*timeout)))
+            .collect();
+        UpstreamTimeouts::new(secs(self.timeouts.request_seconds))
+            .with_accounts(accounts)
+            .with_models(models)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to parse config: {source}")]
+    Parse {
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("Config validation error: {0}")]
+    Validation(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_responses_account_config_parsing() {
+        let config_content = r#"
+[server]
+host = "127.0.0.1"
+port = 3000
+database_path = "data/relay.db"
+
+[[accounts]]
+type = "openai-responses"
+id = "codex-1"
+name = "Codex Account"
+priority = 100
+enabled = true
+api_key = "sk-test-key"
+api_url = "https://api.openai.com/v1"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert_eq!(config.accounts.len(), 1);
+
+        match &config.accounts[0] {
+            AccountConfig::OpenaiResponses {
+                id,
+                name,
+                priority,
+                enabled,
+                api_key,
+                api_url,
+                ..
+            } => {
+                assert_eq!(id, "codex-1");
+                assert_eq!(name, "Codex Account");
+                assert_eq!(*priority, 100);
+                assert!(*enabled);
+                assert_eq!(api_key, "sk-test-key");
+                assert_eq!(api_url.as_deref(), Some("https://api.openai.com/v1"));
+            }
+            _ => panic!("Expected OpenaiResponses account"),
+        }
+    }
+
+    #[test]
+    fn test_claude_session_account_config_parsing() {
+        let config_content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-session"
+id = "session-1"
+name = "Claude.ai Account"
+session_key = "sk-ant-sid01-xxxx"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+
+        match &config.accounts[0] {
+            AccountConfig::ClaudeSession {
+                id,
+                priority,
+                enabled,
+                session_key,
+                ..
+            } => {
+                assert_eq!(id, "session-1");
+                assert_eq!(*priority, 100);
+                assert!(*enabled);
+                assert_eq!(session_key, "sk-ant-sid01-xxxx");
+            }
+            _ => panic!("Expected ClaudeSession account"),
+        }
+    }
+
+    #[test]
+    fn test_bedrock_account_config_parsing() {
+        let config_content = r#"
+[server]
+port = 3000
+
+[bedrock.model_ids]
+"claude-sonnet-4-20250514" = "us.anthropic.claude-sonnet-4-20250514-v1:0"
+
+[[accounts]]
+type = "bedrock"
+id = "bedrock-1"
+name = "Bedrock"
+region = "us-west-2"
+access_key_id = "AKIAEXAMPLE"
+secret_access_key = "secret"
+server_tools = []
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert_eq!(config.accounts[0].server_tools(), Some(&[][..]));
+
+        match &config.accounts[0] {
+            AccountConfig::Bedrock {
+                id,
+                region,
+                access_key_id,
+                session_token,
+                ..
+            } => {
+                assert_eq!(id, "bedrock-1");
+                assert_eq!(region, "us-west-2");
+                assert_eq!(access_key_id, "AKIAEXAMPLE");
+                assert!(session_token.is_none());
+            }
+            _ => panic!("Expected Bedrock account"),
+        }
+        assert_eq!(
+            config.bedrock.model_ids["claude-sonnet-4-20250514"],
+            "us.anthropic.claude-sonnet-4-20250514-v1:0"
+        );
+    }
+
+    #[test]
+    fn test_vertex_account_config_parsing() {
+        let config_content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "vertex"
+id = "vertex-claude"
+name = "Vertex Claude"
+service_account_file = "/etc/relay/vertex.json"
+region = "us-east5"
+
+[[accounts]]
+type = "vertex"
+id = "vertex-gemini"
+name = "Vertex Gemini"
+platform = "gemini"
+service_account_file = "/etc/relay/vertex.json"
+project_id = "other-project"
+region = "global"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+
+        match &config.accounts[0] {
+            AccountConfig::Vertex {
+                platform,
+                project_id,
+                region,
+                ..
+            } => {
+                assert_eq!(*platform, VertexPlatform::Claude);
+                assert!(project_id.is_none());
+                assert_eq!(region, "us-east5");
+            }
+            _ => panic!("Expected Vertex account"),
+        }
+        match &config.accounts[1] {
+            AccountConfig::Vertex {
+                platform,
+                project_id,
+                ..
+            } => {
+                assert_eq!(*platform, VertexPlatform::Gemini);
+                assert_eq!(project_id.as_deref(), Some("other-project"));
+            }
+            _ => panic!("Expected Vertex account"),
+        }
+    }
+
+    #[test]
+    fn test_azure_openai_account_config_parsing() {
+        let config_content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "azure-openai"
+id = "azure-1"
+name = "Azure OpenAI"
+api_key = "azure-key"
+api_url = "https://my-resource.openai.azure.com"
+deployment = "gpt-5-prod"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+
+        match &config.accounts[0] {
+            AccountConfig::AzureOpenai {
+                id,
+                deployment,
+                api_version,
+                ..
+            } => {
+                assert_eq!(id, "azure-1");
+                assert_eq!(deployment, "gpt-5-prod");
+                assert!(api_version.is_none());
+            }
+            _ => panic!("Expected AzureOpenai account"),
+        }
+    }
+
+    #[test]
+    fn test_openrouter_account_config_parsing() {
+        let config_content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "openrouter"
+id = "openrouter-1"
+name = "OpenRouter"
+priority = 10
+api_key = "sk-or-v1-xxx"
+x_title = "Claude Relay"
+
+[accounts.model_ids]
+"claude-sonnet-4-20250514" = "anthropic/claude-sonnet-4"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+
+        match &config.accounts[0] {
+            AccountConfig::Openrouter {
+                priority,
+                api_url,
+                http_referer,
+                x_title,
+                model_ids,
+                ..
+            } => {
+                assert_eq!(*priority, 10);
+                assert!(api_url.is_none());
+                assert!(http_referer.is_none());
+                assert_eq!(x_title.as_deref(), Some("Claude Relay"));
+                assert_eq!(
+                    model_ids["claude-sonnet-4-20250514"],
+                    "anthropic/claude-sonnet-4"
+                );
+            }
+            _ => panic!("Expected Openrouter account"),
+        }
+    }
+
+    #[test]
+    fn test_openai_compatible_account_config_parsing() {
+        let config_content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "openai-compatible"
+id = "ollama"
+name = "Local Ollama"
+priority = 1
+api_url = "http://localhost:11434/v1"
+model = "qwen2.5-coder:32b"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+
+        match &config.accounts[0] {
+            AccountConfig::OpenaiCompatible {
+                api_url,
+                api_key,
+                model,
+                ..
+            } => {
+                assert_eq!(api_url, "http://localhost:11434/v1");
+                assert!(api_key.is_none());
+                assert_eq!(model.as_deref(), Some("qwen2.5-coder:32b"));
+            }
+            _ => panic!("Expected OpenaiCompatible account"),
+        }
+    }
+
+    #[test]
+    fn test_deepseek_account_config_parsing() {
+        let config_content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "deepseek"
+id = "deepseek"
+name = "DeepSeek"
+api_key = "sk-test"
+model = "deepseek-reasoner"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+
+        match &config.accounts[0] {
+            AccountConfig::Deepseek {
+                api_key,
+                api_url,
+                model,
+                ..
+            } => {
+                assert_eq!(api_key, "sk-test");
+                assert!(api_url.is_none());
+                assert_eq!(model.as_deref(), Some("deepseek-reasoner"));
+            }
+            _ => panic!("Expected Deepseek account"),
+        }
+    }
+
+    #[test]
+    fn test_session_config_default_values() {
+        let config_content = r#"
+[server]
+host = "127.0.0.1"
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test-1"
+name = "Test Account"
+api_key = "sk-test"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert_eq!(config.session.sticky_ttl_seconds, 3600);
+        assert_eq!(config.session.renewal_threshold_seconds, 300);
+        assert_eq!(config.session.unavailable_cooldown_seconds, 3600);
+    }
+
+    #[test]
+    fn test_session_config_custom_values() {
+        let config_content = r#"
+[server]
+host = "127.0.0.1"
+port = 3000
+
+[session]
+sticky_ttl_seconds = 7200
+renewal_threshold_seconds = 600
+unavailable_cooldown_seconds = 1800
+
+[[accounts]]
+type = "claude-api"
+id = "test-1"
+name = "Test Account"
+api_key = "sk-test"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+        assert_eq!(config.session.sticky_ttl_seconds, 7200);
+        assert_eq!(config.session.renewal_threshold_seconds, 600);
+        assert_eq!(config.session.unavailable_cooldown_seconds, 1800);
+    }
+
+    #[test]
+    fn test_session_config_partial_override() {
+        let config_content = r#"
+[server]
+host = "127.0.0.1"
+port = 3000
+
+[session]
+unavailable_cooldown_seconds = 300
+
+[[accounts]]
+type = "claude-api"
+id = "test-1"
+name = "Test Account"
+api_key = "sk-test"
+"#;
+
+        let config: Config = toml::from_str(config_content).unwrap();
+        // Default values for unspecified fields
+        assert_eq!(config.session.sticky_ttl_seconds, 3600);
+        assert_eq!(config.session.renewal_threshold_seconds, 300);
+        // Custom value
+        assert_eq!(config.session.unavailable_cooldown_seconds, 300);
+    }
+
+    #[test]
+    fn test_audit_config_defaults_to_disabled() {
+        let content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(!config.audit.enabled);
+        assert_eq!(config.audit.sink, AuditSinkKind::Database);
+        assert!(!config.audit.include_bodies);
+    }
+
+    #[test]
+    fn test_audit_config_jsonl() {
+        let content = r#"
+[server]
+port = 3000
+
+[audit]
+enabled = true
+sink = "jsonl"
+include_bodies = true
+directory = "/var/log/relay"
+max_files = 3
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.audit.enabled);
+        assert_eq!(config.audit.sink, AuditSinkKind::Jsonl);
+        assert!(config.audit.include_bodies);
+        assert_eq!(config.audit.directory, "/var/log/relay");
+        assert_eq!(config.audit.max_files, 3);
+        assert_eq!(config.audit.max_body_bytes, 64 * 1024);
+    }
+
+    #[test]
+    fn test_error_budget_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[error_budget]
+windows_seconds = [60, 900]
+max_timeout_rate = 0.1
+circuit_breaker = true
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.error_budget.windows_seconds, vec![60, 900]);
+        assert_eq!(config.error_budget.max_timeout_rate, 0.1);
+        assert_eq!(config.error_budget.max_server_error_rate, 0.25);
+        assert!(config.error_budget.circuit_breaker);
+        assert_eq!(config.error_budget.breaker_cooldown_seconds, 300);
+    }
+
+    #[test]
+    fn test_error_budget_rejects_invalid_rate() {
+        let content = r#"
+[server]
+port = 3000
+
+[error_budget]
+max_server_error_rate = 1.5
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_webhook_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[[webhooks]]
+url = "https://hooks.example.com/relay"
+secret = "whsec_test"
+events = ["account.circuit_opened"]
+
+[[webhooks]]
+url = "https://ops.example.com/all"
+secret = "whsec_ops"
+max_attempts = 3
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.webhooks.len(), 2);
+        assert!(config.webhooks[0].wants("account.circuit_opened"));
+        assert!(!config.webhooks[0].wants("account.error_budget_exhausted"));
+        assert_eq!(config.webhooks[0].max_attempts, 5);
+        assert_eq!(config.webhooks[0].initial_backoff_ms, 1000);
+        assert!(config.webhooks[1].wants("account.error_budget_exhausted"));
+        assert_eq!(config.webhooks[1].max_attempts, 3);
+    }
+
+    #[test]
+    fn test_webhook_requires_secret() {
+        let content = r#"
+[server]
+port = 3000
+
+[[webhooks]]
+url = "https://hooks.example.com/relay"
+secret = ""
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_context_limits_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[context_limits]
+mode = "warn"
+
+[context_limits.models]
+"claude-3-haiku" = 100000
+"my-model" = 32768
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.context_limits.mode, ContextLimitMode::Warn);
+        assert_eq!(config.context_limits.models["claude-3-haiku"], 100_000);
+        assert_eq!(config.context_limits.models["my-model"], 32_768);
+    }
+
+    #[test]
+    fn test_responses_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[responses]
+backend = "claude"
+claude_model = "claude-opus-4-1-20250805"
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.responses.backend, ResponsesBackend::Claude);
+        assert_eq!(config.responses.claude_model, "claude-opus-4-1-20250805");
+
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.responses.backend, ResponsesBackend::Auto);
+        assert_eq!(config.responses.claude_model, "claude-sonnet-4-20250514");
+    }
+
+    #[test]
+    fn test_gemini_config() {
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+
+[gemini]
+claude_fallback = false
+"#,
+        )
+        .unwrap();
+        assert!(!config.gemini.claude_fallback);
+        assert_eq!(config.gemini.claude_model, "claude-sonnet-4-20250514");
+
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+"#,
+        )
+        .unwrap();
+        assert!(config.gemini.claude_fallback);
+    }
+
+    #[test]
+    fn test_messages_config() {
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+
+[messages]
+backend = "gemini"
+gemini_model = "gemini-2.5-flash"
+openai_fallback = true
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.messages.backend, MessagesBackend::Gemini);
+        assert_eq!(config.messages.gemini_model, "gemini-2.5-flash");
+        assert!(config.messages.openai_fallback);
+
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.messages.backend, MessagesBackend::Auto);
+        assert_eq!(config.messages.gemini_model, "gemini-2.5-pro");
+        assert!(!config.messages.openai_fallback);
+    }
+
+    #[test]
+    fn test_openai_config() {
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+
+[openai]
+backend = "gemini"
+gemini_model = "gemini-2.5-flash"
+reasoning_content = true
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.openai.backend, OpenAIBackend::Gemini);
+        assert_eq!(config.openai.gemini_model, "gemini-2.5-flash");
+        assert!(config.openai.reasoning_content);
+
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.openai.backend, OpenAIBackend::Auto);
+        assert_eq!(config.openai.image_model, "gemini-2.5-flash-image");
+        assert!(!config.openai.reasoning_content);
+        assert!(config.openai.codex_prefixes.is_empty());
+
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+
+[openai]
+backend = "codex"
+codex_prefixes = ["gpt-", "o3"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.openai.backend, OpenAIBackend::Codex);
+        assert_eq!(config.openai.codex_prefixes, ["gpt-", "o3"]);
+    }
+
+    #[test]
+    fn test_model_map() {
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+
+[model_map]
+"gpt-4o" = "claude-sonnet-4-20250514"
+haiku = "claude-3-5-haiku-20241022"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.model_map["gpt-4o"], "claude-sonnet-4-20250514");
+        assert_eq!(config.model_map["haiku"], "claude-3-5-haiku-20241022");
+    }
+
+    #[test]
+    fn test_models() {
+        let config: Config = toml::from_str(
+            r#"
+[server]
+port = 3000
+
+[models]
+upstream = true
+
+[[accounts]]
+type = "claude-api"
+id = "a"
+name = "A"
+api_key = "sk-ant-a"
+models = ["claude-sonnet-4-20250514"]
+
+[[accounts]]
+type = "claude-api"
+id = "b"
+name = "B"
+api_key = "sk-ant-b"
+"#,
+        )
+        .unwrap();
+        assert!(config.models.upstream);
+        assert_eq!(config.accounts[0].models(), ["claude-sonnet-4-20250514"]);
+        assert!(config.accounts[1].models().is_empty());
+    }
+
+    #[test]
+    fn test_context_limits_default_to_reject() {
+        let content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.context_limits.mode, ContextLimitMode::Reject);
+        assert!(config.context_limits.models.is_empty());
+    }
+
+    #[test]
+    fn test_account_draining_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "old"
+name = "Old"
+api_key = "sk-old"
+draining = true
+
+[[accounts]]
+type = "claude-api"
+id = "new"
+name = "New"
+api_key = "sk-new"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.accounts[0].id(), "old");
+        assert!(config.accounts[0].draining());
+        assert!(!config.accounts[1].draining());
+    }
+
+    #[test]
+    fn test_usage_retention_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[database]
+usage_retention_days = 90
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.database.usage_retention_days, Some(90));
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(
+            &content.replace("usage_retention_days = 90", "usage_retention_days = 0"),
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_database_pool_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.database.max_connections, 10);
+        assert_eq!(config.database.busy_timeout_ms, 5000);
+        assert!(config.database.wal);
+        assert_eq!(config.database.synchronous, SynchronousMode::Normal);
+        assert_eq!(config.database.usage_flush_interval_ms, 1000);
+        assert_eq!(config.database.usage_batch_size, 200);
+
+        let content = content.replace(
+            "[[accounts]]",
+            "[database]\nmax_connections = 20\nbusy_timeout_ms = 10000\nwal = false\nsynchronous = \"full\"\n\n[[accounts]]",
+        );
+        let config: Config = toml::from_str(&content).unwrap();
+        assert_eq!(config.database.max_connections, 20);
+        assert_eq!(config.database.busy_timeout_ms, 10000);
+        assert!(!config.database.wal);
+        assert_eq!(config.database.synchronous, SynchronousMode::Full);
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str(&content.replace("max_connections = 20", "max_connections = 0"))
+                .unwrap();
+        assert!(config.validate().is_err());
+
+        let content = content.replace("wal = false", "usage_batch_size = 0");
+        let config: Config = toml::from_str(&content).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_scheduler_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[scheduler]
+strategy = "bandit"
+quota_reserve_ratio = 0.1
+
+[scheduler.bandit]
+reward = "latency"
+exploration_rate = 0.2
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.scheduler.strategy, SchedulingStrategy::Bandit);
+        assert_eq!(config.scheduler.bandit.reward, BanditReward::Latency);
+        assert_eq!(config.scheduler.bandit.exploration_rate, 0.2);
+        assert_eq!(config.scheduler.quota_reserve_ratio, 0.1);
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str(&content.replace("exploration_rate = 0.2", "exploration_rate = 1.5"))
+                .unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config = toml::from_str(
+            &content.replace("quota_reserve_ratio = 0.1", "quota_reserve_ratio = -0.1"),
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_scheduler_defaults_to_priority() {
+        let config = SchedulerConfig::default();
+        assert_eq!(config.strategy, SchedulingStrategy::Priority);
+        assert_eq!(config.bandit.reward, BanditReward::SuccessRate);
+        assert_eq!(config.bandit.exploration_rate, 0.1);
+        assert_eq!(config.quota_reserve_ratio, 0.05);
+    }
+
+    #[test]
+    fn test_alerts_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[alerts]
+daily_token_threshold = 5000000
+
+[[alerts.channels]]
+type = "slack"
+webhook_url = "https://hooks.slack.com/services/T/B/X"
+
+[[alerts.channels]]
+type = "telegram"
+bot_token = "123:abc"
+chat_id = "-1001"
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.alerts.min_cooldown_seconds, 60);
+        assert_eq!(config.alerts.daily_token_threshold, Some(5_000_000));
+        assert_eq!(config.alerts.channels.len(), 2);
+        assert!(matches!(
+            &config.alerts.channels[1],
+            AlertChannelConfig::Telegram { api_url, .. } if api_url == "https://api.telegram.org"
+        ));
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(
+            &content.replace("daily_token_threshold = 5000000", "daily_token_threshold = 0"),
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_config() {
+        let content = r#"
+api_keys = [
+    "plain-key",
+    { key = "ci-bot-key", requests_per_minute = 10, burst = 2, max_concurrent_requests = 2 },
+]
+
+[server]
+port = 3000
+
+[rate_limit]
+requests_per_minute = 600
+max_concurrent_requests = 8
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.rate_limit.requests_per_minute, Some(600));
+        assert_eq!(config.rate_limit.burst, None);
+        assert_eq!(config.api_keys[0].rate_limit(), None);
+        assert_eq!(
+            config.api_keys[1].rate_limit(),
+            Some(RateLimit {
+                requests_per_minute: 10,
+                burst: 2
+            })
+        );
+        assert_eq!(config.rate_limit.max_concurrent_requests, Some(8));
+        assert_eq!(config.api_keys[0].max_concurrent_requests(), None);
+        assert_eq!(config.api_keys[1].max_concurrent_requests(), Some(2));
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&content.replace("burst = 2", "burst = 0")).unwrap();
+        assert!(config.validate().is_err());
+        let zero = content.replace("max_concurrent_requests = 8", "max_concurrent_requests = 0");
+        let config: Config = toml::from_str(&zero).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_ip_filter_config() {
+        let content = r#"
+api_keys = [
+    "plain-key",
+    { key = "office-key", allowed_ips = ["203.0.113.0/24", "2001:db8::1"] },
+]
+
+[server]
+port = 3000
+
+[ip_filter]
+allow = ["10.0.0.0/8", "198.51.100.7"]
+trusted_proxies = ["127.0.0.1"]
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.ip_filter.allow.len(), 2);
+        assert!(config.ip_filter.deny.is_empty());
+        assert_eq!(config.ip_filter.trusted_proxies[0].to_string(), "127.0.0.1");
+        assert!(config.api_keys[0].allowed_ips().is_empty());
+        let office = config.api_keys[1].allowed_ips();
+        assert_eq!(office[1].to_string(), "2001:db8::1");
+
+        assert!(toml::from_str::<Config>(&content.replace("10.0.0.0/8", "10.0.0.0/40")).is_err());
+    }
+
+    #[test]
+    fn test_pricing_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[pricing."claude-sonnet-4"]
+input = 2.5
+output = 12.5
+
+[pricing."my-model"]
+input = 1.0
+output = 2.0
+cache_read = 0.5
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.pricing["claude-sonnet-4"].input, 2.5);
+        assert_eq!(config.pricing["claude-sonnet-4"].cache_write, None);
+        assert_eq!(config.pricing["my-model"].cache_read, Some(0.5));
+    }
+
+    #[test]
+    fn test_api_keys_before_server_section() {
+        let content = r#"
+api_keys = ["key1", "key2"]
+
+[server]
+host = "127.0.0.1"
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.api_keys.len(), 2);
+        assert_eq!(config.api_keys[0].key(), "key1");
+        assert_eq!(config.api_keys[1].key(), "key2");
+        assert!(config.server.public_paths.is_empty());
+        assert!(!config.server.websocket);
+        assert!(!config.server.redact_log_content);
+        assert_eq!(
+            config.server.sse_keep_alive(),
+            Some(Duration::from_secs(15))
+        );
+        let server: ServerConfig = toml::from_str("sse_keep_alive_seconds = 0").unwrap();
+        assert_eq!(server.sse_keep_alive(), None);
+        assert_eq!(config.server.stream_mode, StreamMode::Buffered);
+        assert_eq!(config.server.stream_buffer, 32);
+        assert_eq!(config.server.shutdown_grace_seconds, 30);
+    }
+
+    #[test]
+    fn test_api_anonymous_routes() {
+        let content = r#"
+[server]
+port = 3000
+
+[api]
+anonymous_routes = ["/gemini/*", "/v1beta/*"]
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        let routes = config.api.anonymous_routes.unwrap();
+        assert_eq!(routes, ["/gemini/*", "/v1beta/*"]);
+
+        let relative = content.replace("\"/v1beta/*\"", "\"v1beta/*\"");
+        let config: Config = toml::from_str(&relative).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_server_public_paths() {
+        let content = r#"
+[server]
+port = 3000
+public_paths = ["/v1/models", "/health"]
+redact_log_content = true
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.server.public_paths, ["/v1/models", "/health"]);
+        assert!(config.server.redact_log_content);
+    }
+
+    #[test]
+    fn test_auth_throttle_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[auth_throttle]
+max_failures = 10
+ban_seconds = 600
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.auth_throttle.max_failures, Some(10));
+        assert_eq!(config.auth_throttle.window_seconds, 300);
+        assert_eq!(config.auth_throttle.ban_seconds, 600);
+
+        let zero = content.replace("max_failures = 10", "max_failures = 0");
+        let config: Config = toml::from_str(&zero).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_tenants_config() {
+        let content = r#"
+api_keys = [
+    "shared-key",
+    { key = "team-a-key", tenant = "team-a" },
+]
+
+[server]
+port = 3000
+
+[[tenants]]
+id = "team-a"
+accounts = ["claude-a"]
+
+[[accounts]]
+type = "claude-api"
+id = "claude-a"
+name = "Team A"
+api_key = "sk-a"
+
+[[accounts]]
+type = "claude-api"
+id = "claude-shared"
+name = "Shared"
+api_key = "sk-shared"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.api_keys[0].tenant(), None);
+        assert_eq!(config.api_keys[1].tenant(), Some("team-a"));
+        let tenants = config.account_tenants();
+        assert_eq!(tenants.get("claude-a").map(String::as_str), Some("team-a"));
+        assert!(!tenants.contains_key("claude-shared"));
+
+        for invalid in [
+            content.replace("tenant = \"team-a\"", "tenant = \"team-b\""),
+            content.replace("accounts = [\"claude-a\"]", "accounts = [\"claude-x\"]"),
+            content.replace(
+                "accounts = [\"claude-a\"]",
+                "accounts = [\"claude-a\"]\n\n[[tenants]]\nid = \"team-b\"\naccounts = [\"claude-a\"]",
+            ),
+        ] {
+            let config: Config = toml::from_str(&invalid).unwrap();
+            assert!(config.validate().is_err());
         }
+    }
 
-        Ok(())
+    #[test]
+    fn test_admin_token_config() {
+        let content = r#"
+[server]
+port = 3000
+admin_token = "admin-secret"
+admin_port = 3001
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.server.admin_token.as_deref(), Some("admin-secret"));
+        assert_eq!(config.server.admin_port, Some(3001));
+
+        let same_port = content.replace("admin_port = 3001", "admin_port = 3000");
+        let config: Config = toml::from_str(&same_port).unwrap();
+        assert!(config.validate().is_err());
+        let without_token = content.replace("admin_token = \"admin-secret\"", "");
+        let config: Config = toml::from_str(&without_token).unwrap();
+        assert!(config.validate().is_err());
+        let bad_hash = content.replace("admin-secret", "sha256:abc");
+        let config: Config = toml::from_str(&bad_hash).unwrap();
+        assert!(config.validate().is_err());
     }
-}
 
-#[derive(Debug, thiserror::Error)]
-pub enum ConfigError {
-    #[error("Failed to read config file '{path}': {source}")]
-    Io {
-        path: String,
-        #[source]
-        source: std::io::Error,
-    },
-    #[error("Failed to parse config: {source}")]
-    Parse {
-        #[source]
-        source: toml::de::Error,
-    },
-    #[error("Config validation error: {0}")]
-    Validation(String),
-}
+    #[test]
+    fn test_admin_oidc_config() {
+        let content = r#"
+[server]
+port = 3000
+admin_port = 3001
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+[admin_oidc]
+issuer = "https://accounts.google.com"
+client_id = "relay-admin"
+client_secret = "oidc-secret"
+redirect_url = "https://relay.example.com/admin/oidc/callback"
+allowed_domains = ["example.com"]
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        let oidc = config.admin_oidc.unwrap();
+        assert_eq!(oidc.issuer, "https://accounts.google.com");
+        assert_eq!(oidc.allowed_domains, vec!["example.com"]);
+        assert!(oidc.allowed_emails.is_empty());
+        assert_eq!(oidc.session_ttl_seconds, 8 * 3600);
+
+        // Without an allow-list, anyone with an account at the provider gets in
+        let open = content.replace("allowed_domains = [\"example.com\"]", "");
+        let config: Config = toml::from_str(&open).unwrap();
+        assert!(config.validate().is_err());
+    }
 
     #[test]
-    fn test_openai_responses_account_config_parsing() {
-        let config_content = r#"
+    fn test_server_cors_config() {
+        let content = r#"
 [server]
-host = "127.0.0.1"
 port = 3000
-database_path = "data/relay.db"
+
+[server.cors]
+allowed_origins = ["https://chat.example.com"]
+allowed_methods = ["GET", "POST", "DELETE"]
 
 [[accounts]]
-type = "openai-responses"
-id = "codex-1"
-name = "Codex Account"
-priority = 100
-enabled = true
-api_key = "sk-test-key"
-api_url = "https://api.openai.com/v1"
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
 "#;
+        let config: Config = toml::from_str(content).unwrap();
+        let cors = &config.server.cors;
+        assert_eq!(cors.allowed_origins, ["https://chat.example.com"]);
+        assert_eq!(cors.allowed_methods, ["GET", "POST", "DELETE"]);
+        assert!(cors.allowed_headers.contains(&"x-api-key".to_string()));
+        assert_eq!(cors.max_age_seconds, 3600);
+        assert!(config.validate().is_ok());
 
-        let config: Config = toml::from_str(config_content).unwrap();
-        assert_eq!(config.accounts.len(), 1);
+        let config: Config = toml::from_str(&content.replace("DELETE", "NOT A METHOD")).unwrap();
+        assert!(config.validate().is_err());
+    }
 
-        match &config.accounts[0] {
-            AccountConfig::OpenaiResponses {
-                id,
-                name,
-                priority,
-                enabled,
-                api_key,
-                api_url,
-                ..
-            } => {
-                assert_eq!(id, "codex-1");
-                assert_eq!(name, "Codex Account");
-                assert_eq!(*priority, 100);
-                assert!(*enabled);
-                assert_eq!(api_key, "sk-test-key");
-                assert_eq!(api_url.as_deref(), Some("https://api.openai.com/v1"));
-            }
-            _ => panic!("Expected OpenaiResponses account"),
-        }
+    #[test]
+    fn test_jwt_config() {
+        let content = r#"
+[server]
+port = 3000
+
+[jwt]
+issuer = "https://sso.example.com"
+audience = "claude-relay"
+
+[[jwt.keys]]
+algorithm = "RS256"
+kid = "sso-1"
+public_key_file = "/etc/relay/sso.pem"
+
+[[jwt.keys]]
+algorithm = "HS256"
+secret = "a-shared-secret-of-at-least-32-bytes"
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        let jwt = &config.jwt;
+        assert_eq!(jwt.issuer.as_deref(), Some("https://sso.example.com"));
+        assert_eq!(jwt.subject_claim, "sub");
+        assert_eq!(jwt.leeway_seconds, 60);
+        assert!(matches!(
+            &jwt.keys[0],
+            JwtKeyConfig::RS256 { kid: Some(kid), .. } if kid == "sso-1"
+        ));
+
+        let weak = content.replace("a-shared-secret-of-at-least-32-bytes", "short");
+        let config: Config = toml::from_str(&weak).unwrap();
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_session_config_default_values() {
-        let config_content = r#"
+    fn test_upstream_timeouts_config() {
+        let content = r#"
 [server]
-host = "127.0.0.1"
 port = 3000
 
+[timeouts]
+request_seconds = 300
+models = { "claude-opus-4" = 1200 }
+
 [[accounts]]
 type = "claude-api"
-id = "test-1"
-name = "Test Account"
+id = "slow-proxy"
+name = "Slow proxy"
+api_key = "sk-test"
+request_timeout_seconds = 900
+
+[[accounts]]
+type = "claude-api"
+id = "main"
+name = "Main"
 api_key = "sk-test"
 "#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+        let timeouts = config.upstream_timeouts();
+        let timeout = |account, model| timeouts.for_request(account, model);
+        let secs = Duration::from_secs;
+        assert_eq!(timeout("main", "claude-sonnet-4"), secs(300));
+        assert_eq!(timeout("slow-proxy", "claude-sonnet-4"), secs(900));
+        assert_eq!(timeout("main", "claude-opus-4-1"), secs(1200));
 
-        let config: Config = toml::from_str(config_content).unwrap();
-        assert_eq!(config.session.sticky_ttl_seconds, 3600);
-        assert_eq!(config.session.renewal_threshold_seconds, 300);
-        assert_eq!(config.session.unavailable_cooldown_seconds, 3600);
+        assert_eq!(config.timeouts.first_byte(), None);
+
+        let default: Config =
+            toml::from_str(&content.replace("request_seconds = 300", "")).unwrap();
+        assert_eq!(default.timeouts.request_seconds, 600);
+
+        let first_byte: Config =
+            toml::from_str(&content.replace("request_seconds = 300", "first_byte_seconds = 20"))
+                .unwrap();
+        assert_eq!(first_byte.timeouts.first_byte(), Some(secs(20)));
+        assert_eq!(first_byte.timeouts.first_byte_cooldown_seconds, 60);
+
+        let zero: Config = toml::from_str(&content.replace("= 900", "= 0")).unwrap();
+        assert!(zero.validate().is_err());
     }
 
     #[test]
-    fn test_session_config_custom_values() {
-        let config_content = r#"
+    fn test_retry_config() {
+        let config: Config = toml::from_str("[server]\nport = 3000").unwrap();
+        assert_eq!(config.retry.backoff_ms, 200);
+        assert_eq!(config.retry.max_backoff_ms, 2000);
+        assert_eq!(config.retry.max_retry_after_seconds, 10);
+
+        let content = r#"
 [server]
-host = "127.0.0.1"
 port = 3000
 
-[session]
-sticky_ttl_seconds = 7200
-renewal_threshold_seconds = 600
-unavailable_cooldown_seconds = 1800
+[retry]
+backoff_ms = 0
+max_retry_after_seconds = 30
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.retry.backoff_ms, 0);
+        assert_eq!(config.retry.max_backoff_ms, 2000);
+        assert_eq!(config.retry.max_retry_after_seconds, 30);
+    }
+
+    #[test]
+    fn test_upstream_errors_config() {
+        use relay_core::{CooldownPolicy, CooldownReason};
+
+        let content = r#"
+[server]
+port = 3000
 
 [[accounts]]
 type = "claude-api"
-id = "test-1"
-name = "Test Account"
+id = "test"
+name = "Test"
 api_key = "sk-test"
 "#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.upstream_errors.rate_limit_cooldown_seconds, 60);
+        assert_eq!(config.upstream_errors.overload_cooldown_seconds, 300);
+        assert_eq!(config.upstream_errors.max_cooldown_seconds, 21600);
+        assert_eq!(config.upstream_errors.retryable_statuses, [429, 529]);
+        assert_eq!(config.upstream_errors.server_error_threshold, 3);
+        assert_eq!(config.upstream_errors.server_error_cooldown_seconds, 30);
+        assert_eq!(
+            config.upstream_errors.max_server_error_cooldown_seconds,
+            600
+        );
 
-        let config: Config = toml::from_str(config_content).unwrap();
-        assert_eq!(config.session.sticky_ttl_seconds, 7200);
-        assert_eq!(config.session.renewal_threshold_seconds, 600);
-        assert_eq!(config.session.unavailable_cooldown_seconds, 1800);
+        let content = format!(
+            "{}{}",
+            content,
+            r#"
+[upstream_errors]
+overload_cooldown_seconds = 30
+max_cooldown_seconds = 900
+retryable_statuses = [429, 502, 503]
+server_error_cooldown_seconds = 5
+"#
+        );
+        let config: Config = toml::from_str(&content).unwrap();
+        assert_eq!(config.upstream_errors.rate_limit_cooldown_seconds, 60);
+        assert_eq!(config.upstream_errors.overload_cooldown_seconds, 30);
+        assert_eq!(config.upstream_errors.retryable_statuses, [429, 502, 503]);
+        assert!(config.validate().is_ok());
+
+        let overloaded = CooldownReason::Overloaded {
+            retry_after_secs: None,
+        };
+        let policy = config.cooldown_policy();
+        assert_eq!(
+            policy.cooldown_for("a", &overloaded),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            policy.cooldown_for(
+                "a",
+                &CooldownReason::RateLimited {
+                    retry_after_secs: Some(u64::MAX)
+                }
+            ),
+            Duration::from_secs(900)
+        );
+        assert_eq!(policy.unavailable(), Duration::from_secs(3600));
+        assert_eq!(
+            policy.cooldown_for("a", &CooldownReason::ServerErrors { strikes: 1 }),
+            Duration::from_secs(10)
+        );
+
+        let config: Config = toml::from_str(&content.replace("502", "200")).unwrap();
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_session_config_partial_override() {
-        let config_content = r#"
+    fn test_slow_requests_config() {
+        let config: Config = toml::from_str("[server]\nport = 3000").unwrap();
+        assert_eq!(config.slow_requests.threshold_seconds, 0);
+        assert_eq!(config.slow_requests.stream_threshold_seconds, 0);
+
+        let content = r#"
 [server]
-host = "127.0.0.1"
 port = 3000
 
-[session]
-unavailable_cooldown_seconds = 300
+[slow_requests]
+threshold_seconds = 60
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.slow_requests.threshold_seconds, 60);
+        assert_eq!(config.slow_requests.stream_threshold_seconds, 0);
+    }
+
+    #[test]
+    fn test_http_client_config() {
+        let config: Config = toml::from_str("[server]\nport = 3000").unwrap();
+        let options = config.http_client.options();
+        assert_eq!(options.version, HttpVersion::Http1);
+        assert_eq!(options.pool_idle_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(options.pool_max_idle_per_host, usize::MAX);
+        assert_eq!(options.tcp_keepalive, Some(Duration::from_secs(15)));
+
+        let content = r#"
+[server]
+port = 3000
+
+[http_client]
+http_version = "auto"
+pool_idle_timeout_seconds = 0
+pool_max_idle_per_host = 8
+tcp_keepalive_seconds = 0
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        let options = config.http_client.options();
+        assert_eq!(options.version, HttpVersion::Negotiate);
+        assert_eq!(options.pool_idle_timeout, None);
+        assert_eq!(options.pool_max_idle_per_host, 8);
+        assert_eq!(options.tcp_keepalive, None);
+
+        let content = content.replace("\"auto\"", "\"http3\"");
+        assert!(toml::from_str::<Config>(&content).is_err());
+    }
+
+    #[test]
+    fn test_server_tls_config() {
+        let content = r#"
+[server]
+port = 443
+tls_cert = "/etc/relay/fullchain.pem"
+tls_key = "/etc/relay/privkey.pem"
 
 [[accounts]]
 type = "claude-api"
-id = "test-1"
-name = "Test Account"
+id = "test"
+name = "Test"
 api_key = "sk-test"
 "#;
+        let config: Config = toml::from_str(content).unwrap();
+        let server = &config.server;
+        assert_eq!(server.tls_cert.as_deref(), Some("/etc/relay/fullchain.pem"));
+        assert_eq!(server.tls_key.as_deref(), Some("/etc/relay/privkey.pem"));
+        assert_eq!(server.tls_client_ca, None);
+        assert_eq!(server.tls_client_auth, TlsClientAuth::Required);
+        assert!(config.validate().is_ok());
 
-        let config: Config = toml::from_str(config_content).unwrap();
-        // Default values for unspecified fields
-        assert_eq!(config.session.sticky_ttl_seconds, 3600);
-        assert_eq!(config.session.renewal_threshold_seconds, 300);
-        // Custom value
-        assert_eq!(config.session.unavailable_cooldown_seconds, 300);
+        let mtls = content.replace(
+            "port = 443",
+            "port = 443\ntls_client_ca = \"/etc/relay/clients.pem\"\ntls_client_auth = \"optional\"",
+        );
+        let config: Config = toml::from_str(&mtls).unwrap();
+        assert_eq!(
+            config.server.tls_client_ca.as_deref(),
+            Some("/etc/relay/clients.pem")
+        );
+        assert_eq!(config.server.tls_client_auth, TlsClientAuth::Optional);
+        assert!(config.validate().is_ok());
+
+        let plain = mtls
+            .replace("tls_cert", "# tls_cert")
+            .replace("tls_key", "# tls_key");
+        let config: Config = toml::from_str(&plain).unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config =
+            toml::from_str(&content.replace("tls_key = \"/etc/relay/privkey.pem\"", "")).unwrap();
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_api_keys_before_server_section() {
+    fn test_server_stream_mode() {
         let content = r#"
-api_keys = ["key1", "key2"]
+[server]
+stream_mode = "direct"
+stream_buffer = 8
+shutdown_grace_seconds = 120
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.server.stream_mode, StreamMode::Direct);
+        assert_eq!(config.server.stream_buffer, 8);
+        assert_eq!(config.server.shutdown_grace_seconds, 120);
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            toml::from_str(&content.replace("stream_buffer = 8", "stream_buffer = 0")).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_api_keys_with_profile() {
+        let content = r#"
+api_keys = [
+    "plain-key",
+    { key = "sdk-key", profile = "agent-sdk" },
+    { key = "table-key" },
+]
 
 [server]
 host = "127.0.0.1"
@@ -339,9 +3794,105 @@ name = "Test"
 api_key = "sk-test"
 "#;
         let config: Config = toml::from_str(content).unwrap();
-        assert_eq!(config.api_keys.len(), 2);
-        assert_eq!(config.api_keys[0], "key1");
-        assert_eq!(config.api_keys[1], "key2");
+        assert_eq!(config.api_keys.len(), 3);
+        assert_eq!(config.api_keys[0].key(), "plain-key");
+        assert_eq!(config.api_keys[0].profile(), ClientProfile::ClaudeCode);
+        assert_eq!(config.api_keys[1].key(), "sdk-key");
+        assert_eq!(config.api_keys[1].profile(), ClientProfile::AgentSdk);
+        assert_eq!(config.api_keys[2].profile(), ClientProfile::ClaudeCode);
+    }
+
+    #[test]
+    fn test_api_keys_with_daily_token_cap() {
+        let content = r#"
+api_keys = [
+    "plain-key",
+    { key = "intern-key", max_tokens_per_day = 200000 },
+    { key = "ops-key", account_pinning = true },
+    { key = "haiku-key", allowed_models = ["claude-3-5-haiku-*"] },
+    { key = "claude-only-key", allowed_platforms = ["claude"] },
+    { key = "trial-key", expires_at = "2030-01-31T00:00:00Z" },
+    { key = "billing-key", scopes = ["usage"] },
+]
+
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.api_keys[0].max_tokens_per_day(), None);
+        assert_eq!(config.api_keys[1].max_tokens_per_day(), Some(200_000));
+        assert_eq!(config.api_keys[1].profile(), ClientProfile::ClaudeCode);
+        assert!(!config.api_keys[1].account_pinning());
+        assert!(config.api_keys[2].account_pinning());
+        assert!(config.api_keys[2].allowed_models().is_empty());
+        assert_eq!(config.api_keys[3].allowed_models(), ["claude-3-5-haiku-*"]);
+        assert!(config.api_keys[3].allowed_platforms().is_empty());
+        assert_eq!(config.api_keys[4].allowed_platforms(), [Platform::Claude]);
+        assert_eq!(config.api_keys[4].expires_at(), None);
+        assert_eq!(
+            config.api_keys[5].expires_at().unwrap().to_rfc3339(),
+            "2030-01-31T00:00:00+00:00"
+        );
+        assert!(config.api_keys[5].scopes().is_empty());
+        assert_eq!(config.api_keys[6].scopes(), [KeyScope::Usage]);
+    }
+
+    #[test]
+    fn test_hashed_api_keys() {
+        let content = r#"
+api_keys = [
+    "plain-key",
+    "sha256:7d1a54127b222502f5b79b5fb0803061152a44f92b37e23c6527baf665d4da9a",
+    { key = "sha256:7D1A54127B222502F5B79B5FB0803061152A44F92B37E23C6527BAF665D4DA9A", name = "ci-bot" },
+]
+
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.validate().is_ok());
+
+        let truncated = content.replace("665d4da9a", "665d4da9");
+        let config: Config = toml::from_str(&truncated).unwrap();
+        assert!(config.validate().is_err());
+        let not_hex = content.replace("665d4da9a", "665d4da9z");
+        let config: Config = toml::from_str(&not_hex).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_api_keys_with_name() {
+        let content = r#"
+api_keys = [
+    "plain-key",
+    { key = "sk-ci", name = "ci-bot" },
+]
+
+[server]
+port = 3000
+
+[[accounts]]
+type = "claude-api"
+id = "test"
+name = "Test"
+api_key = "sk-test"
+"#;
+        let config: Config = toml::from_str(content).unwrap();
+        assert_eq!(config.api_keys[0].name(), None);
+        assert_eq!(config.api_keys[1].key(), "sk-ci");
+        assert_eq!(config.api_keys[1].name(), Some("ci-bot"));
     }
 
     #[test]
@@ -0,0 +1,204 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::config::{ContextLimitMode, ContextLimitsConfig};
+
+/// Rough characters-per-token ratio for English text and code. CJK text
+/// tokenizes denser, so it is underestimated rather than falsely rejected.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Flat cost of an inline image or document, whose base64 payload says
+/// little about its token count.
+const INLINE_MEDIA_TOKENS: u64 = 1600;
+
+/// Context windows of well-known model families, keyed by name prefix.
+const DEFAULT_CONTEXT_WINDOWS: &[(&str, u64)] = &[
+    ("claude-", 200_000),
+    ("gemini-", 1_048_576),
+    ("gemini-1.5-pro", 2_097_152),
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-5", 400_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextOverflow {
+    pub estimated_tokens: u64,
+    pub context_window: u64,
+}
+
+/// Per-model context windows, matched by the longest configured prefix.
+pub struct ContextLimits {
+    mode: ContextLimitMode,
+    windows: Vec<(String, u64)>,
+}
+
+impl ContextLimits {
+    pub fn new(config: &ContextLimitsConfig) -> Self {
+        let mut windows: HashMap<String, u64> = DEFAULT_CONTEXT_WINDOWS
+            .iter()
+            .map(|(prefix, window)| (prefix.to_string(), *window))
+            .collect();
+        windows.extend(config.models.clone());
+
+        let mut windows: Vec<(String, u64)> = windows.into_iter().collect();
+        windows.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+
+        Self {
+            mode: config.mode,
+            windows,
+        }
+    }
+
+    pub fn mode(&self) -> ContextLimitMode {
+        self.mode
+    }
+
+    pub fn context_window(&self, model: &str) -> Option<u64> {
+        self.windows
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .map(|(_, window)| *window)
+    }
+
+    /// `None` when the check is off, the model is unknown or the request fits.
+    pub fn check(&self, model: &str, body: &Value) -> Option<ContextOverflow> {
+        if self.mode == ContextLimitMode::Off {
+            return None;
+        }
+        let context_window = self.context_window(model)?;
+        let estimated_tokens = estimate_tokens(body);
+        (estimated_tokens > context_window).then_some(ContextOverflow {
+            estimated_tokens,
+            context_window,
+        })
+    }
+}
+
+/// Estimates prompt tokens from every string value in a request body.
+pub fn estimate_tokens(body: &Value) -> u64 {
+    let mut chars = 0;
+    let mut media = 0;
+    count_value(body, None, &mut chars, &mut media);
+    (chars / CHARS_PER_TOKEN) as u64 + media * INLINE_MEDIA_TOKENS
+}
+
+fn count_value(value: &Value, parent_key: Option<&str>, chars: &mut usize, media: &mut u64) {
+    match value {
+        Value::String(s) => {
+            if s.starts_with("data:") && s.contains(";base64,") {
+                *media += 1;
+            } else {
+                *chars += s.chars().count();
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                count_value(item, parent_key, chars, media);
+            }
+        }
+        Value::Object(map) => {
+            let is_base64_source = map.get("type").and_then(|t| t.as_str()) == Some("base64");
+            let is_inline_data = matches!(parent_key, Some("inlineData" | "inline_data"));
+
+            for (key, child) in map {
+                if key == "data" && (is_base64_source || is_inline_data) {
+                    *media += 1;
+                } else if key != "type" && key != "media_type" && key != "mimeType" {
+                    count_value(child, Some(key), chars, media);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn limits(mode: ContextLimitMode, models: &[(&str, u64)]) -> ContextLimits {
+        ContextLimits::new(&ContextLimitsConfig {
+            mode,
+            models: models
+                .iter()
+                .map(|(prefix, window)| (prefix.to_string(), *window))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let limits = limits(ContextLimitMode::Reject, &[("claude-3-haiku", 1000)]);
+
+        assert_eq!(limits.context_window("claude-3-haiku-20240307"), Some(1000));
+        assert_eq!(
+            limits.context_window("claude-sonnet-4-20250514"),
+            Some(200_000)
+        );
+        assert_eq!(limits.context_window("gemini-1.5-pro-002"), Some(2_097_152));
+        assert_eq!(limits.context_window("gemini-2.5-pro"), Some(1_048_576));
+        assert_eq!(limits.context_window("my-custom-model"), None);
+    }
+
+    #[test]
+    fn test_estimate_skips_base64_payloads() {
+        let body = json!({
+            "model": "claude",
+            "system": "a".repeat(400),
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "b".repeat(800)},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "A".repeat(100_000)}},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                ]
+            }]
+        });
+
+        // 400 + 800 + "claude" + "user" characters, plus two images
+        assert_eq!(estimate_tokens(&body), 1210 / 4 + 2 * INLINE_MEDIA_TOKENS);
+    }
+
+    #[test]
+    fn test_estimate_gemini_inline_data() {
+        let body = json!({
+            "contents": [{
+                "role": "user",
+                "parts": [
+                    {"text": "c".repeat(40)},
+                    {"inlineData": {"mimeType": "image/jpeg", "data": "A".repeat(50_000)}}
+                ]
+            }]
+        });
+
+        assert_eq!(estimate_tokens(&body), 44 / 4 + INLINE_MEDIA_TOKENS);
+    }
+
+    #[test]
+    fn test_check_reports_overflow() {
+        let limits = limits(ContextLimitMode::Reject, &[("tiny-", 10)]);
+        let fits = json!({"messages": [{"content": "x".repeat(40)}]});
+        let overflows = json!({"messages": [{"content": "x".repeat(80)}]});
+
+        assert_eq!(limits.check("tiny-model", &fits), None);
+        assert_eq!(
+            limits.check("tiny-model", &overflows),
+            Some(ContextOverflow {
+                estimated_tokens: 20,
+                context_window: 10
+            })
+        );
+        assert_eq!(limits.check("unknown-model", &overflows), None);
+    }
+
+    #[test]
+    fn test_check_disabled() {
+        let limits = limits(ContextLimitMode::Off, &[("tiny-", 10)]);
+        let body = json!({"messages": [{"content": "x".repeat(80)}]});
+        assert_eq!(limits.check("tiny-model", &body), None);
+    }
+}
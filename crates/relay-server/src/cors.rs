@@ -0,0 +1,129 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+use crate::config::CorsConfig;
+
+/// The CORS layer for `[server.cors]`, or `None` without allowed origins.
+/// `*` allows any origin or header.
+pub fn cors_layer(config: &CorsConfig) -> Result<Option<CorsLayer>, String> {
+    if config.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin.trim_end_matches('/'))
+                    .map_err(|_| format!("Invalid CORS origin '{}'", origin))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| format!("Invalid CORS method '{}'", method))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let headers = if config.allowed_headers.iter().any(|header| header == "*") {
+        AllowHeaders::any()
+    } else {
+        let headers = config
+            .allowed_headers
+            .iter()
+            .map(|header| {
+                HeaderName::from_bytes(header.to_ascii_lowercase().as_bytes())
+                    .map_err(|_| format!("Invalid CORS header '{}'", header))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowHeaders::list(headers)
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(AllowMethods::list(methods))
+            .allow_headers(headers)
+            .max_age(Duration::from_secs(config.max_age_seconds)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::post, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_preflight_from_allowed_origin() {
+        let config = CorsConfig {
+            allowed_origins: vec!["https://chat.example.com/".to_string()],
+            ..Default::default()
+        };
+        let app = Router::new()
+            .route("/openai/v1/chat/completions", post(|| async { "ok" }))
+            .layer(cors_layer(&config).unwrap().unwrap());
+
+        let preflight = |origin: &str| {
+            let request = axum::http::Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/openai/v1/chat/completions")
+                .header("origin", origin)
+                .header("access-control-request-method", "POST")
+                .header(
+                    "access-control-request-headers",
+                    "authorization,content-type",
+                )
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let response = preflight("https://chat.example.com").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://chat.example.com"
+        );
+        assert!(headers["access-control-allow-headers"]
+            .to_str()
+            .unwrap()
+            .contains("authorization"));
+        assert_eq!(headers["access-control-max-age"], "3600");
+
+        let response = preflight("https://evil.example.com").await.unwrap();
+        assert!(response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none());
+    }
+
+    #[test]
+    fn test_cors_layer_config() {
+        assert!(cors_layer(&CorsConfig::default()).unwrap().is_none());
+
+        let any = CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            allowed_headers: vec!["*".to_string()],
+            ..Default::default()
+        };
+        assert!(cors_layer(&any).unwrap().is_some());
+
+        let invalid = CorsConfig {
+            allowed_origins: vec!["https://chat.example.com".to_string()],
+            allowed_methods: vec!["NOT A METHOD".to_string()],
+            ..Default::default()
+        };
+        assert!(cors_layer(&invalid).is_err());
+    }
+}
@@ -1,5 +1,18 @@
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
+use crate::audit::AuditRecord;
+use crate::config::{DatabaseConfig, SynchronousMode};
+use crate::ip_filter::IpRange;
+use crate::middleware::KeyScope;
+use chrono::{DateTime, Utc};
+use relay_claude::ClientProfile;
+use relay_core::Platform;
+use sqlx::{
+    sqlite::{
+        SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous,
+    },
+    Pool, Row, Sqlite,
+};
 use std::path::Path;
+use std::time::Duration;
 use tracing::info;
 
 pub type DbPool = Pool<Sqlite>;
@@ -32,6 +45,155 @@ const MIGRATIONS: &[&str] = &[
     r#"
     ALTER TABLE usage_stats ADD COLUMN client_api_key_hash TEXT NOT NULL DEFAULT 'legacy';
     "#,
+    // Migration 3: Add reasoning_tokens column
+    r#"
+    ALTER TABLE usage_stats ADD COLUMN reasoning_tokens INTEGER NOT NULL DEFAULT 0;
+    "#,
+    // Migration 4: Per-request latency and status log
+    r#"
+    CREATE TABLE IF NOT EXISTS request_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        client_api_key_hash TEXT NOT NULL,
+        platform TEXT NOT NULL,
+        account_id TEXT,
+        model TEXT NOT NULL,
+        path TEXT NOT NULL,
+        status_code INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        retry_count INTEGER NOT NULL DEFAULT 0,
+        streamed INTEGER NOT NULL DEFAULT 0,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_request_log_account_date ON request_log(account_id, created_at);
+    CREATE INDEX IF NOT EXISTS idx_request_log_client_key ON request_log(client_api_key_hash, created_at);
+    "#,
+    // Migration 5: Request ids and the audit log
+    r#"
+    ALTER TABLE request_log ADD COLUMN request_id TEXT;
+
+    CREATE TABLE IF NOT EXISTS audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        request_id TEXT NOT NULL,
+        client_api_key_hash TEXT NOT NULL,
+        method TEXT NOT NULL,
+        path TEXT NOT NULL,
+        model TEXT,
+        account_id TEXT,
+        status_code INTEGER NOT NULL,
+        user_agent TEXT,
+        request_body TEXT,
+        response_body TEXT,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_request_log_request_id ON request_log(request_id);
+    CREATE INDEX IF NOT EXISTS idx_audit_request_id ON audit_log(request_id);
+    CREATE INDEX IF NOT EXISTS idx_audit_client_key ON audit_log(client_api_key_hash, created_at);
+    "#,
+    // Migration 6: Webhook deliveries that ran out of retries
+    r#"
+    CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        event_id TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        url TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        attempts INTEGER NOT NULL,
+        last_error TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        replayed_at DATETIME
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_webhook_dead_letters_created ON webhook_dead_letters(created_at);
+    "#,
+    // Migration 7: Names of configured client keys
+    r#"
+    CREATE TABLE IF NOT EXISTS api_key_names (
+        client_api_key_hash TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+    "#,
+    // Migration 8: Message batches and the account that holds them
+    r#"
+    CREATE TABLE IF NOT EXISTS message_batches (
+        batch_id TEXT PRIMARY KEY,
+        account_id TEXT NOT NULL,
+        client_api_key_hash TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_message_batches_client_key ON message_batches(client_api_key_hash, created_at);
+    "#,
+    // Migration 9: Client keys issued through the admin API
+    r#"
+    CREATE TABLE IF NOT EXISTS client_keys (
+        id TEXT PRIMARY KEY,
+        client_api_key_hash TEXT NOT NULL UNIQUE,
+        key_prefix TEXT NOT NULL,
+        name TEXT NOT NULL,
+        profile TEXT NOT NULL,
+        max_tokens_per_day INTEGER,
+        account_pinning INTEGER NOT NULL DEFAULT 0,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        revoked_at DATETIME
+    );
+    "#,
+    // Migration 10: Models an issued client key may use
+    r#"
+    ALTER TABLE client_keys ADD COLUMN allowed_models TEXT NOT NULL DEFAULT '[]';
+    "#,
+    // Migration 11: Platforms an issued client key may use
+    r#"
+    ALTER TABLE client_keys ADD COLUMN allowed_platforms TEXT NOT NULL DEFAULT '[]';
+    "#,
+    // Migration 12: Request rate of an issued client key
+    r#"
+    ALTER TABLE client_keys ADD COLUMN requests_per_minute INTEGER;
+    ALTER TABLE client_keys ADD COLUMN burst INTEGER;
+    "#,
+    // Migration 13: Expiry of an issued client key
+    r#"
+    ALTER TABLE client_keys ADD COLUMN expires_at TEXT;
+    "#,
+    // Migration 14: Client addresses an issued client key may be used from
+    r#"
+    ALTER TABLE client_keys ADD COLUMN allowed_ips TEXT NOT NULL DEFAULT '[]';
+    "#,
+    // Migration 15: Failed authentication attempts
+    r#"
+    CREATE TABLE IF NOT EXISTS auth_failures (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        masked_key TEXT,
+        client_ip TEXT,
+        path TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_auth_failures_created ON auth_failures(created_at);
+    "#,
+    // Migration 16: The key an issued client key replaced
+    r#"
+    ALTER TABLE client_keys ADD COLUMN rotated_from TEXT;
+    "#,
+    // Migration 17: Client addresses in the audit log
+    r#"
+    ALTER TABLE audit_log ADD COLUMN client_ip TEXT;
+    "#,
+    // Migration 18: Per-key concurrent request limits
+    r#"
+    ALTER TABLE client_keys ADD COLUMN max_concurrent_requests INTEGER;
+    "#,
+    // Migration 19: Tenants of issued client keys
+    r#"
+    ALTER TABLE client_keys ADD COLUMN tenant TEXT;
+    "#,
+    // Migration 20: Scopes of issued client keys
+    r#"
+    ALTER TABLE client_keys ADD COLUMN scopes TEXT NOT NULL DEFAULT '[]';
+    "#,
 ];
 
 async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
@@ -84,49 +246,279 @@ async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
     Ok(())
 }
 
-pub async fn init_database(path: &str) -> Result<DbPool, sqlx::Error> {
+pub async fn init_database(path: &str, config: &DatabaseConfig) -> Result<DbPool, sqlx::Error> {
     if let Some(parent) = Path::new(path).parent() {
         std::fs::create_dir_all(parent).ok();
     }
 
-    let database_url = format!("sqlite:{}?mode=rwc", path);
+    let journal_mode = if config.wal {
+        SqliteJournalMode::Wal
+    } else {
+        SqliteJournalMode::Delete
+    };
+    let synchronous = match config.synchronous {
+        SynchronousMode::Off => SqliteSynchronous::Off,
+        SynchronousMode::Normal => SqliteSynchronous::Normal,
+        SynchronousMode::Full => SqliteSynchronous::Full,
+        SynchronousMode::Extra => SqliteSynchronous::Extra,
+    };
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .journal_mode(journal_mode)
+        .synchronous(synchronous)
+        .busy_timeout(Duration::from_millis(config.busy_timeout_ms));
 
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
+        .max_connections(config.max_connections)
+        .connect_with(options)
         .await?;
 
     run_migrations(&pool).await?;
 
-    info!(database = %path, "Database initialized");
+    info!(
+        database = %path,
+        max_connections = config.max_connections,
+        wal = config.wal,
+        "Database initialized"
+    );
 
     Ok(pool)
 }
 
+/// Token counts reported by the upstream for a single request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cache_creation_tokens: u32,
+    pub cache_read_tokens: u32,
+    /// Reasoning tokens, already counted in `output_tokens`.
+    pub reasoning_tokens: u32,
+}
+
+impl TokenUsage {
+    pub fn is_empty(&self) -> bool {
+        self.input_tokens == 0 && self.output_tokens == 0
+    }
+
+    /// Input, output and cache tokens, as counted against daily limits.
+    pub fn total(&self) -> u64 {
+        self.input_tokens as u64
+            + self.output_tokens as u64
+            + self.cache_creation_tokens as u64
+            + self.cache_read_tokens as u64
+    }
+}
+
+const INSERT_USAGE: &str = r#"
+    INSERT INTO usage_stats
+    (client_api_key_hash, account_id, model, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, reasoning_tokens)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+"#;
+
 pub async fn record_usage(
     pool: &DbPool,
     client_api_key_hash: &str,
     account_id: &str,
     model: &str,
-    input_tokens: u32,
-    output_tokens: u32,
-    cache_creation_tokens: u32,
-    cache_read_tokens: u32,
+    usage: &TokenUsage,
 ) -> Result<(), sqlx::Error> {
+    sqlx::query(INSERT_USAGE)
+        .bind(client_api_key_hash)
+        .bind(account_id)
+        .bind(model)
+        .bind(usage.input_tokens as i64)
+        .bind(usage.output_tokens as i64)
+        .bind(usage.cache_creation_tokens as i64)
+        .bind(usage.cache_read_tokens as i64)
+        .bind(usage.reasoning_tokens as i64)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// A `usage_stats` row waiting to be written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageRow {
+    pub client_api_key_hash: String,
+    pub account_id: String,
+    pub model: String,
+    pub usage: TokenUsage,
+}
+
+/// Inserts `rows` in a single transaction.
+pub async fn record_usage_batch(pool: &DbPool, rows: &[UsageRow]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for row in rows {
+        sqlx::query(INSERT_USAGE)
+            .bind(&row.client_api_key_hash)
+            .bind(&row.account_id)
+            .bind(&row.model)
+            .bind(row.usage.input_tokens as i64)
+            .bind(row.usage.output_tokens as i64)
+            .bind(row.usage.cache_creation_tokens as i64)
+            .bind(row.usage.cache_read_tokens as i64)
+            .bind(row.usage.reasoning_tokens as i64)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// One relayed request as seen by the client.
+#[derive(Debug, Clone)]
+pub struct RequestLogEntry {
+    pub request_id: String,
+    pub client_api_key_hash: String,
+    pub platform: String,
+    /// The account that served the last attempt, if one was selected.
+    pub account_id: Option<String>,
+    pub model: String,
+    pub path: String,
+    pub status_code: u16,
+    pub duration_ms: u64,
+    pub retry_count: u32,
+    pub streamed: bool,
+}
+
+pub async fn record_request(pool: &DbPool, entry: &RequestLogEntry) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"
-        INSERT INTO usage_stats
-        (client_api_key_hash, account_id, model, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens)
-        VALUES (?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO request_log
+        (request_id, client_api_key_hash, platform, account_id, model, path, status_code, duration_ms, retry_count, streamed)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
     )
-    .bind(client_api_key_hash)
+    .bind(&entry.request_id)
+    .bind(&entry.client_api_key_hash)
+    .bind(&entry.platform)
+    .bind(&entry.account_id)
+    .bind(&entry.model)
+    .bind(&entry.path)
+    .bind(entry.status_code as i64)
+    .bind(entry.duration_ms as i64)
+    .bind(entry.retry_count as i64)
+    .bind(entry.streamed)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remembers which account created a message batch, so later requests for
+/// it go to the same account.
+pub async fn insert_message_batch(
+    pool: &DbPool,
+    batch_id: &str,
+    account_id: &str,
+    client_api_key_hash: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO message_batches (batch_id, account_id, client_api_key_hash) VALUES (?, ?, ?)",
+    )
+    .bind(batch_id)
     .bind(account_id)
-    .bind(model)
-    .bind(input_tokens as i64)
-    .bind(output_tokens as i64)
-    .bind(cache_creation_tokens as i64)
-    .bind(cache_read_tokens as i64)
+    .bind(client_api_key_hash)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The account holding `batch_id`, if the batch was created with this
+/// client key.
+pub async fn get_message_batch_account(
+    pool: &DbPool,
+    batch_id: &str,
+    client_api_key_hash: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT account_id FROM message_batches WHERE batch_id = ? AND client_api_key_hash = ?",
+    )
+    .bind(batch_id)
+    .bind(client_api_key_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(account_id,)| account_id))
+}
+
+/// `(batch_id, account_id)` of the client key's batches, newest first.
+pub async fn list_message_batches(
+    pool: &DbPool,
+    client_api_key_hash: &str,
+    limit: i64,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT batch_id, account_id FROM message_batches
+        WHERE client_api_key_hash = ?
+        ORDER BY created_at DESC, rowid DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(client_api_key_hash)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Number of `request_log` rows for one account and status code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountStatusCount {
+    pub account_id: String,
+    pub status_code: u16,
+    pub count: u64,
+}
+
+/// Requests per account and final status code over the last `hours` hours.
+pub async fn get_account_status_counts(
+    pool: &DbPool,
+    hours: i64,
+) -> Result<Vec<AccountStatusCount>, sqlx::Error> {
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT account_id, status_code, COUNT(*)
+        FROM request_log
+        WHERE account_id IS NOT NULL AND created_at >= datetime('now', ? || ' hours')
+        GROUP BY account_id, status_code
+        "#,
+    )
+    .bind(-hours)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(account_id, status_code, count)| AccountStatusCount {
+            account_id,
+            status_code: status_code as u16,
+            count: count as u64,
+        })
+        .collect())
+}
+
+pub async fn record_audit(pool: &DbPool, record: &AuditRecord) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log
+        (request_id, client_api_key_hash, client_ip, method, path, model, account_id, status_code, user_agent, request_body, response_body)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&record.request_id)
+    .bind(&record.client_api_key_hash)
+    .bind(&record.client_ip)
+    .bind(&record.method)
+    .bind(&record.path)
+    .bind(&record.model)
+    .bind(&record.account_id)
+    .bind(record.status_code as i64)
+    .bind(&record.user_agent)
+    .bind(&record.request_body)
+    .bind(&record.response_body)
     .execute(pool)
     .await?;
 
@@ -139,6 +531,8 @@ pub struct UsageAggregate {
     pub account_id: String,
     pub total_input: i64,
     pub total_output: i64,
+    pub total_cache_creation: i64,
+    pub total_cache_read: i64,
     pub total_requests: i64,
 }
 
@@ -148,12 +542,14 @@ pub async fn get_usage_by_account(
     account_id: &str,
     days: i32,
 ) -> Result<UsageAggregate, sqlx::Error> {
-    let row: Option<(String, i64, i64, i64)> = sqlx::query_as(
+    let row: Option<(String, i64, i64, i64, i64, i64)> = sqlx::query_as(
         r#"
         SELECT
             account_id,
             COALESCE(SUM(input_tokens), 0) as total_input,
             COALESCE(SUM(output_tokens), 0) as total_output,
+            COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation,
+            COALESCE(SUM(cache_read_tokens), 0) as total_cache_read,
             COALESCE(SUM(request_count), 0) as total_requests
         FROM usage_stats
         WHERE account_id = ?
@@ -166,23 +562,615 @@ pub async fn get_usage_by_account(
     .fetch_optional(pool)
     .await?;
 
-    Ok(row.map(|(account_id, total_input, total_output, total_requests)| UsageAggregate {
-        account_id,
-        total_input,
-        total_output,
-        total_requests,
-    }).unwrap_or(UsageAggregate {
-        account_id: account_id.to_string(),
-        total_input: 0,
-        total_output: 0,
-        total_requests: 0,
-    }))
+    Ok(row
+        .map(
+            |(
+                account_id,
+                total_input,
+                total_output,
+                total_cache_creation,
+                total_cache_read,
+                total_requests,
+            )| UsageAggregate {
+                account_id,
+                total_input,
+                total_output,
+                total_cache_creation,
+                total_cache_read,
+                total_requests,
+            },
+        )
+        .unwrap_or(UsageAggregate {
+            account_id: account_id.to_string(),
+            total_input: 0,
+            total_output: 0,
+            total_cache_creation: 0,
+            total_cache_read: 0,
+            total_requests: 0,
+        }))
+}
+
+/// Usage of one model on one account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelUsage {
+    pub model: String,
+    pub account_id: String,
+    pub total_input: i64,
+    pub total_output: i64,
+    pub total_cache_creation: i64,
+    pub total_cache_read: i64,
+    pub total_requests: i64,
+}
+
+pub async fn get_usage_by_model(pool: &DbPool, days: i32) -> Result<Vec<ModelUsage>, sqlx::Error> {
+    let rows: Vec<(String, String, i64, i64, i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT
+            model,
+            account_id,
+            COALESCE(SUM(input_tokens), 0) as total_input,
+            COALESCE(SUM(output_tokens), 0) as total_output,
+            COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation,
+            COALESCE(SUM(cache_read_tokens), 0) as total_cache_read,
+            COALESCE(SUM(request_count), 0) as total_requests
+        FROM usage_stats
+        WHERE created_at >= datetime('now', ? || ' days')
+        GROUP BY model, account_id
+        "#,
+    )
+    .bind(-days)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                model,
+                account_id,
+                total_input,
+                total_output,
+                total_cache_creation,
+                total_cache_read,
+                total_requests,
+            )| ModelUsage {
+                model,
+                account_id,
+                total_input,
+                total_output,
+                total_cache_creation,
+                total_cache_read,
+                total_requests,
+            },
+        )
+        .collect())
+}
+
+/// Records the configured name of a client key; names of keys since removed
+/// from the config are kept for older usage.
+pub async fn upsert_api_key_name(
+    pool: &DbPool,
+    client_api_key_hash: &str,
+    name: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO api_key_names (client_api_key_hash, name)
+        VALUES (?, ?)
+        ON CONFLICT(client_api_key_hash) DO UPDATE SET
+            name = excluded.name,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(client_api_key_hash)
+    .bind(name)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A client key issued through the admin API. Only the key's hash is
+/// stored; the key itself is shown once, when it is created.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ClientKey {
+    pub id: String,
+    #[serde(skip)]
+    pub client_api_key_hash: String,
+    /// The start of the key, to tell keys apart.
+    pub key_prefix: String,
+    pub name: String,
+    pub profile: ClientProfile,
+    pub max_tokens_per_day: Option<u64>,
+    pub account_pinning: bool,
+    /// Model patterns such as `claude-3-5-haiku-*`; empty allows all.
+    pub allowed_models: Vec<String>,
+    /// Empty allows every platform.
+    pub allowed_platforms: Vec<Platform>,
+    /// Empty allows every scope.
+    pub scopes: Vec<KeyScope>,
+    pub allowed_ips: Vec<IpRange>,
+    /// Overrides `[rate_limit]` when set.
+    pub requests_per_minute: Option<u32>,
+    pub burst: Option<u32>,
+    /// Overrides `[rate_limit] max_concurrent_requests` when set.
+    pub max_concurrent_requests: Option<u32>,
+    /// A `[[tenants]]` id; the key is only served by that tenant's accounts.
+    pub tenant: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The id of the key this one replaced, whose usage identity it keeps.
+    pub rotated_from: Option<String>,
+    pub created_at: String,
+    pub revoked_at: Option<String>,
+}
+
+// Read by column name: a tuple row would exceed the 16 columns sqlx
+// decodes.
+impl sqlx::FromRow<'_, SqliteRow> for ClientKey {
+    fn from_row(row: &SqliteRow) -> Result<Self, sqlx::Error> {
+        let profile: String = row.try_get("profile")?;
+        let allowed_models: String = row.try_get("allowed_models")?;
+        let allowed_platforms: String = row.try_get("allowed_platforms")?;
+        let scopes: String = row.try_get("scopes")?;
+        let allowed_ips: String = row.try_get("allowed_ips")?;
+        let max_tokens_per_day: Option<i64> = row.try_get("max_tokens_per_day")?;
+        let requests_per_minute: Option<i64> = row.try_get("requests_per_minute")?;
+        let burst: Option<i64> = row.try_get("burst")?;
+        let max_concurrent_requests: Option<i64> = row.try_get("max_concurrent_requests")?;
+        let expires_at: Option<String> = row.try_get("expires_at")?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            client_api_key_hash: row.try_get("client_api_key_hash")?,
+            key_prefix: row.try_get("key_prefix")?,
+            name: row.try_get("name")?,
+            profile: serde_json::from_value(serde_json::Value::String(profile)).unwrap_or_default(),
+            max_tokens_per_day: max_tokens_per_day.map(|tokens| tokens as u64),
+            account_pinning: row.try_get("account_pinning")?,
+            allowed_models: serde_json::from_str(&allowed_models).unwrap_or_default(),
+            allowed_platforms: serde_json::from_str(&allowed_platforms).unwrap_or_default(),
+            scopes: serde_json::from_str(&scopes).unwrap_or_default(),
+            allowed_ips: serde_json::from_str(&allowed_ips).unwrap_or_default(),
+            requests_per_minute: requests_per_minute.map(|rate| rate as u32),
+            burst: burst.map(|burst| burst as u32),
+            max_concurrent_requests: max_concurrent_requests.map(|max| max as u32),
+            tenant: row.try_get("tenant")?,
+            expires_at: expires_at
+                .and_then(|expires_at| DateTime::parse_from_rfc3339(&expires_at).ok())
+                .map(|expires_at| expires_at.with_timezone(&Utc)),
+            rotated_from: row.try_get("rotated_from")?,
+            created_at: row.try_get("created_at")?,
+            revoked_at: row.try_get("revoked_at")?,
+        })
+    }
+}
+
+const CLIENT_KEY_COLUMNS: &str = "id, client_api_key_hash, key_prefix, name, profile, max_tokens_per_day, account_pinning, allowed_models, allowed_platforms, scopes, allowed_ips, requests_per_minute, burst, max_concurrent_requests, tenant, expires_at, rotated_from, created_at, revoked_at";
+
+/// Stores a new client key and returns it as saved, with its creation time.
+pub async fn create_client_key(pool: &DbPool, key: &ClientKey) -> Result<ClientKey, sqlx::Error> {
+    let profile = serde_json::to_value(key.profile)
+        .ok()
+        .and_then(|profile| profile.as_str().map(str::to_string))
+        .unwrap_or_default();
+    sqlx::query(
+        r#"
+        INSERT INTO client_keys (id, client_api_key_hash, key_prefix, name, profile, max_tokens_per_day, account_pinning, allowed_models, allowed_platforms, scopes, allowed_ips, requests_per_minute, burst, max_concurrent_requests, tenant, expires_at, rotated_from)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&key.id)
+    .bind(&key.client_api_key_hash)
+    .bind(&key.key_prefix)
+    .bind(&key.name)
+    .bind(profile)
+    .bind(key.max_tokens_per_day.map(|tokens| tokens as i64))
+    .bind(key.account_pinning)
+    .bind(serde_json::json!(key.allowed_models).to_string())
+    .bind(serde_json::json!(key.allowed_platforms).to_string())
+    .bind(serde_json::json!(key.scopes).to_string())
+    .bind(serde_json::json!(key.allowed_ips).to_string())
+    .bind(key.requests_per_minute)
+    .bind(key.burst)
+    .bind(key.max_concurrent_requests)
+    .bind(&key.tenant)
+    .bind(key.expires_at.map(|expires_at| expires_at.to_rfc3339()))
+    .bind(&key.rotated_from)
+    .execute(pool)
+    .await?;
+
+    let client_key: ClientKey = sqlx::query_as(&format!(
+        "SELECT {} FROM client_keys WHERE id = ?",
+        CLIENT_KEY_COLUMNS
+    ))
+    .bind(&key.id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(client_key)
+}
+
+pub async fn get_client_key(pool: &DbPool, id: &str) -> Result<Option<ClientKey>, sqlx::Error> {
+    let client_key: Option<ClientKey> = sqlx::query_as(&format!(
+        "SELECT {} FROM client_keys WHERE id = ?",
+        CLIENT_KEY_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(client_key)
+}
+
+pub async fn set_client_key_expiry(
+    pool: &DbPool,
+    id: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE client_keys SET expires_at = ? WHERE id = ?")
+        .bind(expires_at.to_rfc3339())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Every issued key, revoked ones included, newest first.
+pub async fn list_client_keys(pool: &DbPool) -> Result<Vec<ClientKey>, sqlx::Error> {
+    let keys: Vec<ClientKey> = sqlx::query_as(&format!(
+        "SELECT {} FROM client_keys ORDER BY created_at DESC, rowid DESC",
+        CLIENT_KEY_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(keys)
+}
+
+/// Revokes a key; `false` if there is no such key or it was already revoked.
+pub async fn revoke_client_key(pool: &DbPool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE client_keys SET revoked_at = CURRENT_TIMESTAMP WHERE id = ? AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Usage of one model by one client key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyUsage {
+    pub client_api_key_hash: String,
+    pub name: Option<String>,
+    pub model: String,
+    pub total_input: i64,
+    pub total_output: i64,
+    pub total_cache_creation: i64,
+    pub total_cache_read: i64,
+    pub total_requests: i64,
+}
+
+type KeyUsageRow = (String, Option<String>, String, i64, i64, i64, i64, i64);
+
+pub async fn get_usage_by_key(pool: &DbPool, days: i32) -> Result<Vec<KeyUsage>, sqlx::Error> {
+    let rows: Vec<KeyUsageRow> = sqlx::query_as(
+        r#"
+        SELECT
+            u.client_api_key_hash,
+            n.name,
+            u.model,
+            COALESCE(SUM(u.input_tokens), 0) as total_input,
+            COALESCE(SUM(u.output_tokens), 0) as total_output,
+            COALESCE(SUM(u.cache_creation_tokens), 0) as total_cache_creation,
+            COALESCE(SUM(u.cache_read_tokens), 0) as total_cache_read,
+            COALESCE(SUM(u.request_count), 0) as total_requests
+        FROM usage_stats u
+        LEFT JOIN api_key_names n ON n.client_api_key_hash = u.client_api_key_hash
+        WHERE u.created_at >= datetime('now', ? || ' days')
+        GROUP BY u.client_api_key_hash, u.model
+        "#,
+    )
+    .bind(-days)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                client_api_key_hash,
+                name,
+                model,
+                total_input,
+                total_output,
+                total_cache_creation,
+                total_cache_read,
+                total_requests,
+            )| KeyUsage {
+                client_api_key_hash,
+                name,
+                model,
+                total_input,
+                total_output,
+                total_cache_creation,
+                total_cache_read,
+                total_requests,
+            },
+        )
+        .collect())
+}
+
+/// Tokens and requests of one UTC day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyUsage {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    /// Input, output and cache tokens.
+    pub total_tokens: i64,
+    pub total_requests: i64,
+}
+
+/// Per-day totals over the last `days` days, oldest first. Days without
+/// usage are omitted.
+pub async fn get_daily_usage(pool: &DbPool, days: i32) -> Result<Vec<DailyUsage>, sqlx::Error> {
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT
+            date(created_at) as day,
+            COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0),
+            COALESCE(SUM(request_count), 0)
+        FROM usage_stats
+        WHERE created_at >= datetime('now', ? || ' days')
+        GROUP BY day
+        ORDER BY day
+        "#,
+    )
+    .bind(-days)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(date, total_tokens, total_requests)| DailyUsage {
+            date,
+            total_tokens,
+            total_requests,
+        })
+        .collect())
 }
 
 // ============================================================================
 // Sticky Session CRUD
 // ============================================================================
 
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub event_id: String,
+    pub event_type: String,
+    pub url: String,
+    pub payload: String,
+    pub attempts: i64,
+    pub last_error: String,
+    pub created_at: String,
+    pub replayed_at: Option<String>,
+}
+
+type DeadLetterRow = (
+    i64,
+    String,
+    String,
+    String,
+    String,
+    i64,
+    String,
+    String,
+    Option<String>,
+);
+
+impl From<DeadLetterRow> for DeadLetter {
+    fn from(row: DeadLetterRow) -> Self {
+        let (id, event_id, event_type, url, payload, attempts, last_error, created_at, replayed_at) =
+            row;
+        Self {
+            id,
+            event_id,
+            event_type,
+            url,
+            payload,
+            attempts,
+            last_error,
+            created_at,
+            replayed_at,
+        }
+    }
+}
+
+const DEAD_LETTER_COLUMNS: &str =
+    "id, event_id, event_type, url, payload, attempts, last_error, created_at, replayed_at";
+
+pub async fn insert_dead_letter(
+    pool: &DbPool,
+    event_id: &str,
+    event_type: &str,
+    url: &str,
+    payload: &str,
+    attempts: u32,
+    last_error: &str,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO webhook_dead_letters (event_id, event_type, url, payload, attempts, last_error)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(event_id)
+    .bind(event_type)
+    .bind(url)
+    .bind(payload)
+    .bind(attempts as i64)
+    .bind(last_error)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+/// Newest first; replayed entries are only included on request.
+pub async fn list_dead_letters(
+    pool: &DbPool,
+    include_replayed: bool,
+    limit: i64,
+) -> Result<Vec<DeadLetter>, sqlx::Error> {
+    let rows: Vec<DeadLetterRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM webhook_dead_letters WHERE ? OR replayed_at IS NULL ORDER BY id DESC LIMIT ?",
+        DEAD_LETTER_COLUMNS
+    ))
+    .bind(include_replayed)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(DeadLetter::from).collect())
+}
+
+pub async fn get_dead_letter(pool: &DbPool, id: i64) -> Result<Option<DeadLetter>, sqlx::Error> {
+    let row: Option<DeadLetterRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM webhook_dead_letters WHERE id = ?",
+        DEAD_LETTER_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(DeadLetter::from))
+}
+
+pub async fn mark_dead_letter_replayed(pool: &DbPool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE webhook_dead_letters SET attempts = attempts + 1, replayed_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn record_dead_letter_failure(
+    pool: &DbPool,
+    id: i64,
+    error: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE webhook_dead_letters SET attempts = attempts + 1, last_error = ? WHERE id = ?",
+    )
+    .bind(error)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A rejected request, recorded without the key itself.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AuthFailure {
+    /// The first and last characters of the key sent, if any.
+    pub masked_key: Option<String>,
+    pub client_ip: Option<String>,
+    pub path: String,
+    /// Such as `missing_key`, `invalid_key` or `expired_key`.
+    pub reason: String,
+    pub created_at: String,
+}
+
+pub async fn record_auth_failure(
+    pool: &DbPool,
+    masked_key: Option<&str>,
+    client_ip: Option<&str>,
+    path: &str,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO auth_failures (masked_key, client_ip, path, reason) VALUES (?, ?, ?, ?)",
+    )
+    .bind(masked_key)
+    .bind(client_ip)
+    .bind(path)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+type AuthFailureRow = (Option<String>, Option<String>, String, String, String);
+
+/// Newest first.
+pub async fn list_auth_failures(
+    pool: &DbPool,
+    limit: i64,
+) -> Result<Vec<AuthFailure>, sqlx::Error> {
+    let rows: Vec<AuthFailureRow> = sqlx::query_as(
+        "SELECT masked_key, client_ip, path, reason, created_at
+         FROM auth_failures ORDER BY id DESC LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(masked_key, client_ip, path, reason, created_at)| AuthFailure {
+                masked_key,
+                client_ip,
+                path,
+                reason,
+                created_at,
+            },
+        )
+        .collect())
+}
+
+/// Tokens of every kind recorded for a client key since UTC midnight.
+pub async fn tokens_used_today(
+    pool: &DbPool,
+    client_api_key_hash: &str,
+) -> Result<u64, sqlx::Error> {
+    let (total,): (i64,) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0)
+        FROM usage_stats
+        WHERE client_api_key_hash = ?
+        AND created_at >= date('now')
+        "#,
+    )
+    .bind(client_api_key_hash)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(total.max(0) as u64)
+}
+
+/// Tokens of every kind recorded across all client keys since UTC midnight.
+pub async fn total_tokens_today(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let (total,): (i64,) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0)
+        FROM usage_stats
+        WHERE created_at >= date('now')
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(total.max(0) as u64)
+}
+
 pub async fn get_sticky_session(
     pool: &DbPool,
     session_hash: &str,
@@ -244,6 +1232,24 @@ pub async fn cleanup_expired_sessions(pool: &DbPool) -> Result<u64, sqlx::Error>
     Ok(result.rows_affected())
 }
 
+/// Deletes usage, request log, audit and auth failure rows older than
+/// `retention_days`, returning the number of rows removed.
+pub async fn purge_old_records(pool: &DbPool, retention_days: u64) -> Result<u64, sqlx::Error> {
+    let cutoff = format!("-{} days", retention_days);
+    let mut purged = 0;
+    for table in ["usage_stats", "request_log", "audit_log", "auth_failures"] {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {} WHERE created_at < datetime('now', ?)",
+            table
+        ))
+        .bind(&cutoff)
+        .execute(pool)
+        .await?;
+        purged += result.rows_affected();
+    }
+    Ok(purged)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,7 +1259,284 @@ mod tests {
         let path = dir.path().join("test.db");
         let path_str = path.to_str().unwrap().to_string();
         std::mem::forget(dir);
-        init_database(&path_str).await.unwrap()
+        init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_init_database_applies_pragmas() {
+        let pool = setup_test_db().await;
+        let (journal_mode,): (String,) = sqlx::query_as("PRAGMA journal_mode")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let (busy_timeout,): (i64,) = sqlx::query_as("PRAGMA busy_timeout")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let (synchronous,): (i64,) = sqlx::query_as("PRAGMA synchronous")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+        assert_eq!(busy_timeout, 5000);
+        assert_eq!(synchronous, 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let config = DatabaseConfig {
+            wal: false,
+            synchronous: SynchronousMode::Full,
+            ..Default::default()
+        };
+        let pool = init_database(path.to_str().unwrap(), &config)
+            .await
+            .unwrap();
+        let (journal_mode,): (String,) = sqlx::query_as("PRAGMA journal_mode")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let (synchronous,): (i64,) = sqlx::query_as("PRAGMA synchronous")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(journal_mode, "delete");
+        assert_eq!(synchronous, 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_daily_usage() {
+        let pool = setup_test_db().await;
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_read_tokens: 10,
+            ..Default::default()
+        };
+        for _ in 0..3 {
+            record_usage(&pool, "key-a", "acc1", "model", &usage)
+                .await
+                .unwrap();
+        }
+        sqlx::query("UPDATE usage_stats SET created_at = datetime('now', '-2 days') WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE usage_stats SET created_at = datetime('now', '-40 days') WHERE id = 2")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let days = get_daily_usage(&pool, 30).await.unwrap();
+
+        assert_eq!(days.len(), 2);
+        assert!(days[0].date < days[1].date);
+        assert_eq!(days[0].total_tokens, 160);
+        assert_eq!(days[1].total_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_account_status_counts() {
+        let pool = setup_test_db().await;
+        let entry = |account_id: Option<&str>, status_code| RequestLogEntry {
+            request_id: "req".to_string(),
+            client_api_key_hash: "key".to_string(),
+            platform: "claude".to_string(),
+            account_id: account_id.map(str::to_string),
+            model: "model".to_string(),
+            path: "/v1/messages".to_string(),
+            status_code,
+            duration_ms: 10,
+            retry_count: 0,
+            streamed: false,
+        };
+        for (account_id, status_code) in [
+            (Some("acc1"), 200),
+            (Some("acc1"), 529),
+            (Some("acc1"), 529),
+            (Some("acc2"), 200),
+            (None, 503),
+        ] {
+            record_request(&pool, &entry(account_id, status_code))
+                .await
+                .unwrap();
+        }
+        sqlx::query("UPDATE request_log SET created_at = datetime('now', '-2 days') WHERE id = 4")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut counts = get_account_status_counts(&pool, 24).await.unwrap();
+        counts.sort_by_key(|c| c.status_code);
+
+        assert_eq!(
+            counts,
+            vec![
+                AccountStatusCount {
+                    account_id: "acc1".to_string(),
+                    status_code: 200,
+                    count: 1,
+                },
+                AccountStatusCount {
+                    account_id: "acc1".to_string(),
+                    status_code: 529,
+                    count: 2,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_batches_are_scoped_to_client_key() {
+        let pool = setup_test_db().await;
+        insert_message_batch(&pool, "msgbatch_1", "acc1", "key-a")
+            .await
+            .unwrap();
+        insert_message_batch(&pool, "msgbatch_2", "acc2", "key-a")
+            .await
+            .unwrap();
+        insert_message_batch(&pool, "msgbatch_3", "acc1", "key-b")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            get_message_batch_account(&pool, "msgbatch_2", "key-a")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("acc2")
+        );
+        assert_eq!(
+            get_message_batch_account(&pool, "msgbatch_3", "key-a")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            list_message_batches(&pool, "key-a", 10).await.unwrap(),
+            vec![
+                ("msgbatch_2".to_string(), "acc2".to_string()),
+                ("msgbatch_1".to_string(), "acc1".to_string()),
+            ]
+        );
+        assert_eq!(list_message_batches(&pool, "key-a", 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_keys_can_be_revoked() {
+        let pool = setup_test_db().await;
+        let key = |id: &str, hash: &str| ClientKey {
+            id: id.to_string(),
+            client_api_key_hash: hash.to_string(),
+            key_prefix: "sk-relay-abc".to_string(),
+            name: "ci-bot".to_string(),
+            profile: ClientProfile::AgentSdk,
+            max_tokens_per_day: Some(1000),
+            account_pinning: true,
+            allowed_models: vec!["claude-3-5-haiku-*".to_string()],
+            allowed_platforms: vec![Platform::Claude],
+            scopes: vec![KeyScope::Relay, KeyScope::Usage],
+            allowed_ips: vec!["203.0.113.0/24".parse().unwrap()],
+            requests_per_minute: Some(60),
+            burst: None,
+            max_concurrent_requests: Some(4),
+            tenant: Some("team-a".to_string()),
+            expires_at: DateTime::parse_from_rfc3339("2030-01-31T00:00:00Z")
+                .ok()
+                .map(|expires_at| expires_at.with_timezone(&Utc)),
+            rotated_from: None,
+            created_at: String::new(),
+            revoked_at: None,
+        };
+
+        let created = create_client_key(&pool, &key("key_1", "hash-1"))
+            .await
+            .unwrap();
+        assert!(!created.created_at.is_empty());
+        create_client_key(&pool, &key("key_2", "hash-2"))
+            .await
+            .unwrap();
+        assert!(create_client_key(&pool, &key("key_3", "hash-1"))
+            .await
+            .is_err());
+
+        assert!(revoke_client_key(&pool, "key_1").await.unwrap());
+        assert!(!revoke_client_key(&pool, "key_1").await.unwrap());
+        assert!(!revoke_client_key(&pool, "unknown").await.unwrap());
+
+        let keys = list_client_keys(&pool).await.unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].id, "key_2");
+        assert_eq!(keys[0].profile, ClientProfile::AgentSdk);
+        assert_eq!(keys[0].max_tokens_per_day, Some(1000));
+        assert!(keys[0].account_pinning);
+        assert_eq!(keys[0].allowed_models, ["claude-3-5-haiku-*"]);
+        assert_eq!(keys[0].allowed_platforms, [Platform::Claude]);
+        assert_eq!(keys[0].scopes, [KeyScope::Relay, KeyScope::Usage]);
+        assert_eq!(keys[0].allowed_ips[0].to_string(), "203.0.113.0/24");
+        assert_eq!(keys[0].requests_per_minute, Some(60));
+        assert_eq!(keys[0].burst, None);
+        assert_eq!(keys[0].max_concurrent_requests, Some(4));
+        assert_eq!(keys[0].tenant.as_deref(), Some("team-a"));
+        assert_eq!(
+            keys[0].expires_at.unwrap().to_rfc3339(),
+            "2030-01-31T00:00:00+00:00"
+        );
+        assert!(keys[0].revoked_at.is_none());
+        assert!(keys[1].revoked_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_purge_old_records() {
+        let pool = setup_test_db().await;
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 50,
+            ..Default::default()
+        };
+        record_usage(&pool, "key-a", "acc1", "model", &usage).await.unwrap();
+        record_usage(&pool, "key-a", "acc1", "model", &usage).await.unwrap();
+        sqlx::query(
+            "UPDATE usage_stats SET created_at = datetime('now', '-31 days') WHERE id = 1",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(purge_old_records(&pool, 30).await.unwrap(), 1);
+        assert_eq!(purge_old_records(&pool, 30).await.unwrap(), 0);
+        let remaining: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM usage_stats")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining.0, 1);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_used_today() {
+        let pool = setup_test_db().await;
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_creation_tokens: 10,
+            cache_read_tokens: 5,
+            reasoning_tokens: 20,
+        };
+        record_usage(&pool, "key-a", "acc1", "model", &usage).await.unwrap();
+        record_usage(&pool, "key-a", "acc2", "model", &usage).await.unwrap();
+        record_usage(&pool, "key-b", "acc1", "model", &usage).await.unwrap();
+        sqlx::query(
+            "INSERT INTO usage_stats (client_api_key_hash, account_id, model, input_tokens, created_at) VALUES ('key-a', 'acc1', 'model', 1000, datetime('now', '-1 day'))",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(tokens_used_today(&pool, "key-a").await.unwrap(), 330);
+        assert_eq!(tokens_used_today(&pool, "key-c").await.unwrap(), 0);
+        assert_eq!(total_tokens_today(&pool).await.unwrap(), 495);
+        assert_eq!(usage.total(), 165);
     }
 
     #[tokio::test]
@@ -370,7 +1653,14 @@ mod tests {
     async fn test_record_usage() {
         let pool = setup_test_db().await;
 
-        record_usage(&pool, "test_key_hash", "acc1", "claude-3-opus", 100, 50, 10, 5)
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_creation_tokens: 10,
+            cache_read_tokens: 5,
+            ..Default::default()
+        };
+        record_usage(&pool, "test_key_hash", "acc1", "claude-3-opus", &usage)
             .await
             .unwrap();
 
@@ -378,6 +1668,138 @@ mod tests {
         assert_eq!(usage.account_id, "acc1");
         assert_eq!(usage.total_input, 100);
         assert_eq!(usage.total_output, 50);
+        assert_eq!(usage.total_cache_creation, 10);
+        assert_eq!(usage.total_cache_read, 5);
         assert_eq!(usage.total_requests, 1);
     }
+
+    #[tokio::test]
+    async fn test_get_usage_by_model() {
+        let pool = setup_test_db().await;
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 50,
+            ..Default::default()
+        };
+        record_usage(&pool, "key-a", "acc1", "opus", &usage).await.unwrap();
+        record_usage(&pool, "key-b", "acc1", "opus", &usage).await.unwrap();
+        record_usage(&pool, "key-a", "acc2", "sonnet", &usage).await.unwrap();
+        sqlx::query(
+            "INSERT INTO usage_stats (client_api_key_hash, account_id, model, input_tokens, created_at) VALUES ('key-a', 'acc1', 'opus', 1000, datetime('now', '-3 days'))",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mut rows = get_usage_by_model(&pool, 1).await.unwrap();
+        rows.sort_by(|a, b| a.model.cmp(&b.model));
+        assert_eq!(
+            rows,
+            vec![
+                ModelUsage {
+                    model: "opus".to_string(),
+                    account_id: "acc1".to_string(),
+                    total_input: 200,
+                    total_output: 100,
+                    total_cache_creation: 0,
+                    total_cache_read: 0,
+                    total_requests: 2,
+                },
+                ModelUsage {
+                    model: "sonnet".to_string(),
+                    account_id: "acc2".to_string(),
+                    total_input: 100,
+                    total_output: 50,
+                    total_cache_creation: 0,
+                    total_cache_read: 0,
+                    total_requests: 1,
+                },
+            ]
+        );
+        let opus_input: i64 = get_usage_by_model(&pool, 7)
+            .await
+            .unwrap()
+            .iter()
+            .filter(|row| row.model == "opus")
+            .map(|row| row.total_input)
+            .sum();
+        assert_eq!(opus_input, 1200);
+    }
+
+    #[tokio::test]
+    async fn test_get_usage_by_key_includes_names() {
+        let pool = setup_test_db().await;
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_read_tokens: 1000,
+            ..Default::default()
+        };
+        record_usage(&pool, "hash-ci", "acc1", "opus", &usage).await.unwrap();
+        record_usage(&pool, "hash-ci", "acc2", "opus", &usage).await.unwrap();
+        record_usage(&pool, "hash-other", "acc1", "opus", &usage).await.unwrap();
+        upsert_api_key_name(&pool, "hash-ci", "old-name").await.unwrap();
+        upsert_api_key_name(&pool, "hash-ci", "ci-bot").await.unwrap();
+
+        let mut rows = get_usage_by_key(&pool, 1).await.unwrap();
+        rows.sort_by(|a, b| a.client_api_key_hash.cmp(&b.client_api_key_hash));
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].name.as_deref(), Some("ci-bot"));
+        assert_eq!(rows[0].total_input, 200);
+        assert_eq!(rows[0].total_cache_read, 2000);
+        assert_eq!(rows[0].total_requests, 2);
+        assert_eq!(rows[1].client_api_key_hash, "hash-other");
+        assert_eq!(rows[1].name, None);
+    }
+
+    #[tokio::test]
+    async fn test_record_request() {
+        let pool = setup_test_db().await;
+
+        let entry = RequestLogEntry {
+            request_id: "req-1".to_string(),
+            client_api_key_hash: "test_key_hash".to_string(),
+            platform: "claude".to_string(),
+            account_id: Some("acc1".to_string()),
+            model: "claude-sonnet-4-20250514".to_string(),
+            path: "/v1/messages".to_string(),
+            status_code: 429,
+            duration_ms: 1250,
+            retry_count: 2,
+            streamed: true,
+        };
+        record_request(&pool, &entry).await.unwrap();
+
+        let row: (String, i64, i64, i64, bool) = sqlx::query_as(
+            "SELECT account_id, status_code, duration_ms, retry_count, streamed FROM request_log",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(row, ("acc1".to_string(), 429, 1250, 2, true));
+    }
+
+    #[tokio::test]
+    async fn test_record_usage_reasoning_tokens() {
+        let pool = setup_test_db().await;
+
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 80,
+            reasoning_tokens: 64,
+            ..Default::default()
+        };
+        record_usage(&pool, "test_key_hash", "acc1", "gpt-5", &usage)
+            .await
+            .unwrap();
+
+        let (reasoning,): (i64,) =
+            sqlx::query_as("SELECT reasoning_tokens FROM usage_stats WHERE account_id = ?")
+                .bind("acc1")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(reasoning, 64);
+    }
 }
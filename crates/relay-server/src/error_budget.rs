@@ -0,0 +1,381 @@
+use axum::response::Response;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use crate::config::ErrorBudgetConfig;
+use crate::scheduler::UnifiedScheduler;
+use crate::webhook::{WebhookDispatcher, WebhookEvent};
+
+/// Response extension marking an error caused by an upstream timeout.
+#[derive(Debug, Clone, Copy)]
+pub struct UpstreamTimeout;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    ServerError,
+    Timeout,
+}
+
+impl Outcome {
+    /// Anything below 500 counts against neither budget.
+    pub fn from_response(response: &Response) -> Self {
+        if response.extensions().get::<UpstreamTimeout>().is_some() {
+            Outcome::Timeout
+        } else if response.status().is_server_error() {
+            Outcome::ServerError
+        } else {
+            Outcome::Success
+        }
+    }
+}
+
+/// Counters for one second of traffic.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    second: u64,
+    requests: u64,
+    server_errors: u64,
+    timeouts: u64,
+}
+
+#[derive(Default)]
+struct AccountBudget {
+    buckets: VecDeque<Bucket>,
+    /// The breaker ignores traffic before this second, so an account coming
+    /// back from a breaker cooldown starts with a clean slate.
+    breaker_since: u64,
+    /// Last exhaustion state per window, to alert on transitions only.
+    exhausted: Vec<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowStats {
+    pub window_seconds: u64,
+    pub requests: u64,
+    pub server_errors: u64,
+    pub timeouts: u64,
+    pub server_error_rate: f64,
+    pub timeout_rate: f64,
+    /// Fraction of the allowed error rate still unused, from 1.0 down to 0.0.
+    pub server_error_budget_remaining: f64,
+    pub timeout_budget_remaining: f64,
+    pub exhausted: bool,
+}
+
+/// Rolling per-account 5xx and timeout rates, checked against the
+/// configured thresholds after every relayed request.
+pub struct ErrorBudgetTracker {
+    config: ErrorBudgetConfig,
+    scheduler: Arc<UnifiedScheduler>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    started: Instant,
+    accounts: Mutex<HashMap<String, AccountBudget>>,
+}
+
+impl ErrorBudgetTracker {
+    pub fn new(mut config: ErrorBudgetConfig, scheduler: Arc<UnifiedScheduler>) -> Self {
+        config.windows_seconds.sort_unstable();
+        config.windows_seconds.dedup();
+
+        Self {
+            config,
+            scheduler,
+            webhooks: None,
+            started: Instant::now(),
+            accounts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sends exhaustion and circuit-open events to the configured webhooks.
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    pub fn config(&self) -> &ErrorBudgetConfig {
+        &self.config
+    }
+
+    pub fn record(&self, account_id: &str, outcome: Outcome) {
+        self.record_at(account_id, outcome, self.now());
+    }
+
+    /// Stats for every configured window, shortest first.
+    pub fn windows(&self, account_id: &str) -> Vec<WindowStats> {
+        let now = self.now();
+        let accounts = self.accounts.lock();
+        let buckets = accounts.get(account_id).map(|b| &b.buckets);
+
+        self.config
+            .windows_seconds
+            .iter()
+            .map(|&window| match buckets {
+                Some(buckets) => self.window_stats(buckets, window, now, 0),
+                None => self.window_stats(&VecDeque::new(), window, now, 0),
+            })
+            .collect()
+    }
+
+    fn now(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    fn record_at(&self, account_id: &str, outcome: Outcome, now: u64) {
+        let mut accounts = self.accounts.lock();
+        let budget = accounts.entry(account_id.to_string()).or_default();
+
+        match budget.buckets.back_mut() {
+            Some(bucket) if bucket.second == now => bucket.add(outcome),
+            _ => {
+                let mut bucket = Bucket::new(now);
+                bucket.add(outcome);
+                budget.buckets.push_back(bucket);
+            }
+        }
+
+        let longest = self.config.windows_seconds.last().copied().unwrap_or(0);
+        while let Some(bucket) = budget.buckets.front() {
+            if bucket.second + longest > now {
+                break;
+            }
+            budget.buckets.pop_front();
+        }
+
+        budget
+            .exhausted
+            .resize(self.config.windows_seconds.len(), false);
+        for (i, &window) in self.config.windows_seconds.iter().enumerate() {
+            let stats = self.window_stats(&budget.buckets, window, now, 0);
+            if stats.exhausted == budget.exhausted[i] {
+                continue;
+            }
+            budget.exhausted[i] = stats.exhausted;
+            if stats.exhausted {
+                warn!(
+                    account_id = account_id,
+                    window_seconds = window,
+                    requests = stats.requests,
+                    server_error_rate = stats.server_error_rate,
+                    timeout_rate = stats.timeout_rate,
+                    "Account error budget exhausted"
+                );
+                self.emit(WebhookEvent::ErrorBudgetExhausted {
+                    account_id: account_id.to_string(),
+                    window_seconds: window,
+                    requests: stats.requests,
+                    server_error_rate: stats.server_error_rate,
+                    timeout_rate: stats.timeout_rate,
+                });
+            } else {
+                info!(
+                    account_id = account_id,
+                    window_seconds = window,
+                    "Account error budget recovered"
+                );
+            }
+        }
+
+        if !self.config.circuit_breaker {
+            return;
+        }
+        let Some(&window) = self.config.windows_seconds.first() else {
+            return;
+        };
+        let stats = self.window_stats(&budget.buckets, window, now, budget.breaker_since);
+        if stats.exhausted && !self.scheduler.is_in_cooldown(account_id) {
+            budget.breaker_since = now + 1;
+            self.scheduler
+                .mark_account_circuit_open(account_id, self.config.breaker_cooldown_seconds);
+            self.emit(WebhookEvent::CircuitOpened {
+                account_id: account_id.to_string(),
+                cooldown_seconds: self.config.breaker_cooldown_seconds,
+            });
+        }
+    }
+
+    fn emit(&self, event: WebhookEvent) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.emit(event);
+        }
+    }
+
+    fn window_stats(
+        &self,
+        buckets: &VecDeque<Bucket>,
+        window: u64,
+        now: u64,
+        since: u64,
+    ) -> WindowStats {
+        let mut requests = 0;
+        let mut server_errors = 0;
+        let mut timeouts = 0;
+        for bucket in buckets
+            .iter()
+            .filter(|b| b.second + window > now && b.second >= since)
+        {
+            requests += bucket.requests;
+            server_errors += bucket.server_errors;
+            timeouts += bucket.timeouts;
+        }
+
+        let rate = |count: u64| {
+            if requests == 0 {
+                0.0
+            } else {
+                count as f64 / requests as f64
+            }
+        };
+        let server_error_rate = rate(server_errors);
+        let timeout_rate = rate(timeouts);
+        let server_error_budget_remaining =
+            (1.0 - server_error_rate / self.config.max_server_error_rate).max(0.0);
+        let timeout_budget_remaining = (1.0 - timeout_rate / self.config.max_timeout_rate).max(0.0);
+
+        WindowStats {
+            window_seconds: window,
+            requests,
+            server_errors,
+            timeouts,
+            server_error_rate,
+            timeout_rate,
+            server_error_budget_remaining,
+            timeout_budget_remaining,
+            exhausted: requests >= self.config.min_requests
+                && (server_error_budget_remaining == 0.0 || timeout_budget_remaining == 0.0),
+        }
+    }
+}
+
+impl Bucket {
+    fn new(second: u64) -> Self {
+        Self {
+            second,
+            requests: 0,
+            server_errors: 0,
+            timeouts: 0,
+        }
+    }
+
+    fn add(&mut self, outcome: Outcome) {
+        self.requests += 1;
+        match outcome {
+            Outcome::Success => {}
+            Outcome::ServerError => self.server_errors += 1,
+            Outcome::Timeout => self.timeouts += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use relay_core::{FixedCooldownPolicy, MemorySessionStore, PriorityLruPolicy, TtlStickyPolicy};
+    use std::time::Duration;
+
+    fn tracker(circuit_breaker: bool) -> ErrorBudgetTracker {
+        let scheduler = UnifiedScheduler::new(
+            Vec::new(),
+            Arc::new(TtlStickyPolicy::new(
+                MemorySessionStore::new(),
+                Duration::from_secs(3600),
+                Duration::from_secs(300),
+            )),
+            Arc::new(PriorityLruPolicy::new()),
+            Arc::new(FixedCooldownPolicy::new(Duration::from_secs(3600))),
+        );
+        ErrorBudgetTracker::new(
+            ErrorBudgetConfig {
+                windows_seconds: vec![3600, 60],
+                min_requests: 4,
+                max_server_error_rate: 0.5,
+                max_timeout_rate: 0.25,
+                circuit_breaker,
+                breaker_cooldown_seconds: 120,
+            },
+            Arc::new(scheduler),
+        )
+    }
+
+    fn stats(tracker: &ErrorBudgetTracker, account_id: &str, now: u64) -> Vec<WindowStats> {
+        let accounts = tracker.accounts.lock();
+        let buckets = &accounts[account_id].buckets;
+        tracker
+            .config
+            .windows_seconds
+            .iter()
+            .map(|&window| tracker.window_stats(buckets, window, now, 0))
+            .collect()
+    }
+
+    #[test]
+    fn test_rates_per_window() {
+        let tracker = tracker(false);
+        tracker.record_at("acc1", Outcome::ServerError, 0);
+        tracker.record_at("acc1", Outcome::Success, 100);
+        tracker.record_at("acc1", Outcome::Timeout, 110);
+        tracker.record_at("acc1", Outcome::Success, 110);
+
+        let windows = stats(&tracker, "acc1", 120);
+        assert_eq!(windows[0].window_seconds, 60);
+        assert_eq!(windows[0].requests, 3);
+        assert_eq!(windows[0].server_errors, 0);
+        assert_eq!(windows[0].timeouts, 1);
+        assert!(!windows[0].exhausted);
+
+        assert_eq!(windows[1].window_seconds, 3600);
+        assert_eq!(windows[1].requests, 4);
+        assert_eq!(windows[1].server_error_rate, 0.25);
+        assert_eq!(windows[1].server_error_budget_remaining, 0.5);
+        assert_eq!(windows[1].timeout_budget_remaining, 0.0);
+        assert!(windows[1].exhausted);
+    }
+
+    #[test]
+    fn test_old_buckets_are_pruned() {
+        let tracker = tracker(false);
+        tracker.record_at("acc1", Outcome::ServerError, 0);
+        tracker.record_at("acc1", Outcome::Success, 3600);
+
+        assert_eq!(tracker.accounts.lock()["acc1"].buckets.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_account_has_empty_windows() {
+        let tracker = tracker(false);
+        let windows = tracker.windows("missing");
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].requests, 0);
+        assert_eq!(windows[0].server_error_budget_remaining, 1.0);
+        assert!(!windows[0].exhausted);
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_once_budget_is_exhausted() {
+        let tracker = tracker(true);
+        for _ in 0..3 {
+            tracker.record_at("acc1", Outcome::ServerError, 10);
+        }
+        // Below min_requests
+        assert!(tracker.scheduler.cooldown("acc1").is_none());
+
+        tracker.record_at("acc1", Outcome::Success, 10);
+        let cooldown = tracker.scheduler.cooldown("acc1").unwrap();
+        assert_eq!(cooldown.reason, "circuit_open");
+        assert!(cooldown.remaining <= Duration::from_secs(120));
+        assert_eq!(tracker.accounts.lock()["acc1"].breaker_since, 11);
+    }
+
+    #[test]
+    fn test_circuit_breaker_disabled() {
+        let tracker = tracker(false);
+        for _ in 0..10 {
+            tracker.record_at("acc1", Outcome::Timeout, 10);
+        }
+        assert!(tracker.scheduler.cooldown("acc1").is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Why a relayed request failed, as seen by the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// 401
+    Unauthorized,
+    /// 403
+    Forbidden,
+    /// 429
+    RateLimited,
+    /// 529
+    Overloaded,
+    /// The upstream did not answer in time.
+    Timeout,
+    /// Any other 5xx.
+    ServerError,
+    /// Any other 4xx.
+    ClientError,
+}
+
+impl ErrorKind {
+    /// `None` for successful requests.
+    pub fn classify(status: u16, timed_out: bool) -> Option<Self> {
+        if timed_out {
+            return Some(ErrorKind::Timeout);
+        }
+        match status {
+            401 => Some(ErrorKind::Unauthorized),
+            403 => Some(ErrorKind::Forbidden),
+            429 => Some(ErrorKind::RateLimited),
+            529 => Some(ErrorKind::Overloaded),
+            500.. => Some(ErrorKind::ServerError),
+            400.. => Some(ErrorKind::ClientError),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ErrorCounts {
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub by_kind: BTreeMap<ErrorKind, u64>,
+}
+
+impl ErrorCounts {
+    pub fn add(&mut self, status: u16, timed_out: bool, count: u64) {
+        self.requests += count;
+        if let Some(kind) = ErrorKind::classify(status, timed_out) {
+            self.errors += count;
+            *self.by_kind.entry(kind).or_default() += count;
+        }
+        self.error_rate = if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        };
+    }
+}
+
+/// Success and error counts per account since the server started.
+#[derive(Default)]
+pub struct ErrorStats {
+    accounts: Mutex<HashMap<String, ErrorCounts>>,
+}
+
+impl ErrorStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, account_id: &str, status: u16, timed_out: bool) {
+        self.accounts
+            .lock()
+            .entry(account_id.to_string())
+            .or_default()
+            .add(status, timed_out, 1);
+    }
+
+    pub fn get(&self, account_id: &str) -> ErrorCounts {
+        self.accounts
+            .lock()
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(ErrorKind::classify(200, false), None);
+        assert_eq!(ErrorKind::classify(304, false), None);
+        assert_eq!(
+            ErrorKind::classify(400, false),
+            Some(ErrorKind::ClientError)
+        );
+        assert_eq!(
+            ErrorKind::classify(401, false),
+            Some(ErrorKind::Unauthorized)
+        );
+        assert_eq!(ErrorKind::classify(403, false), Some(ErrorKind::Forbidden));
+        assert_eq!(
+            ErrorKind::classify(429, false),
+            Some(ErrorKind::RateLimited)
+        );
+        assert_eq!(
+            ErrorKind::classify(502, false),
+            Some(ErrorKind::ServerError)
+        );
+        assert_eq!(ErrorKind::classify(529, false), Some(ErrorKind::Overloaded));
+        assert_eq!(ErrorKind::classify(500, true), Some(ErrorKind::Timeout));
+    }
+
+    #[test]
+    fn test_counts_per_account() {
+        let stats = ErrorStats::new();
+        stats.record("claude-2", 200, false);
+        stats.record("claude-2", 529, false);
+        stats.record("claude-2", 529, false);
+        stats.record("claude-2", 200, false);
+        stats.record("claude-2", 200, false);
+        stats.record("claude-1", 200, false);
+
+        let counts = stats.get("claude-2");
+        assert_eq!(counts.requests, 5);
+        assert_eq!(counts.errors, 2);
+        assert_eq!(counts.error_rate, 0.4);
+        assert_eq!(counts.by_kind[&ErrorKind::Overloaded], 2);
+        assert_eq!(stats.get("claude-1").errors, 0);
+        assert_eq!(stats.get("missing"), ErrorCounts::default());
+
+        let value = serde_json::to_value(&counts).unwrap();
+        assert_eq!(value["by_kind"]["overloaded"], 2);
+    }
+}
@@ -0,0 +1,277 @@
+use axum::http::HeaderMap;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::config::IpFilterConfig;
+
+/// A CIDR block such as `10.0.0.0/8`, or a single address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpRange(IpNet);
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.contains(&ip.to_canonical())
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        s.parse::<IpNet>()
+            .or_else(|_| s.parse::<IpAddr>().map(IpNet::from))
+            .map(|net| Self(net.trunc()))
+            .map_err(|_| format!("invalid IP address or CIDR block '{}'", s))
+    }
+}
+
+impl TryFrom<String> for IpRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<IpRange> for String {
+    fn from(range: IpRange) -> Self {
+        range.to_string()
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            IpNet::V4(net) if net.prefix_len() == 32 => write!(f, "{}", net.addr()),
+            IpNet::V6(net) if net.prefix_len() == 128 => write!(f, "{}", net.addr()),
+            net => write!(f, "{}", net),
+        }
+    }
+}
+
+/// True when no range is given, or `ip` falls in one of them.
+pub fn any_contains(ranges: &[IpRange], ip: Option<IpAddr>) -> bool {
+    ranges.is_empty() || ip.is_some_and(|ip| ranges.iter().any(|range| range.contains(ip)))
+}
+
+/// Server-wide client IP rules, checked before authentication.
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    allow: Vec<IpRange>,
+    deny: Vec<IpRange>,
+    trusted_proxies: Vec<IpRange>,
+}
+
+impl IpFilter {
+    pub fn new(config: &IpFilterConfig) -> Self {
+        Self {
+            allow: config.allow.clone(),
+            deny: config.deny.clone(),
+            trusted_proxies: config.trusted_proxies.clone(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty()
+    }
+
+    /// Denied ranges win over allowed ones; an empty allowlist allows every
+    /// address not denied. An unknown address only passes without an
+    /// allowlist.
+    pub fn permits(&self, ip: Option<IpAddr>) -> bool {
+        if ip.is_some_and(|ip| self.deny.iter().any(|range| range.contains(ip))) {
+            return false;
+        }
+        any_contains(&self.allow, ip)
+    }
+
+    /// The address of the client behind `peer`. Forwarding headers are only
+    /// believed when `peer` is a trusted proxy; `X-Forwarded-For`, or else
+    /// the `for=` entries of `Forwarded`, are read from the right, skipping
+    /// further trusted proxies, so a client cannot spoof its address by
+    /// prepending entries.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let peer = peer.to_canonical();
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+
+        let header_values = |name| {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+        };
+        let mut hops: Vec<&str> = header_values("x-forwarded-for").map(str::trim).collect();
+        if hops.is_empty() {
+            hops = header_values("forwarded")
+                .filter_map(forwarded_for)
+                .collect();
+        }
+        if !hops.is_empty() {
+            let mut client = peer;
+            for hop in hops.into_iter().rev() {
+                let Some(ip) = parse_hop(hop) else {
+                    break;
+                };
+                client = ip.to_canonical();
+                if !self.is_trusted(client) {
+                    break;
+                }
+            }
+            return client;
+        }
+
+        headers
+            .get("x-real-ip")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<IpAddr>().ok())
+            .map(|ip| ip.to_canonical())
+            .unwrap_or(peer)
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|range| range.contains(ip))
+    }
+}
+
+/// The `for=` value of one `Forwarded` element, such as
+/// `for=192.0.2.60;proto=https`.
+fn forwarded_for(element: &str) -> Option<&str> {
+    element.split(';').find_map(|pair| {
+        let (name, value) = pair.trim().split_once('=')?;
+        name.eq_ignore_ascii_case("for")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// An address as proxies write it: bare, or with a port, IPv6 in brackets
+/// (`[2001:db8::1]:4711`). `None` for obfuscated ones such as `unknown`.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    if let Ok(ip) = hop.parse() {
+        return Some(ip);
+    }
+    if let Some(rest) = hop.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    hop.parse::<std::net::SocketAddrV4>()
+        .ok()
+        .map(|addr| IpAddr::V4(*addr.ip()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(list: &[&str]) -> Vec<IpRange> {
+        list.iter().map(|range| range.parse().unwrap()).collect()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ip_range_parsing() {
+        let range: IpRange = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(range.to_string(), "10.0.0.0/8");
+        assert!(range.contains(ip("10.200.0.1")));
+        assert!(range.contains(ip("::ffff:10.0.0.1")));
+        assert!(!range.contains(ip("11.0.0.1")));
+
+        let single: IpRange = "203.0.113.7".parse().unwrap();
+        assert_eq!(single.to_string(), "203.0.113.7");
+        assert!(single.contains(ip("203.0.113.7")));
+        assert!("2001:db8::/32".parse::<IpRange>().is_ok());
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("localhost".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn test_allow_and_deny() {
+        let filter = IpFilter::new(&IpFilterConfig {
+            allow: ranges(&["10.0.0.0/8"]),
+            deny: ranges(&["10.0.0.13"]),
+            trusted_proxies: Vec::new(),
+        });
+        assert!(filter.permits(Some(ip("10.0.0.12"))));
+        assert!(!filter.permits(Some(ip("10.0.0.13"))));
+        assert!(!filter.permits(Some(ip("192.0.2.1"))));
+        assert!(!filter.permits(None));
+
+        let deny_only = IpFilter::new(&IpFilterConfig {
+            deny: ranges(&["192.0.2.0/24"]),
+            ..Default::default()
+        });
+        assert!(deny_only.permits(Some(ip("10.0.0.1"))));
+        assert!(!deny_only.permits(Some(ip("192.0.2.1"))));
+        assert!(deny_only.permits(None));
+        assert!(!IpFilter::default().is_active());
+    }
+
+    #[test]
+    fn test_client_ip_behind_trusted_proxy() {
+        let filter = IpFilter::new(&IpFilterConfig {
+            trusted_proxies: ranges(&["127.0.0.1", "10.0.0.0/8"]),
+            ..Default::default()
+        });
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.append(*name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        // Untrusted peers cannot claim another address
+        let spoofed = headers(&[("x-forwarded-for", "198.51.100.1")]);
+        assert_eq!(
+            filter.client_ip(ip("203.0.113.9"), &spoofed),
+            ip("203.0.113.9")
+        );
+
+        // The rightmost untrusted hop is the client, whatever it prepended
+        let chain = headers(&[("x-forwarded-for", "198.51.100.1, 203.0.113.9, 10.0.0.2")]);
+        assert_eq!(filter.client_ip(ip("127.0.0.1"), &chain), ip("203.0.113.9"));
+        let split = headers(&[
+            ("x-forwarded-for", "203.0.113.9"),
+            ("x-forwarded-for", "10.0.0.2"),
+        ]);
+        assert_eq!(filter.client_ip(ip("127.0.0.1"), &split), ip("203.0.113.9"));
+
+        let forwarded = headers(&[(
+            "forwarded",
+            "for=198.51.100.1, for=\"203.0.113.9:4711\";proto=https, for=10.0.0.2",
+        )]);
+        assert_eq!(
+            filter.client_ip(ip("127.0.0.1"), &forwarded),
+            ip("203.0.113.9")
+        );
+        let forwarded_v6 = headers(&[("forwarded", "For=\"[2001:db8:cafe::17]:4711\"")]);
+        assert_eq!(
+            filter.client_ip(ip("127.0.0.1"), &forwarded_v6),
+            ip("2001:db8:cafe::17")
+        );
+        let obfuscated = headers(&[("forwarded", "for=unknown")]);
+        assert_eq!(
+            filter.client_ip(ip("10.0.0.2"), &obfuscated),
+            ip("10.0.0.2")
+        );
+
+        let real_ip = headers(&[("x-real-ip", "203.0.113.9")]);
+        assert_eq!(
+            filter.client_ip(ip("::ffff:127.0.0.1"), &real_ip),
+            ip("203.0.113.9")
+        );
+        assert_eq!(
+            filter.client_ip(ip("127.0.0.1"), &HeaderMap::new()),
+            ip("127.0.0.1")
+        );
+    }
+}
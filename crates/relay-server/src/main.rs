@@ -1,28 +1,72 @@
+mod alerts;
+mod audit;
+mod auth_throttle;
 mod config;
+mod context_limit;
+mod cors;
 mod db;
+mod error_budget;
+mod error_stats;
+mod ip_filter;
+mod jwt;
 mod middleware;
+mod model_catalog;
+mod model_map;
+mod oidc;
+mod pricing;
+mod rate_limit;
+mod retry;
 mod routes;
 mod scheduler;
+mod server_tools;
+mod slow_requests;
+mod tls;
+mod usage_writer;
+mod webhook;
 
 use axum::{
     middleware as axum_middleware,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use clap::Parser;
-use relay_claude::{ClaudeApiAccount, ClaudeOAuthAccount, ClaudeRelay};
-use relay_core::AccountProvider;
+use futures::FutureExt;
+use relay_bedrock::{BedrockAccount, BedrockRelay};
+use relay_claude::{
+    ClaudeApiAccount, ClaudeOAuthAccount, ClaudeRelay, ClaudeSessionAccount, OpenRouterAccount,
+};
+use relay_core::{
+    AccountProvider, AwsCredentials, AzureCredentials, BanditPolicy, Capture, PriorityLruPolicy,
+    SelectionPolicy,
+};
 use relay_gemini::{GeminiAccount, GeminiRelay};
+use relay_openai::{ChatCompletionsRelay, DeepSeekAccount, OpenAICompatibleAccount};
+use relay_vertex::{ServiceAccountKey, VertexAccount, VertexClaudeRelay, VertexGeminiRelay};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use config::{AccountConfig, Config};
-use middleware::ApiKeyValidator;
+use config::{
+    AccountConfig, Config, MessagesBackend, OpenAIBackend, ResponsesBackend, SchedulingStrategy,
+    VertexPlatform,
+};
+use middleware::{ApiKeyPolicy, ApiKeyValidator, ClientApiKeyHash, ErrorFormat, KeyScope};
 use relay_core::Platform;
-use routes::{ClaudeRouteState, GeminiRouteState, OpenAIRouteState};
-use scheduler::UnifiedScheduler;
+use routes::{
+    AdminRouteState, ClaudeRouteState, GeminiRouteState, OpenAIRouteState, UsageRouteState,
+};
+
+/// How often rows older than `database.usage_retention_days` are purged.
+const RETENTION_INTERVAL_SECS: u64 = 3600;
+
+/// How long requests may take to finish once their streams are cut off at
+/// the end of the shutdown grace period.
+const CUTOFF_DRAIN: std::time::Duration = std::time::Duration::from_secs(5);
 
 #[derive(Parser)]
 #[command(name = "claude-relay")]
@@ -30,6 +74,10 @@ use scheduler::UnifiedScheduler;
 struct Args {
     #[arg(short, long, default_value = "config.toml")]
     config: String,
+    /// Write every upstream request and response, streams included, to a
+    /// file in this directory, with credentials redacted
+    #[arg(long)]
+    capture_dir: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -49,7 +97,7 @@ async fn main() {
     info!(config_path = %args.config, "Starting Claude Relay Service");
     info!(api_keys_count = config.api_keys.len(), api_keys = ?config.api_keys, "Loaded API keys config");
 
-    let pool = match db::init_database(&config.server.database_path).await {
+    let pool = match db::init_database(&config.server.database_path, &config.database).await {
         Ok(p) => p,
         Err(e) => {
             error!(error = %e, "Failed to initialize database");
@@ -71,88 +119,560 @@ async fn main() {
         .iter()
         .filter(|a| a.platform() == Platform::Codex)
         .count();
+    let openai_compatible_count = accounts
+        .iter()
+        .filter(|a| a.platform() == Platform::OpenAI)
+        .count();
 
     info!(
         claude_accounts = claude_count,
         gemini_accounts = gemini_count,
         codex_accounts = codex_count,
+        openai_compatible_accounts = openai_compatible_count,
         total_accounts = accounts.len(),
         "Loaded accounts"
     );
 
-    if claude_count == 0 {
+    let messages_via_gemini = match config.messages.backend {
+        MessagesBackend::Auto => claude_count == 0,
+        MessagesBackend::Claude => false,
+        MessagesBackend::Gemini => true,
+    };
+    if messages_via_gemini {
+        info!(
+            model = %config.messages.gemini_model,
+            "Claude messages endpoints will be served from Gemini accounts"
+        );
+    } else if claude_count == 0 {
         info!("No Claude accounts configured - Claude/OpenAI endpoints will return errors");
     }
-    if gemini_count == 0 {
+    if openai_compatible_count > 0 {
+        info!(
+            messages = config.messages.openai_fallback,
+            "OpenAI-compatible accounts will serve chat/completions requests when no Claude account is available"
+        );
+    }
+    if config.openai.backend == OpenAIBackend::Gemini {
+        info!(
+            model = %config.openai.gemini_model,
+            "OpenAI chat/completions endpoints will be served from Gemini accounts"
+        );
+    }
+    if config.openai.backend == OpenAIBackend::Codex {
+        info!("OpenAI chat/completions requests will be forwarded unchanged to Codex accounts");
+    } else if !config.openai.codex_prefixes.is_empty() {
+        info!(
+            prefixes = ?config.openai.codex_prefixes,
+            "OpenAI chat/completions requests for matching models will be forwarded unchanged to Codex accounts"
+        );
+    }
+    if gemini_count == 0 && config.gemini.claude_fallback {
+        info!(
+            model = %config.gemini.claude_model,
+            "No Gemini accounts configured - Gemini endpoints will be served from Claude accounts"
+        );
+    } else if gemini_count == 0 {
         info!("No Gemini accounts configured - Gemini endpoints will return errors");
     }
-    if codex_count == 0 {
+    let responses_via_claude = match config.responses.backend {
+        ResponsesBackend::Auto => codex_count == 0,
+        ResponsesBackend::Codex => false,
+        ResponsesBackend::Claude => true,
+    };
+    if responses_via_claude {
+        info!(
+            model = %config.responses.claude_model,
+            "OpenAI Responses endpoints will be served from Claude accounts"
+        );
+    } else if codex_count == 0 {
         info!("No Codex accounts configured - OpenAI Responses endpoints will return errors");
     }
 
-    let scheduler = Arc::new(UnifiedScheduler::new(
-        accounts,
-        config.session.sticky_ttl_seconds,
-        config.session.renewal_threshold_seconds,
-        config.session.unavailable_cooldown_seconds,
+    let bandit = match config.scheduler.strategy {
+        SchedulingStrategy::Priority => None,
+        SchedulingStrategy::Bandit => {
+            let bandit = &config.scheduler.bandit;
+            info!(
+                reward = ?bandit.reward,
+                exploration_rate = bandit.exploration_rate,
+                "Bandit scheduling enabled (experimental)"
+            );
+            Some(Arc::new(BanditPolicy::new(
+                bandit.reward,
+                bandit.exploration_rate,
+            )))
+        }
+    };
+    let selection: Arc<dyn SelectionPolicy> = match &bandit {
+        Some(bandit) => bandit.clone(),
+        None => Arc::new(PriorityLruPolicy::new()),
+    };
+
+    if !config.webhooks.is_empty() {
+        info!(count = config.webhooks.len(), "Webhooks enabled");
+    }
+    let webhooks = Arc::new(webhook::WebhookDispatcher::new(
+        config.webhooks.clone(),
         pool.clone(),
     ));
 
+    if !config.alerts.channels.is_empty() {
+        info!(
+            count = config.alerts.channels.len(),
+            "Alert channels enabled"
+        );
+    }
+    let tokens_today = db::total_tokens_today(&pool).await.unwrap_or_else(|e| {
+        error!(error = %e, "Failed to read today's token usage");
+        0
+    });
+    let alerts = Arc::new(alerts::AlertManager::new(
+        config.alerts.clone(),
+        webhooks.clone(),
+        tokens_today,
+    ));
+
+    let scheduler = Arc::new(
+        scheduler::build_scheduler(
+            accounts.clone(),
+            config.session.sticky_ttl_seconds,
+            config.session.renewal_threshold_seconds,
+            config.cooldown_policy(),
+            pool.clone(),
+            selection,
+        )
+        .with_cooldown_listener(alerts.clone())
+        .with_quota_reserve_ratio(config.scheduler.quota_reserve_ratio)
+        .with_server_error_threshold(config.upstream_errors.server_error_threshold)
+        .with_tenants(config.account_tenants()),
+    );
+    for account in config.accounts.iter().filter(|a| a.draining()) {
+        scheduler.set_draining(account.id(), true);
+    }
+
     let scheduler_cleanup = scheduler.clone();
     let cleanup_pool = pool.clone();
+    let usage_retention_days = config.database.usage_retention_days;
+    if let Some(days) = usage_retention_days {
+        info!(days = days, "Usage retention enabled");
+    }
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        let mut retention_interval =
+            tokio::time::interval(std::time::Duration::from_secs(RETENTION_INTERVAL_SECS));
         loop {
-            interval.tick().await;
-            scheduler_cleanup.cleanup_expired_cooldowns();
-            if let Err(e) = db::cleanup_expired_sessions(&cleanup_pool).await {
-                error!(error = %e, "Failed to cleanup expired sessions");
+            tokio::select! {
+                _ = interval.tick() => {
+                    scheduler_cleanup.cleanup_expired_cooldowns();
+                    if let Err(e) = db::cleanup_expired_sessions(&cleanup_pool).await {
+                        error!(error = %e, "Failed to cleanup expired sessions");
+                    }
+                }
+                _ = retention_interval.tick(), if usage_retention_days.is_some() => {
+                    let days = usage_retention_days.unwrap_or_default();
+                    match db::purge_old_records(&cleanup_pool, days).await {
+                        Ok(0) => {}
+                        Ok(purged) => info!(purged = purged, days = days, "Purged old usage records"),
+                        Err(e) => error!(error = %e, "Failed to purge old usage records"),
+                    }
+                }
             }
         }
     });
 
-    let api_key_validator = Arc::new(ApiKeyValidator::new(config.api_keys.clone()));
+    let jwt_verifier = match jwt::JwtVerifier::new(&config.jwt) {
+        Ok(verifier) => verifier,
+        Err(e) => {
+            error!(error = %e, "Failed to load JWT keys");
+            std::process::exit(1);
+        }
+    };
+    if jwt_verifier.is_some() {
+        info!(
+            keys = config.jwt.keys.len(),
+            issuer = ?config.jwt.issuer,
+            audience = ?config.jwt.audience,
+            "JWT authentication enabled"
+        );
+    }
+
+    let api_key_validator = Arc::new(
+        ApiKeyValidator::new(
+            config
+                .api_keys
+                .iter()
+                .map(|k| {
+                    (
+                        k.key().to_string(),
+                        ApiKeyPolicy {
+                            profile: k.profile(),
+                            max_tokens_per_day: k.max_tokens_per_day(),
+                            account_pinning: k.account_pinning(),
+                            allowed_models: k.allowed_models().to_vec(),
+                            allowed_platforms: k.allowed_platforms().to_vec(),
+                            scopes: k.scopes().to_vec(),
+                            allowed_ips: k.allowed_ips().to_vec(),
+                            rate_limit: k.rate_limit(),
+                            max_concurrent_requests: k.max_concurrent_requests(),
+                            tenant: k.tenant().map(str::to_string),
+                            expires_at: k.expires_at(),
+                            usage_hash: None,
+                            pinned_account: None,
+                        },
+                    )
+                })
+                .collect(),
+        )
+        .with_public_paths(config.server.public_paths.clone())
+        .with_anonymous_routes(config.api.anonymous_routes.clone())
+        .with_jwt(jwt_verifier)
+        .with_failure_log(pool.clone())
+        .with_throttle(auth_throttle::AuthThrottle::new(&config.auth_throttle)),
+    );
+    if !config.server.public_paths.is_empty() {
+        info!(paths = ?config.server.public_paths, "Paths served without an API key");
+    }
+    if let Some(routes) = &config.api.anonymous_routes {
+        info!(routes = ?routes, "Routes served without an API key; all others require one");
+    }
+    if let Some(max_failures) = config.auth_throttle.max_failures {
+        info!(
+            max_failures = max_failures,
+            window_seconds = config.auth_throttle.window_seconds,
+            ban_seconds = config.auth_throttle.ban_seconds,
+            "Authentication failure throttling enabled"
+        );
+    }
 
-    if api_key_validator.is_empty() {
+    let issued_keys = match api_key_validator.load_issued_keys(&pool).await {
+        Ok(count) => count,
+        Err(e) => {
+            error!(error = %e, "Failed to load issued API keys");
+            0
+        }
+    };
+    if api_key_validator.is_open() {
         info!("No API keys configured - all requests will be anonymous");
     } else {
-        info!(count = config.api_keys.len(), "API key authentication enabled");
+        info!(
+            count = config.api_keys.len(),
+            issued = issued_keys,
+            "API key authentication enabled"
+        );
+    }
+
+    let key_names: HashMap<String, String> = config
+        .api_keys
+        .iter()
+        .filter_map(|k| {
+            let name = k.name()?.to_string();
+            Some((ClientApiKeyHash::from_configured_key(k.key()).0, name))
+        })
+        .collect();
+    for (hash, name) in &key_names {
+        if let Err(e) = db::upsert_api_key_name(&pool, hash, name).await {
+            error!(name = %name, error = %e, "Failed to store API key name");
+        }
     }
 
-    let claude_relay = Arc::new(ClaudeRelay::new());
-    let gemini_relay = Arc::new(GeminiRelay::new());
-    let codex_relay = Arc::new(relay_codex::CodexRelay::new());
+    let context_limits = Arc::new(context_limit::ContextLimits::new(&config.context_limits));
+    let model_map = Arc::new(model_map::ModelMap::new(&config.model_map));
 
+    let pricing = Arc::new(pricing::Pricing::new(&config.pricing));
+    let mut usage = routes::UsageRecorder::new(pool.clone(), pricing.clone(), webhooks.clone())
+        .with_scheduler(scheduler.clone())
+        .with_alerts(alerts)
+        .with_key_names(key_names);
+    let usage_writer = (config.database.usage_flush_interval_ms > 0).then(|| {
+        Arc::new(usage_writer::UsageWriter::spawn(
+            pool.clone(),
+            std::time::Duration::from_millis(config.database.usage_flush_interval_ms),
+            config.database.usage_batch_size,
+        ))
+    });
+    if let Some(writer) = &usage_writer {
+        usage = usage.with_writer(writer.clone());
+    }
+    let usage = Arc::new(usage);
+
+    let timeouts = Arc::new(config.upstream_timeouts());
+    info!(
+        request_seconds = config.timeouts.request_seconds,
+        "Upstream request timeout configured"
+    );
+    let capture = match &args.capture_dir {
+        Some(dir) => match Capture::new(dir) {
+            Ok(capture) => {
+                warn!(
+                    dir = %dir.display(),
+                    "Capturing upstream exchanges; message content is written as-is"
+                );
+                Some(Arc::new(capture))
+            }
+            Err(e) => {
+                error!(dir = %dir.display(), error = %e, "Failed to create capture directory");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let client_options = config.http_client.options();
+    let bedrock_relay = BedrockRelay::new()
+        .with_model_ids(config.bedrock.model_ids.clone())
+        .with_timeouts(timeouts.clone())
+        .with_capture(capture.clone())
+        .with_client_options(client_options.clone());
+    let vertex_relay = VertexClaudeRelay::new()
+        .with_model_ids(config.vertex.model_ids.clone())
+        .with_timeouts(timeouts.clone())
+        .with_capture(capture.clone())
+        .with_client_options(client_options.clone());
+    let claude_relay = Arc::new(
+        ClaudeRelay::new()
+            .with_rate_limit_observer(scheduler.clone())
+            .with_sigv4_relay(Arc::new(bedrock_relay))
+            .with_vertex_relay(Arc::new(vertex_relay))
+            .with_timeouts(timeouts.clone())
+            .with_capture(capture.clone())
+            .with_client_options(client_options.clone())
+            .with_log_redaction(config.server.redact_log_content),
+    );
+    let vertex_gemini_relay = VertexGeminiRelay::new()
+        .with_timeouts(timeouts.clone())
+        .with_capture(capture.clone())
+        .with_client_options(client_options.clone());
+    let gemini_relay = Arc::new(
+        GeminiRelay::new()
+            .with_vertex_relay(Arc::new(vertex_gemini_relay))
+            .with_timeouts(timeouts.clone())
+            .with_capture(capture.clone())
+            .with_client_options(client_options.clone()),
+    );
+    let codex_relay = Arc::new(
+        relay_codex::CodexRelay::new()
+            .with_timeouts(timeouts.clone())
+            .with_capture(capture.clone())
+            .with_client_options(client_options.clone()),
+    );
+    let openai_compatible_relay = (openai_compatible_count > 0).then(|| {
+        Arc::new(
+            ChatCompletionsRelay::new()
+                .with_timeouts(timeouts.clone())
+                .with_capture(capture.clone())
+                .with_client_options(client_options.clone()),
+        )
+    });
+
+    let mut model_catalog = model_catalog::ModelCatalog::new(&config);
+    if config.models.upstream {
+        let api_account = config
+            .accounts
+            .iter()
+            .find(|a| matches!(a, AccountConfig::ClaudeApi { enabled: true, .. }));
+        match api_account.and_then(|a| accounts.iter().find(|acc| acc.id() == a.id())) {
+            Some(account) => {
+                info!(
+                    account_id = account.id(),
+                    "Listing Claude models from upstream"
+                );
+                model_catalog = model_catalog.with_upstream(claude_relay.clone(), account.clone());
+            }
+            None => warn!("models.upstream is set but no Claude API-key account is enabled"),
+        }
+    }
+    let model_catalog = Arc::new(model_catalog);
+
+    let (cut_streams, cutoff) = routes::StreamCutoff::new();
+    let streaming =
+        routes::StreamSettings::new(&config.server, &config.timeouts).with_cutoff(cutoff);
+    let retry = retry::RetryBackoff::new(&config.retry);
+    let retryable = retry::RetryableStatuses::new(&config.upstream_errors);
     let claude_state = Arc::new(ClaudeRouteState {
         scheduler: scheduler.clone(),
         relay: claude_relay.clone(),
         db_pool: pool.clone(),
+        context_limits: context_limits.clone(),
+        model_map: model_map.clone(),
+        models: model_catalog.clone(),
+        usage: usage.clone(),
+        gemini: messages_via_gemini.then(|| routes::GeminiBackend {
+            relay: gemini_relay.clone(),
+            model: config.messages.gemini_model.clone(),
+        }),
+        openai_compatible: openai_compatible_relay
+            .clone()
+            .filter(|_| config.messages.openai_fallback),
+        server_tools: Arc::new(server_tools::ServerToolFilter::new(&config.accounts)),
+        streaming: streaming.clone(),
+        retry,
+        retryable: retryable.clone(),
     });
 
     let gemini_state = Arc::new(GeminiRouteState {
         scheduler: scheduler.clone(),
-        relay: gemini_relay,
+        relay: gemini_relay.clone(),
         db_pool: pool.clone(),
+        context_limits: context_limits.clone(),
+        model_map: model_map.clone(),
+        models: model_catalog.clone(),
+        usage: usage.clone(),
+        claude: config
+            .gemini
+            .claude_fallback
+            .then(|| routes::ClaudeBackend {
+                relay: claude_relay.clone(),
+                model: config.gemini.claude_model.clone(),
+            }),
+        streaming: streaming.clone(),
     });
 
     let openai_state = Arc::new(OpenAIRouteState {
         scheduler: scheduler.clone(),
-        relay: claude_relay,
+        relay: claude_relay.clone(),
         db_pool: pool.clone(),
+        context_limits: context_limits.clone(),
+        model_map: model_map.clone(),
+        models: model_catalog.clone(),
+        usage: usage.clone(),
+        gemini: matches!(
+            config.openai.backend,
+            OpenAIBackend::Auto | OpenAIBackend::Gemini
+        )
+        .then(|| routes::GeminiBackend {
+            relay: gemini_relay.clone(),
+            model: config.openai.gemini_model.clone(),
+        }),
+        gemini_all_models: config.openai.backend == OpenAIBackend::Gemini,
+        codex: (config.openai.backend == OpenAIBackend::Codex
+            || !config.openai.codex_prefixes.is_empty())
+        .then(|| codex_relay.clone()),
+        codex_all_models: config.openai.backend == OpenAIBackend::Codex,
+        codex_prefixes: config.openai.codex_prefixes.clone(),
+        images: routes::GeminiBackend {
+            relay: gemini_relay.clone(),
+            model: config.openai.image_model.clone(),
+        },
+        reasoning_content: config.openai.reasoning_content,
+        openai_compatible: openai_compatible_relay,
+        streaming: streaming.clone(),
     });
 
     let codex_state = Arc::new(routes::CodexRouteState {
         scheduler: scheduler.clone(),
         relay: codex_relay,
         db_pool: pool.clone(),
+        context_limits: context_limits.clone(),
+        model_map: model_map.clone(),
+        usage: usage.clone(),
+        claude: responses_via_claude.then(|| routes::ClaudeBackend {
+            relay: claude_relay,
+            model: config.responses.claude_model.clone(),
+        }),
+        streaming,
+        retry,
+        retryable,
     });
 
-    let claude_routes = Router::new()
+    let error_budgets = Arc::new(
+        error_budget::ErrorBudgetTracker::new(config.error_budget.clone(), scheduler.clone())
+            .with_webhooks(webhooks.clone()),
+    );
+
+    let error_stats = Arc::new(error_stats::ErrorStats::new());
+    let slow_requests = Arc::new(slow_requests::SlowRequests::new(&config.slow_requests));
+
+    let default_rate_limit = rate_limit::RateLimit::new(
+        config.rate_limit.requests_per_minute,
+        config.rate_limit.burst,
+    );
+    if let Some(limit) = default_rate_limit {
+        info!(
+            requests_per_minute = limit.requests_per_minute,
+            burst = limit.burst,
+            "Default API key rate limit enabled"
+        );
+    }
+    if let Some(max) = config.rate_limit.max_concurrent_requests {
+        info!(
+            max_concurrent_requests = max,
+            "Default API key concurrency limit enabled"
+        );
+    }
+    let rate_limiter = Arc::new(
+        rate_limit::RateLimiter::new(default_rate_limit)
+            .with_max_concurrent(config.rate_limit.max_concurrent_requests),
+    );
+
+    let ip_filter = Arc::new(ip_filter::IpFilter::new(&config.ip_filter));
+    if ip_filter.is_active() {
+        info!(
+            allow = config.ip_filter.allow.len(),
+            deny = config.ip_filter.deny.len(),
+            trusted_proxies = config.ip_filter.trusted_proxies.len(),
+            "Client IP filter enabled"
+        );
+    }
+
+    let admin_state = Arc::new(AdminRouteState {
+        scheduler: scheduler.clone(),
+        error_budgets: error_budgets.clone(),
+        error_stats: error_stats.clone(),
+        slow_requests: slow_requests.clone(),
+        webhooks,
+        usage,
+        bandit: bandit.clone(),
+        api_keys: api_key_validator.clone(),
+        db_pool: pool.clone(),
+    });
+
+    let usage_state = Arc::new(UsageRouteState {
+        db_pool: pool.clone(),
+        scheduler: scheduler.clone(),
+        pricing: pricing.clone(),
+    });
+
+    let batch_routes = Router::new()
+        .route(
+            "/",
+            post(routes::batches::create).get(routes::batches::list),
+        )
+        .route("/:batch_id", get(routes::batches::get))
+        .route("/:batch_id/results", get(routes::batches::results))
+        .route("/:batch_id/cancel", post(routes::batches::cancel));
+
+    let mut claude_routes = Router::new()
         .route("/v1/messages", post(routes::claude::messages))
         .route("/api/v1/messages", post(routes::claude::messages))
         .route("/claude/v1/messages", post(routes::claude::messages))
+        .route(
+            "/v1/messages/count_tokens",
+            post(routes::claude::count_tokens),
+        )
+        .route(
+            "/api/v1/messages/count_tokens",
+            post(routes::claude::count_tokens),
+        )
+        .route(
+            "/claude/v1/messages/count_tokens",
+            post(routes::claude::count_tokens),
+        )
+        .route("/v1/complete", post(routes::complete::complete))
         .route("/v1/models", get(routes::claude::models))
         .route("/api/v1/models", get(routes::claude::models))
+        .nest("/v1/messages/batches", batch_routes.clone())
+        .nest("/api/v1/messages/batches", batch_routes.clone())
+        .nest("/claude/v1/messages/batches", batch_routes);
+    if config.server.websocket {
+        info!("WebSocket streaming enabled at /ws/v1/messages");
+        claude_routes = claude_routes.route("/ws/v1/messages", get(routes::websocket::messages));
+    }
+    #[cfg(feature = "grpc")]
+    let grpc_service = routes::grpc::MessagesService::new(claude_state.clone());
+    let claude_routes = claude_routes
+        .route_layer(axum_middleware::from_fn_with_state(
+            Platform::Claude,
+            middleware::platform_access_middleware,
+        ))
         .with_state(claude_state);
 
     let gemini_routes = Router::new()
@@ -161,6 +681,24 @@ async fn main() {
             post(routes::gemini::generate_content),
         )
         .route("/gemini/v1/models", get(routes::gemini::models))
+        .route(
+            "/gemini/v1beta/models/*model_method",
+            post(routes::gemini::generate_content),
+        )
+        .route("/gemini/v1beta/models", get(routes::gemini::models))
+        .route(
+            "/v1beta/models/*model_method",
+            post(routes::gemini::generate_content),
+        )
+        .route("/v1beta/models", get(routes::gemini::models))
+        .route_layer(axum_middleware::from_fn_with_state(
+            Platform::Gemini,
+            middleware::platform_access_middleware,
+        ))
+        .route_layer(axum_middleware::from_fn_with_state(
+            ErrorFormat::Gemini,
+            middleware::error_format_middleware,
+        ))
         .with_state(gemini_state);
 
     let openai_routes = Router::new()
@@ -168,31 +706,364 @@ async fn main() {
             "/openai/v1/chat/completions",
             post(routes::openai::chat_completions),
         )
+        .route(
+            "/openai/v1/images/generations",
+            post(routes::openai::images_generations),
+        )
         .route("/openai/v1/models", get(routes::openai::models))
+        .route_layer(axum_middleware::from_fn_with_state(
+            Platform::OpenAI,
+            middleware::platform_access_middleware,
+        ))
+        .route_layer(axum_middleware::from_fn_with_state(
+            ErrorFormat::OpenAI,
+            middleware::error_format_middleware,
+        ))
         .with_state(openai_state);
 
     let codex_routes = Router::new()
         .route("/openai/v1/responses", post(routes::codex::responses))
         .route("/v1/responses", post(routes::codex::responses))
+        .route(
+            "/openai/v1/audio/transcriptions",
+            post(routes::codex::audio_transcriptions),
+        )
+        .route(
+            "/v1/audio/transcriptions",
+            post(routes::codex::audio_transcriptions),
+        )
+        .route_layer(axum_middleware::from_fn_with_state(
+            Platform::Codex,
+            middleware::platform_access_middleware,
+        ))
+        .route_layer(axum_middleware::from_fn_with_state(
+            ErrorFormat::OpenAI,
+            middleware::error_format_middleware,
+        ))
         .with_state(codex_state);
 
-    let app = Router::new()
+    let admin_routes = Router::new()
+        .route(
+            "/admin/accounts/error-budgets",
+            get(routes::admin::error_budgets),
+        )
+        .route("/admin/accounts/errors", get(routes::admin::account_errors))
+        .route("/admin/accounts/slow", get(routes::admin::slow_requests))
+        .route(
+            "/admin/accounts/:id/draining",
+            put(routes::admin::set_draining),
+        )
+        .route(
+            "/admin/keys",
+            get(routes::admin::list_keys).post(routes::admin::create_key),
+        )
+        .route("/admin/keys/:id", delete(routes::admin::revoke_key))
+        .route("/admin/keys/:id/rotate", post(routes::admin::rotate_key))
+        .route("/admin/auth-failures", get(routes::admin::auth_failures))
+        .route("/admin/usage/stream", get(routes::admin::usage_stream))
+        .route("/admin/scheduler/bandit", get(routes::admin::bandit_stats))
+        .route(
+            "/admin/webhooks/dead-letters",
+            get(routes::admin::dead_letters),
+        )
+        .route(
+            "/admin/webhooks/dead-letters/:id/replay",
+            post(routes::admin::replay_dead_letter),
+        )
+        .route_layer(axum_middleware::from_fn_with_state(
+            KeyScope::Admin,
+            middleware::scope_middleware,
+        ))
+        .with_state(admin_state);
+
+    let health_routes = Router::new()
+        .route("/health", get(health_check))
+        .route("/health/ready", get(routes::health::ready))
+        .with_state(Arc::new(routes::HealthRouteState {
+            scheduler: scheduler.clone(),
+            db_pool: pool.clone(),
+        }));
+
+    let usage_routes = Router::new()
+        .route("/usage/models", get(routes::usage::models))
+        .route("/usage/keys", get(routes::usage::keys))
+        .route("/usage/report", get(routes::usage::report))
+        .route_layer(axum_middleware::from_fn_with_state(
+            KeyScope::Usage,
+            middleware::scope_middleware,
+        ))
+        .with_state(usage_state);
+
+    let mut app = Router::new()
         .merge(claude_routes)
         .merge(gemini_routes)
         .merge(openai_routes)
         .merge(codex_routes)
-        .route("/health", get(health_check))
+        .route_layer(axum_middleware::from_fn_with_state(
+            KeyScope::Relay,
+            middleware::scope_middleware,
+        ))
+        .merge(usage_routes)
+        .merge(health_routes);
+
+    // Without an admin token or OIDC login, client keys with the admin scope
+    // authenticate the admin routes
+    let admin_oidc = config.admin_oidc.as_ref().map(|oidc| {
+        info!(issuer = %oidc.issuer, "Admin OIDC login enabled");
+        Arc::new(oidc::OidcLogin::new(oidc))
+    });
+    let admin_token = config.server.admin_token.as_deref();
+    let admin_routes = if admin_token.is_some() || admin_oidc.is_some() {
+        let admin_auth = middleware::AdminAuth {
+            token: admin_token.map(middleware::AdminToken::new),
+            oidc: admin_oidc.clone(),
+        };
+        let mut admin_routes = admin_routes.layer(axum_middleware::from_fn_with_state(
+            Arc::new(admin_auth),
+            middleware::admin_auth_middleware,
+        ));
+        if let Some(oidc) = admin_oidc {
+            let oidc_routes = Router::new()
+                .route("/admin/oidc/login", get(routes::admin::oidc_login))
+                .route("/admin/oidc/callback", get(routes::admin::oidc_callback))
+                .route("/admin/oidc/session", get(routes::admin::oidc_session))
+                .route("/admin/oidc/logout", post(routes::admin::oidc_logout))
+                .with_state(oidc);
+            admin_routes = admin_routes.merge(oidc_routes);
+        }
+        Some(admin_routes.layer(axum_middleware::from_fn_with_state(
+            ip_filter.clone(),
+            middleware::ip_filter_middleware,
+        )))
+    } else {
+        warn!("server.admin_token is not set - client API keys with the admin scope can use the admin routes");
+        app = app.merge(admin_routes);
+        None
+    };
+
+    #[cfg(feature = "grpc")]
+    {
+        info!("gRPC service relay.v1.Messages enabled");
+        let grpc_routes = Router::new()
+            .route_service("/relay.v1.Messages/*method", grpc_service)
+            .route_layer(axum_middleware::from_fn_with_state(
+                Platform::Claude,
+                middleware::platform_access_middleware,
+            ))
+            .route_layer(axum_middleware::from_fn_with_state(
+                KeyScope::Relay,
+                middleware::scope_middleware,
+            ));
+        app = app.merge(grpc_routes);
+    }
+
+    if config.audit.enabled {
+        info!(
+            sink = ?config.audit.sink,
+            include_bodies = config.audit.include_bodies,
+            "Audit logging enabled"
+        );
+        let audit_logger = Arc::new(audit::AuditLogger::from_config(&config.audit, pool.clone()));
+        app = app.layer(axum_middleware::from_fn_with_state(
+            audit_logger,
+            middleware::audit_middleware,
+        ));
+    }
+
+    app = app.layer(axum_middleware::from_fn_with_state(
+        scheduler.clone(),
+        middleware::selection_feedback_middleware,
+    ));
+
+    if slow_requests.is_active() {
+        app = app.layer(axum_middleware::from_fn_with_state(
+            slow_requests,
+            middleware::slow_request_middleware,
+        ));
+    }
+
+    let app = app
+        .layer(axum_middleware::from_fn_with_state(
+            error_budgets,
+            middleware::error_budget_middleware,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            error_stats,
+            middleware::error_stats_middleware,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            rate_limiter,
+            middleware::rate_limit_middleware,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            pool.clone(),
+            middleware::request_log_middleware,
+        ))
         .layer(axum_middleware::from_fn_with_state(
             api_key_validator,
             middleware::auth_middleware,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            ip_filter,
+            middleware::ip_filter_middleware,
         ));
+    let (app, admin_app) = match (admin_routes, config.server.admin_port) {
+        (Some(admin_routes), Some(_)) => (app, Some(admin_routes)),
+        (Some(admin_routes), None) => (app.merge(admin_routes), None),
+        (None, _) => (app, None),
+    };
+    // Outermost, so preflights skip authentication and errors carry the headers
+    let app = match cors::cors_layer(&config.server.cors).expect("validated with the config") {
+        Some(cors) => {
+            info!(origins = ?config.server.cors.allowed_origins, "CORS enabled");
+            app.layer(cors)
+        }
+        None => app,
+    };
+
+    let tls = match (&config.server.tls_cert, &config.server.tls_key) {
+        (Some(cert), Some(key)) => {
+            let client_ca = config
+                .server
+                .tls_client_ca
+                .as_deref()
+                .map(|ca| (ca, config.server.tls_client_auth));
+            let loaded = tls::CertResolver::load(cert, key).and_then(|resolver| {
+                let resolver = Arc::new(resolver);
+                let tls_config = tls::server_config(resolver.clone(), client_ca)?;
+                Ok((resolver, tls_config))
+            });
+            match loaded {
+                Ok(loaded) => Some(loaded),
+                Err(e) => {
+                    error!(error = %e, "Failed to load TLS certificate");
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => None,
+    };
 
     let addr = format!("{}:{}", config.server.host, config.server.port);
     let listener = TcpListener::bind(&addr).await.unwrap();
+    let admin = match (admin_app, config.server.admin_port) {
+        (Some(admin_app), Some(port)) => {
+            let admin_addr = format!("{}:{}", config.server.host, port);
+            let admin_listener = TcpListener::bind(&admin_addr).await.unwrap();
+            info!(address = %admin_addr, "Admin routes listening");
+            Some((admin_listener, admin_app))
+        }
+        _ => None,
+    };
 
-    info!(address = %addr, "Server listening");
+    let tls_config = match tls {
+        Some((resolver, tls_config)) => {
+            info!(
+                address = %addr,
+                client_auth = ?config.server.tls_client_ca.as_ref().map(|_| config.server.tls_client_auth),
+                "Server listening with TLS"
+            );
+            resolver.spawn_reload_task();
+            Some(tls_config)
+        }
+        None => {
+            info!(address = %addr, "Server listening");
+            None
+        }
+    };
+    let signal = shutdown_signal().shared();
+    let admin_server = async {
+        if let Some((admin_listener, admin_app)) = admin {
+            serve(
+                admin_listener,
+                admin_app,
+                tls_config.clone(),
+                signal.clone(),
+            )
+            .await;
+        }
+    };
+    let servers = async {
+        tokio::join!(
+            serve(listener, app, tls_config.clone(), signal.clone()),
+            admin_server
+        );
+    };
+    tokio::pin!(servers);
+
+    // New connections stop at the signal; requests in flight get the grace
+    // period to finish, then their streams are ended
+    let grace_seconds = config.server.shutdown_grace_seconds;
+    let grace_over = async {
+        signal.clone().await;
+        info!(grace_seconds, "Waiting for requests in flight");
+        tokio::time::sleep(std::time::Duration::from_secs(grace_seconds)).await;
+    };
+    tokio::select! {
+        _ = &mut servers => {}
+        _ = grace_over => {
+            warn!(grace_seconds, "Shutdown grace period over, ending streams in flight");
+            let _ = cut_streams.send(true);
+            if tokio::time::timeout(CUTOFF_DRAIN, servers).await.is_err() {
+                warn!("Dropping requests still in flight");
+            }
+        }
+    }
+
+    if let Some(writer) = usage_writer {
+        writer.flush().await;
+    }
+    info!("Server stopped");
+}
+
+/// Serves `app` until shutdown, over TLS when `tls_config` is given.
+async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls_config: Option<Arc<tokio_rustls::rustls::ServerConfig>>,
+    signal: impl Future<Output = ()> + Send + 'static,
+) {
+    match tls_config {
+        Some(tls_config) => tls::serve(listener, app, tls_config, signal).await,
+        None => axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(signal)
+        .await
+        .unwrap(),
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
 
-    axum::serve(listener, app).await.unwrap();
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutting down");
 }
 
 fn build_accounts(config: &Config) -> Vec<Arc<dyn AccountProvider>> {
@@ -209,6 +1080,7 @@ fn build_accounts(config: &Config) -> Vec<Arc<dyn AccountProvider>> {
                     refresh_token,
                     api_url,
                     proxy,
+                    ..
                 } => Arc::new(ClaudeOAuthAccount::new(
                     id.clone(),
                     name.clone(),
@@ -218,6 +1090,24 @@ fn build_accounts(config: &Config) -> Vec<Arc<dyn AccountProvider>> {
                     api_url.clone(),
                     proxy.clone(),
                 )),
+                AccountConfig::ClaudeSession {
+                    id,
+                    name,
+                    priority,
+                    enabled,
+                    session_key,
+                    api_url,
+                    proxy,
+                    ..
+                } => Arc::new(ClaudeSessionAccount::new(
+                    id.clone(),
+                    name.clone(),
+                    *priority,
+                    *enabled,
+                    session_key.clone(),
+                    api_url.clone(),
+                    proxy.clone(),
+                )),
                 AccountConfig::ClaudeApi {
                     id,
                     name,
@@ -226,6 +1116,7 @@ fn build_accounts(config: &Config) -> Vec<Arc<dyn AccountProvider>> {
                     api_key,
                     api_url,
                     proxy,
+                    ..
                 } => Arc::new(ClaudeApiAccount::new(
                     id.clone(),
                     name.clone(),
@@ -235,23 +1126,93 @@ fn build_accounts(config: &Config) -> Vec<Arc<dyn AccountProvider>> {
                     api_url.clone(),
                     proxy.clone(),
                 )),
-                AccountConfig::Gemini {
+                AccountConfig::Bedrock {
                     id,
                     name,
                     priority,
                     enabled,
-                    refresh_token,
+                    region,
+                    access_key_id,
+                    secret_access_key,
+                    session_token,
                     api_url,
                     proxy,
-                } => Arc::new(GeminiAccount::new(
+                    ..
+                } => Arc::new(BedrockAccount::new(
                     id.clone(),
                     name.clone(),
                     *priority,
                     *enabled,
-                    refresh_token.clone(),
+                    AwsCredentials {
+                        access_key_id: access_key_id.clone(),
+                        secret_access_key: secret_access_key.clone(),
+                        session_token: session_token.clone(),
+                        region: region.clone(),
+                    },
                     api_url.clone(),
                     proxy.clone(),
                 )),
+                AccountConfig::Vertex {
+                    id,
+                    name,
+                    priority,
+                    enabled,
+                    platform,
+                    service_account_file,
+                    project_id,
+                    region,
+                    api_url,
+                    proxy,
+                    ..
+                } => {
+                    let key = match ServiceAccountKey::from_file(service_account_file) {
+                        Ok(key) => key,
+                        Err(e) => {
+                            error!(account_id = %id, error = %e, "Failed to load Vertex AI account");
+                            std::process::exit(1);
+                        }
+                    };
+                    let platform = match platform {
+                        VertexPlatform::Claude => Platform::Claude,
+                        VertexPlatform::Gemini => Platform::Gemini,
+                    };
+                    Arc::new(
+                        VertexAccount::new(
+                            id.clone(),
+                            name.clone(),
+                            *priority,
+                            *enabled,
+                            platform,
+                            key,
+                            region.clone(),
+                        )
+                        .with_project_id(project_id.clone())
+                        .with_api_url(api_url.clone())
+                        .with_proxy(proxy.clone()),
+                    )
+                }
+                AccountConfig::Gemini {
+                    id,
+                    name,
+                    priority,
+                    enabled,
+                    refresh_token,
+                    api_url,
+                    api_version,
+                    proxy,
+                    ..
+                } => Arc::new(
+                    GeminiAccount::new(
+                        id.clone(),
+                        name.clone(),
+                        *priority,
+                        *enabled,
+                        refresh_token.clone(),
+                        api_url.clone(),
+                        proxy.clone(),
+                    )
+                    .with_api_version(api_version.clone()),
+                ),
                 AccountConfig::OpenaiResponses {
                     id,
                     name,
@@ -260,6 +1221,7 @@ fn build_accounts(config: &Config) -> Vec<Arc<dyn AccountProvider>> {
                     api_key,
                     api_url,
                     proxy,
+                    ..
                 } => Arc::new(relay_codex::CodexAccount::new(
                     id.clone(),
                     name.clone(),
@@ -269,6 +1231,101 @@ fn build_accounts(config: &Config) -> Vec<Arc<dyn AccountProvider>> {
                     api_url.clone(),
                     proxy.clone(),
                 )),
+                AccountConfig::AzureOpenai {
+                    id,
+                    name,
+                    priority,
+                    enabled,
+                    api_key,
+                    api_url,
+                    deployment,
+                    api_version,
+                    proxy,
+                    ..
+                } => Arc::new(relay_codex::AzureOpenAIAccount::new(
+                    id.clone(),
+                    name.clone(),
+                    *priority,
+                    *enabled,
+                    AzureCredentials {
+                        api_key: api_key.clone(),
+                        deployment: deployment.clone(),
+                        api_version: api_version.clone().unwrap_or_else(|| {
+                            relay_codex::AzureOpenAIAccount::DEFAULT_API_VERSION.to_string()
+                        }),
+                    },
+                    api_url.clone(),
+                    proxy.clone(),
+                )),
+                AccountConfig::Openrouter {
+                    id,
+                    name,
+                    priority,
+                    enabled,
+                    api_key,
+                    api_url,
+                    http_referer,
+                    x_title,
+                    model_ids,
+                    proxy,
+                    ..
+                } => Arc::new(
+                    OpenRouterAccount::new(
+                        id.clone(),
+                        name.clone(),
+                        *priority,
+                        *enabled,
+                        api_key.clone(),
+                        api_url.clone(),
+                        proxy.clone(),
+                    )
+                    .with_attribution(http_referer.clone(), x_title.clone())
+                    .with_model_ids(model_ids.clone()),
+                ),
+                AccountConfig::OpenaiCompatible {
+                    id,
+                    name,
+                    priority,
+                    enabled,
+                    api_url,
+                    api_key,
+                    model,
+                    proxy,
+                    ..
+                } => Arc::new(
+                    OpenAICompatibleAccount::new(
+                        id.clone(),
+                        name.clone(),
+                        *priority,
+                        *enabled,
+                        api_url.clone(),
+                        api_key.clone(),
+                        proxy.clone(),
+                    )
+                    .with_model(model.clone()),
+                ),
+                AccountConfig::Deepseek {
+                    id,
+                    name,
+                    priority,
+                    enabled,
+                    api_key,
+                    api_url,
+                    model,
+                    proxy,
+                    ..
+                } => Arc::new(
+                    DeepSeekAccount::new(
+                        id.clone(),
+                        name.clone(),
+                        *priority,
+                        *enabled,
+                        api_key.clone(),
+                        api_url.clone(),
+                        proxy.clone(),
+                    )
+                    .with_model(model.clone()),
+                ),
             }
         })
         .collect()
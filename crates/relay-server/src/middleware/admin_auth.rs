@@ -0,0 +1,221 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::ClientApiKeyHash;
+use crate::oidc::OidcLogin;
+
+/// Header carrying the admin token, for clients that cannot set
+/// `Authorization`.
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+/// Cookie carrying a session from an OIDC login.
+pub const ADMIN_SESSION_COOKIE: &str = "relay_admin_session";
+
+/// The `admin_token` that `/admin` routes require instead of client keys,
+/// held by its SHA-256 hash.
+pub struct AdminToken {
+    hash: String,
+}
+
+impl AdminToken {
+    /// Like client keys, the token may be configured as `sha256:<hex>`.
+    pub fn new(token: &str) -> Self {
+        Self {
+            hash: ClientApiKeyHash::from_configured_key(token).0,
+        }
+    }
+
+    fn matches(&self, presented: &str) -> bool {
+        ClientApiKeyHash::from_api_key(presented).0 == self.hash
+    }
+}
+
+/// How `/admin` routes authenticate: the admin token, sessions from an
+/// OIDC login, or either.
+pub struct AdminAuth {
+    pub token: Option<AdminToken>,
+    pub oidc: Option<Arc<OidcLogin>>,
+}
+
+/// The value of cookie `name`, if the request carries it.
+pub fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then_some(value)
+        })
+}
+
+/// Admits requests bearing the admin token, sent as
+/// `Authorization: Bearer <token>` or [`ADMIN_TOKEN_HEADER`], or an
+/// [`ADMIN_SESSION_COOKIE`] from an OIDC login. Layered on the admin routes
+/// in place of `auth_middleware`, so client keys are not accepted there.
+pub async fn admin_auth_middleware(
+    State(auth): State<Arc<AdminAuth>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get(ADMIN_TOKEN_HEADER)?.to_str().ok());
+
+    if presented.is_some_and(|presented| auth.token.as_ref().is_some_and(|t| t.matches(presented)))
+    {
+        return next.run(request).await;
+    }
+
+    let session = auth
+        .oidc
+        .as_ref()
+        .zip(cookie(headers, ADMIN_SESSION_COOKIE))
+        .and_then(|(oidc, cookie)| oidc.session(cookie));
+    let Some(session) = session else {
+        warn!(path = %request.uri().path(), "Rejected admin request without a valid admin token or session");
+        let message = match auth.oidc {
+            Some(_) => "A valid admin token or session is required; sign in at /admin/oidc/login",
+            None => "A valid admin token is required",
+        };
+        let body = serde_json::json!({
+            "error": {
+                "type": "authentication_error",
+                "message": message
+            }
+        });
+        return (StatusCode::UNAUTHORIZED, Json(body)).into_response();
+    };
+
+    if request.method() != Method::GET {
+        info!(
+            email = %session.email,
+            method = %request.method(),
+            path = %request.uri().path(),
+            "Admin request"
+        );
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{auth_middleware, ApiKeyPolicy, ApiKeyValidator};
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_admin_routes_require_admin_token() {
+        let validator = Arc::new(ApiKeyValidator::new(vec![(
+            "client-key".to_string(),
+            ApiKeyPolicy::default(),
+        )]));
+        let admin = Router::new()
+            .route("/admin/keys", get(|| async { "keys" }))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(AdminAuth {
+                    token: Some(AdminToken::new("admin-secret")),
+                    oidc: None,
+                }),
+                admin_auth_middleware,
+            ));
+        let app = Router::new()
+            .route("/v1/messages", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware))
+            .merge(admin);
+
+        let send = |path: &str, header: Option<(&str, &str)>| {
+            let mut request = axum::http::Request::builder().uri(path);
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let client_key = Some(("x-api-key", "client-key"));
+        let response = send("/v1/messages", client_key).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("/admin/keys", client_key).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let bearer_client_key = Some(("authorization", "Bearer client-key"));
+        let response = send("/admin/keys", bearer_client_key).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let bearer = Some(("authorization", "Bearer admin-secret"));
+        let response = send("/admin/keys", bearer).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let header = Some((ADMIN_TOKEN_HEADER, "admin-secret"));
+        let response = send("/admin/keys", header).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("/v1/messages", bearer).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_admin_session_cookie() {
+        let oidc = Arc::new(OidcLogin::new(&crate::config::AdminOidcConfig {
+            issuer: "https://sso.example.com".to_string(),
+            client_id: "relay-admin".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "https://relay.example.com/admin/oidc/callback".to_string(),
+            allowed_emails: Vec::new(),
+            allowed_domains: vec!["example.com".to_string()],
+            session_ttl_seconds: 3600,
+        }));
+        let (session, _) = oidc.start_session("alice@example.com".to_string());
+        let auth = AdminAuth {
+            token: None,
+            oidc: Some(oidc),
+        };
+        let app = Router::new()
+            .route("/admin/keys", get(|| async { "keys" }))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(auth),
+                admin_auth_middleware,
+            ));
+
+        let send = |cookie: String| {
+            let request = axum::http::Request::builder()
+                .uri("/admin/keys")
+                .header("cookie", cookie)
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let cookie = format!("theme=dark; {}={}", ADMIN_SESSION_COOKIE, session);
+        let response = send(cookie).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let forged = format!("{}=forged", ADMIN_SESSION_COOKIE);
+        let response = send(forged).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        // Without a configured token, no bearer value is taken for one
+        let request = axum::http::Request::builder()
+            .uri("/admin/keys")
+            .header("authorization", "Bearer ")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_hashed_admin_token() {
+        let digest = ClientApiKeyHash::from_api_key("admin-secret").0;
+        let token = AdminToken::new(&format!("sha256:{}", digest));
+        assert!(token.matches("admin-secret"));
+        assert!(!token.matches(&format!("sha256:{}", digest)));
+    }
+}
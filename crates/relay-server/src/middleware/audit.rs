@@ -0,0 +1,350 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use tracing::warn;
+
+use super::{ClientApiKeyHash, ClientIp, RequestContext};
+use crate::audit::{AuditLogger, AuditRecord};
+
+/// Largest request body buffered for auditing; matches axum's default body
+/// limit, which the relay routes enforce anyway.
+const MAX_BUFFERED_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Sends the record once the response body has been fully sent or dropped.
+struct PendingAudit {
+    logger: Arc<AuditLogger>,
+    record: Option<AuditRecord>,
+    captured: Vec<u8>,
+}
+
+impl PendingAudit {
+    fn capture(&mut self, chunk: &[u8]) {
+        // One byte over the limit is enough for redaction to mark truncation
+        let room = (self.logger.max_body_bytes() + 1).saturating_sub(self.captured.len());
+        self.captured
+            .extend_from_slice(&chunk[..room.min(chunk.len())]);
+    }
+}
+
+impl Drop for PendingAudit {
+    fn drop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            record.response_body = Some(self.logger.redact_body(&self.captured));
+            self.logger.log(record);
+        }
+    }
+}
+
+/// Must run inside `request_log_middleware`, which provides the request id.
+pub async fn audit_middleware(
+    State(logger): State<Arc<AuditLogger>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(context) = request.extensions().get::<RequestContext>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let api_key_hash = request
+        .extensions()
+        .get::<ClientApiKeyHash>()
+        .cloned()
+        .unwrap_or_else(ClientApiKeyHash::anonymous);
+    let client_ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ip| ip.0.to_string());
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    let (request, request_body) = if logger.include_bodies() {
+        let (parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, MAX_BUFFERED_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!(error = %e, "Failed to read request body for audit");
+                return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+            }
+        };
+        let redacted = logger.redact_body(&bytes);
+        (
+            Request::from_parts(parts, Body::from(bytes)),
+            Some(redacted),
+        )
+    } else {
+        (request, None)
+    };
+
+    let response = next.run(request).await;
+
+    // Only relayed requests are audited
+    let Some(model) = context.model() else {
+        return response;
+    };
+
+    let record = AuditRecord {
+        request_id: context.request_id().to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        client_api_key_hash: api_key_hash.0,
+        client_ip,
+        method,
+        path,
+        model: Some(model),
+        account_id: context.account_id(),
+        status_code: response.status().as_u16(),
+        user_agent,
+        request_body,
+        response_body: None,
+    };
+
+    if !logger.include_bodies() {
+        logger.log(record);
+        return response;
+    }
+
+    // Tee the body instead of buffering it: capture stays bounded by
+    // max_body_bytes and read errors still reach the client
+    let (parts, body) = response.into_parts();
+    let mut pending = PendingAudit {
+        logger,
+        record: Some(record),
+        captured: Vec::new(),
+    };
+    let body = body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            pending.capture(bytes);
+        }
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditSink;
+    use crate::db::{self, DbPool};
+    use crate::middleware::request_log_middleware;
+    use async_trait::async_trait;
+    use axum::{middleware, routing::post, Extension, Json, Router};
+    use parking_lot::Mutex;
+    use relay_core::Platform;
+    use tower::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct MemorySink(Arc<Mutex<Vec<AuditRecord>>>);
+
+    #[async_trait]
+    impl AuditSink for MemorySink {
+        async fn write(&mut self, record: &AuditRecord) -> anyhow::Result<()> {
+            self.0.lock().push(record.clone());
+            Ok(())
+        }
+    }
+
+    async fn setup_test_db() -> DbPool {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    async fn echo(
+        Extension(ctx): Extension<RequestContext>,
+        Json(body): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        ctx.begin(Platform::Claude, "claude-sonnet-4-20250514", false);
+        ctx.set_account("acc1", 0);
+        Json(serde_json::json!({"echo": body["messages"]}))
+    }
+
+    async fn stream(Extension(ctx): Extension<RequestContext>) -> Body {
+        ctx.begin(Platform::Claude, "claude-sonnet-4-20250514", true);
+        let chunks: Vec<Result<&'static str, std::io::Error>> =
+            vec![Ok("data: one\n\n"), Ok("data: two\n\n")];
+        Body::from_stream(futures::stream::iter(chunks))
+    }
+
+    async fn app(include_bodies: bool) -> (Router, MemorySink) {
+        let sink = MemorySink::default();
+        let logger = Arc::new(AuditLogger::spawn(
+            Box::new(sink.clone()),
+            include_bodies,
+            1024,
+        ));
+        let router = Router::new()
+            .route("/v1/messages", post(echo))
+            .route("/stream", post(stream))
+            .route("/health", post(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(logger, audit_middleware))
+            .layer(middleware::from_fn_with_state(
+                setup_test_db().await,
+                request_log_middleware,
+            ));
+        (router, sink)
+    }
+
+    fn post_json(uri: &str, body: serde_json::Value) -> Request {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::USER_AGENT, "claude-cli/1.0.57 (external, cli)")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn records(sink: &MemorySink) -> Vec<AuditRecord> {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        sink.0.lock().clone()
+    }
+
+    #[tokio::test]
+    async fn test_audits_metadata_without_bodies() {
+        let (app, sink) = app(false).await;
+
+        let response = app
+            .oneshot(post_json(
+                "/v1/messages",
+                serde_json::json!({"messages": []}),
+            ))
+            .await
+            .unwrap();
+        let request_id = response.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let records = records(&sink).await;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].request_id, request_id);
+        assert_eq!(records[0].method, "POST");
+        assert_eq!(records[0].path, "/v1/messages");
+        assert_eq!(records[0].account_id.as_deref(), Some("acc1"));
+        assert_eq!(
+            records[0].user_agent.as_deref(),
+            Some("claude-cli/1.0.57 (external, cli)")
+        );
+        assert!(records[0].request_body.is_none());
+        assert!(records[0].response_body.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_audits_redacted_bodies() {
+        let (app, sink) = app(true).await;
+
+        let response = app
+            .oneshot(post_json(
+                "/v1/messages",
+                serde_json::json!({"messages": ["hi"], "metadata": {"api_key": "sk-secret"}}),
+            ))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"echo":["hi"]}"#);
+
+        let records = records(&sink).await;
+        let request_body = records[0].request_body.as_deref().unwrap();
+        assert!(request_body.contains("[REDACTED]"));
+        assert!(!request_body.contains("sk-secret"));
+        assert_eq!(
+            records[0].response_body.as_deref(),
+            Some(r#"{"echo":["hi"]}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_audits_streamed_response_after_completion() {
+        let (app, sink) = app(true).await;
+
+        let response = app
+            .oneshot(post_json("/stream", serde_json::json!({})))
+            .await
+            .unwrap();
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let records = records(&sink).await;
+        assert_eq!(
+            records[0].response_body.as_deref(),
+            Some("data: one\n\ndata: two\n\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_request_body_over_buffer_limit() {
+        let (app, sink) = app(true).await;
+
+        let response = app
+            .oneshot(post_json(
+                "/v1/messages",
+                serde_json::json!({"messages": ["x".repeat(MAX_BUFFERED_BODY_BYTES)]}),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(records(&sink).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_response_body_error_reaches_client() {
+        async fn broken(Extension(ctx): Extension<RequestContext>) -> Body {
+            ctx.begin(Platform::Claude, "claude-sonnet-4-20250514", false);
+            let chunks: Vec<Result<&'static str, std::io::Error>> = vec![
+                Ok("{\"partial\":"),
+                Err(std::io::Error::other("upstream reset")),
+            ];
+            Body::from_stream(futures::stream::iter(chunks))
+        }
+
+        let sink = MemorySink::default();
+        let logger = Arc::new(AuditLogger::spawn(Box::new(sink.clone()), true, 1024));
+        let app = Router::new()
+            .route("/broken", post(broken))
+            .layer(middleware::from_fn_with_state(logger, audit_middleware))
+            .layer(middleware::from_fn_with_state(
+                setup_test_db().await,
+                request_log_middleware,
+            ));
+
+        let response = app
+            .oneshot(post_json("/broken", serde_json::json!({})))
+            .await
+            .unwrap();
+        assert!(axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .is_err());
+
+        let records = records(&sink).await;
+        assert_eq!(records[0].response_body.as_deref(), Some(r#"{"partial":"#));
+    }
+
+    #[tokio::test]
+    async fn test_skips_non_relayed_requests() {
+        let (app, sink) = app(true).await;
+
+        app.oneshot(post_json("/health", serde_json::json!({})))
+            .await
+            .unwrap();
+
+        assert!(records(&sink).await.is_empty());
+    }
+}
@@ -2,31 +2,328 @@ use axum::{
     extract::{Request, State},
     http::{header, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
+    Json,
 };
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use relay_claude::ClientProfile;
+use relay_core::Platform;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::IpAddr;
 use std::sync::Arc;
 use tracing::warn;
 
-#[derive(Clone)]
+use super::ClientIp;
+use crate::auth_throttle::AuthThrottle;
+use crate::db::{self, DbPool};
+use crate::ip_filter::{self, IpRange};
+use crate::jwt::JwtVerifier;
+use crate::rate_limit::RateLimit;
+
+/// Header naming the account to serve a request, bypassing the scheduler.
+pub const ACCOUNT_PIN_HEADER: &str = "x-relay-account";
+
+/// Marks a configured key given as its hex SHA-256 digest.
+pub const HASHED_KEY_PREFIX: &str = "sha256:";
+
+/// What a key may do, checked per route family by [`scope_middleware`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyScope {
+    /// The model routes of every platform.
+    Relay,
+    /// The `/usage` reports.
+    Usage,
+    /// The `/admin` routes, while no admin token or OIDC login guards them.
+    /// Never granted unless listed.
+    Admin,
+}
+
+/// The scopes of keys that list none, and of JWT and certificate identities.
+pub const DEFAULT_SCOPES: &[KeyScope] = &[KeyScope::Relay, KeyScope::Usage];
+
+impl fmt::Display for KeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KeyScope::Relay => "relay",
+            KeyScope::Usage => "usage",
+            KeyScope::Admin => "admin",
+        })
+    }
+}
+
+/// Per-key options, inserted into request extensions for route handlers.
+#[derive(Clone, Debug, Default)]
+pub struct ApiKeyPolicy {
+    pub profile: ClientProfile,
+    pub max_tokens_per_day: Option<u64>,
+    /// Whether the key may send [`ACCOUNT_PIN_HEADER`].
+    pub account_pinning: bool,
+    /// Model patterns the key may use, where `*` matches any characters;
+    /// empty allows every model.
+    pub allowed_models: Vec<String>,
+    /// Platforms whose routes the key may call; empty allows every platform.
+    pub allowed_platforms: Vec<Platform>,
+    /// What the key may do; empty grants [`DEFAULT_SCOPES`].
+    pub scopes: Vec<KeyScope>,
+    /// Client addresses the key may be used from; empty allows any.
+    pub allowed_ips: Vec<IpRange>,
+    /// Overrides the default request rate.
+    pub rate_limit: Option<RateLimit>,
+    /// Overrides the default limit on requests in flight.
+    pub max_concurrent_requests: Option<u32>,
+    /// The tenant whose accounts serve the key.
+    pub tenant: Option<String>,
+    /// The key is rejected from this time on.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Usage is recorded under this hash instead of the key's own, so a
+    /// rotated key keeps the identity of the key it replaced.
+    pub usage_hash: Option<String>,
+    /// The account this request named with [`ACCOUNT_PIN_HEADER`], set by
+    /// [`auth_middleware`].
+    pub pinned_account: Option<String>,
+}
+
+impl ApiKeyPolicy {
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.allowed_models.is_empty()
+            || self
+                .allowed_models
+                .iter()
+                .any(|pattern| matches_pattern(pattern, model))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+
+    pub fn allows_platform(&self, platform: Platform) -> bool {
+        self.allowed_platforms.is_empty() || self.allowed_platforms.contains(&platform)
+    }
+
+    pub fn allows_scope(&self, scope: KeyScope) -> bool {
+        if self.scopes.is_empty() {
+            DEFAULT_SCOPES.contains(&scope)
+        } else {
+            self.scopes.contains(&scope)
+        }
+    }
+
+    pub fn allows_ip(&self, ip: Option<IpAddr>) -> bool {
+        ip_filter::any_contains(&self.allowed_ips, ip)
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of
+/// characters and everything else must match exactly.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Keys are held by their SHA-256 hash.
 pub struct ApiKeyValidator {
-    valid_keys: HashSet<String>,
+    /// Keys from the config.
+    valid_keys: HashMap<String, ApiKeyPolicy>,
+    /// Unrevoked keys from the `client_keys` table, cached until
+    /// [`ApiKeyValidator::load_issued_keys`] runs again.
+    issued_keys: RwLock<HashMap<String, ApiKeyPolicy>>,
+    /// Paths served without an API key, such as `/v1/models`.
+    public_paths: HashSet<String>,
+    /// Path patterns served without a key; once set, requests elsewhere
+    /// need one even while no keys exist.
+    anonymous_routes: Option<Vec<String>>,
+    /// Accepts bearer JWTs in place of keys.
+    jwt: Option<JwtVerifier>,
+    /// Where rejected requests are recorded.
+    failure_log: Option<DbPool>,
+    /// Bans addresses that keep sending bad keys.
+    throttle: Option<AuthThrottle>,
 }
 
 impl ApiKeyValidator {
-    pub fn new(keys: Vec<String>) -> Self {
+    /// Keys may be given in plaintext or as `sha256:<hex digest>`.
+    pub fn new(keys: Vec<(String, ApiKeyPolicy)>) -> Self {
         Self {
-            valid_keys: keys.into_iter().collect(),
+            valid_keys: keys
+                .into_iter()
+                .map(|(key, policy)| (ClientApiKeyHash::from_configured_key(&key).0, policy))
+                .collect(),
+            issued_keys: RwLock::default(),
+            public_paths: HashSet::new(),
+            anonymous_routes: None,
+            jwt: None,
+            failure_log: None,
+            throttle: None,
         }
     }
 
-    pub fn validate(&self, key: &str) -> bool {
-        self.valid_keys.contains(key)
+    /// Replaces the cached issued keys with the unrevoked ones in the
+    /// database, returning how many there are.
+    pub async fn load_issued_keys(&self, pool: &DbPool) -> Result<usize, sqlx::Error> {
+        let all_keys = db::list_client_keys(pool).await?;
+        let by_id: HashMap<&str, &db::ClientKey> =
+            all_keys.iter().map(|key| (key.id.as_str(), key)).collect();
+        // A rotated key takes the identity of the first key in its chain
+        let usage_hash = |key: &db::ClientKey| {
+            let mut first = key;
+            for _ in 0..by_id.len() {
+                match first.rotated_from.as_deref().and_then(|id| by_id.get(id)) {
+                    Some(previous) => first = previous,
+                    None => break,
+                }
+            }
+            (first.id != key.id).then(|| first.client_api_key_hash.clone())
+        };
+        let keys: HashMap<String, ApiKeyPolicy> = all_keys
+            .iter()
+            .filter(|key| key.revoked_at.is_none())
+            .map(|key| {
+                let policy = ApiKeyPolicy {
+                    profile: key.profile,
+                    max_tokens_per_day: key.max_tokens_per_day,
+                    account_pinning: key.account_pinning,
+                    allowed_models: key.allowed_models.clone(),
+                    allowed_platforms: key.allowed_platforms.clone(),
+                    scopes: key.scopes.clone(),
+                    allowed_ips: key.allowed_ips.clone(),
+                    rate_limit: RateLimit::new(key.requests_per_minute, key.burst),
+                    max_concurrent_requests: key.max_concurrent_requests,
+                    tenant: key.tenant.clone(),
+                    expires_at: key.expires_at,
+                    usage_hash: usage_hash(key),
+                    pinned_account: None,
+                };
+                (key.client_api_key_hash.clone(), policy)
+            })
+            .collect();
+        let count = keys.len();
+        *self.issued_keys.write() = keys;
+        Ok(count)
+    }
+
+    pub fn with_public_paths(mut self, paths: impl IntoIterator<Item = String>) -> Self {
+        self.public_paths = paths.into_iter().collect();
+        self
+    }
+
+    pub fn with_anonymous_routes(mut self, routes: Option<Vec<String>>) -> Self {
+        self.anonymous_routes = routes;
+        self
+    }
+
+    pub fn with_jwt(mut self, verifier: Option<JwtVerifier>) -> Self {
+        self.jwt = verifier;
+        self
+    }
+
+    /// Records rejected requests in the `auth_failures` table.
+    pub fn with_failure_log(mut self, pool: DbPool) -> Self {
+        self.failure_log = Some(pool);
+        self
+    }
+
+    pub fn with_throttle(mut self, throttle: Option<AuthThrottle>) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Records a rejected request without waiting for the write. Requests
+    /// that sent a key count towards banning their address.
+    fn record_failure(&self, request: &Request, api_key: Option<&str>, reason: &'static str) {
+        let client_ip = request.extensions().get::<ClientIp>().map(|ip| ip.0);
+        if let (Some(throttle), Some(ip), Some(_)) = (&self.throttle, client_ip, api_key) {
+            if throttle.record_failure(ip) {
+                warn!(client_ip = %ip, "Banned address after repeated authentication failures");
+            }
+        }
+
+        let Some(pool) = self.failure_log.clone() else {
+            return;
+        };
+        let masked_key = api_key.map(mask_key);
+        let client_ip = client_ip.map(|ip| ip.to_string());
+        let path = request.uri().path().to_string();
+        tokio::spawn(async move {
+            let recorded = db::record_auth_failure(
+                &pool,
+                masked_key.as_deref(),
+                client_ip.as_deref(),
+                &path,
+                reason,
+            )
+            .await;
+            if let Err(e) = recorded {
+                warn!(error = %e, "Failed to record authentication failure");
+            }
+        });
     }
 
+    pub fn is_public(&self, path: &str) -> bool {
+        self.public_paths.contains(path)
+            || self
+                .anonymous_routes
+                .iter()
+                .flatten()
+                .any(|pattern| matches_pattern(pattern, path))
+    }
+
+    pub fn policy(&self, key: &str) -> Option<ApiKeyPolicy> {
+        let hash = ClientApiKeyHash::from_api_key(key).0;
+        match self.valid_keys.get(&hash) {
+            Some(policy) => Some(policy.clone()),
+            None => self.issued_keys.read().get(&hash).cloned(),
+        }
+    }
+
+    /// The identity and policy of a bearer JWT, which gets the default
+    /// policy and scopes. `None` for tokens not shaped like a JWT.
+    fn jwt_identity(&self, token: &str) -> Option<(ClientApiKeyHash, ApiKeyPolicy)> {
+        let verifier = self.jwt.as_ref()?;
+        if !JwtVerifier::looks_like_jwt(token) {
+            return None;
+        }
+        match verifier.verify(token) {
+            Ok(subject) => Some((
+                ClientApiKeyHash::from_jwt_subject(&subject),
+                ApiKeyPolicy {
+                    scopes: DEFAULT_SCOPES.to_vec(),
+                    ..Default::default()
+                },
+            )),
+            Err(e) => {
+                warn!(error = %e, "Invalid JWT");
+                None
+            }
+        }
+    }
+
+    /// Whether no keys are configured or issued.
     pub fn is_empty(&self) -> bool {
-        self.valid_keys.is_empty()
+        self.valid_keys.is_empty() && self.issued_keys.read().is_empty() && self.jwt.is_none()
+    }
+
+    /// With no keys and no `anonymous_routes`, every request is anonymous.
+    pub fn is_open(&self) -> bool {
+        self.is_empty() && self.anonymous_routes.is_none()
     }
 }
 
@@ -38,9 +335,43 @@ impl ClientApiKeyHash {
         Self(hex::encode(Sha256::digest(api_key.as_bytes())))
     }
 
+    /// The hash of a configured key, which is either the key itself or its
+    /// digest prefixed with [`HASHED_KEY_PREFIX`].
+    pub fn from_configured_key(key: &str) -> Self {
+        match key.strip_prefix(HASHED_KEY_PREFIX) {
+            Some(digest) => Self(digest.to_ascii_lowercase()),
+            None => Self::from_api_key(key),
+        }
+    }
+
+    /// Requests authenticated with a JWT are recorded under its subject.
+    pub fn from_jwt_subject(subject: &str) -> Self {
+        Self(format!("jwt:{}", subject))
+    }
+
     pub fn anonymous() -> Self {
         Self("anonymous".to_string())
     }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.0 == "anonymous"
+    }
+}
+
+/// A client certificate verified against `tls_client_ca` in the TLS
+/// handshake, which authenticates requests that carry no API key.
+#[derive(Clone, Debug)]
+pub struct ClientCertificate {
+    /// SHA-256 of the certificate, recorded in place of a key hash.
+    pub fingerprint: String,
+}
+
+impl ClientCertificate {
+    pub fn from_der(der: &[u8]) -> Self {
+        Self {
+            fingerprint: hex::encode(Sha256::digest(der)),
+        }
+    }
 }
 
 pub async fn auth_middleware(
@@ -48,11 +379,39 @@ pub async fn auth_middleware(
     mut request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    if validator.is_empty() {
-        request.extensions_mut().insert(ClientApiKeyHash::anonymous());
-        return Ok(next.run(request).await);
+    if validator.is_open() {
+        return Ok(next.run(anonymous(request)).await);
+    }
+
+    let client_ip = request.extensions().get::<ClientIp>().map(|ip| ip.0);
+    let banned_for = validator
+        .throttle
+        .as_ref()
+        .zip(client_ip)
+        .and_then(|(throttle, ip)| throttle.banned_for(ip));
+    if let Some(banned_for) = banned_for {
+        let retry_after = banned_for.as_secs_f64().ceil().max(1.0) as u64;
+        let body = serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "rate_limit_error",
+                "message": format!(
+                    "Too many failed authentication attempts from this address. Retry after {} seconds.",
+                    retry_after
+                )
+            }
+        });
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(body),
+        )
+            .into_response());
     }
 
+    // Public paths still attribute requests that carry a valid key
+    let is_public = validator.is_public(request.uri().path());
+
     let api_key = {
         let auth_header = request
             .headers()
@@ -64,28 +423,162 @@ pub async fn auth_middleware(
                 h.strip_prefix("Bearer ").unwrap().to_string()
             }
             _ => {
-                if let Some(key) = request.headers().get("x-api-key").and_then(|v| v.to_str().ok()) {
+                // Gemini SDKs send their key as x-goog-api-key
+                let key = ["x-api-key", "x-goog-api-key"]
+                    .into_iter()
+                    .find_map(|name| request.headers().get(name)?.to_str().ok());
+                if let Some(key) = key {
                     key.to_string()
+                } else if let Some(certificate) =
+                    request.extensions().get::<ClientCertificate>().cloned()
+                {
+                    return Ok(next.run(certified(request, certificate)).await);
+                } else if is_public {
+                    return Ok(next.run(anonymous(request)).await);
                 } else {
                     warn!("Missing API key in request");
+                    validator.record_failure(&request, None, "missing_key");
                     return Err(StatusCode::UNAUTHORIZED);
                 }
             }
         }
     };
 
-    if !validator.validate(&api_key) {
+    let identity = match validator.policy(&api_key) {
+        Some(policy) => {
+            let key_hash = match &policy.usage_hash {
+                Some(hash) => ClientApiKeyHash(hash.clone()),
+                None => ClientApiKeyHash::from_api_key(&api_key),
+            };
+            Some((key_hash, policy))
+        }
+        None => validator.jwt_identity(&api_key),
+    };
+    let Some((key_hash, mut policy)) = identity else {
+        if is_public {
+            return Ok(next.run(anonymous(request)).await);
+        }
         warn!(api_key = %mask_key(&api_key), "Invalid API key");
+        validator.record_failure(&request, Some(&api_key), "invalid_key");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    if policy.is_expired() {
+        if is_public {
+            return Ok(next.run(anonymous(request)).await);
+        }
+        warn!(api_key = %mask_key(&api_key), "Expired API key");
+        validator.record_failure(&request, Some(&api_key), "expired_key");
         return Err(StatusCode::UNAUTHORIZED);
     }
 
-    request
-        .extensions_mut()
-        .insert(ClientApiKeyHash::from_api_key(&api_key));
+    if !policy.allows_ip(client_ip) {
+        warn!(
+            api_key = %mask_key(&api_key),
+            client_ip = ?client_ip,
+            "API key used from a disallowed address"
+        );
+        validator.record_failure(&request, Some(&api_key), "address_not_allowed");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if let Some(account) = request.headers().get(ACCOUNT_PIN_HEADER) {
+        if !policy.account_pinning {
+            warn!(api_key = %mask_key(&api_key), "API key may not pin accounts");
+            return Err(StatusCode::FORBIDDEN);
+        }
+        let account = account.to_str().map_err(|_| StatusCode::BAD_REQUEST)?;
+        policy.pinned_account = Some(account.to_string());
+    }
+
+    request.extensions_mut().insert(key_hash);
+    request.extensions_mut().insert(policy.profile);
+    request.extensions_mut().insert(policy);
 
     Ok(next.run(request).await)
 }
 
+/// Rejects keys not allowed on `platform`. Layered on each platform's
+/// routes, so it runs after [`auth_middleware`] has attached the policy.
+pub async fn platform_access_middleware(
+    State(platform): State<Platform>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let allowed = request
+        .extensions()
+        .get::<ApiKeyPolicy>()
+        .is_none_or(|policy| policy.allows_platform(platform));
+    if !allowed {
+        warn!(platform = %platform, "Platform not allowed for API key");
+        let body = serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "permission_error",
+                "message": format!("This API key is not allowed to use {} routes", platform)
+            }
+        });
+        return (StatusCode::FORBIDDEN, Json(body)).into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Rejects keys without `scope`. Layered on each route family, so it runs
+/// after [`auth_middleware`] has attached the policy.
+pub async fn scope_middleware(
+    State(scope): State<KeyScope>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Open relays and public paths let anyone in anonymously, but never to
+    // the admin routes
+    let anonymous = request
+        .extensions()
+        .get::<ClientApiKeyHash>()
+        .is_some_and(ClientApiKeyHash::is_anonymous);
+    if scope == KeyScope::Admin && anonymous {
+        warn!(path = %request.uri().path(), "Anonymous request to an admin route");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let allowed = request
+        .extensions()
+        .get::<ApiKeyPolicy>()
+        .is_none_or(|policy| policy.allows_scope(scope));
+    if !allowed {
+        warn!(scope = %scope, path = %request.uri().path(), "Scope not granted to API key");
+        let body = serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "permission_error",
+                "message": format!("This API key does not have the {} scope", scope)
+            }
+        });
+        return (StatusCode::FORBIDDEN, Json(body)).into_response();
+    }
+
+    next.run(request).await
+}
+
+fn anonymous(mut request: Request) -> Request {
+    request.extensions_mut().insert(ClientApiKeyHash::anonymous());
+    request.extensions_mut().insert(ClientProfile::default());
+    request.extensions_mut().insert(ApiKeyPolicy::default());
+    request
+}
+
+fn certified(mut request: Request, certificate: ClientCertificate) -> Request {
+    request
+        .extensions_mut()
+        .insert(ClientApiKeyHash(certificate.fingerprint));
+    request.extensions_mut().insert(ClientProfile::default());
+    request.extensions_mut().insert(ApiKeyPolicy {
+        scopes: DEFAULT_SCOPES.to_vec(),
+        ..Default::default()
+    });
+    request
+}
+
 fn mask_key(key: &str) -> String {
     if key.len() <= 8 {
         return "***".to_string();
@@ -119,12 +612,566 @@ mod tests {
         assert!(hash.0.chars().all(|c| c.is_ascii_hexdigit()));
     }
 
+    #[test]
+    fn test_hashed_configured_key() {
+        let digest = ClientApiKeyHash::from_api_key("cli-key").0;
+        let hashed = format!("sha256:{}", digest.to_ascii_uppercase());
+        assert_eq!(ClientApiKeyHash::from_configured_key(&hashed).0, digest);
+        assert_eq!(ClientApiKeyHash::from_configured_key("cli-key").0, digest);
+
+        let validator = ApiKeyValidator::new(vec![
+            (hashed, ApiKeyPolicy::default()),
+            ("plain-key".to_string(), ApiKeyPolicy::default()),
+        ]);
+        assert!(validator.policy("cli-key").is_some());
+        assert!(validator.policy("plain-key").is_some());
+        assert!(validator.policy(&format!("sha256:{}", digest)).is_none());
+    }
+
     #[test]
     fn test_anonymous_hash() {
         let hash = ClientApiKeyHash::anonymous();
         assert_eq!(hash.0, "anonymous");
     }
 
+    #[test]
+    fn test_validator_policy_lookup() {
+        let validator = ApiKeyValidator::new(vec![
+            ("cli-key".to_string(), ApiKeyPolicy::default()),
+            (
+                "sdk-key".to_string(),
+                ApiKeyPolicy {
+                    profile: ClientProfile::AgentSdk,
+                    max_tokens_per_day: Some(1000),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let sdk = validator.policy("sdk-key").unwrap();
+        assert_eq!(sdk.profile, ClientProfile::AgentSdk);
+        assert_eq!(sdk.max_tokens_per_day, Some(1000));
+        let cli = validator.policy("cli-key").unwrap();
+        assert_eq!(cli.profile, ClientProfile::ClaudeCode);
+        assert_eq!(cli.max_tokens_per_day, None);
+        assert!(validator.policy("unknown").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_public_paths_skip_authentication() {
+        use axum::{body::Body, middleware, routing::get, Extension, Router};
+        use tower::ServiceExt;
+
+        let validator = Arc::new(
+            ApiKeyValidator::new(vec![("cli-key".to_string(), ApiKeyPolicy::default())])
+                .with_public_paths(["/v1/models".to_string()]),
+        );
+        let app = Router::new()
+            .route(
+                "/v1/models",
+                get(|Extension(hash): Extension<ClientApiKeyHash>| async move { hash.0 }),
+            )
+            .route("/v1/messages", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |path: &str, key: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri(path);
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = send("/v1/models", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"anonymous");
+
+        let response = send("/v1/models", Some("cli-key")).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.len(), 64);
+
+        let response = send("/v1/models", Some("wrong-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send("/v1/messages", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_routes() {
+        use axum::{body::Body, middleware, routing::get, Router};
+        use tower::ServiceExt;
+
+        let routes = Some(vec!["/gemini/*".to_string()]);
+        let app = |keys: Vec<(String, ApiKeyPolicy)>| {
+            let validator =
+                Arc::new(ApiKeyValidator::new(keys).with_anonymous_routes(routes.clone()));
+            Router::new()
+                .route("/gemini/v1/models", get(|| async { "ok" }))
+                .route("/v1/messages", get(|| async { "ok" }))
+                .layer(middleware::from_fn_with_state(validator, auth_middleware))
+        };
+        let status = |app: Router, path: &'static str, key: Option<&'static str>| async move {
+            let mut request = axum::http::Request::builder().uri(path);
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let request = request.body(Body::empty()).unwrap();
+            app.oneshot(request).await.unwrap().status()
+        };
+
+        // Without any keys, only the anonymous routes are served
+        let keyless = app(Vec::new());
+        assert_eq!(
+            status(keyless.clone(), "/gemini/v1/models", None).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(keyless, "/v1/messages", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let keyed = app(vec![("cli-key".to_string(), ApiKeyPolicy::default())]);
+        assert_eq!(
+            status(keyed.clone(), "/gemini/v1/models", None).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(keyed.clone(), "/v1/messages", Some("cli-key")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(keyed, "/v1/messages", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_account_pin_requires_permission() {
+        use axum::{body::Body, middleware, routing::get, Extension, Router};
+        use tower::ServiceExt;
+
+        let pinning = ApiKeyPolicy {
+            account_pinning: true,
+            ..Default::default()
+        };
+        let validator = Arc::new(ApiKeyValidator::new(vec![
+            ("cli-key".to_string(), ApiKeyPolicy::default()),
+            ("ops-key".to_string(), pinning),
+        ]));
+        let app = Router::new()
+            .route(
+                "/v1/messages",
+                get(|Extension(policy): Extension<ApiKeyPolicy>| async move {
+                    policy.pinned_account.unwrap_or_default()
+                }),
+            )
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |key: &str| {
+            let request = axum::http::Request::builder()
+                .uri("/v1/messages")
+                .header("x-api-key", key)
+                .header(ACCOUNT_PIN_HEADER, "claude-1");
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = send("cli-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = send("ops-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"claude-1");
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_replaces_api_key() {
+        use axum::{body::Body, middleware, routing::get, Extension, Router};
+        use tower::ServiceExt;
+
+        let validator = Arc::new(ApiKeyValidator::new(vec![(
+            "cli-key".to_string(),
+            ApiKeyPolicy::default(),
+        )]));
+        let app = Router::new()
+            .route("/admin/keys", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(
+                KeyScope::Admin,
+                scope_middleware,
+            ))
+            .route(
+                "/v1/messages",
+                get(|Extension(hash): Extension<ClientApiKeyHash>| async move { hash.0 }),
+            )
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let certificate = ClientCertificate::from_der(b"billing-service");
+        let send_to = |path: &'static str, cert: Option<ClientCertificate>, key: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri(path);
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let mut request = request.body(Body::empty()).unwrap();
+            if let Some(cert) = cert {
+                request.extensions_mut().insert(cert);
+            }
+            app.clone().oneshot(request)
+        };
+        let send = |certificate, key| send_to("/v1/messages", certificate, key);
+
+        let response = send(None, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(Some(certificate.clone()), None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], certificate.fingerprint.as_bytes());
+        let response = send_to("/admin/keys", Some(certificate.clone()), None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // A key sent alongside the certificate must still be valid
+        let response = send(Some(certificate.clone()), Some("wrong-key"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send(Some(certificate), Some("cli-key")).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let key_hash = ClientApiKeyHash::from_api_key("cli-key");
+        assert_eq!(&body[..], key_hash.0.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_jwt_replaces_api_key() {
+        use crate::config::{JwtConfig, JwtKeyConfig};
+        use axum::{body::Body, middleware, routing::get, Extension, Router};
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+        use tower::ServiceExt;
+
+        let secret = "a-shared-secret-of-at-least-32-bytes";
+        let jwt = JwtVerifier::new(&JwtConfig {
+            audience: Some("claude-relay".to_string()),
+            keys: vec![JwtKeyConfig::HS256 {
+                kid: None,
+                secret: secret.to_string(),
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+        let validator = Arc::new(
+            ApiKeyValidator::new(vec![("cli-key".to_string(), ApiKeyPolicy::default())])
+                .with_jwt(jwt),
+        );
+        let app = Router::new()
+            .route("/admin/keys", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(
+                KeyScope::Admin,
+                scope_middleware,
+            ))
+            .route(
+                "/v1/messages",
+                get(|Extension(hash): Extension<ClientApiKeyHash>| async move { hash.0 }),
+            )
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let token = |audience: &str| {
+            let claims = serde_json::json!({
+                "sub": "svc-reports",
+                "aud": audience,
+                "exp": Utc::now().timestamp() + 300,
+            });
+            let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+            let message = format!("{}.{}", header, URL_SAFE_NO_PAD.encode(claims.to_string()));
+            let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+            let signature = ring::hmac::sign(&key, message.as_bytes());
+            format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature))
+        };
+        let send_to = |path: &'static str, bearer: String| {
+            let request = axum::http::Request::builder()
+                .uri(path)
+                .header("authorization", format!("Bearer {}", bearer))
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let send = |bearer| send_to("/v1/messages", bearer);
+
+        let response = send(token("claude-relay")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"jwt:svc-reports");
+        let response = send_to("/admin/keys", token("claude-relay")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = send(token("another-service")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send("cli-key".to_string()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_expired_key_is_rejected() {
+        use axum::{body::Body, middleware, routing::get, Router};
+        use tower::ServiceExt;
+
+        let policy = |days: i64| ApiKeyPolicy {
+            expires_at: Some(Utc::now() + chrono::Duration::days(days)),
+            ..Default::default()
+        };
+        let validator = Arc::new(ApiKeyValidator::new(vec![
+            ("trial-key".to_string(), policy(7)),
+            ("expired-key".to_string(), policy(-1)),
+        ]));
+        let app = Router::new()
+            .route("/v1/messages", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |key: &str| {
+            let request = axum::http::Request::builder()
+                .uri("/v1/messages")
+                .header("x-api-key", key);
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = send("trial-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("expired-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_ban_address() {
+        use crate::config::AuthThrottleConfig;
+        use axum::{body::Body, middleware, routing::get, Router};
+        use tower::ServiceExt;
+
+        let throttle = AuthThrottle::new(&AuthThrottleConfig {
+            max_failures: Some(2),
+            ..Default::default()
+        });
+        let validator = Arc::new(
+            ApiKeyValidator::new(vec![("cli-key".to_string(), ApiKeyPolicy::default())])
+                .with_throttle(throttle),
+        );
+        let app = Router::new()
+            .route("/v1/messages", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |ip: &str, key: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri("/v1/messages");
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let mut request = request.body(Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(ClientIp(ip.parse().unwrap()));
+            app.clone().oneshot(request)
+        };
+
+        // Requests without a key do not count
+        for _ in 0..3 {
+            let response = send("203.0.113.9", None).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        for _ in 0..2 {
+            let response = send("203.0.113.9", Some("guess")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // Banned, even with a valid key; other addresses are unaffected
+        let response = send("203.0.113.9", Some("cli-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "900");
+        let response = send("198.51.100.1", Some("cli-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_platform_access() {
+        use axum::{body::Body, middleware, routing::get, Router};
+        use tower::ServiceExt;
+
+        let claude_only = ApiKeyPolicy {
+            allowed_platforms: vec![Platform::Claude],
+            ..Default::default()
+        };
+        let validator = Arc::new(ApiKeyValidator::new(vec![
+            ("cli-key".to_string(), ApiKeyPolicy::default()),
+            ("claude-key".to_string(), claude_only),
+        ]));
+        let tagged = |path: &str, platform: Platform| {
+            Router::new()
+                .route(path, get(|| async { "ok" }))
+                .route_layer(middleware::from_fn_with_state(
+                    platform,
+                    platform_access_middleware,
+                ))
+        };
+        let app = Router::new()
+            .merge(tagged("/v1/messages", Platform::Claude))
+            .merge(tagged("/v1beta/models", Platform::Gemini))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |path: &str, key: &str| {
+            let request = axum::http::Request::builder()
+                .uri(path)
+                .header("x-api-key", key);
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = send("/v1/messages", "claude-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("/v1beta/models", "claude-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send("/v1beta/models", "cli-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_scopes() {
+        use axum::{body::Body, middleware, routing::get, Router};
+        use tower::ServiceExt;
+
+        let usage_only = ApiKeyPolicy {
+            scopes: vec![KeyScope::Usage],
+            ..Default::default()
+        };
+        let relay_only = ApiKeyPolicy {
+            scopes: vec![KeyScope::Relay],
+            ..Default::default()
+        };
+        let admin = ApiKeyPolicy {
+            scopes: vec![KeyScope::Admin],
+            ..Default::default()
+        };
+        let validator = Arc::new(ApiKeyValidator::new(vec![
+            ("cli-key".to_string(), ApiKeyPolicy::default()),
+            ("billing-key".to_string(), usage_only),
+            ("relay-key".to_string(), relay_only),
+            ("admin-key".to_string(), admin),
+        ]));
+        let scoped = |path: &str, scope: KeyScope| {
+            Router::new()
+                .route(path, get(|| async { "ok" }))
+                .route_layer(middleware::from_fn_with_state(scope, scope_middleware))
+        };
+        let app = Router::new()
+            .merge(scoped("/v1/messages", KeyScope::Relay))
+            .merge(scoped("/usage/keys", KeyScope::Usage))
+            .merge(scoped("/admin/keys", KeyScope::Admin))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let status = |path: &'static str, key: &'static str| {
+            let request = axum::http::Request::builder()
+                .uri(path)
+                .header("x-api-key", key);
+            let response = app.clone().oneshot(request.body(Body::empty()).unwrap());
+            async { response.await.unwrap().status() }
+        };
+
+        assert_eq!(status("/usage/keys", "billing-key").await, StatusCode::OK);
+        assert_eq!(
+            status("/v1/messages", "billing-key").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(status("/v1/messages", "relay-key").await, StatusCode::OK);
+        assert_eq!(
+            status("/usage/keys", "relay-key").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status("/admin/keys", "relay-key").await,
+            StatusCode::FORBIDDEN
+        );
+        // Keys without scopes may relay and read usage, but admin must be
+        // granted explicitly
+        assert_eq!(status("/v1/messages", "cli-key").await, StatusCode::OK);
+        assert_eq!(status("/usage/keys", "cli-key").await, StatusCode::OK);
+        assert_eq!(
+            status("/admin/keys", "cli-key").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(status("/admin/keys", "admin-key").await, StatusCode::OK);
+        assert_eq!(
+            status("/v1/messages", "admin-key").await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_reject_anonymous_requests() {
+        use axum::{body::Body, middleware, routing::post, Router};
+        use tower::ServiceExt;
+
+        let app = |validator: ApiKeyValidator| {
+            Router::new()
+                .route("/admin/keys", post(|| async { "created" }))
+                .route_layer(middleware::from_fn_with_state(
+                    KeyScope::Admin,
+                    scope_middleware,
+                ))
+                .route("/v1/messages", post(|| async { "ok" }))
+                .layer(middleware::from_fn_with_state(
+                    Arc::new(validator),
+                    auth_middleware,
+                ))
+        };
+        let post = |app: Router, path: &'static str| async move {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri(path)
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(request).await.unwrap().status()
+        };
+
+        // Without any keys the relay is open, but issuing the first key is not
+        let open = app(ApiKeyValidator::new(vec![]));
+        assert_eq!(post(open.clone(), "/v1/messages").await, StatusCode::OK);
+        assert_eq!(post(open, "/admin/keys").await, StatusCode::UNAUTHORIZED);
+
+        let keys = vec![("cli-key".to_string(), ApiKeyPolicy::default())];
+        let routes = Some(vec!["/admin/keys".to_string()]);
+        let public = app(ApiKeyValidator::new(keys).with_anonymous_routes(routes));
+        assert_eq!(post(public, "/admin/keys").await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_allowed_models() {
+        let policy = ApiKeyPolicy {
+            allowed_models: vec![
+                "claude-3-5-haiku-*".to_string(),
+                "gpt-4o-mini".to_string(),
+                "gemini-*-flash*".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert!(policy.allows_model("claude-3-5-haiku-20241022"));
+        assert!(policy.allows_model("gpt-4o-mini"));
+        assert!(policy.allows_model("gemini-2.0-flash-lite"));
+        assert!(!policy.allows_model("claude-sonnet-4-20250514"));
+        assert!(!policy.allows_model("gpt-4o-mini-2024"));
+        assert!(!policy.allows_model("gemini-flash"));
+        assert!(ApiKeyPolicy::default().allows_model("claude-opus-4-1"));
+    }
+
     #[test]
     fn test_mask_key_short() {
         assert_eq!(mask_key("12345678"), "***");
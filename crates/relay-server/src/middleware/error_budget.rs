@@ -0,0 +1,26 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use super::RequestContext;
+use crate::error_budget::{ErrorBudgetTracker, Outcome};
+
+/// Feeds the final status of each relayed request into the account's error
+/// budget. Must run inside `request_log_middleware`.
+pub async fn error_budget_middleware(
+    State(tracker): State<Arc<ErrorBudgetTracker>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let context = request.extensions().get::<RequestContext>().cloned();
+    let response = next.run(request).await;
+
+    if let Some(account_id) = context.and_then(|c| c.account_id()) {
+        tracker.record(&account_id, Outcome::from_response(&response));
+    }
+
+    response
+}
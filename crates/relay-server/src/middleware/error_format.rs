@@ -0,0 +1,190 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::{json, Value};
+
+/// The error body shape of a non-Anthropic family of routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// `{"error": {"message", "type", "param", "code"}}`
+    OpenAI,
+    /// `{"error": {"code", "message", "status"}}`
+    Gemini,
+}
+
+impl ErrorFormat {
+    /// Re-shapes the `error` object of an Anthropic error body sent with
+    /// `status`.
+    fn body(self, status: StatusCode, error: &Value) -> Value {
+        let message = error["message"].as_str().unwrap_or_default();
+        let error_type = error["type"].as_str().unwrap_or("api_error");
+        match self {
+            ErrorFormat::OpenAI => {
+                let (error_type, code) = match status {
+                    StatusCode::TOO_MANY_REQUESTS => ("requests", "rate_limit_exceeded"),
+                    s if s.is_server_error() => ("server_error", error_type),
+                    _ => ("invalid_request_error", error_type),
+                };
+                json!({
+                    "error": {
+                        "message": message,
+                        "type": error_type,
+                        "param": null,
+                        "code": code
+                    }
+                })
+            }
+            ErrorFormat::Gemini => json!({
+                "error": {
+                    "code": status.as_u16(),
+                    "message": message,
+                    "status": google_status(status)
+                }
+            }),
+        }
+    }
+}
+
+/// The `google.rpc.Code` name Google APIs report for an HTTP status.
+fn google_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::PAYLOAD_TOO_LARGE => "INVALID_ARGUMENT",
+        StatusCode::UNAUTHORIZED => "UNAUTHENTICATED",
+        StatusCode::FORBIDDEN => "PERMISSION_DENIED",
+        StatusCode::NOT_FOUND => "NOT_FOUND",
+        StatusCode::CONFLICT => "ABORTED",
+        StatusCode::TOO_MANY_REQUESTS => "RESOURCE_EXHAUSTED",
+        StatusCode::NOT_IMPLEMENTED => "UNIMPLEMENTED",
+        StatusCode::SERVICE_UNAVAILABLE => "UNAVAILABLE",
+        StatusCode::GATEWAY_TIMEOUT => "DEADLINE_EXCEEDED",
+        s if s.is_client_error() => "FAILED_PRECONDITION",
+        _ => "INTERNAL",
+    }
+}
+
+/// Rewrites the Anthropic-style error bodies the relay itself produces,
+/// `{"type": "error", "error": {...}}`, into the shape of the protocol the
+/// route speaks, so OpenAI and Gemini SDKs can parse them. Upstream errors
+/// passed through in their own shape are left alone.
+pub async fn error_format_middleware(
+    State(format): State<ErrorFormat>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let error = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .filter(|body| body["type"] == "error" && body["error"].is_object());
+    let Some(error) = error else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let body = format.body(parts.status, &error["error"]);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, response::IntoResponse, routing::get, Json, Router};
+    use tower::ServiceExt;
+
+    fn app(format: ErrorFormat) -> Router {
+        Router::new()
+            .route(
+                "/limited",
+                get(|| async {
+                    let body = json!({
+                        "type": "error",
+                        "error": {"type": "rate_limit_error", "message": "Slow down"}
+                    });
+                    (StatusCode::TOO_MANY_REQUESTS, Json(body))
+                }),
+            )
+            .route(
+                "/upstream",
+                get(|| async {
+                    let body =
+                        json!({"error": {"message": "Bad key", "type": "invalid_request_error"}});
+                    (StatusCode::UNAUTHORIZED, Json(body)).into_response()
+                }),
+            )
+            .route("/ok", get(|| async { Json(json!({"type": "error"})) }))
+            .route_layer(middleware::from_fn_with_state(
+                format,
+                error_format_middleware,
+            ))
+    }
+
+    async fn get_json(app: Router, path: &str) -> (StatusCode, Value) {
+        let request = axum::http::Request::builder()
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_openai_error_body() {
+        let (status, body) = get_json(app(ErrorFormat::OpenAI), "/limited").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            body,
+            json!({"error": {
+                "message": "Slow down",
+                "type": "requests",
+                "param": null,
+                "code": "rate_limit_exceeded"
+            }})
+        );
+
+        let (_, body) = get_json(app(ErrorFormat::OpenAI), "/upstream").await;
+        assert_eq!(body["error"]["message"], "Bad key");
+        let (_, body) = get_json(app(ErrorFormat::OpenAI), "/ok").await;
+        assert_eq!(body, json!({"type": "error"}));
+    }
+
+    #[tokio::test]
+    async fn test_gemini_error_body() {
+        let (status, body) = get_json(app(ErrorFormat::Gemini), "/limited").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            body,
+            json!({"error": {"code": 429, "message": "Slow down", "status": "RESOURCE_EXHAUSTED"}})
+        );
+        assert_eq!(
+            ErrorFormat::Gemini.body(StatusCode::BAD_REQUEST, &json!({}))["error"]["status"],
+            "INVALID_ARGUMENT"
+        );
+        assert_eq!(
+            ErrorFormat::OpenAI.body(
+                StatusCode::GATEWAY_TIMEOUT,
+                &json!({"type": "api_error", "message": "Timed out"})
+            )["error"]["type"],
+            "server_error"
+        );
+    }
+}
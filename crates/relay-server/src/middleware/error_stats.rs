@@ -0,0 +1,28 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use super::RequestContext;
+use crate::error_budget::UpstreamTimeout;
+use crate::error_stats::ErrorStats;
+
+/// Counts the final status of each relayed request per account. Must run
+/// inside `request_log_middleware`.
+pub async fn error_stats_middleware(
+    State(stats): State<Arc<ErrorStats>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let context = request.extensions().get::<RequestContext>().cloned();
+    let response = next.run(request).await;
+
+    if let Some(account_id) = context.and_then(|c| c.account_id()) {
+        let timed_out = response.extensions().get::<UpstreamTimeout>().is_some();
+        stats.record(&account_id, response.status().as_u16(), timed_out);
+    }
+
+    response
+}
@@ -0,0 +1,111 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::ip_filter::IpFilter;
+
+/// The client's address, resolved through trusted proxies; inserted into
+/// request extensions by [`ip_filter_middleware`].
+#[derive(Clone, Copy, Debug)]
+pub struct ClientIp(pub IpAddr);
+
+/// Resolves the client address and rejects it with 403 when `[ip_filter]`
+/// does not permit it. Runs outside `auth_middleware`, so filtered clients
+/// never reach key validation.
+pub async fn ip_filter_middleware(
+    State(filter): State<Arc<IpFilter>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let client_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| filter.client_ip(info.0.ip(), request.headers()));
+
+    if !filter.permits(client_ip) {
+        warn!(client_ip = ?client_ip, "Client address not allowed");
+        let body = serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "permission_error",
+                "message": "Requests from this address are not allowed"
+            }
+        });
+        return (StatusCode::FORBIDDEN, Json(body)).into_response();
+    }
+
+    if let Some(ip) = client_ip {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IpFilterConfig;
+    use crate::middleware::{auth_middleware, ApiKeyPolicy, ApiKeyValidator};
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_ip_filter_runs_before_auth() {
+        let filter = Arc::new(IpFilter::new(&IpFilterConfig {
+            deny: vec!["192.0.2.0/24".parse().unwrap()],
+            trusted_proxies: vec!["127.0.0.1".parse().unwrap()],
+            ..Default::default()
+        }));
+        let office = ApiKeyPolicy {
+            allowed_ips: vec!["203.0.113.0/24".parse().unwrap()],
+            ..Default::default()
+        };
+        let validator = Arc::new(ApiKeyValidator::new(vec![(
+            "office-key".to_string(),
+            office,
+        )]));
+        let app = Router::new()
+            .route("/v1/messages", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware))
+            .layer(middleware::from_fn_with_state(filter, ip_filter_middleware));
+
+        let send = |peer: &str, forwarded_for: Option<&str>, key: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri("/v1/messages");
+            if let Some(forwarded_for) = forwarded_for {
+                request = request.header("x-forwarded-for", forwarded_for);
+            }
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let mut request = request.body(Body::empty()).unwrap();
+            let peer = SocketAddr::new(peer.parse().unwrap(), 40000);
+            request.extensions_mut().insert(ConnectInfo(peer));
+            app.clone().oneshot(request)
+        };
+
+        // Denied before the missing key is noticed
+        let response = send("192.0.2.1", None, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send("127.0.0.1", Some("192.0.2.1"), None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send("203.0.113.5", None, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // The key is bound to the office range, also when proxied
+        let key = Some("office-key");
+        let response = send("203.0.113.5", None, key).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("127.0.0.1", Some("203.0.113.5"), key).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("198.51.100.1", Some("203.0.113.5"), key)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
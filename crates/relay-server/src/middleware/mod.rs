@@ -1,3 +1,26 @@
+mod admin_auth;
+mod audit;
 mod auth;
+mod error_budget;
+mod error_format;
+mod error_stats;
+mod ip_filter;
+mod rate_limit;
+mod request_log;
+mod selection_feedback;
+mod slow_request;
 
-pub use auth::{auth_middleware, ApiKeyValidator, ClientApiKeyHash};
+pub use admin_auth::{admin_auth_middleware, cookie, AdminAuth, AdminToken, ADMIN_SESSION_COOKIE};
+pub use audit::audit_middleware;
+pub use auth::{
+    auth_middleware, platform_access_middleware, scope_middleware, ApiKeyPolicy, ApiKeyValidator,
+    ClientApiKeyHash, ClientCertificate, KeyScope, HASHED_KEY_PREFIX,
+};
+pub use error_budget::error_budget_middleware;
+pub use error_format::{error_format_middleware, ErrorFormat};
+pub use error_stats::error_stats_middleware;
+pub use ip_filter::{ip_filter_middleware, ClientIp};
+pub use rate_limit::rate_limit_middleware;
+pub use request_log::{request_log_middleware, RequestContext};
+pub use selection_feedback::selection_feedback_middleware;
+pub use slow_request::slow_request_middleware;
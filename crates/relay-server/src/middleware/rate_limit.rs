@@ -0,0 +1,259 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use tracing::warn;
+
+use super::{ApiKeyPolicy, ClientApiKeyHash, ClientIp};
+use crate::rate_limit::{Quota, RateLimit, RateLimiter};
+
+/// The key's requests per minute.
+pub const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+/// Requests the key can make right away.
+pub const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+/// Seconds until the key's bucket is full again.
+pub const RATE_LIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Answers 429 with `Retry-After` once a key has used up its request rate,
+/// and 429 while a key already has its maximum of requests in flight.
+/// Responses to rate limited keys carry `x-ratelimit-*` headers, so clients
+/// can pace themselves. Anonymous requests share a bucket per client
+/// address at the default rate, without a concurrency limit. Must run
+/// inside `auth_middleware`.
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let key = request
+        .extensions()
+        .get::<ClientApiKeyHash>()
+        .and_then(|hash| {
+            if !hash.is_anonymous() {
+                return Some(hash.0.clone());
+            }
+            let ip = request.extensions().get::<ClientIp>()?;
+            Some(format!("ip:{}", ip.0))
+        });
+    let policy = request.extensions().get::<ApiKeyPolicy>();
+    let own = policy.and_then(|policy| policy.rate_limit);
+    let own_max_concurrent = policy.and_then(|policy| policy.max_concurrent_requests);
+
+    let mut in_flight = None;
+    let max_concurrent = limiter.max_concurrent_for(own_max_concurrent);
+    if let (Some(key), Some(max)) = (key.as_deref(), max_concurrent) {
+        if !key.starts_with("ip:") {
+            in_flight = limiter.enter(key, max);
+            if in_flight.is_none() {
+                warn!(max_concurrent = max, "API key concurrency limit reached");
+                let body = serde_json::json!({
+                    "type": "error",
+                    "error": {
+                        "type": "rate_limit_error",
+                        "message": format!(
+                            "This API key already has {} requests in progress. Retry once one completes.",
+                            max
+                        )
+                    }
+                });
+                return (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response();
+            }
+        }
+    }
+
+    let mut rate = None;
+    if let (Some(key), Some(limit)) = (key, limiter.limit_for(own)) {
+        let quota = limiter.acquire(&key, limit);
+        rate = Some((limit, quota.unwrap_or_else(|(quota, _)| quota)));
+        if let Err((quota, wait)) = quota {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            warn!(retry_after = retry_after, "API key rate limit reached");
+            let client = if key.starts_with("ip:") {
+                "address"
+            } else {
+                "API key"
+            };
+            let body = serde_json::json!({
+                "type": "error",
+                "error": {
+                    "type": "rate_limit_error",
+                    "message": format!(
+                        "Rate limit of {} requests per minute reached for this {}. Retry after {} seconds.",
+                        limit.requests_per_minute, client, retry_after
+                    )
+                }
+            });
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(body),
+            )
+                .into_response();
+            insert_rate_limit_headers(response.headers_mut(), limit, quota);
+            return response;
+        }
+    }
+
+    let mut response = next.run(request).await;
+    if let Some((limit, quota)) = rate {
+        insert_rate_limit_headers(response.headers_mut(), limit, quota);
+    }
+    let Some(in_flight) = in_flight else {
+        return response;
+    };
+
+    // Streamed responses hold the slot until the body is done
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _ = &in_flight;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, limit: RateLimit, quota: Quota) {
+    let reset = quota.reset.as_secs_f64().ceil() as u64;
+    headers.insert(RATE_LIMIT_LIMIT, limit.requests_per_minute.into());
+    headers.insert(RATE_LIMIT_REMAINING, quota.remaining.into());
+    headers.insert(RATE_LIMIT_RESET, reset.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{auth_middleware, ApiKeyValidator};
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_rate_limited_key_gets_retry_after() {
+        let limited = ApiKeyPolicy {
+            rate_limit: RateLimit::new(Some(1), Some(1)),
+            ..Default::default()
+        };
+        let validator = Arc::new(
+            ApiKeyValidator::new(vec![
+                ("cli-key".to_string(), ApiKeyPolicy::default()),
+                ("limited-key".to_string(), limited),
+            ])
+            .with_public_paths(["/v1/models".to_string()]),
+        );
+        let limiter = Arc::new(RateLimiter::new(None));
+        let app = Router::new()
+            .route("/v1/models", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                limiter,
+                rate_limit_middleware,
+            ))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |key: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri("/v1/models");
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = send(Some("limited-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATE_LIMIT_LIMIT], "1");
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING], "0");
+        assert_eq!(response.headers()[RATE_LIMIT_RESET], "60");
+        let response = send(Some("limited-key")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "60");
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING], "0");
+
+        for _ in 0..3 {
+            let response = send(Some("cli-key")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(!response.headers().contains_key(RATE_LIMIT_LIMIT));
+            let response = send(None).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_requests_limited_per_address() {
+        let validator = Arc::new(ApiKeyValidator::new(Vec::new()));
+        let limiter = Arc::new(RateLimiter::new(RateLimit::new(Some(1), Some(1))));
+        let app = Router::new()
+            .route("/v1/models", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                limiter,
+                rate_limit_middleware,
+            ))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |ip: &str| {
+            let mut request = axum::http::Request::builder()
+                .uri("/v1/models")
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ClientIp(ip.parse().unwrap()));
+            app.clone().oneshot(request)
+        };
+
+        let response = send("203.0.113.9").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("203.0.113.9").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = send("198.51.100.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_limited_until_stream_ends() {
+        let streaming = ApiKeyPolicy {
+            max_concurrent_requests: Some(1),
+            ..Default::default()
+        };
+        let validator = Arc::new(ApiKeyValidator::new(vec![
+            ("agent-key".to_string(), streaming),
+            ("cli-key".to_string(), ApiKeyPolicy::default()),
+        ]));
+        let limiter = Arc::new(RateLimiter::new(None));
+        let app = Router::new()
+            .route(
+                "/v1/messages",
+                get(|| async {
+                    let events = futures::stream::pending::<Result<String, std::io::Error>>();
+                    Body::from_stream(events)
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                limiter,
+                rate_limit_middleware,
+            ))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+
+        let send = |key: &str| {
+            let request = axum::http::Request::builder()
+                .uri("/v1/messages")
+                .header("x-api-key", key)
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let streaming = send("agent-key").await.unwrap();
+        assert_eq!(streaming.status(), StatusCode::OK);
+        let response = send("agent-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = send("cli-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        drop(streaming);
+        let response = send("agent-key").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
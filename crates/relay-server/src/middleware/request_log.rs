@@ -0,0 +1,294 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use relay_core::Platform;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+use super::ClientApiKeyHash;
+use crate::db::{self, DbPool, RequestLogEntry};
+
+#[derive(Default)]
+struct RequestInfo {
+    platform: Option<Platform>,
+    model: String,
+    account_id: Option<String>,
+    retry_count: u32,
+    streamed: bool,
+}
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Per-request details filled in by route handlers and written to
+/// `request_log` once the response has been fully sent.
+#[derive(Clone)]
+pub struct RequestContext {
+    request_id: Arc<str>,
+    info: Arc<Mutex<RequestInfo>>,
+    started: Instant,
+}
+
+impl RequestContext {
+    pub fn new() -> Self {
+        Self {
+            request_id: uuid::Uuid::new_v4().to_string().into(),
+            info: Arc::default(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    pub fn begin(&self, platform: Platform, model: &str, streamed: bool) {
+        let mut info = self.info.lock();
+        info.platform = Some(platform);
+        info.model = model.to_string();
+        info.streamed = streamed;
+    }
+
+    /// Records the account used by the current attempt; `attempt` is zero-based.
+    pub fn set_account(&self, account_id: &str, attempt: usize) {
+        let mut info = self.info.lock();
+        info.account_id = Some(account_id.to_string());
+        info.retry_count = attempt as u32;
+    }
+
+    /// `None` until a handler has called [`RequestContext::begin`].
+    pub fn model(&self) -> Option<String> {
+        let info = self.info.lock();
+        info.platform.map(|_| info.model.clone())
+    }
+
+    pub fn account_id(&self) -> Option<String> {
+        self.info.lock().account_id.clone()
+    }
+
+    pub fn is_streamed(&self) -> bool {
+        self.info.lock().streamed
+    }
+
+    pub fn platform(&self) -> Option<Platform> {
+        self.info.lock().platform
+    }
+
+    /// Time since the request reached the server.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the log entry when dropped, i.e. after the last body chunk was
+/// sent or the client went away.
+struct PendingLog {
+    pool: DbPool,
+    entry: Option<RequestLogEntry>,
+    started: Instant,
+}
+
+impl Drop for PendingLog {
+    fn drop(&mut self) {
+        let Some(mut entry) = self.entry.take() else {
+            return;
+        };
+        entry.duration_ms = self.started.elapsed().as_millis() as u64;
+
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = db::record_request(&pool, &entry).await {
+                error!(error = %e, "Failed to record request log");
+            }
+        });
+    }
+}
+
+pub async fn request_log_middleware(
+    State(pool): State<DbPool>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let path = request.uri().path().to_string();
+    let api_key_hash = request
+        .extensions()
+        .get::<ClientApiKeyHash>()
+        .cloned()
+        .unwrap_or_else(ClientApiKeyHash::anonymous);
+
+    let context = RequestContext::new();
+    request.extensions_mut().insert(context.clone());
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(context.request_id()) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    let entry = {
+        let info = context.info.lock();
+        // Only relayed requests populate the context
+        let Some(platform) = info.platform else {
+            return response;
+        };
+        RequestLogEntry {
+            request_id: context.request_id().to_string(),
+            client_api_key_hash: api_key_hash.0,
+            platform: platform.to_string(),
+            account_id: info.account_id.clone(),
+            model: info.model.clone(),
+            path,
+            status_code: response.status().as_u16(),
+            duration_ms: 0,
+            retry_count: info.retry_count,
+            streamed: info.streamed,
+        }
+    };
+
+    let streamed = entry.streamed;
+    let pending = PendingLog {
+        pool,
+        entry: Some(entry),
+        started,
+    };
+
+    if !streamed {
+        // Dropping `pending` here records the request
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _ = &pending;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, middleware, routing::get, Extension, Router};
+    use tower::ServiceExt;
+
+    async fn setup_test_db() -> DbPool {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    async fn failing(Extension(ctx): Extension<RequestContext>) -> StatusCode {
+        ctx.begin(Platform::Claude, "claude-sonnet-4-20250514", false);
+        ctx.set_account("acc2", 1);
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+
+    async fn streaming(Extension(ctx): Extension<RequestContext>) -> Body {
+        ctx.begin(Platform::Gemini, "gemini-2.5-pro", true);
+        ctx.set_account("gem1", 0);
+        let chunks: Vec<Result<&'static str, std::io::Error>> =
+            vec![Ok("data: a\n\n"), Ok("data: b\n\n")];
+        Body::from_stream(futures::stream::iter(chunks))
+    }
+
+    fn app(pool: DbPool) -> Router {
+        Router::new()
+            .route("/failing", get(failing))
+            .route("/streaming", get(streaming))
+            .route("/health", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(pool, request_log_middleware))
+    }
+
+    async fn logged_rows(pool: &DbPool) -> Vec<(String, Option<String>, i64, i64, bool)> {
+        // The entry is written from a spawned task
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        sqlx::query_as(
+            "SELECT platform, account_id, status_code, retry_count, streamed FROM request_log",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    fn get_request(uri: &str) -> Request {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_logs_status_and_retries() {
+        let pool = setup_test_db().await;
+
+        let response = app(pool.clone())
+            .oneshot(get_request("/failing"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let rows = logged_rows(&pool).await;
+        assert_eq!(
+            rows,
+            vec![(
+                "claude".to_string(),
+                Some("acc2".to_string()),
+                503,
+                1,
+                false
+            )]
+        );
+
+        let request_id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        let (logged_id,): (String,) = sqlx::query_as("SELECT request_id FROM request_log")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(logged_id, request_id);
+    }
+
+    #[tokio::test]
+    async fn test_logs_streamed_request_after_body_completes() {
+        let pool = setup_test_db().await;
+
+        let response = app(pool.clone())
+            .oneshot(get_request("/streaming"))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"data: a\n\ndata: b\n\n");
+
+        let rows = logged_rows(&pool).await;
+        assert_eq!(
+            rows,
+            vec![("gemini".to_string(), Some("gem1".to_string()), 200, 0, true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_skips_requests_without_context() {
+        let pool = setup_test_db().await;
+
+        app(pool.clone())
+            .oneshot(get_request("/health"))
+            .await
+            .unwrap();
+
+        assert!(logged_rows(&pool).await.is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use relay_core::RequestFeedback;
+use std::sync::Arc;
+
+use super::RequestContext;
+use crate::error_budget::Outcome;
+use crate::scheduler::UnifiedScheduler;
+
+/// Reports whether each relayed request succeeded, and how long the response
+/// headers took, to the scheduler: its selection policy learns from it, and
+/// a success ends the account's run of server errors. Must run inside
+/// `request_log_middleware`.
+pub async fn selection_feedback_middleware(
+    State(scheduler): State<Arc<UnifiedScheduler>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let context = request.extensions().get::<RequestContext>().cloned();
+    let response = next.run(request).await;
+
+    if let Some(context) = context {
+        if let Some(account_id) = context.account_id() {
+            scheduler.record_feedback(
+                &account_id,
+                &RequestFeedback {
+                    success: Outcome::from_response(&response) == Outcome::Success,
+                    latency: context.elapsed(),
+                },
+            );
+        }
+    }
+
+    response
+}
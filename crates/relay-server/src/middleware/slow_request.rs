@@ -0,0 +1,61 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+
+use super::RequestContext;
+use crate::slow_requests::SlowRequests;
+
+/// Checks the request against its threshold when dropped, i.e. once a
+/// streamed body was sent or the client went away.
+struct SlowCheck {
+    slow: Arc<SlowRequests>,
+    context: RequestContext,
+}
+
+impl Drop for SlowCheck {
+    fn drop(&mut self) {
+        let Some(account_id) = self.context.account_id() else {
+            return;
+        };
+        self.slow.record(
+            &account_id,
+            &self.context.model().unwrap_or_default(),
+            self.context.is_streamed(),
+            self.context.elapsed(),
+        );
+    }
+}
+
+/// Logs and counts relayed requests slower than the `[slow_requests]`
+/// thresholds; streams are timed until their body ends. Must run inside
+/// `request_log_middleware`.
+pub async fn slow_request_middleware(
+    State(slow): State<Arc<SlowRequests>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let context = request.extensions().get::<RequestContext>().cloned();
+    let response = next.run(request).await;
+    let Some(context) = context else {
+        return response;
+    };
+
+    let streamed = context.is_streamed();
+    let check = SlowCheck { slow, context };
+    if !streamed {
+        // Dropping `check` here times the request
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _ = &check;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
@@ -0,0 +1,212 @@
+use parking_lot::Mutex;
+use relay_claude::ClaudeRelay;
+use relay_core::AccountProvider;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::config::{AccountConfig, Config, VertexPlatform};
+
+/// Listed when no Claude account declares `models`.
+const DEFAULT_CLAUDE_MODELS: &[&str] = &[
+    "claude-opus-4-1-20250805",
+    "claude-opus-4-20250514",
+    "claude-sonnet-4-20250514",
+    "claude-3-7-sonnet-20250219",
+    "claude-3-5-haiku-20241022",
+];
+
+/// Listed when no Gemini account declares `models`.
+const DEFAULT_GEMINI_MODELS: &[&str] = &[
+    "gemini-2.5-pro",
+    "gemini-2.5-flash",
+    "gemini-2.5-flash-lite",
+];
+
+/// How long an upstream model list is reused.
+const UPSTREAM_TTL: Duration = Duration::from_secs(3600);
+
+/// Models served by the relay, as listed by the `models` endpoints: the
+/// `models` declared on each account, the `[model_map]` aliases and,
+/// optionally, the upstream list of a Claude API-key account.
+pub struct ModelCatalog {
+    claude: Vec<String>,
+    gemini: Vec<String>,
+    codex: Vec<String>,
+    aliases: Vec<String>,
+    upstream: Option<UpstreamModels>,
+}
+
+struct UpstreamModels {
+    relay: Arc<ClaudeRelay>,
+    account: Arc<dyn AccountProvider>,
+    cached: Mutex<Option<(Instant, Vec<String>)>>,
+}
+
+impl ModelCatalog {
+    pub fn new(config: &Config) -> Self {
+        let mut claude = Vec::new();
+        let mut gemini = Vec::new();
+        let mut codex = Vec::new();
+        for account in &config.accounts {
+            let models = match account {
+                AccountConfig::ClaudeOauth { .. }
+                | AccountConfig::ClaudeSession { .. }
+                | AccountConfig::ClaudeApi { .. }
+                | AccountConfig::Bedrock { .. }
+                | AccountConfig::Openrouter { .. }
+                | AccountConfig::OpenaiCompatible { .. }
+                | AccountConfig::Deepseek { .. } => &mut claude,
+                AccountConfig::Vertex { platform, .. } => match platform {
+                    VertexPlatform::Claude => &mut claude,
+                    VertexPlatform::Gemini => &mut gemini,
+                },
+                AccountConfig::Gemini { .. } => &mut gemini,
+                AccountConfig::OpenaiResponses { .. } | AccountConfig::AzureOpenai { .. } => {
+                    &mut codex
+                }
+            };
+            for model in account.models() {
+                if !models.contains(model) {
+                    models.push(model.clone());
+                }
+            }
+        }
+        if claude.is_empty() {
+            claude = DEFAULT_CLAUDE_MODELS
+                .iter()
+                .map(|m| m.to_string())
+                .collect();
+        }
+        if gemini.is_empty() {
+            gemini = DEFAULT_GEMINI_MODELS
+                .iter()
+                .map(|m| m.to_string())
+                .collect();
+        }
+
+        let mut aliases: Vec<String> = config.model_map.keys().cloned().collect();
+        aliases.sort();
+
+        Self {
+            claude,
+            gemini,
+            codex,
+            aliases,
+            upstream: None,
+        }
+    }
+
+    /// Lists Claude models from the account's `/v1/models` instead of the
+    /// configured ones.
+    pub fn with_upstream(
+        mut self,
+        relay: Arc<ClaudeRelay>,
+        account: Arc<dyn AccountProvider>,
+    ) -> Self {
+        self.upstream = Some(UpstreamModels {
+            relay,
+            account,
+            cached: Mutex::new(None),
+        });
+        self
+    }
+
+    /// The upstream list when one is configured and reachable, otherwise
+    /// the configured models.
+    pub async fn claude_models(&self) -> Vec<String> {
+        let Some(upstream) = &self.upstream else {
+            return self.claude.clone();
+        };
+        if let Some((fetched, models)) = upstream.cached.lock().as_ref() {
+            if fetched.elapsed() < UPSTREAM_TTL {
+                return models.clone();
+            }
+        }
+
+        match upstream.relay.list_models(upstream.account.as_ref()).await {
+            Ok(models) => {
+                *upstream.cached.lock() = Some((Instant::now(), models.clone()));
+                models
+            }
+            Err(e) => {
+                warn!(
+                    account_id = upstream.account.id(),
+                    error = %e,
+                    "Failed to list upstream models, using configured ones"
+                );
+                self.claude.clone()
+            }
+        }
+    }
+
+    pub fn gemini_models(&self) -> &[String] {
+        &self.gemini
+    }
+
+    pub fn codex_models(&self) -> &[String] {
+        &self.codex
+    }
+
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(&format!("[server]\nport = 3000\n{}", toml)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_declared_models_replace_defaults() {
+        let catalog = ModelCatalog::new(&config(
+            r#"
+[model_map]
+"gpt-4o" = "claude-sonnet-4-20250514"
+haiku = "claude-3-5-haiku-20241022"
+
+[[accounts]]
+type = "claude-api"
+id = "a"
+name = "A"
+api_key = "sk-ant-a"
+models = ["claude-sonnet-4-20250514"]
+
+[[accounts]]
+type = "claude-api"
+id = "b"
+name = "B"
+api_key = "sk-ant-b"
+models = ["claude-sonnet-4-20250514", "claude-opus-4-20250514"]
+
+[[accounts]]
+type = "openai-responses"
+id = "c"
+name = "C"
+api_key = "sk-c"
+models = ["gpt-5"]
+"#,
+        ));
+
+        assert_eq!(
+            catalog.claude_models().await,
+            ["claude-sonnet-4-20250514", "claude-opus-4-20250514"]
+        );
+        assert_eq!(catalog.gemini_models(), DEFAULT_GEMINI_MODELS);
+        assert_eq!(catalog.codex_models(), ["gpt-5"]);
+        assert_eq!(catalog.aliases(), ["gpt-4o", "haiku"]);
+    }
+
+    #[tokio::test]
+    async fn test_defaults_without_declared_models() {
+        let catalog = ModelCatalog::new(&config(""));
+
+        assert_eq!(catalog.claude_models().await, DEFAULT_CLAUDE_MODELS);
+        assert!(catalog.codex_models().is_empty());
+        assert!(catalog.aliases().is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Model aliases from `[model_map]`, resolved by every route before an
+/// account is selected. Names are matched exactly and resolved once, so a
+/// target is never looked up again.
+pub struct ModelMap {
+    aliases: HashMap<String, String>,
+}
+
+impl ModelMap {
+    pub fn new(aliases: &HashMap<String, String>) -> Self {
+        Self {
+            aliases: aliases.clone(),
+        }
+    }
+
+    /// Replaces an aliased `model` with its target.
+    pub fn apply(&self, model: &mut String) {
+        if let Some(target) = self.aliases.get(model.as_str()) {
+            debug!(alias = %model, model = %target, "Mapped model alias");
+            *model = target.clone();
+        }
+    }
+
+    /// Replaces an aliased string `model` field of a JSON request body.
+    pub fn apply_to_body(&self, body: &mut Value) {
+        if let Some(Value::String(model)) = body.get_mut("model") {
+            self.apply(model);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn model_map(aliases: &[(&str, &str)]) -> ModelMap {
+        ModelMap::new(
+            &aliases
+                .iter()
+                .map(|(alias, model)| (alias.to_string(), model.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_apply_matches_exact_names_only() {
+        let map = model_map(&[("gpt-4o", "claude-sonnet-4-20250514")]);
+
+        let mut model = "gpt-4o".to_string();
+        map.apply(&mut model);
+        assert_eq!(model, "claude-sonnet-4-20250514");
+
+        let mut model = "gpt-4o-mini".to_string();
+        map.apply(&mut model);
+        assert_eq!(model, "gpt-4o-mini");
+    }
+
+    #[test]
+    fn test_targets_are_not_resolved_again() {
+        let map = model_map(&[("a", "b"), ("b", "c")]);
+
+        let mut model = "a".to_string();
+        map.apply(&mut model);
+
+        assert_eq!(model, "b");
+    }
+
+    #[test]
+    fn test_apply_to_body() {
+        let map = model_map(&[("haiku", "claude-3-5-haiku-20241022")]);
+
+        let mut body = json!({"model": "haiku", "messages": []});
+        map.apply_to_body(&mut body);
+        assert_eq!(body["model"], "claude-3-5-haiku-20241022");
+
+        let mut body = json!({"messages": []});
+        map.apply_to_body(&mut body);
+        assert!(body.get("model").is_none());
+    }
+}
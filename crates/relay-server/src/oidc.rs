@@ -0,0 +1,361 @@
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::AdminOidcConfig;
+use crate::jwt::{JwtError, JwtVerifier};
+
+/// How long a login may stay at the provider before its state is dropped.
+pub const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Pending logins and sessions tracked before stale entries are swept.
+const MAX_TRACKED: usize = 10_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    #[error("OIDC provider request failed: {0}")]
+    Provider(String),
+    #[error("Unknown or expired login state")]
+    State,
+    #[error("Too many logins in progress, try again later")]
+    Busy,
+    #[error("Invalid ID token: {0}")]
+    IdToken(#[from] JwtError),
+    #[error("ID token nonce mismatch")]
+    Nonce,
+    #[error("ID token has no verified email")]
+    Email,
+    #[error("{0} may not use the admin routes")]
+    NotAllowed(String),
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(e: reqwest::Error) -> Self {
+        OidcError::Provider(e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// An administrator signed in through the provider.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminSession {
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+struct PendingLogin {
+    nonce: String,
+    started: Instant,
+}
+
+/// The authorization code flow against `[admin_oidc]`, and the sessions it
+/// opens. Endpoints and keys are looked up on every login, so rotated
+/// provider keys need no restart.
+pub struct OidcLogin {
+    config: AdminOidcConfig,
+    http: reqwest::Client,
+    /// `state` parameter → login waiting for its callback.
+    pending: Mutex<HashMap<String, PendingLogin>>,
+    /// SHA-256 of the session cookie → session.
+    sessions: Mutex<HashMap<String, AdminSession>>,
+}
+
+impl OidcLogin {
+    pub fn new(config: &AdminOidcConfig) -> Self {
+        Self {
+            config: config.clone(),
+            http: reqwest::Client::new(),
+            pending: Mutex::default(),
+            sessions: Mutex::default(),
+        }
+    }
+
+    pub fn session_ttl(&self) -> Duration {
+        Duration::from_secs(self.config.session_ttl_seconds)
+    }
+
+    /// Whether the session cookie should be `Secure`, i.e. the relay is
+    /// reached over HTTPS.
+    pub fn secure_cookie(&self) -> bool {
+        self.config.redirect_url.starts_with("https://")
+    }
+
+    /// Where to send the browser to sign in, along with the login state
+    /// the callback must come back with.
+    pub async fn authorization_url(&self) -> Result<(String, String), OidcError> {
+        let discovery = self.discover().await?;
+        let state = uuid::Uuid::new_v4().simple().to_string();
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let url = reqwest::Url::parse_with_params(
+            &discovery.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", &self.config.client_id),
+                ("redirect_uri", &self.config.redirect_url),
+                ("scope", "openid email"),
+                ("state", &state),
+                ("nonce", &nonce),
+            ],
+        )
+        .map_err(|e| OidcError::Provider(e.to_string()))?;
+
+        self.track_login(state.clone(), nonce, Instant::now())?;
+        Ok((url.into(), state))
+    }
+
+    /// Remembers a login until its callback. Anyone may start a login, so
+    /// new ones are refused while the map is still full of live ones.
+    fn track_login(&self, state: String, nonce: String, now: Instant) -> Result<(), OidcError> {
+        let mut pending = self.pending.lock();
+        if pending.len() >= MAX_TRACKED {
+            pending.retain(|_, login| now.duration_since(login.started) < LOGIN_TIMEOUT);
+            if pending.len() >= MAX_TRACKED {
+                return Err(OidcError::Busy);
+            }
+        }
+        pending.insert(
+            state,
+            PendingLogin {
+                nonce,
+                started: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Redeems the provider's callback for a session, returning the
+    /// session cookie value along with it.
+    pub async fn complete(
+        &self,
+        code: &str,
+        state: &str,
+    ) -> Result<(String, AdminSession), OidcError> {
+        let login = self.pending.lock().remove(state).ok_or(OidcError::State)?;
+        if login.started.elapsed() >= LOGIN_TIMEOUT {
+            return Err(OidcError::State);
+        }
+
+        let discovery = self.discover().await?;
+        let tokens: TokenResponse = self
+            .http
+            .post(&discovery.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.config.redirect_url),
+                ("client_id", &self.config.client_id),
+                ("client_secret", &self.config.client_secret),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let jwks: serde_json::Value = self
+            .http
+            .get(&discovery.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let verifier = JwtVerifier::from_jwks(&jwks, &self.config.issuer, &self.config.client_id);
+        let claims = verifier.verify_claims(&tokens.id_token)?;
+        let email = self.admit(&claims, &login.nonce)?;
+        Ok(self.start_session(email))
+    }
+
+    /// The session behind a cookie value, while it lasts.
+    pub fn session(&self, cookie: &str) -> Option<AdminSession> {
+        self.session_at(cookie, Utc::now())
+    }
+
+    pub fn end_session(&self, cookie: &str) {
+        self.sessions.lock().remove(&session_key(cookie));
+    }
+
+    /// Opens a session for `email`, returning its cookie value.
+    pub fn start_session(&self, email: String) -> (String, AdminSession) {
+        let now = Utc::now();
+        let cookie = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let session = AdminSession {
+            email,
+            expires_at: now + self.session_ttl(),
+        };
+
+        let mut sessions = self.sessions.lock();
+        if sessions.len() >= MAX_TRACKED {
+            sessions.retain(|_, session| session.expires_at > now);
+        }
+        sessions.insert(session_key(&cookie), session.clone());
+        (cookie, session)
+    }
+
+    fn session_at(&self, cookie: &str, now: DateTime<Utc>) -> Option<AdminSession> {
+        let mut sessions = self.sessions.lock();
+        let key = session_key(cookie);
+        let session = sessions.get(&key)?;
+        if session.expires_at <= now {
+            sessions.remove(&key);
+            return None;
+        }
+        Some(session.clone())
+    }
+
+    async fn discover(&self) -> Result<Discovery, OidcError> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer.trim_end_matches('/')
+        );
+        Ok(self
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// The email of verified ID token `claims` from the login that sent
+    /// `nonce`, if it is allowed in.
+    fn admit(&self, claims: &serde_json::Value, nonce: &str) -> Result<String, OidcError> {
+        if claims["nonce"].as_str() != Some(nonce) {
+            return Err(OidcError::Nonce);
+        }
+        let email = claims["email"]
+            .as_str()
+            .filter(|_| claims["email_verified"] != false)
+            .ok_or(OidcError::Email)?
+            .to_lowercase();
+
+        let allowed = self
+            .config
+            .allowed_emails
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(&email))
+            || email.rsplit_once('@').is_some_and(|(_, domain)| {
+                self.config
+                    .allowed_domains
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+            });
+        if !allowed {
+            return Err(OidcError::NotAllowed(email));
+        }
+        Ok(email)
+    }
+}
+
+/// Sessions are held by the hash of their cookie, like client keys.
+fn session_key(cookie: &str) -> String {
+    hex::encode(Sha256::digest(cookie.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn login() -> OidcLogin {
+        OidcLogin::new(&AdminOidcConfig {
+            issuer: "https://sso.example.com".to_string(),
+            client_id: "relay-admin".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "https://relay.example.com/admin/oidc/callback".to_string(),
+            allowed_emails: vec!["Ops@Partner.org".to_string()],
+            allowed_domains: vec!["example.com".to_string()],
+            session_ttl_seconds: 3600,
+        })
+    }
+
+    #[test]
+    fn test_admit_checks_nonce_and_allow_list() {
+        let login = login();
+        let claims =
+            |email: &str| json!({ "nonce": "n-1", "email": email, "email_verified": true });
+
+        assert_eq!(
+            login.admit(&claims("Alice@Example.com"), "n-1").unwrap(),
+            "alice@example.com"
+        );
+        assert_eq!(
+            login.admit(&claims("ops@partner.org"), "n-1").unwrap(),
+            "ops@partner.org"
+        );
+        assert!(matches!(
+            login.admit(&claims("mallory@example.com.evil"), "n-1"),
+            Err(OidcError::NotAllowed(_))
+        ));
+        assert!(matches!(
+            login.admit(&claims("alice@example.com"), "n-2"),
+            Err(OidcError::Nonce)
+        ));
+        let unverified =
+            json!({ "nonce": "n-1", "email": "alice@example.com", "email_verified": false });
+        assert!(matches!(
+            login.admit(&unverified, "n-1"),
+            Err(OidcError::Email)
+        ));
+        assert!(matches!(
+            login.admit(&json!({ "nonce": "n-1" }), "n-1"),
+            Err(OidcError::Email)
+        ));
+    }
+
+    #[test]
+    fn test_pending_logins_are_bounded() {
+        let login = login();
+        let start = Instant::now();
+        for i in 0..MAX_TRACKED {
+            login
+                .track_login(i.to_string(), "n".to_string(), start)
+                .unwrap();
+        }
+        assert!(matches!(
+            login.track_login("full".to_string(), "n".to_string(), start),
+            Err(OidcError::Busy)
+        ));
+
+        let later = start + LOGIN_TIMEOUT;
+        login
+            .track_login("later".to_string(), "n".to_string(), later)
+            .unwrap();
+        assert_eq!(login.pending.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_sessions_expire_and_end() {
+        let login = login();
+        let (cookie, session) = login.start_session("alice@example.com".to_string());
+        assert_eq!(login.session(&cookie).unwrap().email, "alice@example.com");
+        assert!(login.session("forged").is_none());
+
+        let expired = session.expires_at;
+        assert!(login.session_at(&cookie, expired).is_none());
+        assert!(login.session(&cookie).is_none());
+
+        let (cookie, _) = login.start_session("alice@example.com".to_string());
+        login.end_session(&cookie);
+        assert!(login.session(&cookie).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use crate::config::ModelPriceConfig;
+use crate::db::TokenUsage;
+
+const CACHE_WRITE_MULTIPLIER: f64 = 1.25;
+const CACHE_READ_MULTIPLIER: f64 = 0.1;
+
+/// Anthropic list prices in USD per million tokens, keyed by name prefix:
+/// (input, output).
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+impl ModelPrice {
+    fn from_config(config: &ModelPriceConfig) -> Self {
+        Self {
+            input: config.input,
+            output: config.output,
+            cache_write: config
+                .cache_write
+                .unwrap_or(config.input * CACHE_WRITE_MULTIPLIER),
+            cache_read: config
+                .cache_read
+                .unwrap_or(config.input * CACHE_READ_MULTIPLIER),
+        }
+    }
+
+    /// Cost in USD of the given token counts.
+    pub fn cost(&self, input: u64, output: u64, cache_write: u64, cache_read: u64) -> f64 {
+        let tokens = |count: u64, per_million: f64| count as f64 * per_million / 1_000_000.0;
+        tokens(input, self.input)
+            + tokens(output, self.output)
+            + tokens(cache_write, self.cache_write)
+            + tokens(cache_read, self.cache_read)
+    }
+}
+
+/// Per-model token prices, matched by the longest configured prefix.
+pub struct Pricing {
+    prices: Vec<(String, ModelPrice)>,
+}
+
+impl Pricing {
+    pub fn new(config: &HashMap<String, ModelPriceConfig>) -> Self {
+        let mut prices: HashMap<String, ModelPrice> = DEFAULT_PRICES
+            .iter()
+            .map(|&(prefix, input, output)| {
+                let price = ModelPrice::from_config(&ModelPriceConfig {
+                    input,
+                    output,
+                    cache_write: None,
+                    cache_read: None,
+                });
+                (prefix.to_string(), price)
+            })
+            .collect();
+        prices.extend(
+            config
+                .iter()
+                .map(|(prefix, price)| (prefix.clone(), ModelPrice::from_config(price))),
+        );
+
+        let mut prices: Vec<(String, ModelPrice)> = prices.into_iter().collect();
+        prices.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+
+        Self { prices }
+    }
+
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.prices
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .map(|(_, price)| *price)
+    }
+
+    /// Cost in USD, or `None` for models without a known price. Reasoning
+    /// tokens are already part of `output_tokens`.
+    pub fn cost(&self, model: &str, usage: &TokenUsage) -> Option<f64> {
+        let price = self.price(model)?;
+        Some(price.cost(
+            usage.input_tokens as u64,
+            usage.output_tokens as u64,
+            usage.cache_creation_tokens as u64,
+            usage.cache_read_tokens as u64,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u32, output: u32, cache_creation: u32, cache_read: u32) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_tokens: cache_creation,
+            cache_read_tokens: cache_read,
+            reasoning_tokens: 0,
+        }
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let pricing = Pricing::new(&HashMap::new());
+
+        assert_eq!(
+            pricing.price("claude-opus-4-5-20251101").unwrap().input,
+            5.0
+        );
+        assert_eq!(
+            pricing.price("claude-opus-4-1-20250805").unwrap().input,
+            15.0
+        );
+        assert_eq!(pricing.price("gpt-4o"), None);
+    }
+
+    #[test]
+    fn test_cost_includes_cache_tokens() {
+        let pricing = Pricing::new(&HashMap::new());
+        let cost = pricing
+            .cost(
+                "claude-sonnet-4-20250514",
+                &usage(1_000_000, 100_000, 200_000, 1_000_000),
+            )
+            .unwrap();
+
+        // 3.00 input + 1.50 output + 0.75 cache write + 0.30 cache read
+        assert!((cost - 5.55).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_configured_prices_override_defaults() {
+        let config = HashMap::from([
+            (
+                "claude-sonnet-4".to_string(),
+                ModelPriceConfig {
+                    input: 2.0,
+                    output: 10.0,
+                    cache_write: None,
+                    cache_read: Some(1.0),
+                },
+            ),
+            (
+                "gpt-4o".to_string(),
+                ModelPriceConfig {
+                    input: 2.5,
+                    output: 10.0,
+                    cache_write: None,
+                    cache_read: None,
+                },
+            ),
+        ]);
+        let pricing = Pricing::new(&config);
+
+        let sonnet = pricing.price("claude-sonnet-4-20250514").unwrap();
+        assert_eq!(sonnet.cache_write, 2.5);
+        assert_eq!(sonnet.cache_read, 1.0);
+        let cost = pricing.cost("gpt-4o-mini", &usage(2_000_000, 0, 0, 0));
+        assert_eq!(cost, Some(5.0));
+    }
+}
@@ -0,0 +1,242 @@
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Buckets kept before full ones are dropped; anonymous callers get one per
+/// address, so this bounds memory under rotating (e.g. IPv6) addresses.
+const MAX_TRACKED: usize = 10_000;
+
+/// A request rate: `requests_per_minute` on average, up to `burst` at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_minute: u32,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// `None` without a rate; the burst defaults to a minute's requests.
+    pub fn new(requests_per_minute: Option<u32>, burst: Option<u32>) -> Option<Self> {
+        let requests_per_minute = requests_per_minute?;
+        Some(Self {
+            requests_per_minute,
+            burst: burst.unwrap_or(requests_per_minute),
+        })
+    }
+
+    fn per_second(&self) -> f64 {
+        self.requests_per_minute as f64 / 60.0
+    }
+}
+
+/// A key's bucket after a request, as reported in `x-ratelimit-*` headers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    /// Requests the key can make right away.
+    pub remaining: u32,
+    /// Until the bucket is full again.
+    pub reset: Duration,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket is full again and the same as a fresh one.
+    full_at: Instant,
+}
+
+/// A token bucket per client key, refilled continuously at the key's rate,
+/// and a count of each key's requests in flight.
+pub struct RateLimiter {
+    /// Applies to keys without a limit of their own.
+    default: Option<RateLimit>,
+    /// Applies to keys without a concurrency limit of their own.
+    default_max_concurrent: Option<u32>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    in_flight: Mutex<HashMap<String, u32>>,
+}
+
+/// Holds one of a key's concurrent request slots until dropped.
+pub struct InFlight {
+    limiter: Arc<RateLimiter>,
+    key: String,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut in_flight = self.limiter.in_flight.lock();
+        if let Some(count) = in_flight.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(default: Option<RateLimit>) -> Self {
+        Self {
+            default,
+            default_max_concurrent: None,
+            buckets: Mutex::default(),
+            in_flight: Mutex::default(),
+        }
+    }
+
+    /// Limits keys without a concurrency limit of their own to this many
+    /// requests in flight.
+    pub fn with_max_concurrent(mut self, default: Option<u32>) -> Self {
+        self.default_max_concurrent = default;
+        self
+    }
+
+    /// The key's own limit, or else the default.
+    pub fn limit_for(&self, own: Option<RateLimit>) -> Option<RateLimit> {
+        own.or(self.default)
+    }
+
+    /// The key's own concurrency limit, or else the default.
+    pub fn max_concurrent_for(&self, own: Option<u32>) -> Option<u32> {
+        own.or(self.default_max_concurrent)
+    }
+
+    /// Takes one of the key's `max` concurrent request slots, or `None` when
+    /// all are in use.
+    pub fn enter(self: &Arc<Self>, key: &str, max: u32) -> Option<InFlight> {
+        let mut in_flight = self.in_flight.lock();
+        let count = in_flight.entry(key.to_string()).or_default();
+        if *count >= max {
+            return None;
+        }
+        *count += 1;
+        Some(InFlight {
+            limiter: self.clone(),
+            key: key.to_string(),
+        })
+    }
+
+    /// Takes one request from the key's bucket, or returns how long until
+    /// one is available. Either way with what is left of the bucket.
+    pub fn acquire(&self, key: &str, limit: RateLimit) -> Result<Quota, (Quota, Duration)> {
+        self.acquire_at(key, limit, Instant::now())
+    }
+
+    fn acquire_at(
+        &self,
+        key: &str,
+        limit: RateLimit,
+        now: Instant,
+    ) -> Result<Quota, (Quota, Duration)> {
+        let burst = limit.burst as f64;
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_TRACKED {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            full_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second()).min(burst);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let quota = Quota {
+            remaining: bucket.tokens.floor() as u32,
+            reset: Duration::from_secs_f64((burst - bucket.tokens) / limit.per_second()),
+        };
+        bucket.full_at = now + quota.reset;
+        if allowed {
+            return Ok(quota);
+        }
+        let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second());
+        Err((quota, wait))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = RateLimiter::new(None);
+        let limit = RateLimit::new(Some(60), Some(2)).unwrap();
+        let start = Instant::now();
+
+        let quota = limiter.acquire_at("key-a", limit, start).unwrap();
+        assert_eq!(quota.remaining, 1);
+        assert_eq!(quota.reset, Duration::from_secs(1));
+        assert!(limiter.acquire_at("key-a", limit, start).is_ok());
+        let (quota, wait) = limiter.acquire_at("key-a", limit, start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        assert_eq!(quota.remaining, 0);
+        assert_eq!(quota.reset, Duration::from_secs(2));
+
+        // Other keys have buckets of their own
+        assert!(limiter.acquire_at("key-b", limit, start).is_ok());
+
+        let later = start + Duration::from_millis(500);
+        let (_, wait) = limiter.acquire_at("key-a", limit, later).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        assert!(limiter
+            .acquire_at("key-a", limit, start + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_full_buckets_are_evicted() {
+        let limiter = RateLimiter::new(None);
+        let limit = RateLimit::new(Some(60), Some(1)).unwrap();
+        let start = Instant::now();
+
+        for i in 0..MAX_TRACKED {
+            assert!(limiter.acquire_at(&format!("ip:{i}"), limit, start).is_ok());
+        }
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.acquire_at("ip:late", limit, later).is_ok());
+        assert_eq!(limiter.buckets.lock().len(), MAX_TRACKED + 1);
+
+        // Every earlier bucket has refilled by now
+        let refilled = start + Duration::from_secs(1);
+        assert!(limiter.acquire_at("ip:new", limit, refilled).is_ok());
+        assert_eq!(limiter.buckets.lock().len(), 2);
+        assert!(limiter.acquire_at("ip:late", limit, refilled).is_err());
+    }
+
+    #[test]
+    fn test_limit_for_falls_back_to_default() {
+        let default = RateLimit::new(Some(120), None);
+        assert_eq!(default.unwrap().burst, 120);
+
+        let limiter = RateLimiter::new(default);
+        let own = RateLimit::new(Some(10), Some(5));
+        assert_eq!(limiter.limit_for(own), own);
+        assert_eq!(limiter.limit_for(None), default);
+        assert_eq!(RateLimiter::new(None).limit_for(None), None);
+    }
+
+    #[test]
+    fn test_in_flight_slots_are_released() {
+        let limiter = Arc::new(RateLimiter::new(None).with_max_concurrent(Some(2)));
+        assert_eq!(limiter.max_concurrent_for(None), Some(2));
+        assert_eq!(limiter.max_concurrent_for(Some(5)), Some(5));
+
+        let first = limiter.enter("key-a", 2).unwrap();
+        let second = limiter.enter("key-a", 2).unwrap();
+        assert!(limiter.enter("key-a", 2).is_none());
+        assert!(limiter.enter("key-b", 2).is_some());
+
+        drop(first);
+        let third = limiter.enter("key-a", 2).unwrap();
+        drop(second);
+        drop(third);
+        assert!(limiter.in_flight.lock().is_empty());
+    }
+}
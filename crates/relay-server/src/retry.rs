@@ -0,0 +1,198 @@
+use relay_core::RelayError;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::config::{RetryConfig, UpstreamErrorsConfig};
+
+/// The upstream statuses a request is retried on another account for.
+#[derive(Debug, Clone)]
+pub struct RetryableStatuses(Arc<[u16]>);
+
+impl RetryableStatuses {
+    pub fn new(config: &UpstreamErrorsConfig) -> Self {
+        Self(config.retryable_statuses.as_slice().into())
+    }
+
+    /// Whether the status behind `error` is retryable; errors that carry
+    /// no upstream status are not.
+    pub fn contains(&self, error: &RelayError) -> bool {
+        let status = match error {
+            RelayError::RateLimited(_) => 429,
+            RelayError::Overloaded { .. } => 529,
+            RelayError::Upstream { status, .. } => *status,
+            _ => return false,
+        };
+        self.0.contains(&status)
+    }
+}
+
+/// Spaces out the attempts of a request retried on other accounts, so a
+/// failure they all share, such as an upstream incident, does not use up
+/// every attempt within milliseconds.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryBackoff {
+    backoff: Duration,
+    max_backoff: Duration,
+    max_retry_after: Duration,
+}
+
+impl RetryBackoff {
+    pub fn new(config: &RetryConfig) -> Self {
+        Self {
+            backoff: Duration::from_millis(config.backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            max_retry_after: Duration::from_secs(config.max_retry_after_seconds),
+        }
+    }
+
+    /// Pacing for the attempts of one request.
+    pub fn start(&self) -> RetryPacer {
+        RetryPacer {
+            backoff: *self,
+            failures: 0,
+            retry_after: None,
+        }
+    }
+}
+
+/// The failed attempts of one request so far.
+pub struct RetryPacer {
+    backoff: RetryBackoff,
+    failures: u32,
+    /// The last `Retry-After`, while every attempt has been rate limited.
+    retry_after: Option<u64>,
+}
+
+impl RetryPacer {
+    /// Counts a failed attempt that is about to be retried.
+    pub fn failed(&mut self, error: &RelayError) {
+        self.retry_after = match error {
+            // A limit that named no wait leaves only the backoff
+            RelayError::RateLimited(secs) if self.failures == 0 || self.retry_after.is_some() => {
+                Some(secs.unwrap_or(0))
+            }
+            _ => None,
+        };
+        self.failures += 1;
+    }
+
+    /// Waits before the next attempt; the first goes out at once.
+    pub async fn wait(&self) {
+        let delay = self.delay(random_unit());
+        if !delay.is_zero() {
+            debug!(
+                failures = self.failures,
+                delay_ms = delay.as_millis() as u64,
+                "Backing off before retrying"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Exponential backoff with half of it jittered by `jitter` in
+    /// `[0, 1)`. When two or more accounts in a row were rate limited, the
+    /// limit looks shared, so the upstream's `Retry-After` is waited out,
+    /// up to `max_retry_after`.
+    fn delay(&self, jitter: f64) -> Duration {
+        let Some(doublings) = self.failures.checked_sub(1) else {
+            return Duration::ZERO;
+        };
+        let backoff = self
+            .backoff
+            .backoff
+            .saturating_mul(1 << doublings.min(16))
+            .min(self.backoff.max_backoff);
+        let backoff = backoff.mul_f64(0.5 + jitter / 2.0);
+
+        match self.retry_after {
+            Some(secs) if self.failures >= 2 => {
+                backoff.max(Duration::from_secs(secs).min(self.backoff.max_retry_after))
+            }
+            _ => backoff,
+        }
+    }
+}
+
+/// Uniform sample in `[0, 1)` from std's randomly keyed hasher.
+fn random_unit() -> f64 {
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backoff() -> RetryBackoff {
+        RetryBackoff::new(&RetryConfig {
+            backoff_ms: 100,
+            max_backoff_ms: 300,
+            max_retry_after_seconds: 5,
+        })
+    }
+
+    #[test]
+    fn test_exponential_backoff_with_jitter() {
+        let mut pacer = backoff().start();
+        assert_eq!(pacer.delay(0.9), Duration::ZERO);
+
+        let overloaded = RelayError::Overloaded {
+            retry_after_secs: Some(300),
+        };
+        pacer.failed(&overloaded);
+        assert_eq!(pacer.delay(0.0), Duration::from_millis(50));
+        assert_eq!(pacer.delay(1.0), Duration::from_millis(100));
+        pacer.failed(&overloaded);
+        assert_eq!(pacer.delay(1.0), Duration::from_millis(200));
+        pacer.failed(&overloaded);
+        assert_eq!(pacer.delay(1.0), Duration::from_millis(300));
+        assert_eq!(pacer.delay(0.0), Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        let mut config = UpstreamErrorsConfig::default();
+        let retryable = RetryableStatuses::new(&config);
+        assert!(retryable.contains(&RelayError::RateLimited(None)));
+        assert!(retryable.contains(&RelayError::Overloaded {
+            retry_after_secs: None
+        }));
+        let bad_gateway = RelayError::Upstream {
+            status: 502,
+            message: String::new(),
+        };
+        assert!(!retryable.contains(&bad_gateway));
+        assert!(!retryable.contains(&RelayError::InvalidRequest(String::new())));
+
+        config.retryable_statuses = vec![502];
+        let retryable = RetryableStatuses::new(&config);
+        assert!(retryable.contains(&bad_gateway));
+        assert!(!retryable.contains(&RelayError::RateLimited(None)));
+    }
+
+    #[test]
+    fn test_shared_rate_limit_waits_for_retry_after() {
+        let mut pacer = backoff().start();
+        pacer.failed(&RelayError::RateLimited(Some(3)));
+        // One rate-limited account says nothing about the others
+        assert_eq!(pacer.delay(1.0), Duration::from_millis(100));
+        pacer.failed(&RelayError::RateLimited(Some(3)));
+        assert_eq!(pacer.delay(1.0), Duration::from_secs(3));
+        pacer.failed(&RelayError::RateLimited(Some(60)));
+        assert_eq!(pacer.delay(1.0), Duration::from_secs(5));
+
+        let mut pacer = backoff().start();
+        pacer.failed(&RelayError::Unauthorized(String::new()));
+        pacer.failed(&RelayError::RateLimited(Some(3)));
+        assert_eq!(pacer.delay(1.0), Duration::from_millis(200));
+
+        let mut pacer = backoff().start();
+        pacer.failed(&RelayError::RateLimited(None));
+        pacer.failed(&RelayError::RateLimited(None));
+        assert_eq!(pacer.delay(1.0), Duration::from_millis(200));
+    }
+}
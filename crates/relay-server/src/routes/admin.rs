@@ -0,0 +1,1263 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Redirect, Response,
+    },
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use relay_claude::ClientProfile;
+use relay_core::{ArmStats, BanditPolicy, BanditReward, Platform, Scheduler};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::db::{self, AuthFailure, ClientKey, DbPool, DeadLetter};
+use crate::error_budget::{ErrorBudgetTracker, WindowStats};
+use crate::error_stats::{ErrorCounts, ErrorStats};
+use crate::ip_filter::IpRange;
+use crate::middleware::{
+    cookie, ApiKeyValidator, ClientApiKeyHash, KeyScope, ADMIN_SESSION_COOKIE,
+};
+use crate::oidc::{AdminSession, OidcError, OidcLogin, LOGIN_TIMEOUT};
+use crate::routes::UsageRecorder;
+use crate::scheduler::UnifiedScheduler;
+use crate::slow_requests::{SlowCounts, SlowRequests};
+use crate::webhook::{ReplayError, WebhookDispatcher};
+
+const DEAD_LETTER_LIMIT: i64 = 100;
+
+/// Most auth failures returned at once.
+const MAX_AUTH_FAILURES: i64 = 1000;
+
+/// Characters of an issued key kept in `client_keys` to tell keys apart.
+const KEY_PREFIX_LEN: usize = 12;
+
+/// Holds the state of the login this browser started, so a callback
+/// carrying someone else's state is refused.
+const OIDC_STATE_COOKIE: &str = "relay_oidc_state";
+
+pub struct AdminRouteState {
+    pub scheduler: Arc<UnifiedScheduler>,
+    pub error_budgets: Arc<ErrorBudgetTracker>,
+    pub error_stats: Arc<ErrorStats>,
+    pub slow_requests: Arc<SlowRequests>,
+    pub webhooks: Arc<WebhookDispatcher>,
+    pub usage: Arc<UsageRecorder>,
+    /// Set when the experimental bandit scheduling strategy is enabled.
+    pub bandit: Option<Arc<BanditPolicy>>,
+    pub api_keys: Arc<ApiKeyValidator>,
+    pub db_pool: DbPool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBudgetReport {
+    pub thresholds: ErrorBudgetThresholds,
+    pub accounts: Vec<AccountErrorBudget>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBudgetThresholds {
+    pub min_requests: u64,
+    pub max_server_error_rate: f64,
+    pub max_timeout_rate: f64,
+    pub circuit_breaker: bool,
+    pub breaker_cooldown_seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountErrorBudget {
+    pub id: String,
+    pub name: String,
+    pub platform: Platform,
+    pub draining: bool,
+    pub cooldown: Option<AccountCooldown>,
+    pub windows: Vec<WindowStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountCooldown {
+    pub reason: String,
+    pub remaining_seconds: u64,
+}
+
+/// `GET /admin/accounts/error-budgets`
+pub async fn error_budgets(State(state): State<Arc<AdminRouteState>>) -> Json<ErrorBudgetReport> {
+    let config = state.error_budgets.config();
+
+    let accounts = state
+        .scheduler
+        .all_accounts()
+        .iter()
+        .map(|account| AccountErrorBudget {
+            id: account.id().to_string(),
+            name: account.name().to_string(),
+            platform: account.platform(),
+            draining: state.scheduler.is_draining(account.id()),
+            cooldown: state
+                .scheduler
+                .cooldown(account.id())
+                .map(|c| AccountCooldown {
+                    reason: c.reason,
+                    remaining_seconds: c.remaining.as_secs(),
+                }),
+            windows: state.error_budgets.windows(account.id()),
+        })
+        .collect();
+
+    Json(ErrorBudgetReport {
+        thresholds: ErrorBudgetThresholds {
+            min_requests: config.min_requests,
+            max_server_error_rate: config.max_server_error_rate,
+            max_timeout_rate: config.max_timeout_rate,
+            circuit_breaker: config.circuit_breaker,
+            breaker_cooldown_seconds: config.breaker_cooldown_seconds,
+        },
+        accounts,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountErrorsQuery {
+    #[serde(default = "default_error_hours")]
+    pub hours: i64,
+}
+
+fn default_error_hours() -> i64 {
+    24
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountErrorsReport {
+    pub hours: i64,
+    pub accounts: Vec<AccountErrors>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountErrors {
+    pub id: String,
+    pub name: String,
+    pub platform: Platform,
+    /// Counted in memory since the server started.
+    pub since_start: ErrorCounts,
+    /// From `request_log` over the last `hours` hours. Timeouts show up as
+    /// `server_error` here.
+    pub recent: ErrorCounts,
+}
+
+/// `GET /admin/accounts/errors?hours=24`
+pub async fn account_errors(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<AccountErrorsQuery>,
+) -> Result<Json<AccountErrorsReport>, Response> {
+    if query.hours < 1 {
+        return Err(admin_error(
+            StatusCode::BAD_REQUEST,
+            "hours must be at least 1".to_string(),
+        ));
+    }
+    let rows = db::get_account_status_counts(&state.db_pool, query.hours)
+        .await
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut recent: HashMap<String, ErrorCounts> = HashMap::new();
+    for row in rows {
+        recent
+            .entry(row.account_id)
+            .or_default()
+            .add(row.status_code, false, row.count);
+    }
+
+    let accounts = state
+        .scheduler
+        .all_accounts()
+        .iter()
+        .map(|account| AccountErrors {
+            id: account.id().to_string(),
+            name: account.name().to_string(),
+            platform: account.platform(),
+            since_start: state.error_stats.get(account.id()),
+            recent: recent.remove(account.id()).unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(Json(AccountErrorsReport {
+        hours: query.hours,
+        accounts,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlowRequestsReport {
+    /// `None` while the threshold is off.
+    pub threshold_seconds: Option<u64>,
+    pub stream_threshold_seconds: Option<u64>,
+    pub accounts: Vec<AccountSlowRequests>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountSlowRequests {
+    pub id: String,
+    pub name: String,
+    pub platform: Platform,
+    /// Counted in memory since the server started.
+    pub since_start: SlowCounts,
+}
+
+/// `GET /admin/accounts/slow`
+pub async fn slow_requests(State(state): State<Arc<AdminRouteState>>) -> Json<SlowRequestsReport> {
+    let threshold = |streamed| {
+        state
+            .slow_requests
+            .threshold(streamed)
+            .map(|threshold| threshold.as_secs())
+    };
+    let accounts = state
+        .scheduler
+        .all_accounts()
+        .iter()
+        .map(|account| AccountSlowRequests {
+            id: account.id().to_string(),
+            name: account.name().to_string(),
+            platform: account.platform(),
+            since_start: state.slow_requests.get(account.id()),
+        })
+        .collect();
+
+    Json(SlowRequestsReport {
+        threshold_seconds: threshold(false),
+        stream_threshold_seconds: threshold(true),
+        accounts,
+    })
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DrainingState {
+    pub draining: bool,
+}
+
+/// `PUT /admin/accounts/:id/draining`
+///
+/// A draining account keeps serving its sticky sessions but is never picked
+/// for new ones, so it can be retired once those sessions have expired.
+pub async fn set_draining(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+    Json(body): Json<DrainingState>,
+) -> Response {
+    if !state.scheduler.set_draining(&id, body.draining) {
+        return admin_error(StatusCode::NOT_FOUND, format!("Account {} not found", id));
+    }
+    Json(serde_json::json!({"id": id, "draining": body.draining})).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub profile: ClientProfile,
+    pub max_tokens_per_day: Option<u64>,
+    #[serde(default)]
+    pub account_pinning: bool,
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    #[serde(default)]
+    pub allowed_platforms: Vec<Platform>,
+    #[serde(default)]
+    pub scopes: Vec<KeyScope>,
+    #[serde(default)]
+    pub allowed_ips: Vec<IpRange>,
+    pub requests_per_minute: Option<u32>,
+    pub burst: Option<u32>,
+    pub max_concurrent_requests: Option<u32>,
+    /// A `[[tenants]]` id from the config.
+    pub tenant: Option<String>,
+    /// RFC 3339; the key is rejected from then on.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedKey {
+    /// The key itself, which is not stored and cannot be shown again.
+    pub key: String,
+    #[serde(flatten)]
+    pub client_key: ClientKey,
+}
+
+/// `GET /admin/keys`
+pub async fn list_keys(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<Json<Vec<ClientKey>>, Response> {
+    db::list_client_keys(&state.db_pool)
+        .await
+        .map(Json)
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// `POST /admin/keys`
+///
+/// Issues a client key, usable at once without a restart.
+pub async fn create_key(
+    State(state): State<Arc<AdminRouteState>>,
+    Json(body): Json<CreateKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedKey>), Response> {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(admin_error(
+            StatusCode::BAD_REQUEST,
+            "name must not be empty".to_string(),
+        ));
+    }
+    if body.requests_per_minute == Some(0) || body.burst == Some(0) {
+        return Err(admin_error(
+            StatusCode::BAD_REQUEST,
+            "requests_per_minute and burst must be at least 1".to_string(),
+        ));
+    }
+    if body.max_concurrent_requests == Some(0) {
+        return Err(admin_error(
+            StatusCode::BAD_REQUEST,
+            "max_concurrent_requests must be at least 1".to_string(),
+        ));
+    }
+    if let Some(tenant) = body.tenant.as_deref() {
+        if !state.scheduler.has_tenant(tenant) {
+            return Err(admin_error(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown tenant {}", tenant),
+            ));
+        }
+    }
+    if body.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(admin_error(
+            StatusCode::BAD_REQUEST,
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    let key = generate_key();
+    let client_key = ClientKey {
+        id: generate_key_id(),
+        client_api_key_hash: ClientApiKeyHash::from_api_key(&key).0,
+        key_prefix: key[..KEY_PREFIX_LEN].to_string(),
+        name: name.to_string(),
+        profile: body.profile,
+        max_tokens_per_day: body.max_tokens_per_day,
+        account_pinning: body.account_pinning,
+        allowed_models: body.allowed_models,
+        allowed_platforms: body.allowed_platforms,
+        scopes: body.scopes,
+        allowed_ips: body.allowed_ips,
+        requests_per_minute: body.requests_per_minute,
+        burst: body.burst,
+        max_concurrent_requests: body.max_concurrent_requests,
+        tenant: body.tenant,
+        expires_at: body.expires_at,
+        rotated_from: None,
+        created_at: String::new(),
+        revoked_at: None,
+    };
+    let internal = |e: sqlx::Error| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let client_key = db::create_client_key(&state.db_pool, &client_key)
+        .await
+        .map_err(internal)?;
+    db::upsert_api_key_name(&state.db_pool, &client_key.client_api_key_hash, name)
+        .await
+        .map_err(internal)?;
+    state
+        .api_keys
+        .load_issued_keys(&state.db_pool)
+        .await
+        .map_err(internal)?;
+
+    info!(id = %client_key.id, name = %name, "Issued API key");
+    Ok((StatusCode::CREATED, Json(CreatedKey { key, client_key })))
+}
+
+fn generate_key() -> String {
+    format!("sk-relay-{}", uuid::Uuid::new_v4().simple())
+}
+
+fn generate_key_id() -> String {
+    format!("key_{}", uuid::Uuid::new_v4().simple())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RotateKeyRequest {
+    /// How long the replaced key keeps working.
+    #[serde(default = "default_rotation_grace_seconds")]
+    pub grace_seconds: u64,
+}
+
+fn default_rotation_grace_seconds() -> u64 {
+    86400
+}
+
+/// `POST /admin/keys/:id/rotate`
+///
+/// Issues a replacement with the same options and expires the old key after
+/// `grace_seconds`. Usage of both is recorded under the old key's identity.
+pub async fn rotate_key(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+    Json(body): Json<RotateKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedKey>), Response> {
+    let internal = |e: sqlx::Error| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let old = db::get_client_key(&state.db_pool, &id)
+        .await
+        .map_err(internal)?
+        .filter(|key| key.revoked_at.is_none() && key.expires_at.is_none_or(|at| at > Utc::now()))
+        .ok_or_else(|| {
+            admin_error(
+                StatusCode::NOT_FOUND,
+                format!("Active key {} not found", id),
+            )
+        })?;
+    let grace_ends = i64::try_from(body.grace_seconds)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|grace| Utc::now().checked_add_signed(grace))
+        .ok_or_else(|| {
+            admin_error(
+                StatusCode::BAD_REQUEST,
+                "grace_seconds is too large".to_string(),
+            )
+        })?;
+
+    let key = generate_key();
+    let replacement = ClientKey {
+        id: generate_key_id(),
+        client_api_key_hash: ClientApiKeyHash::from_api_key(&key).0,
+        key_prefix: key[..KEY_PREFIX_LEN].to_string(),
+        rotated_from: Some(old.id.clone()),
+        created_at: String::new(),
+        ..old.clone()
+    };
+    let client_key = db::create_client_key(&state.db_pool, &replacement)
+        .await
+        .map_err(internal)?;
+    let old_expires_at = old.expires_at.map_or(grace_ends, |at| at.min(grace_ends));
+    db::set_client_key_expiry(&state.db_pool, &old.id, old_expires_at)
+        .await
+        .map_err(internal)?;
+    state
+        .api_keys
+        .load_issued_keys(&state.db_pool)
+        .await
+        .map_err(internal)?;
+
+    info!(
+        id = %client_key.id,
+        rotated_from = %old.id,
+        old_expires_at = %old_expires_at,
+        "Rotated API key"
+    );
+    Ok((StatusCode::CREATED, Json(CreatedKey { key, client_key })))
+}
+
+/// `DELETE /admin/keys/:id`
+///
+/// Revokes an issued key; requests using it are rejected from then on.
+pub async fn revoke_key(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+) -> Response {
+    let internal = |e: sqlx::Error| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    match db::revoke_client_key(&state.db_pool, &id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return admin_error(
+                StatusCode::NOT_FOUND,
+                format!("Active key {} not found", id),
+            )
+        }
+        Err(e) => return internal(e),
+    }
+    if let Err(e) = state.api_keys.load_issued_keys(&state.db_pool).await {
+        return internal(e);
+    }
+
+    info!(id = %id, "Revoked API key");
+    Json(serde_json::json!({"id": id, "revoked": true})).into_response()
+}
+
+/// `GET /admin/usage/stream`
+///
+/// Sends a `usage` event per completed request. A client that falls behind
+/// gets a `lagged` event with the number of requests it missed.
+pub async fn usage_stream(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = futures::stream::unfold(state.usage.subscribe(), |mut receiver| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(usage) => match Event::default().event("usage").json_data(&usage) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!(error = %e, "Failed to serialize usage event");
+                        continue;
+                    }
+                },
+                Err(RecvError::Lagged(skipped)) => Event::default()
+                    .event("lagged")
+                    .data(serde_json::json!({ "skipped": skipped }).to_string()),
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok(event), receiver));
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[derive(Debug, Serialize)]
+pub struct BanditReport {
+    pub reward: BanditReward,
+    pub exploration_rate: f64,
+    pub arms: Vec<ArmStats>,
+}
+
+/// `GET /admin/scheduler/bandit`
+pub async fn bandit_stats(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<Json<BanditReport>, Response> {
+    let bandit = state.bandit.as_ref().ok_or_else(|| {
+        admin_error(
+            StatusCode::NOT_FOUND,
+            "Bandit scheduling is not enabled".to_string(),
+        )
+    })?;
+    Ok(Json(BanditReport {
+        reward: bandit.reward(),
+        exploration_rate: bandit.exploration_rate(),
+        arms: bandit.stats(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterQuery {
+    #[serde(default)]
+    pub include_replayed: bool,
+}
+
+/// `GET /admin/webhooks/dead-letters`
+pub async fn dead_letters(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Result<Json<Vec<DeadLetter>>, Response> {
+    db::list_dead_letters(&state.db_pool, query.include_replayed, DEAD_LETTER_LIMIT)
+        .await
+        .map(Json)
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// `POST /admin/webhooks/dead-letters/:id/replay`
+pub async fn replay_dead_letter(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<i64>,
+) -> Response {
+    match state.webhooks.replay(id).await {
+        Ok(()) => Json(serde_json::json!({"id": id, "replayed": true})).into_response(),
+        Err(e) => {
+            let status = match e {
+                ReplayError::NotFound(_) => StatusCode::NOT_FOUND,
+                ReplayError::AlreadyReplayed(_) | ReplayError::UnknownEndpoint(_) => {
+                    StatusCode::CONFLICT
+                }
+                ReplayError::Delivery(_) => StatusCode::BAD_GATEWAY,
+                ReplayError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            admin_error(status, e.to_string())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthFailureQuery {
+    #[serde(default = "default_auth_failure_limit")]
+    pub limit: i64,
+}
+
+fn default_auth_failure_limit() -> i64 {
+    100
+}
+
+/// `GET /admin/auth-failures?limit=100`
+///
+/// Recently rejected requests, newest first, to spot key scanning.
+pub async fn auth_failures(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<AuthFailureQuery>,
+) -> Result<Json<Vec<AuthFailure>>, Response> {
+    if !(1..=MAX_AUTH_FAILURES).contains(&query.limit) {
+        return Err(admin_error(
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {}", MAX_AUTH_FAILURES),
+        ));
+    }
+    db::list_auth_failures(&state.db_pool, query.limit)
+        .await
+        .map(Json)
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Starts an OIDC login by sending the browser to the provider.
+pub async fn oidc_login(State(oidc): State<Arc<OidcLogin>>) -> Response {
+    match oidc.authorization_url().await {
+        Ok((url, login_state)) => {
+            let cookie = state_cookie(&oidc, &login_state, LOGIN_TIMEOUT.as_secs());
+            ([(header::SET_COOKIE, cookie)], Redirect::to(&url)).into_response()
+        }
+        Err(OidcError::Busy) => {
+            admin_error(StatusCode::SERVICE_UNAVAILABLE, OidcError::Busy.to_string())
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to start OIDC login");
+            admin_error(StatusCode::BAD_GATEWAY, e.to_string())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    /// Set instead of `code` when the provider refused the login.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Where the provider sends the browser back; opens a session cookie on
+/// success and shows the session. The `state` must match the cookie set
+/// by [`oidc_login`] in this browser.
+pub async fn oidc_callback(
+    State(oidc): State<Arc<OidcLogin>>,
+    headers: HeaderMap,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Response {
+    let (Some(code), Some(login_state)) = (query.code, query.state) else {
+        let error = query.error.unwrap_or_else(|| "missing code".to_string());
+        return admin_error(
+            StatusCode::UNAUTHORIZED,
+            format!("OIDC login failed: {}", error),
+        );
+    };
+    if cookie(&headers, OIDC_STATE_COOKIE) != Some(login_state.as_str()) {
+        warn!("OIDC callback state does not match this browser's login");
+        return admin_error(StatusCode::UNAUTHORIZED, OidcError::State.to_string());
+    }
+
+    let (cookie, session) = match oidc.complete(&code, &login_state).await {
+        Ok(session) => session,
+        Err(e) => {
+            warn!(error = %e, "OIDC login failed");
+            let status = match e {
+                OidcError::Provider(_) => StatusCode::BAD_GATEWAY,
+                OidcError::NotAllowed(_) => StatusCode::FORBIDDEN,
+                _ => StatusCode::UNAUTHORIZED,
+            };
+            return admin_error(status, e.to_string());
+        }
+    };
+
+    info!(email = %session.email, "Admin signed in through OIDC");
+    let cookie = session_cookie(&oidc, &cookie, oidc.session_ttl().as_secs());
+    let cleared = state_cookie(&oidc, "", 0);
+    (
+        [(header::SET_COOKIE, cookie), (header::SET_COOKIE, cleared)],
+        Redirect::to("/admin/oidc/session"),
+    )
+        .into_response()
+}
+
+/// The administrator signed in with the session cookie.
+pub async fn oidc_session(
+    State(oidc): State<Arc<OidcLogin>>,
+    headers: HeaderMap,
+) -> Result<Json<AdminSession>, Response> {
+    cookie(&headers, ADMIN_SESSION_COOKIE)
+        .and_then(|cookie| oidc.session(cookie))
+        .map(Json)
+        .ok_or_else(|| admin_error(StatusCode::UNAUTHORIZED, "Not signed in".to_string()))
+}
+
+pub async fn oidc_logout(State(oidc): State<Arc<OidcLogin>>, headers: HeaderMap) -> Response {
+    if let Some(cookie) = cookie(&headers, ADMIN_SESSION_COOKIE) {
+        oidc.end_session(cookie);
+    }
+    let cleared = session_cookie(&oidc, "", 0);
+    (StatusCode::NO_CONTENT, [(header::SET_COOKIE, cleared)]).into_response()
+}
+
+/// `Set-Cookie` for the admin session. `SameSite=Lax` keeps other sites
+/// from sending it with state-changing requests.
+fn session_cookie(oidc: &OidcLogin, value: &str, max_age: u64) -> String {
+    format!(
+        "{}={}; Path=/admin; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        ADMIN_SESSION_COOKIE,
+        value,
+        max_age,
+        if oidc.secure_cookie() { "; Secure" } else { "" }
+    )
+}
+
+/// `Set-Cookie` for the login state, sent back only to the callback.
+fn state_cookie(oidc: &OidcLogin, value: &str, max_age: u64) -> String {
+    format!(
+        "{}={}; Path=/admin/oidc; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        OIDC_STATE_COOKIE,
+        value,
+        max_age,
+        if oidc.secure_cookie() { "; Secure" } else { "" }
+    )
+}
+
+pub(super) fn admin_error(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "error": {
+                "type": "api_error",
+                "message": message
+            }
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ErrorBudgetConfig, SlowRequestsConfig};
+    use crate::db::TokenUsage;
+    use crate::error_budget::Outcome;
+    use crate::middleware::{ClientApiKeyHash, RequestContext};
+    use crate::pricing::Pricing;
+    use futures::StreamExt;
+    use relay_claude::ClaudeApiAccount;
+    use relay_core::{
+        AccountProvider, FixedCooldownPolicy, MemorySessionStore, PriorityLruPolicy,
+        RequestFeedback, SelectionPolicy, TtlStickyPolicy,
+    };
+    use std::time::Duration;
+
+    async fn setup_test_db() -> DbPool {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    async fn state() -> Arc<AdminRouteState> {
+        let account: Arc<dyn AccountProvider> = Arc::new(ClaudeApiAccount::new(
+            "acc1".to_string(),
+            "Primary".to_string(),
+            100,
+            true,
+            "sk-test".to_string(),
+            None,
+            None,
+        ));
+        let scheduler = Arc::new(UnifiedScheduler::new(
+            vec![account],
+            Arc::new(TtlStickyPolicy::new(
+                MemorySessionStore::new(),
+                Duration::from_secs(3600),
+                Duration::from_secs(300),
+            )),
+            Arc::new(PriorityLruPolicy::new()),
+            Arc::new(FixedCooldownPolicy::new(Duration::from_secs(3600))),
+        ));
+        let error_budgets = Arc::new(ErrorBudgetTracker::new(
+            ErrorBudgetConfig {
+                min_requests: 2,
+                circuit_breaker: true,
+                ..Default::default()
+            },
+            scheduler.clone(),
+        ));
+        let db_pool = setup_test_db().await;
+        let webhooks = Arc::new(WebhookDispatcher::new(Vec::new(), db_pool.clone()));
+        let usage = Arc::new(UsageRecorder::new(
+            db_pool.clone(),
+            Arc::new(Pricing::new(&Default::default())),
+            webhooks.clone(),
+        ));
+        Arc::new(AdminRouteState {
+            scheduler,
+            error_budgets,
+            error_stats: Arc::new(ErrorStats::new()),
+            slow_requests: Arc::new(SlowRequests::new(&SlowRequestsConfig {
+                threshold_seconds: 60,
+                stream_threshold_seconds: 0,
+            })),
+            webhooks,
+            usage,
+            bandit: Some(Arc::new(BanditPolicy::new(BanditReward::Latency, 0.1))),
+            api_keys: Arc::new(ApiKeyValidator::new(Vec::new())),
+            db_pool,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_error_budgets_report() {
+        let state = state().await;
+        state.error_budgets.record("acc1", Outcome::ServerError);
+        state.error_budgets.record("acc1", Outcome::Success);
+
+        let Json(report) = error_budgets(State(state)).await;
+        let value = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(value["thresholds"]["max_server_error_rate"], 0.25);
+        let account = &value["accounts"][0];
+        assert_eq!(account["id"], "acc1");
+        assert_eq!(account["platform"], "claude");
+        assert_eq!(account["draining"], false);
+        assert_eq!(account["cooldown"]["reason"], "circuit_open");
+        assert_eq!(account["windows"][0]["window_seconds"], 300);
+        assert_eq!(account["windows"][0]["requests"], 2);
+        assert_eq!(account["windows"][0]["server_error_rate"], 0.5);
+        assert_eq!(account["windows"][0]["exhausted"], true);
+        assert_eq!(account["windows"][1]["window_seconds"], 3600);
+    }
+
+    #[tokio::test]
+    async fn test_account_errors_report() {
+        let state = state().await;
+        state.error_stats.record("acc1", 200, false);
+        state.error_stats.record("acc1", 529, false);
+        let entry = |status_code| db::RequestLogEntry {
+            request_id: "req".to_string(),
+            client_api_key_hash: "key".to_string(),
+            platform: "claude".to_string(),
+            account_id: Some("acc1".to_string()),
+            model: "model".to_string(),
+            path: "/v1/messages".to_string(),
+            status_code,
+            duration_ms: 10,
+            retry_count: 0,
+            streamed: false,
+        };
+        for status_code in [200, 200, 429, 500] {
+            db::record_request(&state.db_pool, &entry(status_code))
+                .await
+                .unwrap();
+        }
+
+        let Json(report) = account_errors(
+            State(state.clone()),
+            Query(AccountErrorsQuery { hours: 24 }),
+        )
+        .await
+        .unwrap();
+        let value = serde_json::to_value(&report).unwrap();
+
+        let account = &value["accounts"][0];
+        assert_eq!(account["id"], "acc1");
+        assert_eq!(account["since_start"]["requests"], 2);
+        assert_eq!(account["since_start"]["error_rate"], 0.5);
+        assert_eq!(account["since_start"]["by_kind"]["overloaded"], 1);
+        assert_eq!(account["recent"]["requests"], 4);
+        assert_eq!(account["recent"]["errors"], 2);
+        assert_eq!(account["recent"]["by_kind"]["rate_limited"], 1);
+        assert_eq!(account["recent"]["by_kind"]["server_error"], 1);
+
+        let response = account_errors(State(state), Query(AccountErrorsQuery { hours: 0 }))
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_slow_requests_report() {
+        let state = state().await;
+        state
+            .slow_requests
+            .record("acc1", "model", false, Duration::from_secs(90));
+
+        let Json(report) = slow_requests(State(state)).await;
+        let value = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(value["threshold_seconds"], 60);
+        assert!(value["stream_threshold_seconds"].is_null());
+        let account = &value["accounts"][0];
+        assert_eq!(account["id"], "acc1");
+        assert_eq!(account["since_start"]["requests"], 1);
+        assert_eq!(account["since_start"]["streams"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_draining() {
+        let state = state().await;
+
+        let response = set_draining(
+            State(state.clone()),
+            Path("acc1".to_string()),
+            Json(DrainingState { draining: true }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.scheduler.is_draining("acc1"));
+
+        let Json(report) = error_budgets(State(state.clone())).await;
+        assert!(report.accounts[0].draining);
+
+        let response = set_draining(
+            State(state),
+            Path("missing".to_string()),
+            Json(DrainingState { draining: true }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_issued_keys_take_effect_without_restart() {
+        let state = state().await;
+        assert!(state.api_keys.is_empty());
+
+        let request = CreateKeyRequest {
+            name: "ci-bot".to_string(),
+            profile: ClientProfile::AgentSdk,
+            max_tokens_per_day: Some(1000),
+            account_pinning: false,
+            allowed_models: Vec::new(),
+            allowed_platforms: Vec::new(),
+            scopes: Vec::new(),
+            allowed_ips: Vec::new(),
+            requests_per_minute: None,
+            burst: None,
+            max_concurrent_requests: None,
+            tenant: None,
+            expires_at: None,
+        };
+        let (status, Json(created)) = create_key(State(state.clone()), Json(request))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created.key.starts_with(&created.client_key.key_prefix));
+        let policy = state.api_keys.policy(&created.key).unwrap();
+        assert_eq!(policy.profile, ClientProfile::AgentSdk);
+        assert_eq!(policy.max_tokens_per_day, Some(1000));
+
+        let Json(keys) = list_keys(State(state.clone())).await.unwrap();
+        let listed = serde_json::to_value(&keys).unwrap();
+        assert_eq!(listed[0]["name"], "ci-bot");
+        assert!(listed[0].get("client_api_key_hash").is_none());
+
+        let id = created.client_key.id.clone();
+        let response = revoke_key(State(state.clone()), Path(id.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.api_keys.policy(&created.key).is_none());
+        let response = revoke_key(State(state.clone()), Path(id)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let request = CreateKeyRequest {
+            name: " ".to_string(),
+            profile: ClientProfile::ClaudeCode,
+            max_tokens_per_day: None,
+            account_pinning: false,
+            allowed_models: Vec::new(),
+            allowed_platforms: Vec::new(),
+            scopes: Vec::new(),
+            allowed_ips: Vec::new(),
+            requests_per_minute: None,
+            burst: None,
+            max_concurrent_requests: None,
+            tenant: None,
+            expires_at: None,
+        };
+        let response = create_key(State(state.clone()), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = CreateKeyRequest {
+            name: "trial".to_string(),
+            profile: ClientProfile::ClaudeCode,
+            max_tokens_per_day: None,
+            account_pinning: false,
+            allowed_models: Vec::new(),
+            allowed_platforms: Vec::new(),
+            scopes: Vec::new(),
+            allowed_ips: Vec::new(),
+            requests_per_minute: None,
+            burst: None,
+            max_concurrent_requests: None,
+            tenant: None,
+            expires_at: Some(Utc::now() - chrono::Duration::hours(1)),
+        };
+        let response = create_key(State(state.clone()), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = CreateKeyRequest {
+            name: "team-b".to_string(),
+            profile: ClientProfile::ClaudeCode,
+            max_tokens_per_day: None,
+            account_pinning: false,
+            allowed_models: Vec::new(),
+            allowed_platforms: Vec::new(),
+            scopes: Vec::new(),
+            allowed_ips: Vec::new(),
+            requests_per_minute: None,
+            burst: None,
+            max_concurrent_requests: None,
+            tenant: Some("team-b".to_string()),
+            expires_at: None,
+        };
+        let response = create_key(State(state), Json(request)).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_rotated_key_keeps_usage_identity() {
+        let state = state().await;
+        let request = CreateKeyRequest {
+            name: "ci-bot".to_string(),
+            profile: ClientProfile::AgentSdk,
+            max_tokens_per_day: Some(1000),
+            account_pinning: false,
+            allowed_models: Vec::new(),
+            allowed_platforms: Vec::new(),
+            scopes: Vec::new(),
+            allowed_ips: Vec::new(),
+            requests_per_minute: None,
+            burst: None,
+            max_concurrent_requests: None,
+            tenant: None,
+            expires_at: None,
+        };
+        let (_, Json(first)) = create_key(State(state.clone()), Json(request))
+            .await
+            .unwrap();
+        let rotate = |id: &str, grace_seconds| {
+            let body = RotateKeyRequest { grace_seconds };
+            rotate_key(State(state.clone()), Path(id.to_string()), Json(body))
+        };
+
+        let (status, Json(second)) = rotate(&first.client_key.id, 3600).await.unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(second.client_key.name, "ci-bot");
+        assert_eq!(
+            second.client_key.rotated_from,
+            Some(first.client_key.id.clone())
+        );
+        let old = state.api_keys.policy(&first.key).unwrap();
+        assert!(!old.is_expired());
+        assert!(old.expires_at.unwrap() <= Utc::now() + chrono::Duration::hours(1));
+        let new = state.api_keys.policy(&second.key).unwrap();
+        assert_eq!(new.profile, ClientProfile::AgentSdk);
+        assert_eq!(new.max_tokens_per_day, Some(1000));
+        let first_hash = first.client_key.client_api_key_hash.clone();
+        assert_eq!(new.usage_hash.as_ref(), Some(&first_hash));
+
+        // Without grace the replaced key stops working at once
+        let (_, Json(third)) = rotate(&second.client_key.id, 0).await.unwrap();
+        assert!(state.api_keys.policy(&second.key).unwrap().is_expired());
+        let newest = state.api_keys.policy(&third.key).unwrap();
+        assert_eq!(newest.usage_hash, Some(first_hash));
+
+        let response = rotate(&second.client_key.id, 0).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = rotate("key_unknown", 0).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_usage_stream_sends_completed_requests() {
+        let state = state().await;
+        let response = usage_stream(State(state.clone())).await.into_response();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let context = RequestContext::new();
+        context.begin(Platform::Claude, "claude-sonnet-4-20250514", true);
+        state
+            .usage
+            .record(
+                &context,
+                &ClientApiKeyHash::from_api_key("dashboard-key"),
+                "acc1",
+                "claude-sonnet-4-20250514",
+                TokenUsage {
+                    input_tokens: 120,
+                    output_tokens: 30,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        let mut body = response.into_body().into_data_stream();
+        let chunk = tokio::time::timeout(Duration::from_secs(1), body.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(text.starts_with("event: usage\ndata: "));
+        let data: serde_json::Value =
+            serde_json::from_str(text.lines().nth(1).unwrap().trim_start_matches("data: "))
+                .unwrap();
+        assert_eq!(data["account_id"], "acc1");
+        assert_eq!(data["model"], "claude-sonnet-4-20250514");
+        assert_eq!(data["input_tokens"], 120);
+        assert_eq!(data["output_tokens"], 30);
+    }
+
+    #[tokio::test]
+    async fn test_bandit_stats() {
+        let state = state().await;
+        let bandit = state.bandit.clone().unwrap();
+        bandit.record_selected("acc1");
+        bandit.record_feedback(
+            "acc1",
+            &RequestFeedback {
+                success: true,
+                latency: Duration::from_millis(250),
+            },
+        );
+
+        let Json(report) = bandit_stats(State(state.clone())).await.unwrap();
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(value["reward"], "latency");
+        assert_eq!(value["arms"][0]["account_id"], "acc1");
+        assert_eq!(value["arms"][0]["mean_latency_ms"], 250.0);
+
+        let state = Arc::new(AdminRouteState {
+            scheduler: state.scheduler.clone(),
+            error_budgets: state.error_budgets.clone(),
+            error_stats: state.error_stats.clone(),
+            slow_requests: state.slow_requests.clone(),
+            webhooks: state.webhooks.clone(),
+            usage: state.usage.clone(),
+            bandit: None,
+            api_keys: state.api_keys.clone(),
+            db_pool: state.db_pool.clone(),
+        });
+        let response = bandit_stats(State(state)).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_listing_and_replay_errors() {
+        let state = state().await;
+        let id = db::insert_dead_letter(
+            &state.db_pool,
+            "evt-1",
+            "account.circuit_opened",
+            "https://removed.example.com/hook",
+            "{}",
+            5,
+            "HTTP 500",
+        )
+        .await
+        .unwrap();
+
+        let Json(letters) = dead_letters(
+            State(state.clone()),
+            Query(DeadLetterQuery {
+                include_replayed: false,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].url, "https://removed.example.com/hook");
+
+        let response = replay_dead_letter(State(state.clone()), Path(id)).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = replay_dead_letter(State(state), Path(id + 1)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_auth_failures_recorded_and_listed() {
+        use crate::middleware::{auth_middleware, ApiKeyPolicy};
+        use axum::{body::Body, middleware, routing::get, Router};
+        use tower::ServiceExt;
+
+        let state = state().await;
+        let validator = Arc::new(
+            ApiKeyValidator::new(vec![("cli-key".to_string(), ApiKeyPolicy::default())])
+                .with_failure_log(state.db_pool.clone()),
+        );
+        let app = Router::new()
+            .route("/v1/messages", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(validator, auth_middleware));
+        for key in [None, Some("sk-guessed-0123456789"), Some("cli-key")] {
+            let mut request = axum::http::Request::builder().uri("/v1/messages");
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            app.clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+        }
+
+        let list = |limit| auth_failures(State(state.clone()), Query(AuthFailureQuery { limit }));
+        let mut failures = Vec::new();
+        for _ in 0..50 {
+            failures = list(10).await.unwrap().0;
+            if failures.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(failures.len(), 2);
+        let mut reasons: Vec<&str> = failures.iter().map(|f| f.reason.as_str()).collect();
+        reasons.sort();
+        assert_eq!(reasons, ["invalid_key", "missing_key"]);
+        let invalid = failures.iter().find(|f| f.reason == "invalid_key").unwrap();
+        assert_eq!(invalid.masked_key.as_deref(), Some("sk-g...6789"));
+        assert_eq!(invalid.path, "/v1/messages");
+
+        let response = list(0).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_oidc_callback_requires_this_browsers_state() {
+        let oidc = Arc::new(OidcLogin::new(&crate::config::AdminOidcConfig {
+            issuer: "https://sso.example.com".to_string(),
+            client_id: "relay-admin".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "https://relay.example.com/admin/oidc/callback".to_string(),
+            allowed_emails: Vec::new(),
+            allowed_domains: vec!["example.com".to_string()],
+            session_ttl_seconds: 3600,
+        }));
+        let callback = |cookie: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(cookie) = cookie {
+                headers.insert(header::COOKIE, cookie.parse().unwrap());
+            }
+            let query = OidcCallbackQuery {
+                code: Some("code".to_string()),
+                state: Some("victim-state".to_string()),
+                error: None,
+            };
+            oidc_callback(State(oidc.clone()), headers, Query(query))
+        };
+
+        for cookie in [None, Some("relay_oidc_state=attacker-state")] {
+            let response = callback(cookie).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert!(response.headers().get(header::SET_COOKIE).is_none());
+        }
+
+        assert!(state_cookie(&oidc, "s-1", 600)
+            .starts_with("relay_oidc_state=s-1; Path=/admin/oidc; Max-Age=600; HttpOnly"));
+    }
+}
@@ -0,0 +1,290 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures::StreamExt;
+use relay_claude::ClientProfile;
+use relay_core::{AccountProvider, Platform, RelayError, Scheduler};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use super::claude::{extract_client_headers, handle_relay_error, AppError, ClaudeRouteState};
+use crate::db;
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+use crate::routes::check_allowed_model;
+
+const MAX_RETRIES: usize = 3;
+const DEFAULT_LIST_LIMIT: i64 = 20;
+const MAX_LIST_LIMIT: i64 = 100;
+
+/// `POST /v1/messages/batches`
+///
+/// The batch is created on the selected account and remembered, so later
+/// requests for it are sent with that account's credentials.
+pub async fn create(
+    State(state): State<Arc<ClaudeRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(profile): Extension<ClientProfile>,
+    Extension(request_context): Extension<RequestContext>,
+    headers: HeaderMap,
+    Json(mut request): Json<serde_json::Value>,
+) -> Result<Response, AppError> {
+    if let Some(requests) = request["requests"].as_array_mut() {
+        for item in requests {
+            state.model_map.apply_to_body(&mut item["params"]);
+            let model = item["params"]["model"].as_str().unwrap_or_default();
+            if let Some(response) = check_allowed_model(&key_policy, model) {
+                return Ok(response);
+            }
+        }
+    }
+    let model = request["requests"][0]["params"]["model"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    request_context.begin(Platform::Claude, &model, false);
+    let client_headers = extract_client_headers(&headers, profile);
+
+    let mut excluded_accounts: HashSet<String> = HashSet::new();
+    let mut last_error: Option<RelayError> = None;
+    let mut pacer = state.retry.start();
+
+    for attempt in 0..MAX_RETRIES {
+        let account = match state
+            .scheduler
+            .select_tenant_account(
+                Platform::Claude,
+                key_policy.tenant.as_deref(),
+                &request,
+                &excluded_accounts,
+            )
+            .await
+        {
+            Ok(acc) => acc,
+            Err(e) => return Err(last_error.unwrap_or(e).into()),
+        };
+
+        let account_id = account.id().to_string();
+        request_context.set_account(&account_id, attempt);
+        pacer.wait().await;
+
+        match state
+            .relay
+            .create_batch(account.as_ref(), &request, &client_headers)
+            .await
+        {
+            Ok(batch) => {
+                match batch["id"].as_str() {
+                    Some(batch_id) => {
+                        info!(batch_id = batch_id, account_id = %account_id, "Created message batch");
+                        if let Err(e) = db::insert_message_batch(
+                            &state.db_pool,
+                            batch_id,
+                            &account_id,
+                            &api_key_hash.0,
+                        )
+                        .await
+                        {
+                            error!(batch_id = batch_id, error = %e, "Failed to store message batch");
+                        }
+                    }
+                    None => warn!(account_id = %account_id, "Message batch response has no id"),
+                }
+                return Ok(Json(batch).into_response());
+            }
+            Err(e) => {
+                if handle_relay_error(&e, &account_id, &state.scheduler, &state.retryable) {
+                    warn!(
+                        account_id = %account_id,
+                        error = %e,
+                        attempt = attempt + 1,
+                        "Batch creation failed, will try another account"
+                    );
+                    excluded_accounts.insert(account_id);
+                    pacer.failed(&e);
+                    last_error = Some(e);
+                    continue;
+                }
+                return Err(e.into());
+            }
+        }
+    }
+
+    Err(last_error
+        .unwrap_or(RelayError::NoAccount(Platform::Claude))
+        .into())
+}
+
+/// `GET /v1/messages/batches/:id`
+pub async fn get(
+    State(state): State<Arc<ClaudeRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(profile): Extension<ClientProfile>,
+    headers: HeaderMap,
+    Path(batch_id): Path<String>,
+) -> Result<Response, AppError> {
+    let account = match batch_account(&state, &batch_id, &api_key_hash).await {
+        Ok(account) => account,
+        Err(response) => return Ok(response),
+    };
+    let client_headers = extract_client_headers(&headers, profile);
+
+    let batch = state
+        .relay
+        .get_batch(account.as_ref(), &batch_id, &client_headers)
+        .await?;
+    Ok(Json(batch).into_response())
+}
+
+/// `POST /v1/messages/batches/:id/cancel`
+pub async fn cancel(
+    State(state): State<Arc<ClaudeRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(profile): Extension<ClientProfile>,
+    headers: HeaderMap,
+    Path(batch_id): Path<String>,
+) -> Result<Response, AppError> {
+    let account = match batch_account(&state, &batch_id, &api_key_hash).await {
+        Ok(account) => account,
+        Err(response) => return Ok(response),
+    };
+    let client_headers = extract_client_headers(&headers, profile);
+
+    let batch = state
+        .relay
+        .cancel_batch(account.as_ref(), &batch_id, &client_headers)
+        .await?;
+    Ok(Json(batch).into_response())
+}
+
+/// `GET /v1/messages/batches/:id/results`: JSONL, streamed from the
+/// upstream as it arrives.
+pub async fn results(
+    State(state): State<Arc<ClaudeRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(profile): Extension<ClientProfile>,
+    headers: HeaderMap,
+    Path(batch_id): Path<String>,
+) -> Result<Response, AppError> {
+    let account = match batch_account(&state, &batch_id, &api_key_hash).await {
+        Ok(account) => account,
+        Err(response) => return Ok(response),
+    };
+    let client_headers = extract_client_headers(&headers, profile);
+
+    let stream = state
+        .relay
+        .batch_results(account.as_ref(), &batch_id, &client_headers)
+        .await?;
+    let body = Body::from_stream(
+        stream.map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string()))),
+    );
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/x-jsonl")
+        .body(body)
+        .unwrap())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    pub limit: Option<i64>,
+}
+
+/// `GET /v1/messages/batches?limit=20`
+///
+/// Lists the batches created with the caller's key, newest first, each
+/// fetched from the account holding it. Batches on accounts no longer in the
+/// config are skipped.
+pub async fn list(
+    State(state): State<Arc<ClaudeRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(profile): Extension<ClientProfile>,
+    headers: HeaderMap,
+    Query(query): Query<ListQuery>,
+) -> Result<Response, AppError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LIST_LIMIT)
+        .clamp(1, MAX_LIST_LIMIT);
+    let mut rows = match db::list_message_batches(&state.db_pool, &api_key_hash.0, limit + 1).await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!(error = %e, "Failed to list message batches");
+            return Err(RelayError::Internal(e.to_string()).into());
+        }
+    };
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let client_headers = extract_client_headers(&headers, profile);
+
+    let accounts = state.scheduler.all_accounts();
+    let mut data = Vec::with_capacity(rows.len());
+    for (batch_id, account_id) in rows {
+        let Some(account) = accounts.iter().find(|a| a.id() == account_id) else {
+            warn!(batch_id = %batch_id, account_id = %account_id, "Batch account no longer configured");
+            continue;
+        };
+        data.push(
+            state
+                .relay
+                .get_batch(account.as_ref(), &batch_id, &client_headers)
+                .await?,
+        );
+    }
+
+    let first_id = data.first().map(|batch| batch["id"].clone());
+    let last_id = data.last().map(|batch| batch["id"].clone());
+    Ok(Json(serde_json::json!({
+        "data": data,
+        "has_more": has_more,
+        "first_id": first_id,
+        "last_id": last_id,
+    }))
+    .into_response())
+}
+
+/// The account holding a batch created with this client key, or a
+/// `not_found_error` response.
+async fn batch_account(
+    state: &ClaudeRouteState,
+    batch_id: &str,
+    api_key_hash: &ClientApiKeyHash,
+) -> Result<Arc<dyn AccountProvider>, Response> {
+    let account_id =
+        match db::get_message_batch_account(&state.db_pool, batch_id, &api_key_hash.0).await {
+            Ok(account_id) => account_id,
+            Err(e) => {
+                error!(batch_id = batch_id, error = %e, "Failed to look up message batch");
+                return Err(AppError::from(RelayError::Internal(e.to_string())).into_response());
+            }
+        };
+    account_id
+        .and_then(|id| {
+            state
+                .scheduler
+                .all_accounts()
+                .into_iter()
+                .find(|account| account.id() == id)
+        })
+        .ok_or_else(|| not_found(batch_id))
+}
+
+fn not_found(batch_id: &str) -> Response {
+    let body = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "not_found_error",
+            "message": format!("Message batch {} not found", batch_id)
+        }
+    });
+    (StatusCode::NOT_FOUND, Json(body)).into_response()
+}
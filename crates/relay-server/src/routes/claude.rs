@@ -1,28 +1,54 @@
 use axum::{
-    body::Body,
     extract::State,
-    http::{header, HeaderMap, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Extension, Json,
 };
 use bytes::Bytes;
 use futures::stream::StreamExt;
-use relay_claude::{extract_usage_from_chunk, ClientHeaders, ClaudeRelay, MessagesRequest};
-use relay_core::{Platform, RelayError};
+use relay_anthropic_to_gemini::{ClaudeStreamConverter, ClaudeToGeminiConverter};
+use relay_anthropic_to_openai::ClaudeToOpenAIConverter;
+use relay_claude::{ClaudeRelay, ClientHeaders, ClientProfile, MessagesRequest, UsageTracker};
+use relay_core::{Platform, Relay, RelayError};
+use relay_gemini::{GeminiRequest, StreamFormat, StreamUsage};
+use relay_openai::ChatCompletionsRelay;
 use std::collections::HashSet;
 use std::sync::Arc;
-use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, info, warn};
 
-use crate::db::DbPool;
-use crate::middleware::ClientApiKeyHash;
-use crate::routes::record_usage_if_valid;
+use crate::context_limit::ContextLimits;
+use crate::db::{DbPool, TokenUsage};
+use crate::error_budget::UpstreamTimeout;
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+use crate::model_catalog::ModelCatalog;
+use crate::model_map::ModelMap;
+use crate::retry::{RetryBackoff, RetryableStatuses};
+use crate::routes::{
+    check_allowed_model, check_context_limit, check_daily_token_cap, close_upstream,
+    cool_down_on_oauth_failure, model_list, select_account, ApiJson, ChunkUsage, GeminiBackend,
+    StreamSettings, UsageRecord, UsageRecorder, OAUTH_REFRESH_FAILED,
+};
 use crate::scheduler::UnifiedScheduler;
+use crate::server_tools::{ServerToolFilter, STRIPPED_SERVER_TOOLS_HEADER};
 
 pub struct ClaudeRouteState {
     pub scheduler: Arc<UnifiedScheduler>,
     pub relay: Arc<ClaudeRelay>,
     pub db_pool: DbPool,
+    pub context_limits: Arc<ContextLimits>,
+    pub model_map: Arc<ModelMap>,
+    pub models: Arc<ModelCatalog>,
+    pub usage: Arc<UsageRecorder>,
+    /// Serves `/v1/messages` from Gemini accounts instead of Claude ones.
+    pub gemini: Option<GeminiBackend>,
+    /// Serves `/v1/messages` from OpenAI-compatible accounts when no Claude
+    /// account is available.
+    pub openai_compatible: Option<Arc<ChatCompletionsRelay>>,
+    /// Server tools each account accepts.
+    pub server_tools: Arc<ServerToolFilter>,
+    pub streaming: StreamSettings,
+    pub retry: RetryBackoff,
+    pub retryable: RetryableStatuses,
 }
 
 const CLAUDE_CODE_HEADER_KEYS: &[&str] = &[
@@ -44,7 +70,7 @@ const CLAUDE_CODE_HEADER_KEYS: &[&str] = &[
 
 const MAX_RETRIES: usize = 3;
 
-fn extract_client_headers(headers: &HeaderMap) -> ClientHeaders {
+pub(super) fn extract_client_headers(headers: &HeaderMap, profile: ClientProfile) -> ClientHeaders {
     let mut client_headers = ClientHeaders::new();
 
     for key in CLAUDE_CODE_HEADER_KEYS {
@@ -55,26 +81,51 @@ fn extract_client_headers(headers: &HeaderMap) -> ClientHeaders {
         }
     }
 
+    if profile == ClientProfile::AgentSdk {
+        // SDK releases add stainless headers faster than we can list them
+        for (name, value) in headers {
+            if name.as_str().starts_with("x-stainless-") {
+                if let Ok(v) = value.to_str() {
+                    client_headers.insert(name.to_string(), v.to_string());
+                }
+            }
+        }
+    }
+
     if client_headers.is_empty() {
-        return ClientHeaders::with_defaults();
+        client_headers = ClientHeaders::with_defaults_for(profile);
     }
 
+    client_headers.profile = profile;
+    client_headers.beta = headers
+        .get("anthropic-beta")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
     client_headers
 }
 
-fn handle_relay_error(
+pub(super) fn handle_relay_error(
     error: &RelayError,
     account_id: &str,
     scheduler: &UnifiedScheduler,
+    retryable: &RetryableStatuses,
 ) -> bool {
     match error {
         RelayError::RateLimited(retry_after) => {
             scheduler.mark_account_rate_limited(account_id, *retry_after);
-            true
+            retryable.contains(error)
         }
-        RelayError::Overloaded { retry_after_minutes } => {
-            scheduler.mark_account_overloaded(account_id, *retry_after_minutes as u64);
-            true
+        RelayError::Overloaded { retry_after_secs } => {
+            scheduler.mark_account_overloaded(account_id, *retry_after_secs);
+            retryable.contains(error)
+        }
+        RelayError::Upstream {
+            status: status @ 500..=599,
+            ..
+        } => {
+            scheduler.mark_account_server_error(account_id, *status);
+            retryable.contains(error)
         }
         RelayError::OpusWeeklyLimit => {
             scheduler.mark_account_unavailable(account_id, "opus_weekly_limit");
@@ -92,38 +143,106 @@ fn handle_relay_error(
             scheduler.mark_account_unavailable(account_id, "insufficient_quota");
             true
         }
+        RelayError::OAuth(_) => {
+            scheduler.mark_account_unavailable(account_id, OAUTH_REFRESH_FAILED);
+            true
+        }
+        RelayError::FirstByteTimeout {
+            retry_after_secs, ..
+        } => {
+            scheduler.mark_account_first_byte_timeout(account_id, *retry_after_secs);
+            true
+        }
         RelayError::ContentFiltered(_) => {
             false
         }
-        _ => false,
+        _ => retryable.contains(error),
     }
 }
 
 pub async fn messages(
     State(state): State<Arc<ClaudeRouteState>>,
     Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(profile): Extension<ClientProfile>,
+    Extension(request_context): Extension<RequestContext>,
     headers: HeaderMap,
-    Json(request): Json<MessagesRequest>,
+    ApiJson(mut request): ApiJson<MessagesRequest>,
 ) -> Result<Response, AppError> {
+    request.validate()?;
+    let pin = key_policy.pinned_account.clone();
+    state.model_map.apply(&mut request.model);
     let is_stream = request.stream;
     let model = request.model.clone();
+    request_context.begin(Platform::Claude, &model, is_stream);
+
+    if let Some(response) = check_allowed_model(&key_policy, &model) {
+        return Ok(response);
+    }
+    if let Some(response) =
+        check_daily_token_cap(&state.db_pool, &api_key_hash, &key_policy).await
+    {
+        return Ok(response);
+    }
+
+    if let Some(gemini) = state.gemini.as_ref().filter(|_| pin.is_none()) {
+        let tenant = key_policy.tenant.as_deref();
+        return messages_via_gemini(
+            &state,
+            gemini,
+            api_key_hash,
+            tenant,
+            request_context,
+            request,
+        )
+        .await;
+    }
 
     info!(model = %model, stream = is_stream, "Received Claude messages request");
 
     let body_value = serde_json::to_value(&request).unwrap_or_default();
-    let client_headers = extract_client_headers(&headers);
+    let client_headers = extract_client_headers(&headers, profile);
+
+    // The 1M-token beta lifts the window beyond what the table knows about
+    let long_context = client_headers
+        .beta
+        .as_deref()
+        .is_some_and(|beta| beta.contains("context-1m"));
+    if !long_context {
+        if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+            return Ok(response);
+        }
+    }
 
     let mut excluded_accounts: HashSet<String> = HashSet::new();
     let mut last_error: Option<RelayError> = None;
+    let mut pacer = state.retry.start();
 
     for attempt in 0..MAX_RETRIES {
-        let account = match state
-            .scheduler
-            .select_account_excluding(Platform::Claude, &body_value, &excluded_accounts)
-            .await
+        let account = match select_account(
+            &state.scheduler,
+            pin.as_deref(),
+            key_policy.tenant.as_deref(),
+            Platform::Claude,
+            &body_value,
+            &excluded_accounts,
+        )
+        .await
         {
             Ok(acc) => acc,
+            Err(e) if pin.is_some() => return Err(AppError(e)),
             Err(e) => {
+                if let Some(relay) = &state.openai_compatible {
+                    return messages_via_openai_compatible(
+                        &state,
+                        relay,
+                        api_key_hash,
+                        key_policy.tenant.as_deref(),
+                        request_context,
+                        request,
+                    )
+                    .await;
+                }
                 if let Some(prev_error) = last_error {
                     return Err(AppError(prev_error));
                 }
@@ -132,6 +251,8 @@ pub async fn messages(
         };
 
         let account_id = account.id().to_string();
+        request_context.set_account(&account_id, attempt);
+        pacer.wait().await;
 
         if attempt > 0 {
             info!(
@@ -141,30 +262,56 @@ pub async fn messages(
             );
         }
 
+        let mut account_request = request.clone();
+        let stripped_tools = state.server_tools.apply(
+            &account_id,
+            &mut account_request.tools,
+            &mut account_request.tool_choice,
+        );
+        if !stripped_tools.is_empty() {
+            warn!(
+                account_id = %account_id,
+                tools = ?stripped_tools,
+                "Stripped server tools the account does not accept"
+            );
+        }
+
         let result = if is_stream {
-            state
-                .relay
-                .relay_stream_with_headers(account.as_ref(), request.clone(), &client_headers)
-                .await
+            let connect = state.relay.relay_stream_with_headers(
+                account.as_ref(),
+                account_request,
+                &client_headers,
+            );
+            state.streaming.first_byte(connect).await
         } else {
             match state
                 .relay
-                .relay_with_headers(account.as_ref(), request.clone(), &client_headers)
+                .relay_with_headers(account.as_ref(), account_request, &client_headers)
                 .await
             {
                 Ok(response) => {
-                    record_usage_if_valid(
-                        &state.db_pool,
-                        &api_key_hash,
-                        &account_id,
-                        &model,
-                        response.usage.input_tokens,
-                        response.usage.output_tokens,
-                        response.usage.cache_creation_input_tokens.unwrap_or(0),
-                        response.usage.cache_read_input_tokens.unwrap_or(0),
-                    )
-                    .await;
-                    return Ok(Json(response).into_response());
+                    state
+                        .usage
+                        .record(
+                            &request_context,
+                            &api_key_hash,
+                            &account_id,
+                            &model,
+                            TokenUsage {
+                                input_tokens: response.usage.input_tokens,
+                                output_tokens: response.usage.output_tokens,
+                                cache_creation_tokens: response
+                                    .usage
+                                    .cache_creation_input_tokens
+                                    .unwrap_or(0),
+                                cache_read_tokens: response.usage.cache_read_input_tokens.unwrap_or(0),
+                                ..Default::default()
+                            },
+                        )
+                        .await;
+                    let mut response = Json(response).into_response();
+                    mark_stripped_tools(&mut response, &stripped_tools);
+                    return Ok(response);
                 }
                 Err(e) => Err(e),
             }
@@ -172,72 +319,32 @@ pub async fn messages(
 
         match result {
             Ok(stream) => {
-                let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(32);
-
-                let db_pool = state.db_pool.clone();
-                let api_key_hash_clone = api_key_hash.clone();
-                let account_id_clone = account_id.clone();
-                let model_clone = model.clone();
-
-                tokio::spawn(async move {
-                    let mut stream = stream;
-                    let mut total_input = 0u32;
-                    let mut total_output = 0u32;
-                    let mut cache_creation = 0u32;
-                    let mut cache_read = 0u32;
-
-                    while let Some(chunk) = stream.next().await {
-                        match chunk {
-                            Ok(bytes) => {
-                                if let Some(usage) = extract_usage_from_chunk(&bytes) {
-                                    total_input = total_input.max(usage.input_tokens);
-                                    total_output = total_output.max(usage.output_tokens);
-                                    if let Some(cc) = usage.cache_creation_input_tokens {
-                                        cache_creation = cache_creation.max(cc);
-                                    }
-                                    if let Some(cr) = usage.cache_read_input_tokens {
-                                        cache_read = cache_read.max(cr);
-                                    }
-                                }
-
-                                if tx.send(Ok(bytes)).await.is_err() {
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                error!(error = %e, "Stream error");
-                                break;
-                            }
-                        }
-                    }
-
-                    record_usage_if_valid(
-                        &db_pool,
-                        &api_key_hash_clone,
-                        &account_id_clone,
-                        &model_clone,
-                        total_input,
-                        total_output,
-                        cache_creation,
-                        cache_read,
-                    )
-                    .await;
-                });
-
-                let body = Body::from_stream(ReceiverStream::new(rx));
-
-                return Ok(Response::builder()
+                let record = UsageRecord {
+                    recorder: state.usage.clone(),
+                    context: request_context.clone(),
+                    api_key_hash: api_key_hash.clone(),
+                    account_id: account_id.clone(),
+                    model: model.clone(),
+                };
+                let body = state
+                    .streaming
+                    .passthrough(stream, UsageTracker::new(), record);
+
+                let mut response = Response::builder()
                     .status(StatusCode::OK)
                     .header(header::CONTENT_TYPE, "text/event-stream")
                     .header(header::CACHE_CONTROL, "no-cache")
                     .header("X-Accel-Buffering", "no")
                     .body(body)
-                    .unwrap());
+                    .unwrap();
+                mark_stripped_tools(&mut response, &stripped_tools);
+                return Ok(response);
             }
             Err(e) => {
-                let should_retry = handle_relay_error(&e, &account_id, &state.scheduler);
+                let should_retry =
+                    handle_relay_error(&e, &account_id, &state.scheduler, &state.retryable);
 
-                if should_retry {
+                if should_retry && pin.is_none() {
                     warn!(
                         account_id = %account_id,
                         error = %e,
@@ -245,6 +352,7 @@ pub async fn messages(
                         "Request failed, will try another account"
                     );
                     excluded_accounts.insert(account_id);
+                    pacer.failed(&e);
                     last_error = Some(e);
                     continue;
                 }
@@ -257,17 +365,365 @@ pub async fn messages(
     Err(AppError(last_error.unwrap_or(RelayError::NoAccount(Platform::Claude))))
 }
 
-pub async fn models() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "object": "list",
-        "data": [
-            {"id": "claude-sonnet-4-20250514", "object": "model", "created": 1704067200, "owned_by": "anthropic"},
-            {"id": "claude-3-5-sonnet-20241022", "object": "model", "created": 1704067200, "owned_by": "anthropic"},
-            {"id": "claude-3-5-haiku-20241022", "object": "model", "created": 1704067200, "owned_by": "anthropic"},
-            {"id": "claude-3-opus-20240229", "object": "model", "created": 1704067200, "owned_by": "anthropic"},
-            {"id": "claude-opus-4-20250514", "object": "model", "created": 1704067200, "owned_by": "anthropic"}
-        ]
-    }))
+impl ChunkUsage for UsageTracker {
+    fn push(&mut self, chunk: &Bytes) {
+        UsageTracker::push(self, chunk);
+    }
+
+    fn usage(&self) -> Option<TokenUsage> {
+        let usage = UsageTracker::usage(self);
+        Some(TokenUsage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_creation_tokens: usage.cache_creation_input_tokens.unwrap_or(0),
+            cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
+            ..Default::default()
+        })
+    }
+}
+
+/// Lists the server tools stripped from the request in a response header.
+fn mark_stripped_tools(response: &mut Response, stripped_tools: &[String]) {
+    if stripped_tools.is_empty() {
+        return;
+    }
+    if let Ok(value) = HeaderValue::from_str(&stripped_tools.join(",")) {
+        response
+            .headers_mut()
+            .insert(STRIPPED_SERVER_TOOLS_HEADER, value);
+    }
+}
+
+/// Serves an Anthropic Messages request from a Gemini account by translating
+/// it to `generateContent` and the reply back to Anthropic events.
+async fn messages_via_gemini(
+    state: &ClaudeRouteState,
+    gemini: &GeminiBackend,
+    api_key_hash: ClientApiKeyHash,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    request: MessagesRequest,
+) -> Result<Response, AppError> {
+    let is_stream = request.stream;
+    let model = gemini.model_for(&request.model);
+    request_context.begin(Platform::Gemini, &model, is_stream);
+
+    info!(
+        requested_model = %request.model,
+        model = %model,
+        stream = is_stream,
+        "Serving Claude messages request from Gemini"
+    );
+
+    let body = ClaudeToGeminiConverter::convert_request(request)?;
+    let body_value = serde_json::to_value(&body).unwrap_or_default();
+
+    if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+        return Ok(response);
+    }
+
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::Gemini, tenant, &body_value, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    let gemini_request = GeminiRequest {
+        model: model.clone(),
+        body,
+        stream: is_stream,
+        stream_format: StreamFormat::Sse,
+    };
+
+    if is_stream {
+        let stream = gemini
+            .relay
+            .relay_stream(account.as_ref(), gemini_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        let stream = state.streaming.cut(stream);
+        let (tx, rx) = state.streaming.channel();
+
+        let recorder = state.usage.clone();
+        let request_context = request_context.clone();
+
+        tokio::spawn(async move {
+            let mut stream = stream;
+            let mut converter = ClaudeStreamConverter::new(&model);
+            let mut tracker = StreamUsage::new(StreamFormat::Sse);
+
+            'forward: while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        tracker.push(&bytes);
+
+                        for event in converter.push(&bytes) {
+                            let sse_data = ClaudeStreamConverter::encode(&event);
+                            if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                                info!("Client disconnected, closing upstream stream");
+                                break 'forward;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!(error = %e, "Stream error");
+                        break;
+                    }
+                }
+            }
+            close_upstream(stream);
+
+            for event in converter.finish() {
+                let sse_data = ClaudeStreamConverter::encode(&event);
+                if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                    break;
+                }
+            }
+
+            recorder
+                .record(
+                    &request_context,
+                    &api_key_hash,
+                    &account_id,
+                    &model,
+                    tracker.total(),
+                )
+                .await;
+        });
+
+        let body = state.streaming.body(rx);
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Accel-Buffering", "no")
+            .body(body)
+            .unwrap())
+    } else {
+        let response = gemini
+            .relay
+            .relay(account.as_ref(), gemini_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        let message = ClaudeToGeminiConverter::convert_response(response, &model);
+
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: message.usage.input_tokens,
+                    output_tokens: message.usage.output_tokens,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        Ok(Json(message).into_response())
+    }
+}
+
+/// Serves an Anthropic Messages request from an OpenAI-compatible account,
+/// the last resort once every Claude account is unavailable, by translating
+/// it to `chat/completions` and the reply back to Anthropic events.
+async fn messages_via_openai_compatible(
+    state: &ClaudeRouteState,
+    relay: &ChatCompletionsRelay,
+    api_key_hash: ClientApiKeyHash,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    request: MessagesRequest,
+) -> Result<Response, AppError> {
+    let is_stream = request.stream;
+    let model = request.model.clone();
+    request_context.begin(Platform::OpenAI, &model, is_stream);
+
+    info!(
+        model = %model,
+        stream = is_stream,
+        "Serving Claude messages request from an OpenAI-compatible account"
+    );
+
+    let openai_request = ClaudeToOpenAIConverter::convert_request(request)?;
+    let body_value = serde_json::to_value(&openai_request).unwrap_or_default();
+
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::OpenAI, tenant, &body_value, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    if is_stream {
+        let stream = relay.relay_stream(account.as_ref(), openai_request).await?;
+
+        let stream = state.streaming.cut(stream);
+        let (tx, rx) = state.streaming.channel();
+
+        let recorder = state.usage.clone();
+        let request_context = request_context.clone();
+
+        tokio::spawn(async move {
+            let mut stream = stream;
+            let mut converter = relay_anthropic_to_openai::ClaudeStreamConverter::new(&model);
+            let mut tracker = relay_openai::UsageTracker::new();
+
+            'forward: while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        tracker.push(&bytes);
+
+                        for event in converter.push(&bytes) {
+                            let sse_data =
+                                relay_anthropic_to_openai::ClaudeStreamConverter::encode(&event);
+                            if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                                info!("Client disconnected, closing upstream stream");
+                                break 'forward;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!(error = %e, "Stream error");
+                        break;
+                    }
+                }
+            }
+            close_upstream(stream);
+
+            for event in converter.finish() {
+                let sse_data = relay_anthropic_to_openai::ClaudeStreamConverter::encode(&event);
+                if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                    break;
+                }
+            }
+
+            recorder
+                .record(
+                    &request_context,
+                    &api_key_hash,
+                    &account_id,
+                    &model,
+                    tracker.total(),
+                )
+                .await;
+        });
+
+        let body = state.streaming.body(rx);
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Accel-Buffering", "no")
+            .body(body)
+            .unwrap())
+    } else {
+        let response = relay.relay(account.as_ref(), openai_request).await?;
+
+        let message = ClaudeToOpenAIConverter::convert_response(response, &model);
+
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: message.usage.input_tokens,
+                    output_tokens: message.usage.output_tokens,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        Ok(Json(message).into_response())
+    }
+}
+
+/// `POST /v1/messages/count_tokens`: forwarded to the upstream with the same
+/// account selection and headers as `/v1/messages`. Counting is free, so
+/// neither the daily token cap nor usage recording applies.
+pub async fn count_tokens(
+    State(state): State<Arc<ClaudeRouteState>>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(profile): Extension<ClientProfile>,
+    Extension(request_context): Extension<RequestContext>,
+    headers: HeaderMap,
+    ApiJson(mut request): ApiJson<serde_json::Value>,
+) -> Result<Response, AppError> {
+    state.model_map.apply_to_body(&mut request);
+    let model = request["model"].as_str().unwrap_or_default().to_string();
+    request_context.begin(Platform::Claude, &model, false);
+    let client_headers = extract_client_headers(&headers, profile);
+
+    let mut excluded_accounts: HashSet<String> = HashSet::new();
+    let mut last_error: Option<RelayError> = None;
+    let mut pacer = state.retry.start();
+
+    for attempt in 0..MAX_RETRIES {
+        let account = match state
+            .scheduler
+            .select_tenant_account(
+                Platform::Claude,
+                key_policy.tenant.as_deref(),
+                &request,
+                &excluded_accounts,
+            )
+            .await
+        {
+            Ok(acc) => acc,
+            Err(e) => return Err(AppError(last_error.unwrap_or(e))),
+        };
+
+        let account_id = account.id().to_string();
+        request_context.set_account(&account_id, attempt);
+        pacer.wait().await;
+
+        match state
+            .relay
+            .count_tokens(account.as_ref(), &request, &client_headers)
+            .await
+        {
+            Ok(response) => return Ok(Json(response).into_response()),
+            Err(e) => {
+                if handle_relay_error(&e, &account_id, &state.scheduler, &state.retryable) {
+                    warn!(
+                        account_id = %account_id,
+                        error = %e,
+                        attempt = attempt + 1,
+                        "count_tokens failed, will try another account"
+                    );
+                    excluded_accounts.insert(account_id);
+                    pacer.failed(&e);
+                    last_error = Some(e);
+                    continue;
+                }
+                return Err(AppError(e));
+            }
+        }
+    }
+
+    Err(AppError(
+        last_error.unwrap_or(RelayError::NoAccount(Platform::Claude)),
+    ))
+}
+
+/// Claude models followed by the `[model_map]` aliases.
+pub async fn models(State(state): State<Arc<ClaudeRouteState>>) -> impl IntoResponse {
+    let claude = state.models.claude_models().await;
+    let models = claude.iter().map(|m| (m.as_str(), "anthropic"));
+    let aliases = state.models.aliases().iter().map(|m| (m.as_str(), "relay"));
+    Json(model_list(models.chain(aliases)))
 }
 
 pub struct AppError(RelayError);
@@ -281,17 +737,24 @@ impl From<RelayError> for AppError {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, message) = match &self.0 {
+            RelayError::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            RelayError::InvalidTool(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             RelayError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg.clone()),
             RelayError::ContentFiltered(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             RelayError::OrganizationDisabled(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             RelayError::RateLimited(retry_after) => (
                 StatusCode::TOO_MANY_REQUESTS,
-                format!("Rate limited, retry after {} seconds", retry_after),
+                match retry_after {
+                    Some(secs) => format!("Rate limited, retry after {} seconds", secs),
+                    None => "Rate limited".to_string(),
+                },
             ),
             RelayError::NoAccount(platform) => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 format!("No available account for {:?}", platform),
             ),
+            RelayError::Timeout(msg) => (StatusCode::GATEWAY_TIMEOUT, msg.clone()),
+            e @ RelayError::FirstByteTimeout { .. } => (StatusCode::GATEWAY_TIMEOUT, e.to_string()),
             RelayError::Upstream { status, message } => (
                 StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY),
                 message.clone(),
@@ -301,13 +764,25 @@ impl IntoResponse for AppError {
 
         error!(error = %self.0, "Request error");
 
+        let error_type = match self.0 {
+            RelayError::InvalidRequest(_) => "invalid_request_error",
+            _ => "api_error",
+        };
         let body = serde_json::json!({
+            "type": "error",
             "error": {
-                "type": "api_error",
+                "type": error_type,
                 "message": message
             }
         });
 
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if matches!(
+            self.0,
+            RelayError::Timeout(_) | RelayError::FirstByteTimeout { .. }
+        ) {
+            response.extensions_mut().insert(UpstreamTimeout);
+        }
+        response
     }
 }
@@ -3,26 +3,61 @@ use axum::{
     extract::State,
     http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use bytes::Bytes;
 use futures::stream::StreamExt;
-use relay_codex::{CodexRelay, ResponsesRequest};
-use relay_core::{Platform, RelayError};
+use relay_codex::{CodexRelay, ResponsesRequest, ResponsesUsage, UsageTracker};
+use relay_core::{Platform, Relay, RelayError};
+use relay_openai_to_anthropic::{ResponsesStreamConverter, ResponsesToClaudeConverter};
 use std::collections::HashSet;
 use std::sync::Arc;
-use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, info, warn};
 
 use super::claude::AppError;
-use crate::db::DbPool;
+use crate::context_limit::ContextLimits;
+use crate::db::{DbPool, TokenUsage};
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+use crate::model_map::ModelMap;
+use crate::retry::{RetryBackoff, RetryableStatuses};
+use crate::routes::{
+    check_allowed_model, check_context_limit, check_daily_token_cap, close_upstream,
+    cool_down_on_oauth_failure, select_account, ChunkUsage, ClaudeBackend, StreamSettings,
+    UsageRecord, UsageRecorder,
+};
 use crate::scheduler::UnifiedScheduler;
 
 pub struct CodexRouteState {
     pub scheduler: Arc<UnifiedScheduler>,
     pub relay: Arc<CodexRelay>,
-    #[allow(dead_code)] // Reserved for future usage tracking when Codex API exposes token counts
     pub db_pool: DbPool,
+    pub context_limits: Arc<ContextLimits>,
+    pub model_map: Arc<ModelMap>,
+    pub usage: Arc<UsageRecorder>,
+    /// Set when Responses requests are served from Claude accounts.
+    pub claude: Option<ClaudeBackend>,
+    pub streaming: StreamSettings,
+    pub retry: RetryBackoff,
+    pub retryable: RetryableStatuses,
+}
+
+fn token_usage(usage: ResponsesUsage) -> TokenUsage {
+    TokenUsage {
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        reasoning_tokens: usage.reasoning_tokens,
+        ..Default::default()
+    }
+}
+
+impl ChunkUsage for UsageTracker {
+    fn push(&mut self, chunk: &Bytes) {
+        UsageTracker::push(self, chunk);
+    }
+
+    fn usage(&self) -> Option<TokenUsage> {
+        UsageTracker::usage(self).map(token_usage)
+    }
 }
 
 const MAX_RETRIES: usize = 3;
@@ -31,15 +66,23 @@ fn handle_relay_error(
     error: &RelayError,
     account_id: &str,
     scheduler: &UnifiedScheduler,
+    retryable: &RetryableStatuses,
 ) -> bool {
     match error {
         RelayError::RateLimited(retry_after) => {
             scheduler.mark_account_rate_limited(account_id, *retry_after);
-            true
+            retryable.contains(error)
         }
-        RelayError::Overloaded { retry_after_minutes } => {
-            scheduler.mark_account_overloaded(account_id, *retry_after_minutes as u64);
-            true
+        RelayError::Overloaded { retry_after_secs } => {
+            scheduler.mark_account_overloaded(account_id, *retry_after_secs);
+            retryable.contains(error)
+        }
+        RelayError::Upstream {
+            status: status @ 500..=599,
+            ..
+        } => {
+            scheduler.mark_account_server_error(account_id, *status);
+            retryable.contains(error)
         }
         RelayError::Unauthorized(_) => {
             scheduler.mark_account_unavailable(account_id, "unauthorized");
@@ -49,33 +92,76 @@ fn handle_relay_error(
             scheduler.mark_account_unavailable(account_id, "insufficient_quota");
             true
         }
+        RelayError::FirstByteTimeout {
+            retry_after_secs, ..
+        } => {
+            scheduler.mark_account_first_byte_timeout(account_id, *retry_after_secs);
+            true
+        }
         RelayError::ContentFiltered(_) => {
             false
         }
-        _ => false,
+        _ => retryable.contains(error),
     }
 }
 
 pub async fn responses(
     State(state): State<Arc<CodexRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(request_context): Extension<RequestContext>,
     _headers: HeaderMap,
-    Json(request): Json<ResponsesRequest>,
+    Json(mut request): Json<ResponsesRequest>,
 ) -> Result<Response, AppError> {
+    let pin = key_policy.pinned_account.clone();
+    state.model_map.apply(&mut request.model);
+    if let Some(claude) = state.claude.as_ref().filter(|_| pin.is_none()) {
+        return responses_via_claude(
+            &state,
+            claude,
+            api_key_hash,
+            key_policy,
+            request_context,
+            request,
+        )
+        .await;
+    }
+
     let is_stream = request.stream;
     let model = request.model.clone();
+    request_context.begin(Platform::Codex, &model, is_stream);
+
+    if let Some(response) = check_allowed_model(&key_policy, &model) {
+        return Ok(response);
+    }
+    if let Some(response) =
+        check_daily_token_cap(&state.db_pool, &api_key_hash, &key_policy).await
+    {
+        return Ok(response);
+    }
 
     info!(model = %model, stream = is_stream, "Received OpenAI Responses request");
 
     let body_value = serde_json::to_value(&request).unwrap_or_default();
 
+    if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+        return Ok(response);
+    }
+
     let mut excluded_accounts: HashSet<String> = HashSet::new();
     let mut last_error: Option<RelayError> = None;
+    let mut pacer = state.retry.start();
 
     for attempt in 0..MAX_RETRIES {
-        let account = match state
-            .scheduler
-            .select_account_excluding(Platform::Codex, &body_value, &excluded_accounts)
-            .await
+        let account = match select_account(
+            &state.scheduler,
+            pin.as_deref(),
+            key_policy.tenant.as_deref(),
+            Platform::Codex,
+            &body_value,
+            &excluded_accounts,
+        )
+        .await
         {
             Ok(acc) => acc,
             Err(e) => {
@@ -87,6 +173,8 @@ pub async fn responses(
         };
 
         let account_id = account.id().to_string();
+        request_context.set_account(&account_id, attempt);
+        pacer.wait().await;
 
         if attempt > 0 {
             info!(
@@ -97,43 +185,47 @@ pub async fn responses(
         }
 
         let result = if is_stream {
-            state
+            let connect = state
                 .relay
-                .relay_stream(account.as_ref(), request.clone(), "/responses")
-                .await
+                .relay_stream(account.as_ref(), request.clone(), "/responses");
+            state.streaming.first_byte(connect).await
         } else {
             match state
                 .relay
                 .relay(account.as_ref(), request.clone(), "/responses")
                 .await
             {
-                Ok(response) => return Ok(Json(response).into_response()),
+                Ok(response) => {
+                    if let Some(usage) = response.usage() {
+                        state
+                            .usage
+                            .record(
+                                &request_context,
+                                &api_key_hash,
+                                &account_id,
+                                &model,
+                                token_usage(usage),
+                            )
+                            .await;
+                    }
+                    return Ok(Json(response).into_response());
+                }
                 Err(e) => Err(e),
             }
         };
 
         match result {
             Ok(stream) => {
-                let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(32);
-
-                tokio::spawn(async move {
-                    let mut stream = stream;
-                    while let Some(chunk) = stream.next().await {
-                        match chunk {
-                            Ok(bytes) => {
-                                if tx.send(Ok(bytes)).await.is_err() {
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                error!(error = %e, "Codex stream error");
-                                break;
-                            }
-                        }
-                    }
-                });
-
-                let body = Body::from_stream(ReceiverStream::new(rx));
+                let record = UsageRecord {
+                    recorder: state.usage.clone(),
+                    context: request_context.clone(),
+                    api_key_hash: api_key_hash.clone(),
+                    account_id: account_id.clone(),
+                    model: model.clone(),
+                };
+                let body = state
+                    .streaming
+                    .passthrough(stream, UsageTracker::new(), record);
 
                 return Ok(Response::builder()
                     .status(StatusCode::OK)
@@ -144,9 +236,10 @@ pub async fn responses(
                     .unwrap());
             }
             Err(e) => {
-                let should_retry = handle_relay_error(&e, &account_id, &state.scheduler);
+                let should_retry =
+                    handle_relay_error(&e, &account_id, &state.scheduler, &state.retryable);
 
-                if should_retry {
+                if should_retry && pin.is_none() {
                     warn!(
                         account_id = %account_id,
                         error = %e,
@@ -154,6 +247,7 @@ pub async fn responses(
                         "Codex request failed, will try another account"
                     );
                     excluded_accounts.insert(account_id);
+                    pacer.failed(&e);
                     last_error = Some(e);
                     continue;
                 }
@@ -165,3 +259,201 @@ pub async fn responses(
 
     Err(AppError::from(last_error.unwrap_or(RelayError::NoAccount(Platform::Codex))))
 }
+
+/// Serves a Responses request from a Claude account by translating it to
+/// Anthropic Messages and the reply back to the Responses format.
+async fn responses_via_claude(
+    state: &CodexRouteState,
+    claude: &ClaudeBackend,
+    api_key_hash: ClientApiKeyHash,
+    key_policy: ApiKeyPolicy,
+    request_context: RequestContext,
+    request: ResponsesRequest,
+) -> Result<Response, AppError> {
+    let is_stream = request.stream;
+    let model = claude.model_for(&request.model);
+    request_context.begin(Platform::Claude, &model, is_stream);
+
+    if let Some(response) = check_allowed_model(&key_policy, &model) {
+        return Ok(response);
+    }
+    if let Some(response) =
+        check_daily_token_cap(&state.db_pool, &api_key_hash, &key_policy).await
+    {
+        return Ok(response);
+    }
+
+    info!(
+        requested_model = %request.model,
+        model = %model,
+        stream = is_stream,
+        "Serving OpenAI Responses request from Claude"
+    );
+
+    let request_value = serde_json::to_value(&request).unwrap_or_default();
+    let claude_request = ResponsesToClaudeConverter::convert_request(&request_value, &model)?;
+    let body_value = serde_json::to_value(&claude_request).unwrap_or_default();
+
+    if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+        return Ok(response);
+    }
+
+    let account = state
+        .scheduler
+        .select_tenant_account(
+            Platform::Claude,
+            key_policy.tenant.as_deref(),
+            &body_value,
+            &HashSet::new(),
+        )
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    if is_stream {
+        let stream = claude
+            .relay
+            .relay_stream(account.as_ref(), claude_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        let stream = state.streaming.cut(stream);
+        let (tx, rx) = state.streaming.channel();
+
+        let recorder = state.usage.clone();
+        let request_context = request_context.clone();
+        let account_id_clone = account_id.clone();
+        let model_clone = model.clone();
+
+        tokio::spawn(async move {
+            let mut stream = stream;
+            let mut converter = ResponsesStreamConverter::new();
+            let mut tracker = relay_claude::UsageTracker::new();
+
+            'forward: while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        tracker.push(&bytes);
+
+                        for event in converter.push(&bytes) {
+                            let sse_data = ResponsesStreamConverter::encode(&event);
+                            if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                                info!("Client disconnected, closing upstream stream");
+                                break 'forward;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!(error = %e, "Stream error");
+                        break;
+                    }
+                }
+            }
+            close_upstream(stream);
+
+            recorder
+                .record(
+                    &request_context,
+                    &api_key_hash,
+                    &account_id_clone,
+                    &model_clone,
+                    tracker.total(),
+                )
+                .await;
+        });
+
+        let body = state.streaming.body(rx);
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Accel-Buffering", "no")
+            .body(body)
+            .unwrap())
+    } else {
+        let response = claude
+            .relay
+            .relay(account.as_ref(), claude_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: response.usage.input_tokens,
+                    output_tokens: response.usage.output_tokens,
+                    cache_creation_tokens: response.usage.cache_creation_input_tokens.unwrap_or(0),
+                    cache_read_tokens: response.usage.cache_read_input_tokens.unwrap_or(0),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        Ok(Json(ResponsesToClaudeConverter::convert_response(response)).into_response())
+    }
+}
+
+/// Forwards an audio transcription upload to a Codex account. The multipart
+/// body is streamed through unread, so a failed request is not retried.
+pub async fn audio_transcriptions(
+    State(state): State<Arc<CodexRouteState>>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(request_context): Extension<RequestContext>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with("multipart/form-data") {
+        return Err(AppError::from(RelayError::InvalidRequest(
+            "Expected a multipart/form-data body".to_string(),
+        )));
+    }
+
+    // The model is a form field, which is never parsed here
+    request_context.begin(Platform::Codex, "", false);
+
+    info!("Received OpenAI audio transcription request");
+
+    let account = state
+        .scheduler
+        .select_tenant_account(
+            Platform::Codex,
+            key_policy.tenant.as_deref(),
+            &serde_json::Value::Null,
+            &HashSet::new(),
+        )
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    let response = state
+        .relay
+        .relay_raw(
+            account.as_ref(),
+            "/audio/transcriptions",
+            &content_type,
+            reqwest::Body::wrap_stream(body.into_data_stream()),
+        )
+        .await
+        .inspect_err(|e| {
+            handle_relay_error(e, &account_id, &state.scheduler, &state.retryable);
+        })?;
+
+    let mut builder = Response::builder().status(StatusCode::OK);
+    if let Some(content_type) = response.content_type {
+        builder = builder.header(header::CONTENT_TYPE, content_type);
+    }
+    Ok(builder.body(Body::from(response.body)).unwrap())
+}
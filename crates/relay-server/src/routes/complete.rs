@@ -0,0 +1,69 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap},
+    response::Response,
+    Extension,
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use relay_claude::{
+    ClientProfile, CompleteRequest, CompleteResponse, CompleteStreamConverter, MessagesResponse,
+};
+use relay_core::RelayError;
+use std::sync::Arc;
+
+use super::claude::{messages, AppError, ClaudeRouteState};
+use super::ApiJson;
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+
+/// `POST /v1/complete`
+///
+/// Legacy Text Completions requests are served as Messages requests, so
+/// they get the same account selection, retries and fallbacks, and the
+/// reply is translated back. Error responses are returned unchanged.
+pub async fn complete(
+    State(state): State<Arc<ClaudeRouteState>>,
+    api_key_hash: Extension<ClientApiKeyHash>,
+    key_policy: Extension<ApiKeyPolicy>,
+    profile: Extension<ClientProfile>,
+    request_context: Extension<RequestContext>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<CompleteRequest>,
+) -> Result<Response, AppError> {
+    let is_stream = request.stream;
+    let request = request.into_messages_request()?;
+
+    let response = messages(
+        State(state),
+        api_key_hash,
+        key_policy,
+        profile,
+        request_context,
+        headers,
+        ApiJson(request),
+    )
+    .await?;
+    if !response.status().is_success() {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    if is_stream {
+        let mut converter = CompleteStreamConverter::new();
+        let events = body
+            .into_data_stream()
+            .map(move |chunk| chunk.map(|bytes| Bytes::from(converter.push(&bytes).concat())));
+        return Ok(Response::from_parts(parts, Body::from_stream(events)));
+    }
+
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| RelayError::Internal(format!("Failed to read messages response: {}", e)))?;
+    let response: MessagesResponse = serde_json::from_slice(&body).map_err(RelayError::from)?;
+    let completion =
+        serde_json::to_vec(&CompleteResponse::from_messages(response)).map_err(RelayError::from)?;
+    Ok(Response::from_parts(parts, Body::from(completion)))
+}
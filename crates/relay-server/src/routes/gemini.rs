@@ -1,27 +1,69 @@
 use axum::{
-    body::Body,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{header, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use bytes::Bytes;
 use futures::stream::StreamExt;
+use relay_claude::ClientHeaders;
 use relay_core::{Platform, Relay, RelayError};
-use relay_gemini::{GeminiRelay, GeminiRequest, GenerateContentRequest};
+use relay_gemini::{
+    normalize_tools, BatchEmbedContentsRequest, EmbedContentRequest, GeminiRelay, GeminiRequest,
+    GenerateContentRequest, StreamFormat, StreamUsage,
+};
+use relay_gemini_to_anthropic::{GeminiStreamConverter, GeminiToClaudeConverter};
+use serde::Deserialize;
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, info};
 
 use super::claude::AppError;
-use crate::db::DbPool;
+use crate::context_limit::ContextLimits;
+use crate::db::{DbPool, TokenUsage};
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+use crate::model_catalog::ModelCatalog;
+use crate::model_map::ModelMap;
+use crate::routes::{
+    check_allowed_model, check_context_limit, check_daily_token_cap, close_upstream,
+    cool_down_on_oauth_failure, select_account, ChunkUsage, ClaudeBackend, StreamSettings,
+    UsageRecord, UsageRecorder,
+};
 use crate::scheduler::UnifiedScheduler;
 
 pub struct GeminiRouteState {
     pub scheduler: Arc<UnifiedScheduler>,
     pub relay: Arc<GeminiRelay>,
-    #[allow(dead_code)] // Reserved for future usage tracking when Gemini API exposes token counts
     pub db_pool: DbPool,
+    pub context_limits: Arc<ContextLimits>,
+    pub model_map: Arc<ModelMap>,
+    pub models: Arc<ModelCatalog>,
+    pub usage: Arc<UsageRecorder>,
+    /// Serves requests when no Gemini account is available.
+    pub claude: Option<ClaudeBackend>,
+    /// Keep-alive pings only go to `alt=sse` streams.
+    pub streaming: StreamSettings,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateContentQuery {
+    /// `sse` for server-sent events; otherwise streams are a JSON array.
+    pub alt: Option<String>,
+}
+
+impl ChunkUsage for StreamUsage {
+    fn push(&mut self, chunk: &Bytes) {
+        StreamUsage::push(self, chunk);
+    }
+
+    fn usage(&self) -> Option<TokenUsage> {
+        let usage = StreamUsage::usage(self);
+        Some(TokenUsage {
+            input_tokens: usage.prompt_token_count,
+            output_tokens: usage.candidates_token_count,
+            ..Default::default()
+        })
+    }
 }
 
 fn parse_model_and_method(path: &str) -> Result<(String, String), RelayError> {
@@ -39,39 +81,351 @@ fn parse_model_and_method(path: &str) -> Result<(String, String), RelayError> {
 
 pub async fn generate_content(
     State(state): State<Arc<GeminiRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(request_context): Extension<RequestContext>,
     Path(model_method): Path<String>,
-    Json(body): Json<GenerateContentRequest>,
+    Query(query): Query<GenerateContentQuery>,
+    Json(body): Json<serde_json::Value>,
 ) -> Result<Response, AppError> {
-    let (model, method) = parse_model_and_method(&model_method)?;
+    let (mut model, method) = parse_model_and_method(&model_method)?;
+    let stream_format = StreamFormat::from_alt(query.alt.as_deref()).ok_or_else(|| {
+        RelayError::InvalidRequest(format!(
+            "Unsupported alt: {}",
+            query.alt.as_deref().unwrap_or_default()
+        ))
+    })?;
+    state.model_map.apply(&mut model);
+    let tenant = key_policy.tenant.as_deref();
 
     info!(model = %model, method = %method, "Received Gemini request");
 
-    let is_stream = method == "streamGenerateContent";
+    let is_stream = match method.as_str() {
+        "generateContent" => false,
+        "streamGenerateContent" => true,
+        "countTokens" => return count_tokens(&state, tenant, request_context, &model, body).await,
+        "embedContent" | "batchEmbedContents" => {
+            return embed_contents(&state, tenant, request_context, &model, &method, body).await
+        }
+        _ => {
+            return Err(RelayError::InvalidRequest(format!(
+                "Unsupported Gemini method: {}",
+                method
+            ))
+            .into())
+        }
+    };
+    request_context.begin(Platform::Gemini, &model, is_stream);
+    let mut body: GenerateContentRequest = serde_json::from_value(body)
+        .map_err(|e| RelayError::InvalidRequest(format!("Invalid request body: {}", e)))?;
+    if let Some(tools) = body.tools.as_mut() {
+        normalize_tools(tools)?;
+    }
+
+    if let Some(response) = check_allowed_model(&key_policy, &model) {
+        return Ok(response);
+    }
+    if let Some(response) =
+        check_daily_token_cap(&state.db_pool, &api_key_hash, &key_policy).await
+    {
+        return Ok(response);
+    }
 
     let body_value = serde_json::to_value(&body).unwrap_or_default();
-    let account = state
-        .scheduler
-        .select_account(Platform::Gemini, &body_value)
-        .await?;
+
+    if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+        return Ok(response);
+    }
+    let selected = select_account(
+        &state.scheduler,
+        key_policy.pinned_account.as_deref(),
+        tenant,
+        Platform::Gemini,
+        &body_value,
+        &HashSet::new(),
+    )
+    .await;
+    let account = match (selected, &state.claude) {
+        (Ok(account), _) => account,
+        (Err(RelayError::NoAccount(_)), Some(claude)) => {
+            return generate_content_via_claude(
+                &state,
+                claude,
+                api_key_hash,
+                tenant,
+                request_context,
+                &model,
+                is_stream.then_some(stream_format),
+                body,
+            )
+            .await;
+        }
+        (Err(e), _) => return Err(e.into()),
+    };
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
 
     let request = GeminiRequest {
-        model,
+        model: model.clone(),
         body,
         stream: is_stream,
+        stream_format,
     };
 
     if is_stream {
-        let stream = state.relay.relay_stream(account.as_ref(), request).await?;
+        let stream = state
+            .relay
+            .relay_stream(account.as_ref(), request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
 
-        let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(32);
+        let streaming = match stream_format {
+            StreamFormat::Sse => state.streaming.clone(),
+            StreamFormat::Json => state.streaming.without_keep_alive(),
+        };
+        let record = UsageRecord {
+            recorder: state.usage.clone(),
+            context: request_context.clone(),
+            api_key_hash,
+            account_id,
+            model,
+        };
+        let body = streaming.passthrough(stream, StreamUsage::new(stream_format), record);
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, stream_format.content_type())
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Accel-Buffering", "no")
+            .body(body)
+            .unwrap())
+    } else {
+        let response = state
+            .relay
+            .relay(account.as_ref(), request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        if let Some(ref usage) = response.usage_metadata {
+            state
+                .usage
+                .record(
+                    &request_context,
+                    &api_key_hash,
+                    &account_id,
+                    &model,
+                    TokenUsage {
+                        input_tokens: usage.prompt_token_count,
+                        output_tokens: usage.candidates_token_count,
+                        ..Default::default()
+                    },
+                )
+                .await;
+        }
+
+        Ok(Json(response).into_response())
+    }
+}
+
+/// `models/*:countTokens`: forwarded to the upstream unchanged, or counted
+/// by a Claude account when Gemini requests fall back to Claude. Counting is
+/// free, so no usage is recorded.
+async fn count_tokens(
+    state: &GeminiRouteState,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    model: &str,
+    body: serde_json::Value,
+) -> Result<Response, AppError> {
+    request_context.begin(Platform::Gemini, model, false);
+
+    let selected = state
+        .scheduler
+        .select_tenant_account(Platform::Gemini, tenant, &body, &HashSet::new())
+        .await;
+    let account = match (selected, &state.claude) {
+        (Ok(account), _) => account,
+        (Err(RelayError::NoAccount(_)), Some(claude)) => {
+            return count_tokens_via_claude(state, claude, tenant, request_context, model, body)
+                .await;
+        }
+        (Err(e), _) => return Err(e.into()),
+    };
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    let response = state
+        .relay
+        .count_tokens(account.as_ref(), model, &body)
+        .await
+        .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+    Ok(Json(response).into_response())
+}
+
+/// `models/*:embedContent` and `models/*:batchEmbedContents`. Embedding
+/// responses carry no token counts, so no usage is recorded.
+async fn embed_contents(
+    state: &GeminiRouteState,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    model: &str,
+    method: &str,
+    body: serde_json::Value,
+) -> Result<Response, AppError> {
+    request_context.begin(Platform::Gemini, model, false);
+
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::Gemini, tenant, &body, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    let invalid_body = |e: serde_json::Error| {
+        RelayError::InvalidRequest(format!("Invalid {} request body: {}", method, e))
+    };
+    let response = if method == "embedContent" {
+        let request: EmbedContentRequest = serde_json::from_value(body).map_err(invalid_body)?;
+        state
+            .relay
+            .embed_content(account.as_ref(), model, &request)
+            .await
+            .map(|r| Json(r).into_response())
+    } else {
+        let request: BatchEmbedContentsRequest =
+            serde_json::from_value(body).map_err(invalid_body)?;
+        state
+            .relay
+            .batch_embed_contents(account.as_ref(), model, &request)
+            .await
+            .map(|r| Json(r).into_response())
+    };
+
+    Ok(response.inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?)
+}
+
+async fn count_tokens_via_claude(
+    state: &GeminiRouteState,
+    claude: &ClaudeBackend,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    requested_model: &str,
+    body: serde_json::Value,
+) -> Result<Response, AppError> {
+    let model = claude.model_for(requested_model);
+    request_context.begin(Platform::Claude, &model, false);
+
+    // countTokens takes either bare contents or a full generateContent request
+    let request = body.get("generateContentRequest").cloned().unwrap_or(body);
+    let request: GenerateContentRequest = serde_json::from_value(request)
+        .map_err(|e| RelayError::InvalidRequest(format!("Invalid request body: {}", e)))?;
+    let claude_request = GeminiToClaudeConverter::convert_request(request, &model, false)?;
+
+    // count_tokens rejects generation parameters such as max_tokens
+    let mut count_request = serde_json::json!({
+        "model": claude_request.model,
+        "messages": claude_request.messages,
+        "system": claude_request.system,
+    });
+    if let Some(tools) = claude_request.tools {
+        count_request["tools"] = serde_json::json!(tools);
+    }
+
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::Claude, tenant, &count_request, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    let response = claude
+        .relay
+        .count_tokens(account.as_ref(), &count_request, &ClientHeaders::default())
+        .await
+        .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+    Ok(Json(serde_json::json!({
+        "totalTokens": response["input_tokens"].as_u64().unwrap_or_default()
+    }))
+    .into_response())
+}
+
+/// Serves a Gemini request from a Claude account by translating it to
+/// Anthropic Messages and the reply back to the Gemini format. `stream` is
+/// the format of a streaming reply.
+#[allow(clippy::too_many_arguments)]
+async fn generate_content_via_claude(
+    state: &GeminiRouteState,
+    claude: &ClaudeBackend,
+    api_key_hash: ClientApiKeyHash,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    requested_model: &str,
+    stream: Option<StreamFormat>,
+    body: GenerateContentRequest,
+) -> Result<Response, AppError> {
+    let is_stream = stream.is_some();
+    let model = claude.model_for(requested_model);
+    request_context.begin(Platform::Claude, &model, is_stream);
+
+    info!(
+        requested_model = %requested_model,
+        model = %model,
+        stream = is_stream,
+        "No Gemini account available, serving Gemini request from Claude"
+    );
+
+    let claude_request = GeminiToClaudeConverter::convert_request(body, &model, is_stream)?;
+    let body_value = serde_json::to_value(&claude_request).unwrap_or_default();
+
+    if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+        return Ok(response);
+    }
+
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::Claude, tenant, &body_value, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    if let Some(stream_format) = stream {
+        let stream = claude
+            .relay
+            .relay_stream(account.as_ref(), claude_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        let stream = state.streaming.cut(stream);
+        let (tx, rx) = state.streaming.channel();
+
+        let recorder = state.usage.clone();
+        let request_context = request_context.clone();
 
         tokio::spawn(async move {
             let mut stream = stream;
-            while let Some(chunk) = stream.next().await {
+            let mut converter = GeminiStreamConverter::new();
+            let mut tracker = relay_claude::UsageTracker::new();
+            let mut sent = 0;
+
+            'forward: while let Some(chunk) = stream.next().await {
                 match chunk {
                     Ok(bytes) => {
-                        if tx.send(Ok(bytes)).await.is_err() {
-                            break;
+                        tracker.push(&bytes);
+
+                        for gemini_chunk in converter.push(&bytes) {
+                            let data = stream_format.encode(&gemini_chunk, sent);
+                            sent += 1;
+                            if tx.send(Ok(Bytes::from(data))).await.is_err() {
+                                info!("Client disconnected, closing upstream stream");
+                                break 'forward;
+                            }
                         }
                     }
                     Err(e) => {
@@ -80,29 +434,73 @@ pub async fn generate_content(
                     }
                 }
             }
+            close_upstream(stream);
+
+            let end = stream_format.end(sent);
+            if !end.is_empty() {
+                let _ = tx.send(Ok(Bytes::from(end))).await;
+            }
+
+            recorder
+                .record(
+                    &request_context,
+                    &api_key_hash,
+                    &account_id,
+                    &model,
+                    tracker.total(),
+                )
+                .await;
         });
 
-        let body = Body::from_stream(ReceiverStream::new(rx));
+        let streaming = match stream_format {
+            StreamFormat::Sse => state.streaming.clone(),
+            StreamFormat::Json => state.streaming.without_keep_alive(),
+        };
+        let body = streaming.body(rx);
 
         Ok(Response::builder()
             .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CONTENT_TYPE, stream_format.content_type())
             .header(header::CACHE_CONTROL, "no-cache")
             .header("X-Accel-Buffering", "no")
             .body(body)
             .unwrap())
     } else {
-        let response = state.relay.relay(account.as_ref(), request).await?;
-        Ok(Json(response).into_response())
+        let response = claude
+            .relay
+            .relay(account.as_ref(), claude_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: response.usage.input_tokens,
+                    output_tokens: response.usage.output_tokens,
+                    cache_creation_tokens: response.usage.cache_creation_input_tokens.unwrap_or(0),
+                    cache_read_tokens: response.usage.cache_read_input_tokens.unwrap_or(0),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        Ok(Json(GeminiToClaudeConverter::convert_response(response)).into_response())
     }
 }
 
-pub async fn models() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "models": [
-            {"name": "models/gemini-2.0-flash-exp", "displayName": "Gemini 2.0 Flash"},
-            {"name": "models/gemini-1.5-pro", "displayName": "Gemini 1.5 Pro"},
-            {"name": "models/gemini-1.5-flash", "displayName": "Gemini 1.5 Flash"}
-        ]
-    }))
+/// Gemini models followed by the `[model_map]` aliases.
+pub async fn models(State(state): State<Arc<GeminiRouteState>>) -> impl IntoResponse {
+    let models: Vec<serde_json::Value> = state
+        .models
+        .gemini_models()
+        .iter()
+        .chain(state.models.aliases())
+        .map(|m| serde_json::json!({"name": format!("models/{}", m), "displayName": m}))
+        .collect();
+    Json(serde_json::json!({ "models": models }))
 }
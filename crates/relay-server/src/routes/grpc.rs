@@ -0,0 +1,259 @@
+//! `relay.v1.Messages`, a gRPC mirror of `/v1/messages` for internal
+//! services that prefer gRPC to SSE. The protocol is in
+//! `proto/relay.proto`; the messages below are written by hand to match it,
+//! so building needs no `protoc`.
+//!
+//! The service is mounted on the HTTP router, so it shares the scheduler,
+//! relays, API key check and request log with the HTTP routes. Requests go
+//! through the Messages route itself and its responses are re-encoded.
+
+use axum::{
+    extract::State,
+    http::{self, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures::{Stream, StreamExt};
+use relay_claude::{ClientProfile, MessagesRequest};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{Body, BoxFuture, Service, StdError};
+use tonic::server::{Grpc, NamedService, ServerStreamingService, UnaryService};
+use tonic::{Code, Status};
+
+use super::claude::{messages, ClaudeRouteState};
+use super::websocket::drain_event_data;
+use super::ApiJson;
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+
+/// A Messages API request or response body as JSON.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct JsonBody {
+    #[prost(string, tag = "1")]
+    pub json: String,
+}
+
+/// The data of one streamed Messages event, such as `content_block_delta`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StreamEvent {
+    #[prost(string, tag = "1")]
+    pub event: String,
+    #[prost(string, tag = "2")]
+    pub json: String,
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<StreamEvent, Status>> + Send>>;
+
+#[derive(Clone)]
+pub struct MessagesService {
+    state: Arc<ClaudeRouteState>,
+}
+
+impl MessagesService {
+    pub fn new(state: Arc<ClaudeRouteState>) -> Self {
+        Self { state }
+    }
+}
+
+impl NamedService for MessagesService {
+    const NAME: &'static str = "relay.v1.Messages";
+}
+
+impl<B> Service<http::Request<B>> for MessagesService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let state = self.state.clone();
+        match request.uri().path() {
+            "/relay.v1.Messages/Create" => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(Create(state), request).await)
+            }),
+            "/relay.v1.Messages/Stream" => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.server_streaming(StreamEvents(state), request).await)
+            }),
+            path => {
+                let status = Status::unimplemented(format!("Unknown method {}", path));
+                Box::pin(async move { Ok(status.into_http()) })
+            }
+        }
+    }
+}
+
+/// `Create`: one Messages request, answered with the whole response.
+struct Create(Arc<ClaudeRouteState>);
+
+impl UnaryService<JsonBody> for Create {
+    type Response = JsonBody;
+    type Future = BoxFuture<tonic::Response<JsonBody>, Status>;
+
+    fn call(&mut self, request: tonic::Request<JsonBody>) -> Self::Future {
+        let state = self.0.clone();
+        Box::pin(async move {
+            let response = relay(state, request, false).await?;
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?;
+            Ok(tonic::Response::new(JsonBody {
+                json: String::from_utf8_lossy(&body).into_owned(),
+            }))
+        })
+    }
+}
+
+/// `Stream`: one Messages request, answered with its events as they arrive.
+struct StreamEvents(Arc<ClaudeRouteState>);
+
+impl ServerStreamingService<JsonBody> for StreamEvents {
+    type Response = StreamEvent;
+    type ResponseStream = EventStream;
+    type Future = BoxFuture<tonic::Response<EventStream>, Status>;
+
+    fn call(&mut self, request: tonic::Request<JsonBody>) -> Self::Future {
+        let state = self.0.clone();
+        Box::pin(async move {
+            let response = relay(state, request, true).await?;
+            let mut buffer = Vec::new();
+            let events = response
+                .into_body()
+                .into_data_stream()
+                .flat_map(move |chunk| {
+                    let events: Vec<Result<StreamEvent, Status>> = match chunk {
+                        Ok(bytes) => {
+                            buffer.extend_from_slice(&bytes);
+                            drain_event_data(&mut buffer)
+                                .into_iter()
+                                .map(stream_event)
+                                .map(Ok)
+                                .collect()
+                        }
+                        Err(e) => vec![Err(Status::unavailable(e.to_string()))],
+                    };
+                    futures::stream::iter(events)
+                });
+            Ok(tonic::Response::new(Box::pin(events) as EventStream))
+        })
+    }
+}
+
+/// Serves the request through the Messages route with the caller details
+/// the auth middleware attached, turning an error response into a status.
+async fn relay(
+    state: Arc<ClaudeRouteState>,
+    request: tonic::Request<JsonBody>,
+    stream: bool,
+) -> Result<Response, Status> {
+    let (metadata, extensions, body) = request.into_parts();
+    let mut request: MessagesRequest = serde_json::from_str(&body.json)
+        .map_err(|e| Status::invalid_argument(format!("Invalid request body: {}", e)))?;
+    request.stream = stream;
+
+    let extension = |name: &str| Status::internal(format!("Missing {} extension", name));
+    let response = messages(
+        State(state),
+        Extension(
+            extensions
+                .get::<ClientApiKeyHash>()
+                .cloned()
+                .ok_or_else(|| extension("api key"))?,
+        ),
+        Extension(
+            extensions
+                .get::<ApiKeyPolicy>()
+                .cloned()
+                .ok_or_else(|| extension("key policy"))?,
+        ),
+        Extension(
+            extensions
+                .get::<ClientProfile>()
+                .copied()
+                .ok_or_else(|| extension("client profile"))?,
+        ),
+        Extension(
+            extensions
+                .get::<RequestContext>()
+                .cloned()
+                .ok_or_else(|| extension("request context"))?,
+        ),
+        metadata.into_headers(),
+        ApiJson(request),
+    )
+    .await
+    .into_response();
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap_or_default();
+    Err(Status::new(
+        grpc_code(status),
+        String::from_utf8_lossy(&body).into_owned(),
+    ))
+}
+
+fn stream_event(json: String) -> StreamEvent {
+    let event = serde_json::from_str::<serde_json::Value>(&json)
+        .ok()
+        .and_then(|value| value["type"].as_str().map(str::to_string))
+        .unwrap_or_default();
+    StreamEvent { event, json }
+}
+
+/// The gRPC code for an HTTP error status from the Messages route.
+fn grpc_code(status: StatusCode) -> Code {
+    match status {
+        StatusCode::BAD_REQUEST => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::NOT_IMPLEMENTED => Code::Unimplemented,
+        StatusCode::SERVICE_UNAVAILABLE | StatusCode::BAD_GATEWAY => Code::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        _ => Code::Internal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_event_names_the_event_type() {
+        let event = stream_event(r#"{"type":"message_stop"}"#.to_string());
+        assert_eq!(event.event, "message_stop");
+        assert_eq!(event.json, r#"{"type":"message_stop"}"#);
+    }
+
+    #[test]
+    fn test_grpc_code() {
+        assert_eq!(grpc_code(StatusCode::UNAUTHORIZED), Code::Unauthenticated);
+        assert_eq!(
+            grpc_code(StatusCode::TOO_MANY_REQUESTS),
+            Code::ResourceExhausted
+        );
+        assert_eq!(
+            grpc_code(StatusCode::SERVICE_UNAVAILABLE),
+            Code::Unavailable
+        );
+        assert_eq!(grpc_code(StatusCode::INTERNAL_SERVER_ERROR), Code::Internal);
+    }
+}
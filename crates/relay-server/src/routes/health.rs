@@ -0,0 +1,151 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use relay_core::{Scheduler, TokenCacheStatus};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::db::DbPool;
+use crate::scheduler::UnifiedScheduler;
+
+pub struct HealthRouteState {
+    pub scheduler: Arc<UnifiedScheduler>,
+    pub db_pool: DbPool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub database: bool,
+    /// Only platforms with configured accounts are listed.
+    pub platforms: BTreeMap<String, PlatformReadiness>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PlatformReadiness {
+    pub accounts: usize,
+    /// Enabled accounts that are neither cooling down nor draining.
+    pub available: usize,
+    /// Accounts with a cached access token, by token state.
+    pub token_cache: BTreeMap<TokenCacheStatus, usize>,
+}
+
+/// `GET /health/ready`: 503 when the database is unreachable or a platform
+/// with configured accounts has none that can take new requests.
+pub async fn ready(State(state): State<Arc<HealthRouteState>>) -> impl IntoResponse {
+    let database = match sqlx::query("SELECT 1").execute(&state.db_pool).await {
+        Ok(_) => true,
+        Err(e) => {
+            warn!(error = %e, "Readiness check could not reach the database");
+            false
+        }
+    };
+
+    let mut platforms: BTreeMap<String, PlatformReadiness> = BTreeMap::new();
+    for account in state.scheduler.all_accounts() {
+        let platform = platforms.entry(account.platform().to_string()).or_default();
+        platform.accounts += 1;
+        if account.is_available()
+            && !state.scheduler.is_in_cooldown(account.id())
+            && !state.scheduler.is_draining(account.id())
+        {
+            platform.available += 1;
+        }
+        if let Some(status) = account.token_cache_status() {
+            *platform.token_cache.entry(status).or_default() += 1;
+        }
+    }
+
+    let ready = database && platforms.values().all(|p| p.available > 0);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(ReadinessReport {
+            ready,
+            database,
+            platforms,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use relay_claude::ClaudeApiAccount;
+    use relay_core::{
+        AccountProvider, FixedCooldownPolicy, MemorySessionStore, PriorityLruPolicy,
+        TtlStickyPolicy,
+    };
+    use std::time::Duration;
+
+    async fn state() -> Arc<HealthRouteState> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        let db_pool = db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap();
+
+        let accounts: Vec<Arc<dyn AccountProvider>> = ["acc1", "acc2"]
+            .into_iter()
+            .map(|id| {
+                Arc::new(ClaudeApiAccount::new(
+                    id.to_string(),
+                    id.to_string(),
+                    100,
+                    true,
+                    "sk-test".to_string(),
+                    None,
+                    None,
+                )) as Arc<dyn AccountProvider>
+            })
+            .collect();
+        let scheduler = Arc::new(UnifiedScheduler::new(
+            accounts,
+            Arc::new(TtlStickyPolicy::new(
+                MemorySessionStore::new(),
+                Duration::from_secs(3600),
+                Duration::from_secs(300),
+            )),
+            Arc::new(PriorityLruPolicy::new()),
+            Arc::new(FixedCooldownPolicy::new(Duration::from_secs(3600))),
+        ));
+        Arc::new(HealthRouteState { scheduler, db_pool })
+    }
+
+    async fn report(state: Arc<HealthRouteState>) -> (StatusCode, serde_json::Value) {
+        let response = ready(State(state)).await.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_ready_counts_usable_accounts_per_platform() {
+        let state = state().await;
+        state.scheduler.mark_account_rate_limited("acc1", Some(60));
+
+        let (status, body) = report(state.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["ready"], true);
+        assert_eq!(body["database"], true);
+        assert_eq!(body["platforms"]["claude"]["accounts"], 2);
+        assert_eq!(body["platforms"]["claude"]["available"], 1);
+        assert!(body["platforms"].get("gemini").is_none());
+
+        state.scheduler.set_draining("acc2", true);
+        let (status, body) = report(state).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["platforms"]["claude"]["available"], 0);
+    }
+}
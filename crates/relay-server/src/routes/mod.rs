@@ -1,45 +1,394 @@
+pub mod admin;
+pub mod batches;
 pub mod claude;
 pub mod codex;
+pub mod complete;
 pub mod gemini;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
 pub mod openai;
+mod streaming;
+pub mod usage;
+pub mod websocket;
 
+pub use admin::AdminRouteState;
 pub use claude::ClaudeRouteState;
 pub use codex::CodexRouteState;
 pub use gemini::GeminiRouteState;
+pub use health::HealthRouteState;
 pub use openai::OpenAIRouteState;
+pub use streaming::{close_upstream, ChunkUsage, StreamCutoff, StreamSettings, UsageRecord};
+pub use usage::UsageRouteState;
 
-use crate::db::{self, DbPool};
-use crate::middleware::ClientApiKeyHash;
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use relay_claude::ClaudeRelay;
+use relay_core::{AccountProvider, Platform, RelayError};
+use relay_gemini::GeminiRelay;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::alerts::AlertManager;
+use crate::config::ContextLimitMode;
+use crate::context_limit::ContextLimits;
+use crate::db::{self, DbPool, TokenUsage, UsageRow};
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+use crate::pricing::Pricing;
+use crate::scheduler::UnifiedScheduler;
+use crate::usage_writer::UsageWriter;
+use crate::webhook::{UsageEvent, WebhookDispatcher, WebhookEvent};
+
+/// Events buffered per live subscriber before it starts missing some.
+const LIVE_USAGE_CAPACITY: usize = 256;
+
+/// Cooldown reason for accounts whose OAuth token could not be refreshed.
+pub const OAUTH_REFRESH_FAILED: &str = "oauth_refresh_failed";
 
 pub async fn record_usage_if_valid(
     pool: &DbPool,
     api_key_hash: &ClientApiKeyHash,
     account_id: &str,
     model: &str,
-    input_tokens: u32,
-    output_tokens: u32,
-    cache_creation: u32,
-    cache_read: u32,
+    usage: TokenUsage,
 ) {
-    if input_tokens == 0 && output_tokens == 0 {
+    if usage.is_empty() {
         return;
     }
-    if let Err(e) = db::record_usage(
-        pool,
-        &api_key_hash.0,
-        account_id,
-        model,
-        input_tokens,
-        output_tokens,
-        cache_creation,
-        cache_read,
-    )
-    .await
-    {
+    if let Err(e) = db::record_usage(pool, &api_key_hash.0, account_id, model, &usage).await {
         tracing::error!(error = %e, "Failed to record usage");
     }
 }
 
+/// Stores the usage of a completed request and publishes it as a
+/// `usage.recorded` webhook event and to live subscribers.
+pub struct UsageRecorder {
+    db_pool: DbPool,
+    pricing: Arc<Pricing>,
+    webhooks: Arc<WebhookDispatcher>,
+    live: broadcast::Sender<UsageEvent>,
+    scheduler: Option<Arc<UnifiedScheduler>>,
+    alerts: Option<Arc<AlertManager>>,
+    /// Client key hash → configured name.
+    key_names: HashMap<String, String>,
+    writer: Option<Arc<UsageWriter>>,
+}
+
+impl UsageRecorder {
+    pub fn new(db_pool: DbPool, pricing: Arc<Pricing>, webhooks: Arc<WebhookDispatcher>) -> Self {
+        Self {
+            db_pool,
+            pricing,
+            webhooks,
+            live: broadcast::channel(LIVE_USAGE_CAPACITY).0,
+            scheduler: None,
+            alerts: None,
+            key_names: HashMap::new(),
+            writer: None,
+        }
+    }
+
+    /// Reports request costs to the scheduler's selection policy.
+    pub fn with_scheduler(mut self, scheduler: Arc<UnifiedScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Counts usage towards the daily alert threshold.
+    pub fn with_alerts(mut self, alerts: Arc<AlertManager>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    pub fn with_key_names(mut self, key_names: HashMap<String, String>) -> Self {
+        self.key_names = key_names;
+        self
+    }
+
+    /// Writes usage through a batching writer instead of one insert per
+    /// request.
+    pub fn with_writer(mut self, writer: Arc<UsageWriter>) -> Self {
+        self.writer = Some(writer);
+        self
+    }
+
+    /// Receives every usage event recorded from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<UsageEvent> {
+        self.live.subscribe()
+    }
+
+    pub async fn record(
+        &self,
+        context: &RequestContext,
+        api_key_hash: &ClientApiKeyHash,
+        account_id: &str,
+        model: &str,
+        usage: TokenUsage,
+    ) {
+        if usage.is_empty() {
+            return;
+        }
+        let event = UsageEvent {
+            request_id: context.request_id().to_string(),
+            client_api_key_hash: api_key_hash.0.clone(),
+            client_api_key_name: self.key_names.get(&api_key_hash.0).cloned(),
+            platform: context.platform(),
+            account_id: account_id.to_string(),
+            model: model.to_string(),
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_creation_tokens: usage.cache_creation_tokens,
+            cache_read_tokens: usage.cache_read_tokens,
+            reasoning_tokens: usage.reasoning_tokens,
+            cost_usd: self.pricing.cost(model, &usage),
+            latency_ms: context.elapsed().as_millis() as u64,
+        };
+        if let (Some(scheduler), Some(cost_usd)) = (&self.scheduler, event.cost_usd) {
+            scheduler.record_cost(account_id, cost_usd);
+        }
+        if let Some(alerts) = &self.alerts {
+            alerts.record_usage(usage.total());
+        }
+        match &self.writer {
+            Some(writer) => {
+                writer
+                    .record(UsageRow {
+                        client_api_key_hash: api_key_hash.0.clone(),
+                        account_id: account_id.to_string(),
+                        model: model.to_string(),
+                        usage,
+                    })
+                    .await
+            }
+            None => {
+                record_usage_if_valid(&self.db_pool, api_key_hash, account_id, model, usage).await
+            }
+        }
+        // Fails only when nobody is subscribed
+        let _ = self.live.send(event.clone());
+        self.webhooks.emit(WebhookEvent::UsageRecorded(event));
+    }
+}
+
+/// Claude accounts serving requests made in another platform's API format.
+pub struct ClaudeBackend {
+    pub relay: Arc<ClaudeRelay>,
+    /// Replaces requested models that are not Claude models.
+    pub model: String,
+}
+
+impl ClaudeBackend {
+    pub fn model_for(&self, requested: &str) -> String {
+        if requested.starts_with("claude") {
+            requested.to_string()
+        } else {
+            self.model.clone()
+        }
+    }
+}
+
+/// Gemini accounts serving requests made in the Anthropic Messages format.
+pub struct GeminiBackend {
+    pub relay: Arc<GeminiRelay>,
+    /// Replaces requested models that are not Gemini models.
+    pub model: String,
+}
+
+impl GeminiBackend {
+    pub fn model_for(&self, requested: &str) -> String {
+        if requested.starts_with("gemini") {
+            requested.to_string()
+        } else {
+            self.model.clone()
+        }
+    }
+}
+
+/// Reported as `created` for every listed model, which the relay does not track.
+const MODEL_CREATED: u64 = 1704067200;
+
+/// OpenAI-style model list of `(id, owned_by)` pairs.
+pub fn model_list<'a>(models: impl IntoIterator<Item = (&'a str, &'a str)>) -> serde_json::Value {
+    let data: Vec<serde_json::Value> = models
+        .into_iter()
+        .map(|(id, owned_by)| {
+            serde_json::json!({
+                "id": id,
+                "object": "model",
+                "created": MODEL_CREATED,
+                "owned_by": owned_by
+            })
+        })
+        .collect();
+    serde_json::json!({ "object": "list", "data": data })
+}
+
+/// The account a request is pinned to with `x-relay-account`, or else the
+/// scheduler's choice among the accounts serving `tenant`. A pin to
+/// another tenant's account is refused like one to an unknown account.
+pub async fn select_account(
+    scheduler: &UnifiedScheduler,
+    pin: Option<&str>,
+    tenant: Option<&str>,
+    platform: Platform,
+    request_body: &serde_json::Value,
+    excluded: &HashSet<String>,
+) -> Result<Arc<dyn AccountProvider>, RelayError> {
+    match pin {
+        Some(account_id) => {
+            tracing::info!(account_id = %account_id, platform = %platform, "Using pinned account");
+            if scheduler.tenant_of(account_id) != tenant {
+                return Err(RelayError::InvalidRequest(format!(
+                    "No {} account {}",
+                    platform, account_id
+                )));
+            }
+            scheduler.pinned_account(platform, account_id)
+        }
+        None => {
+            scheduler
+                .select_tenant_account(platform, tenant, request_body, excluded)
+                .await
+        }
+    }
+}
+
+/// Puts an account whose OAuth token could not be refreshed into cooldown so
+/// later requests pick another one.
+pub fn cool_down_on_oauth_failure(
+    scheduler: &UnifiedScheduler,
+    account_id: &str,
+    error: &RelayError,
+) {
+    if let RelayError::OAuth(_) = error {
+        scheduler.mark_account_unavailable(account_id, OAUTH_REFRESH_FAILED);
+    }
+}
+
+/// Returns a `rate_limit_error` response once the key has used up its
+/// `max_tokens_per_day`. Lookup failures let the request through.
+pub async fn check_daily_token_cap(
+    pool: &DbPool,
+    api_key_hash: &ClientApiKeyHash,
+    policy: &ApiKeyPolicy,
+) -> Option<Response> {
+    let limit = policy.max_tokens_per_day?;
+    let used = match db::tokens_used_today(pool, &api_key_hash.0).await {
+        Ok(used) => used,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to read daily token usage");
+            return None;
+        }
+    };
+    if used < limit {
+        return None;
+    }
+
+    tracing::warn!(used = used, limit = limit, "Daily token cap reached");
+    let body = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "rate_limit_error",
+            "message": format!(
+                "Daily token limit of {} reached for this API key ({} used). Resets at 00:00 UTC.",
+                limit, used
+            )
+        }
+    });
+    Some((StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response())
+}
+
+/// Returns a `permission_error` response when the key may not use `model`.
+pub fn check_allowed_model(policy: &ApiKeyPolicy, model: &str) -> Option<Response> {
+    if policy.allows_model(model) {
+        return None;
+    }
+
+    tracing::warn!(model = %model, "Model not allowed for API key");
+    let body = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "permission_error",
+            "message": format!("This API key is not allowed to use model {}", model)
+        }
+    });
+    Some((StatusCode::FORBIDDEN, Json(body)).into_response())
+}
+
+/// `Json` for Anthropic-style routes: a body that cannot be parsed gets an
+/// `invalid_request_error` naming the problem, instead of axum's plain-text
+/// 422 or 400.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => {
+                let (status, error_type) = match rejection.status() {
+                    StatusCode::PAYLOAD_TOO_LARGE => {
+                        (StatusCode::PAYLOAD_TOO_LARGE, "request_too_large")
+                    }
+                    _ => (StatusCode::BAD_REQUEST, "invalid_request_error"),
+                };
+                let body = serde_json::json!({
+                    "type": "error",
+                    "error": {
+                        "type": error_type,
+                        "message": rejection.body_text()
+                    }
+                });
+                Err((status, Json(body)).into_response())
+            }
+        }
+    }
+}
+
+/// Returns an `invalid_request_error` response when the request clearly
+/// exceeds the model's context window and the check is in reject mode.
+pub fn check_context_limit(
+    limits: &ContextLimits,
+    model: &str,
+    body: &serde_json::Value,
+) -> Option<Response> {
+    let overflow = limits.check(model, body)?;
+    tracing::warn!(
+        model = %model,
+        estimated_tokens = overflow.estimated_tokens,
+        context_window = overflow.context_window,
+        "Request exceeds model context window"
+    );
+    if limits.mode() != ContextLimitMode::Reject {
+        return None;
+    }
+
+    let body = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "invalid_request_error",
+            "message": format!(
+                "prompt is too long: an estimated {} tokens > {} maximum",
+                overflow.estimated_tokens, overflow.context_window
+            )
+        }
+    });
+    Some((StatusCode::BAD_REQUEST, Json(body)).into_response())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,7 +399,9 @@ mod tests {
         let path = dir.path().join("test.db");
         let path_str = path.to_str().unwrap().to_string();
         std::mem::forget(dir);
-        init_database(&path_str).await.unwrap()
+        init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
     }
 
     #[tokio::test]
@@ -58,7 +409,7 @@ mod tests {
         let pool = setup_test_db().await;
         let api_key_hash = ClientApiKeyHash::from_api_key("test-key");
 
-        record_usage_if_valid(&pool, &api_key_hash, "acc1", "model", 0, 0, 0, 0).await;
+        record_usage_if_valid(&pool, &api_key_hash, "acc1", "model", TokenUsage::default()).await;
 
         let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
         assert_eq!(usage.total_requests, 0);
@@ -69,7 +420,17 @@ mod tests {
         let pool = setup_test_db().await;
         let api_key_hash = ClientApiKeyHash::from_api_key("test-key");
 
-        record_usage_if_valid(&pool, &api_key_hash, "acc1", "model", 100, 0, 0, 0).await;
+        record_usage_if_valid(
+            &pool,
+            &api_key_hash,
+            "acc1",
+            "model",
+            TokenUsage {
+                input_tokens: 100,
+                ..Default::default()
+            },
+        )
+        .await;
 
         let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
         assert_eq!(usage.total_requests, 1);
@@ -82,7 +443,17 @@ mod tests {
         let pool = setup_test_db().await;
         let api_key_hash = ClientApiKeyHash::from_api_key("test-key");
 
-        record_usage_if_valid(&pool, &api_key_hash, "acc1", "model", 0, 50, 0, 0).await;
+        record_usage_if_valid(
+            &pool,
+            &api_key_hash,
+            "acc1",
+            "model",
+            TokenUsage {
+                output_tokens: 50,
+                ..Default::default()
+            },
+        )
+        .await;
 
         let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
         assert_eq!(usage.total_requests, 1);
@@ -95,7 +466,20 @@ mod tests {
         let pool = setup_test_db().await;
         let api_key_hash = ClientApiKeyHash::from_api_key("test-key");
 
-        record_usage_if_valid(&pool, &api_key_hash, "acc1", "model", 100, 50, 20, 30).await;
+        record_usage_if_valid(
+            &pool,
+            &api_key_hash,
+            "acc1",
+            "model",
+            TokenUsage {
+                input_tokens: 100,
+                output_tokens: 50,
+                cache_creation_tokens: 20,
+                cache_read_tokens: 30,
+                ..Default::default()
+            },
+        )
+        .await;
 
         let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
         assert_eq!(usage.total_requests, 1);
@@ -108,9 +492,298 @@ mod tests {
         let pool = setup_test_db().await;
         let api_key_hash = ClientApiKeyHash::anonymous();
 
-        record_usage_if_valid(&pool, &api_key_hash, "acc1", "model", 100, 50, 0, 0).await;
+        record_usage_if_valid(
+            &pool,
+            &api_key_hash,
+            "acc1",
+            "model",
+            TokenUsage {
+                input_tokens: 100,
+                output_tokens: 50,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
+        assert_eq!(usage.total_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_usage_recorder_emits_usage_event() {
+        use axum::{extract::State, routing::post, Router};
+        use parking_lot::Mutex;
+        use relay_core::Platform;
+
+        let received: Arc<Mutex<Vec<String>>> = Arc::default();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(received): State<Arc<Mutex<Vec<String>>>>, body: String| async move {
+                        received.lock().push(body);
+                        StatusCode::NO_CONTENT
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let pool = setup_test_db().await;
+        let webhooks = WebhookDispatcher::new(
+            vec![crate::config::WebhookConfig {
+                url,
+                secret: "whsec_test".to_string(),
+                events: vec!["usage.recorded".to_string()],
+                max_attempts: 1,
+                initial_backoff_ms: 1,
+                timeout_seconds: 5,
+            }],
+            pool.clone(),
+        );
+        let api_key_hash = ClientApiKeyHash::from_api_key("billing-key");
+        let recorder = UsageRecorder::new(
+            pool.clone(),
+            Arc::new(Pricing::new(&Default::default())),
+            Arc::new(webhooks),
+        )
+        .with_key_names(HashMap::from([(
+            api_key_hash.0.clone(),
+            "billing".to_string(),
+        )]));
+        let context = RequestContext::new();
+        context.begin(Platform::Claude, "claude-sonnet-4-20250514", false);
+
+        recorder
+            .record(
+                &context,
+                &api_key_hash,
+                "acc1",
+                "claude-sonnet-4-20250514",
+                TokenUsage::default(),
+            )
+            .await;
+        recorder
+            .record(
+                &context,
+                &api_key_hash,
+                "acc1",
+                "claude-sonnet-4-20250514",
+                TokenUsage {
+                    input_tokens: 1_000_000,
+                    output_tokens: 100_000,
+                    ..Default::default()
+                },
+            )
+            .await;
 
         let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
         assert_eq!(usage.total_requests, 1);
+
+        for _ in 0..100 {
+            if !received.lock().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let received = received.lock();
+        assert_eq!(received.len(), 1);
+        let event: serde_json::Value = serde_json::from_str(&received[0]).unwrap();
+        assert_eq!(event["type"], "usage.recorded");
+        assert_eq!(event["data"]["request_id"], context.request_id());
+        assert_eq!(event["data"]["client_api_key_hash"], api_key_hash.0);
+        assert_eq!(event["data"]["client_api_key_name"], "billing");
+        assert_eq!(event["data"]["platform"], "claude");
+        assert_eq!(event["data"]["account_id"], "acc1");
+        assert_eq!(event["data"]["input_tokens"], 1_000_000);
+        assert_eq!(event["data"]["cost_usd"], 4.5);
+        assert!(event["data"]["latency_ms"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_usage_recorder_writes_through_writer() {
+        let pool = setup_test_db().await;
+        let writer = Arc::new(UsageWriter::spawn(
+            pool.clone(),
+            std::time::Duration::from_secs(3600),
+            100,
+        ));
+        let recorder = UsageRecorder::new(
+            pool.clone(),
+            Arc::new(Pricing::new(&Default::default())),
+            Arc::new(WebhookDispatcher::new(Vec::new(), pool.clone())),
+        )
+        .with_writer(writer.clone());
+        let context = RequestContext::new();
+        context.begin(relay_core::Platform::Claude, "claude-sonnet-4-20250514", false);
+        let usage = TokenUsage {
+            input_tokens: 100,
+            output_tokens: 10,
+            ..Default::default()
+        };
+
+        let api_key_hash = ClientApiKeyHash::from_api_key("k");
+        recorder
+            .record(&context, &api_key_hash, "acc1", "m", usage)
+            .await;
+        let before = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
+        assert_eq!(before.total_requests, 0);
+
+        writer.flush().await;
+        let after = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
+        assert_eq!(after.total_requests, 1);
+        assert_eq!(after.total_input, 100);
+    }
+
+    #[tokio::test]
+    async fn test_allowed_model_check() {
+        let policy = ApiKeyPolicy {
+            allowed_models: vec!["claude-3-5-haiku-*".to_string()],
+            ..Default::default()
+        };
+        assert!(check_allowed_model(&policy, "claude-3-5-haiku-20241022").is_none());
+
+        let response = check_allowed_model(&policy, "claude-opus-4-1").unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["type"], "permission_error");
+    }
+
+    #[tokio::test]
+    async fn test_daily_token_cap() {
+        let pool = setup_test_db().await;
+        let api_key_hash = ClientApiKeyHash::from_api_key("intern-key");
+        let policy = ApiKeyPolicy {
+            max_tokens_per_day: Some(150),
+            ..Default::default()
+        };
+
+        assert!(check_daily_token_cap(&pool, &api_key_hash, &policy)
+            .await
+            .is_none());
+
+        record_usage_if_valid(
+            &pool,
+            &api_key_hash,
+            "acc1",
+            "model",
+            TokenUsage {
+                input_tokens: 100,
+                output_tokens: 50,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let response = check_daily_token_cap(&pool, &api_key_hash, &policy)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["type"], "error");
+        assert_eq!(body["error"]["type"], "rate_limit_error");
+
+        // Keys without a cap are never blocked
+        assert!(
+            check_daily_token_cap(&pool, &api_key_hash, &ApiKeyPolicy::default())
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_context_limit_rejects_oversized_request() {
+        let body = serde_json::json!({"messages": [{"content": "x".repeat(80)}]});
+        let limits = |mode| {
+            ContextLimits::new(&crate::config::ContextLimitsConfig {
+                mode,
+                models: [("tiny-".to_string(), 10)].into_iter().collect(),
+            })
+        };
+
+        let response = check_context_limit(&limits(ContextLimitMode::Reject), "tiny-1", &body)
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert_eq!(
+            error["error"]["message"],
+            "prompt is too long: an estimated 20 tokens > 10 maximum"
+        );
+
+        assert!(check_context_limit(&limits(ContextLimitMode::Warn), "tiny-1", &body).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_messages_get_invalid_request_error() {
+        use crate::routes::claude::AppError;
+        use axum::{body::Body, routing::post, Router};
+        use relay_claude::MessagesRequest;
+        use tower::ServiceExt;
+
+        let app = Router::new().route(
+            "/v1/messages",
+            post(|ApiJson(request): ApiJson<MessagesRequest>| async move {
+                request.validate().map_err(AppError::from)?;
+                Ok::<_, AppError>("ok")
+            }),
+        );
+        let send = |body: &str| {
+            let request = axum::http::Request::post("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            async {
+                let response = app.clone().oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+                (status, error)
+            }
+        };
+
+        for body in [
+            "{\"model\": ",
+            r#"{"max_tokens": 16, "messages": []}"#,
+            r#"{"model": "claude-sonnet-4", "max_tokens": 16, "messages": []}"#,
+            r#"{"model": "claude-sonnet-4", "messages": [{"role": "user", "content": "Hi"}]}"#,
+        ] {
+            let (status, error) = send(body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+            assert_eq!(error["type"], "error");
+            assert_eq!(error["error"]["type"], "invalid_request_error");
+        }
+
+        let (_, error) = send(
+            r#"{"model": "claude-sonnet-4", "max_tokens": 16,
+                "messages": [{"role": "human", "content": "Hi"}]}"#,
+        )
+        .await;
+        assert_eq!(
+            error["error"]["message"],
+            "messages.0.role: Input should be 'user' or 'assistant'"
+        );
+
+        let (status, _) = send(
+            r#"{"model": "claude-sonnet-4", "max_tokens": 16,
+                "messages": [{"role": "user", "content": "Hi"}]}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
     }
 }
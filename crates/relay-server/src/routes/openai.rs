@@ -7,94 +7,513 @@ use axum::{
 };
 use bytes::Bytes;
 use futures::stream::StreamExt;
-use relay_claude::{extract_usage_from_chunk, ClaudeRelay};
-use relay_core::{Platform, Relay};
-use relay_openai_to_anthropic::{ChatCompletionRequest, OpenAIToClaudeConverter};
+use relay_claude::ClaudeRelay;
+use relay_codex::CodexRelay;
+use relay_core::{Platform, Relay, RelayError};
+use relay_gemini::{GeminiRequest, StreamFormat, StreamUsage};
+use relay_openai::ChatCompletionsRelay;
+use relay_openai_to_anthropic::{
+    ChatCompletionRequest, ChatCompletionResponse, OpenAIToClaudeConverter, StreamConverter,
+    DONE_EVENT,
+};
+use relay_openai_to_gemini::{
+    ImageGenerationRequest, ImagesToGeminiConverter, OpenAIStreamConverter, OpenAIToGeminiConverter,
+};
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, info};
 
 use super::claude::AppError;
-use crate::db::DbPool;
-use crate::middleware::ClientApiKeyHash;
-use crate::routes::record_usage_if_valid;
+use crate::context_limit::ContextLimits;
+use crate::db::{DbPool, TokenUsage};
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+use crate::model_catalog::ModelCatalog;
+use crate::model_map::ModelMap;
+use crate::routes::{
+    check_allowed_model, check_context_limit, check_daily_token_cap, close_upstream,
+    cool_down_on_oauth_failure, model_list, ChunkUsage, GeminiBackend, StreamSettings, UsageRecord,
+    UsageRecorder,
+};
 use crate::scheduler::UnifiedScheduler;
 
 pub struct OpenAIRouteState {
     pub scheduler: Arc<UnifiedScheduler>,
     pub relay: Arc<ClaudeRelay>,
     pub db_pool: DbPool,
+    pub context_limits: Arc<ContextLimits>,
+    pub model_map: Arc<ModelMap>,
+    pub models: Arc<ModelCatalog>,
+    pub usage: Arc<UsageRecorder>,
+    /// Serves `gemini-*` models, or every model when `gemini_all_models` is set.
+    pub gemini: Option<GeminiBackend>,
+    pub gemini_all_models: bool,
+    /// Forwards requests unchanged for models starting with one of
+    /// `codex_prefixes`, or every model when `codex_all_models` is set.
+    pub codex: Option<Arc<CodexRelay>>,
+    pub codex_all_models: bool,
+    pub codex_prefixes: Vec<String>,
+    /// Serves `/v1/images/generations`.
+    pub images: GeminiBackend,
+    /// Returns Claude thinking as `reasoning_content`.
+    pub reasoning_content: bool,
+    /// Serves requests from OpenAI-compatible accounts when no Claude
+    /// account is available.
+    pub openai_compatible: Option<Arc<ChatCompletionsRelay>>,
+    pub streaming: StreamSettings,
+}
+
+impl ChunkUsage for relay_openai::UsageTracker {
+    fn push(&mut self, chunk: &Bytes) {
+        relay_openai::UsageTracker::push(self, chunk);
+    }
+
+    fn usage(&self) -> Option<TokenUsage> {
+        let usage = relay_openai::UsageTracker::usage(self)?;
+        Some(TokenUsage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+            ..Default::default()
+        })
+    }
 }
 
 pub async fn chat_completions(
     State(state): State<Arc<OpenAIRouteState>>,
     Extension(api_key_hash): Extension<ClientApiKeyHash>,
-    Json(request): Json<ChatCompletionRequest>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(mut body): Json<serde_json::Value>,
 ) -> Result<Response, AppError> {
-    let is_stream = request.stream;
-    let model = request.model.clone();
+    state.model_map.apply_to_body(&mut body);
+    let is_stream = body["stream"].as_bool().unwrap_or(false);
+    let model = body["model"].as_str().unwrap_or_default().to_string();
+    let tenant = key_policy.tenant.as_deref();
+    request_context.begin(Platform::Claude, &model, is_stream);
+
+    if let Some(response) = check_allowed_model(&key_policy, &model) {
+        return Ok(response);
+    }
+    if let Some(response) =
+        check_daily_token_cap(&state.db_pool, &api_key_hash, &key_policy).await
+    {
+        return Ok(response);
+    }
+
+    // Forwarded as received, so fields the converters do not model survive
+    let codex = state.codex.as_ref().filter(|_| {
+        state.codex_all_models
+            || state
+                .codex_prefixes
+                .iter()
+                .any(|prefix| model.starts_with(prefix.as_str()))
+    });
+    if let Some(codex) = codex {
+        return chat_completions_via_codex(
+            &state,
+            codex,
+            api_key_hash,
+            tenant,
+            request_context,
+            body,
+        )
+        .await;
+    }
+
+    let request: ChatCompletionRequest = serde_json::from_value(body).map_err(|e| {
+        RelayError::InvalidRequest(format!("Invalid chat/completions request: {}", e))
+    })?;
+
+    let gemini = state
+        .gemini
+        .as_ref()
+        .filter(|_| state.gemini_all_models || model.starts_with("gemini"));
+    if let Some(gemini) = gemini {
+        return chat_completions_via_gemini(
+            &state,
+            gemini,
+            api_key_hash,
+            tenant,
+            request_context,
+            request,
+        )
+        .await;
+    }
 
     info!(model = %model, stream = is_stream, "Received OpenAI chat/completions request");
 
+    let include_usage = request.include_usage();
+    let fallback = state
+        .openai_compatible
+        .as_ref()
+        .map(|relay| (relay, request.clone()));
     let claude_request = OpenAIToClaudeConverter::convert_request(request)?;
     let body_value = serde_json::to_value(&claude_request).unwrap_or_default();
 
-    let account = state
+    if let Some(response) =
+        check_context_limit(&state.context_limits, &claude_request.model, &body_value)
+    {
+        return Ok(response);
+    }
+
+    let account = match state
         .scheduler
-        .select_account(Platform::Claude, &body_value)
-        .await?;
+        .select_tenant_account(Platform::Claude, tenant, &body_value, &HashSet::new())
+        .await
+    {
+        Ok(account) => account,
+        Err(e) => {
+            let Some((relay, request)) = fallback else {
+                return Err(e.into());
+            };
+            return chat_completions_via_openai_compatible(
+                &state,
+                relay,
+                api_key_hash,
+                tenant,
+                request_context,
+                request,
+            )
+            .await;
+        }
+    };
 
     let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
 
     if is_stream {
         let stream = state
             .relay
             .relay_stream(account.as_ref(), claude_request)
-            .await?;
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
 
-        let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(32);
+        let stream = state.streaming.cut(stream);
+        let (tx, rx) = state.streaming.channel();
 
-        let db_pool = state.db_pool.clone();
+        let recorder = state.usage.clone();
+        let request_context = request_context.clone();
         let api_key_hash_clone = api_key_hash.clone();
         let account_id_clone = account_id.clone();
         let model_clone = model.clone();
+        let reasoning_content = state.reasoning_content;
 
         tokio::spawn(async move {
             let mut stream = stream;
-            let mut buffer = String::new();
-            let mut total_input = 0u32;
-            let mut total_output = 0u32;
-            let mut cache_creation = 0u32;
-            let mut cache_read = 0u32;
+            let mut converter = StreamConverter::new()
+                .include_reasoning(reasoning_content)
+                .include_usage(include_usage);
+            let mut tracker = relay_claude::UsageTracker::new();
 
-            while let Some(chunk) = stream.next().await {
+            'forward: while let Some(chunk) = stream.next().await {
                 match chunk {
                     Ok(bytes) => {
-                        if let Some(usage) = extract_usage_from_chunk(&bytes) {
-                            total_input = total_input.max(usage.input_tokens);
-                            total_output = total_output.max(usage.output_tokens);
-                            if let Some(cc) = usage.cache_creation_input_tokens {
-                                cache_creation = cache_creation.max(cc);
-                            }
-                            if let Some(cr) = usage.cache_read_input_tokens {
-                                cache_read = cache_read.max(cr);
+                        tracker.push(&bytes);
+
+                        for openai_chunk in converter.push(&bytes) {
+                            let sse_data = StreamConverter::encode(&openai_chunk);
+                            if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                                info!("Client disconnected, closing upstream stream");
+                                break 'forward;
                             }
                         }
+                    }
+                    Err(e) => {
+                        error!(error = %e, "Stream error");
+                        break;
+                    }
+                }
+            }
+            close_upstream(stream);
+
+            let _ = tx.send(Ok(Bytes::from(DONE_EVENT))).await;
+
+            recorder
+                .record(
+                    &request_context,
+                    &api_key_hash_clone,
+                    &account_id_clone,
+                    &model_clone,
+                    tracker.total(),
+                )
+                .await;
+        });
+
+        let body = state.streaming.body(rx);
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Accel-Buffering", "no")
+            .body(body)
+            .unwrap())
+    } else {
+        let response = state
+            .relay
+            .relay(account.as_ref(), claude_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: response.usage.input_tokens,
+                    output_tokens: response.usage.output_tokens,
+                    cache_creation_tokens: response.usage.cache_creation_input_tokens.unwrap_or(0),
+                    cache_read_tokens: response.usage.cache_read_input_tokens.unwrap_or(0),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        let openai_response =
+            OpenAIToClaudeConverter::convert_response(response, state.reasoning_content);
+        Ok(Json(openai_response).into_response())
+    }
+}
+
+/// Serves a chat completion unchanged from an OpenAI-compatible account, the
+/// last resort once every Claude account is unavailable.
+async fn chat_completions_via_openai_compatible(
+    state: &OpenAIRouteState,
+    relay: &ChatCompletionsRelay,
+    api_key_hash: ClientApiKeyHash,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    request: ChatCompletionRequest,
+) -> Result<Response, AppError> {
+    let is_stream = request.stream;
+    let model = request.model.clone();
+    request_context.begin(Platform::OpenAI, &model, is_stream);
+
+    info!(
+        model = %model,
+        stream = is_stream,
+        "Serving OpenAI chat/completions request from an OpenAI-compatible account"
+    );
+
+    let body_value = serde_json::to_value(&request).unwrap_or_default();
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::OpenAI, tenant, &body_value, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
 
-                        if let Ok(text) = std::str::from_utf8(&bytes) {
-                            buffer.push_str(text);
+    if is_stream {
+        let stream = relay.relay_stream(account.as_ref(), request).await?;
+
+        let record = UsageRecord {
+            recorder: state.usage.clone(),
+            context: request_context.clone(),
+            api_key_hash,
+            account_id,
+            model,
+        };
+        let body = state
+            .streaming
+            .passthrough(stream, relay_openai::UsageTracker::new(), record);
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Accel-Buffering", "no")
+            .body(body)
+            .unwrap())
+    } else {
+        let response = relay.relay(account.as_ref(), request).await?;
+        let usage = response.usage.clone().unwrap_or_default();
+
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: usage.prompt_tokens,
+                    output_tokens: usage.completion_tokens,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        Ok(Json(response).into_response())
+    }
+}
 
-                            while let Some(pos) = buffer.find("\n\n") {
-                                let line = buffer[..pos].to_string();
-                                buffer = buffer[pos + 2..].to_string();
+/// Forwards a chat completion unchanged to a Codex account's
+/// `/chat/completions`, streaming the upstream bytes back as they arrive.
+async fn chat_completions_via_codex(
+    state: &OpenAIRouteState,
+    relay: &CodexRelay,
+    api_key_hash: ClientApiKeyHash,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    body_value: serde_json::Value,
+) -> Result<Response, AppError> {
+    let is_stream = body_value["stream"].as_bool().unwrap_or(false);
+    let model = body_value["model"].as_str().unwrap_or_default().to_string();
+    request_context.begin(Platform::Codex, &model, is_stream);
+
+    info!(
+        model = %model,
+        stream = is_stream,
+        "Forwarding OpenAI chat/completions request to a Codex account"
+    );
+
+    if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+        return Ok(response);
+    }
 
-                                if let Some(openai_chunk) = convert_sse_chunk(&line) {
-                                    let sse_data =
-                                        format!("data: {}\n\n", serde_json::to_string(&openai_chunk).unwrap());
-                                    if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
-                                        return;
-                                    }
-                                }
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::Codex, tenant, &body_value, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    if is_stream {
+        let stream = relay
+            .relay_json_stream(account.as_ref(), "/chat/completions", &body_value)
+            .await?;
+
+        let record = UsageRecord {
+            recorder: state.usage.clone(),
+            context: request_context.clone(),
+            api_key_hash,
+            account_id,
+            model,
+        };
+        let body = state
+            .streaming
+            .passthrough(stream, relay_openai::UsageTracker::new(), record);
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header("X-Accel-Buffering", "no")
+            .body(body)
+            .unwrap())
+    } else {
+        let response = relay
+            .relay_json(account.as_ref(), "/chat/completions", &body_value)
+            .await?;
+
+        let usage = serde_json::from_slice::<ChatCompletionResponse>(&response.body)
+            .ok()
+            .and_then(|r| r.usage)
+            .unwrap_or_default();
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: usage.prompt_tokens,
+                    output_tokens: usage.completion_tokens,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(
+                header::CONTENT_TYPE,
+                response
+                    .content_type
+                    .unwrap_or_else(|| "application/json".to_string()),
+            )
+            .body(Body::from(response.body))
+            .unwrap())
+    }
+}
+
+/// Serves a chat completion from a Gemini account by translating it to
+/// `generateContent` and the reply back to the OpenAI format.
+async fn chat_completions_via_gemini(
+    state: &OpenAIRouteState,
+    gemini: &GeminiBackend,
+    api_key_hash: ClientApiKeyHash,
+    tenant: Option<&str>,
+    request_context: RequestContext,
+    request: ChatCompletionRequest,
+) -> Result<Response, AppError> {
+    let is_stream = request.stream;
+    let model = gemini.model_for(&request.model);
+    request_context.begin(Platform::Gemini, &model, is_stream);
+
+    info!(
+        requested_model = %request.model,
+        model = %model,
+        stream = is_stream,
+        "Serving OpenAI chat/completions request from Gemini"
+    );
+
+    let include_usage = request.include_usage();
+    let body = OpenAIToGeminiConverter::convert_request(request)?;
+    let body_value = serde_json::to_value(&body).unwrap_or_default();
+
+    if let Some(response) = check_context_limit(&state.context_limits, &model, &body_value) {
+        return Ok(response);
+    }
+
+    let account = state
+        .scheduler
+        .select_tenant_account(Platform::Gemini, tenant, &body_value, &HashSet::new())
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    let gemini_request = GeminiRequest {
+        model: model.clone(),
+        body,
+        stream: is_stream,
+        stream_format: StreamFormat::Sse,
+    };
+
+    if is_stream {
+        let stream = gemini
+            .relay
+            .relay_stream(account.as_ref(), gemini_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        let stream = state.streaming.cut(stream);
+        let (tx, rx) = state.streaming.channel();
+
+        let recorder = state.usage.clone();
+        let request_context = request_context.clone();
+
+        tokio::spawn(async move {
+            let mut stream = stream;
+            let mut converter = OpenAIStreamConverter::new(&model).include_usage(include_usage);
+            let mut tracker = StreamUsage::new(StreamFormat::Sse);
+
+            'forward: while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        tracker.push(&bytes);
+
+                        for openai_chunk in converter.push(&bytes) {
+                            let sse_data = OpenAIStreamConverter::encode(&openai_chunk);
+                            if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                                info!("Client disconnected, closing upstream stream");
+                                break 'forward;
                             }
                         }
                     }
@@ -104,23 +523,26 @@ pub async fn chat_completions(
                     }
                 }
             }
+            close_upstream(stream);
 
-            let _ = tx.send(Ok(Bytes::from("data: [DONE]\n\n"))).await;
-
-            record_usage_if_valid(
-                &db_pool,
-                &api_key_hash_clone,
-                &account_id_clone,
-                &model_clone,
-                total_input,
-                total_output,
-                cache_creation,
-                cache_read,
-            )
-            .await;
+            for openai_chunk in converter.finish() {
+                let sse_data = OpenAIStreamConverter::encode(&openai_chunk);
+                let _ = tx.send(Ok(Bytes::from(sse_data))).await;
+            }
+            let _ = tx.send(Ok(Bytes::from(DONE_EVENT))).await;
+
+            recorder
+                .record(
+                    &request_context,
+                    &api_key_hash,
+                    &account_id,
+                    &model,
+                    tracker.total(),
+                )
+                .await;
         });
 
-        let body = Body::from_stream(ReceiverStream::new(rx));
+        let body = state.streaming.body(rx);
 
         Ok(Response::builder()
             .status(StatusCode::OK)
@@ -130,103 +552,137 @@ pub async fn chat_completions(
             .body(body)
             .unwrap())
     } else {
-        let response = state.relay.relay(account.as_ref(), claude_request).await?;
-
-        record_usage_if_valid(
-            &state.db_pool,
-            &api_key_hash,
-            &account_id,
-            &model,
-            response.usage.input_tokens,
-            response.usage.output_tokens,
-            response.usage.cache_creation_input_tokens.unwrap_or(0),
-            response.usage.cache_read_input_tokens.unwrap_or(0),
-        )
-        .await;
+        let response = gemini
+            .relay
+            .relay(account.as_ref(), gemini_request)
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
 
-        let openai_response = OpenAIToClaudeConverter::convert_response(response);
-        Ok(Json(openai_response).into_response())
+        let completion = OpenAIToGeminiConverter::convert_response(response, &model);
+        let usage = completion.usage.clone().unwrap_or_default();
+
+        state
+            .usage
+            .record(
+                &request_context,
+                &api_key_hash,
+                &account_id,
+                &model,
+                TokenUsage {
+                    input_tokens: usage.prompt_tokens,
+                    output_tokens: usage.completion_tokens,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        Ok(Json(completion).into_response())
     }
 }
 
-fn convert_sse_chunk(line: &str) -> Option<serde_json::Value> {
-    if !line.starts_with("data: ") {
-        return None;
-    }
-
-    let json_str = line.strip_prefix("data: ")?;
-    if json_str == "[DONE]" {
-        return None;
-    }
-
-    let value: serde_json::Value = serde_json::from_str(json_str).ok()?;
-
-    let event_type = value.get("type")?.as_str()?;
-
-    match event_type {
-        "content_block_delta" => {
-            let delta = value.get("delta")?;
-            let text = delta.get("text")?.as_str()?;
-
-            Some(serde_json::json!({
-                "id": "chatcmpl-relay",
-                "object": "chat.completion.chunk",
-                "created": std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_secs())
-                    .unwrap_or(0),
-                "model": "claude",
-                "choices": [{
-                    "index": 0,
-                    "delta": {
-                        "content": text
-                    },
-                    "finish_reason": null
-                }]
-            }))
-        }
-        "message_start" => Some(serde_json::json!({
-            "id": "chatcmpl-relay",
-            "object": "chat.completion.chunk",
-            "created": std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0),
-            "model": "claude",
-            "choices": [{
-                "index": 0,
-                "delta": {
-                    "role": "assistant"
+/// `POST /openai/v1/images/generations`: each requested image is one
+/// `generateContent` call to an image-capable Gemini model.
+pub async fn images_generations(
+    State(state): State<Arc<OpenAIRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(request_context): Extension<RequestContext>,
+    Json(mut request): Json<ImageGenerationRequest>,
+) -> Result<Response, AppError> {
+    if let Some(model) = &mut request.model {
+        state.model_map.apply(model);
+    }
+    let model = state
+        .images
+        .model_for(request.model.as_deref().unwrap_or_default());
+    request_context.begin(Platform::Gemini, &model, false);
+
+    if let Some(response) = check_allowed_model(&key_policy, &model) {
+        return Ok(response);
+    }
+    if let Some(response) =
+        check_daily_token_cap(&state.db_pool, &api_key_hash, &key_policy).await
+    {
+        return Ok(response);
+    }
+
+    info!(
+        model = %model,
+        n = request.image_count(),
+        "Received OpenAI images/generations request"
+    );
+
+    let body = ImagesToGeminiConverter::convert_request(&request)?;
+    let body_value = serde_json::to_value(&body).unwrap_or_default();
+
+    let account = state
+        .scheduler
+        .select_tenant_account(
+            Platform::Gemini,
+            key_policy.tenant.as_deref(),
+            &body_value,
+            &HashSet::new(),
+        )
+        .await?;
+
+    let account_id = account.id().to_string();
+    request_context.set_account(&account_id, 0);
+
+    let mut responses = Vec::new();
+    let mut total = TokenUsage::default();
+    for _ in 0..request.image_count() {
+        let response = state
+            .images
+            .relay
+            .relay(
+                account.as_ref(),
+                GeminiRequest {
+                    model: model.clone(),
+                    body: body.clone(),
+                    stream: false,
+                    stream_format: StreamFormat::Sse,
                 },
-                "finish_reason": null
-            }]
-        })),
-        "message_stop" => Some(serde_json::json!({
-            "id": "chatcmpl-relay",
-            "object": "chat.completion.chunk",
-            "created": std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0),
-            "model": "claude",
-            "choices": [{
-                "index": 0,
-                "delta": {},
-                "finish_reason": "stop"
-            }]
-        })),
-        _ => None,
+            )
+            .await
+            .inspect_err(|e| cool_down_on_oauth_failure(&state.scheduler, &account_id, e))?;
+
+        if let Some(ref usage) = response.usage_metadata {
+            total.input_tokens += usage.prompt_token_count;
+            total.output_tokens += usage.candidates_token_count;
+        }
+        responses.push(response);
     }
+
+    state
+        .usage
+        .record(&request_context, &api_key_hash, &account_id, &model, total)
+        .await;
+
+    let images = ImagesToGeminiConverter::convert_response(&request, responses)?;
+    Ok(Json(images).into_response())
 }
 
-pub async fn models() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "object": "list",
-        "data": [
-            {"id": "gpt-4o", "object": "model", "created": 1704067200, "owned_by": "openai"},
-            {"id": "gpt-4o-mini", "object": "model", "created": 1704067200, "owned_by": "openai"},
-            {"id": "gpt-4-turbo", "object": "model", "created": 1704067200, "owned_by": "openai"},
-            {"id": "gpt-3.5-turbo", "object": "model", "created": 1704067200, "owned_by": "openai"}
-        ]
-    }))
+/// The models chat completions are served from, the Codex models of
+/// `/openai/v1/responses` and the `[model_map]` aliases.
+pub async fn models(State(state): State<Arc<OpenAIRouteState>>) -> impl IntoResponse {
+    let claude = if state.gemini.is_some() && state.gemini_all_models {
+        Vec::new()
+    } else {
+        state.models.claude_models().await
+    };
+    let gemini = match state.gemini {
+        Some(_) => state.models.gemini_models(),
+        None => &[],
+    };
+
+    let codex = state.models.codex_models();
+    let aliases = state.models.aliases();
+
+    let models = claude
+        .iter()
+        .map(|m| (m.as_str(), "anthropic"))
+        .chain(gemini.iter().map(|m| (m.as_str(), "google")))
+        .chain(codex.iter().map(|m| (m.as_str(), "openai")))
+        .chain(aliases.iter().map(|m| (m.as_str(), "relay")));
+    Json(model_list(models))
 }
@@ -0,0 +1,469 @@
+use axum::body::Body;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use relay_core::{BoxStream, RelayError};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{error, info};
+
+use super::UsageRecorder;
+use crate::config::{ServerConfig, StreamMode, TimeoutsConfig};
+use crate::db::TokenUsage;
+use crate::middleware::{ClientApiKeyHash, RequestContext};
+
+/// SSE comment frame sent on idle streams; clients skip it.
+const SSE_PING: &[u8] = b": ping\n\n";
+
+type Chunk = Result<Bytes, std::io::Error>;
+
+type Upstream = BoxStream<relay_core::Result<Bytes>>;
+
+/// Token usage read off a relayed stream as its chunks go by.
+pub trait ChunkUsage: Send + 'static {
+    fn push(&mut self, chunk: &Bytes);
+
+    /// `None` when the stream never reported any.
+    fn usage(&self) -> Option<TokenUsage>;
+
+    /// The usage to record, nothing when the stream never reported any.
+    fn total(&self) -> TokenUsage {
+        self.usage().unwrap_or_default()
+    }
+}
+
+/// Where a stream's usage is recorded once it ends.
+pub struct UsageRecord {
+    pub recorder: Arc<UsageRecorder>,
+    pub context: RequestContext,
+    pub api_key_hash: ClientApiKeyHash,
+    pub account_id: String,
+    pub model: String,
+}
+
+impl UsageRecord {
+    async fn finish(self, usage: Option<TokenUsage>) {
+        let Some(usage) = usage else {
+            return;
+        };
+        self.recorder
+            .record(
+                &self.context,
+                &self.api_key_hash,
+                &self.account_id,
+                &self.model,
+                usage,
+            )
+            .await;
+    }
+}
+
+/// Ends the relayed streams still running once the shutdown grace period
+/// is over, so their responses finish and the usage streamed so far is
+/// recorded before the process exits.
+#[derive(Debug, Clone)]
+pub struct StreamCutoff(watch::Receiver<bool>);
+
+impl StreamCutoff {
+    /// The cutoff, and the sender that reaches it by sending `true`.
+    pub fn new() -> (watch::Sender<bool>, Self) {
+        let (tx, rx) = watch::channel(false);
+        (tx, Self(rx))
+    }
+
+    async fn reached(mut self) {
+        if self.0.wait_for(|cut| *cut).await.is_err() {
+            // Nothing will reach it
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+impl Default for StreamCutoff {
+    /// Never reached.
+    fn default() -> Self {
+        Self::new().1
+    }
+}
+
+/// How relayed streams reach the client, from `[server]`, and how long
+/// they may take to start, from `[timeouts]`.
+#[derive(Debug, Clone)]
+pub struct StreamSettings {
+    mode: StreamMode,
+    buffer: usize,
+    keep_alive: Option<Duration>,
+    first_byte: Option<Duration>,
+    first_byte_cooldown: u64,
+    cutoff: StreamCutoff,
+}
+
+impl StreamSettings {
+    pub fn new(server: &ServerConfig, timeouts: &TimeoutsConfig) -> Self {
+        Self {
+            mode: server.stream_mode,
+            buffer: server.stream_buffer,
+            keep_alive: server.sse_keep_alive(),
+            first_byte: timeouts.first_byte(),
+            first_byte_cooldown: timeouts.first_byte_cooldown_seconds,
+            cutoff: StreamCutoff::default(),
+        }
+    }
+
+    /// Streams end when `cutoff` is reached.
+    pub fn with_cutoff(self, cutoff: StreamCutoff) -> Self {
+        Self { cutoff, ..self }
+    }
+
+    /// Opens a stream with `connect` and waits for its first chunk, which
+    /// is put back in front. Past the first-byte timeout the connection is
+    /// dropped and [`RelayError::FirstByteTimeout`] returned, so a hung
+    /// upstream fails over to another account instead of holding the
+    /// client until the request timeout.
+    pub async fn first_byte<F>(&self, connect: F) -> relay_core::Result<Upstream>
+    where
+        F: Future<Output = relay_core::Result<Upstream>>,
+    {
+        let Some(timeout) = self.first_byte else {
+            return connect.await;
+        };
+        let opened = tokio::time::timeout(timeout, async {
+            let mut upstream = connect.await?;
+            let first = upstream.next().await;
+            Ok::<_, RelayError>((first, upstream))
+        })
+        .await
+        .map_err(|_| RelayError::FirstByteTimeout {
+            timeout_secs: timeout.as_secs(),
+            retry_after_secs: self.first_byte_cooldown,
+        })?;
+
+        let (first, rest) = opened?;
+        Ok(match first {
+            Some(first) => Box::pin(futures::stream::iter([first]).chain(rest)),
+            None => rest,
+        })
+    }
+
+    /// For streams that are not SSE, which have no comment frames.
+    pub fn without_keep_alive(&self) -> Self {
+        Self {
+            keep_alive: None,
+            ..self.clone()
+        }
+    }
+
+    /// The channel a converting task writes to; the receiver goes to
+    /// [`Self::body`].
+    pub fn channel(&self) -> (mpsc::Sender<Chunk>, mpsc::Receiver<Chunk>) {
+        mpsc::channel(self.buffer)
+    }
+
+    pub fn body(&self, rx: mpsc::Receiver<Chunk>) -> Body {
+        sse_body(ReceiverStream::new(rx), self.keep_alive)
+    }
+
+    /// Ends `upstream` at the cutoff as if the upstream had finished, so a
+    /// converting task still sends its closing events and records the
+    /// usage so far before the response ends.
+    pub fn cut(&self, upstream: Upstream) -> Upstream {
+        Box::pin(upstream.take_until(self.cutoff.clone().reached()))
+    }
+
+    /// Relays `upstream` to the client unchanged, feeding each chunk to
+    /// `usage`, which is recorded once the stream ends or the client goes
+    /// away.
+    pub fn passthrough(
+        &self,
+        upstream: Upstream,
+        usage: impl ChunkUsage,
+        record: UsageRecord,
+    ) -> Body {
+        let upstream = self.cut(upstream);
+        match self.mode {
+            StreamMode::Buffered => {
+                let (tx, rx) = self.channel();
+                tokio::spawn(async move {
+                    let mut upstream = upstream;
+                    let mut usage = usage;
+
+                    while let Some(chunk) = upstream.next().await {
+                        match chunk {
+                            Ok(bytes) => {
+                                usage.push(&bytes);
+                                if tx.send(Ok(bytes)).await.is_err() {
+                                    info!("Client disconnected, closing upstream stream");
+                                    break;
+                                }
+                            }
+                            Err(e) => {
+                                error!(error = %e, "Stream error");
+                                break;
+                            }
+                        }
+                    }
+                    close_upstream(upstream);
+
+                    record.finish(usage.usage()).await;
+                });
+                self.body(rx)
+            }
+            StreamMode::Direct => {
+                let guard = RecordOnDrop {
+                    usage,
+                    record: Some(record),
+                };
+                let events = futures::stream::unfold(
+                    (upstream, guard),
+                    |(mut upstream, mut guard)| async move {
+                        match upstream.next().await {
+                            Some(Ok(bytes)) => {
+                                guard.usage.push(&bytes);
+                                Some((Ok(bytes), (upstream, guard)))
+                            }
+                            end => {
+                                if let Some(Err(e)) = end {
+                                    error!(error = %e, "Stream error");
+                                }
+                                // Before the response ends, which a shutdown
+                                // waits for
+                                guard.finish().await;
+                                None
+                            }
+                        }
+                    },
+                );
+                sse_body(events, self.keep_alive)
+            }
+        }
+    }
+}
+
+/// Records a direct stream's usage as it ends, or when the body lets go of
+/// it because the client went away.
+struct RecordOnDrop<U: ChunkUsage> {
+    usage: U,
+    record: Option<UsageRecord>,
+}
+
+impl<U: ChunkUsage> RecordOnDrop<U> {
+    async fn finish(mut self) {
+        if let Some(record) = self.record.take() {
+            record.finish(self.usage.usage()).await;
+        }
+    }
+}
+
+impl<U: ChunkUsage> Drop for RecordOnDrop<U> {
+    fn drop(&mut self) {
+        if let Some(record) = self.record.take() {
+            tokio::spawn(record.finish(self.usage.usage()));
+        }
+    }
+}
+
+/// Ends a forwarding task's read of the upstream `stream`. Dropping it
+/// closes the connection, so when the client has gone away the upstream
+/// stops generating, and billing, before the partial usage is recorded.
+pub fn close_upstream<S>(stream: S) {
+    drop(stream);
+}
+
+/// The body of an SSE response made of `events`. With `keep_alive`, a
+/// [`SSE_PING`] goes out whenever nothing has been sent for that long, but
+/// only between events, never inside one split across chunks.
+fn sse_body<S>(events: S, keep_alive: Option<Duration>) -> Body
+where
+    S: Stream<Item = Chunk> + Send + 'static,
+{
+    let Some(period) = keep_alive else {
+        return Body::from_stream(events);
+    };
+
+    let ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    let events = futures::stream::unfold(
+        (Box::pin(events), ticker, true),
+        |(mut events, mut ticker, between_events)| async move {
+            tokio::select! {
+                biased;
+                chunk = events.next() => {
+                    let chunk = chunk?;
+                    let between_events = match &chunk {
+                        Ok(bytes) if !bytes.is_empty() => {
+                            bytes.ends_with(b"\n\n") || bytes.ends_with(b"\r\n\r\n")
+                        }
+                        _ => between_events,
+                    };
+                    ticker.reset();
+                    Some((chunk, (events, ticker, between_events)))
+                }
+                _ = ticker.tick(), if between_events => {
+                    let ping = Ok(Bytes::from_static(SSE_PING));
+                    Some((ping, (events, ticker, between_events)))
+                }
+            }
+        },
+    );
+    Body::from_stream(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::pricing::Pricing;
+    use crate::webhook::WebhookDispatcher;
+    use relay_core::Platform;
+
+    /// Counts bytes as output tokens.
+    struct ByteCount(u32);
+
+    impl ChunkUsage for ByteCount {
+        fn push(&mut self, chunk: &Bytes) {
+            self.0 += chunk.len() as u32;
+        }
+
+        fn usage(&self) -> Option<TokenUsage> {
+            Some(TokenUsage {
+                output_tokens: self.0,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sse_body_pings_between_events() {
+        let (tx, rx) = mpsc::channel(8);
+        tokio::spawn(async move {
+            let pause = || tokio::time::sleep(Duration::from_millis(60));
+            tx.send(Ok(Bytes::from("event: a\nda"))).await.unwrap();
+            pause().await;
+            tx.send(Ok(Bytes::from("ta: 1\n\n"))).await.unwrap();
+            pause().await;
+            tx.send(Ok(Bytes::from("event: b\ndata: 2\n\n")))
+                .await
+                .unwrap();
+        });
+
+        let body = sse_body(ReceiverStream::new(rx), Some(Duration::from_millis(10)));
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with("event: a\ndata: 1\n\n: ping\n\n"));
+        assert!(body.ends_with(": ping\n\nevent: b\ndata: 2\n\n"));
+    }
+
+    #[tokio::test]
+    async fn test_first_byte_timeout() {
+        let settings = StreamSettings {
+            first_byte: Some(Duration::from_millis(50)),
+            first_byte_cooldown: 30,
+            ..StreamSettings::new(&Default::default(), &Default::default())
+        };
+
+        let hung = settings
+            .first_byte(async { Ok(Box::pin(futures::stream::pending()) as Upstream) })
+            .await;
+        assert!(matches!(
+            hung,
+            Err(RelayError::FirstByteTimeout {
+                retry_after_secs: 30,
+                ..
+            })
+        ));
+
+        let upstream = settings
+            .first_byte(async {
+                let chunks = [
+                    Ok(Bytes::from("data: 1\n\n")),
+                    Ok(Bytes::from("data: 2\n\n")),
+                ];
+                Ok(Box::pin(futures::stream::iter(chunks)) as Upstream)
+            })
+            .await
+            .unwrap();
+        let chunks: Vec<_> = upstream.map(Result::unwrap).collect().await;
+        assert_eq!(chunks, ["data: 1\n\n", "data: 2\n\n"]);
+    }
+
+    fn usage_record(pool: &db::DbPool) -> UsageRecord {
+        let recorder = UsageRecorder::new(
+            pool.clone(),
+            Arc::new(Pricing::new(&Default::default())),
+            Arc::new(WebhookDispatcher::new(Vec::new(), pool.clone())),
+        );
+        let context = RequestContext::new();
+        context.begin(Platform::Claude, "m", true);
+        UsageRecord {
+            recorder: Arc::new(recorder),
+            context,
+            api_key_hash: ClientApiKeyHash::from_api_key("k"),
+            account_id: "acc1".to_string(),
+            model: "m".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cutoff_ends_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let pool = db::init_database(path.to_str().unwrap(), &Default::default())
+            .await
+            .unwrap();
+
+        for mode in [StreamMode::Buffered, StreamMode::Direct] {
+            let (cut, cutoff) = StreamCutoff::new();
+            let server = ServerConfig {
+                stream_mode: mode,
+                ..Default::default()
+            };
+            let settings = StreamSettings::new(&server, &Default::default()).with_cutoff(cutoff);
+            let upstream: Upstream = Box::pin(
+                futures::stream::iter([Ok(Bytes::from("data: 1\n\n"))])
+                    .chain(futures::stream::pending()),
+            );
+            let body = settings.passthrough(upstream, ByteCount(0), usage_record(&pool));
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                cut.send(true).unwrap();
+            });
+
+            let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            assert_eq!(body, "data: 1\n\n");
+        }
+
+        // Recorded before the responses ended
+        let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
+        assert_eq!(usage.total_requests, 2);
+        assert_eq!(usage.total_output, 18);
+    }
+
+    #[tokio::test]
+    async fn test_direct_passthrough_records_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let pool = db::init_database(path.to_str().unwrap(), &Default::default())
+            .await
+            .unwrap();
+        let record = usage_record(&pool);
+
+        let server = ServerConfig {
+            stream_mode: StreamMode::Direct,
+            ..Default::default()
+        };
+        let upstream: Upstream = Box::pin(futures::stream::iter([
+            Ok(Bytes::from("data: 1\n\n")),
+            Ok(Bytes::from("data: 22\n\n")),
+        ]));
+        let settings = StreamSettings::new(&server, &Default::default());
+        let body = settings.passthrough(upstream, ByteCount(0), record);
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(body, "data: 1\n\ndata: 22\n\n");
+
+        // Recorded as the stream ends, before the body does
+        let usage = db::get_usage_by_account(&pool, "acc1", 1).await.unwrap();
+        assert_eq!(usage.total_requests, 1);
+        assert_eq!(usage.total_output, 19);
+    }
+}
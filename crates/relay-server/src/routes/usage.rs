@@ -0,0 +1,670 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, Response},
+    Json,
+};
+use relay_core::{Platform, Scheduler};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::admin::admin_error;
+use crate::db::{self, DbPool};
+use crate::pricing::Pricing;
+use crate::scheduler::UnifiedScheduler;
+
+pub struct UsageRouteState {
+    pub db_pool: DbPool,
+    pub scheduler: Arc<UnifiedScheduler>,
+    pub pricing: Arc<Pricing>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    #[serde(default = "default_days")]
+    pub days: i32,
+}
+
+fn default_days() -> i32 {
+    30
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelUsageReport {
+    pub days: i32,
+    /// Sum over models with a known price.
+    pub total_cost_usd: f64,
+    pub models: Vec<ModelUsageEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelUsageEntry {
+    pub model: String,
+    /// `None` for accounts no longer in the config.
+    pub platform: Option<Platform>,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    /// `None` for models without a known price.
+    pub cost_usd: Option<f64>,
+}
+
+impl ModelUsageEntry {
+    fn total_tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+}
+
+/// `GET /usage/models?days=30`: tokens, requests and cost per model and
+/// platform, heaviest first.
+pub async fn models(
+    State(state): State<Arc<UsageRouteState>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<ModelUsageReport>, Response> {
+    if let Some(response) = invalid_days(query.days) {
+        return Err(response);
+    }
+    let models = model_usage(&state, query.days).await?;
+
+    Ok(Json(ModelUsageReport {
+        days: query.days,
+        total_cost_usd: models.iter().filter_map(|entry| entry.cost_usd).sum(),
+        models,
+    }))
+}
+
+async fn model_usage(state: &UsageRouteState, days: i32) -> Result<Vec<ModelUsageEntry>, Response> {
+    let rows = db::get_usage_by_model(&state.db_pool, days)
+        .await
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let platforms = account_platforms(state);
+
+    let mut models: HashMap<(String, Option<Platform>), ModelUsageEntry> = HashMap::new();
+    for row in rows {
+        let platform = platforms.get(&row.account_id).copied();
+        let entry = models
+            .entry((row.model.clone(), platform))
+            .or_insert_with(|| ModelUsageEntry {
+                model: row.model,
+                platform,
+                requests: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 0,
+                cost_usd: None,
+            });
+        entry.requests += row.total_requests;
+        entry.input_tokens += row.total_input;
+        entry.output_tokens += row.total_output;
+        entry.cache_creation_tokens += row.total_cache_creation;
+        entry.cache_read_tokens += row.total_cache_read;
+    }
+
+    let mut models: Vec<ModelUsageEntry> = models.into_values().collect();
+    for entry in &mut models {
+        entry.cost_usd = cost_usd(
+            &state.pricing,
+            &entry.model,
+            [
+                entry.input_tokens,
+                entry.output_tokens,
+                entry.cache_creation_tokens,
+                entry.cache_read_tokens,
+            ],
+        );
+    }
+    models.sort_by(|a, b| {
+        b.total_tokens()
+            .cmp(&a.total_tokens())
+            .then_with(|| a.model.cmp(&b.model))
+    });
+    Ok(models)
+}
+
+/// usage_stats has no platform column; every account serves one platform.
+fn account_platforms(state: &UsageRouteState) -> HashMap<String, Platform> {
+    state
+        .scheduler
+        .all_accounts()
+        .iter()
+        .map(|account| (account.id().to_string(), account.platform()))
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyUsageReport {
+    pub days: i32,
+    pub keys: Vec<KeyUsageEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyUsageEntry {
+    pub client_api_key_hash: String,
+    /// Configured `name` of the key, if any.
+    pub name: Option<String>,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    /// Sum over models with a known price.
+    pub cost_usd: f64,
+}
+
+impl KeyUsageEntry {
+    fn total_tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+}
+
+/// `GET /usage/keys?days=30`: tokens, requests and cost per client key,
+/// most expensive first.
+pub async fn keys(
+    State(state): State<Arc<UsageRouteState>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<KeyUsageReport>, Response> {
+    if let Some(response) = invalid_days(query.days) {
+        return Err(response);
+    }
+    let keys = key_usage(&state, query.days).await?;
+
+    Ok(Json(KeyUsageReport {
+        days: query.days,
+        keys,
+    }))
+}
+
+async fn key_usage(state: &UsageRouteState, days: i32) -> Result<Vec<KeyUsageEntry>, Response> {
+    let rows = db::get_usage_by_key(&state.db_pool, days)
+        .await
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut keys: HashMap<String, KeyUsageEntry> = HashMap::new();
+    for row in rows {
+        let cost = cost_usd(
+            &state.pricing,
+            &row.model,
+            [
+                row.total_input,
+                row.total_output,
+                row.total_cache_creation,
+                row.total_cache_read,
+            ],
+        );
+        let entry = keys
+            .entry(row.client_api_key_hash.clone())
+            .or_insert_with(|| KeyUsageEntry {
+                client_api_key_hash: row.client_api_key_hash,
+                name: row.name,
+                requests: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 0,
+                cost_usd: 0.0,
+            });
+        entry.requests += row.total_requests;
+        entry.input_tokens += row.total_input;
+        entry.output_tokens += row.total_output;
+        entry.cache_creation_tokens += row.total_cache_creation;
+        entry.cache_read_tokens += row.total_cache_read;
+        entry.cost_usd += cost.unwrap_or_default();
+    }
+
+    let mut keys: Vec<KeyUsageEntry> = keys.into_values().collect();
+    keys.sort_by(|a, b| {
+        b.cost_usd
+            .total_cmp(&a.cost_usd)
+            .then_with(|| a.client_api_key_hash.cmp(&b.client_api_key_hash))
+    });
+    Ok(keys)
+}
+
+/// Usage of one account, as shown in the HTML report.
+struct AccountUsageEntry {
+    account_id: String,
+    platform: Option<Platform>,
+    requests: i64,
+    total_tokens: i64,
+    cost_usd: f64,
+}
+
+async fn account_usage(
+    state: &UsageRouteState,
+    days: i32,
+) -> Result<Vec<AccountUsageEntry>, Response> {
+    let rows = db::get_usage_by_model(&state.db_pool, days)
+        .await
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let platforms = account_platforms(state);
+
+    let mut accounts: HashMap<String, AccountUsageEntry> = HashMap::new();
+    for row in rows {
+        let tokens = [
+            row.total_input,
+            row.total_output,
+            row.total_cache_creation,
+            row.total_cache_read,
+        ];
+        let cost = cost_usd(&state.pricing, &row.model, tokens);
+        let entry = accounts
+            .entry(row.account_id.clone())
+            .or_insert_with(|| AccountUsageEntry {
+                platform: platforms.get(&row.account_id).copied(),
+                account_id: row.account_id,
+                requests: 0,
+                total_tokens: 0,
+                cost_usd: 0.0,
+            });
+        entry.requests += row.total_requests;
+        entry.total_tokens += tokens.iter().sum::<i64>();
+        entry.cost_usd += cost.unwrap_or_default();
+    }
+
+    let mut accounts: Vec<AccountUsageEntry> = accounts.into_values().collect();
+    accounts.sort_by(|a, b| {
+        b.total_tokens
+            .cmp(&a.total_tokens)
+            .then_with(|| a.account_id.cmp(&b.account_id))
+    });
+    Ok(accounts)
+}
+
+/// `GET /usage/report?days=30`: a self-contained HTML page with daily,
+/// per-account and per-key usage.
+pub async fn report(
+    State(state): State<Arc<UsageRouteState>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Html<String>, Response> {
+    if let Some(response) = invalid_days(query.days) {
+        return Err(response);
+    }
+    let daily = db::get_daily_usage(&state.db_pool, query.days)
+        .await
+        .map_err(|e| admin_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let accounts = account_usage(&state, query.days).await?;
+    let keys = key_usage(&state, query.days).await?;
+
+    Ok(Html(render_report(query.days, &daily, &accounts, &keys)))
+}
+
+fn invalid_days(days: i32) -> Option<Response> {
+    (days < 1).then(|| {
+        admin_error(
+            StatusCode::BAD_REQUEST,
+            "days must be at least 1".to_string(),
+        )
+    })
+}
+
+const REPORT_STYLE: &str = "\
+body{font-family:system-ui,sans-serif;margin:2rem;color:#222}\
+h1{font-size:1.4rem}h2{font-size:1.1rem;margin-top:2rem}\
+table{border-collapse:collapse;min-width:40rem}\
+th,td{padding:.3rem .6rem;border-bottom:1px solid #ddd;text-align:right}\
+th:first-child,td:first-child{text-align:left}\
+.bar{background:#4a7bd0;height:.8rem}\
+.chart{display:flex;align-items:flex-end;gap:2px;height:10rem;border-bottom:1px solid #999}\
+.chart div{flex:1;background:#4a7bd0;min-height:1px}\
+.muted{color:#888}";
+
+fn render_report(
+    days: i32,
+    daily: &[db::DailyUsage],
+    accounts: &[AccountUsageEntry],
+    keys: &[KeyUsageEntry],
+) -> String {
+    let total_tokens: i64 = daily.iter().map(|day| day.total_tokens).sum();
+    let total_requests: i64 = daily.iter().map(|day| day.total_requests).sum();
+    let total_cost: f64 = accounts.iter().map(|account| account.cost_usd).sum();
+
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Usage report</title>\
+         <style>{}</style></head><body><h1>Usage, last {} days</h1>\
+         <p>{} tokens, {} requests, ${:.2}</p>",
+        REPORT_STYLE,
+        days,
+        format_count(total_tokens),
+        format_count(total_requests),
+        total_cost
+    );
+
+    html.push_str("<h2>Tokens per day</h2>");
+    if daily.is_empty() {
+        html.push_str("<p class=\"muted\">No usage recorded.</p>");
+    } else {
+        let max = daily.iter().map(|day| day.total_tokens).max().unwrap_or(0);
+        html.push_str("<div class=\"chart\">");
+        for day in daily {
+            html.push_str(&format!(
+                "<div style=\"height:{:.1}%\" title=\"{}: {} tokens, {} requests\"></div>",
+                percent(day.total_tokens, max),
+                escape_html(&day.date),
+                format_count(day.total_tokens),
+                format_count(day.total_requests)
+            ));
+        }
+        html.push_str("</div>");
+    }
+
+    html.push_str(
+        "<h2>Accounts</h2><table><tr><th>Account</th><th>Platform</th>\
+         <th>Requests</th><th>Tokens</th><th>Cost (USD)</th><th></th></tr>",
+    );
+    let max = accounts.iter().map(|a| a.total_tokens).max().unwrap_or(0);
+    for account in accounts {
+        let platform = account
+            .platform
+            .map(|platform| platform.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td>{}</tr>",
+            escape_html(&account.account_id),
+            escape_html(&platform),
+            format_count(account.requests),
+            format_count(account.total_tokens),
+            account.cost_usd,
+            bar_cell(account.total_tokens, max)
+        ));
+    }
+    html.push_str("</table>");
+
+    html.push_str(
+        "<h2>API keys</h2><table><tr><th>Key</th><th>Requests</th>\
+         <th>Tokens</th><th>Cost (USD)</th><th></th></tr>",
+    );
+    let max = keys.iter().map(|k| k.total_tokens()).max().unwrap_or(0);
+    for key in keys {
+        let label = match &key.name {
+            Some(name) => escape_html(name),
+            None => format!(
+                "<span class=\"muted\">{}</span>",
+                escape_html(&key.client_api_key_hash[..key.client_api_key_hash.len().min(12)])
+            ),
+        };
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td>{}</tr>",
+            label,
+            format_count(key.requests),
+            format_count(key.total_tokens()),
+            key.cost_usd,
+            bar_cell(key.total_tokens(), max)
+        ));
+    }
+    html.push_str("</table></body></html>");
+    html
+}
+
+fn bar_cell(value: i64, max: i64) -> String {
+    format!(
+        "<td style=\"width:12rem\"><div class=\"bar\" style=\"width:{:.1}%\"></div></td>",
+        percent(value, max)
+    )
+}
+
+fn percent(value: i64, max: i64) -> f64 {
+    if max <= 0 {
+        0.0
+    } else {
+        value.max(0) as f64 * 100.0 / max as f64
+    }
+}
+
+/// `1234567` → `1,234,567`
+fn format_count(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value < 0 {
+        out.push('-');
+    }
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `tokens` are input, output, cache write and cache read counts.
+fn cost_usd(pricing: &Pricing, model: &str, tokens: [i64; 4]) -> Option<f64> {
+    let [input, output, cache_write, cache_read] = tokens.map(|count| count.max(0) as u64);
+    pricing
+        .price(model)
+        .map(|price| price.cost(input, output, cache_write, cache_read))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TokenUsage;
+    use crate::middleware::ClientApiKeyHash;
+    use relay_claude::ClaudeApiAccount;
+    use relay_core::{
+        AccountProvider, FixedCooldownPolicy, MemorySessionStore, PriorityLruPolicy,
+        TtlStickyPolicy,
+    };
+    use std::time::Duration;
+
+    async fn setup_test_db() -> DbPool {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    async fn state() -> Arc<UsageRouteState> {
+        let accounts: Vec<Arc<dyn AccountProvider>> = ["acc1", "acc2"]
+            .iter()
+            .map(|id| -> Arc<dyn AccountProvider> {
+                Arc::new(ClaudeApiAccount::new(
+                    id.to_string(),
+                    id.to_string(),
+                    100,
+                    true,
+                    "sk-test".to_string(),
+                    None,
+                    None,
+                ))
+            })
+            .collect();
+        let scheduler = Arc::new(UnifiedScheduler::new(
+            accounts,
+            Arc::new(TtlStickyPolicy::new(
+                MemorySessionStore::new(),
+                Duration::from_secs(3600),
+                Duration::from_secs(300),
+            )),
+            Arc::new(PriorityLruPolicy::new()),
+            Arc::new(FixedCooldownPolicy::new(Duration::from_secs(3600))),
+        ));
+        Arc::new(UsageRouteState {
+            db_pool: setup_test_db().await,
+            scheduler,
+            pricing: Arc::new(Pricing::new(&Default::default())),
+        })
+    }
+
+    fn usage(input: u32, output: u32) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_models_include_cache_tokens_in_cost() {
+        let state = state().await;
+        let cached = TokenUsage {
+            input_tokens: 1_000,
+            output_tokens: 1_000,
+            cache_creation_tokens: 100_000,
+            cache_read_tokens: 1_000_000,
+            ..Default::default()
+        };
+        db::record_usage(&state.db_pool, "key", "acc1", "claude-sonnet-4", &cached)
+            .await
+            .unwrap();
+        db::record_usage(&state.db_pool, "key", "acc1", "my-model", &usage(10, 10))
+            .await
+            .unwrap();
+
+        let Json(report) = models(State(state), Query(UsageQuery { days: 30 }))
+            .await
+            .unwrap();
+
+        let sonnet = &report.models[0];
+        assert_eq!(sonnet.cache_creation_tokens, 100_000);
+        assert_eq!(sonnet.cache_read_tokens, 1_000_000);
+        // 0.003 input + 0.015 output + 0.375 cache write + 0.30 cache read
+        assert!((sonnet.cost_usd.unwrap() - 0.693).abs() < 1e-9);
+        assert_eq!(report.models[1].cost_usd, None);
+        assert!((report.total_cost_usd - 0.693).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_models_groups_by_model_and_platform() {
+        let state = state().await;
+        let pool = &state.db_pool;
+        db::record_usage(pool, "key", "acc1", "claude-opus-4", &usage(100, 10))
+            .await
+            .unwrap();
+        db::record_usage(pool, "key", "acc2", "claude-opus-4", &usage(200, 20))
+            .await
+            .unwrap();
+        db::record_usage(pool, "key", "acc1", "claude-haiku-4-5", &usage(50, 5))
+            .await
+            .unwrap();
+        db::record_usage(pool, "key", "removed", "claude-haiku-4-5", &usage(1, 1))
+            .await
+            .unwrap();
+
+        let Json(report) = models(State(state), Query(UsageQuery { days: 30 }))
+            .await
+            .unwrap();
+        let value = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(value["days"], 30);
+        let models = value["models"].as_array().unwrap();
+        assert_eq!(models.len(), 3);
+        assert_eq!(models[0]["model"], "claude-opus-4");
+        assert_eq!(models[0]["platform"], "claude");
+        assert_eq!(models[0]["requests"], 2);
+        assert_eq!(models[0]["input_tokens"], 300);
+        assert_eq!(models[0]["output_tokens"], 30);
+        assert_eq!(models[1]["model"], "claude-haiku-4-5");
+        assert_eq!(models[1]["platform"], "claude");
+        assert_eq!(models[2]["platform"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_keys_show_names_and_cost() {
+        let state = state().await;
+        let pool = &state.db_pool;
+        let ci = ClientApiKeyHash::from_api_key("sk-ci");
+        db::upsert_api_key_name(pool, &ci.0, "ci-bot")
+            .await
+            .unwrap();
+        db::record_usage(pool, &ci.0, "acc1", "claude-opus-4", &usage(1_000_000, 0))
+            .await
+            .unwrap();
+        db::record_usage(
+            pool,
+            &ci.0,
+            "acc1",
+            "claude-haiku-4-5",
+            &usage(1_000_000, 0),
+        )
+        .await
+        .unwrap();
+        db::record_usage(pool, "anonymous", "acc1", "my-model", &usage(500, 500))
+            .await
+            .unwrap();
+
+        let Json(report) = keys(State(state), Query(UsageQuery { days: 30 }))
+            .await
+            .unwrap();
+
+        assert_eq!(report.keys.len(), 2);
+        let top = &report.keys[0];
+        assert_eq!(top.client_api_key_hash, ci.0);
+        assert_eq!(top.name.as_deref(), Some("ci-bot"));
+        assert_eq!(top.requests, 2);
+        assert_eq!(top.input_tokens, 2_000_000);
+        // 15.00 opus + 1.00 haiku
+        assert!((top.cost_usd - 16.0).abs() < 1e-9);
+        assert_eq!(report.keys[1].name, None);
+        assert_eq!(report.keys[1].cost_usd, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_models_rejects_non_positive_days() {
+        let state = state().await;
+
+        let response = models(State(state), Query(UsageQuery { days: 0 }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_report_renders_accounts_and_keys() {
+        let state = state().await;
+        let pool = &state.db_pool;
+        let ci = ClientApiKeyHash::from_api_key("sk-ci");
+        db::upsert_api_key_name(pool, &ci.0, "<ci-bot>")
+            .await
+            .unwrap();
+        db::record_usage(pool, &ci.0, "acc1", "claude-opus-4", &usage(1_000_000, 0))
+            .await
+            .unwrap();
+        db::record_usage(pool, "anonymous", "acc2", "my-model", &usage(1_500, 0))
+            .await
+            .unwrap();
+
+        let Html(html) = report(State(state), Query(UsageQuery { days: 30 }))
+            .await
+            .unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("1,001,500 tokens, 2 requests, $15.00"));
+        assert!(
+            html.contains("<td>acc1</td><td>claude</td><td>1</td><td>1,000,000</td><td>15.00</td>")
+        );
+        assert!(html.contains("&lt;ci-bot&gt;"));
+        assert!(!html.contains("<ci-bot>"));
+        assert!(html.contains("<span class=\"muted\">anonymous</span>"));
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1_000), "1,000");
+        assert_eq!(format_count(-1_234_567), "-1,234,567");
+    }
+}
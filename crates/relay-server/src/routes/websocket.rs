@@ -0,0 +1,172 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures::StreamExt;
+use relay_claude::{ClientProfile, MessagesRequest};
+use relay_core::RelayError;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use super::claude::{messages as relay_messages, AppError, ClaudeRouteState};
+use super::ApiJson;
+use crate::middleware::{ApiKeyPolicy, ClientApiKeyHash, RequestContext};
+
+/// Everything the Messages route needs from the upgrade request, kept for
+/// each request sent over the socket.
+#[derive(Clone)]
+struct Caller {
+    api_key_hash: ClientApiKeyHash,
+    key_policy: ApiKeyPolicy,
+    profile: ClientProfile,
+    request_context: RequestContext,
+    headers: HeaderMap,
+}
+
+/// `GET /ws/v1/messages`
+///
+/// Each text message is a Messages request, always streamed: every SSE
+/// event's data is sent back as one text message, ending with
+/// `message_stop`. Failed requests get the error body as a single message.
+/// Requests on one socket are served in turn, and the request log records
+/// the connection rather than each request.
+pub async fn messages(
+    State(state): State<Arc<ClaudeRouteState>>,
+    Extension(api_key_hash): Extension<ClientApiKeyHash>,
+    Extension(key_policy): Extension<ApiKeyPolicy>,
+    Extension(profile): Extension<ClientProfile>,
+    Extension(request_context): Extension<RequestContext>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let caller = Caller {
+        api_key_hash,
+        key_policy,
+        profile,
+        request_context,
+        headers,
+    };
+    ws.on_upgrade(move |socket| serve(socket, state, caller))
+}
+
+async fn serve(mut socket: WebSocket, state: Arc<ClaudeRouteState>, caller: Caller) {
+    while let Some(Ok(message)) = socket.recv().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let response = match serde_json::from_str::<MessagesRequest>(&text) {
+            Ok(mut request) => {
+                request.stream = true;
+                relay(&state, &caller, request).await
+            }
+            Err(e) => AppError::from(RelayError::InvalidRequest(format!(
+                "Invalid request body: {}",
+                e
+            )))
+            .into_response(),
+        };
+
+        if let Err(e) = send_events(&mut socket, response).await {
+            debug!(error = %e, "WebSocket client went away");
+            break;
+        }
+    }
+}
+
+async fn relay(
+    state: &Arc<ClaudeRouteState>,
+    caller: &Caller,
+    request: MessagesRequest,
+) -> Response {
+    let caller = caller.clone();
+    relay_messages(
+        State(state.clone()),
+        Extension(caller.api_key_hash),
+        Extension(caller.key_policy),
+        Extension(caller.profile),
+        Extension(caller.request_context),
+        caller.headers,
+        ApiJson(request),
+    )
+    .await
+    .into_response()
+}
+
+/// Sends each SSE event's data as a text message, or a non-streamed body,
+/// such as an error, as one message.
+async fn send_events(socket: &mut WebSocket, response: Response) -> Result<(), axum::Error> {
+    let status = response.status();
+    let mut body = response.into_body().into_data_stream();
+
+    if !status.is_success() {
+        let mut text = Vec::new();
+        while let Some(Ok(chunk)) = body.next().await {
+            text.extend_from_slice(&chunk);
+        }
+        return socket
+            .send(Message::Text(String::from_utf8_lossy(&text).into_owned()))
+            .await;
+    }
+
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                warn!(error = %e, "Stream error");
+                break;
+            }
+        };
+        buffer.extend_from_slice(&chunk);
+        for data in drain_event_data(&mut buffer) {
+            socket.send(Message::Text(data)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The data of each complete SSE event in `buffer`, leaving a trailing
+/// partial event for the next chunk.
+pub(super) fn drain_event_data(buffer: &mut Vec<u8>) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Some(pos) = buffer.windows(2).position(|w| w == b"\n\n") {
+        let event: Vec<u8> = buffer.drain(..pos + 2).collect();
+        let data = String::from_utf8_lossy(&event[..pos])
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(str::trim_start)
+            .collect::<String>();
+        if !data.is_empty() {
+            events.push(data);
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_event_data_keeps_partial_events() {
+        let mut buffer =
+            b"event: ping\ndata: {\"type\":\"ping\"}\n\nevent: message_stop\nda".to_vec();
+        assert_eq!(drain_event_data(&mut buffer), [r#"{"type":"ping"}"#]);
+        assert_eq!(buffer, b"event: message_stop\nda");
+
+        buffer.extend_from_slice(b"ta: {\"type\":\"message_stop\"}\n\n: keep-alive\n\n");
+        assert_eq!(
+            drain_event_data(&mut buffer),
+            [r#"{"type":"message_stop"}"#]
+        );
+        assert!(buffer.is_empty());
+    }
+}
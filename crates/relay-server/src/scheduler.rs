@@ -1,300 +1,71 @@
 use crate::db::{self, DbPool};
-use parking_lot::RwLock;
-use relay_core::{generate_session_hash, AccountProvider, Platform, Result};
-use std::collections::{HashMap, HashSet};
+use async_trait::async_trait;
+use relay_core::{
+    AccountProvider, FixedCooldownPolicy, RelayError, Result, SelectionPolicy, SessionStore,
+    StickySession, TtlStickyPolicy,
+};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tracing::{debug, info, warn};
+use std::time::Duration;
 
-pub struct AccountCooldown {
-    until: Instant,
-    #[allow(dead_code)] // Reserved for future logging/debugging
-    reason: String,
-}
-
-pub struct AccountUsage {
-    last_used: Instant,
-    request_count: u64,
-}
+pub use relay_core::UnifiedScheduler;
 
-pub struct UnifiedScheduler {
-    accounts: Vec<Arc<dyn AccountProvider>>,
-    db_pool: DbPool,
-    cooldowns: RwLock<HashMap<String, AccountCooldown>>,
-    usage: RwLock<HashMap<String, AccountUsage>>,
-    sticky_ttl: Duration,
-    renewal_threshold: Duration,
-    unavailable_cooldown: Duration,
+/// Sticky session bindings persisted in the `sticky_sessions` table.
+pub struct SqliteSessionStore {
+    pool: DbPool,
 }
 
-impl UnifiedScheduler {
-    pub fn new(
-        accounts: Vec<Arc<dyn AccountProvider>>,
-        sticky_ttl_secs: u64,
-        renewal_threshold_secs: u64,
-        unavailable_cooldown_secs: u64,
-        db_pool: DbPool,
-    ) -> Self {
-        Self {
-            accounts,
-            db_pool,
-            cooldowns: RwLock::new(HashMap::new()),
-            usage: RwLock::new(HashMap::new()),
-            sticky_ttl: Duration::from_secs(sticky_ttl_secs),
-            renewal_threshold: Duration::from_secs(renewal_threshold_secs),
-            unavailable_cooldown: Duration::from_secs(unavailable_cooldown_secs),
-        }
-    }
-
-    pub fn mark_account_rate_limited(&self, account_id: &str, retry_after_secs: u64) {
-        let mut cooldowns = self.cooldowns.write();
-        let until = Instant::now() + Duration::from_secs(retry_after_secs);
-        cooldowns.insert(
-            account_id.to_string(),
-            AccountCooldown {
-                until,
-                reason: "rate_limited".to_string(),
-            },
-        );
-        info!(
-            account_id = account_id,
-            retry_after_secs = retry_after_secs,
-            "Account marked as rate limited"
-        );
-    }
-
-    pub fn mark_account_overloaded(&self, account_id: &str, minutes: u64) {
-        let mut cooldowns = self.cooldowns.write();
-        let until = Instant::now() + Duration::from_secs(minutes * 60);
-        cooldowns.insert(
-            account_id.to_string(),
-            AccountCooldown {
-                until,
-                reason: "overloaded".to_string(),
-            },
-        );
-        info!(
-            account_id = account_id,
-            minutes = minutes,
-            "Account marked as overloaded"
-        );
-    }
-
-    pub fn mark_account_unavailable(&self, account_id: &str, reason: &str) {
-        let mut cooldowns = self.cooldowns.write();
-        let until = Instant::now() + self.unavailable_cooldown;
-        cooldowns.insert(
-            account_id.to_string(),
-            AccountCooldown {
-                until,
-                reason: reason.to_string(),
-            },
-        );
-        warn!(
-            account_id = account_id,
-            reason = reason,
-            cooldown_seconds = self.unavailable_cooldown.as_secs(),
-            "Account marked as unavailable"
-        );
-    }
-
-    fn is_account_in_cooldown(&self, account_id: &str) -> bool {
-        let cooldowns = self.cooldowns.read();
-        if let Some(cooldown) = cooldowns.get(account_id) {
-            if Instant::now() < cooldown.until {
-                return true;
-            }
-        }
-        false
-    }
-
-    fn record_account_used(&self, account_id: &str) {
-        let mut usage = self.usage.write();
-        let entry = usage.entry(account_id.to_string()).or_insert(AccountUsage {
-            last_used: Instant::now(),
-            request_count: 0,
-        });
-        entry.last_used = Instant::now();
-        entry.request_count += 1;
-    }
-
-    fn get_last_used(&self, account_id: &str) -> Option<Instant> {
-        let usage = self.usage.read();
-        usage.get(account_id).map(|u| u.last_used)
+impl SqliteSessionStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
     }
+}
 
-    pub async fn select_account(
-        &self,
-        platform: Platform,
-        request_body: &serde_json::Value,
-    ) -> Result<Arc<dyn AccountProvider>> {
-        self.select_account_excluding(platform, request_body, &HashSet::new())
+#[async_trait]
+impl SessionStore for SqliteSessionStore {
+    async fn get(&self, session_hash: &str) -> Result<Option<StickySession>> {
+        let session = db::get_sticky_session(&self.pool, session_hash)
             .await
-    }
-
-    pub async fn select_account_excluding(
-        &self,
-        platform: Platform,
-        request_body: &serde_json::Value,
-        excluded: &HashSet<String>,
-    ) -> Result<Arc<dyn AccountProvider>> {
-        let session_hash = generate_session_hash(request_body);
-
-        if let Some(ref hash) = session_hash {
-            if let Some(account) = self.get_sticky_account(hash, platform, excluded).await {
-                debug!(session_hash = %hash, account_id = account.id(), "Using sticky session account");
-                self.record_account_used(account.id());
-                return Ok(account);
-            }
-        }
-
-        let account = self.select_available_account(platform, excluded)?;
-
-        if let Some(hash) = session_hash {
-            self.set_sticky_session(&hash, account.id()).await;
-            debug!(session_hash = %hash, account_id = account.id(), "Created new sticky session");
-        }
-
-        info!(
-            account_id = account.id(),
-            account_name = account.name(),
-            priority = account.priority(),
-            platform = ?platform,
-            "Selected account for request"
-        );
-
-        self.record_account_used(account.id());
-        Ok(account)
-    }
-
-    async fn get_sticky_account(
-        &self,
-        session_hash: &str,
-        platform: Platform,
-        excluded: &HashSet<String>,
-    ) -> Option<Arc<dyn AccountProvider>> {
-        // Query database for sticky session
-        let session = match db::get_sticky_session(&self.db_pool, session_hash).await {
-            Ok(Some(s)) => s,
-            Ok(None) => return None,
-            Err(e) => {
-                warn!(error = %e, session_hash = %session_hash, "Failed to get sticky session");
-                return None;
-            }
-        };
-
-        let (account_id, remaining_secs) = session;
-
-        // Check if account is excluded or in cooldown
-        if excluded.contains(&account_id) {
-            return None;
-        }
-        if self.is_account_in_cooldown(&account_id) {
-            return None;
-        }
-
-        // Find the account
-        let account = self.accounts.iter().find(|a| {
-            a.id() == account_id && a.platform() == platform && a.is_available()
-        })?;
-
-        // Smart renewal: only renew if remaining time < threshold
-        if remaining_secs < self.renewal_threshold.as_secs() as i64 {
-            let ttl = self.sticky_ttl.as_secs() as i64;
-            if let Err(e) =
-                db::upsert_sticky_session(&self.db_pool, session_hash, &account_id, ttl).await
-            {
-                warn!(error = %e, session_hash = %session_hash, "Failed to renew sticky session");
-            } else {
-                debug!(session_hash = %session_hash, "Renewed sticky session");
-            }
-        }
+            .map_err(|e| RelayError::Database(e.to_string()))?;
 
-        Some(account.clone())
+        Ok(session.map(|(account_id, remaining_secs)| StickySession {
+            account_id,
+            remaining: Duration::from_secs(remaining_secs.max(0) as u64),
+        }))
     }
 
-    async fn set_sticky_session(&self, session_hash: &str, account_id: &str) {
-        let ttl = self.sticky_ttl.as_secs() as i64;
-        if let Err(e) =
-            db::upsert_sticky_session(&self.db_pool, session_hash, account_id, ttl).await
-        {
-            warn!(error = %e, session_hash = %session_hash, "Failed to set sticky session");
-        }
-    }
-
-    fn select_available_account(
-        &self,
-        platform: Platform,
-        excluded: &HashSet<String>,
-    ) -> Result<Arc<dyn AccountProvider>> {
-        let mut available: Vec<_> = self
-            .accounts
-            .iter()
-            .filter(|a| {
-                a.platform() == platform
-                    && a.is_available()
-                    && !excluded.contains(a.id())
-                    && !self.is_account_in_cooldown(a.id())
-            })
-            .cloned()
-            .collect();
-
-        if available.is_empty() {
-            warn!(platform = ?platform, "No available accounts for platform");
-            return Err(relay_core::RelayError::NoAccount(platform));
-        }
-
-        available.sort_by(|a, b| {
-            let priority_cmp = b.priority().cmp(&a.priority());
-            if priority_cmp != std::cmp::Ordering::Equal {
-                return priority_cmp;
-            }
-
-            let a_last_used = self.get_last_used(a.id());
-            let b_last_used = self.get_last_used(b.id());
-
-            match (a_last_used, b_last_used) {
-                (Some(a_time), Some(b_time)) => a_time.cmp(&b_time),
-                (None, Some(_)) => std::cmp::Ordering::Less,
-                (Some(_), None) => std::cmp::Ordering::Greater,
-                (None, None) => std::cmp::Ordering::Equal,
-            }
-        });
-
-        Ok(available.remove(0))
-    }
-
-    pub fn cleanup_expired_cooldowns(&self) {
-        let now = Instant::now();
-        let mut cooldowns = self.cooldowns.write();
-        let before = cooldowns.len();
-        cooldowns.retain(|_, cooldown| now < cooldown.until);
-        let removed = before - cooldowns.len();
-        if removed > 0 {
-            debug!(removed = removed, "Cleaned up expired account cooldowns");
-        }
-    }
-
-    #[allow(dead_code)] // Reserved for admin API
-    pub fn get_accounts_by_platform(&self, platform: Platform) -> Vec<Arc<dyn AccountProvider>> {
-        self.accounts
-            .iter()
-            .filter(|a| a.platform() == platform)
-            .cloned()
-            .collect()
+    async fn put(&self, session_hash: &str, account_id: &str, ttl: Duration) -> Result<()> {
+        db::upsert_sticky_session(&self.pool, session_hash, account_id, ttl.as_secs() as i64)
+            .await
+            .map_err(|e| RelayError::Database(e.to_string()))
     }
+}
 
-    #[allow(dead_code)] // Reserved for admin API
-    pub fn get_all_accounts(&self) -> &[Arc<dyn AccountProvider>] {
-        &self.accounts
-    }
+pub fn build_scheduler(
+    accounts: Vec<Arc<dyn AccountProvider>>,
+    sticky_ttl_secs: u64,
+    renewal_threshold_secs: u64,
+    cooldown: FixedCooldownPolicy,
+    db_pool: DbPool,
+    selection: Arc<dyn SelectionPolicy>,
+) -> UnifiedScheduler {
+    UnifiedScheduler::new(
+        accounts,
+        Arc::new(TtlStickyPolicy::new(
+            SqliteSessionStore::new(db_pool),
+            Duration::from_secs(sticky_ttl_secs),
+            Duration::from_secs(renewal_threshold_secs),
+        )),
+        selection,
+        Arc::new(cooldown),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use async_trait::async_trait;
-    use relay_core::{Credentials, ProxyConfig};
+    use relay_core::{
+        generate_session_hash, Credentials, Platform, PriorityLruPolicy, ProxyConfig,
+    };
     use std::sync::atomic::{AtomicBool, Ordering};
 
     struct MockAccount {
@@ -361,7 +132,9 @@ mod tests {
         let path = dir.path().join("test.db");
         let path_str = path.to_str().unwrap().to_string();
         std::mem::forget(dir);
-        db::init_database(&path_str).await.unwrap()
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
     }
 
     async fn setup_scheduler() -> (UnifiedScheduler, DbPool) {
@@ -370,7 +143,14 @@ mod tests {
             Arc::new(MockAccount::new("acc1", Platform::Claude, 100)),
             Arc::new(MockAccount::new("acc2", Platform::Claude, 50)),
         ];
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 3600, pool.clone());
+        let scheduler = build_scheduler(
+            accounts,
+            3600,
+            300,
+            FixedCooldownPolicy::new(Duration::from_secs(3600)),
+            pool.clone(),
+            Arc::new(PriorityLruPolicy::new()),
+        );
         (scheduler, pool)
     }
 
@@ -378,34 +158,26 @@ mod tests {
     // Existing tests (adapted)
     // ========================================================================
 
-    #[tokio::test]
-    async fn test_scheduler_creation_with_custom_cooldown() {
-        let pool = setup_test_db().await;
-        let accounts: Vec<Arc<dyn AccountProvider>> =
-            vec![Arc::new(MockAccount::new("test-1", Platform::Claude, 100))];
-
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 1800, pool);
-
-        assert_eq!(scheduler.sticky_ttl, Duration::from_secs(3600));
-        assert_eq!(scheduler.renewal_threshold, Duration::from_secs(300));
-        assert_eq!(scheduler.unavailable_cooldown, Duration::from_secs(1800));
-    }
-
     #[tokio::test]
     async fn test_mark_account_unavailable_uses_configured_cooldown() {
         let pool = setup_test_db().await;
         let accounts: Vec<Arc<dyn AccountProvider>> =
             vec![Arc::new(MockAccount::new("test-1", Platform::Claude, 100))];
 
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 5, pool);
+        let scheduler = build_scheduler(
+            accounts,
+            3600,
+            300,
+            FixedCooldownPolicy::new(Duration::from_secs(5)),
+            pool,
+            Arc::new(PriorityLruPolicy::new()),
+        );
 
         scheduler.mark_account_unavailable("test-1", "test_reason");
 
-        assert!(scheduler.is_account_in_cooldown("test-1"));
+        assert!(scheduler.is_in_cooldown("test-1"));
 
-        let cooldowns = scheduler.cooldowns.read();
-        let cooldown = cooldowns.get("test-1").unwrap();
-        let remaining = cooldown.until.duration_since(Instant::now());
+        let remaining = scheduler.cooldown("test-1").unwrap().remaining;
         assert!(remaining <= Duration::from_secs(5));
         assert!(remaining >= Duration::from_secs(4));
     }
@@ -416,15 +188,19 @@ mod tests {
         let accounts: Vec<Arc<dyn AccountProvider>> =
             vec![Arc::new(MockAccount::new("test-1", Platform::Claude, 100))];
 
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 3600, pool);
-
-        scheduler.mark_account_rate_limited("test-1", 60);
+        let scheduler = build_scheduler(
+            accounts,
+            3600,
+            300,
+            FixedCooldownPolicy::new(Duration::from_secs(3600)),
+            pool,
+            Arc::new(PriorityLruPolicy::new()),
+        );
 
-        assert!(scheduler.is_account_in_cooldown("test-1"));
+        scheduler.mark_account_rate_limited("test-1", Some(60));
 
-        let cooldowns = scheduler.cooldowns.read();
-        let cooldown = cooldowns.get("test-1").unwrap();
-        assert_eq!(cooldown.reason, "rate_limited");
+        assert!(scheduler.is_in_cooldown("test-1"));
+        assert_eq!(scheduler.cooldown("test-1").unwrap().reason, "rate_limited");
     }
 
     #[tokio::test]
@@ -433,33 +209,19 @@ mod tests {
         let accounts: Vec<Arc<dyn AccountProvider>> =
             vec![Arc::new(MockAccount::new("test-1", Platform::Claude, 100))];
 
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 3600, pool);
-
-        scheduler.mark_account_overloaded("test-1", 5);
-
-        assert!(scheduler.is_account_in_cooldown("test-1"));
-
-        let cooldowns = scheduler.cooldowns.read();
-        let cooldown = cooldowns.get("test-1").unwrap();
-        assert_eq!(cooldown.reason, "overloaded");
-    }
-
-    #[tokio::test]
-    async fn test_cooldown_cleanup() {
-        let pool = setup_test_db().await;
-        let accounts: Vec<Arc<dyn AccountProvider>> =
-            vec![Arc::new(MockAccount::new("test-1", Platform::Claude, 100))];
-
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 0, pool);
-
-        scheduler.mark_account_unavailable("test-1", "test_reason");
-
-        std::thread::sleep(Duration::from_millis(10));
+        let scheduler = build_scheduler(
+            accounts,
+            3600,
+            300,
+            FixedCooldownPolicy::new(Duration::from_secs(3600)),
+            pool,
+            Arc::new(PriorityLruPolicy::new()),
+        );
 
-        scheduler.cleanup_expired_cooldowns();
+        scheduler.mark_account_overloaded("test-1", None);
 
-        let cooldowns = scheduler.cooldowns.read();
-        assert!(cooldowns.is_empty());
+        assert!(scheduler.is_in_cooldown("test-1"));
+        assert_eq!(scheduler.cooldown("test-1").unwrap().reason, "overloaded");
     }
 
     #[tokio::test]
@@ -470,7 +232,14 @@ mod tests {
             Arc::new(MockAccount::new("test-2", Platform::Claude, 50)),
         ];
 
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 3600, pool);
+        let scheduler = build_scheduler(
+            accounts,
+            3600,
+            300,
+            FixedCooldownPolicy::new(Duration::from_secs(3600)),
+            pool,
+            Arc::new(PriorityLruPolicy::new()),
+        );
 
         scheduler.mark_account_unavailable("test-1", "test_reason");
 
@@ -516,10 +285,19 @@ mod tests {
 
         // First "run"
         let first_account_id = {
-            let pool = db::init_database(&path_str).await.unwrap();
+            let pool = db::init_database(&path_str, &Default::default())
+                .await
+                .unwrap();
             let accounts: Vec<Arc<dyn AccountProvider>> =
                 vec![Arc::new(MockAccount::new("acc1", Platform::Claude, 100))];
-            let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 3600, pool);
+            let scheduler = build_scheduler(
+                accounts,
+                3600,
+                300,
+                FixedCooldownPolicy::new(Duration::from_secs(3600)),
+                pool,
+                Arc::new(PriorityLruPolicy::new()),
+            );
             let account = scheduler
                 .select_account(Platform::Claude, &body)
                 .await
@@ -528,12 +306,21 @@ mod tests {
         };
 
         // Simulate restart with new scheduler, same database
-        let pool = db::init_database(&path_str).await.unwrap();
+        let pool = db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap();
         let accounts: Vec<Arc<dyn AccountProvider>> = vec![
             Arc::new(MockAccount::new("acc1", Platform::Claude, 100)),
             Arc::new(MockAccount::new("acc2", Platform::Claude, 50)),
         ];
-        let scheduler = UnifiedScheduler::new(accounts, 3600, 300, 3600, pool);
+        let scheduler = build_scheduler(
+            accounts,
+            3600,
+            300,
+            FixedCooldownPolicy::new(Duration::from_secs(3600)),
+            pool,
+            Arc::new(PriorityLruPolicy::new()),
+        );
 
         // Should return same account (restored from database)
         let account = scheduler
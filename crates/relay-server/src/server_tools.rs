@@ -0,0 +1,148 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::config::AccountConfig;
+
+/// Anthropic tools executed by the upstream rather than the client.
+const SERVER_TOOLS: &[&str] = &["web_search", "web_fetch", "code_execution"];
+
+/// Response header listing the server tools stripped from a request.
+pub const STRIPPED_SERVER_TOOLS_HEADER: &str = "x-relay-stripped-server-tools";
+
+/// Server tools each account accepts, from the accounts' `server_tools`.
+/// Accounts without the option accept every server tool.
+pub struct ServerToolFilter {
+    allowed: HashMap<String, HashSet<String>>,
+}
+
+impl ServerToolFilter {
+    pub fn new(accounts: &[AccountConfig]) -> Self {
+        Self {
+            allowed: accounts
+                .iter()
+                .filter_map(|account| {
+                    let tools = account.server_tools()?;
+                    Some((account.id().to_string(), tools.iter().cloned().collect()))
+                })
+                .collect(),
+        }
+    }
+
+    /// Removes the server tools `account_id` does not accept from `tools`,
+    /// and a `tool_choice` naming one of them, returning the removed tools'
+    /// names.
+    pub fn apply(
+        &self,
+        account_id: &str,
+        tools: &mut Option<Vec<Value>>,
+        tool_choice: &mut Option<Value>,
+    ) -> Vec<String> {
+        let (Some(allowed), Some(list)) = (self.allowed.get(account_id), tools.as_mut()) else {
+            return Vec::new();
+        };
+
+        let mut stripped = Vec::new();
+        list.retain(|tool| match server_tool(tool) {
+            Some(name) if !allowed.contains(name) => {
+                stripped.push(name.to_string());
+                false
+            }
+            _ => true,
+        });
+        if stripped.is_empty() {
+            return stripped;
+        }
+
+        if list.is_empty() {
+            *tools = None;
+            *tool_choice = None;
+        } else if let Some(name) = tool_choice.as_ref().and_then(|c| c.get("name")?.as_str()) {
+            let names_stripped = !list.iter().any(|tool| tool["name"] == name);
+            if names_stripped {
+                *tool_choice = None;
+            }
+        }
+        stripped
+    }
+}
+
+/// The server tool a tool definition declares, such as `web_search` for
+/// `{"type": "web_search_20250305", ...}`.
+fn server_tool(tool: &Value) -> Option<&'static str> {
+    let tool_type = tool.get("type")?.as_str()?;
+    SERVER_TOOLS.iter().copied().find(|name| {
+        tool_type
+            .strip_prefix(name)
+            .is_some_and(|version| version.is_empty() || version.starts_with('_'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn filter(account_id: &str, allowed: &[&str]) -> ServerToolFilter {
+        ServerToolFilter {
+            allowed: HashMap::from([(
+                account_id.to_string(),
+                allowed.iter().map(|t| t.to_string()).collect(),
+            )]),
+        }
+    }
+
+    fn tools() -> Option<Vec<Value>> {
+        Some(vec![
+            json!({"name": "get_weather", "input_schema": {"type": "object"}}),
+            json!({"type": "web_search_20250305", "name": "web_search", "max_uses": 3}),
+            json!({"type": "code_execution_20250522", "name": "code_execution"}),
+        ])
+    }
+
+    #[test]
+    fn test_strips_server_tools_not_allowed() {
+        let filter = filter("bedrock", &["code_execution"]);
+        let mut tools = tools();
+        let mut tool_choice = Some(json!({"type": "tool", "name": "web_search"}));
+
+        let stripped = filter.apply("bedrock", &mut tools, &mut tool_choice);
+
+        assert_eq!(stripped, ["web_search"]);
+        let names: Vec<&str> = tools
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["get_weather", "code_execution"]);
+        assert!(tool_choice.is_none());
+    }
+
+    #[test]
+    fn test_unconfigured_account_keeps_every_tool() {
+        let filter = filter("bedrock", &[]);
+        let mut tools = tools();
+        let mut tool_choice = Some(json!({"type": "auto"}));
+
+        assert!(filter
+            .apply("claude", &mut tools, &mut tool_choice)
+            .is_empty());
+        assert_eq!(tools.unwrap().len(), 3);
+        assert!(tool_choice.is_some());
+    }
+
+    #[test]
+    fn test_removing_every_tool_clears_tool_choice() {
+        let filter = filter("bedrock", &[]);
+        let mut tools = Some(vec![
+            json!({"type": "web_fetch_20250910", "name": "web_fetch"}),
+        ]);
+        let mut tool_choice = Some(json!({"type": "any"}));
+
+        let stripped = filter.apply("bedrock", &mut tools, &mut tool_choice);
+
+        assert_eq!(stripped, ["web_fetch"]);
+        assert!(tools.is_none());
+        assert!(tool_choice.is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::SlowRequestsConfig;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SlowCounts {
+    pub requests: u64,
+    pub streams: u64,
+}
+
+/// Relayed requests slower than the `[slow_requests]` thresholds, counted
+/// per account since the server started.
+pub struct SlowRequests {
+    threshold: Option<Duration>,
+    stream_threshold: Option<Duration>,
+    accounts: Mutex<HashMap<String, SlowCounts>>,
+}
+
+impl SlowRequests {
+    pub fn new(config: &SlowRequestsConfig) -> Self {
+        let threshold = |secs| (secs > 0).then(|| Duration::from_secs(secs));
+        Self {
+            threshold: threshold(config.threshold_seconds),
+            stream_threshold: threshold(config.stream_threshold_seconds),
+            accounts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.threshold.is_some() || self.stream_threshold.is_some()
+    }
+
+    /// The threshold for streamed or other requests; `None` when off.
+    pub fn threshold(&self, streamed: bool) -> Option<Duration> {
+        if streamed {
+            self.stream_threshold
+        } else {
+            self.threshold
+        }
+    }
+
+    /// Logs and counts a request that took `duration` when that is over its
+    /// threshold. Returns whether it was.
+    pub fn record(
+        &self,
+        account_id: &str,
+        model: &str,
+        streamed: bool,
+        duration: Duration,
+    ) -> bool {
+        let Some(threshold) = self.threshold(streamed) else {
+            return false;
+        };
+        if duration <= threshold {
+            return false;
+        }
+
+        warn!(
+            account_id = account_id,
+            model = model,
+            duration_ms = duration.as_millis() as u64,
+            threshold_ms = threshold.as_millis() as u64,
+            streamed = streamed,
+            "Slow request"
+        );
+        let mut accounts = self.accounts.lock();
+        let counts = accounts.entry(account_id.to_string()).or_default();
+        if streamed {
+            counts.streams += 1;
+        } else {
+            counts.requests += 1;
+        }
+        true
+    }
+
+    pub fn get(&self, account_id: &str) -> SlowCounts {
+        self.accounts
+            .lock()
+            .get(account_id)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_requests_over_threshold() {
+        let slow = SlowRequests::new(&SlowRequestsConfig {
+            threshold_seconds: 10,
+            stream_threshold_seconds: 0,
+        });
+        assert!(slow.is_active());
+        let secs = Duration::from_secs;
+
+        assert!(!slow.record("acc1", "claude-sonnet-4", false, secs(10)));
+        assert!(slow.record("acc1", "claude-sonnet-4", false, secs(11)));
+        assert!(slow.record("acc1", "claude-opus-4", false, secs(30)));
+        // Streams have their own threshold, off here
+        assert!(!slow.record("acc1", "claude-opus-4", true, secs(300)));
+
+        assert_eq!(
+            slow.get("acc1"),
+            SlowCounts {
+                requests: 2,
+                streams: 0
+            }
+        );
+        assert_eq!(slow.get("acc2"), SlowCounts::default());
+
+        let off = SlowRequests::new(&SlowRequestsConfig::default());
+        assert!(!off.is_active());
+        assert!(!off.record("acc1", "claude-opus-4", false, secs(3600)));
+    }
+}
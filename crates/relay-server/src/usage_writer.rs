@@ -0,0 +1,165 @@
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, warn};
+
+use crate::db::{self, DbPool, UsageRow};
+
+const CHANNEL_CAPACITY: usize = 4096;
+
+enum Command {
+    Record(UsageRow),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Buffers usage rows and writes them in batches from a background task, so
+/// bursts of short requests don't each wait on their own SQLite insert.
+pub struct UsageWriter {
+    tx: mpsc::Sender<Command>,
+}
+
+impl UsageWriter {
+    /// Flushes every `flush_interval`, or as soon as `batch_size` rows are
+    /// buffered.
+    pub fn spawn(pool: DbPool, flush_interval: Duration, batch_size: usize) -> Self {
+        let (tx, mut rx) = mpsc::channel::<Command>(CHANNEL_CAPACITY);
+
+        tokio::spawn(async move {
+            let mut buffer = Vec::with_capacity(batch_size);
+            let start = tokio::time::Instant::now() + flush_interval;
+            let mut ticker = tokio::time::interval_at(start, flush_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    command = rx.recv() => match command {
+                        Some(Command::Record(row)) => {
+                            buffer.push(row);
+                            if buffer.len() >= batch_size {
+                                flush(&pool, &mut buffer).await;
+                            }
+                        }
+                        Some(Command::Flush(done)) => {
+                            flush(&pool, &mut buffer).await;
+                            let _ = done.send(());
+                        }
+                        None => {
+                            flush(&pool, &mut buffer).await;
+                            break;
+                        }
+                    },
+                    _ = ticker.tick() => flush(&pool, &mut buffer).await,
+                }
+            }
+        });
+
+        Self { tx }
+    }
+
+    /// Queues a row, waiting only if the buffer is backed up.
+    pub async fn record(&self, row: UsageRow) {
+        if self.tx.send(Command::Record(row)).await.is_err() {
+            warn!("Usage writer stopped, dropping usage row");
+        }
+    }
+
+    /// Writes everything queued so far, e.g. before shutting down.
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.tx.send(Command::Flush(done)).await.is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+async fn flush(pool: &DbPool, buffer: &mut Vec<UsageRow>) {
+    if buffer.is_empty() {
+        return;
+    }
+    if let Err(e) = db::record_usage_batch(pool, buffer).await {
+        error!(error = %e, rows = buffer.len(), "Failed to write usage batch");
+    }
+    buffer.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TokenUsage;
+
+    async fn setup_test_db() -> DbPool {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    fn row(account_id: &str) -> UsageRow {
+        UsageRow {
+            client_api_key_hash: "key".to_string(),
+            account_id: account_id.to_string(),
+            model: "model".to_string(),
+            usage: TokenUsage {
+                input_tokens: 10,
+                output_tokens: 5,
+                ..Default::default()
+            },
+        }
+    }
+
+    async fn count(pool: &DbPool) -> i64 {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM usage_stats")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        count
+    }
+
+    #[tokio::test]
+    async fn test_rows_are_buffered_until_flush() {
+        let pool = setup_test_db().await;
+        let writer = UsageWriter::spawn(pool.clone(), Duration::from_secs(3600), 100);
+
+        writer.record(row("acc1")).await;
+        writer.record(row("acc2")).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(count(&pool).await, 0);
+
+        writer.flush().await;
+        assert_eq!(count(&pool).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_full_batch_is_written_immediately() {
+        let pool = setup_test_db().await;
+        let writer = UsageWriter::spawn(pool.clone(), Duration::from_secs(3600), 3);
+
+        for _ in 0..3 {
+            writer.record(row("acc1")).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(count(&pool).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_rows_are_written_on_interval() {
+        let pool = setup_test_db().await;
+        let writer = UsageWriter::spawn(pool.clone(), Duration::from_millis(20), 100);
+
+        writer.record(row("acc1")).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(count(&pool).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropping_writer_flushes() {
+        let pool = setup_test_db().await;
+        let writer = UsageWriter::spawn(pool.clone(), Duration::from_secs(3600), 100);
+
+        writer.record(row("acc1")).await;
+        drop(writer);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(count(&pool).await, 1);
+    }
+}
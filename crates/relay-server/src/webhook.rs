@@ -0,0 +1,480 @@
+use relay_core::Platform;
+use ring::hmac;
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::alerts::Alert;
+use crate::config::WebhookConfig;
+use crate::db::{self, DbPool};
+
+pub const SIGNATURE_HEADER: &str = "x-relay-signature";
+pub const TIMESTAMP_HEADER: &str = "x-relay-timestamp";
+pub const EVENT_HEADER: &str = "x-relay-event";
+pub const DELIVERY_HEADER: &str = "x-relay-delivery";
+
+/// Longest wait between delivery attempts, however many are configured.
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum WebhookEvent {
+    #[serde(rename = "account.error_budget_exhausted")]
+    ErrorBudgetExhausted {
+        account_id: String,
+        window_seconds: u64,
+        requests: u64,
+        server_error_rate: f64,
+        timeout_rate: f64,
+    },
+    #[serde(rename = "account.circuit_opened")]
+    CircuitOpened {
+        account_id: String,
+        cooldown_seconds: u64,
+    },
+    #[serde(rename = "usage.recorded")]
+    UsageRecorded(UsageEvent),
+    #[serde(rename = "alert")]
+    Alert(Alert),
+}
+
+/// Usage of one completed request.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageEvent {
+    pub request_id: String,
+    pub client_api_key_hash: String,
+    /// Configured `name` of the client key, if any.
+    pub client_api_key_name: Option<String>,
+    pub platform: Option<Platform>,
+    pub account_id: String,
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cache_creation_tokens: u32,
+    pub cache_read_tokens: u32,
+    pub reasoning_tokens: u32,
+    /// `None` for models without a known price.
+    pub cost_usd: Option<f64>,
+    pub latency_ms: u64,
+}
+
+impl WebhookEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            WebhookEvent::ErrorBudgetExhausted { .. } => "account.error_budget_exhausted",
+            WebhookEvent::CircuitOpened { .. } => "account.circuit_opened",
+            WebhookEvent::UsageRecorded(_) => "usage.recorded",
+            WebhookEvent::Alert(_) => "alert",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    id: &'a str,
+    created_at: String,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Dead letter {0} not found")]
+    NotFound(i64),
+    #[error("Dead letter {0} was already replayed")]
+    AlreadyReplayed(i64),
+    #[error("Webhook endpoint {0} is no longer configured")]
+    UnknownEndpoint(String),
+    #[error("Delivery failed: {0}")]
+    Delivery(String),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Delivers events to every subscribed endpoint in the background, retrying
+/// with exponential backoff and parking exhausted deliveries in
+/// `webhook_dead_letters` for replay.
+pub struct WebhookDispatcher {
+    endpoints: Vec<WebhookConfig>,
+    client: reqwest::Client,
+    pool: DbPool,
+}
+
+impl WebhookDispatcher {
+    pub fn new(endpoints: Vec<WebhookConfig>, pool: DbPool) -> Self {
+        Self {
+            endpoints,
+            client: reqwest::Client::new(),
+            pool,
+        }
+    }
+
+    pub fn emit(&self, event: WebhookEvent) {
+        let event_type = event.event_type();
+        let endpoints: Vec<WebhookConfig> = self
+            .endpoints
+            .iter()
+            .filter(|e| e.wants(event_type))
+            .cloned()
+            .collect();
+        if endpoints.is_empty() {
+            return;
+        }
+
+        let event_id = uuid::Uuid::new_v4().to_string();
+        let payload = match serde_json::to_string(&Envelope {
+            id: &event_id,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            event: &event,
+        }) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!(error = %e, event_type = event_type, "Failed to serialize webhook event");
+                return;
+            }
+        };
+
+        for endpoint in endpoints {
+            let client = self.client.clone();
+            let pool = self.pool.clone();
+            let event_id = event_id.clone();
+            let payload = payload.clone();
+            tokio::spawn(async move {
+                deliver_with_retries(&client, &pool, &endpoint, &event_id, event_type, &payload)
+                    .await;
+            });
+        }
+    }
+
+    /// Makes one more delivery attempt for a dead letter.
+    pub async fn replay(&self, id: i64) -> Result<(), ReplayError> {
+        let letter = db::get_dead_letter(&self.pool, id)
+            .await?
+            .ok_or(ReplayError::NotFound(id))?;
+        if letter.replayed_at.is_some() {
+            return Err(ReplayError::AlreadyReplayed(id));
+        }
+        let endpoint = self
+            .endpoints
+            .iter()
+            .find(|e| e.url == letter.url)
+            .ok_or_else(|| ReplayError::UnknownEndpoint(letter.url.clone()))?;
+
+        match deliver(
+            &self.client,
+            endpoint,
+            &letter.event_id,
+            &letter.event_type,
+            &letter.payload,
+        )
+        .await
+        {
+            Ok(()) => {
+                db::mark_dead_letter_replayed(&self.pool, id).await?;
+                info!(id = id, url = %letter.url, "Replayed webhook delivery");
+                Ok(())
+            }
+            Err(e) => {
+                db::record_dead_letter_failure(&self.pool, id, &e).await?;
+                Err(ReplayError::Delivery(e))
+            }
+        }
+    }
+}
+
+fn next_backoff(backoff: Duration) -> Duration {
+    backoff.saturating_mul(2).min(MAX_BACKOFF)
+}
+
+async fn deliver_with_retries(
+    client: &reqwest::Client,
+    pool: &DbPool,
+    endpoint: &WebhookConfig,
+    event_id: &str,
+    event_type: &str,
+    payload: &str,
+) {
+    let mut backoff = Duration::from_millis(endpoint.initial_backoff_ms);
+    let mut last_error = String::new();
+
+    for attempt in 1..=endpoint.max_attempts {
+        match deliver(client, endpoint, event_id, event_type, payload).await {
+            Ok(()) => return,
+            Err(e) => {
+                warn!(
+                    url = %endpoint.url,
+                    event_type = event_type,
+                    attempt = attempt,
+                    error = %e,
+                    "Webhook delivery failed"
+                );
+                last_error = e;
+            }
+        }
+        if attempt < endpoint.max_attempts {
+            tokio::time::sleep(backoff).await;
+            backoff = next_backoff(backoff);
+        }
+    }
+
+    if let Err(e) = db::insert_dead_letter(
+        pool,
+        event_id,
+        event_type,
+        &endpoint.url,
+        payload,
+        endpoint.max_attempts,
+        &last_error,
+    )
+    .await
+    {
+        error!(error = %e, url = %endpoint.url, "Failed to store webhook dead letter");
+    }
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    endpoint: &WebhookConfig,
+    event_id: &str,
+    event_type: &str,
+    payload: &str,
+) -> Result<(), String> {
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = sign(&endpoint.secret, timestamp, payload.as_bytes());
+
+    let response = client
+        .post(&endpoint.url)
+        .timeout(Duration::from_secs(endpoint.timeout_seconds))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, signature)
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(EVENT_HEADER, event_type)
+        .header(DELIVERY_HEADER, event_id)
+        .body(payload.to_string())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", response.status().as_u16()))
+    }
+}
+
+/// `sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">`. The timestamp travels in
+/// `x-relay-timestamp`, so receivers can verify the sender and reject stale
+/// replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    format!("sha256={}", hex::encode(hmac::sign(&key, &message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use parking_lot::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    async fn setup_test_db() -> DbPool {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap().to_string();
+        std::mem::forget(dir);
+        db::init_database(&path_str, &Default::default())
+            .await
+            .unwrap()
+    }
+
+    /// Fails the first `failures` deliveries, then accepts.
+    #[derive(Clone, Default)]
+    struct Receiver {
+        failures: Arc<AtomicUsize>,
+        received: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+    }
+
+    async fn receive(
+        State(receiver): State<Receiver>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        receiver.received.lock().push((headers, body));
+        let remaining = receiver.failures.load(Ordering::SeqCst);
+        if remaining > 0 {
+            receiver.failures.store(remaining - 1, Ordering::SeqCst);
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    async fn start_receiver(failures: usize) -> (Receiver, String) {
+        let receiver = Receiver::default();
+        receiver.failures.store(failures, Ordering::SeqCst);
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (receiver, url)
+    }
+
+    fn endpoint(url: &str, max_attempts: u32) -> WebhookConfig {
+        WebhookConfig {
+            url: url.to_string(),
+            secret: "whsec_test".to_string(),
+            events: Vec::new(),
+            max_attempts,
+            initial_backoff_ms: 1,
+            timeout_seconds: 5,
+        }
+    }
+
+    fn event() -> WebhookEvent {
+        WebhookEvent::CircuitOpened {
+            account_id: "acc1".to_string(),
+            cooldown_seconds: 300,
+        }
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    #[test]
+    fn test_sign_is_hmac_sha256_of_timestamped_body() {
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, br#"{"ok":true}"#),
+            "sha256=85876387ad9d6be57a04653bc0729da757049f58afb10ba6cac3bedaecf4fda3"
+        );
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        assert_eq!(next_backoff(Duration::from_secs(1)), Duration::from_secs(2));
+        let mut backoff = Duration::from_millis(500);
+        for _ in 0..100 {
+            backoff = next_backoff(backoff);
+        }
+        assert_eq!(backoff, MAX_BACKOFF);
+        assert_eq!(next_backoff(Duration::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let value = serde_json::to_value(Envelope {
+            id: "evt-1",
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            event: &event(),
+        })
+        .unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "id": "evt-1",
+                "created_at": "2025-01-01T00:00:00Z",
+                "type": "account.circuit_opened",
+                "data": {"account_id": "acc1", "cooldown_seconds": 300}
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_retried() {
+        let (receiver, url) = start_receiver(2).await;
+        let dispatcher = WebhookDispatcher::new(vec![endpoint(&url, 5)], setup_test_db().await);
+
+        dispatcher.emit(event());
+        wait_for(|| receiver.received.lock().len() == 3).await;
+
+        let received = receiver.received.lock();
+        let (headers, body) = &received[2];
+        assert_eq!(headers[EVENT_HEADER], "account.circuit_opened");
+        assert_eq!(headers[DELIVERY_HEADER], received[0].0[DELIVERY_HEADER]);
+
+        let timestamp: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            headers[SIGNATURE_HEADER],
+            sign("whsec_test", timestamp, body.as_bytes())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_delivery_is_dead_lettered_and_replayed() {
+        let (receiver, url) = start_receiver(2).await;
+        let pool = setup_test_db().await;
+        let dispatcher = WebhookDispatcher::new(vec![endpoint(&url, 2)], pool.clone());
+
+        dispatcher.emit(event());
+        let mut letters = Vec::new();
+        for _ in 0..100 {
+            letters = db::list_dead_letters(&pool, false, 10).await.unwrap();
+            if !letters.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].event_type, "account.circuit_opened");
+        assert_eq!(letters[0].attempts, 2);
+        assert_eq!(letters[0].last_error, "HTTP 500");
+
+        dispatcher.replay(letters[0].id).await.unwrap();
+        assert_eq!(receiver.received.lock().len(), 3);
+        assert!(db::list_dead_letters(&pool, false, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let replayed = db::get_dead_letter(&pool, letters[0].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(replayed.attempts, 3);
+        assert!(replayed.replayed_at.is_some());
+        assert!(matches!(
+            dispatcher.replay(letters[0].id).await,
+            Err(ReplayError::AlreadyReplayed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_requires_configured_endpoint() {
+        let pool = setup_test_db().await;
+        let id = db::insert_dead_letter(
+            &pool,
+            "evt-1",
+            "account.circuit_opened",
+            "https://removed.example.com/hook",
+            "{}",
+            5,
+            "HTTP 500",
+        )
+        .await
+        .unwrap();
+        let dispatcher = WebhookDispatcher::new(Vec::new(), pool);
+
+        assert!(matches!(
+            dispatcher.replay(id).await,
+            Err(ReplayError::UnknownEndpoint(_))
+        ));
+        assert!(matches!(
+            dispatcher.replay(id + 1).await,
+            Err(ReplayError::NotFound(_))
+        ));
+    }
+}
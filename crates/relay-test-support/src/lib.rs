@@ -0,0 +1,111 @@
+//! Fixture harness shared by the converter crates' streaming tests.
+//!
+//! Each crate keeps its recorded streams in `tests/fixtures`: `<name>.sse`
+//! is the upstream body and `<name>.expected` the exact bytes a client
+//! should receive once it has been converted.
+
+use axum::{body::Body, http::header, response::Response, routing::post, Router};
+use bytes::Bytes;
+use relay_claude::{ClaudeApiAccount, ClaudeRelay, MessagesRequest};
+use relay_core::{BoxStream, Relay, Result};
+use std::path::PathBuf;
+
+/// Size of the pieces a fixture is cut into, small enough to split events,
+/// CRLF terminators and multi-byte characters across reads.
+pub const CHUNK_SIZE: usize = 7;
+
+/// Reads `tests/fixtures/<name>` of the calling crate.
+#[macro_export]
+macro_rules! fixture {
+    ($name:expr) => {
+        $crate::read_fixture(env!("CARGO_MANIFEST_DIR"), $name)
+    };
+}
+
+/// Asserts that `$actual`, converted from `<name>.sse`, equals
+/// `<name>.expected`, or `<name>.<ext>` when an extension is given.
+#[macro_export]
+macro_rules! assert_fixture {
+    ($actual:expr, $name:expr, $ext:expr) => {{
+        let name = $name;
+        let expected = $crate::fixture!(&format!("{}.{}", name, $ext));
+        assert_eq!($actual, expected, "conversion of {}.sse changed", name);
+    }};
+    ($actual:expr, $name:expr) => {
+        $crate::assert_fixture!($actual, $name, "expected")
+    };
+}
+
+pub fn read_fixture(manifest_dir: &str, name: &str) -> String {
+    let path = PathBuf::from(manifest_dir)
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read fixture {}: {}", path.display(), e))
+}
+
+/// Feeds `sse` to a converter in [`CHUNK_SIZE`] pieces, then finishes it,
+/// returning everything it emitted as encoded for the client.
+pub fn replay<C, T>(
+    sse: &str,
+    converter: &mut C,
+    push: fn(&mut C, &[u8]) -> Vec<T>,
+    finish: fn(&mut C) -> Vec<T>,
+    encode: fn(&T) -> String,
+) -> String {
+    let mut output = String::new();
+    for chunk in sse.as_bytes().chunks(CHUNK_SIZE) {
+        for item in push(converter, chunk) {
+            output.push_str(&encode(&item));
+        }
+    }
+    for item in finish(converter) {
+        output.push_str(&encode(&item));
+    }
+    output
+}
+
+/// Relays `request` to a local stand-in for the Anthropic Messages API that
+/// replays `sse` in [`CHUNK_SIZE`] pieces, returning the stream a converter
+/// would read.
+pub async fn relay_fixture(sse: String, request: MessagesRequest) -> BoxStream<Result<Bytes>> {
+    let app = Router::new().route(
+        "/v1/messages",
+        post(move || {
+            let body = sse.clone();
+            async move {
+                let chunks: Vec<std::result::Result<Bytes, std::io::Error>> = body
+                    .into_bytes()
+                    .chunks(CHUNK_SIZE)
+                    .map(|c| Ok(Bytes::copy_from_slice(c)))
+                    .collect();
+
+                Response::builder()
+                    .header(header::CONTENT_TYPE, "text/event-stream")
+                    .body(Body::from_stream(futures::stream::iter(chunks)))
+                    .unwrap()
+            }
+        }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let account = ClaudeApiAccount::new(
+        "mock".to_string(),
+        "Mock".to_string(),
+        100,
+        true,
+        "sk-ant-test".to_string(),
+        Some(format!("http://{}", addr)),
+        None,
+    );
+    ClaudeRelay::new()
+        .relay_stream(&account, request)
+        .await
+        .unwrap()
+}
@@ -0,0 +1,162 @@
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use relay_core::{
+    AccountProvider, Credentials, Platform, ProxyConfig, RelayError, Result, TokenCacheStatus,
+    TokenInfo, VertexCredentials,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::service_account::ServiceAccountKey;
+
+/// A Google Cloud project calling Claude or Gemini models on Vertex AI with
+/// a service account. It reports the platform of the models it serves so it
+/// shares that platform's account pool.
+pub struct VertexAccount {
+    id: String,
+    name: String,
+    priority: u32,
+    enabled: AtomicBool,
+    platform: Platform,
+    key: ServiceAccountKey,
+    project_id: Option<String>,
+    region: String,
+    api_url: Option<String>,
+    proxy: Option<ProxyConfig>,
+    token_cache: RwLock<Option<TokenInfo>>,
+    unavailable_until: RwLock<Option<Instant>>,
+}
+
+impl VertexAccount {
+    pub fn new(
+        id: String,
+        name: String,
+        priority: u32,
+        enabled: bool,
+        platform: Platform,
+        key: ServiceAccountKey,
+        region: String,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            priority,
+            enabled: AtomicBool::new(enabled),
+            platform,
+            key,
+            project_id: None,
+            region,
+            api_url: None,
+            proxy: None,
+            token_cache: RwLock::new(None),
+            unavailable_until: RwLock::new(None),
+        }
+    }
+
+    /// Bills a project other than the one the service account belongs to.
+    pub fn with_project_id(mut self, project_id: Option<String>) -> Self {
+        self.project_id = project_id;
+        self
+    }
+
+    /// Replaces `https://{region}-aiplatform.googleapis.com`.
+    pub fn with_api_url(mut self, api_url: Option<String>) -> Self {
+        self.api_url = api_url;
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    fn credentials(&self, access_token: String) -> Result<Credentials> {
+        let project_id = self
+            .project_id
+            .as_ref()
+            .or(self.key.project_id.as_ref())
+            .ok_or_else(|| {
+                RelayError::Config(format!("Vertex account {} has no project_id", self.id))
+            })?;
+        Ok(Credentials::Vertex(VertexCredentials {
+            access_token,
+            project_id: project_id.clone(),
+            region: self.region.clone(),
+        }))
+    }
+}
+
+#[async_trait]
+impl AccountProvider for VertexAccount {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    fn is_available(&self) -> bool {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        if let Some(until) = *self.unavailable_until.read() {
+            if Instant::now() < until {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    async fn get_credentials(&self) -> Result<Credentials> {
+        {
+            let cache = self.token_cache.read();
+            if let Some(ref token) = *cache {
+                if token.is_valid() {
+                    return self.credentials(token.access_token.clone());
+                }
+            }
+        }
+
+        let new_token = self.key.fetch_token(self.proxy.as_ref()).await?;
+
+        {
+            let mut cache = self.token_cache.write();
+            *cache = Some(new_token.clone());
+        }
+
+        self.credentials(new_token.access_token)
+    }
+
+    fn proxy_config(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    fn api_url(&self) -> Option<&str> {
+        self.api_url.as_deref()
+    }
+
+    fn token_cache_status(&self) -> Option<TokenCacheStatus> {
+        Some(TokenCacheStatus::of(self.token_cache.read().as_ref()))
+    }
+
+    fn mark_unavailable(&self, duration: Duration, _reason: &str) {
+        let mut until = self.unavailable_until.write();
+        *until = Some(Instant::now() + duration);
+    }
+
+    fn mark_available(&self) {
+        let mut until = self.unavailable_until.write();
+        *until = None;
+    }
+}
@@ -0,0 +1,7 @@
+mod account;
+mod relay;
+mod service_account;
+
+pub use account::VertexAccount;
+pub use relay::{model_url, VertexClaudeRelay, VertexGeminiRelay};
+pub use service_account::ServiceAccountKey;
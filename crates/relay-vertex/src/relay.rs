@@ -0,0 +1,363 @@
+use async_stream::try_stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use relay_claude::{MessagesRequest, MessagesResponse};
+use relay_core::{
+    read_error_response, send_captured, AccountProvider, BoxStream, Capture, ClientCache,
+    Credentials, HttpClientOptions, Relay, RelayError, Result, UpstreamTimeouts, VertexCredentials,
+};
+use relay_gemini::{GeminiRequest, GenerateContentResponse, StreamUsage};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Relays Anthropic Messages requests to Claude models on Vertex AI.
+pub struct VertexClaudeRelay {
+    clients: ClientCache,
+    model_ids: HashMap<String, String>,
+    timeouts: Arc<UpstreamTimeouts>,
+    capture: Option<Arc<Capture>>,
+}
+
+impl VertexClaudeRelay {
+    const ANTHROPIC_VERSION: &'static str = "vertex-2023-10-16";
+
+    pub fn new() -> Self {
+        Self {
+            clients: ClientCache::default(),
+            model_ids: HashMap::new(),
+            timeouts: Arc::default(),
+            capture: None,
+        }
+    }
+
+    /// Bounds each upstream request, body included.
+    pub fn with_timeouts(mut self, timeouts: Arc<UpstreamTimeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Writes each upstream exchange to `capture`, for debugging.
+    pub fn with_capture(mut self, capture: Option<Arc<Capture>>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// HTTP version, pooling and keepalive of upstream connections.
+    pub fn with_client_options(mut self, options: HttpClientOptions) -> Self {
+        self.clients = ClientCache::new(options);
+        self
+    }
+
+    /// Vertex model ids for Anthropic model names, such as
+    /// `claude-sonnet-4-20250514` to `claude-sonnet-4@20250514`.
+    pub fn with_model_ids(mut self, model_ids: HashMap<String, String>) -> Self {
+        self.model_ids = model_ids;
+        self
+    }
+
+    /// The configured id for `model`; otherwise a trailing `-YYYYMMDD` date
+    /// becomes the `@YYYYMMDD` version Vertex expects.
+    pub fn model_id(&self, model: &str) -> String {
+        if let Some(id) = self.model_ids.get(model) {
+            return id.clone();
+        }
+        match model.rsplit_once('-') {
+            Some((name, date))
+                if !model.contains('@')
+                    && date.len() == 8
+                    && date.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                format!("{}@{}", name, date)
+            }
+            _ => model.to_string(),
+        }
+    }
+
+    /// The request body with the model moved to the URL.
+    fn body(request: &MessagesRequest) -> Result<serde_json::Value> {
+        let mut body = serde_json::to_value(request)
+            .map_err(|e| RelayError::InvalidRequest(format!("Invalid request: {}", e)))?;
+        if let Some(object) = body.as_object_mut() {
+            object.remove("model");
+            object.insert(
+                "anthropic_version".to_string(),
+                serde_json::Value::String(Self::ANTHROPIC_VERSION.to_string()),
+            );
+        }
+        Ok(body)
+    }
+
+    async fn send(
+        &self,
+        account: &dyn AccountProvider,
+        request: &MessagesRequest,
+        method: &str,
+    ) -> Result<reqwest::Response> {
+        let model_id = self.model_id(&request.model);
+        let body = Self::body(request)?;
+        send(
+            &self.clients,
+            self.timeouts.for_request(account.id(), &request.model),
+            self.capture.as_deref(),
+            account,
+            "anthropic",
+            &model_id,
+            method,
+            &body,
+        )
+        .await
+    }
+}
+
+impl Default for VertexClaudeRelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Relay for VertexClaudeRelay {
+    type Request = MessagesRequest;
+    type Response = MessagesResponse;
+
+    async fn relay(
+        &self,
+        account: &dyn AccountProvider,
+        request: MessagesRequest,
+    ) -> Result<MessagesResponse> {
+        let response = self.send(account, &request, "rawPredict").await?;
+
+        let mut body: serde_json::Value = response.json().await?;
+        if body.get("model").is_none() {
+            body["model"] = serde_json::Value::String(request.model.clone());
+        }
+        let resp: MessagesResponse = serde_json::from_value(body)
+            .map_err(|e| RelayError::Internal(format!("Invalid Vertex AI response: {}", e)))?;
+
+        info!(
+            account_id = %account.id(),
+            input_tokens = resp.usage.input_tokens,
+            output_tokens = resp.usage.output_tokens,
+            stop_reason = ?resp.stop_reason,
+            "Vertex AI Claude request completed"
+        );
+        Ok(resp)
+    }
+
+    async fn relay_stream(
+        &self,
+        account: &dyn AccountProvider,
+        request: MessagesRequest,
+    ) -> Result<BoxStream<Result<Bytes>>> {
+        // Vertex streams the same SSE events as the Anthropic API
+        let response = self.send(account, &request, "streamRawPredict").await?;
+        let stream = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(RelayError::from));
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Relays generateContent requests to Gemini models on Vertex AI.
+pub struct VertexGeminiRelay {
+    clients: ClientCache,
+    timeouts: Arc<UpstreamTimeouts>,
+    capture: Option<Arc<Capture>>,
+}
+
+impl VertexGeminiRelay {
+    pub fn new() -> Self {
+        Self {
+            clients: ClientCache::default(),
+            timeouts: Arc::default(),
+            capture: None,
+        }
+    }
+
+    /// Bounds each upstream request, body included.
+    pub fn with_timeouts(mut self, timeouts: Arc<UpstreamTimeouts>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Writes each upstream exchange to `capture`, for debugging.
+    pub fn with_capture(mut self, capture: Option<Arc<Capture>>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// HTTP version, pooling and keepalive of upstream connections.
+    pub fn with_client_options(mut self, options: HttpClientOptions) -> Self {
+        self.clients = ClientCache::new(options);
+        self
+    }
+}
+
+impl Default for VertexGeminiRelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Relay for VertexGeminiRelay {
+    type Request = GeminiRequest;
+    type Response = GenerateContentResponse;
+
+    async fn relay(
+        &self,
+        account: &dyn AccountProvider,
+        request: GeminiRequest,
+    ) -> Result<GenerateContentResponse> {
+        let response = send(
+            &self.clients,
+            self.timeouts.for_request(account.id(), &request.model),
+            self.capture.as_deref(),
+            account,
+            "google",
+            &request.model,
+            "generateContent",
+            &request.body,
+        )
+        .await?;
+
+        let resp: GenerateContentResponse = response.json().await?;
+        if let Some(ref usage) = resp.usage_metadata {
+            info!(
+                account_id = account.id(),
+                prompt_tokens = usage.prompt_token_count,
+                candidates_tokens = usage.candidates_token_count,
+                "Vertex AI Gemini request completed"
+            );
+        }
+        Ok(resp)
+    }
+
+    async fn relay_stream(
+        &self,
+        account: &dyn AccountProvider,
+        request: GeminiRequest,
+    ) -> Result<BoxStream<Result<Bytes>>> {
+        let method = format!("streamGenerateContent{}", request.stream_format.query());
+        let response = send(
+            &self.clients,
+            self.timeouts.for_request(account.id(), &request.model),
+            self.capture.as_deref(),
+            account,
+            "google",
+            &request.model,
+            &method,
+            &request.body,
+        )
+        .await?;
+
+        let account_id = account.id().to_string();
+        let stream_format = request.stream_format;
+
+        let stream = try_stream! {
+            let mut byte_stream = response.bytes_stream();
+            let mut stream_usage = StreamUsage::new(stream_format);
+
+            while let Some(chunk_result) = byte_stream.next().await {
+                let chunk = chunk_result?;
+                stream_usage.push(&chunk);
+                yield chunk;
+            }
+
+            let total_usage = stream_usage.usage();
+            if total_usage.prompt_token_count > 0 || total_usage.candidates_token_count > 0 {
+                info!(
+                    account_id = account_id,
+                    prompt_tokens = total_usage.prompt_token_count,
+                    candidates_tokens = total_usage.candidates_token_count,
+                    "Vertex AI Gemini streaming request completed"
+                );
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// URL of a publisher model method in the account's project and region.
+pub fn model_url(
+    api_url: Option<&str>,
+    credentials: &VertexCredentials,
+    publisher: &str,
+    model: &str,
+    method: &str,
+) -> String {
+    let endpoint = match api_url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None if credentials.region == "global" => "https://aiplatform.googleapis.com".to_string(),
+        None => format!("https://{}-aiplatform.googleapis.com", credentials.region),
+    };
+    format!(
+        "{}/v1/projects/{}/locations/{}/publishers/{}/models/{}:{}",
+        endpoint, credentials.project_id, credentials.region, publisher, model, method
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn send<B: Serialize + ?Sized>(
+    clients: &ClientCache,
+    timeout: Duration,
+    capture: Option<&Capture>,
+    account: &dyn AccountProvider,
+    publisher: &str,
+    model: &str,
+    method: &str,
+    body: &B,
+) -> Result<reqwest::Response> {
+    let credentials = match account.get_credentials().await? {
+        Credentials::Vertex(credentials) => credentials,
+        _ => {
+            return Err(RelayError::Config(format!(
+                "Account {} has no Vertex AI credentials",
+                account.id()
+            )))
+        }
+    };
+    let client = clients.client(account.proxy_config())?;
+    let url = model_url(account.api_url(), &credentials, publisher, model, method);
+
+    debug!(
+        account_id = %account.id(),
+        model = model,
+        url = %url,
+        "Sending Vertex AI request"
+    );
+
+    let builder = client
+        .post(&url)
+        .header(
+            "Authorization",
+            format!("Bearer {}", credentials.access_token),
+        )
+        .header("Content-Type", "application/json")
+        .timeout(timeout)
+        .json(body);
+    let response = send_captured(builder, capture).await?;
+
+    let status = response.status();
+    debug!(
+        account_id = %account.id(),
+        status = %status,
+        "Received Vertex AI response"
+    );
+    if !status.is_success() {
+        let error = read_error_response(response).await;
+        warn!(
+            account_id = %account.id(),
+            model = model,
+            error = %error,
+            "Vertex AI request failed"
+        );
+        return Err(error);
+    }
+    Ok(response)
+}
@@ -0,0 +1,160 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use relay_core::{sanitize_response_body, ProxyConfig, RelayError, Result, TokenInfo};
+use reqwest::Client;
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::Deserialize;
+use tracing::{debug, error, info};
+
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const JWT_BEARER_GRANT: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+const ASSERTION_LIFETIME_SECS: i64 = 3600;
+
+/// The JSON key of a Google Cloud service account, as downloaded from the
+/// console.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    /// PKCS#8 PEM.
+    pub private_key: String,
+    #[serde(default)]
+    pub private_key_id: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+impl ServiceAccountKey {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| RelayError::Config(format!("Invalid service account key: {}", e)))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            RelayError::Config(format!(
+                "Failed to read service account key {}: {}",
+                path, e
+            ))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// A JWT signed with the private key, asserting the account's identity
+    /// for the `cloud-platform` scope.
+    pub fn assertion(&self, now: DateTime<Utc>) -> Result<String> {
+        let mut header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+        if let Some(kid) = &self.private_key_id {
+            header["kid"] = serde_json::Value::String(kid.clone());
+        }
+        let claims = serde_json::json!({
+            "iss": self.client_email,
+            "scope": SCOPE,
+            "aud": self.token_uri,
+            "iat": now.timestamp(),
+            "exp": now.timestamp() + ASSERTION_LIFETIME_SECS,
+        });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+
+        let key_pair = RsaKeyPair::from_pkcs8(&pem_to_der(&self.private_key)?)
+            .map_err(|e| RelayError::Config(format!("Invalid service account key: {}", e)))?;
+        let mut signature = vec![0; key_pair.public().modulus_len()];
+        key_pair
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| RelayError::Internal("Failed to sign JWT assertion".to_string()))?;
+
+        Ok(format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// Exchanges a fresh assertion for an access token at `token_uri`.
+    pub async fn fetch_token(&self, proxy_config: Option<&ProxyConfig>) -> Result<TokenInfo> {
+        let client = build_client(proxy_config)?;
+        let assertion = self.assertion(Utc::now())?;
+
+        debug!(client_email = %self.client_email, "Fetching service account token");
+
+        let response = client
+            .post(&self.token_uri)
+            .form(&[("grant_type", JWT_BEARER_GRANT), ("assertion", &assertion)])
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = match response.text().await {
+                Ok(text) => sanitize_response_body(text),
+                Err(e) => format!("[Failed to read response body: {}]", e),
+            };
+            error!(
+                "Service account token request failed: HTTP {} - {}",
+                status, body
+            );
+            return Err(RelayError::OAuth(format!("HTTP {}: {}", status, body)));
+        }
+
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| RelayError::OAuth(format!("Failed to parse token response: {}", e)))?;
+
+        info!(
+            client_email = %self.client_email,
+            expires_in = token_response.expires_in,
+            "Service account token fetched successfully"
+        );
+
+        Ok(TokenInfo::new(
+            token_response.access_token,
+            token_response.expires_in,
+        ))
+    }
+}
+
+fn pem_to_der(pem: &str) -> Result<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+    STANDARD
+        .decode(body)
+        .map_err(|e| RelayError::Config(format!("Invalid service account private key: {}", e)))
+}
+
+fn build_client(proxy_config: Option<&ProxyConfig>) -> Result<Client> {
+    let mut builder = Client::builder().timeout(std::time::Duration::from_secs(30));
+
+    if let Some(proxy) = proxy_config {
+        if let Some(proxy_url) = proxy.to_url() {
+            let proxy = reqwest::Proxy::all(&proxy_url)
+                .map_err(|e| RelayError::Config(format!("Invalid proxy URL: {}", e)))?;
+            builder = builder.proxy(proxy);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| RelayError::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}